@@ -0,0 +1,120 @@
+// Grid and rendering hot-path benchmarks: cargo bench --bench grid
+// The binary has no library target, so the modules under test are compiled in directly.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/chunked_grid.rs"]
+mod chunked_grid;
+
+#[allow(dead_code)]
+#[path = "../src/config.rs"]
+mod config;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/term_caps.rs"]
+mod term_caps;
+
+#[allow(dead_code, unused_imports)]
+#[path = "../src/frame.rs"]
+mod frame;
+
+use chunked_grid::ChunkedGrid;
+
+const ROW_WIDTH: usize = 200;
+const VIEW_WIDTH: u16 = 200;
+const VIEW_HEIGHT: u16 = 60;
+
+/// A row with a few short words spread across several chunks
+fn sparse_grid() -> ChunkedGrid {
+    let mut grid = ChunkedGrid::new();
+    for col in (0..ROW_WIDTH).step_by(40) {
+        grid.insert_at(10, col, "word");
+    }
+    grid
+}
+
+/// A row filled edge to edge
+fn dense_grid() -> ChunkedGrid {
+    let mut grid = ChunkedGrid::new();
+    grid.insert_at(10, 0, &"x".repeat(ROW_WIDTH));
+    grid
+}
+
+/// A full page of prose: 500 lines of 80 columns
+fn page_grid() -> ChunkedGrid {
+    let line = "The quick brown fox jumps over the lazy dog. ".repeat(2);
+    let mut grid = ChunkedGrid::new();
+    for row in 0..500 {
+        grid.insert_at(row, 0, &line[..80]);
+    }
+    grid
+}
+
+fn line_per_cell(grid: &ChunkedGrid) -> String {
+    (0..ROW_WIDTH).map(|col| grid.get(10, col)).collect()
+}
+
+fn line_retrieval(c: &mut Criterion) {
+    for (name, grid) in [("sparse", sparse_grid()), ("dense", dense_grid())] {
+        c.bench_function(&format!("line/get_per_cell/{}", name), |b| {
+            b.iter(|| line_per_cell(black_box(&grid)))
+        });
+        c.bench_function(&format!("line/line_runs/{}", name), |b| {
+            b.iter(|| black_box(&grid).line_runs(10, 0..ROW_WIDTH))
+        });
+    }
+}
+
+fn cell_access(c: &mut Criterion) {
+    let grid = page_grid();
+    c.bench_function("grid/set_10k", |b| {
+        b.iter(|| {
+            let mut grid = ChunkedGrid::new();
+            for i in 0..10_000 {
+                grid.set(i / 100, i % 100, 'x');
+            }
+            grid
+        })
+    });
+    c.bench_function("grid/get_10k", |b| {
+        b.iter(|| (0..10_000).filter(|i| black_box(&grid).get(i / 100, i % 100) != ' ').count())
+    });
+    c.bench_function("grid/insert_at_mid_line", |b| {
+        b.iter_batched(page_grid, |mut grid| grid.insert_at(250, 40, "inserted text"), BatchSize::LargeInput)
+    });
+    c.bench_function("grid/to_lines_500x80", |b| b.iter(|| black_box(&grid).to_lines()));
+}
+
+fn selection(c: &mut Criterion) {
+    let grid = page_grid();
+    let mut selected = grid.clone();
+    selected.start_selection(0, 0);
+    selected.update_selection(499, 79);
+
+    c.bench_function("selection/copy_500x80", |b| b.iter(|| black_box(&selected).copy_block()));
+    c.bench_function("selection/cut_500x80", |b| {
+        b.iter_batched(|| selected.clone(), |mut grid| grid.cut_block(), BatchSize::LargeInput)
+    });
+    let block = selected.copy_block().unwrap_or_default();
+    c.bench_function("selection/paste_500x80", |b| {
+        b.iter_batched(ChunkedGrid::new, |mut grid| grid.paste_block(&block, 1000, 1000), BatchSize::LargeInput)
+    });
+}
+
+fn frame_assembly(c: &mut Criterion) {
+    let full = page_grid();
+    let mut selected = full.clone();
+    selected.start_selection(10, 10);
+    selected.update_selection(40, 60);
+    let empty = ChunkedGrid::new();
+
+    for (name, grid) in [("full", &full), ("selection", &selected), ("empty", &empty)] {
+        c.bench_function(&format!("frame/{}_200x60", name), |b| {
+            b.iter(|| frame::build(black_box(grid), 0, 0, 0, 1, VIEW_WIDTH, VIEW_HEIGHT))
+        });
+    }
+}
+
+criterion_group!(benches, line_retrieval, cell_access, selection, frame_assembly);
+criterion_main!(benches);
@@ -0,0 +1,76 @@
+// Snap to grid and alignment guides
+// With Settings → Snap to Grid on, a click or paste on empty canvas lands on the nearest
+// vertical grid line (every grid_col_spacing columns, 8 by default) instead of the exact cell,
+// which keeps blocks of text on a tidy set of columns. Clicks and pastes on text or right after
+// it stay put, so editing a line works as before. For a moment afterwards, dotted guides run
+// from the cursor to the nearest blocks above and below that start in the same column.
+use crate::chunked_grid::ChunkedGrid;
+use crate::config::alignment::GUIDE_ROWS;
+
+/// A vertical guide line at `col` from row `top` to row `bottom`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Guide {
+    pub col: usize,
+    pub top: usize,
+    pub bottom: usize,
+}
+
+/// The column a click or paste at (row, col) snaps to, with grid lines every `spacing`
+/// columns counted from the canvas origin (like the drawn grid)
+pub fn snap(grid: &ChunkedGrid, row: usize, col: usize, spacing: usize) -> usize {
+    let blank = |col: usize| grid.get(row, col) == ' ';
+    if spacing <= 1 || !blank(col) || (col > 0 && !blank(col - 1)) {
+        return col;
+    }
+    let (_, logical_col) = grid.logical_pos(row, col);
+    let offset = logical_col.rem_euclid(spacing as i64) as usize;
+    let target = if offset * 2 <= spacing && offset <= col { col - offset } else { col + spacing - offset };
+    if blank(target) {
+        target
+    } else {
+        col
+    }
+}
+
+/// Whether a block of text starts at (row, col): a character there with a blank to its left
+fn starts_block(grid: &ChunkedGrid, row: usize, col: usize) -> bool {
+    grid.get(row, col) != ' ' && (col == 0 || grid.get(row, col - 1) == ' ')
+}
+
+/// Guides from (row, col) to the nearest blocks above and below it that start in `col`
+pub fn guides(grid: &ChunkedGrid, row: usize, col: usize) -> Vec<Guide> {
+    let above = (row.saturating_sub(GUIDE_ROWS)..row).rev().find(|&r| starts_block(grid, r, col));
+    let below = (row + 1..=row + GUIDE_ROWS).find(|&r| starts_block(grid, r, col));
+    let above = above.map(|top| Guide { col, top, bottom: row });
+    let below = below.map(|bottom| Guide { col, top: row, bottom });
+    above.into_iter().chain(below).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_and_guides() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 8, "title");
+        grid.insert_at(0, 16, "x");
+        grid.insert_at(4, 0, "hello");
+
+        // Empty canvas snaps to the nearest grid line; text and the cell after it don't
+        assert_eq!(snap(&grid, 2, 11, 8), 8);
+        assert_eq!(snap(&grid, 2, 13, 8), 16);
+        assert_eq!(snap(&grid, 2, 3, 8), 0);
+        assert_eq!(snap(&grid, 4, 2, 8), 2);
+        assert_eq!(snap(&grid, 4, 5, 8), 5);
+        assert_eq!(snap(&grid, 4, 7, 8), 8);
+        assert_eq!(snap(&grid, 2, 11, 1), 11);
+        // ...and never onto text
+        assert_eq!(snap(&grid, 0, 15, 8), 15);
+        assert_eq!(snap(&grid, 0, 13, 8), 13);
+
+        assert_eq!(guides(&grid, 2, 8), [Guide { col: 8, top: 0, bottom: 2 }]);
+        assert_eq!(guides(&grid, 2, 0), [Guide { col: 0, top: 2, bottom: 4 }]);
+        assert!(guides(&grid, 2, 24).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+// Colored terminal output
+// Text copied out of a terminal or saved from a command (`ls --color=always > files.txt`,
+// `script` logs) carries ANSI escape sequences. Pasting or importing it keeps the colors as
+// cell styles instead of writing the escape bytes onto the canvas: the 16 basic colors, the
+// 256-color palette and 24-bit colors, foreground and background, bold and underline. Other
+// sequences (cursor movement, window titles, ...) and carriage returns are dropped.
+use crate::chunked_grid::{ChunkedGrid, Style};
+
+type Rgb = (u8, u8, u8);
+
+/// The basic colors as xterm draws them: black, red, green, yellow, blue, magenta, cyan and
+/// white, then their bright versions
+const BASIC: [Rgb; 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+pub fn has_escapes(text: &str) -> bool {
+    text.contains('\x1b')
+}
+
+/// Each line of `text` as characters with the style their escape sequences gave them
+pub fn parse(text: &str) -> Vec<Vec<(char, Style)>> {
+    let mut lines = vec![Vec::new()];
+    let mut style = Style::default();
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => match chars.next() {
+                // CSI: parameters up to a final byte; only SGR (ending in 'm') matters here
+                Some('[') => {
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            if c == 'm' {
+                                apply_sgr(&mut style, &params);
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                // OSC (titles, hyperlinks): up to BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                // Character set selection
+                Some('(' | ')') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\n' => lines.push(Vec::new()),
+            c if c.is_control() && c != '\t' => {}
+            c => lines.last_mut().expect("there is always a line").push((c, style)),
+        }
+    }
+    if lines.len() > 1 && lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// The characters of parsed lines, without their styles
+pub fn plain_lines(lines: &[Vec<(char, Style)>]) -> Vec<String> {
+    lines.iter().map(|line| line.iter().map(|&(ch, _)| ch).collect()).collect()
+}
+
+/// A canvas holding `text` from the top left, in its colors
+pub fn to_grid(text: &str) -> ChunkedGrid {
+    let mut grid = ChunkedGrid::new();
+    for (row, line) in parse(text).iter().enumerate() {
+        for (col, &(ch, style)) in line.iter().enumerate() {
+            grid.set_styled(row, col, ch, style);
+        }
+    }
+    grid
+}
+
+/// Apply the codes of one SGR sequence ("1;31", "38;5;208", ...)
+fn apply_sgr(style: &mut Style, params: &str) {
+    let codes: Vec<u16> = params.split([';', ':']).map(|code| code.parse().unwrap_or(0)).collect();
+    let mut i = 0;
+    while i < codes.len() {
+        match codes[i] {
+            0 => *style = Style::default(),
+            1 => style.bold = true,
+            4 => style.underline = true,
+            22 => style.bold = false,
+            24 => style.underline = false,
+            code @ 30..=37 => style.fg = Some(BASIC[(code - 30) as usize]),
+            code @ 90..=97 => style.fg = Some(BASIC[(code - 90 + 8) as usize]),
+            39 => style.fg = None,
+            code @ 40..=47 => style.bg = Some(BASIC[(code - 40) as usize]),
+            code @ 100..=107 => style.bg = Some(BASIC[(code - 100 + 8) as usize]),
+            49 => style.bg = None,
+            code @ (38 | 48) => {
+                let (color, used) = extended_color(&codes[i + 1..]);
+                if let Some(color) = color {
+                    if code == 38 {
+                        style.fg = Some(color);
+                    } else {
+                        style.bg = Some(color);
+                    }
+                }
+                i += used;
+            }
+            // Italics, blinking and the like aren't kept
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// The color after a 38 or 48 code ("5;n" or "2;r;g;b") and how many codes it took
+fn extended_color(codes: &[u16]) -> (Option<Rgb>, usize) {
+    match codes {
+        [5, n, ..] => (Some(palette_color(*n as u8)), 2),
+        [2, r, g, b, ..] => (Some((*r as u8, *g as u8, *b as u8)), 4),
+        _ => (None, codes.len()),
+    }
+}
+
+/// One of the 256 palette colors: the basic ones, a 6x6x6 cube, then 24 grays
+fn palette_color(n: u8) -> Rgb {
+    match n {
+        0..=15 => BASIC[n as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            (level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ansi() {
+        let text = "\x1b]0;title\x07\x1b[1;31merror\x1b[0m: bad\r\n\x1b[38;5;208mo\x1b[48;2;1;2;3mk\x1b[39m!\x1b[K\n";
+        let lines = parse(text);
+        assert_eq!(plain_lines(&lines), ["error: bad", "ok!"]);
+        let red = Style { fg: Some((205, 0, 0)), bold: true, ..Style::default() };
+        assert_eq!((lines[0][0].1, lines[0][4].1, lines[0][5].1), (red, red, Style::default()));
+        assert_eq!(lines[1][0].1.fg, Some((255, 135, 0)));
+        assert_eq!(lines[1][1].1, Style { fg: Some((255, 135, 0)), bg: Some((1, 2, 3)), ..Style::default() });
+        assert_eq!(lines[1][2].1, Style { bg: Some((1, 2, 3)), ..Style::default() });
+
+        let grid = to_grid(text);
+        assert_eq!(grid.to_lines(), ["error: bad", "ok!"]);
+        assert_eq!((grid.style(0, 0), grid.style(0, 5), grid.style(0, 6)), (red, Style::default(), Style::default()));
+        assert!(!has_escapes("plain"));
+    }
+}
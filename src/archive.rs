@@ -0,0 +1,331 @@
+// Whole-database archives for backups and moving between machines
+// An archive is a .tar.gz holding manifest.json (format version, export time and every note's
+// metadata), notes/<id>.md with each note's stored content, and attachments/<id>/<n> with
+// its attachments. Importing matches notes by id; see ConflictPolicy for what happens when a
+// note exists on both sides with different contents.
+use crate::config::archive::{MAX_ENTRY_BYTES, MAX_TOTAL_BYTES};
+use crate::notes_database::{Attachment, Note, NotesDatabase};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+const FORMAT: &str = "chonk-note-archive";
+const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    format: String,
+    version: u32,
+    exported_at: DateTime<Utc>,
+    notes: Vec<NoteEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoteEntry {
+    id: String,
+    title: String,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    /// Stored content, verbatim (plain text, or the sparse format for sparse notes)
+    file: String,
+    attachments: Vec<AttachmentEntry>,
+    #[serde(default)]
+    accent: Option<(u8, u8, u8)>,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    word_goal: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AttachmentEntry {
+    filename: String,
+    mime: String,
+    file: String,
+}
+
+/// What to do with an archived note whose id already exists with different contents
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep whichever version was modified last
+    Newer,
+    /// Keep the local note
+    Skip,
+    /// Overwrite the local note
+    Replace,
+    /// Keep the local note and add the archived one as a copy
+    KeepBoth,
+}
+
+impl ConflictPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "newer" => Some(Self::Newer),
+            "skip" => Some(Self::Skip),
+            "replace" => Some(Self::Replace),
+            "keep-both" => Some(Self::KeepBoth),
+            _ => None,
+        }
+    }
+}
+
+/// Counts from an import, by what happened to each archived note
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub added: usize,
+    pub replaced: usize,
+    pub copied: usize,
+    /// Identical to the local note, or kept local by the policy
+    pub skipped: usize,
+}
+
+/// Write every note and attachment as an archive; returns the number of notes
+pub fn export(db: &NotesDatabase, writer: impl Write) -> Result<usize> {
+    let mut tar = TarWriter { out: GzEncoder::new(writer, flate2::Compression::default()) };
+    let mut manifest = Manifest { format: FORMAT.to_string(), version: VERSION, exported_at: Utc::now(), notes: Vec::new() };
+
+    let page_size = crate::config::notes_list::PAGE_SIZE;
+    loop {
+        let page = db.list_notes_page(page_size, manifest.notes.len())?;
+        for note in &page {
+            let file = format!("notes/{}.md", note.id);
+            tar.add(&file, note.content.as_bytes())?;
+            let mut attachments = Vec::new();
+            for (i, attachment) in db.attachments(&note.id)?.into_iter().enumerate() {
+                let file = format!("attachments/{}/{}", note.id, i);
+                tar.add(&file, &attachment.data)?;
+                attachments.push(AttachmentEntry { filename: attachment.filename, mime: attachment.mime, file });
+            }
+            manifest.notes.push(NoteEntry {
+                id: note.id.clone(),
+                title: note.title.clone(),
+                tags: note.tags.clone(),
+                created_at: note.created_at,
+                updated_at: note.updated_at,
+                file,
+                attachments,
+                accent: note.accent,
+                icon: note.icon.clone(),
+                word_goal: note.word_goal,
+            });
+        }
+        if page.len() < page_size {
+            break;
+        }
+    }
+
+    tar.add("manifest.json", &serde_json::to_vec_pretty(&manifest)?)?;
+    tar.finish()?.finish()?;
+    Ok(manifest.notes.len())
+}
+
+/// Add the notes in an archive, resolving notes that exist on both sides with `policy`
+pub fn import(db: &NotesDatabase, reader: impl Read, policy: ConflictPolicy) -> Result<ImportSummary> {
+    let mut files = read_tar(GzDecoder::new(reader)).context("Reading the archive")?;
+    let manifest: Manifest = serde_json::from_slice(
+        &files.remove("manifest.json").ok_or_else(|| anyhow::anyhow!("Not a chonk-note archive (no manifest.json)"))?,
+    )?;
+    if manifest.format != FORMAT {
+        anyhow::bail!("Not a chonk-note archive");
+    }
+    if manifest.version > VERSION {
+        anyhow::bail!("Archive format version {} is newer than this chonk-note supports ({})", manifest.version, VERSION);
+    }
+
+    let mut summary = ImportSummary::default();
+    for entry in manifest.notes {
+        let mut take = |file: &str| files.remove(file).ok_or_else(|| anyhow::anyhow!("{} is missing from the archive", file));
+        let content = String::from_utf8(take(&entry.file)?).with_context(|| format!("{} is not UTF-8", entry.file))?;
+        let attachments = entry
+            .attachments
+            .iter()
+            .map(|a| Ok(Attachment { filename: a.filename.clone(), mime: a.mime.clone(), data: take(&a.file)? }))
+            .collect::<Result<Vec<_>>>()?;
+        let note = Note {
+            id: entry.id,
+            title: entry.title,
+            content,
+            tags: entry.tags,
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            accent: entry.accent,
+            icon: entry.icon,
+            word_goal: entry.word_goal,
+        };
+
+        let target = match db.get_note(&note.id)? {
+            None => {
+                summary.added += 1;
+                Some(note)
+            }
+            Some(local) if local.content == note.content && local.title == note.title && local.tags == note.tags => {
+                summary.skipped += 1;
+                None
+            }
+            Some(local) => match policy {
+                ConflictPolicy::Replace => Some(note),
+                ConflictPolicy::Newer if note.updated_at > local.updated_at => Some(note),
+                ConflictPolicy::Newer | ConflictPolicy::Skip => {
+                    summary.skipped += 1;
+                    None
+                }
+                ConflictPolicy::KeepBoth => {
+                    let copy = db.create_note_at(format!("{} (from archive)", note.title), note.content,
+                                                 note.tags, Some(note.created_at), Some(note.updated_at))?;
+                    for attachment in &attachments {
+                        db.add_attachment(&copy.id, attachment)?;
+                    }
+                    summary.copied += 1;
+                    None
+                }
+            },
+        };
+
+        if let Some(note) = target {
+            if db.get_note(&note.id)?.is_some() {
+                summary.replaced += 1;
+            }
+            db.put_note(&note)?;
+            db.delete_attachments(&note.id)?;
+            for attachment in &attachments {
+                db.add_attachment(&note.id, attachment)?;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Minimal ustar writer: regular files with short names, which is all archives contain
+struct TarWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TarWriter<W> {
+    fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        if name.len() >= 100 {
+            anyhow::bail!("Archive entry name too long: {}", name);
+        }
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[136..148].copy_from_slice(format!("{:011o}\0", Utc::now().timestamp().max(0)).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        // The checksum is computed with its own field as spaces
+        header[148..156].copy_from_slice(b"        ");
+        let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+        self.out.write_all(&header)?;
+        self.out.write_all(data)?;
+        self.out.write_all(&vec![0u8; (512 - data.len() % 512) % 512])?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<W> {
+        self.out.write_all(&[0u8; 1024])?;
+        Ok(self.out)
+    }
+}
+
+/// Regular files of a tar stream, by name
+fn read_tar(mut input: impl Read) -> Result<HashMap<String, Vec<u8>>> {
+    let mut files = HashMap::new();
+    let mut header = [0u8; 512];
+    let mut total = 0usize;
+    loop {
+        input.read_exact(&mut header)?;
+        if header.iter().all(|&b| b == 0) {
+            return Ok(files);
+        }
+        let field = |range: std::ops::Range<usize>| {
+            let bytes = &header[range];
+            String::from_utf8_lossy(&bytes[..bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len())]).to_string()
+        };
+        let name = field(0..100);
+        let size = usize::from_str_radix(field(124..136).trim(), 8).with_context(|| format!("Bad size for {}", name))?;
+        // The size comes from the archive, so it is checked before anything is allocated for it
+        total = total.saturating_add(size);
+        if size > MAX_ENTRY_BYTES || total > MAX_TOTAL_BYTES {
+            anyhow::bail!("{} is too large ({} bytes); the archive may be damaged", name, size);
+        }
+        let mut data = Vec::new();
+        input.by_ref().take(size as u64).read_to_end(&mut data)?;
+        if data.len() < size {
+            anyhow::bail!("Archive ends in the middle of {}", name);
+        }
+        std::io::copy(&mut input.by_ref().take(((512 - size % 512) % 512) as u64), &mut std::io::sink())?;
+        if header[156] == b'0' || header[156] == 0 {
+            files.insert(name, data);
+        }
+    }
+}
+
+/// Default file name for an export: chonk-note-<date>-<time>.tar.gz
+pub fn default_file_name() -> String {
+    format!("chonk-note-{}.tar.gz", chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip() -> Result<()> {
+        let source = NotesDatabase::open_in_memory()?;
+        let note = source.create_note("Plan".to_string(), "step 1\nstep 2".to_string(), vec!["work".to_string()])?;
+        let attachment = Attachment { filename: "map.png".to_string(), mime: "image/png".to_string(), data: vec![1, 2, 3] };
+        source.add_attachment(&note.id, &attachment)?;
+        source.create_note("Other".to_string(), String::new(), vec![])?;
+
+        let mut archive = Vec::new();
+        assert_eq!(export(&source, &mut archive)?, 2);
+
+        // Into an empty database: everything is added, ids and dates included
+        let target = NotesDatabase::open_in_memory()?;
+        assert_eq!(import(&target, archive.as_slice(), ConflictPolicy::Newer)?.added, 2);
+        let copy = target.get_note(&note.id)?.unwrap();
+        assert_eq!((copy.content.as_str(), copy.tags.as_slice(), copy.created_at), ("step 1\nstep 2", &note.tags[..], note.created_at));
+        assert_eq!(target.attachments(&note.id)?, vec![attachment]);
+
+        // Again, with the note edited locally since
+        target.update_note(&note.id, "Plan".to_string(), "step 1 done".to_string(), vec![])?;
+        let summary = import(&target, archive.as_slice(), ConflictPolicy::Newer)?;
+        assert_eq!(summary, ImportSummary { skipped: 2, ..Default::default() });
+        assert_eq!(target.get_note(&note.id)?.unwrap().content, "step 1 done");
+
+        let summary = import(&target, archive.as_slice(), ConflictPolicy::KeepBoth)?;
+        assert_eq!((summary.copied, target.count_notes()?), (1, 3));
+        let summary = import(&target, archive.as_slice(), ConflictPolicy::Replace)?;
+        assert_eq!(summary.replaced, 1);
+        assert_eq!(target.get_note(&note.id)?.unwrap().content, "step 1\nstep 2");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tar_checks_sizes() -> Result<()> {
+        let mut tar = TarWriter { out: Vec::new() };
+        tar.add("notes/a.md", b"hello")?;
+        let tar = tar.finish()?;
+        assert_eq!(read_tar(tar.as_slice())?["notes/a.md"], b"hello");
+
+        // A header claiming a huge size (the checksum isn't checked) or more data than there is
+        let mut huge = tar.clone();
+        huge[124..136].copy_from_slice(b"77777777777\0");
+        assert!(read_tar(huge.as_slice()).unwrap_err().to_string().contains("too large"));
+        let mut short = tar[..512].to_vec();
+        short[124..136].copy_from_slice(b"00000010000\0");
+        short.extend_from_slice(b"hello");
+        assert!(read_tar(short.as_slice()).unwrap_err().to_string().contains("ends in the middle"));
+        Ok(())
+    }
+}
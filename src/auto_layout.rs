@@ -0,0 +1,267 @@
+// Tidying scattered notes
+// Tidy layout (command palette) packs a note whose blocks have drifted across the canvas.
+// Text that touches, from line to line and across short gaps between words, is one island;
+// islands sharing rows (side-by-side columns, a table) move together as a band; and the bands
+// are stacked in reading order against the leftmost column, one blank row apart. Islands are
+// found from the occupied chunks only, so a large sparse canvas is cheap to scan. Rows left
+// open under image and embed tokens move with them. The whole move is one undo step.
+// Select block (Ctrl+Shift+A) selects the island under the cursor, to grab a paragraph or a
+// diagram wherever it sits.
+use crate::chunked_grid::{ChunkedGrid, Style};
+use crate::config::auto_layout::{BAND_GAP_ROWS, MAX_WORD_GAP};
+use crate::config::{images::THUMBNAIL_ROWS, transclusion::MAX_ROWS};
+use crate::{images, transclusion, undo};
+use std::collections::BTreeMap;
+
+/// Columns `start..=end` of `row`, with no gap wider than MAX_WORD_GAP blanks
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    row: usize,
+    start: usize,
+    end: usize,
+}
+
+/// A connected block of text and its bounds
+#[derive(Debug, Clone, PartialEq)]
+struct Island {
+    top: usize,
+    left: usize,
+    bottom: usize,
+    right: usize,
+}
+
+/// The runs of text on each row, top to bottom and left to right
+fn runs(grid: &ChunkedGrid) -> Vec<Run> {
+    let mut rows: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (row, col) in grid.cell_positions() {
+        if grid.get(row, col) != ' ' {
+            rows.entry(row).or_default().push(col);
+        }
+    }
+    let mut runs = Vec::new();
+    for (row, mut cols) in rows {
+        cols.sort_unstable();
+        let mut run = Run { row, start: cols[0], end: cols[0] };
+        for &col in &cols[1..] {
+            if col - run.end > MAX_WORD_GAP + 1 {
+                runs.push(run);
+                run.start = col;
+            }
+            run.end = col;
+        }
+        runs.push(run);
+    }
+    runs
+}
+
+/// Index of the set `i` belongs to, shortening the path on the way
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// The islands of the canvas, in reading order, each with its runs
+fn connected(grid: &ChunkedGrid) -> Vec<(Island, Vec<Run>)> {
+    let runs = runs(grid);
+    let mut parent: Vec<usize> = (0..runs.len()).collect();
+    // Runs are sorted by row, so the runs of the row above are the ones just before this row's
+    let mut above = 0..0;
+    let mut current = 0..0;
+    for i in 0..runs.len() {
+        if i == 0 || runs[i].row != runs[i - 1].row {
+            above = if i > 0 && runs[i - 1].row + 1 == runs[i].row { current.clone() } else { i..i };
+            current = i..i;
+        }
+        current.end = i + 1;
+        for j in above.clone() {
+            if runs[j].start <= runs[i].end && runs[i].start <= runs[j].end {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                parent[a] = b;
+            }
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<Run>> = BTreeMap::new();
+    for (i, run) in runs.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(*run);
+    }
+    let mut islands: Vec<(Island, Vec<Run>)> = groups
+        .into_values()
+        .map(|runs| {
+            let island = Island {
+                top: runs.iter().map(|run| run.row).min().unwrap_or(0),
+                left: runs.iter().map(|run| run.start).min().unwrap_or(0),
+                bottom: runs.iter().map(|run| run.row).max().unwrap_or(0),
+                right: runs.iter().map(|run| run.end).max().unwrap_or(0),
+            };
+            (island, runs)
+        })
+        .collect();
+    islands.sort_by_key(|(island, _)| (island.top, island.left));
+    islands
+}
+
+/// The last row kept open under the image and embed tokens of an island: the blank rows
+/// already below a token, up to as many as it draws over
+fn reserved_bottom(grid: &ChunkedGrid, island: &Island, runs: &[Run]) -> usize {
+    let blank_row = |row: usize| (island.left..=island.right).all(|col| grid.get(row, col) == ' ');
+    let mut bottom = island.bottom;
+    for run in runs {
+        let line: Vec<char> = (run.start..=run.end).map(|col| grid.get(run.row, col)).collect();
+        let rows = if !transclusion::embeds_in_line(&line).is_empty() {
+            MAX_ROWS
+        } else if !images::tokens_in_line(&line).is_empty() {
+            THUMBNAIL_ROWS
+        } else {
+            continue;
+        };
+        let open = (run.row + 1..=run.row + rows).take_while(|&row| blank_row(row)).count();
+        bottom = bottom.max(run.row + open);
+    }
+    bottom
+}
+
+/// Bounds (first row, first col, last row, last col) of the island with text at (row, col) or
+/// just after it
+pub fn island_at(grid: &ChunkedGrid, row: usize, col: usize) -> Option<(usize, usize, usize, usize)> {
+    connected(grid)
+        .into_iter()
+        .find(|(_, runs)| runs.iter().any(|run| run.row == row && run.start <= col && col <= run.end + 1))
+        .map(|(island, _)| (island.top, island.left, island.bottom, island.right))
+}
+
+/// Stack the islands of `grid` and return the undo step (already applied) with the number of
+/// islands moved, or None if the canvas is empty or already tidy
+pub fn tidy(grid: &mut ChunkedGrid) -> Option<(undo::Command, usize)> {
+    let mut islands = connected(grid);
+    for (island, runs) in &mut islands {
+        island.bottom = island.bottom.max(reserved_bottom(grid, island, runs));
+    }
+    let left = islands.iter().map(|(island, _)| island.left).min()?;
+
+    // Islands that share rows form a band, which moves as a whole
+    let mut bands: Vec<(Island, Vec<&[Run]>)> = Vec::new();
+    for (island, runs) in &islands {
+        match bands.last_mut() {
+            Some((band, members)) if island.top <= band.bottom => {
+                band.bottom = band.bottom.max(island.bottom);
+                band.left = band.left.min(island.left);
+                members.push(runs);
+            }
+            _ => bands.push((island.clone(), vec![runs])),
+        }
+    }
+
+    // Where each run goes
+    let mut moves = Vec::new();
+    let mut moved = 0;
+    let mut next_top = bands[0].0.top;
+    for (band, members) in &bands {
+        if (band.top, band.left) != (next_top, left) {
+            moved += members.len();
+        }
+        for runs in members {
+            let targets = runs.iter().map(|run| (*run, run.row - band.top + next_top, run.start - band.left + left));
+            moves.extend(targets.filter(|&(run, row, col)| (run.row, run.start) != (row, col)));
+        }
+        next_top += band.bottom - band.top + 1 + BAND_GAP_ROWS;
+    }
+    if moves.is_empty() {
+        return None;
+    }
+
+    // Old styles come off before the text is cleared and go back on after it is rewritten, so
+    // undoing restores them after their characters (as in App::write_styled_block)
+    let mut unstyle = Vec::new();
+    let mut restyle = Vec::new();
+    let mut texts = Vec::new();
+    for &(run, row, col) in &moves {
+        let text: String = (run.start..=run.end).map(|c| grid.get(run.row, c)).collect();
+        for offset in 0..text.chars().count() {
+            let style = grid.style(run.row, run.start + offset);
+            if !style.is_plain() {
+                unstyle.push((run.row, run.start + offset, style, Style::default()));
+                restyle.push((row, col + offset, Style::default(), style));
+            }
+        }
+        texts.push(text);
+    }
+
+    let mut commands = Vec::new();
+    let mut run = |command: undo::Command, grid: &mut ChunkedGrid| {
+        command.execute(grid);
+        commands.push(command);
+    };
+    if !unstyle.is_empty() {
+        run(undo::Command::Restyle { cells: unstyle }, grid);
+    }
+    for (&(from, _, _), text) in moves.iter().zip(&texts) {
+        let blank = " ".repeat(text.chars().count());
+        run(undo::Command::PasteBlock { row: from.row, col: from.start, lines: vec![blank], replaced_content: vec![text.clone()] }, grid);
+    }
+    for (&(_, row, col), text) in moves.iter().zip(texts) {
+        let replaced: String = (0..text.chars().count()).map(|offset| grid.get(row, col + offset)).collect();
+        run(undo::Command::PasteBlock { row, col, lines: vec![text], replaced_content: vec![replaced] }, grid);
+    }
+    if !restyle.is_empty() {
+        run(undo::Command::Restyle { cells: restyle }, grid);
+    }
+    Some((undo::Command::Group { commands }, moved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(grid: &ChunkedGrid, row: usize) -> String {
+        grid.get_line(row, 0, 60).trim_end().to_string()
+    }
+
+    #[test]
+    fn test_tidy() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 4, "Title");
+        grid.insert_at(1, 4, "more text");
+        grid.insert_at(6, 30, "a  b");
+        grid.insert_at(7, 33, "c");
+        grid.insert_at(6, 50, "table");
+        grid.insert_at(20, 10, "end");
+        grid.set_style(20, 10, Style { bold: true, ..Style::default() });
+        let before = grid.to_lines();
+
+        let islands = connected(&grid);
+        assert_eq!(islands.len(), 4);
+        assert_eq!(islands[1].0, Island { top: 6, left: 30, bottom: 7, right: 33 });
+        assert_eq!(island_at(&grid, 6, 32), Some((6, 30, 7, 33)));
+        assert_eq!(island_at(&grid, 1, 13), Some((0, 4, 1, 12)));
+        assert_eq!(island_at(&grid, 1, 14), None);
+
+        // The first band stays; the side-by-side islands keep their spacing
+        let (command, moved) = tidy(&mut grid).unwrap();
+        assert_eq!(moved, 3);
+        assert_eq!(line(&grid, 3), "    a  b                table");
+        assert_eq!(line(&grid, 4), "       c");
+        assert_eq!(line(&grid, 6), "    end");
+        assert!(grid.style(6, 4).bold && !grid.style(20, 10).bold);
+        assert!(tidy(&mut grid).is_none());
+
+        command.undo(&mut grid);
+        assert_eq!(grid.to_lines(), before);
+        assert!(grid.style(20, 10).bold && !grid.style(6, 4).bold);
+        assert!(tidy(&mut ChunkedGrid::new()).is_none());
+    }
+
+    #[test]
+    fn test_tidy_keeps_room_under_images() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 0, &images::token("a.png"));
+        grid.insert_at(12, 0, "caption");
+        let (_, moved) = tidy(&mut grid).unwrap();
+        assert_eq!(moved, 1);
+        assert_eq!(line(&grid, THUMBNAIL_ROWS + 2), "caption");
+    }
+}
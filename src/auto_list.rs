@@ -0,0 +1,125 @@
+// Line-start helpers for Enter: list markers and indentation
+use crate::settings::EnterIndent;
+
+/// A list item at the start of a line: "- ", "* ", "1. " and/or a "[ ] " checkbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    /// Leading spaces before the marker
+    pub indent: usize,
+    /// "-", "*" or the item number, if the line has a bullet
+    pub bullet: Option<Bullet>,
+    pub checkbox: bool,
+    /// Column where the item text starts (after the marker and its space)
+    pub content_col: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bullet {
+    Symbol(char),
+    Number(u64),
+}
+
+impl ListItem {
+    /// Parse the list marker at the start of `line`, if any
+    pub fn parse(line: &str) -> Option<Self> {
+        let chars: Vec<char> = line.chars().collect();
+        let indent = chars.iter().take_while(|&&c| c == ' ').count();
+        let mut pos = indent;
+
+        let bullet = match chars.get(pos) {
+            Some(&c @ ('-' | '*')) if chars.get(pos + 1) == Some(&' ') => {
+                pos += 2;
+                Some(Bullet::Symbol(c))
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let digits: String = chars[pos..].iter().take_while(|c| c.is_ascii_digit()).collect();
+                let after = pos + digits.len();
+                match (chars.get(after), chars.get(after + 1), digits.parse()) {
+                    (Some('.'), Some(' '), Ok(n)) => {
+                        pos = after + 2;
+                        Some(Bullet::Number(n))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let checkbox = chars.len() >= pos + 4
+            && chars[pos] == '['
+            && matches!(chars[pos + 1], ' ' | 'x' | 'X')
+            && chars[pos + 2] == ']'
+            && chars[pos + 3] == ' ';
+        if checkbox {
+            pos += 4;
+        }
+
+        if bullet.is_none() && !checkbox {
+            return None;
+        }
+        Some(ListItem { indent, bullet, checkbox, content_col: pos })
+    }
+
+    /// Marker for the next item: same indent and bullet, number incremented, checkbox unchecked
+    pub fn next_marker(&self) -> String {
+        let mut marker = " ".repeat(self.indent);
+        match self.bullet {
+            Some(Bullet::Symbol(c)) => {
+                marker.push(c);
+                marker.push(' ');
+            }
+            Some(Bullet::Number(n)) => marker.push_str(&format!("{}. ", n + 1)),
+            None => {}
+        }
+        if self.checkbox {
+            marker.push_str("[ ] ");
+        }
+        marker
+    }
+}
+
+/// Column the new line should start at when Enter is pressed at `col` of `chars`
+pub fn new_line_indent(chars: &[char], col: usize, mode: EnterIndent) -> usize {
+    let col = col.min(chars.len());
+    match mode {
+        EnterIndent::Off => 0,
+        EnterIndent::Indent if chars.iter().all(|&c| c == ' ') => 0,
+        EnterIndent::Indent => chars.iter().take_while(|&&c| c == ' ').count(),
+        EnterIndent::Column => {
+            // Back over spaces before the cursor, then to the start of the text block
+            // (blocks are separated by a gap of two or more spaces, as in csv_export)
+            let mut start = col;
+            while start > 0 && chars[start - 1] == ' ' {
+                start -= 1;
+            }
+            while start > 0 && !(chars[start - 1] == ' ' && (start == 1 || chars[start - 2] == ' ')) {
+                start -= 1;
+            }
+            start
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_markers() {
+        assert_eq!(ListItem::parse("- milk").unwrap().next_marker(), "- ");
+        assert_eq!(ListItem::parse("  9. nine").unwrap().next_marker(), "  10. ");
+        assert_eq!(ListItem::parse("* [x] done").unwrap().next_marker(), "* [ ] ");
+        assert_eq!(ListItem::parse("[ ] task").unwrap().content_col, 4);
+        assert_eq!(ListItem::parse("-1 is negative"), None);
+        assert_eq!(ListItem::parse("2.5 apples"), None);
+    }
+
+    #[test]
+    fn test_new_line_indent() {
+        let chars: Vec<char> = "    todo      a b c".chars().collect();
+        assert_eq!(new_line_indent(&chars, 8, EnterIndent::Indent), 4);
+        assert_eq!(new_line_indent(&chars, 19, EnterIndent::Column), 14);
+        assert_eq!(new_line_indent(&chars, 12, EnterIndent::Column), 4);
+        assert_eq!(new_line_indent(&chars, 19, EnterIndent::Off), 0);
+    }
+}
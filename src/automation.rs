@@ -0,0 +1,460 @@
+// Automation server: JSON-RPC 2.0 over a Unix socket
+// When enabled in settings.json, chonk-note listens on <data dir>/chonk-note/chonk-note.sock
+// (owner-only) while it runs. Each line is one request; each reply is one line. Methods:
+//   notes.list {limit?, offset?}        notes.search {query}     notes.get {id}
+//   notes.create {title?, content?, tags?}
+//   notes.update {id, title?, content?, tags?}                   notes.delete {id}
+// The socket also speaks the Model Context Protocol (initialize, tools/list, tools/call), and
+// `chonk-note mcp` bridges it to stdio so assistants can use it as an MCP server. Calls run in
+// the editor, so the sidebar and the open note stay in step with what tools change. Content
+// goes both ways as plain text; notes.update content replaces the note's text, layout and colors.
+use crate::config::automation;
+use crate::notes_database::Note;
+use crate::{hooks, note_format, App};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+
+/// JSON-RPC error codes
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        Self::new(SERVER_ERROR, e.to_string())
+    }
+}
+
+/// A notes.* call waiting for the editor
+pub struct Call {
+    pub method: String,
+    pub params: Value,
+    pub reply: oneshot::Sender<Result<Value, RpcError>>,
+}
+
+pub fn socket_path() -> Option<PathBuf> {
+    crate::data_dir::file(automation::SOCKET_NAME)
+}
+
+/// The listening socket; removed again when dropped
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Start listening; calls for the editor arrive on the returned channel
+pub fn start() -> Result<(Server, mpsc::UnboundedReceiver<Call>)> {
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("another chonk-note is already serving {}", path.display());
+        }
+        // Left behind by an instance that didn't exit cleanly
+        std::fs::remove_file(&path)?;
+    }
+    let listener = crate::instance::bind_owner_only(&path)?;
+    listener.set_nonblocking(true)?;
+    let listener = tokio::net::UnixListener::from_std(listener)?;
+    log::info!("Automation server listening on {}", path.display());
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &tx).await {
+                    log::warn!("Automation connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok((Server { path }, rx))
+}
+
+async fn handle_connection(stream: impl AsyncRead + AsyncWrite, calls: &mpsc::UnboundedSender<Call>) -> Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+    loop {
+        // Read at most one byte past the limit, so an endless line isn't buffered whole
+        buf.clear();
+        let limit = automation::MAX_REQUEST_BYTES as u64 + 1;
+        if (&mut reader).take(limit).read_until(b'\n', &mut buf).await? == 0 {
+            break;
+        }
+        if buf.len() > automation::MAX_REQUEST_BYTES && buf.last() != Some(&b'\n') {
+            anyhow::bail!("request longer than {} bytes", automation::MAX_REQUEST_BYTES);
+        }
+        let line = String::from_utf8_lossy(&buf);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle_message(message, calls).await,
+            Err(e) => Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } })),
+        };
+        if let Some(response) = response {
+            writer.write_all(format!("{}\n", response).as_bytes()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The reply to one request (None for notifications, which get no reply)
+async fn handle_message(message: Value, calls: &mpsc::UnboundedSender<Call>) -> Option<Value> {
+    let id = message.get("id").cloned();
+    let result = match message.get("method").and_then(Value::as_str) {
+        None => Err(RpcError::new(INVALID_REQUEST, "missing method")),
+        Some(method) => {
+            let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+            dispatch(method, params, calls).await
+        }
+    };
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": e.code, "message": e.message } }),
+    })
+}
+
+async fn dispatch(method: &str, params: Value, calls: &mpsc::UnboundedSender<Call>) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(json!({
+            "protocolVersion": params.get("protocolVersion").cloned().unwrap_or_else(|| json!(automation::MCP_VERSION)),
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "chonk-note", "version": env!("CARGO_PKG_VERSION") },
+        })),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tools() })),
+        "tools/call" => {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+            let Some(method) = TOOLS.iter().find(|tool| tool.name == name).map(|tool| tool.method) else {
+                return Err(RpcError::new(INVALID_PARAMS, format!("unknown tool: {}", name)));
+            };
+            let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            // Tool failures are results the model can read, not protocol errors
+            Ok(match call_editor(method, arguments, calls).await {
+                Ok(value) => json!({ "content": [{ "type": "text", "text": serde_json::to_string_pretty(&value).unwrap_or_default() }] }),
+                Err(e) => json!({ "content": [{ "type": "text", "text": e.message }], "isError": true }),
+            })
+        }
+        method if method.starts_with("notes.") => call_editor(method, params, calls).await,
+        method => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+    }
+}
+
+async fn call_editor(method: &str, params: Value, calls: &mpsc::UnboundedSender<Call>) -> Result<Value, RpcError> {
+    let (reply, response) = oneshot::channel();
+    calls
+        .send(Call { method: method.to_string(), params, reply })
+        .map_err(|_| RpcError::new(SERVER_ERROR, "editor has stopped"))?;
+    response.await.unwrap_or_else(|_| Err(RpcError::new(SERVER_ERROR, "editor has stopped")))
+}
+
+struct Tool {
+    name: &'static str,
+    method: &'static str,
+    description: &'static str,
+    /// (parameter, JSON type, description, required)
+    params: &'static [(&'static str, &'static str, &'static str, bool)],
+}
+
+const TOOLS: &[Tool] = &[
+    Tool { name: "list_notes", method: "notes.list", description: "List notes, newest first (id, title, tags, dates)",
+           params: &[("limit", "integer", "Most notes to return (default 100)", false), ("offset", "integer", "Notes to skip", false)] },
+    Tool { name: "search_notes", method: "notes.search", description: "Find notes whose title, content or tags contain the text",
+           params: &[("query", "string", "Text to look for", true)] },
+    Tool { name: "read_note", method: "notes.get", description: "Read a note, including its content",
+           params: &[("id", "string", "Note id", true)] },
+    Tool { name: "create_note", method: "notes.create", description: "Create a note",
+           params: &[("title", "string", "Title (defaults to the first line)", false), ("content", "string", "Text of the note", false),
+                     ("tags", "array", "Tags", false)] },
+    Tool { name: "update_note", method: "notes.update", description: "Change a note's title, content or tags; omitted fields are kept",
+           params: &[("id", "string", "Note id", true), ("title", "string", "New title", false),
+                     ("content", "string", "New content as plain text (replaces the whole note, including its colors)", false), ("tags", "array", "New tags", false)] },
+    Tool { name: "delete_note", method: "notes.delete", description: "Delete a note",
+           params: &[("id", "string", "Note id", true)] },
+];
+
+/// MCP tool descriptions with JSON Schema inputs
+fn tools() -> Vec<Value> {
+    TOOLS
+        .iter()
+        .map(|tool| {
+            let mut properties = serde_json::Map::new();
+            for (name, kind, description, _) in tool.params {
+                let mut schema = json!({ "type": kind, "description": description });
+                if *kind == "array" {
+                    schema["items"] = json!({ "type": "string" });
+                }
+                properties.insert(name.to_string(), schema);
+            }
+            let required: Vec<&str> = tool.params.iter().filter(|p| p.3).map(|p| p.0).collect();
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": { "type": "object", "properties": properties, "required": required },
+            })
+        })
+        .collect()
+}
+
+fn summary(note: &Note) -> Value {
+    json!({ "id": note.id, "title": note.title, "tags": note.tags, "created_at": note.created_at, "updated_at": note.updated_at })
+}
+
+/// The summary and the note's text (its stored form is internal to the editor)
+fn full(note: &Note) -> Value {
+    let mut value = summary(note);
+    value["content"] = json!(note_format::decode(&note.content).to_lines().join("\n"));
+    value
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(RpcError::new(INVALID_PARAMS, format!("{} must be a string", name))),
+    }
+}
+
+fn required<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    string_param(params, name)?.ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("{} is required", name)))
+}
+
+fn tags_param(params: &Value) -> Result<Option<Vec<String>>, RpcError> {
+    match params.get("tags") {
+        None | Some(Value::Null) => Ok(None),
+        Some(tags) => serde_json::from_value(tags.clone())
+            .map(Some)
+            .map_err(|_| RpcError::new(INVALID_PARAMS, "tags must be an array of strings")),
+    }
+}
+
+fn count_param(params: &Value, name: &str, default: usize) -> Result<usize, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(default),
+        Some(value) => value
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("{} must be a non-negative integer", name))),
+    }
+}
+
+/// Run a notes.* call against the editor's database and state
+pub fn execute(app: &mut App, method: &str, params: &Value) -> Result<Value, RpcError> {
+    let no_note = |id: &str| RpcError::new(SERVER_ERROR, format!("no note with id {}", id));
+    match method {
+        "notes.list" => {
+            let limit = count_param(params, "limit", automation::DEFAULT_LIST_LIMIT)?.min(automation::MAX_LIST_LIMIT);
+            let notes = app.notes_mode.db.list_notes_page(limit, count_param(params, "offset", 0)?)?;
+            Ok(json!(notes.iter().map(summary).collect::<Vec<_>>()))
+        }
+        "notes.search" => {
+            let notes = app.notes_mode.db.search_notes(required(params, "query")?)?;
+            Ok(json!(notes.iter().map(summary).collect::<Vec<_>>()))
+        }
+        "notes.get" => {
+            let id = required(params, "id")?;
+            // The open note's latest text is in the editor, possibly not saved yet
+            if app.notes_mode.current_note.as_ref().is_some_and(|n| n.id == id) {
+                app.save_current_note()?;
+            }
+            let note = app.notes_mode.db.get_note(id)?.ok_or_else(|| no_note(id))?;
+            Ok(full(&note))
+        }
+        "notes.create" => {
+            let content = string_param(params, "content")?.unwrap_or_default().replace("\r\n", "\n");
+            let title = match string_param(params, "title")?.map(str::trim) {
+                Some(title) if !title.is_empty() => title.to_string(),
+                _ => note_format::first_line_title_of(&content).unwrap_or_else(|| "Untitled".to_string()),
+            };
+            let note = app.notes_mode.db.create_note(title, content, tags_param(params)?.unwrap_or_default())?;
+            app.hooks.fire(hooks::Event::Create, &note);
+            app.invalidate_notes_list();
+            app.refresh_notes_list();
+            app.status_message = format!("\"{}\" created by a tool", note.title);
+            app.needs_redraw = true;
+            Ok(full(&note))
+        }
+        "notes.update" => {
+            let id = required(params, "id")?;
+            let is_open = app.notes_mode.current_note.as_ref().is_some_and(|n| n.id == id);
+            if is_open {
+                app.save_current_note()?;
+                if app.save_conflict.is_some() {
+                    return Err(RpcError::new(SERVER_ERROR, "the note has a save conflict open in the editor"));
+                }
+            }
+            let mut note = app.notes_mode.db.get_note(id)?.ok_or_else(|| no_note(id))?;
+            if let Some(title) = string_param(params, "title")? {
+                note.title = title.to_string();
+            }
+            if let Some(content) = string_param(params, "content")? {
+                note.content = content.replace("\r\n", "\n");
+            }
+            if let Some(tags) = tags_param(params)? {
+                note.tags = tags;
+            }
+            note.updated_at = app.notes_mode.db.update_note(&note.id, note.title.clone(), note.content.clone(), note.tags.clone())?;
+
+            if let Some(entry) = app.notes_list.iter_mut().find(|n| n.id == note.id) {
+                *entry = note.clone();
+            }
+            if is_open {
+                // Show the new text where the user was looking
+                let (cursor, viewport) = ((app.cursor_row, app.cursor_col), (app.viewport_row, app.viewport_col));
+                app.load_note(note.clone());
+                (app.cursor_row, app.cursor_col) = cursor;
+                (app.viewport_row, app.viewport_col) = viewport;
+                app.dirty = false;
+            }
+            app.status_message = format!("\"{}\" updated by a tool", note.title);
+            app.needs_redraw = true;
+            Ok(full(&note))
+        }
+        "notes.delete" => {
+            let id = required(params, "id")?;
+            let note = app.notes_mode.db.get_note(id)?.ok_or_else(|| no_note(id))?;
+            let open_index = app.notes_list.iter().position(|n| n.id == id)
+                .filter(|_| app.notes_mode.current_note.as_ref().is_some_and(|n| n.id == id));
+            match open_index {
+                // Deleting the open note opens another, like deleting it from the sidebar
+                Some(index) => {
+                    app.finish_db_requests()?;
+                    app.dirty = false;
+                    app.selected_note_index = index;
+                    app.delete_selected_note()?;
+                }
+                None => {
+                    app.notes_mode.db.delete_note(id)?;
+                    app.invalidate_notes_list();
+                    app.refresh_notes_list();
+                }
+            }
+            app.status_message = format!("\"{}\" deleted by a tool", note.title);
+            app.needs_redraw = true;
+            Ok(json!({ "deleted": id }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method: {}", method))),
+    }
+}
+
+/// `chonk-note mcp`: relay stdin/stdout to the running editor's socket
+pub fn bridge_stdio() -> Result<()> {
+    use std::io::{BufRead, Write};
+
+    let path = socket_path().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    let socket = std::os::unix::net::UnixStream::connect(&path).map_err(|e| {
+        anyhow::anyhow!("cannot reach chonk-note at {} ({}); start it with \"automation_server\": true in settings.json", path.display(), e)
+    })?;
+    let mut to_editor = socket.try_clone()?;
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+            if writeln!(to_editor, "{}", line).is_err() {
+                break;
+            }
+        }
+        let _ = to_editor.shutdown(std::net::Shutdown::Write);
+    });
+
+    let mut stdout = std::io::stdout().lock();
+    for line in std::io::BufReader::new(socket).lines() {
+        writeln!(stdout, "{}", line?)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes_database::NotesDatabase;
+    use crate::notes_mode::NotesMode;
+    use crate::settings::Settings;
+
+    #[tokio::test]
+    async fn test_json_rpc_and_mcp() -> Result<()> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Call>();
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Shopping".to_string(), "Shopping\neggs".to_string(), vec!["home".to_string()])?;
+        // Opens the note above
+        let mut app = App::with_parts(NotesMode::with_db(db), Settings::default());
+        // The editor isn't Send, so it answers calls on this task alongside the client
+        let editor = async {
+            while let Some(call) = rx.recv().await {
+                let _ = call.reply.send(execute(&mut app, &call.method, &call.params));
+            }
+        };
+        let client = async move {
+            let request = |message: Value| handle_message(message, &tx);
+            let found = request(json!({ "jsonrpc": "2.0", "id": 1, "method": "notes.search", "params": { "query": "eggs" } })).await.unwrap();
+            let id = found["result"][0]["id"].as_str().unwrap().to_string();
+            let created = request(json!({ "jsonrpc": "2.0", "id": 2, "method": "notes.create",
+                                          "params": { "content": "Ideas\nmore" } })).await.unwrap();
+            assert_eq!(created["result"]["title"], "Ideas");
+
+            // Through MCP: the note is open in the editor, which picks up the new text
+            let called = request(json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                                         "params": { "name": "update_note", "arguments": { "id": id, "content": "Shopping\nmilk" } } })).await.unwrap();
+            assert!(called["result"]["content"][0]["text"].as_str().unwrap().contains("milk"));
+            let missing = request(json!({ "jsonrpc": "2.0", "id": 4, "method": "notes.get", "params": {} })).await.unwrap();
+            assert_eq!(missing["error"]["code"], INVALID_PARAMS);
+            assert!(request(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await.is_none());
+            id
+        };
+        let (id, ()) = tokio::join!(client, editor);
+
+        assert_eq!(app.grid.get_line(1, 0, 3), "milk");
+        assert_eq!(app.notes_list.iter().find(|n| n.id == id).map(|n| n.content.as_str()), Some("Shopping\nmilk"));
+        assert_eq!(app.notes_list.len(), 2);
+
+        // Tools get a styled note as its text, not its stored form
+        let mut grid = crate::chunked_grid::ChunkedGrid::from_lines(&["Red".to_string()]);
+        grid.set_style(0, 0, crate::chunked_grid::Style { bold: true, ..Default::default() });
+        let styled = app.notes_mode.db.create_note("Red".to_string(), note_format::encode(&grid), vec![])?;
+        assert!(styled.content.contains('\u{1}'));
+        let read = execute(&mut app, "notes.get", &json!({ "id": styled.id })).unwrap();
+        assert_eq!(read["content"], "Red");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_size_limit() {
+        let (tx, _rx) = mpsc::unbounded_channel::<Call>();
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (_read_half, mut write_half) = tokio::io::split(client);
+        // A line that never ends is refused once it passes the limit
+        let sender = tokio::spawn(async move {
+            let chunk = vec![b'x'; 64 * 1024];
+            while write_half.write_all(&chunk).await.is_ok() {}
+        });
+        let result = handle_connection(server, &tx).await;
+        assert!(result.unwrap_err().to_string().contains("request longer than"));
+        sender.abort();
+    }
+}
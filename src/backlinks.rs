@@ -0,0 +1,241 @@
+// Backlinks and unlinked mentions of the open note
+// The backlinks panel lists the other notes that link to the open note with [[Title]] (or
+// embed it with ![[Title]]), and below them the places where its title appears as plain text.
+// Clicking an entry opens that note at the mention; "link" on an unlinked mention rewrites it
+// as [[Title]] in the other note. Finding mentions reads every note containing the title, so
+// it runs on the database thread and the panel fills in when it finishes. Titles shorter
+// than MIN_TITLE_CHARS aren't searched as plain text; they would match nearly everything.
+use crate::config::backlinks::{MAX_MENTIONS, MIN_TITLE_CHARS};
+use crate::note_format;
+use crate::notes_database::NotesDatabase;
+use crate::transclusion;
+use anyhow::Result;
+
+/// One place another note mentions the open note; positions are in that note's lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mention {
+    pub note_id: String,
+    pub note_title: String,
+    pub line: usize,
+    pub col: usize,
+    /// Characters of the mention ([[Title]] for a link, the title for plain text)
+    pub len: usize,
+    /// The line around the mention
+    pub context: String,
+    pub linked: bool,
+}
+
+/// A row of the backlinks panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row {
+    /// Section header: its label and how many entries it has
+    Header(&'static str, usize),
+    /// Index into `mentions`
+    Mention(usize),
+    /// Shown under a section with nothing in it
+    Empty,
+}
+
+/// What the panel shows, and whether it needs finding again
+pub struct Backlinks {
+    pub mentions: Vec<Mention>,
+    /// The note the mentions are of (None before the first search finishes)
+    pub note_id: Option<String>,
+    pub scroll: usize,
+    /// A search is running on the database thread
+    pub pending: bool,
+    stale: bool,
+}
+
+impl Backlinks {
+    pub fn new() -> Self {
+        Self { mentions: Vec::new(), note_id: None, scroll: 0, pending: false, stale: true }
+    }
+
+    /// Search again (called whenever notes change or another note opens)
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Whether a search should be started for the note `note_id`
+    pub fn needs_search(&self, note_id: &str) -> bool {
+        !self.pending && (self.stale || self.note_id.as_deref() != Some(note_id))
+    }
+
+    pub fn start_search(&mut self) {
+        self.pending = true;
+        self.stale = false;
+    }
+
+    /// A search finished; results for a note that is no longer open are dropped
+    pub fn finish_search(&mut self, note_id: String, mentions: Vec<Mention>, current: Option<&str>) {
+        self.pending = false;
+        if current != Some(note_id.as_str()) {
+            return;
+        }
+        if self.note_id.as_deref() != Some(note_id.as_str()) {
+            self.scroll = 0;
+        }
+        self.note_id = Some(note_id);
+        self.mentions = mentions;
+        self.scroll = self.scroll.min(self.rows().len().saturating_sub(1));
+    }
+
+    /// Panel rows: linked mentions first, then unlinked ones
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (label, linked) in [("Linked", true), ("Unlinked mentions", false)] {
+            let section: Vec<usize> = (0..self.mentions.len()).filter(|&i| self.mentions[i].linked == linked).collect();
+            rows.push(Row::Header(label, section.len()));
+            if section.is_empty() {
+                rows.push(Row::Empty);
+            }
+            rows.extend(section.into_iter().map(Row::Mention));
+        }
+        rows
+    }
+}
+
+/// Every mention of the note titled `title` in other notes, at most MAX_MENTIONS
+pub fn find(db: &NotesDatabase, note_id: &str, title: &str) -> Result<Vec<Mention>> {
+    let title = title.trim();
+    if !transclusion::linkable(title) {
+        return Ok(Vec::new());
+    }
+    let wanted: Vec<char> = title.chars().map(fold).collect();
+    let plain_text = wanted.len() >= MIN_TITLE_CHARS;
+    let mut mentions = Vec::new();
+    for note in db.notes_containing(title)? {
+        if note.id == note_id {
+            continue;
+        }
+        for (line_index, line) in note_format::decode(&note.content).to_lines().iter().enumerate() {
+            let chars: Vec<char> = line.chars().collect();
+            let tokens: Vec<(usize, usize, String)> =
+                transclusion::links_in_line(&chars).into_iter().chain(transclusion::embeds_in_line(&chars)).collect();
+            let mut found: Vec<(usize, usize, bool)> = tokens
+                .iter()
+                .filter(|(_, _, linked)| linked.to_lowercase() == title.to_lowercase())
+                .map(|&(start, end, _)| (start, end - start, true))
+                .collect();
+            if plain_text {
+                let lower: Vec<char> = chars.iter().copied().map(fold).collect();
+                found.extend(
+                    plain_mentions(&lower, &wanted)
+                        .filter(|&col| !tokens.iter().any(|&(start, end, _)| col < end && col + wanted.len() > start))
+                        .map(|col| (col, wanted.len(), false)),
+                );
+            }
+            for (col, len, linked) in found {
+                let context: String = chars[col.saturating_sub(12)..].iter().collect();
+                mentions.push(Mention {
+                    note_id: note.id.clone(),
+                    note_title: note.title.clone(),
+                    line: line_index,
+                    col,
+                    len,
+                    context: context.trim().to_string(),
+                    linked,
+                });
+                if mentions.len() == MAX_MENTIONS {
+                    return Ok(mentions);
+                }
+            }
+        }
+    }
+    Ok(mentions)
+}
+
+/// Lower case, one character for one so columns stay put
+fn fold(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Columns where `wanted` appears in `line` as a whole word (both already folded)
+fn plain_mentions<'a>(line: &'a [char], wanted: &'a [char]) -> impl Iterator<Item = usize> + 'a {
+    let word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+    (0..(line.len() + 1).saturating_sub(wanted.len())).filter(move |&col| {
+        line[col..].starts_with(wanted)
+            && !word(col.checked_sub(1).and_then(|before| line.get(before)))
+            && !word(line.get(col + wanted.len()))
+    })
+}
+
+/// Rewrite an unlinked mention as [[title]] in its note (the rest of its line moves right).
+/// False if the note has changed so the mention is no longer there.
+pub fn link(db: &NotesDatabase, mention: &Mention, title: &str) -> Result<bool> {
+    let Some(note) = db.get_note(&mention.note_id)? else {
+        return Ok(false);
+    };
+    // Splice the token into the decoded grid, so the rest of the note (its origin, its
+    // layout, its colors) is written back as it was; the token takes the mention's style
+    let mut grid = note_format::decode(&note.content);
+    let (top, left) = grid.bounds().map_or((0, 0), |(row, col, _, _)| (row, col));
+    let (row, col) = (top + mention.line, left + mention.col);
+    if !(0..mention.len).map(|i| fold(grid.get(row, col + i))).eq(title.chars().map(fold)) {
+        return Ok(false);
+    }
+    let token = transclusion::link_token(title);
+    let style = grid.style(row, col);
+    grid.shift_right(row, col + mention.len, token.chars().count().saturating_sub(mention.len));
+    for (i, ch) in token.chars().enumerate() {
+        grid.set_styled(row, col + i, ch, style);
+    }
+    db.update_note(&note.id, note.title, note_format::encode(&grid), note.tags)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked_grid::{ChunkedGrid, Style};
+
+    #[test]
+    fn test_mentions() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let target = db.create_note("Garden".to_string(), String::new(), vec![])?;
+        let other = db.create_note("Plans".to_string(), "see [[garden]]\nthe garden, not gardening".to_string(), vec![])?;
+
+        let mentions = find(&db, &target.id, "Garden")?;
+        let found: Vec<_> = mentions.iter().map(|m| (m.note_id.as_str(), m.line, m.col, m.len, m.linked)).collect();
+        assert_eq!(found, [(other.id.as_str(), 0, 4, 10, true), (other.id.as_str(), 1, 4, 6, false)]);
+
+        let mut backlinks = Backlinks::new();
+        backlinks.finish_search(target.id.clone(), mentions.clone(), Some(&target.id));
+        assert_eq!(backlinks.rows(), [Row::Header("Linked", 1), Row::Mention(0), Row::Header("Unlinked mentions", 1), Row::Mention(1)]);
+
+        assert!(link(&db, &mentions[1], "Garden")?);
+        assert_eq!(db.get_note(&other.id)?.map(|note| note.content).as_deref(), Some("see [[garden]]\nthe [[Garden]], not gardening"));
+        // Already linked, so it's no longer there as plain text
+        assert!(!link(&db, &mentions[1], "Garden")?);
+        assert!(find(&db, &target.id, "Garden")?.iter().all(|m| m.linked));
+
+        // Text left of the origin stays left of it
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(40, 40, "<-");
+        grid.insert_at(40, 44, "the garden");
+        grid.origin = (40, 43);
+        let placed = db.create_note("Placed".to_string(), note_format::encode(&grid), vec![])?;
+        let mention = find(&db, &target.id, "Garden")?.into_iter().find(|m| m.note_id == placed.id).unwrap();
+        assert!(link(&db, &mention, "Garden")?);
+        let linked = note_format::decode(&db.get_note(&placed.id)?.unwrap().content);
+        let (top, left, _, _) = linked.bounds().unwrap();
+        assert_eq!(linked.logical_pos(top, left), (0, -3));
+        assert_eq!(linked.to_lines(), ["<-  the [[Garden]]"]);
+
+        // Colors stay where they were: on the mention, now the token, and after it
+        let red = Style { fg: Some((200, 0, 0)), ..Style::default() };
+        let mut grid = ChunkedGrid::from_lines(&["a garden bed".to_string()]);
+        for col in 2..12 {
+            grid.set_style(0, col, red);
+        }
+        let colored = db.create_note("Colored".to_string(), note_format::encode(&grid), vec![])?;
+        let mention = find(&db, &target.id, "Garden")?.into_iter().find(|m| m.note_id == colored.id).unwrap();
+        assert!(link(&db, &mention, "Garden")?);
+        let linked = note_format::decode(&db.get_note(&colored.id)?.unwrap().content);
+        assert_eq!(linked.to_lines(), ["a [[Garden]] bed"]);
+        assert_eq!(linked.style(0, 0), Style::default());
+        assert!((2..16).filter(|&col| linked.get(0, col) != ' ').all(|col| linked.style(0, col) == red));
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+// Follow mode: broadcasting the editor's view
+// Serves the open note's visible rows and cursor read-only over HTTP, and `follow` shows them.
+use crate::capture_server::tokens_match;
+use crate::config::broadcast;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::watch;
+
+/// What viewers see: the editor's visible rows and where the cursor is among them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    pub title: String,
+    pub rows: Vec<String>,
+    /// (row, column) in `rows`, when the cursor is on screen
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// A running broadcast; stops when dropped
+pub struct Broadcast {
+    /// The address to give viewers, token included
+    pub url: String,
+    view: watch::Sender<View>,
+    server: tokio::task::JoinHandle<()>,
+}
+
+impl Broadcast {
+    /// Listen on every interface at `port` (any free port for 0)
+    pub fn start(port: u16) -> Result<Self> {
+        // A fresh secret for this broadcast
+        let token = crate::data_dir::random_hex(16);
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port)).with_context(|| format!("Listening on port {}", port))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        log::info!("Broadcasting on port {}", port);
+
+        let (view, viewers) = watch::channel(View::default());
+        let server_token = token.clone();
+        let server = tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let (token, viewers) = (server_token.clone(), viewers.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, &token, viewers).await {
+                        log::debug!("Broadcast viewer disconnected: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Self { url: format!("http://{}:{}/?t={}", local_address(), port, token), view, server })
+    }
+
+    /// Send `view` to every viewer, if it changed
+    pub fn publish(&self, view: View) {
+        self.view.send_if_modified(|current| {
+            let changed = *current != view;
+            if changed {
+                *current = view;
+            }
+            changed
+        });
+    }
+}
+
+impl Drop for Broadcast {
+    fn drop(&mut self) {
+        // Viewers' event streams end when `view` goes with it
+        self.server.abort();
+    }
+}
+
+/// This machine's address on the local network. Connecting a UDP socket sends nothing; it
+/// only picks the interface that traffic would leave through.
+fn local_address() -> String {
+    std::net::UdpSocket::bind(("0.0.0.0", 0))
+        .and_then(|socket| {
+            socket.connect(("192.0.2.1", 9))?;
+            socket.local_addr()
+        })
+        .map(|address| address.ip().to_string())
+        .unwrap_or_else(|_| "127.0.0.1".to_string())
+}
+
+async fn handle_connection(mut stream: impl AsyncRead + AsyncWrite + Unpin, token: &str, mut view: watch::Receiver<View>) -> Result<()> {
+    let head = tokio::time::timeout(Duration::from_millis(broadcast::TIMEOUT_MS), read_head(&mut stream))
+        .await
+        .map_err(|_| anyhow::anyhow!("no request within {} ms", broadcast::TIMEOUT_MS))??;
+    let mut request_line = head.lines().next().unwrap_or("").split_whitespace();
+    let (method, target) = (request_line.next().unwrap_or(""), request_line.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let sent = query.split('&').find_map(|pair| pair.strip_prefix("t=")).unwrap_or("");
+
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", "use GET").await;
+    }
+    if !tokens_match(sent.as_bytes(), token.as_bytes()) {
+        return respond(&mut stream, "401 Unauthorized", "text/plain", "missing or wrong token").await;
+    }
+    match path {
+        "/" => respond(&mut stream, "200 OK", "text/html; charset=utf-8", PAGE).await,
+        "/view" => {
+            let body = serde_json::to_string(&*view.borrow())?;
+            respond(&mut stream, "200 OK", "application/json", &body).await
+        }
+        "/events" => {
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")
+                .await?;
+            loop {
+                let data = serde_json::to_string(&*view.borrow_and_update())?;
+                stream.write_all(format!("data: {}\n\n", data).as_bytes()).await?;
+                stream.flush().await?;
+                // Err once the broadcast has stopped
+                if view.changed().await.is_err() {
+                    break;
+                }
+            }
+            stream.shutdown().await?;
+            Ok(())
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "not found").await,
+    }
+}
+
+/// The request line and headers
+async fn read_head(stream: &mut (impl AsyncRead + Unpin)) -> Result<String> {
+    let mut buf = Vec::new();
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        anyhow::ensure!(buf.len() <= broadcast::MAX_HEADER_BYTES, "headers too large");
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "incomplete request");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+async fn respond(stream: &mut (impl AsyncWrite + Unpin), status: &str, content_type: &str, body: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// The page browsers get: the rows in a <pre>, the cursor in reverse video
+const PAGE: &str = r#"<!DOCTYPE html>
+<html><head><meta charset="utf-8"><title>chonk-note</title>
+<style>
+  body { margin: 0; background: #1e1e1e; color: #ddd; font-family: ui-monospace, Menlo, Consolas, monospace; }
+  header { background: #00707a; color: #fff; font-weight: bold; padding: 2px 8px; }
+  pre { margin: 0; padding: 4px 8px; line-height: 1.25; }
+  .cursor { background: #ddd; color: #1e1e1e; }
+</style></head>
+<body><header id="title">Connecting…</header><pre id="rows"></pre>
+<script>
+  const title = document.getElementById("title"), rows = document.getElementById("rows");
+  const events = new EventSource("/events" + location.search);
+  events.onmessage = (event) => {
+    const view = JSON.parse(event.data);
+    title.textContent = document.title = view.title;
+    rows.replaceChildren();
+    view.rows.forEach((row, r) => {
+      let chars = Array.from(row);
+      if (view.cursor && view.cursor[0] === r) {
+        const c = view.cursor[1];
+        while (chars.length <= c) chars.push(" ");
+        const cursor = document.createElement("span");
+        cursor.className = "cursor";
+        cursor.textContent = chars[c];
+        rows.append(chars.slice(0, c).join(""), cursor, chars.slice(c + 1).join(""));
+      } else {
+        rows.append(row);
+      }
+      rows.append("\n");
+    });
+  };
+  events.onerror = () => { if (events.readyState === EventSource.CLOSED) title.textContent = "The broadcast has ended"; };
+</script></body></html>
+"#;
+
+/// `chonk-note follow <address>`: show a broadcast in this terminal until it ends
+pub fn follow(url: &str) -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let rest = url.strip_prefix("http://").with_context(|| format!("not a broadcast address: {}", url))?;
+    let (host, target) = rest.split_once('/').unwrap_or((rest, ""));
+    let query = target.split_once('?').map_or("", |(_, query)| query);
+    let mut stream = std::net::TcpStream::connect(host).with_context(|| format!("Connecting to {}", host))?;
+    write!(stream, "GET /events?{} HTTP/1.1\r\nHost: {}\r\nAccept: text/event-stream\r\n\r\n", query, host)?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    anyhow::ensure!(status.split_whitespace().nth(1) == Some("200"), "{} refused: {}", url, printable(status.trim()));
+    // The rest of the headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        line.clear();
+    }
+
+    let mut stdout = std::io::stdout();
+    for line in reader.lines() {
+        let Some(data) = line?.strip_prefix("data: ").map(str::to_string) else {
+            continue;
+        };
+        let view: View = serde_json::from_str(&data)?;
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        stdout.write_all(render(&view, width as usize, height as usize).as_bytes())?;
+        stdout.flush()?;
+    }
+    println!("\nThe broadcast has ended");
+    Ok(())
+}
+
+/// A frame for the follower's terminal: a title bar, then as many rows as fit. The view comes
+/// from another machine, so its control characters are replaced before they reach the terminal.
+fn render(view: &View, width: usize, height: usize) -> String {
+    let title = format!(" Following: {} (Ctrl+C to stop)", printable(&view.title));
+    let mut frame = format!("\x1b[H\x1b[2J\x1b[7m{:<width$}\x1b[0m", title.chars().take(width).collect::<String>(), width = width);
+    for (r, row) in view.rows.iter().take(height.saturating_sub(1)).enumerate() {
+        let mut chars: Vec<char> = printable(row).chars().take(width).collect();
+        frame.push_str("\r\n");
+        match view.cursor {
+            Some((cursor_row, col)) if cursor_row == r && col < width => {
+                chars.resize(chars.len().max(col + 1), ' ');
+                let before: String = chars[..col].iter().collect();
+                let after: String = chars[col + 1..].iter().collect();
+                frame.push_str(&format!("{}\x1b[7m{}\x1b[0m{}", before, chars[col], after));
+            }
+            _ => frame.extend(chars),
+        }
+    }
+    frame
+}
+
+/// `text` with C0 and C1 control characters (escape sequences, bells, ...) shown as U+FFFD
+fn printable(text: &str) -> String {
+    text.chars().map(|c| if c.is_control() { '\u{FFFD}' } else { c }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(target: &str, view: &watch::Receiver<View>) -> String {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let server = tokio::spawn(handle_connection(server, "secret", view.clone()));
+        let (mut read_half, mut write_half) = tokio::io::split(client);
+        write_half.write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        read_half.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap().unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let view = View { title: "Talk".to_string(), rows: vec!["Hello".to_string(), "world".to_string()], cursor: Some((1, 2)) };
+        let (sender, viewers) = watch::channel(view.clone());
+
+        let response = get("/view?t=secret", &viewers).await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(serde_json::from_str::<View>(body).unwrap(), view);
+        assert!(get("/view?t=guess!", &viewers).await.starts_with("HTTP/1.1 401"));
+        assert!(get("/?t=secret", &viewers).await.contains("EventSource"));
+
+        // The event stream sends the view, then ends with the broadcast
+        let events = tokio::spawn({
+            let viewers = viewers.clone();
+            async move { get("/events?t=secret", &viewers).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        drop((sender, viewers));
+        let response = events.await.unwrap();
+        assert!(response.contains(&format!("data: {}\n\n", serde_json::to_string(&view).unwrap())));
+
+        assert_eq!(render(&view, 20, 3), "\x1b[H\x1b[2J\x1b[7m Following: Talk (Ct\x1b[0m\r\nHello\r\nwo\x1b[7mr\x1b[0mld");
+
+        // A peer's escape sequences are not passed on to the terminal
+        let hostile = View { title: "\x1b]0;owned\x07".to_string(), rows: vec!["a\x1b[2Jb\u{9b}c".to_string()], cursor: None };
+        let frame = render(&hostile, 40, 3);
+        assert!(frame.ends_with("\r\na\u{FFFD}[2Jb\u{FFFD}c"));
+        assert!(!frame.contains("\x07") && frame.matches('\x1b').count() == 4);
+    }
+}
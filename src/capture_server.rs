@@ -0,0 +1,200 @@
+// Local capture endpoint for browser extensions and scripts
+// When enabled in settings.json, chonk-note listens on 127.0.0.1 while it runs and accepts
+//   POST /capture   Authorization: Bearer <token>   {"title": "...", "content": "...", "url": "...", "tags": [...]}
+// Each capture is handed to the editor, which creates the note and replies with its id.
+// The token lives in <data dir>/chonk-note/capture-token (created on first start, owner-only).
+use crate::config::capture;
+use anyhow::Result;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug, Deserialize)]
+pub struct CaptureRequest {
+    #[serde(default)]
+    pub title: String,
+    pub content: String,
+    /// Page the content came from; kept at the end of the note
+    pub url: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// A capture waiting for the editor; `reply` gets the new note's id or an error message
+pub struct Capture {
+    pub request: CaptureRequest,
+    pub reply: oneshot::Sender<Result<String, String>>,
+}
+
+/// Start listening on 127.0.0.1:`port`; captures arrive on the returned channel
+pub async fn start(port: u16) -> Result<mpsc::UnboundedReceiver<Capture>> {
+    // The shared secret clients send
+    let token = crate::data_dir::load_or_create_secret("capture-token", 16)?;
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    log::info!("Capture endpoint listening on 127.0.0.1:{}", port);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            let (tx, token) = (tx.clone(), token.clone());
+            tokio::spawn(async move {
+                let handled = tokio::time::timeout(
+                    Duration::from_millis(capture::TIMEOUT_MS),
+                    handle_connection(stream, &token, &tx),
+                );
+                if let Ok(Err(e)) = handled.await {
+                    log::warn!("Capture connection failed: {}", e);
+                }
+            });
+        }
+    });
+    Ok(rx)
+}
+
+
+async fn handle_connection(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    token: &str,
+    captures: &mpsc::UnboundedSender<Capture>,
+) -> Result<()> {
+    let (status, body) = match read_request(&mut stream, token).await {
+        Ok(Some(request)) => {
+            let (reply, response) = oneshot::channel();
+            captures.send(Capture { request, reply }).map_err(|_| anyhow::anyhow!("Editor has stopped"))?;
+            match response.await {
+                Ok(Ok(id)) => ("201 Created", serde_json::json!({ "id": id })),
+                Ok(Err(e)) => ("500 Internal Server Error", serde_json::json!({ "error": e })),
+                Err(_) => ("503 Service Unavailable", serde_json::json!({ "error": "editor has stopped" })),
+            }
+        }
+        // CORS preflight from a browser extension
+        Ok(None) => ("204 No Content", serde_json::Value::Null),
+        Err((status, message)) => (status, serde_json::json!({ "error": message })),
+    };
+
+    let body = if body.is_null() { String::new() } else { body.to_string() };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Read and check one request; Ok(None) for a preflight, Err(status, message) to refuse it
+async fn read_request(stream: &mut (impl AsyncRead + Unpin), token: &str) -> Result<Option<CaptureRequest>, (&'static str, String)> {
+    let bad_request = |message: &str| ("400 Bad Request", message.to_string());
+
+    // Headers, plus whatever part of the body came with them
+    let mut buf = Vec::new();
+    let header_end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if buf.len() > capture::MAX_HEADER_BYTES {
+            return Err(("431 Request Header Fields Too Large", "headers too large".to_string()));
+        }
+        let mut chunk = [0u8; 4096];
+        let n = stream.read(&mut chunk).await.map_err(|e| bad_request(&e.to_string()))?;
+        if n == 0 {
+            return Err(bad_request("incomplete request"));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let (method, path) = (request_line.next().unwrap_or(""), request_line.next().unwrap_or(""));
+    let header = |name: &str| {
+        head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.trim().eq_ignore_ascii_case(name).then(|| value.trim().to_string())
+        })
+    };
+
+    if path.split('?').next() != Some("/capture") {
+        return Err(("404 Not Found", "only /capture is served".to_string()));
+    }
+    match method {
+        "OPTIONS" => return Ok(None),
+        "POST" => {}
+        _ => return Err(("405 Method Not Allowed", "use POST".to_string())),
+    }
+
+    let sent = header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer ").map(str::to_string))
+        .or_else(|| header("X-Chonk-Token"))
+        .unwrap_or_default();
+    if !tokens_match(sent.as_bytes(), token.as_bytes()) {
+        return Err(("401 Unauthorized", "missing or wrong token".to_string()));
+    }
+
+    let length: usize = header("Content-Length")
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| ("411 Length Required", "Content-Length is required".to_string()))?;
+    if length > capture::MAX_BODY_BYTES {
+        return Err(("413 Payload Too Large", format!("body is limited to {} bytes", capture::MAX_BODY_BYTES)));
+    }
+    let mut body = buf[header_end..].to_vec();
+    body.resize(length, 0);
+    let already = (buf.len() - header_end).min(length);
+    stream.read_exact(&mut body[already..]).await.map_err(|e| bad_request(&e.to_string()))?;
+
+    serde_json::from_slice(&body).map(Some).map_err(|e| bad_request(&format!("invalid JSON: {}", e)))
+}
+
+/// Compare without stopping at the first difference, so timing doesn't reveal the token
+pub fn tokens_match(sent: &[u8], expected: &[u8]) -> bool {
+    sent.len() == expected.len() && sent.iter().zip(expected).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn exchange(request: &str, reply_with: Result<String, String>) -> (String, Option<CaptureRequest>) {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = tokio::spawn(async move { handle_connection(server, "secret", &tx).await });
+        let (mut read_half, mut write_half) = tokio::io::split(client);
+        write_half.write_all(request.as_bytes()).await.unwrap();
+
+        let received = tokio::select! {
+            capture = rx.recv() => capture.map(|capture| {
+                let _ = capture.reply.send(reply_with);
+                capture.request
+            }),
+            _ = tokio::time::sleep(Duration::from_millis(500)) => None,
+        };
+        let mut response = String::new();
+        read_half.read_to_string(&mut response).await.unwrap();
+        server.await.unwrap().unwrap();
+        (response, received)
+    }
+
+    #[tokio::test]
+    async fn test_capture_requests() {
+        let body = r#"{"title": "Clip", "content": "Quoted text", "url": "https://example.com"}"#;
+        let request = format!(
+            "POST /capture HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+        let (response, received) = exchange(&request, Ok("abc123".to_string())).await;
+        assert!(response.starts_with("HTTP/1.1 201"));
+        assert!(response.ends_with(r#"{"id":"abc123"}"#));
+        let received = received.unwrap();
+        assert_eq!((received.title.as_str(), received.url.as_deref()), ("Clip", Some("https://example.com")));
+
+        // Wrong token: refused before reaching the editor
+        let request = request.replace("Bearer secret", "Bearer guess!");
+        let (response, received) = exchange(&request, Ok(String::new())).await;
+        assert!(response.starts_with("HTTP/1.1 401"));
+        assert!(received.is_none());
+    }
+}
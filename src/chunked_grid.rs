@@ -2,46 +2,150 @@
 // Each chunk is 32x32 characters, loaded/unloaded on demand
 // This allows clicking/typing ANYWHERE in a truly infinite space
 
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+pub const CHUNK_SIZE: usize = 32;
+
+/// Rough heap cost of one cell in a live chunk (BTreeMap entry plus node overhead)
+const LIVE_CELL_BYTES: usize = 40;
+/// Heap cost of one cell in a packed chunk
+const PACKED_CELL_BYTES: usize = std::mem::size_of::<(u8, u8, char)>();
+
+/// How a cell is drawn beyond its character: colors (None: the editor's own), bold and
+/// underline. Styles are saved with the note, copied and pasted with the text and moved
+/// along with it by edits; blank cells have none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Style {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<(u8, u8, u8)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<(u8, u8, u8)>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub underline: bool,
+}
 
-const CHUNK_SIZE: usize = 32;
+impl Style {
+    pub fn is_plain(&self) -> bool {
+        *self == Style::default()
+    }
+}
 
 /// A single chunk - 32x32 block of characters
 #[derive(Debug, Clone)]
 struct Chunk {
     // Sparse storage within the chunk
     cells: BTreeMap<(usize, usize), char>,
+    // Compact sorted copy for chunks far from the view; while packed, `cells` is empty.
+    // Reads work on either form, writes unpack first.
+    packed: Option<Box<[(u8, u8, char)]>>,
+    // Styles of the cells that have one (never packed; few cells are styled)
+    styles: BTreeMap<(usize, usize), Style>,
 }
 
 impl Chunk {
     fn new() -> Self {
         Self {
             cells: BTreeMap::new(),
+            packed: None,
+            styles: BTreeMap::new(),
         }
     }
 
-    fn set(&mut self, local_row: usize, local_col: usize, ch: char) {
+    fn set(&mut self, local_row: usize, local_col: usize, ch: char, style: Style) {
+        self.unpack();
         if ch == ' ' || ch == '\n' || ch == '\r' {
             self.cells.remove(&(local_row, local_col));
+            self.styles.remove(&(local_row, local_col));
         } else {
             self.cells.insert((local_row, local_col), ch);
+            if style.is_plain() {
+                self.styles.remove(&(local_row, local_col));
+            } else {
+                self.styles.insert((local_row, local_col), style);
+            }
         }
     }
 
+    fn style(&self, local_row: usize, local_col: usize) -> Style {
+        self.styles.get(&(local_row, local_col)).copied().unwrap_or_default()
+    }
+
     fn get(&self, local_row: usize, local_col: usize) -> char {
-        self.cells.get(&(local_row, local_col)).copied().unwrap_or(' ')
+        match &self.packed {
+            Some(packed) => packed
+                .binary_search_by_key(&(local_row as u8, local_col as u8), |&(r, c, _)| (r, c))
+                .map(|i| packed[i].2)
+                .unwrap_or(' '),
+            None => self.cells.get(&(local_row, local_col)).copied().unwrap_or(' '),
+        }
     }
 
     fn is_empty(&self) -> bool {
-        self.cells.is_empty()
+        self.cells.is_empty() && self.packed.is_none()
+    }
+
+    fn len(&self) -> usize {
+        self.cells.len() + self.packed.as_ref().map_or(0, |p| p.len())
+    }
+
+    fn is_packed(&self) -> bool {
+        self.packed.is_some()
+    }
+
+    /// Estimated heap bytes held by this chunk's cells
+    fn approx_bytes(&self) -> usize {
+        (self.cells.len() + self.styles.len()) * LIVE_CELL_BYTES + self.packed.as_ref().map_or(0, |p| p.len() * PACKED_CELL_BYTES)
     }
 
     /// Get all non-empty cells in this chunk
     fn cells(&self) -> impl Iterator<Item = ((usize, usize), char)> + '_ {
-        self.cells.iter().map(|((r, c), ch)| ((*r, *c), *ch))
+        let packed = self.packed.as_deref().unwrap_or_default();
+        self.cells
+            .iter()
+            .map(|((r, c), ch)| ((*r, *c), *ch))
+            .chain(packed.iter().map(|&(r, c, ch)| ((r as usize, c as usize), ch)))
+    }
+
+    /// Non-empty cells on one local row, in column order
+    fn row_cells(&self, local_row: usize) -> impl Iterator<Item = (usize, char)> + '_ {
+        let packed = self.packed.as_deref().unwrap_or_default();
+        let row = local_row as u8;
+        let first = packed.partition_point(|&(r, _, _)| r < row);
+        let last = packed.partition_point(|&(r, _, _)| r <= row);
+        self.cells
+            .range((local_row, 0)..(local_row, CHUNK_SIZE))
+            .map(|(&(_, c), &ch)| (c, ch))
+            .chain(packed[first..last].iter().map(|&(_, c, ch)| (c as usize, ch)))
+    }
+
+    /// Move the cells into the compact form
+    fn pack(&mut self) {
+        if self.packed.is_none() {
+            let cells = std::mem::take(&mut self.cells);
+            self.packed = Some(cells.into_iter().map(|((r, c), ch)| (r as u8, c as u8, ch)).collect());
+        }
+    }
+
+    fn unpack(&mut self) {
+        if let Some(packed) = self.packed.take() {
+            self.cells = packed.iter().map(|&(r, c, ch)| ((r as usize, c as usize), ch)).collect();
+        }
     }
 }
 
+/// Approximate memory held by a grid's cells (shown in the Note info popup)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub chunks: usize,
+    pub packed_chunks: usize,
+    pub cells: usize,
+    pub bytes: usize,
+}
+
 /// Block selection - rectangular region
 #[derive(Debug, Clone)]
 pub struct BlockSelection {
@@ -112,6 +216,11 @@ pub struct ChunkedGrid {
 
     // Block selection (Excel-style rectangular selection)
     pub selection: Option<BlockSelection>,
+
+    // Storage position of the logical origin (row 1, col 1 in the status line).
+    // Growing the canvas up/left shifts every chunk down/right and moves this with them,
+    // so positions stay usize while content can sit "above" and "left of" the origin.
+    pub origin: (usize, usize),
 }
 
 impl ChunkedGrid {
@@ -121,6 +230,7 @@ impl ChunkedGrid {
             min_chunk: (0, 0),
             max_chunk: (0, 0),
             selection: None,
+            origin: (0, 0),
         }
     }
 
@@ -140,13 +250,18 @@ impl ChunkedGrid {
         (row, col)
     }
 
-    /// Set character at ANY position - auto-creates chunks
+    /// Set character at ANY position - auto-creates chunks. The cell loses any style it had.
     pub fn set(&mut self, row: usize, col: usize, ch: char) {
+        self.set_styled(row, col, ch, Style::default());
+    }
+
+    /// Set a character drawn in `style` (blank cells never keep a style)
+    pub fn set_styled(&mut self, row: usize, col: usize, ch: char, style: Style) {
         let (chunk_pos, local_pos) = Self::pos_to_chunk(row, col);
 
         // Get or create chunk
         let chunk = self.chunks.entry(chunk_pos).or_insert_with(Chunk::new);
-        chunk.set(local_pos.0, local_pos.1, ch);
+        chunk.set(local_pos.0, local_pos.1, ch, style);
 
         // If chunk is now empty, remove it
         if chunk.is_empty() {
@@ -170,6 +285,32 @@ impl ChunkedGrid {
             .unwrap_or(' ')
     }
 
+    /// Style of the cell at a position (plain for blank cells)
+    pub fn style(&self, row: usize, col: usize) -> Style {
+        let (chunk_pos, local_pos) = Self::pos_to_chunk(row, col);
+        self.chunks.get(&chunk_pos).map(|chunk| chunk.style(local_pos.0, local_pos.1)).unwrap_or_default()
+    }
+
+    /// Restyle the character at a position, keeping it (blank cells stay plain)
+    pub fn set_style(&mut self, row: usize, col: usize, style: Style) {
+        let ch = self.get(row, col);
+        if ch != ' ' {
+            self.set_styled(row, col, ch, style);
+        }
+    }
+
+    /// Whether any cell has a style
+    pub fn has_styles(&self) -> bool {
+        self.chunks.values().any(|chunk| !chunk.styles.is_empty())
+    }
+
+    /// Positions and styles of all styled cells, chunk by chunk
+    pub fn styled_cells(&self) -> impl Iterator<Item = ((usize, usize), Style)> + '_ {
+        self.chunks.iter().flat_map(|(&chunk_pos, chunk)| {
+            chunk.styles.iter().map(move |(&local, &style)| (Self::chunk_to_pos(chunk_pos, local), style))
+        })
+    }
+
     /// Get the bounds (min_col, max_col) of a specific row - O(1) chunk lookup
     pub fn get_line_bounds(&self, row: usize) -> Option<(usize, usize)> {
         let start_chunk_row = (row / CHUNK_SIZE) as i32;
@@ -204,6 +345,23 @@ impl ChunkedGrid {
             .unwrap_or(0)
     }
 
+    /// Like get_line_length, but also ignoring stored whitespace (tabs, no-break spaces, ...)
+    pub fn get_text_length(&self, row: usize) -> usize {
+        let mut len = self.get_line_length(row);
+        while len > 0 && self.get(row, len - 1).is_whitespace() {
+            len -= 1;
+        }
+        len
+    }
+
+    /// Cells holding whitespace at the end of their line, rightmost first on each row
+    pub fn trailing_whitespace(&self) -> Vec<(usize, usize)> {
+        let rows: BTreeSet<usize> = self.cell_positions().map(|(row, _)| row).collect();
+        rows.into_iter()
+            .flat_map(|row| (self.get_text_length(row)..self.get_line_length(row)).rev().map(move |col| (row, col)))
+            .collect()
+    }
+
     /// Insert string at position
     pub fn insert_at(&mut self, row: usize, col: usize, text: &str) {
         for (i, ch) in text.chars().enumerate() {
@@ -232,8 +390,8 @@ impl ChunkedGrid {
 
         // Shift characters from right to left to avoid overwrites
         for col in (start_col..line_len).rev() {
-            let ch = self.get(row, col);
-            self.set(row, col + count, ch);
+            let (ch, style) = (self.get(row, col), self.style(row, col));
+            self.set_styled(row, col + count, ch, style);
         }
 
         // Clear the gap
@@ -256,14 +414,88 @@ impl ChunkedGrid {
         // Shift characters from left to right
         for col in start_col..line_len {
             if col + count < line_len {
-                let ch = self.get(row, col + count);
-                self.set(row, col, ch);
+                let (ch, style) = (self.get(row, col + count), self.style(row, col + count));
+                self.set_styled(row, col, ch, style);
             } else {
                 self.set(row, col, ' ');
             }
         }
     }
 
+    /// Move the text of consecutive rows starting at `first_row` sideways from `col`: each row by
+    /// its amount, right (positive) or left (negative) over blank cells (indenting a block)
+    pub fn shift_rows(&mut self, first_row: usize, col: usize, amounts: &[isize]) {
+        for (i, &amount) in amounts.iter().enumerate() {
+            if amount > 0 {
+                self.shift_right(first_row + i, col, amount as usize);
+            } else {
+                self.shift_left(first_row + i, col, amount.unsigned_abs());
+            }
+        }
+    }
+
+    /// Blank cells (up to `max`) starting at `col` that have text after them on the row
+    pub fn blank_run(&self, row: usize, col: usize, max: usize) -> usize {
+        let line_len = self.get_line_length(row);
+        (col..(col + max).min(line_len)).take_while(|&c| self.get(row, c) == ' ').count()
+    }
+
+    /// Move every row at or below `row` down by one, leaving `row` empty
+    pub fn insert_row(&mut self, row: usize) {
+        self.move_rows(row, 1);
+    }
+
+    /// Clear `row` and move every row below it up by one
+    pub fn remove_row(&mut self, row: usize) {
+        self.clear_line_from(row, 0);
+        self.move_rows(row + 1, -1);
+    }
+
+    /// Clear `row` from `start_col` to the end of the line
+    pub fn clear_line_from(&mut self, row: usize, start_col: usize) {
+        for col in start_col..self.get_line_length(row) {
+            self.set(row, col, ' ');
+        }
+    }
+
+    /// Move all cells on rows >= `from` by `by` rows
+    fn move_rows(&mut self, from: usize, by: isize) {
+        let moved: Vec<((usize, usize), char, Style)> = self
+            .cell_positions()
+            .filter(|&(row, _)| row >= from)
+            .map(|(row, col)| ((row, col), self.get(row, col), self.style(row, col)))
+            .collect();
+        for &((row, col), _, _) in &moved {
+            self.set(row, col, ' ');
+        }
+        for ((row, col), ch, style) in moved {
+            self.set_styled(row.saturating_add_signed(by), col, ch, style);
+        }
+    }
+
+    /// Move rows `first..=last` up (or down) by one; the row they pass goes to the other end.
+    /// Moving up needs `first > 0`.
+    pub fn move_row_range(&mut self, first: usize, last: usize, up: bool) {
+        let (top, bottom) = if up { (first - 1, last) } else { (first, last + 1) };
+        let moved: Vec<((usize, usize), char, Style)> = self
+            .cell_positions()
+            .filter(|&(row, _)| (top..=bottom).contains(&row))
+            .map(|(row, col)| ((row, col), self.get(row, col), self.style(row, col)))
+            .collect();
+        for &((row, col), _, _) in &moved {
+            self.set(row, col, ' ');
+        }
+        for ((row, col), ch, style) in moved {
+            let row = match (up, row) {
+                (true, row) if row == top => bottom,
+                (true, row) => row - 1,
+                (false, row) if row == bottom => top,
+                (false, row) => row + 1,
+            };
+            self.set_styled(row, col, ch, style);
+        }
+    }
+
     /// Delete range of characters on a line
     pub fn delete_range(&mut self, row: usize, start_col: usize, end_col: usize) {
         if start_col >= end_col {
@@ -274,6 +506,35 @@ impl ChunkedGrid {
         self.shift_left(row, start_col, count);
     }
 
+    /// Move all content (and the origin and selection) by whole chunks.
+    /// Positive values make room above/left; negative values undo that and must not
+    /// push content below position 0.
+    pub fn shift_chunks(&mut self, chunk_rows: i32, chunk_cols: i32) {
+        let rows = chunk_rows as isize * CHUNK_SIZE as isize;
+        let cols = chunk_cols as isize * CHUNK_SIZE as isize;
+        let shift = |pos: usize, by: isize| pos.saturating_add_signed(by);
+
+        self.chunks = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .map(|((r, c), chunk)| ((r + chunk_rows, c + chunk_cols), chunk))
+            .collect();
+        self.min_chunk = (self.min_chunk.0 + chunk_rows, self.min_chunk.1 + chunk_cols);
+        self.max_chunk = (self.max_chunk.0 + chunk_rows, self.max_chunk.1 + chunk_cols);
+        self.origin = (shift(self.origin.0, rows), shift(self.origin.1, cols));
+
+        if let Some(sel) = self.selection.as_mut() {
+            sel.start_row = shift(sel.start_row, rows);
+            sel.end_row = shift(sel.end_row, rows);
+            sel.start_col = shift(sel.start_col, cols);
+            sel.end_col = shift(sel.end_col, cols);
+        }
+    }
+
+    /// Signed (row, col) of a storage position relative to the origin
+    pub fn logical_pos(&self, row: usize, col: usize) -> (i64, i64) {
+        (row as i64 - self.origin.0 as i64, col as i64 - self.origin.1 as i64)
+    }
+
     /// Get all chunks that intersect with a viewport
     pub fn get_visible_chunks(&self, viewport: Viewport) -> Vec<(i32, i32)> {
         let (start_chunk, _) = Self::pos_to_chunk(viewport.start_row, viewport.start_col);
@@ -291,14 +552,73 @@ impl ChunkedGrid {
         visible
     }
 
+    /// Non-empty cells inside the viewport (inclusive bounds), visiting only the occupied
+    /// chunks that intersect it instead of probing every position
+    pub fn visible_cells(&self, viewport: Viewport) -> impl Iterator<Item = ((usize, usize), char)> + '_ {
+        self.get_visible_chunks(viewport)
+            .into_iter()
+            .flat_map(move |chunk_pos| {
+                self.chunks[&chunk_pos]
+                    .cells()
+                    .map(move |(local, ch)| (Self::chunk_to_pos(chunk_pos, local), ch))
+            })
+            .filter(move |&((row, col), _)| {
+                (viewport.start_row..=viewport.end_row).contains(&row)
+                    && (viewport.start_col..=viewport.end_col).contains(&col)
+            })
+    }
+
+    /// Styled cells inside the viewport (inclusive bounds), like visible_cells
+    pub fn visible_styles(&self, viewport: Viewport) -> impl Iterator<Item = ((usize, usize), Style)> + '_ {
+        self.get_visible_chunks(viewport)
+            .into_iter()
+            .flat_map(move |chunk_pos| {
+                self.chunks[&chunk_pos].styles.iter().map(move |(&local, &style)| (Self::chunk_to_pos(chunk_pos, local), style))
+            })
+            .filter(move |&((row, col), _)| {
+                (viewport.start_row..=viewport.end_row).contains(&row)
+                    && (viewport.start_col..=viewport.end_col).contains(&col)
+            })
+    }
+
+    /// Contiguous runs of non-space characters on `row` within `cols`, as (start_col, text).
+    /// Only chunks on this row that overlap the range are visited, using ordered range
+    /// lookups instead of one get() per cell.
+    pub fn line_runs(&self, row: usize, cols: Range<usize>) -> Vec<(usize, String)> {
+        let mut runs: Vec<(usize, String)> = Vec::new();
+        if cols.is_empty() {
+            return runs;
+        }
+        let ((chunk_row, first_chunk_col), (local_row, _)) = Self::pos_to_chunk(row, cols.start);
+        let ((_, last_chunk_col), _) = Self::pos_to_chunk(row, cols.end - 1);
+
+        let mut next_col = usize::MAX;
+        for (&chunk_pos, chunk) in self.chunks.range((chunk_row, first_chunk_col)..=(chunk_row, last_chunk_col)) {
+            for (local_col, ch) in chunk.row_cells(local_row) {
+                let (_, col) = Self::chunk_to_pos(chunk_pos, (local_row, local_col));
+                if !cols.contains(&col) {
+                    continue;
+                }
+                match runs.last_mut() {
+                    Some((_, text)) if col == next_col => text.push(ch),
+                    _ => runs.push((col, ch.to_string())),
+                }
+                next_col = col + 1;
+            }
+        }
+        runs
+    }
+
     /// Get content for a specific line (for rendering)
     pub fn get_line(&self, row: usize, start_col: usize, end_col: usize) -> String {
         let mut line = String::new();
-        for col in start_col..=end_col {
-            line.push(self.get(row, col));
+        let mut col = start_col;
+        for (run_col, text) in self.line_runs(row, start_col..end_col + 1) {
+            line.extend(std::iter::repeat_n(' ', run_col - col));
+            line.push_str(&text);
+            col = run_col + text.chars().count();
         }
-        // Trim trailing spaces
-        line.trim_end().to_string()
+        line
     }
 
     /// Get bounds of actual content
@@ -325,9 +645,67 @@ impl ChunkedGrid {
         Some((min_row, min_col, max_row, max_col))
     }
 
+    /// Positions of all non-empty cells, chunk by chunk
+    pub fn cell_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|(&chunk_pos, chunk)| chunk.cells().map(move |(local, _)| Self::chunk_to_pos(chunk_pos, local)))
+    }
+
     /// Count total non-empty cells
     pub fn cell_count(&self) -> usize {
-        self.chunks.values().map(|chunk| chunk.cells.len()).sum()
+        self.chunks.values().map(|chunk| chunk.len()).sum()
+    }
+
+    /// Chunk, cell and estimated byte counts (packed chunks count at their compact size)
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage {
+            chunks: self.chunks.len(),
+            bytes: self.chunks.len() * std::mem::size_of::<Chunk>(),
+            ..MemoryUsage::default()
+        };
+        for chunk in self.chunks.values() {
+            usage.cells += chunk.len();
+            usage.bytes += chunk.approx_bytes();
+            usage.packed_chunks += chunk.is_packed() as usize;
+        }
+        usage
+    }
+
+    /// Pack chunks outside `keep`, farthest first, until the estimated memory is at most
+    /// `cap_bytes` (or nothing else can be packed). Packed chunks are unpacked again the
+    /// next time they are written to. Returns how many chunks were packed.
+    pub fn enforce_memory_cap(&mut self, cap_bytes: usize, keep: Viewport) -> usize {
+        let mut bytes = self.memory_usage().bytes;
+        if bytes <= cap_bytes {
+            return 0;
+        }
+
+        let (near, _) = Self::pos_to_chunk(keep.start_row, keep.start_col);
+        let (far, _) = Self::pos_to_chunk(keep.end_row, keep.end_col);
+        let distance = |(row, col): (i32, i32)| {
+            let dr = (near.0 - row).max(row - far.0).max(0);
+            let dc = (near.1 - col).max(col - far.1).max(0);
+            dr.max(dc)
+        };
+        let mut candidates: Vec<(i32, (i32, i32))> = self.chunks.iter()
+            .filter(|(&pos, chunk)| !chunk.is_packed() && distance(pos) > 0)
+            .map(|(&pos, _)| (distance(pos), pos))
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut packed = 0;
+        for (_, pos) in candidates {
+            if bytes <= cap_bytes {
+                break;
+            }
+            let chunk = self.chunks.get_mut(&pos).expect("candidate chunk exists");
+            let before = chunk.approx_bytes();
+            chunk.pack();
+            bytes -= before - chunk.approx_bytes();
+            packed += 1;
+        }
+        packed
     }
 
     /// Count loaded chunks
@@ -370,6 +748,7 @@ impl ChunkedGrid {
         self.chunks.clear();
         self.min_chunk = (0, 0);
         self.max_chunk = (0, 0);
+        self.origin = (0, 0);
     }
 
     /// Convert to string representation
@@ -417,6 +796,12 @@ impl ChunkedGrid {
         Some(lines)
     }
 
+    /// Styles of the selected block's cells, row by row like copy_block
+    pub fn copy_block_styles(&self) -> Option<Vec<Vec<Style>>> {
+        let (min_row, min_col, max_row, max_col) = self.selection.as_ref()?.bounds();
+        Some((min_row..=max_row).map(|row| (min_col..=max_col).map(|col| self.style(row, col)).collect()).collect())
+    }
+
     /// Cut selected block (copy and clear the region)
     pub fn cut_block(&mut self) -> Option<Vec<String>> {
         let copied = self.copy_block();
@@ -457,6 +842,7 @@ pub struct Viewport {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_chunk_positioning() {
@@ -528,6 +914,124 @@ mod tests {
         assert!(visible.len() < grid.chunk_count());
     }
 
+    #[test]
+    fn test_memory_cap_packs_far_chunks() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 0, "near");
+        grid.insert_at(1000, 1000, "far away");
+        let view = Viewport { start_row: 0, start_col: 0, end_row: 40, end_col: 80 };
+
+        let before = grid.memory_usage();
+        assert_eq!(grid.enforce_memory_cap(0, view), 1);
+        let after = grid.memory_usage();
+        assert_eq!(after.packed_chunks, 1);
+        assert_eq!(after.cells, before.cells);
+        assert!(after.bytes < before.bytes);
+
+        // Packed chunks still read, and unpack on write
+        assert_eq!(grid.line_runs(1000, 990..1010), vec![(1000, "far".to_string()), (1004, "away".to_string())]);
+        assert_eq!(grid.get(1000, 1003), ' ');
+        grid.set(1000, 1003, 'X');
+        assert_eq!(grid.get_line(1000, 1000, 1007), "farXaway");
+        assert_eq!(grid.memory_usage().packed_chunks, 0);
+    }
+
+    #[test]
+    fn test_line_runs() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(3, 30, "ab");
+        grid.insert_at(3, 32, "cd"); // Continues across the chunk boundary
+        grid.insert_at(3, 40, "x");
+        grid.set(4, 31, 'z');
+
+        assert_eq!(grid.line_runs(3, 0..100), vec![(30, "abcd".to_string()), (40, "x".to_string())]);
+        assert_eq!(grid.line_runs(3, 31..40), vec![(31, "bcd".to_string())]);
+        assert_eq!(grid.get_line(3, 28, 41), "  abcd      x");
+    }
+
+    #[test]
+    fn test_visible_cells() {
+        let mut grid = ChunkedGrid::new();
+        grid.set(5, 5, 'A');
+        grid.set(40, 10, 'B'); // Same chunk column, but below the viewport
+        grid.set(500, 500, 'C');
+
+        let viewport = Viewport { start_row: 0, start_col: 0, end_row: 35, end_col: 79 };
+        let cells: Vec<_> = grid.visible_cells(viewport).collect();
+        assert_eq!(cells, vec![((5, 5), 'A')]);
+    }
+
+    #[test]
+    fn test_shift_chunks() {
+        let mut grid = ChunkedGrid::new();
+        grid.set(0, 0, 'A');
+        grid.start_selection(0, 0);
+
+        grid.shift_chunks(1, 2);
+        assert_eq!(grid.get(32, 64), 'A');
+        assert_eq!(grid.origin, (32, 64));
+        assert_eq!(grid.logical_pos(0, 0), (-32, -64));
+        assert!(grid.selection.as_ref().unwrap().contains(32, 64));
+
+        grid.shift_chunks(-1, -2);
+        assert_eq!(grid.get(0, 0), 'A');
+        assert_eq!(grid.origin, (0, 0));
+    }
+
+    #[test]
+    fn test_trailing_whitespace() {
+        let mut grid = ChunkedGrid::from_string("a\t\u{a0}\nb c\n\t");
+        assert_eq!((grid.get_line_length(0), grid.get_text_length(0)), (3, 1));
+        assert_eq!(grid.trailing_whitespace(), vec![(0, 2), (0, 1), (2, 0)]);
+        for (row, col) in grid.trailing_whitespace() {
+            grid.delete_at(row, col);
+        }
+        assert_eq!(grid.to_lines(), vec!["a", "b c"]);
+    }
+
+    #[test]
+    fn test_shift_rows() {
+        let mut grid = ChunkedGrid::from_string("ab  cd\nef gh\n\nij");
+        grid.shift_rows(0, 2, &[2, 2, 2, 2]);
+        assert_eq!(grid.to_lines(), vec!["ab    cd", "ef   gh", "", "ij"]);
+        let amounts: Vec<isize> = (0..4).map(|row| -(grid.blank_run(row, 2, 3) as isize)).collect();
+        assert_eq!(amounts, vec![-3, -3, 0, 0]);
+        grid.shift_rows(0, 2, &amounts);
+        assert_eq!(grid.to_lines(), vec!["ab cd", "efgh", "", "ij"]);
+    }
+
+    #[test]
+    fn test_move_row_range() {
+        let mut grid = ChunkedGrid::from_string("a\nb\nc\nd");
+        grid.move_row_range(1, 2, true);
+        assert_eq!(grid.to_lines(), vec!["b", "c", "a", "d"]);
+        grid.move_row_range(0, 1, false);
+        assert_eq!(grid.to_lines(), vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_cell_styles() {
+        let bold = Style { bold: true, ..Style::default() };
+        let mut grid = ChunkedGrid::from_string("abc\nd");
+        grid.set_style(0, 1, bold);
+        grid.set_style(0, 5, bold); // Blank: stays plain
+        assert!(grid.has_styles());
+        assert_eq!(grid.styled_cells().collect::<Vec<_>>(), [((0, 1), bold)]);
+
+        // Styles move with their characters
+        grid.shift_right(0, 0, 2);
+        assert_eq!((grid.style(0, 1), grid.style(0, 3)), (Style::default(), bold));
+        grid.shift_left(0, 0, 1);
+        grid.move_row_range(0, 0, false);
+        assert_eq!(grid.style(1, 2), bold);
+        grid.selection = Some(BlockSelection { start_row: 1, start_col: 1, end_row: 1, end_col: 2 });
+        assert_eq!(grid.copy_block_styles(), Some(vec![vec![Style::default(), bold]]));
+
+        // Writing a character (or a blank) replaces the style
+        grid.set(1, 2, 'x');
+        assert!(!grid.has_styles());
+    }
+
     #[test]
     fn test_export_import() {
         let mut grid = ChunkedGrid::new();
@@ -542,4 +1046,62 @@ mod tests {
         assert_eq!(grid2.get(5, 10), 'H');
         assert_eq!(grid2.get(10, 20), 'W');
     }
+
+    /// Every non-empty cell, in position order
+    fn snapshot(grid: &ChunkedGrid) -> Vec<((usize, usize), char)> {
+        let mut cells: Vec<_> = grid.cell_positions().map(|(r, c)| ((r, c), grid.get(r, c))).collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    fn grid_strategy() -> impl Strategy<Value = ChunkedGrid> {
+        prop::collection::vec((0..100usize, 0..100usize, proptest::char::range('!', '~')), 0..120).prop_map(|cells| {
+            let mut grid = ChunkedGrid::new();
+            for (row, col, ch) in cells {
+                grid.set(row, col, ch);
+            }
+            grid
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_lines_round_trip(grid in grid_strategy()) {
+            // to_lines() starts at the content bounds, so compare relative to them
+            let (min_row, min_col) = grid.bounds().map_or((0, 0), |(r, c, _, _)| (r, c));
+            let expected: Vec<_> = snapshot(&grid)
+                .into_iter()
+                .map(|((r, c), ch)| ((r - min_row, c - min_col), ch))
+                .collect();
+            prop_assert_eq!(snapshot(&ChunkedGrid::from_lines(&grid.to_lines())), expected);
+        }
+
+        #[test]
+        fn prop_copy_cut_paste_match_selection(
+            grid in grid_strategy(),
+            start in (0..100usize, 0..100usize),
+            end in (0..100usize, 0..100usize),
+        ) {
+            let mut grid = grid;
+            let original = snapshot(&grid);
+            grid.start_selection(start.0, start.1);
+            grid.update_selection(end.0, end.1);
+            let (min_row, min_col, max_row, max_col) = grid.selection.as_ref().unwrap().bounds();
+            let inside = |(r, c): (usize, usize)| (min_row..=max_row).contains(&r) && (min_col..=max_col).contains(&c);
+
+            // Copy is exactly the selected rectangle
+            let copied = grid.copy_block().unwrap();
+            prop_assert_eq!(copied.len(), max_row - min_row + 1);
+            prop_assert!(copied.iter().all(|line| line.chars().count() == max_col - min_col + 1));
+
+            // Cut returns the same block and clears only the rectangle
+            prop_assert_eq!(grid.cut_block(), Some(copied.clone()));
+            let outside: Vec<_> = original.iter().filter(|(pos, _)| !inside(*pos)).cloned().collect();
+            prop_assert_eq!(snapshot(&grid), outside);
+
+            // Pasting it back restores the grid
+            grid.paste_block(&copied, min_row, min_col);
+            prop_assert_eq!(snapshot(&grid), original);
+        }
+    }
 }
@@ -0,0 +1,149 @@
+// Command-line subcommands
+// `chonk-note` with no arguments opens the editor. Anything else is a subcommand that works
+// on the notes database directly and exits.
+use crate::archive::{self, ConflictPolicy};
+use crate::chunked_grid::ChunkedGrid;
+use crate::notes_database::NotesDatabase;
+use anyhow::{Context, Result};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const USAGE: &str = "Usage:
+  chonk-note                 Open the editor
+  chonk-note open <title>    Open the editor at the note with that title. With an editor
+                             already running, either opens the note in that one instead
+  chonk-note cat [--pager] <title>
+                             Print a note (the most recently edited one with that title)
+                             to stdout, through $PAGER with --pager
+  chonk-note import <path>   Import a text file, an Evernote .enex export, or an Apple Notes
+                             HTML export (a single .html file or a folder of them)
+  chonk-note export-archive [file.tar.gz]
+                             Write every note and attachment to an archive
+  chonk-note import-archive <file.tar.gz> [--on-conflict=newer|skip|replace|keep-both]
+                             Add the notes from an archive. Notes that exist here with
+                             different contents keep the newer version by default
+  chonk-note follow <address>
+                             Follow another editor's broadcast (Notes menu → Start
+                             broadcast there) in this terminal
+  chonk-note mcp             Relay MCP (JSON-RPC) on stdin/stdout to the running editor
+                             (needs \"automation_server\": true in settings.json)";
+
+/// Run the subcommand in `args` (program name excluded); None means start the editor
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    let (command, rest) = args.split_first()?;
+    Some(match (command.as_str(), rest) {
+        // Starts the editor, see open_title
+        ("open", title) if !title.is_empty() => return None,
+        ("cat", [option, title @ ..]) if option == "--pager" && !title.is_empty() => cat(&title.join(" "), true),
+        ("cat", title) if !title.is_empty() => cat(&title.join(" "), false),
+        ("import", [path]) => import(Path::new(path)),
+        ("export-archive", []) => export_archive(Path::new(&archive::default_file_name())),
+        ("export-archive", [path]) => export_archive(Path::new(path)),
+        ("import-archive", [path]) => import_archive(Path::new(path), ConflictPolicy::Newer),
+        ("import-archive", [path, option]) => match option.strip_prefix("--on-conflict=").and_then(ConflictPolicy::parse) {
+            Some(policy) => import_archive(Path::new(path), policy),
+            None => Err(anyhow::anyhow!("unknown option: {}\n\n{}", option, USAGE)),
+        },
+        ("follow", [url]) => crate::broadcast::follow(url),
+        ("mcp", []) => crate::automation::bridge_stdio(),
+        ("help" | "-h" | "--help", _) => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(anyhow::anyhow!("unknown arguments: {}\n\n{}", args.join(" "), USAGE)),
+    })
+}
+
+/// The note `chonk-note open <title>` asks the editor for
+pub fn open_title(args: &[String]) -> Option<String> {
+    match args.split_first() {
+        Some((command, title)) if command == "open" && !title.is_empty() => Some(title.join(" ")),
+        _ => None,
+    }
+}
+
+fn cat(title: &str, pager: bool) -> Result<()> {
+    let db = NotesDatabase::new()?;
+    let note = db.find_note_by_title(title)?.with_context(|| format!("no note titled \"{}\"", title))?;
+    let text = note_text(&crate::note_format::decode(&note.content));
+    if pager { page(&text) } else { print_text(&text) }
+}
+
+/// A note's canvas as plain text: its lines without trailing blanks
+pub fn note_text(grid: &ChunkedGrid) -> String {
+    let mut text: String = grid.to_lines().iter().map(|line| format!("{}\n", line.trim_end())).collect();
+    let kept = text.trim_end().len();
+    text.truncate(kept);
+    text.push('\n');
+    text
+}
+
+/// Write `text` through $PAGER when it is set and stdout is a terminal, else straight to stdout
+pub fn page(text: &str) -> Result<()> {
+    let pager = std::env::var("PAGER").ok().filter(|pager| !pager.trim().is_empty());
+    let Some(pager) = pager.filter(|_| std::io::stdout().is_terminal()) else {
+        return print_text(text);
+    };
+    // PAGER may carry arguments ("less -R")
+    let mut words = pager.split_whitespace();
+    let mut child = Command::new(words.next().unwrap_or("less"))
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Starting pager {}", pager))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may quit before reading everything; that is not an error
+        let _ = stdin.write_all(text.as_bytes());
+    }
+    child.wait()?;
+    Ok(())
+}
+
+fn print_text(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match stdout.write_all(text.as_bytes()).and_then(|()| stdout.flush()) {
+        // Piped into `head` and the like
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+fn import(path: &Path) -> Result<()> {
+    let db = NotesDatabase::new()?;
+    let notes = crate::importers::import_path(&db, path)?;
+    let attachments: usize = notes.iter().map(|note| db.count_attachments(&note.id).unwrap_or(0)).sum();
+    println!("Imported {} note(s) with {} attachment(s) from {}", notes.len(), attachments, path.display());
+    Ok(())
+}
+
+fn export_archive(path: &Path) -> Result<()> {
+    let db = NotesDatabase::new()?;
+    let file = std::fs::File::create(path).with_context(|| format!("Creating {}", path.display()))?;
+    let count = archive::export(&db, std::io::BufWriter::new(file))?;
+    println!("Exported {} note(s) to {}", count, path.display());
+    Ok(())
+}
+
+fn import_archive(path: &Path, policy: ConflictPolicy) -> Result<()> {
+    let db = NotesDatabase::new()?;
+    let file = std::fs::File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    let summary = archive::import(&db, std::io::BufReader::new(file), policy)?;
+    println!(
+        "Imported {}: {} added, {} replaced, {} added as copies, {} unchanged or kept",
+        path.display(), summary.added, summary.replaced, summary.copied, summary.skipped
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_text() {
+        let grid = ChunkedGrid::from_string("title  \n\n  body\n\n");
+        assert_eq!(note_text(&grid), "title\n\n  body\n");
+        assert_eq!(note_text(&ChunkedGrid::new()), "\n");
+    }
+}
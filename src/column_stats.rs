@@ -0,0 +1,81 @@
+// Spreadsheet-style statistics over the numbers inside a block selection
+
+/// Summary of the numbers found in a selection
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColumnStats {
+    pub count: usize,
+    pub sum: f64,
+    pub average: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ColumnStats {
+    /// Stats for every number in `lines`; None if there are none
+    pub fn from_lines(lines: &[String]) -> Option<Self> {
+        let numbers: Vec<f64> = lines.iter().flat_map(|line| parse_numbers(line)).collect();
+        if numbers.is_empty() {
+            return None;
+        }
+        let sum: f64 = numbers.iter().sum();
+        Some(Self {
+            count: numbers.len(),
+            sum,
+            average: sum / numbers.len() as f64,
+            min: numbers.iter().copied().fold(f64::INFINITY, f64::min),
+            max: numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// Numbers in a line, separated by whitespace or | ; delimiters.
+/// Accepts "1,234.50", "$12", "-3", "(4)" as -4, and "15%".
+pub fn parse_numbers(line: &str) -> Vec<f64> {
+    line.split(|c: char| c.is_whitespace() || c == '|' || c == ';')
+        .filter_map(parse_number)
+        .collect()
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    let token = token.trim_matches(|c: char| c == ',' || c == ':');
+    // Accounting style negative: (42)
+    let (negative, token) = match token.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        Some(inner) => (true, inner),
+        None => (false, token),
+    };
+    let token = token.trim_start_matches(['$', '€', '£', '¥']).trim_end_matches('%');
+    let cleaned: String = token.chars().filter(|&c| c != ',').collect();
+    if !cleaned.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let value: f64 = cleaned.parse().ok()?;
+    value.is_finite().then_some(if negative { -value } else { value })
+}
+
+/// Format for display/insertion: integers without decimals, others rounded to 4 places
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let text = format!("{:.4}", value);
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_stats() {
+        let lines: Vec<String> = ["  12  apples", "$1,000.50 |  3", "n/a", "(2.5)", "15%"]
+            .iter().map(|s| s.to_string()).collect();
+        let stats = ColumnStats::from_lines(&lines).unwrap();
+        assert_eq!(stats.count, 5);
+        assert_eq!(format_number(stats.sum), "1028");
+        assert_eq!(stats.min, -2.5);
+        assert_eq!(stats.max, 1000.5);
+        assert_eq!(format_number(stats.average), "205.6");
+        assert_eq!(ColumnStats::from_lines(&["none here".to_string()]), None);
+    }
+}
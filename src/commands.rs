@@ -0,0 +1,327 @@
+// Command registry: every action the editor offers, with its label and shortcut
+// COMMANDS lists them for the command palette (F1 / Ctrl+Shift+P) and the Notes menu;
+// BINDINGS maps keys to them for the keyboard handler. App::run_command carries them out.
+// Keys whose meaning depends on state (Ctrl+N/Ctrl+P while searching, Ctrl+Shift+D's
+// second press) stay in keyboard.rs; their commands are listed here with the usual hint.
+use crate::kitty_native::{KeyCode, KeyEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Save,
+    Quit,
+    NewNote,
+    TodaysNote,
+    StartScreen,
+    OpenNote,
+    PreviousNote,
+    NextNote,
+    DuplicateNote,
+    NoteColor,
+    NoteIcon,
+    WordGoal,
+    Favorite,
+    RenameNote,
+    ExportNote,
+    ImportNote,
+    PrintNote,
+    NoteInfo,
+    DeleteNote,
+    Scratchpad,
+    ClearScratchpad,
+    FocusTimer,
+    SyncNow,
+    Broadcast,
+    Search,
+    SaveSearch,
+    DeleteSavedSearch,
+    NextMatch,
+    PreviousMatch,
+    GoToLine,
+    FindCursor,
+    JumpBack,
+    JumpForward,
+    Undo,
+    Redo,
+    UndoHistory,
+    SelectAll,
+    SelectIsland,
+    SelectWord,
+    SelectLine,
+    SelectParagraph,
+    OpenLineBelow,
+    OpenLineAbove,
+    JoinLines,
+    OpenLink,
+    RevealPath,
+    EmbedNote,
+    PinPreview,
+    ClosePreview,
+    ExtractToNote,
+    DuplicateDown,
+    DuplicateUp,
+    MoveRowsUp,
+    MoveRowsDown,
+    StripTrailingWhitespace,
+    TidyLayout,
+    FilterSelection,
+    ColorSelection,
+    BoldSelection,
+    InsertCommandOutput,
+    SplitSelection,
+    SelectNextOccurrence,
+    SelectAllOccurrences,
+    Copy,
+    Cut,
+    Paste,
+    SelectionTotals,
+    CopyAsCsv,
+    InsertDate,
+    InsertTime,
+    InsertDateTime,
+    UnicodeInput,
+    Digraph,
+    ToggleLogNote,
+    ToggleGridLines,
+    ToggleWhitespace,
+    ToggleWrapPaste,
+    ToggleSmoothScroll,
+    ToggleOsc52,
+    ToggleAutoTitle,
+    ToggleSnapToGrid,
+    ToggleStartScreen,
+    ReloadConfig,
+    ToggleSidebar,
+    ToggleOutline,
+    ToggleBacklinks,
+    ToggleTasks,
+    ToggleSettings,
+    PerformanceHud,
+    Palette,
+}
+
+/// Every command in palette order: (command, label, shortcut hint)
+pub const COMMANDS: &[(Command, &str, &str)] = &[
+    (Command::Save, "Save note", "Ctrl+S"),
+    (Command::NewNote, "New note", "Ctrl+N"),
+    (Command::TodaysNote, "Today's note", ""),
+    (Command::StartScreen, "Start screen", ""),
+    (Command::OpenNote, "Open note...", "Ctrl+O"),
+    (Command::PreviousNote, "Previous note", "Ctrl+↑"),
+    (Command::NextNote, "Next note", "Ctrl+↓"),
+    (Command::DuplicateNote, "Duplicate", ""),
+    (Command::NoteColor, "Note color (cycle)", ""),
+    (Command::NoteIcon, "Note icon...", ""),
+    (Command::WordGoal, "Word goal...", ""),
+    (Command::Favorite, "Add to favorites", "Alt+1..9"),
+    (Command::RenameNote, "Rename", "Double-click"),
+    (Command::ExportNote, "Export to Documents", ""),
+    (Command::ImportNote, "Import from file...", ""),
+    (Command::PrintNote, "Print note to terminal and quit", ""),
+    (Command::NoteInfo, "Note info", ""),
+    (Command::Scratchpad, "Scratchpad", "F9"),
+    (Command::ClearScratchpad, "Clear scratchpad", ""),
+    (Command::FocusTimer, "Start focus timer", ""),
+    (Command::SyncNow, "Sync now", ""),
+    (Command::Broadcast, "Start broadcast", ""),
+    (Command::DeleteNote, "Delete note", "Ctrl+Shift+D"),
+    (Command::Search, "Search", "Ctrl+F"),
+    (Command::SaveSearch, "Save search to sidebar...", ""),
+    (Command::DeleteSavedSearch, "Remove saved search...", ""),
+    (Command::NextMatch, "Next search match", "Ctrl+N"),
+    (Command::PreviousMatch, "Previous search match", "Ctrl+P"),
+    (Command::GoToLine, "Go to line...", "Ctrl+L"),
+    (Command::FindCursor, "Find cursor (flash its row and column)", "Alt+C"),
+    (Command::JumpBack, "Jump back", "Alt+←"),
+    (Command::JumpForward, "Jump forward", "Alt+→"),
+    (Command::Undo, "Undo", "Ctrl+Z"),
+    (Command::Redo, "Redo", "Ctrl+Y"),
+    (Command::UndoHistory, "Undo history", "Alt+H"),
+    (Command::SelectAll, "Select all", "Ctrl+A"),
+    (Command::SelectIsland, "Select block under cursor", "Ctrl+Shift+A"),
+    (Command::SelectWord, "Select word", "Alt+Shift+W"),
+    (Command::SelectLine, "Select line", "Alt+Shift+L"),
+    (Command::SelectParagraph, "Select paragraph", "Alt+Shift+P"),
+    (Command::OpenLineBelow, "Open line below", "Ctrl+Enter"),
+    (Command::OpenLineAbove, "Open line above", "Ctrl+Shift+Enter"),
+    (Command::JoinLines, "Join lines", "Alt+J"),
+    (Command::OpenLink, "Open link, file or linked note under cursor", "Alt+O"),
+    (Command::RevealPath, "Reveal file under cursor in file manager", "Alt+R"),
+    (Command::EmbedNote, "Embed note...", ""),
+    (Command::PinPreview, "Pin note preview...", ""),
+    (Command::ClosePreview, "Close note preview", ""),
+    (Command::ExtractToNote, "Move selection to new note...", ""),
+    (Command::DuplicateDown, "Duplicate line or selection", "Alt+Shift+↓"),
+    (Command::DuplicateUp, "Duplicate line or selection (stay on original)", "Alt+Shift+↑"),
+    (Command::MoveRowsUp, "Move line up", "Alt+↑"),
+    (Command::MoveRowsDown, "Move line down", "Alt+↓"),
+    (Command::StripTrailingWhitespace, "Strip trailing whitespace", ""),
+    (Command::TidyLayout, "Tidy layout (stack scattered blocks)", ""),
+    (Command::FilterSelection, "Filter selection through command...", ""),
+    (Command::ColorSelection, "Color selection...", ""),
+    (Command::BoldSelection, "Bold selection", "Ctrl+B"),
+    (Command::InsertCommandOutput, "Insert command output...", ""),
+    (Command::SplitSelection, "Split selection into lines", "Alt+S"),
+    (Command::SelectNextOccurrence, "Select next occurrence", "Ctrl+D"),
+    (Command::SelectAllOccurrences, "Select all occurrences", "Ctrl+Shift+L"),
+    (Command::Copy, "Copy", "Ctrl+C"),
+    (Command::Cut, "Cut", "Ctrl+X"),
+    (Command::Paste, "Paste", "Ctrl+V"),
+    (Command::SelectionTotals, "Totals of selected numbers", "Ctrl+T"),
+    (Command::CopyAsCsv, "Copy selection as CSV", "Ctrl+E"),
+    (Command::InsertDate, "Insert date", "Alt+D"),
+    (Command::InsertTime, "Insert time", "Alt+T"),
+    (Command::InsertDateTime, "Insert ISO date and time", "Alt+I"),
+    (Command::UnicodeInput, "Insert Unicode character...", "Ctrl+U"),
+    (Command::Digraph, "Insert digraph...", "Ctrl+K"),
+    (Command::ToggleLogNote, "Toggle log note", "Alt+L"),
+    (Command::ToggleGridLines, "Toggle grid lines", "Ctrl+G"),
+    (Command::ToggleWhitespace, "Toggle whitespace and hidden characters", "Alt+W"),
+    (Command::ToggleWrapPaste, "Toggle soft-wrapped paste", ""),
+    (Command::ToggleSmoothScroll, "Toggle smooth scroll", ""),
+    (Command::ToggleOsc52, "Toggle OSC 52 clipboard", ""),
+    (Command::ToggleAutoTitle, "Toggle auto titles", ""),
+    (Command::ToggleSnapToGrid, "Toggle snap to grid", ""),
+    (Command::ToggleStartScreen, "Toggle start screen at launch", ""),
+    (Command::ReloadConfig, "Reload settings and plugins", ""),
+    (Command::ToggleSidebar, "Toggle notes sidebar", ""),
+    (Command::ToggleOutline, "Toggle outline panel", ""),
+    (Command::ToggleBacklinks, "Toggle backlinks panel", ""),
+    (Command::ToggleTasks, "Toggle tasks panel (TODO, FIXME, [ ])", ""),
+    (Command::ToggleSettings, "Toggle settings panel", ""),
+    (Command::PerformanceHud, "Toggle performance HUD", "F12"),
+    (Command::Quit, "Quit", "Ctrl+Q"),
+];
+
+/// A key with the modifiers it needs (Cmd never matches; Shift is optional unless listed)
+pub struct Binding {
+    pub code: KeyCode,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+const fn key(code: KeyCode) -> Binding {
+    Binding { code, ctrl: false, alt: false, shift: false }
+}
+
+const fn ctrl(c: char) -> Binding {
+    Binding { code: KeyCode::Char(c), ctrl: true, alt: false, shift: false }
+}
+
+const fn ctrl_shift(c: char) -> Binding {
+    Binding { code: KeyCode::Char(c), ctrl: true, alt: false, shift: true }
+}
+
+const fn alt(code: KeyCode) -> Binding {
+    Binding { code, ctrl: false, alt: true, shift: false }
+}
+
+const fn alt_shift(c: char) -> Binding {
+    Binding { code: KeyCode::Char(c), ctrl: false, alt: true, shift: true }
+}
+
+pub const BINDINGS: &[(Binding, Command)] = &[
+    (ctrl('s'), Command::Save),
+    (ctrl('q'), Command::Quit),
+    (ctrl('n'), Command::NewNote),
+    (ctrl('o'), Command::OpenNote),
+    (Binding { code: KeyCode::Up, ctrl: true, alt: false, shift: false }, Command::PreviousNote),
+    (Binding { code: KeyCode::Down, ctrl: true, alt: false, shift: false }, Command::NextNote),
+    (key(KeyCode::F(9)), Command::Scratchpad),
+    (ctrl('f'), Command::Search),
+    (ctrl('l'), Command::GoToLine),
+    (alt(KeyCode::Char('c')), Command::FindCursor),
+    (alt(KeyCode::Left), Command::JumpBack),
+    (alt(KeyCode::Right), Command::JumpForward),
+    (alt(KeyCode::Up), Command::MoveRowsUp),
+    (Binding { code: KeyCode::Down, ctrl: false, alt: true, shift: true }, Command::DuplicateDown),
+    (Binding { code: KeyCode::Up, ctrl: false, alt: true, shift: true }, Command::DuplicateUp),
+    (alt(KeyCode::Down), Command::MoveRowsDown),
+    (ctrl('z'), Command::Undo),
+    (ctrl('y'), Command::Redo),
+    (ctrl_shift('z'), Command::Redo),
+    (alt(KeyCode::Char('h')), Command::UndoHistory),
+    (ctrl('a'), Command::SelectAll),
+    (ctrl_shift('a'), Command::SelectIsland),
+    (alt_shift('w'), Command::SelectWord),
+    (alt_shift('l'), Command::SelectLine),
+    (alt_shift('p'), Command::SelectParagraph),
+    (alt(KeyCode::Char('s')), Command::SplitSelection),
+    (alt(KeyCode::Char('j')), Command::JoinLines),
+    (alt(KeyCode::Char('o')), Command::OpenLink),
+    (alt(KeyCode::Char('r')), Command::RevealPath),
+    (Binding { code: KeyCode::Enter, ctrl: true, alt: false, shift: false }, Command::OpenLineBelow),
+    (Binding { code: KeyCode::Enter, ctrl: true, alt: false, shift: true }, Command::OpenLineAbove),
+    (ctrl('d'), Command::SelectNextOccurrence),
+    (ctrl_shift('l'), Command::SelectAllOccurrences),
+    (ctrl('c'), Command::Copy),
+    (ctrl('x'), Command::Cut),
+    (ctrl('v'), Command::Paste),
+    (ctrl('t'), Command::SelectionTotals),
+    (ctrl('e'), Command::CopyAsCsv),
+    (ctrl('b'), Command::BoldSelection),
+    (alt(KeyCode::Char('d')), Command::InsertDate),
+    (alt(KeyCode::Char('t')), Command::InsertTime),
+    (alt(KeyCode::Char('i')), Command::InsertDateTime),
+    (ctrl('u'), Command::UnicodeInput),
+    (ctrl('k'), Command::Digraph),
+    (alt(KeyCode::Char('l')), Command::ToggleLogNote),
+    (ctrl('g'), Command::ToggleGridLines),
+    (alt(KeyCode::Char('w')), Command::ToggleWhitespace),
+    (key(KeyCode::F(12)), Command::PerformanceHud),
+    (ctrl_shift('p'), Command::Palette),
+    (key(KeyCode::F(1)), Command::Palette),
+];
+
+impl Binding {
+    fn matches(&self, key: &KeyEvent, exact_shift: bool) -> bool {
+        let code_matches = match (self.code, key.code) {
+            (KeyCode::Char(bound), KeyCode::Char(pressed)) => bound == pressed.to_ascii_lowercase(),
+            (bound, pressed) => bound == pressed,
+        };
+        let shift_matches = if exact_shift { key.modifiers.shift == self.shift } else { !self.shift };
+        code_matches && shift_matches && key.modifiers.ctrl == self.ctrl && key.modifiers.alt == self.alt && !key.modifiers.cmd
+    }
+}
+
+/// Command bound to a key; Shift+key falls back to the key's own binding if it has none
+pub fn bound_to(key: &KeyEvent) -> Option<Command> {
+    let find = |exact_shift| BINDINGS.iter().find(|(binding, _)| binding.matches(key, exact_shift)).map(|&(_, command)| command);
+    find(true).or_else(|| find(false))
+}
+
+/// (label, shortcut hint) of a command
+pub fn describe(command: Command) -> (&'static str, &'static str) {
+    COMMANDS
+        .iter()
+        .find(|(c, _, _)| *c == command)
+        .map(|&(_, label, hint)| (label, hint))
+        .unwrap_or(("", ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitty_native::KeyModifiers;
+
+    fn press(code: KeyCode, ctrl: bool, alt: bool, shift: bool) -> KeyEvent {
+        KeyEvent { code, modifiers: KeyModifiers { ctrl, alt, shift, cmd: false } }
+    }
+
+    #[test]
+    fn test_bindings() {
+        assert_eq!(bound_to(&press(KeyCode::Char('z'), true, false, false)), Some(Command::Undo));
+        assert_eq!(bound_to(&press(KeyCode::Char('Z'), true, false, true)), Some(Command::Redo));
+        // Shift doesn't matter for keys without a Shift binding
+        assert_eq!(bound_to(&press(KeyCode::Char('s'), true, false, true)), Some(Command::Save));
+        assert_eq!(bound_to(&press(KeyCode::Char('p'), true, false, true)), Some(Command::Palette));
+        assert_eq!(bound_to(&press(KeyCode::Char('p'), true, false, false)), None);
+        assert_eq!(bound_to(&press(KeyCode::Left, false, true, false)), Some(Command::JumpBack));
+        assert_eq!(bound_to(&press(KeyCode::Char('s'), false, false, false)), None);
+
+        // Everything the palette shows has a label, and every bound command is in the palette
+        for (_, command) in BINDINGS {
+            assert!(*command == Command::Palette || !describe(*command).0.is_empty(), "{:?}", command);
+        }
+    }
+}
@@ -5,6 +5,11 @@ pub mod layout {
     pub const SIDEBAR_WIDTH_EXPANDED: u16 = 30;
     pub const SIDEBAR_WIDTH_COLLAPSED: u16 = 0;
     pub const SETTINGS_PANEL_WIDTH: u16 = 35;
+    pub const OUTLINE_PANEL_WIDTH: u16 = 30;
+    pub const HISTORY_PANEL_WIDTH: u16 = 40;
+    pub const PREVIEW_PANEL_WIDTH: u16 = 50; // Pinned note preview, at most half the screen
+    pub const BACKLINKS_PANEL_WIDTH: u16 = 36;
+    pub const TASKS_PANEL_WIDTH: u16 = 40;
     pub const GRID_VERTICAL_SPACING: usize = 8;
     pub const GRID_HORIZONTAL_SPACING: usize = 4;
     pub const VISIBLE_NOTE_COUNT_APPROX: usize = 30;
@@ -14,12 +19,221 @@ pub mod layout {
 pub mod timing {
     pub const FRAME_TIME_MS: u128 = 8; // 120 FPS for responsive cursor movement
     pub const SAVE_INTERVAL_MS: u128 = 2000; // 2 seconds auto-save debounce
+    pub const AUTOSAVE_CHECK_MS: u64 = 500; // How often the idle loop wakes to auto-save unsaved changes
+    pub const UNDO_GROUP_WINDOW_MS: u128 = 1000; // Typing pauses longer than this start a new undo step
+    pub const LIVE_RELOAD_MS: u64 = 1000; // How often the database is checked for changes by other processes
+    pub const CURSOR_FLASH_MS: u64 = 700; // How long Find cursor shows its crosshair
+}
+
+/// Grid Line Presets (offered in the Settings panel)
+pub mod grid {
+    pub const SPACING_PRESETS: &[(usize, usize)] = &[(8, 4), (4, 2), (10, 5), (16, 8)]; // (columns, rows)
+    pub const COLOR_PRESETS: &[(u8, u8, u8)] = &[
+        (60, 60, 60),   // Dark gray
+        (40, 70, 120),  // Blue
+        (40, 100, 60),  // Green
+        (120, 90, 20),  // Amber
+    ];
+}
+
+/// Undo History Constants
+pub mod undo {
+    pub const DEFAULT_DEPTH: usize = 100; // Edits remembered before the oldest are pruned
+    pub const DEPTH_PRESETS: &[usize] = &[100, 250, 1000, 5000]; // Choices offered in Settings
+}
+
+/// Note Storage Constants
+pub mod storage {
+    pub const SPARSE_MIN_AREA: usize = 100_000; // Bounding-box cells before the sparse save format is considered
+    pub const SPARSE_AREA_PER_CELL: usize = 16; // ...and it is used once the box is this many times the text
+    pub const AUTO_TITLE_MAX_CHARS: usize = 60; // Longest title taken from a note's first line
+}
+
+/// Notes Sidebar Paging
+pub mod notes_list {
+    pub const PAGE_SIZE: usize = 100; // Notes fetched at a time
+    pub const PREFETCH_MARGIN: usize = 20; // Fetch the next page when scrolling within this many of the end
+    pub const ACCENT_PRESETS: &[(u8, u8, u8)] = &[
+        (230, 80, 80),   // Red
+        (240, 150, 50),  // Orange
+        (230, 200, 60),  // Yellow
+        (90, 190, 100),  // Green
+        (80, 150, 240),  // Blue
+        (170, 110, 230), // Purple
+    ];
+    pub const SMART_VIEW_MAX_NOTES: usize = 8; // Notes shown under an unfolded smart view
+    pub const TRASH_DAYS: i64 = 30; // Recently deleted notes are purged after this many days
+    pub const FAVORITES: u8 = 9; // Favorite slots, opened with Alt+1..Alt+9
+}
+
+/// Database Archives (export-archive / import-archive)
+pub mod archive {
+    pub const MAX_ENTRY_BYTES: usize = 512 * 1024 * 1024; // Largest file read from an archive
+    pub const MAX_TOTAL_BYTES: usize = 2 * 1024 * 1024 * 1024; // Most data read from one archive
+}
+
+/// Local Capture Endpoint
+pub mod capture {
+    pub const DEFAULT_PORT: u16 = 7373; // 127.0.0.1 port when capture_server is enabled
+    pub const MAX_HEADER_BYTES: usize = 16 * 1024; // Larger request headers are refused
+    pub const MAX_BODY_BYTES: usize = 1024 * 1024; // Largest accepted JSON payload
+    pub const TIMEOUT_MS: u64 = 5000; // A connection must send its request within this time
+}
+
+/// Follow Mode
+pub mod broadcast {
+    pub const DEFAULT_PORT: u16 = 7374; // Port the broadcast is served on, on every interface
+    pub const MAX_HEADER_BYTES: usize = 16 * 1024; // Larger request headers are refused
+    pub const TIMEOUT_MS: u64 = 5000; // A viewer must send its request within this time
+}
+
+/// Single Instance
+pub mod instance {
+    pub const LOCK_NAME: &str = "chonk-note.lock"; // Held by the running editor, next to notes.db
+    pub const SOCKET_NAME: &str = "instance.sock"; // Where a second invocation reaches it
+}
+
+/// Automation Server (JSON-RPC over a Unix socket)
+pub mod automation {
+    pub const SOCKET_NAME: &str = "chonk-note.sock"; // Next to notes.db
+    pub const MAX_REQUEST_BYTES: usize = 4 * 1024 * 1024; // Longest request line accepted
+    pub const DEFAULT_LIST_LIMIT: usize = 100; // notes.list without a limit
+    pub const MAX_LIST_LIMIT: usize = 1000; // Most notes one notes.list call returns
+    pub const MCP_VERSION: &str = "2024-11-05"; // Model Context Protocol revision offered to clients
+}
+
+/// Grid Memory Cap
+pub mod memory {
+    pub const CAP_PRESETS_MB: &[usize] = &[0, 4, 16, 64]; // 0 = no cap; offered in the Settings panel
+}
+
+/// Drag Selection Autoscroll
+pub mod autoscroll {
+    pub const EDGE_MARGIN: usize = 3; // Cells from the editor edge where dragging starts scrolling
+    pub const INTERVAL_MS: u64 = 40; // Scroll step timer while the drag is held near an edge
+    pub const MAX_SPEED: usize = 16; // Most rows/cols per step (speed grows with depth into the margin)
+}
+
+/// Tab Key Constants
+pub mod tab {
+    pub const DEFAULT_WIDTH: usize = 4; // Spaces inserted per Tab
+    pub const WIDTH_PRESETS: &[usize] = &[2, 4, 8]; // Offered in the Settings panel
+}
+
+/// Default Date/Time Insertion Formats (strftime)
+pub mod timestamps {
+    pub const DATE_FORMAT: &str = "%Y-%m-%d";
+    pub const TIME_FORMAT: &str = "%H:%M";
+    pub const LOG_ENTRY_FORMAT: &str = "[%Y-%m-%d %H:%M] "; // Prepended to new lines in the log note
+}
+
+/// Images Pasted Into Notes
+pub mod images {
+    pub const THUMBNAIL_ROWS: usize = 8; // Rows opened below the token and covered by the thumbnail
+    pub const THUMBNAIL_MAX_COLS: usize = 48; // Wide images are squeezed to this many columns
+}
+
+/// Note Transclusion
+pub mod transclusion {
+    pub const MAX_ROWS: usize = 40; // Most lines of another note shown under a ![[Title]] token
+}
+
+/// Backlinks Panel
+pub mod backlinks {
+    pub const MIN_TITLE_CHARS: usize = 3; // Shorter titles are only found in [[links]], not plain text
+    pub const MAX_MENTIONS: usize = 200; // Most entries the panel lists
+}
+
+/// Tasks Panel
+pub mod tasks {
+    pub const MAX_TASKS: usize = 500; // Most items gathered from the other notes
+}
+
+/// Encrypted Sync
+pub mod sync {
+    pub const DEFAULT_MINUTES: u64 = 5; // Time between automatic syncs when sync_url is set
+    pub const KEY_FILE: &str = "sync-key"; // Encryption key, next to the database
+}
+
+/// Word Count Goals
+pub mod word_goal {
+    pub const BAR_CELLS: usize = 8; // Width of the progress bar in the status line
+}
+
+/// Snap to Grid and Alignment Guides
+pub mod alignment {
+    pub const GUIDE_ROWS: usize = 40; // How far above and below guides look for blocks in the same column
+    pub const GUIDE_MS: u64 = 1500; // How long guides stay after a click or paste
+}
+
+/// Start Screen
+pub mod dashboard {
+    pub const RECENT_NOTES: usize = 10; // Most recently edited notes listed
+}
+
+/// Settings Problems Banner
+pub mod config_banner {
+    pub const MAX_PROBLEMS: usize = 5; // Listed before "...and N more"
+}
+
+/// Tidy Layout
+pub mod auto_layout {
+    pub const MAX_WORD_GAP: usize = 3; // Widest run of blanks that still joins text into one island
+    pub const BAND_GAP_ROWS: usize = 1; // Blank rows left between stacked blocks
+}
+
+/// Selection Coloring (Color selection...)
+pub mod highlighter {
+    pub const TEXT_COLORS: &[(&str, (u8, u8, u8))] = &[
+        ("Red", (230, 80, 80)),
+        ("Orange", (240, 150, 50)),
+        ("Yellow", (230, 200, 60)),
+        ("Green", (90, 190, 100)),
+        ("Teal", (0, 200, 200)),
+        ("Blue", (80, 150, 240)),
+        ("Purple", (170, 110, 230)),
+        ("Gray", (140, 140, 140)),
+    ];
+    pub const HIGHLIGHT_COLORS: &[(&str, (u8, u8, u8))] = &[
+        ("Yellow", (140, 120, 0)), // Dark enough for the editor's light text
+        ("Orange", (160, 85, 20)),
+        ("Red", (140, 40, 40)),
+        ("Pink", (150, 50, 110)),
+        ("Purple", (95, 60, 150)),
+        ("Blue", (40, 80, 150)),
+        ("Green", (40, 110, 50)),
+        ("Gray", (80, 80, 80)),
+    ];
+}
+
+/// Shell Commands
+pub mod shell {
+    pub const TIMEOUT_MS: u64 = 10_000; // A command still running after this is killed
+    pub const MAX_OUTPUT_BYTES: u64 = 1024 * 1024; // Commands printing more than this fail
+    pub const INSERT_CONFIRM_LINES: usize = 50; // Inserting longer output asks first
+    pub const MAX_INSERT_LINES: usize = 2000; // Inserted output is cut to this many lines
+}
+
+/// Focus Timer
+pub mod focus {
+    pub const DEFAULT_MINUTES: u64 = 25; // Length of a focus session
+}
+
+/// Log File Constants
+pub mod logging {
+    pub const MAX_FILE_BYTES: u64 = 1024 * 1024; // Rotate chonk-note.log past 1 MB
+    pub const BACKUP_COUNT: usize = 3; // Rotated files kept (chonk-note.log.1 .. .3)
 }
 
 /// Navigation Constants
 pub mod navigation {
     pub const PAGE_JUMP_ROWS: usize = 20; // Number of rows to jump for Page Up/Down
     pub const PAGE_JUMP_COLS: usize = 20; // Number of columns to jump for horizontal scrolling
+    pub const WHEEL_SCROLL_ROWS: usize = 3; // Default rows per mouse wheel notch
+    pub const WHEEL_SCROLL_PRESETS: &[usize] = &[1, 3, 5, 10]; // Offered in the Settings panel
+    pub const JUMP_LIST_LEN: usize = 100; // Positions kept for Alt+Left / Alt+Right
+    pub const SMOOTH_SCROLL_DIVISOR: usize = 3; // Smooth scroll covers 1/n of the remaining distance per frame
+    pub const WHEEL_SCROLL_COLS: usize = 6; // Columns per horizontal wheel notch (or Shift+wheel)
 }
 
 /// Color Theme (RGB values)
@@ -32,14 +246,43 @@ pub mod colors {
     pub const SELECTION_BG: (u8, u8, u8) = (255, 20, 147); // Deep pink
     pub const SELECTION_FG: (u8, u8, u8) = (255, 255, 255); // White
 
+    /// Extra cursors (Ctrl+Click, Ctrl+D)
+    pub const SECONDARY_CURSOR_BG: (u8, u8, u8) = (0, 200, 200); // Bright teal
+    pub const SECONDARY_CURSOR_FG: (u8, u8, u8) = (0, 0, 0); // Black
+
+    /// Other occurrences of the selected text
+    pub const MATCH_HIGHLIGHT_BG: (u8, u8, u8) = (110, 70, 20); // Dim amber
+
+    /// The cursor's row and column: tinted with the Cursor Highlight setting, and flashed by Find cursor
+    pub const CURSOR_LINE_BG: (u8, u8, u8) = (38, 42, 52); // Faint slate
+    pub const CURSOR_FLASH_BG: (u8, u8, u8) = (255, 193, 7); // Amber, like selected items
+    pub const CURSOR_FLASH_FG: (u8, u8, u8) = (0, 0, 0); // Black
+
+    /// Whitespace mode (Alt+W): dots and arrows for blanks and tabs, and hidden characters
+    pub const WHITESPACE_FG: (u8, u8, u8) = (75, 75, 85); // Faint gray
+    pub const HIDDEN_CHAR_BG: (u8, u8, u8) = (150, 30, 30); // Dark red
+    pub const HIDDEN_CHAR_FG: (u8, u8, u8) = (255, 255, 255); // White
+
+    /// Alignment guides (Snap to Grid)
+    pub const GUIDE_FG: (u8, u8, u8) = (0, 170, 170); // Teal, like the origin axes but brighter
+
+    /// URLs and file paths in note text (drawn underlined)
+    pub const LINK_FG: (u8, u8, u8) = (100, 170, 255); // Light blue
+
+    /// Notes embedded with ![[Title]] (read-only, drawn over the canvas)
+    pub const EMBED_BG: (u8, u8, u8) = (28, 34, 44); // Slate
+    pub const EMBED_FG: (u8, u8, u8) = (170, 180, 195); // Muted gray-blue
+
     /// Grid line colors
     pub const GRID_LINE_FG: (u8, u8, u8) = (60, 60, 60); // Dark gray
+    pub const ORIGIN_AXIS_FG: (u8, u8, u8) = (0, 128, 128); // Teal, matches title bar
 
     /// Sidebar colors
     pub const SIDEBAR_BG: (u8, u8, u8) = (30, 60, 100); // Dark blue
     pub const SIDEBAR_FG: (u8, u8, u8) = (220, 220, 220); // Light gray
     pub const SIDEBAR_ICON_FG: (u8, u8, u8) = (200, 200, 200); // Slightly darker gray
     pub const SIDEBAR_SCROLL_FG: (u8, u8, u8) = (76, 175, 80); // Green
+    pub const SIDEBAR_HOVER_BG: (u8, u8, u8) = (50, 90, 140); // Lighter blue under the pointer
 
     /// Selected item colors
     pub const SELECTED_ITEM_BG: (u8, u8, u8) = (255, 193, 7); // Amber/Gold
@@ -47,11 +290,12 @@ pub mod colors {
 }
 
 /// Helper function to format RGB color for terminal escape code
+/// (downgraded to the 256/16-color palette when the terminal lacks truecolor)
 pub fn rgb_bg(r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[48;2;{};{};{}m", r, g, b)
+    crate::term_caps::bg(r, g, b)
 }
 
 /// Helper function to format RGB foreground color for terminal escape code
 pub fn rgb_fg(r: u8, g: u8, b: u8) -> String {
-    format!("\x1b[38;2;{};{};{}m", r, g, b)
+    crate::term_caps::fg(r, g, b)
 }
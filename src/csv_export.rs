@@ -0,0 +1,96 @@
+// Turn a rectangular block of text laid out as a table into CSV.
+// Column boundaries are runs of columns that are blank on every row.
+
+/// Gaps narrower than this don't separate columns, so "New York" stays one cell
+const MIN_GAP: usize = 2;
+
+/// Split block lines into cells at the column gaps shared by all rows
+pub fn split_columns(lines: &[String]) -> Vec<Vec<String>> {
+    let rows: Vec<Vec<char>> = lines.iter().map(|l| l.chars().collect()).collect();
+    let rows: Vec<Vec<char>> = rows.into_iter().filter(|r| r.iter().any(|c| !c.is_whitespace())).collect();
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let blank = |col: usize| rows.iter().all(|r| r.get(col).is_none_or(|c| c.is_whitespace()));
+
+    // Prefer wide gaps; a table separated by single spaces falls back to any gap
+    let mut spans = column_spans(width, MIN_GAP, &blank);
+    if spans.len() <= 1 {
+        spans = column_spans(width, 1, &blank);
+    }
+
+    rows.iter()
+        .map(|row| {
+            spans.iter()
+                .map(|&(start, end)| {
+                    let cell: String = row.iter().skip(start).take(end - start).collect();
+                    cell.trim().to_string()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// (start, end) ranges of the columns between gaps of at least `min_gap` blank columns
+fn column_spans(width: usize, min_gap: usize, blank: &dyn Fn(usize) -> bool) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut gap = 0;
+
+    for col in 0..width {
+        if blank(col) {
+            gap += 1;
+            continue;
+        }
+        match start {
+            Some(s) if gap >= min_gap => {
+                spans.push((s, col - gap));
+                start = Some(col);
+            }
+            None => start = Some(col),
+            _ => {}
+        }
+        gap = 0;
+    }
+    if let Some(s) = start {
+        spans.push((s, width - gap));
+    }
+    spans
+}
+
+/// Render rows as RFC 4180 CSV (quoted where needed, CRLF-free)
+pub fn to_csv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| row.iter().map(|cell| escape(cell)).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_to_csv() {
+        let lines: Vec<String> = [
+            "City       Pop     Note",
+            "New York   8,336   big",
+            "",
+            "Boston     675     \"hub\"",
+        ].iter().map(|s| s.to_string()).collect();
+
+        let rows = split_columns(&lines);
+        assert_eq!(rows[1], vec!["New York", "8,336", "big"]);
+        assert_eq!(to_csv(&rows), "City,Pop,Note\nNew York,\"8,336\",big\nBoston,675,\"\"\"hub\"\"\"");
+
+        // Single-space tables still split
+        let rows = split_columns(&["a b".to_string(), "c d".to_string()]);
+        assert_eq!(rows, vec![vec!["a", "b"], vec!["c", "d"]]);
+    }
+}
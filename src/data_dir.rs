@@ -0,0 +1,54 @@
+// The app's data directory
+// <data dir>/chonk-note holds the database, settings, logs, plugins, sockets and secrets.
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+
+/// <data dir>/chonk-note
+pub fn dir() -> Option<PathBuf> {
+    Some(dirs::data_local_dir()?.join("chonk-note"))
+}
+
+/// A file or directory inside it
+pub fn file(name: &str) -> Option<PathBuf> {
+    Some(dir()?.join(name))
+}
+
+/// `len` random bytes from the OS, as hex digits
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The secret kept in file `name`, as hex digits; `len` random bytes are written there
+/// (readable only by the owner) the first time
+pub fn load_or_create_secret(name: &str, len: usize) -> Result<String> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let path = file(name).context("Could not find data directory")?;
+    if let Ok(secret) = std::fs::read_to_string(&path) {
+        if !secret.trim().is_empty() {
+            return Ok(secret.trim().to_string());
+        }
+    }
+    let secret = random_hex(len);
+    let mut out = std::fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(&path)?;
+    writeln!(out, "{}", secret)?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_hex() {
+        let (a, b) = (random_hex(16), random_hex(16));
+        assert_eq!(a.len(), 32);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+}
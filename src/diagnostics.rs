@@ -0,0 +1,109 @@
+// Runtime diagnostics: frame timing, input latency and autosave timing for
+// the performance HUD.
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent frames/inputs kept for averages
+const SAMPLE_WINDOW: usize = 120;
+
+/// Rolling window of durations with average and maximum
+#[derive(Default)]
+pub struct Samples {
+    values: VecDeque<Duration>,
+}
+
+impl Samples {
+    pub fn push(&mut self, value: Duration) {
+        if self.values.len() == SAMPLE_WINDOW {
+            self.values.pop_front();
+        }
+        self.values.push_back(value);
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.values.back().copied()
+    }
+
+    pub fn average(&self) -> Option<Duration> {
+        if self.values.is_empty() {
+            return None;
+        }
+        Some(self.values.iter().sum::<Duration>() / self.values.len() as u32)
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.values.iter().max().copied()
+    }
+}
+
+/// Measurements shown in the performance HUD
+#[derive(Default)]
+pub struct Diagnostics {
+    pub hud_visible: bool,
+    pub frame_times: Samples,
+    pub frames_rendered: u64,
+    /// Editor cells written during the last frame
+    pub cells_redrawn: usize,
+    /// Time from reading an input event to the frame that shows its effect
+    pub input_latency: Samples,
+    pending_input: Option<Instant>,
+    pub autosave_times: Samples,
+    pub last_autosave: Option<Instant>,
+    /// Drag/motion events dropped because a newer one arrived before the frame
+    pub motion_events_coalesced: u64,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that an input event arrived; the next frame completes its latency sample
+    pub fn input_received(&mut self, at: Instant) {
+        self.pending_input.get_or_insert(at);
+    }
+
+    /// Record a finished frame that started at `started`
+    pub fn frame_rendered(&mut self, started: Instant) {
+        let now = Instant::now();
+        self.frame_times.push(now.duration_since(started));
+        self.frames_rendered += 1;
+        if let Some(input_at) = self.pending_input.take() {
+            self.input_latency.push(now.duration_since(input_at));
+        }
+    }
+
+    pub fn autosave_finished(&mut self, elapsed: Duration) {
+        self.autosave_times.push(elapsed);
+        self.last_autosave = Some(Instant::now());
+        log::debug!("Autosave took {:.2} ms", elapsed.as_secs_f64() * 1000.0);
+    }
+}
+
+/// Format an optional duration as milliseconds for the HUD
+pub fn format_ms(value: Option<Duration>) -> String {
+    match value {
+        Some(d) => format!("{:.2} ms", d.as_secs_f64() * 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_samples_window() {
+        let mut samples = Samples::default();
+        assert_eq!(samples.average(), None);
+
+        for ms in 1..=SAMPLE_WINDOW as u64 + 10 {
+            samples.push(Duration::from_millis(ms));
+        }
+        // Oldest ten samples fell out of the window
+        assert_eq!(samples.values.len(), SAMPLE_WINDOW);
+        assert_eq!(samples.last(), Some(Duration::from_millis(SAMPLE_WINDOW as u64 + 10)));
+        assert_eq!(samples.max(), samples.last());
+        assert_eq!(samples.average(), Some(Duration::from_micros(70_500)));
+    }
+}
@@ -0,0 +1,324 @@
+// Diffs for comparing two versions of a note: line diff, word diff within a
+// line, side-by-side pairing, and a three-way line merge.
+// Plain LCS after trimming the common prefix/suffix.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffOp {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+// Above this many LCS cells, fall back to "all removed, then all added"
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+/// One step of an index-based edit script from `old` to `new`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Same(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Diff `old` against `new` line by line
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffOp> {
+    edits(old, new)
+        .into_iter()
+        .map(|edit| match edit {
+            Edit::Same(i, _) => DiffOp::Same(old[i].clone()),
+            Edit::Removed(i) => DiffOp::Removed(old[i].clone()),
+            Edit::Added(j) => DiffOp::Added(new[j].clone()),
+        })
+        .collect()
+}
+
+/// Diff two lines word by word; each op holds a word or a run of whitespace
+pub fn diff_words(old: &str, new: &str) -> Vec<DiffOp> {
+    diff_lines(&split_words(old), &split_words(new))
+}
+
+/// Split into alternating runs of whitespace and non-whitespace
+fn split_words(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for ch in text.chars() {
+        match tokens.last_mut() {
+            Some(last) if last.chars().next().is_some_and(|c| c.is_whitespace() == ch.is_whitespace()) => last.push(ch),
+            _ => tokens.push(ch.to_string()),
+        }
+    }
+    tokens
+}
+
+/// A row of a side-by-side view: left is the old version, right the new one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideBySideRow {
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub changed: bool,
+}
+
+/// Pair up a line diff into rows, matching each run of removed lines with the added lines after it
+pub fn side_by_side(ops: &[DiffOp]) -> Vec<SideBySideRow> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < ops.len() {
+        if let DiffOp::Same(line) = &ops[i] {
+            rows.push(SideBySideRow { left: Some(line.clone()), right: Some(line.clone()), changed: false });
+            i += 1;
+            continue;
+        }
+
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        while i < ops.len() {
+            match &ops[i] {
+                DiffOp::Removed(line) if added.is_empty() => removed.push(line.clone()),
+                DiffOp::Added(line) => added.push(line.clone()),
+                _ => break,
+            }
+            i += 1;
+        }
+        for k in 0..removed.len().max(added.len()) {
+            rows.push(SideBySideRow {
+                left: removed.get(k).cloned(),
+                right: added.get(k).cloned(),
+                changed: true,
+            });
+        }
+    }
+    rows
+}
+
+/// Result of a three-way merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merge {
+    pub lines: Vec<String>,
+    /// Number of regions both sides changed differently (wrapped in conflict markers)
+    pub conflicts: usize,
+}
+
+pub const CONFLICT_START: &str = "<<<<<<< mine";
+pub const CONFLICT_SEPARATOR: &str = "=======";
+pub const CONFLICT_END: &str = ">>>>>>> theirs";
+
+/// A change to base[start..end], replaced by `lines`
+struct Hunk {
+    start: usize,
+    end: usize,
+    lines: Vec<String>,
+}
+
+fn hunks(base: &[String], other: &[String]) -> Vec<Hunk> {
+    let mut result: Vec<Hunk> = Vec::new();
+    let mut base_pos = 0;
+    let mut current: Option<Hunk> = None;
+
+    for edit in edits(base, other) {
+        match edit {
+            Edit::Same(i, _) => {
+                result.extend(current.take());
+                base_pos = i + 1;
+            }
+            Edit::Removed(i) => {
+                current.get_or_insert(Hunk { start: i, end: i, lines: Vec::new() }).end = i + 1;
+                base_pos = i + 1;
+            }
+            Edit::Added(j) => {
+                current
+                    .get_or_insert(Hunk { start: base_pos, end: base_pos, lines: Vec::new() })
+                    .lines
+                    .push(other[j].clone());
+            }
+        }
+    }
+    result.extend(current);
+    result
+}
+
+/// Apply the hunks that fall inside base[start..end] and return that region's new lines
+fn apply_region(base: &[String], hunks: &[&Hunk], start: usize, end: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for hunk in hunks {
+        lines.extend_from_slice(&base[pos..hunk.start]);
+        lines.extend(hunk.lines.iter().cloned());
+        pos = hunk.end;
+    }
+    lines.extend_from_slice(&base[pos..end]);
+    lines
+}
+
+/// Merge two edited versions of `base`. Changes made by only one side are applied;
+/// overlapping changes that differ are kept side by side inside conflict markers.
+pub fn merge3(base: &[String], mine: &[String], theirs: &[String]) -> Merge {
+    let mine_hunks = hunks(base, mine);
+    let theirs_hunks = hunks(base, theirs);
+    let (mut m, mut t) = (0, 0);
+    let mut pos = 0;
+    let mut merge = Merge { lines: Vec::new(), conflicts: 0 };
+
+    while m < mine_hunks.len() || t < theirs_hunks.len() {
+        // Start a region at whichever hunk comes first
+        let first_is_mine = t >= theirs_hunks.len()
+            || (m < mine_hunks.len() && mine_hunks[m].start <= theirs_hunks[t].start);
+        let first = if first_is_mine { &mine_hunks[m] } else { &theirs_hunks[t] };
+        let (start, mut end) = (first.start, first.end);
+
+        // Grow the region while hunks from either side overlap it
+        let mut region_mine = Vec::new();
+        let mut region_theirs = Vec::new();
+        loop {
+            if m < mine_hunks.len() && (mine_hunks[m].start < end || mine_hunks[m].start == start) {
+                end = end.max(mine_hunks[m].end);
+                region_mine.push(&mine_hunks[m]);
+                m += 1;
+            } else if t < theirs_hunks.len() && (theirs_hunks[t].start < end || theirs_hunks[t].start == start) {
+                end = end.max(theirs_hunks[t].end);
+                region_theirs.push(&theirs_hunks[t]);
+                t += 1;
+            } else {
+                break;
+            }
+        }
+
+        merge.lines.extend_from_slice(&base[pos..start]);
+        let mine_lines = apply_region(base, &region_mine, start, end);
+        let theirs_lines = apply_region(base, &region_theirs, start, end);
+
+        if region_theirs.is_empty() || mine_lines == theirs_lines {
+            merge.lines.extend(mine_lines);
+        } else if region_mine.is_empty() {
+            merge.lines.extend(theirs_lines);
+        } else {
+            merge.conflicts += 1;
+            merge.lines.push(CONFLICT_START.to_string());
+            merge.lines.extend(mine_lines);
+            merge.lines.push(CONFLICT_SEPARATOR.to_string());
+            merge.lines.extend(theirs_lines);
+            merge.lines.push(CONFLICT_END.to_string());
+        }
+        pos = end;
+    }
+
+    merge.lines.extend_from_slice(&base[pos..]);
+    merge
+}
+
+/// Edit script from `old` to `new`
+fn edits<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut result: Vec<Edit> = (0..prefix).map(|i| Edit::Same(i, i)).collect();
+
+    if old_mid.len() * new_mid.len() > MAX_LCS_CELLS {
+        result.extend((0..old_mid.len()).map(|i| Edit::Removed(prefix + i)));
+        result.extend((0..new_mid.len()).map(|j| Edit::Added(prefix + j)));
+    } else {
+        result.extend(lcs_edits(old_mid, new_mid).into_iter().map(|edit| match edit {
+            Edit::Same(i, j) => Edit::Same(prefix + i, prefix + j),
+            Edit::Removed(i) => Edit::Removed(prefix + i),
+            Edit::Added(j) => Edit::Added(prefix + j),
+        }));
+    }
+
+    let (old_tail, new_tail) = (old.len() - suffix, new.len() - suffix);
+    result.extend((0..suffix).map(|k| Edit::Same(old_tail + k, new_tail + k)));
+    result
+}
+
+fn lcs_edits<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let (n, m) = (old.len(), new.len());
+
+    // lengths[i][j] = LCS length of old[i..] and new[j..]
+    let mut lengths = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if old[i] == new[j] {
+            result.push(Edit::Same(i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            result.push(Edit::Removed(i));
+            i += 1;
+        } else {
+            result.push(Edit::Added(j));
+            j += 1;
+        }
+    }
+    result.extend((i..n).map(Edit::Removed));
+    result.extend((j..m).map(Edit::Added));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let ops = diff_lines(&lines("a\nb\nc"), &lines("a\nx\nc\nd"));
+        assert_eq!(ops, vec![
+            DiffOp::Same("a".into()),
+            DiffOp::Removed("b".into()),
+            DiffOp::Added("x".into()),
+            DiffOp::Same("c".into()),
+            DiffOp::Added("d".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_diff_words_and_side_by_side() {
+        let ops = diff_words("buy milk today", "buy oat milk today");
+        assert_eq!(ops, vec![
+            DiffOp::Same("buy".into()),
+            DiffOp::Same(" ".into()),
+            DiffOp::Added("oat".into()),
+            DiffOp::Added(" ".into()),
+            DiffOp::Same("milk".into()),
+            DiffOp::Same(" ".into()),
+            DiffOp::Same("today".into()),
+        ]);
+
+        let rows = side_by_side(&diff_lines(&lines("a\nb\nc"), &lines("a\nx\ny\nc")));
+        assert_eq!(rows.len(), 4);
+        assert_eq!((rows[1].left.as_deref(), rows[1].right.as_deref()), (Some("b"), Some("x")));
+        assert_eq!((rows[2].left.as_deref(), rows[2].right.as_deref()), (None, Some("y")));
+        assert!(!rows[3].changed);
+    }
+
+    #[test]
+    fn test_merge3() {
+        let base = lines("one\ntwo\nthree\nfour");
+
+        // Non-overlapping edits from both sides are combined
+        let merged = merge3(&base, &lines("one\n2\nthree\nfour"), &lines("one\ntwo\nthree\nfour\nfive"));
+        assert_eq!(merged, Merge { lines: lines("one\n2\nthree\nfour\nfive"), conflicts: 0 });
+
+        // Different edits to the same line are marked as a conflict
+        let merged = merge3(&base, &lines("one\nmine\nthree\nfour"), &lines("one\ntheirs\nthree\nfour"));
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.lines, lines("one\n<<<<<<< mine\nmine\n=======\ntheirs\n>>>>>>> theirs\nthree\nfour"));
+    }
+}
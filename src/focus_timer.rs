@@ -0,0 +1,66 @@
+// Focus (pomodoro) timer shown in the status line
+// Started and stopped from the Notes menu. When a session runs out the editor rings the
+// terminal bell (focus_bell) and appends a line about it to the log note, if there is one.
+use std::time::{Duration, Instant};
+
+pub struct FocusTimer {
+    started: Instant,
+    duration: Duration,
+    /// Title of the note open when the session started, for the log line
+    pub note_title: Option<String>,
+}
+
+impl FocusTimer {
+    pub fn start(minutes: u64, note_title: Option<String>, now: Instant) -> Self {
+        Self { started: now, duration: Duration::from_secs(minutes.max(1) * 60), note_title }
+    }
+
+    pub fn minutes(&self) -> u64 {
+        self.duration.as_secs() / 60
+    }
+
+    /// Whole minutes since the session started
+    pub fn elapsed_minutes(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.started).as_secs() / 60
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.duration.saturating_sub(now.saturating_duration_since(self.started))
+    }
+
+    pub fn is_finished(&self, now: Instant) -> bool {
+        self.remaining(now).is_zero()
+    }
+
+    /// Countdown for the status line, rounded up so a fresh 25 minute session shows 25:00
+    pub fn label(&self, now: Instant) -> String {
+        let remaining = self.remaining(now);
+        let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+        format!(" Focus {:02}:{:02} ", secs / 60, secs % 60)
+    }
+
+    /// When the countdown next changes (or the session ends)
+    pub fn next_change(&self, now: Instant) -> Instant {
+        match self.remaining(now).subsec_nanos() {
+            0 => now + Duration::from_secs(1),
+            nanos => now + Duration::from_nanos(nanos as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_countdown() {
+        let now = Instant::now();
+        let timer = FocusTimer::start(25, None, now);
+        assert_eq!(timer.label(now), " Focus 25:00 ");
+        assert_eq!(timer.label(now + Duration::from_millis(61_500)), " Focus 23:59 ");
+        assert_eq!(timer.next_change(now + Duration::from_millis(61_500)), now + Duration::from_secs(62));
+        assert!(!timer.is_finished(now + Duration::from_secs(25 * 60 - 1)));
+        assert!(timer.is_finished(now + Duration::from_secs(25 * 60)));
+        assert_eq!(timer.elapsed_minutes(now + Duration::from_secs(25 * 60 + 5)), 25);
+    }
+}
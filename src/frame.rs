@@ -0,0 +1,193 @@
+// Editor frame assembly
+// Lays out the visible part of the grid and turns each screen row into positioned text runs.
+// Kept free of terminal I/O so a full frame can be benchmarked (benches/grid.rs).
+// In low-bandwidth mode RowCache compares rows with the previous frame so only changed
+// rows are sent.
+use crate::chunked_grid::{ChunkedGrid, Style, Viewport};
+use crate::config::{colors, rgb_bg, rgb_fg};
+use std::fmt::Write;
+
+/// One assembled editor frame
+pub struct EditorFrame {
+    /// Characters on screen, row by row (spaces where the canvas is empty)
+    pub screen: Vec<Vec<char>>,
+    /// Escape sequences that draw each screen row at its position (each starts by clearing the line)
+    pub rows: Vec<String>,
+    /// Cells actually written (blank cells are only cleared)
+    pub cells_drawn: usize,
+}
+
+/// Build the frame for a `width` x `height` editor at screen position (x, y) showing the
+/// canvas from (top_row, left_col). Only occupied chunks in view are visited.
+pub fn build(grid: &ChunkedGrid, top_row: usize, left_col: usize, x: u16, y: u16, width: u16, height: u16) -> EditorFrame {
+    let mut screen = vec![vec![' '; width as usize]; height as usize];
+    // Cell styles, only looked up when the note has any
+    let mut styles = Vec::new();
+    if width > 0 && height > 0 {
+        let viewport = Viewport {
+            start_row: top_row,
+            start_col: left_col,
+            end_row: top_row + height as usize - 1,
+            end_col: left_col + width as usize - 1,
+        };
+        for ((row, col), ch) in grid.visible_cells(viewport) {
+            screen[row - top_row][col - left_col] = if is_invisible(ch) { ' ' } else { ch };
+        }
+        if grid.has_styles() {
+            styles = vec![vec![Style::default(); width as usize]; height as usize];
+            for ((row, col), style) in grid.visible_styles(viewport) {
+                styles[row - top_row][col - left_col] = style;
+            }
+        }
+    }
+    let style_at = |screen_row: usize, screen_col: usize| styles.get(screen_row).map_or(Style::default(), |row: &Vec<Style>| row[screen_col]);
+
+    let sel_bg = rgb_bg(colors::SELECTION_BG.0, colors::SELECTION_BG.1, colors::SELECTION_BG.2);
+    let sel_fg = rgb_fg(colors::SELECTION_FG.0, colors::SELECTION_FG.1, colors::SELECTION_FG.2);
+    let mut rows = Vec::with_capacity(screen.len());
+    let mut cells_drawn = 0;
+
+    for (screen_row, cells) in screen.iter().enumerate() {
+        let grid_row = top_row + screen_row;
+        let term_row = y + screen_row as u16 + 1;
+        let mut output = String::new();
+
+        // Clear line
+        let _ = write!(output, "\x1b[{};{}H\x1b[K", term_row, x + 1);
+
+        // Split the row into runs of selected / unselected cells
+        let in_selection = |screen_col: usize| {
+            grid.selection.as_ref().is_some_and(|sel| sel.contains(grid_row, left_col + screen_col))
+        };
+        let mut start = 0;
+        while start < cells.len() {
+            let selected = in_selection(start);
+            let end = (start..cells.len()).find(|&c| in_selection(c) != selected).unwrap_or(cells.len());
+            if selected {
+                // Selected cells always show their background, even for spaces
+                let run: String = cells[start..end].iter().collect();
+                let _ = write!(output, "\x1b[{};{}H{}{}{}\x1b[0m", term_row, x + start as u16 + 1, sel_bg, sel_fg, run);
+                cells_drawn += end - start;
+            } else {
+                // Blank cells were already cleared, so only print from the first character to
+                // the last, in pieces of one style
+                let first = start + cells[start..end].iter().take_while(|&&c| c == ' ').count();
+                let last = end - cells[first..end].iter().rev().take_while(|c| c.is_whitespace()).count();
+                if first < last {
+                    let _ = write!(output, "\x1b[{};{}H", term_row, x + first as u16 + 1);
+                }
+                let mut piece = first;
+                while piece < last {
+                    let style = style_at(screen_row, piece);
+                    let piece_end = (piece..last).find(|&c| style_at(screen_row, c) != style).unwrap_or(last);
+                    let text: String = cells[piece..piece_end].iter().collect();
+                    if style.is_plain() {
+                        output.push_str(&text);
+                    } else {
+                        let _ = write!(output, "{}{}\x1b[0m", style_escape(style), text);
+                    }
+                    piece = piece_end;
+                }
+                cells_drawn += last - first;
+            }
+            start = end;
+        }
+        rows.push(output);
+    }
+
+    EditorFrame { screen, rows, cells_drawn }
+}
+
+/// Characters that would throw the rest of their row out of line if printed: tabs and other
+/// control characters move the terminal's cursor, zero-width and direction marks take no
+/// cell. They are drawn blank (or marked, when whitespace is shown).
+pub fn is_invisible(ch: char) -> bool {
+    ch.is_control()
+        || matches!(ch, '\u{ad}' | '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{2066}'..='\u{2069}' | '\u{feff}')
+}
+
+/// Escape sequence that switches to a cell style
+fn style_escape(style: Style) -> String {
+    let fg = style.fg.map(|(r, g, b)| rgb_fg(r, g, b)).unwrap_or_default();
+    let bg = style.bg.map(|(r, g, b)| rgb_bg(r, g, b)).unwrap_or_default();
+    let bold = if style.bold { "\x1b[1m" } else { "" };
+    let underline = if style.underline { "\x1b[4m" } else { "" };
+    format!("{}{}{}{}", fg, bg, bold, underline)
+}
+
+/// Editor rows sent in the previous frame (low-bandwidth diff updates)
+#[derive(Default)]
+pub struct RowCache {
+    layout: Option<u64>,
+    rows: Vec<String>,
+}
+
+impl RowCache {
+    /// Send every row of this frame and the next one (the screen was cleared, or something
+    /// is drawn over the editor)
+    pub fn invalidate(&mut self) {
+        self.layout = None;
+        self.rows.clear();
+    }
+
+    /// Keep the previous frame only if it was drawn with the same layout
+    pub fn start_frame(&mut self, layout: u64) {
+        if self.layout != Some(layout) {
+            self.layout = Some(layout);
+            self.rows.clear();
+        }
+    }
+
+    /// Output for the rows that differ from the previous frame; remembers `rows` for the next one
+    pub fn update(&mut self, rows: Vec<String>) -> String {
+        let output = rows
+            .iter()
+            .enumerate()
+            .filter(|&(i, row)| self.rows.get(i) != Some(row))
+            .map(|(_, row)| row.as_str())
+            .collect();
+        self.rows = rows;
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_frame() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(1, 3, "hi");
+        grid.insert_at(100, 100, "offscreen");
+
+        let frame = build(&grid, 0, 0, 0, 1, 10, 3);
+        assert_eq!(frame.screen[1].iter().collect::<String>(), "   hi     ");
+        assert_eq!(frame.cells_drawn, 2);
+        assert!(frame.rows[1].contains("\x1b[3;4Hhi"));
+
+        // A pasted tab or zero-width space doesn't push the rest of the row along
+        let mut tabbed = ChunkedGrid::new();
+        tabbed.insert_at(0, 0, "a\tb\u{200b}c");
+        assert_eq!(build(&tabbed, 0, 0, 0, 1, 6, 1).screen[0].iter().collect::<String>(), "a b c ");
+
+        // Styled cells are drawn in their colors
+        let mut styled = ChunkedGrid::new();
+        styled.insert_at(0, 0, "ok go");
+        styled.set_style(0, 3, Style { fg: Some((255, 0, 0)), ..Style::default() });
+        styled.set_style(0, 4, Style { bold: true, ..Style::default() });
+        let red = build(&styled, 0, 0, 0, 1, 10, 1);
+        assert!(red.rows[0].ends_with(&format!("\x1b[2;1Hok {}g\x1b[0m\x1b[1mo\x1b[0m", rgb_fg(255, 0, 0))));
+
+        // Only changed rows are resent while the layout stays the same
+        let mut cache = RowCache::default();
+        cache.start_frame(1);
+        assert_eq!(cache.update(frame.rows.clone()), frame.rows.concat());
+        grid.insert_at(2, 0, "x");
+        let next = build(&grid, 0, 0, 0, 1, 10, 3);
+        cache.start_frame(1);
+        assert_eq!(cache.update(next.rows.clone()), next.rows[2]);
+        cache.start_frame(2);
+        assert_eq!(cache.update(next.rows.clone()), next.rows.concat());
+    }
+}
@@ -0,0 +1,770 @@
+// Headless driver for end-to-end tests
+// Runs the real keyboard/mouse handlers against an App backed by an in-memory database,
+// default settings and a fixed screen size, so flows can be checked without a terminal.
+use crate::kitty_native::{InputEvent, KeyCode, KeyEvent, KeyModifiers, KittyTerminal, MouseButton, MouseEvent};
+use crate::mouse::MouseState;
+use crate::notes_database::NotesDatabase;
+use crate::notes_mode::NotesMode;
+use crate::settings::Settings;
+use crate::{keyboard, mouse, App};
+use anyhow::Result;
+
+const NO_MODIFIERS: KeyModifiers = KeyModifiers { ctrl: false, alt: false, shift: false, cmd: false };
+
+pub struct Driver {
+    pub app: App,
+    mouse_state: MouseState,
+}
+
+impl Driver {
+    /// Empty database, default settings, 80x24 screen
+    pub fn new() -> Result<Self> {
+        Ok(Self::with_db(NotesDatabase::open_in_memory()?))
+    }
+
+    /// Like `new`, on a database the test set up
+    pub fn with_db(db: NotesDatabase) -> Self {
+        let mut app = App::with_parts(NotesMode::with_db(db), Settings::default());
+        app.fixed_term_size = Some((80, 24));
+        Self { app, mouse_state: MouseState::default() }
+    }
+
+    pub async fn key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) -> Result<()> {
+        keyboard::handle_input(&mut self.app, KeyEvent { code, modifiers }).await?;
+        Ok(())
+    }
+
+    pub async fn key(&mut self, code: KeyCode) -> Result<()> {
+        self.key_with(code, NO_MODIFIERS).await
+    }
+
+    pub async fn ctrl(&mut self, ch: char) -> Result<()> {
+        self.key_with(KeyCode::Char(ch), KeyModifiers::CONTROL).await
+    }
+
+    /// Feed raw terminal bytes through the input parser, as the event loop does
+    pub async fn terminal_input(&mut self, bytes: &[u8]) -> Result<()> {
+        let mut buffer = bytes.to_vec();
+        while let Some(event) = KittyTerminal::parse_input_with_remainder(&buffer.clone(), &mut buffer)? {
+            if let InputEvent::Key(key) = event {
+                keyboard::handle_input(&mut self.app, key).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Type text as individual key presses ('\n' presses Enter)
+    pub async fn type_text(&mut self, text: &str) -> Result<()> {
+        for ch in text.chars() {
+            match ch {
+                '\n' => self.key(KeyCode::Enter).await?,
+                ch => self.key(KeyCode::Char(ch)).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Press and release the left button at a screen cell (0-based)
+    pub async fn click(&mut self, x: u16, y: u16) -> Result<()> {
+        self.click_with(x, y, NO_MODIFIERS).await
+    }
+
+    pub async fn click_with(&mut self, x: u16, y: u16, modifiers: KeyModifiers) -> Result<()> {
+        for is_press in [true, false] {
+            let event = MouseEvent { button: Some(MouseButton::Left), x, y, modifiers, is_press, is_drag: false };
+            mouse::handle_mouse(&mut self.app, event, &mut self.mouse_state).await?;
+        }
+        Ok(())
+    }
+
+    /// Text of a canvas row, trailing spaces trimmed
+    pub fn line(&self, row: usize) -> String {
+        let len = self.app.grid.get_line_length(row);
+        if len == 0 {
+            return String::new();
+        }
+        self.app.grid.get_line(row, 0, len - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_type_switch_undo() -> Result<()> {
+        let mut driver = Driver::new()?;
+
+        // Two notes, each with some text
+        driver.ctrl('n').await?;
+        driver.type_text("alpha\nbeta").await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("alpha".to_string(), "beta".to_string()));
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 4));
+
+        driver.ctrl('n').await?;
+        driver.type_text("gamma").await?;
+        let first_id = driver.app.notes_list[1].id.clone();
+        let stored = driver.app.notes_mode.db.get_note(&first_id)?.unwrap();
+        assert_eq!(stored.content, "alpha\nbeta");
+
+        // Undo works within the note
+        driver.ctrl('z').await?;
+        assert_eq!(driver.line(0), "");
+        driver.ctrl('y').await?;
+        assert_eq!(driver.line(0), "gamma");
+
+        // Switch back with the picker (most recent first); history doesn't cross notes
+        driver.ctrl('o').await?;
+        driver.key(KeyCode::Down).await?;
+        driver.key(KeyCode::Enter).await?;
+        assert_eq!(driver.app.notes_mode.current_note.as_ref().map(|n| n.id.clone()), Some(first_id));
+        assert_eq!(driver.line(0), "alpha");
+        driver.ctrl('z').await?;
+        assert_eq!(driver.app.status_message, "Nothing to undo");
+        assert_eq!(driver.line(1), "beta");
+
+        // The other note was saved on the way out
+        let second = driver.app.notes_list.iter().find(|n| n.content == "gamma");
+        assert!(second.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_goal_column() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("a long first line\nab\nanother long line").await?;
+        driver.key(KeyCode::Home).await?;
+        for _ in 0..2 {
+            driver.key(KeyCode::Up).await?;
+        }
+        driver.key(KeyCode::End).await?;
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (0, 17));
+
+        // Free movement over the canvas keeps the column as is
+        driver.key(KeyCode::Down).await?;
+        assert_eq!(driver.app.cursor_col, 17);
+
+        // Snapping stops at the short line's end, then returns to the column
+        driver.app.settings.snap_to_line_end = true;
+        driver.key(KeyCode::Up).await?;
+        driver.key(KeyCode::Down).await?;
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 2));
+        driver.key(KeyCode::Down).await?;
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (2, 17));
+
+        // Any other move starts a new run from wherever the cursor is
+        driver.key(KeyCode::Left).await?;
+        driver.key(KeyCode::Up).await?;
+        driver.key(KeyCode::Up).await?;
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (0, 16));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_command_palette() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.key(KeyCode::F(1)).await?;
+        driver.type_text("tgl").await?;
+        driver.key(KeyCode::Enter).await?;
+        assert!(driver.app.show_grid_lines);
+        assert!(driver.app.overlays.is_empty());
+
+        // Shortcuts run the same commands
+        driver.ctrl('g').await?;
+        assert_eq!((driver.app.show_grid_lines, driver.app.status_message.as_str()), (false, "Grid lines: OFF"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multiple_cursors() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.app.sidebar_expanded = false;
+        driver.type_text("foo = foo\nfoo").await?;
+
+        // Ctrl+D takes the word under the cursor, then each next occurrence
+        driver.click(0, 1).await?;
+        for _ in 0..3 {
+            driver.ctrl('d').await?;
+        }
+        assert_eq!(driver.app.selections.as_ref().map(|s| s.len()), Some(3));
+        driver.type_text("xy").await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("xy = xy".to_string(), "xy".to_string()));
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 2));
+
+        // The burst at all three is one undo step
+        driver.ctrl('z').await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("foo = foo".to_string(), "foo".to_string()));
+        assert!(driver.app.selections.is_none());
+
+        // Ctrl+Click adds a caret next to the cursor
+        driver.click(0, 1).await?;
+        driver.click_with(1, 2, KeyModifiers::CONTROL).await?;
+        driver.type_text("!").await?;
+        driver.key(KeyCode::Backspace).await?;
+        driver.type_text("?").await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("?foo = foo".to_string(), "f?oo".to_string()));
+        driver.key(KeyCode::Esc).await?;
+        assert!(driver.app.selections.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_select_all_occurrences() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("cat dog cat\ncatalog").await?;
+
+        // From the cursor on the last "cat" of the first line: all three, that one primary
+        driver.key(KeyCode::Up).await?;
+        driver.key(KeyCode::End).await?;
+        driver.key_with(KeyCode::Char('L'), KeyModifiers { ctrl: true, shift: true, ..NO_MODIFIERS }).await?;
+        assert_eq!(driver.app.status_message, "3 occurrences of \"cat\" selected");
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (0, 11));
+        driver.type_text("owl").await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("owl dog owl".to_string(), "owlalog".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_line() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("one\ntwo").await?;
+
+        driver.key(KeyCode::Up).await?;
+        driver.key_with(KeyCode::Down, KeyModifiers { alt: true, shift: true, ..NO_MODIFIERS }).await?;
+        assert_eq!((driver.line(0), driver.line(1), driver.line(2)), ("one".to_string(), "one".to_string(), "two".to_string()));
+        assert_eq!(driver.app.cursor_row, 1);
+
+        // One undo step
+        driver.ctrl('z').await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("one".to_string(), "two".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_lines() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("one\n    two\nthree").await?;
+
+        driver.key(KeyCode::Up).await?;
+        driver.key(KeyCode::Up).await?;
+        driver.key_with(KeyCode::Char('j'), KeyModifiers { alt: true, ..NO_MODIFIERS }).await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("one two".to_string(), "three".to_string()));
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (0, 4));
+
+        driver.ctrl('z').await?;
+        assert_eq!((driver.line(0), driver.line(1), driver.line(2)), ("one".to_string(), "    two".to_string(), "three".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_open_line() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("  one\ntwo").await?;
+
+        // Ctrl+Enter and Ctrl+Shift+Enter as the kitty keyboard protocol reports them
+        driver.key(KeyCode::Up).await?;
+        driver.terminal_input(b"\x1b[13;5u").await?;
+        assert_eq!((driver.line(0), driver.line(1), driver.line(2)), ("  one".to_string(), String::new(), "two".to_string()));
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 2));
+
+        driver.terminal_input(b"\x1b[13;6u").await?;
+        assert_eq!((driver.line(1), driver.line(2)), (String::new(), String::new()));
+        assert_eq!(driver.app.cursor_row, 1);
+        driver.ctrl('z').await?;
+        driver.ctrl('z').await?;
+        assert_eq!((driver.line(0), driver.line(1)), ("  one".to_string(), "two".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filter_block() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("pear\napple\nfig\nend").await?;
+
+        driver.app.filter_block((0, 0, 2, 4), "sort; echo kiwi");
+        assert_eq!((0..5).map(|row| driver.line(row)).collect::<Vec<_>>(), ["apple", "fig", "pear", "kiwi", "end"]);
+        driver.app.filter_block((0, 0, 0, 4), "exit 1");
+        assert!(driver.app.status_message.contains("failed"));
+        driver.ctrl('z').await?;
+        assert_eq!((0..4).map(|row| driver.line(row)).collect::<Vec<_>>(), ["pear", "apple", "fig", "end"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_styled_block() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        let red = crate::Style { fg: Some((255, 0, 0)), ..Default::default() };
+        driver.app.write_styled_block(0, 0, vec!["ok".to_string()], &[vec![red, red]]);
+        assert_eq!((driver.line(0), driver.app.grid.style(0, 1)), ("ok".to_string(), red));
+
+        // Styles survive saving, and undo/redo
+        driver.ctrl('s').await?;
+        let saved = driver.app.notes_mode.current_note.clone().unwrap().content;
+        assert_eq!(crate::note_format::decode(&saved).style(0, 0), red);
+        driver.ctrl('z').await?;
+        assert!(!driver.app.grid.has_styles());
+        driver.ctrl('y').await?;
+        assert_eq!(driver.app.grid.style(0, 1), red);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_color_selection() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("hi there").await?;
+
+        // The palette's second row is highlights; with no selection the cursor's line is colored
+        driver.app.run_command(crate::commands::Command::ColorSelection)?;
+        driver.key(KeyCode::Down).await?;
+        driver.key(KeyCode::Enter).await?;
+        let yellow = crate::config::highlighter::HIGHLIGHT_COLORS[0].1;
+        assert_eq!((driver.app.grid.style(0, 0).bg, driver.app.grid.style(0, 2).bg), (Some(yellow), None));
+        driver.ctrl('b').await?;
+        assert_eq!(driver.app.grid.style(0, 7), crate::Style { bg: Some(yellow), bold: true, ..Default::default() });
+
+        driver.ctrl('z').await?;
+        assert!(!driver.app.grid.style(0, 7).bold);
+        driver.ctrl('s').await?;
+        let saved = driver.app.notes_mode.current_note.clone().unwrap().content;
+        assert_eq!(crate::note_format::decode(&saved).style(0, 7).bg, Some(yellow));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_cursor() -> Result<()> {
+        use crate::settings::CursorHighlight;
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.app.settings.cursor_highlight = CursorHighlight::Line;
+        driver.app.viewport_anchor = (driver.app.cursor_row, driver.app.cursor_col);
+
+        // Alt+C flashes a crosshair and has the viewport follow the cursor again
+        driver.key_with(KeyCode::Char('c'), KeyModifiers { alt: true, ..NO_MODIFIERS }).await?;
+        assert_eq!(driver.app.cursor_highlight(), (CursorHighlight::Crosshair, true));
+        assert_ne!(driver.app.viewport_anchor, (driver.app.cursor_row, driver.app.cursor_col));
+        driver.app.cursor_flash_until = Some(std::time::Instant::now());
+        driver.app.expire_highlights();
+        assert_eq!(driver.app.cursor_highlight(), (CursorHighlight::Line, false));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snap_to_grid() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.app.sidebar_expanded = false;
+        driver.app.settings.snap_to_grid = true;
+        driver.type_text("        title").await?;
+
+        // A click on empty canvas lands on the grid line, with a guide up to the title
+        driver.click(10, 4).await?;
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (3, 8));
+        assert_eq!(driver.app.guides, [crate::alignment::Guide { col: 8, top: 0, bottom: 3 }]);
+        driver.type_text("body").await?;
+        assert_eq!(driver.line(3), "        body");
+
+        // Clicking just after text doesn't move the cursor
+        driver.click(12, 4).await?;
+        assert_eq!(driver.app.cursor_col, 12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tidy_layout() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("title\n\n\n\n\n              stray").await?;
+
+        driver.app.run_command(crate::commands::Command::TidyLayout)?;
+        assert_eq!((0..3).map(|row| driver.line(row)).collect::<Vec<_>>(), ["title", "", "stray"]);
+        assert_eq!(driver.app.status_message, "Moved 1 block");
+
+        // One undo step puts it back
+        driver.ctrl('z').await?;
+        assert_eq!(driver.line(5), "              stray");
+        assert!(driver.line(2).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_select_island() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("intro\n\n    +--+  a\n    |  |\n    +--+").await?;
+
+        // Ctrl+Shift+A grabs the diagram and the label beside it, not the intro
+        driver.key_with(KeyCode::Char('A'), KeyModifiers { ctrl: true, shift: true, ..NO_MODIFIERS }).await?;
+        let block = driver.app.grid.selection.as_ref().map(|selection| selection.bounds());
+        assert_eq!(block, Some((2, 4, 4, 10)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_text_objects() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("title\n\none two\nthree\n\nend").await?;
+        let alt_shift = KeyModifiers { alt: true, shift: true, ..NO_MODIFIERS };
+
+        driver.app.cursor_row = 2;
+        driver.app.cursor_col = 5;
+        driver.key_with(KeyCode::Char('W'), alt_shift).await?;
+        assert_eq!(driver.app.grid.selection.as_ref().map(|selection| selection.bounds()), Some((2, 4, 2, 6)));
+
+        // Delete blanks the paragraph as one undo step
+        driver.key_with(KeyCode::Char('P'), alt_shift).await?;
+        assert_eq!(driver.app.grid.selection.as_ref().map(|selection| selection.bounds()), Some((2, 0, 3, 6)));
+        driver.key(KeyCode::Delete).await?;
+        assert_eq!((driver.line(2), driver.line(3), driver.line(5)), (String::new(), String::new(), "end".to_string()));
+        driver.ctrl('z').await?;
+        assert_eq!((driver.line(2), driver.line(3)), ("one two".to_string(), "three".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_favorites() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Groceries".to_string(), "milk".to_string(), vec![])?;
+        db.create_note("Journal".to_string(), "today".to_string(), vec![])?;
+        let mut driver = Driver::with_db(db);
+        driver.app.open_note_titled("Groceries")?;
+        driver.app.run_command(crate::commands::Command::Favorite)?;
+        assert_eq!(driver.app.status_message, "Alt+1 opens \"Groceries\"");
+        assert_eq!(driver.app.command_label(crate::commands::Command::Favorite), "Remove from favorites");
+
+        // Alt+1 comes back from another note; an empty slot just says so
+        driver.app.open_note_titled("Journal")?;
+        let alt = KeyModifiers { alt: true, ..NO_MODIFIERS };
+        driver.key_with(KeyCode::Char('1'), alt).await?;
+        assert_eq!(driver.line(0), "milk");
+        driver.key_with(KeyCode::Char('2'), alt).await?;
+        assert!(driver.app.status_message.starts_with("No favorite 2"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_text_above_origin_survives_reload() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("start").await?;
+        driver.key(KeyCode::Home).await?;
+        driver.key(KeyCode::Up).await?;
+        driver.type_text("above").await?;
+        let (row, col) = (driver.app.cursor_row, driver.app.cursor_col);
+        assert_eq!(driver.app.grid.logical_pos(row, col), (-1, 5));
+        driver.app.save_current_note()?;
+
+        // Reloading keeps Ln/Col (what the status line, go-to-line and the jump list use)
+        let id = driver.app.notes_mode.current_note.as_ref().map(|note| note.id.clone()).unwrap();
+        let note = driver.app.notes_mode.db.get_note(&id)?.unwrap();
+        driver.app.load_note(note);
+        let grid = &driver.app.grid;
+        let (top, left, _, _) = grid.bounds().unwrap();
+        assert_eq!(grid.logical_pos(top, left), (-1, 0));
+        assert_eq!(grid.get_line(top, left, left + 4), "above");
+        assert_eq!(grid.get_line(top + 1, left, left + 4), "start");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_growing_canvas_is_not_an_edit() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("hello").await?;
+        let entries = driver.app.undo_stack.history().len();
+
+        // Up at the top row grows the canvas, moving the text down a chunk
+        driver.key(KeyCode::Up).await?;
+        for _ in 0..5 {
+            driver.key(KeyCode::PageUp).await?;
+        }
+        let (top, left) = driver.app.grid.origin;
+        assert!(top > 0);
+        assert_eq!(driver.app.undo_stack.history().len(), entries);
+
+        // Undo and redo still act on the typing, where it now sits
+        driver.ctrl('z').await?;
+        assert_eq!(driver.app.grid.get_line(top, left, left + 4).trim(), "");
+        driver.ctrl('y').await?;
+        assert_eq!(driver.app.grid.get_line(top, left, left + 4), "hello");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sidebar_tooltip() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Tagged".to_string(), "one two\nthree".to_string(), vec!["work".to_string(), "ideas".to_string()])?;
+        let mut driver = Driver::with_db(db);
+        driver.app.sidebar_expanded = true;
+        let y = 1 + driver.app.smart_view_rows() as u16;
+
+        // Resting the pointer on a note shows its tooltip; a key closes it and still reaches the app
+        let event = MouseEvent { button: None, x: 3, y, modifiers: NO_MODIFIERS, is_press: false, is_drag: true };
+        mouse::handle_mouse(&mut driver.app, event, &mut driver.mouse_state).await?;
+        assert!(!driver.app.overlays.is_empty());
+        let note = driver.app.notes_list[0].clone();
+        let lines = driver.app.note_tooltip_lines(&note);
+        assert_eq!(lines[..2], ["Tags: work, ideas", "3 words"]);
+        assert!(lines[2].starts_with("Edited "));
+        driver.key(KeyCode::Char('x')).await?;
+        assert!(driver.app.overlays.is_empty());
+        assert!(driver.line(0).starts_with('x'));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_screen() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Older".to_string(), "old".to_string(), vec![])?;
+        db.create_note("Newer".to_string(), "new".to_string(), vec![])?;
+        let settings = Settings { start_screen: true, ..Settings::default() };
+        let mut driver = Driver { app: App::with_parts(NotesMode::with_db(db), settings), mouse_state: MouseState::default() };
+        driver.app.fixed_term_size = Some((80, 24));
+        assert!(driver.app.notes_mode.current_note.is_none());
+
+        // Enter opens the most recently edited note; Esc falls back to the newest
+        driver.app.open_start_screen();
+        driver.key(KeyCode::Enter).await?;
+        assert_eq!(driver.line(0), "new");
+        driver.app.notes_mode.current_note = None;
+        driver.app.open_start_screen();
+        driver.key(KeyCode::Esc).await?;
+        assert!(driver.app.notes_mode.current_note.is_some() && driver.app.overlays.is_empty());
+
+        // The Start column creates today's note, once
+        driver.app.open_start_screen();
+        driver.key(KeyCode::Right).await?;
+        driver.key(KeyCode::Right).await?;
+        driver.key(KeyCode::Enter).await?;
+        let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+        assert_eq!(driver.app.notes_mode.current_note.as_ref().map(|note| note.title.as_str()), Some(today.as_str()));
+        driver.app.run_command(crate::commands::Command::TodaysNote)?;
+        assert_eq!(driver.app.notes_mode.db.count_notes()?, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_extract_to_note() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("intro\n  step one\n  step two\nend").await?;
+
+        driver.app.extract_to_note((1, 2, 2, 9), "")?;
+        let note = driver.app.notes_mode.db.find_note_by_title("step one")?.expect("new note");
+        assert_eq!(note.content, "step one\nstep two");
+        driver.key(KeyCode::Enter).await?;
+        assert_eq!((0..4).map(|row| driver.line(row)).collect::<Vec<_>>(), ["intro", "  [[step one]]", "", "end"]);
+
+        // One undo step brings the text back; the link opens the new note
+        driver.ctrl('z').await?;
+        assert_eq!(driver.line(2), "  step two");
+        driver.ctrl('y').await?;
+        driver.key(KeyCode::Left).await?;
+        driver.app.open_link_at_cursor();
+        assert_eq!(driver.line(0), "step one");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backlinks_panel() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Packing list".to_string(), "books".to_string(), vec![])?;
+        db.create_note("Log".to_string(), "day one\n  bought a packing list".to_string(), vec![])?;
+        let mut driver = Driver::with_db(db);
+        driver.app.open_note_titled("Packing list")?;
+
+        driver.app.run_command(crate::commands::Command::ToggleBacklinks)?;
+        driver.app.refresh_backlinks();
+        let mentions = &driver.app.backlinks.mentions;
+        assert_eq!((mentions.len(), mentions[0].linked, mentions[0].line, mentions[0].col), (1, false, 1, 11));
+
+        // One click links it; the other note then shows up as linked
+        driver.app.link_mention(0);
+        driver.app.refresh_backlinks();
+        assert!(driver.app.backlinks.mentions[0].linked);
+        driver.app.open_mention(0)?;
+        assert_eq!(driver.line(1), "  bought a [[Packing list]]");
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 11));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tasks_panel() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let other = db.create_note("Errands".to_string(), "[ ] stamps".to_string(), vec![])?;
+        let mut driver = Driver::with_db(db);
+        driver.ctrl('n').await?;
+        driver.type_text("plan\nTODO book hall").await?;
+
+        driver.app.run_command(crate::commands::Command::ToggleTasks)?;
+        driver.app.refresh_tasks();
+        let items: Vec<_> = driver.app.tasks.items.iter().map(|task| (task.note_id == other.id, task.text.as_str())).collect();
+        assert_eq!(items, [(false, "book hall"), (true, "stamps")]);
+
+        // The open note changes in the editor, as one undo step; others in the database
+        driver.app.toggle_task(0);
+        assert_eq!(driver.line(1), "DONE book hall");
+        driver.app.toggle_task(1);
+        assert_eq!(driver.app.notes_mode.db.get_note(&other.id)?.map(|note| note.content).as_deref(), Some("[x] stamps"));
+        driver.app.refresh_tasks();
+        assert!(driver.app.tasks.items.iter().all(|task| task.done));
+        driver.ctrl('z').await?;
+        assert_eq!(driver.line(1), "TODO book hall");
+
+        driver.app.open_task(1)?;
+        assert_eq!((driver.line(0), driver.app.cursor_row, driver.app.cursor_col), ("[x] stamps".to_string(), 0, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_plugin_commands() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("chonk-plugin-commands-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("shout"))?;
+        std::fs::write(
+            root.join("shout").join("plugin.json"),
+            r#"{"name": "Shout", "commands": [
+                {"label": "Upper case", "run": "tr a-z A-Z", "output": "replace"},
+                {"label": "Summary", "run": "echo \"Notes on $CHONK_NOTE_TITLE\"; wc -l", "input": "note", "output": "new_note"}]}"#,
+        )?;
+        let mut driver = Driver::new()?;
+        driver.app.load_plugins(&root);
+        driver.ctrl('n').await?;
+        driver.type_text("quiet\nplease").await?;
+
+        driver.app.run_plugin_command(0, 0)?;
+        assert_eq!((driver.line(0), driver.line(1)), ("quiet".to_string(), "PLEASE".to_string()));
+        driver.ctrl('z').await?;
+        assert_eq!(driver.line(1), "please");
+
+        driver.app.run_plugin_command(0, 1)?;
+        let summary = driver.app.notes_mode.db.find_note_by_title("Notes on Untitled")?.expect("new note");
+        assert_eq!(summary.content, "Notes on Untitled\n2");
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_problems_banner() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("chonk-plugin-problems-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("broken"))?;
+        std::fs::write(root.join("broken").join("plugin.json"), r#"{"commands": []}"#)?;
+        let mut driver = Driver::new()?;
+        driver.app.load_plugins(&root);
+        std::fs::remove_dir_all(&root)?;
+
+        // A plugin that can't be read is listed instead of skipped silently; Esc hides the list
+        assert!(driver.app.plugins.is_empty());
+        assert_eq!(driver.app.config_problems.len(), 1);
+        assert!(driver.app.config_problems[0].contains("missing field `name`"));
+        driver.key(KeyCode::Esc).await?;
+        assert!(driver.app.config_problems.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_insert_command_output() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text(">\nend").await?;
+        driver.key(KeyCode::Up).await?;
+        driver.key(KeyCode::End).await?;
+
+        driver.app.insert_command_output("printf 'a\\tb\\nc\\n'");
+        assert_eq!((0..3).map(|row| driver.line(row)).collect::<Vec<_>>(), [">a    b", " c", "end"]);
+        assert_eq!((driver.app.cursor_row, driver.app.cursor_col), (1, 2));
+        // Long output asks first
+        driver.app.insert_command_output("seq 100");
+        assert_eq!(driver.line(2), "end");
+        driver.key(KeyCode::Char('y')).await?;
+        assert_eq!((driver.line(1), driver.line(101)), (" c1".to_string(), "end".to_string()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_live_reload() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("chonk-note-live-reload-{}.db", std::process::id()));
+        let db = NotesDatabase::open_at(path.clone())?;
+        let note = db.create_note("Shared".to_string(), "mine".to_string(), vec![])?;
+        let other = db.reopen()?;
+        let mut driver = Driver::with_db(db);
+        driver.app.load_note(note.clone());
+        driver.app.check_external_changes()?;
+
+        // Another process edits the open note and adds one
+        other.update_note(&note.id, "Shared".to_string(), "theirs".to_string(), vec![])?;
+        other.create_note("Synced".to_string(), String::new(), vec![])?;
+        driver.app.check_external_changes()?;
+        assert_eq!(driver.line(0), "theirs");
+        assert_eq!((driver.app.notes_list[0].title.as_str(), driver.app.notes_total), ("Synced", 2));
+
+        // Unsaved edits aren't replaced without asking
+        driver.type_text("x").await?;
+        other.update_note(&note.id, "Shared".to_string(), "again".to_string(), vec![])?;
+        driver.app.check_external_changes()?;
+        assert!(driver.app.save_conflict.is_some());
+        assert_eq!(driver.line(0), "xtheirs");
+
+        drop((driver, other));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_note_from_terminal_bytes() -> Result<()> {
+        let mut driver = Driver::new()?;
+        driver.ctrl('n').await?;
+        driver.type_text("keep").await?;
+        driver.ctrl('n').await?;
+        driver.type_text("gone").await?;
+
+        // Legacy Ctrl+D (byte 4) selects, it doesn't delete
+        driver.terminal_input(&[4]).await?;
+        assert!(driver.app.delete_confirmation_note.is_none());
+        driver.key(KeyCode::Esc).await?;
+
+        // Ctrl+Shift+D as the kitty keyboard protocol and modifyOtherKeys report it
+        driver.terminal_input(b"\x1b[100;6u").await?;
+        assert!(driver.app.delete_confirmation_note.is_some());
+        driver.terminal_input(b"\x1b[27;6;100~").await?;
+        assert_eq!(driver.app.notes_list.len(), 1);
+        assert_eq!(driver.app.notes_list[0].content, "keep");
+        Ok(())
+    }
+
+    #[test]
+    fn test_notes_list_loads_pages() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        for i in 0..130 {
+            db.create_note(format!("Note {}", i), String::new(), vec![])?;
+        }
+        let mut app = App::with_parts(NotesMode::with_db(db), Settings::default());
+        assert_eq!((app.notes_list.len(), app.notes_total), (100, 130));
+
+        // Scrolling near the end of the loaded page fetches the rest
+        app.ensure_notes_loaded(50);
+        assert_eq!(app.notes_list.len(), 100);
+        app.ensure_notes_loaded(90);
+        assert_eq!(app.notes_list.len(), 130);
+        assert_eq!(app.notes_list[129].title, "Note 0");
+
+        // The list is cached until invalidated
+        app.notes_mode.db.create_note("Late".to_string(), String::new(), vec![])?;
+        app.reload_notes_list();
+        assert_eq!(app.notes_total, 130);
+        app.invalidate_notes_list();
+        app.reload_notes_list();
+        assert_eq!((app.notes_list[0].title.as_str(), app.notes_total), ("Late", 131));
+        Ok(())
+    }
+}
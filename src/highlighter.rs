@@ -0,0 +1,112 @@
+// Coloring selections
+// Color selection... (command palette, right-click menu) opens a small palette for the block
+// selection, or the cursor's line: a text color, a highlight (background) color, bold,
+// underline, or clearing every style. Ctrl+B makes the block bold. Like a highlighter pen, a
+// paint the block's characters all have already comes off again. Only characters take styles;
+// the blanks between words stay plain. The styles are saved with the note (see note_format.rs)
+// and each paint is one undo step.
+use crate::chunked_grid::{ChunkedGrid, Style};
+use crate::config::highlighter::{HIGHLIGHT_COLORS, TEXT_COLORS};
+use crate::undo;
+
+type Rgb = (u8, u8, u8);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Paint {
+    Text(Rgb),
+    Highlight(Rgb),
+    Bold,
+    Underline,
+    /// Remove every style
+    Clear,
+}
+
+impl Paint {
+    /// Whether `style` has this paint already
+    fn is_on(self, style: Style) -> bool {
+        match self {
+            Paint::Text(color) => style.fg == Some(color),
+            Paint::Highlight(color) => style.bg == Some(color),
+            Paint::Bold => style.bold,
+            Paint::Underline => style.underline,
+            Paint::Clear => style.is_plain(),
+        }
+    }
+
+    /// `style` with this paint put on, or taken off
+    fn apply(self, mut style: Style, on: bool) -> Style {
+        match self {
+            Paint::Text(color) => style.fg = on.then_some(color),
+            Paint::Highlight(color) => style.bg = on.then_some(color),
+            Paint::Bold => style.bold = on,
+            Paint::Underline => style.underline = on,
+            Paint::Clear => style = Style::default(),
+        }
+        style
+    }
+
+    /// Name for the status line ("Red text", "Bold", ...)
+    pub fn describe(self) -> String {
+        let name = |colors: &[(&str, Rgb)], color: Rgb| {
+            colors.iter().find(|(_, c)| *c == color).map_or_else(|| format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2), |(name, _)| name.to_string())
+        };
+        match self {
+            Paint::Text(color) => format!("{} text", name(TEXT_COLORS, color)),
+            Paint::Highlight(color) => format!("{} highlight", name(HIGHLIGHT_COLORS, color)),
+            Paint::Bold => "Bold".to_string(),
+            Paint::Underline => "Underline".to_string(),
+            Paint::Clear => "Plain text".to_string(),
+        }
+    }
+}
+
+/// The undo step painting the characters of the block (first row, first col, last row,
+/// last col), or taking the paint off when they all have it. None if nothing would change.
+/// The bool says whether the paint went on.
+pub fn paint(grid: &ChunkedGrid, (first_row, first_col, last_row, last_col): (usize, usize, usize, usize), paint: Paint) -> Option<(undo::Command, bool)> {
+    let chars: Vec<(usize, usize, Style)> = (first_row..=last_row)
+        .flat_map(|row| (first_col..=last_col).map(move |col| (row, col)))
+        .filter(|&(row, col)| grid.get(row, col) != ' ')
+        .map(|(row, col)| (row, col, grid.style(row, col)))
+        .collect();
+    let on = paint == Paint::Clear || !chars.iter().all(|&(_, _, style)| paint.is_on(style));
+    let cells: Vec<_> = chars
+        .into_iter()
+        .map(|(row, col, before)| (row, col, before, paint.apply(before, on)))
+        .filter(|&(_, _, before, after)| before != after)
+        .collect();
+    (!cells.is_empty()).then_some((undo::Command::Restyle { cells }, on))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        let mut grid = ChunkedGrid::from_lines(&["a b".to_string(), "cd".to_string()]);
+        let red = TEXT_COLORS[0].1;
+        let (command, on) = paint(&grid, (0, 0, 1, 2), Paint::Text(red)).unwrap();
+        assert!(on);
+        command.execute(&mut grid);
+        assert_eq!((grid.style(0, 2).fg, grid.style(1, 1).fg, grid.style(0, 1)), (Some(red), Some(red), Style::default()));
+
+        // Bold only adds to the color; painting again takes it off
+        let (bold, _) = paint(&grid, (0, 0, 0, 2), Paint::Bold).unwrap();
+        bold.execute(&mut grid);
+        assert_eq!(grid.style(0, 0), Style { fg: Some(red), bold: true, ..Style::default() });
+        let (unbold, on) = paint(&grid, (0, 0, 0, 2), Paint::Bold).unwrap();
+        assert!(!on);
+        unbold.execute(&mut grid);
+        assert_eq!(grid.style(0, 0), Style { fg: Some(red), ..Style::default() });
+        unbold.undo(&mut grid);
+        assert!(grid.style(0, 2).bold);
+
+        let (clear, _) = paint(&grid, (0, 0, 1, 2), Paint::Clear).unwrap();
+        clear.execute(&mut grid);
+        assert!(!grid.has_styles());
+        assert!(paint(&grid, (0, 0, 1, 2), Paint::Clear).is_none());
+        assert!(paint(&grid, (5, 0, 6, 9), Paint::Bold).is_none());
+        assert_eq!(Paint::Highlight(HIGHLIGHT_COLORS[0].1).describe(), "Yellow highlight");
+    }
+}
@@ -0,0 +1,154 @@
+// Event hooks
+// Shell commands from settings.json run on note events, in order, on one background thread.
+use crate::note_format;
+use crate::notes_database::Note;
+use crate::shell;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::sync::Mutex;
+
+/// An event's commands waiting for the hook thread
+struct Job {
+    event: Event,
+    commands: Vec<String>,
+    note: Note,
+}
+
+/// Queue of the hook thread, started by the first event that has commands
+static QUEUE: Mutex<Option<mpsc::Sender<Job>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    /// The open note was saved
+    Save,
+    /// Another note was opened in the editor
+    NoteOpen,
+    /// A note was created from the editor (new, duplicate, move to new note, capture, automation)
+    Create,
+}
+
+impl Event {
+    /// Name in settings.json and CHONK_EVENT
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Save => "on_save",
+            Event::NoteOpen => "on_note_open",
+            Event::Create => "on_create",
+        }
+    }
+}
+
+/// Commands to run for each event
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    pub on_save: Vec<String>,
+    pub on_note_open: Vec<String>,
+    pub on_create: Vec<String>,
+}
+
+impl Hooks {
+    pub fn commands(&self, event: Event) -> &[String] {
+        match event {
+            Event::Save => &self.on_save,
+            Event::NoteOpen => &self.on_note_open,
+            Event::Create => &self.on_create,
+        }
+    }
+
+    /// Add another set of hooks (a plugin's) after these
+    pub fn extend(&mut self, other: &Hooks) {
+        self.on_save.extend(other.on_save.iter().cloned());
+        self.on_note_open.extend(other.on_note_open.iter().cloned());
+        self.on_create.extend(other.on_create.iter().cloned());
+    }
+
+    /// Run the event's commands for `note` in the background
+    pub fn fire(&self, event: Event, note: &Note) {
+        let commands = self.commands(event).to_vec();
+        if commands.is_empty() {
+            return;
+        }
+        let job = Job { event, commands, note: note.clone() };
+        let mut queue = QUEUE.lock().unwrap_or_else(|e| e.into_inner());
+        let job = match queue.as_ref() {
+            Some(sender) => match sender.send(job) {
+                Ok(()) => return,
+                Err(mpsc::SendError(job)) => job,
+            },
+            None => job,
+        };
+        // No thread yet (or it stopped): start one with this job first in line
+        let (sender, jobs) = mpsc::channel();
+        let _ = sender.send(job);
+        let started = std::thread::Builder::new().name("hooks".to_string()).spawn(move || {
+            for job in jobs {
+                for command in &job.commands {
+                    if let Err(e) = run(command, job.event, &job.note) {
+                        log::warn!("{} hook `{}` failed: {}", job.event.name(), command, e);
+                    }
+                }
+            }
+        });
+        match started {
+            Ok(_) => *queue = Some(sender),
+            Err(e) => log::warn!("Couldn't start the hook thread: {}", e),
+        }
+    }
+}
+
+/// Run one hook command for `note`, waiting for it to finish
+pub fn run(command: &str, event: Event, note: &Note) -> Result<()> {
+    let text = note_format::decode(&note.content).to_lines().join("\n");
+    let env = [("CHONK_EVENT", event.name()), ("CHONK_NOTE_ID", note.id.as_str()), ("CHONK_NOTE_TITLE", note.title.as_str())];
+    shell::run_with_env(command, &text, &env)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes_database::NotesDatabase;
+
+    #[test]
+    fn test_hooks() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let note = db.create_note("Journal".to_string(), "dear diary".to_string(), vec![])?;
+        let out = std::env::temp_dir().join(format!("chonk-hook-{}", std::process::id()));
+        let command = format!("{{ echo \"$CHONK_EVENT $CHONK_NOTE_TITLE\"; cat; }} > '{}'", out.display());
+        run(&command, Event::Save, &note)?;
+        assert_eq!(std::fs::read_to_string(&out)?, "on_save Journal\ndear diary");
+        std::fs::remove_file(&out)?;
+
+        assert!(run("exit 1", Event::Create, &note).is_err());
+        let hooks: Hooks = serde_json::from_str(r#"{"on_create": ["true"]}"#)?;
+        assert_eq!((hooks.commands(Event::Create).len(), hooks.commands(Event::Save).len()), (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_hooks_run_in_order() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let note = db.create_note("Journal".to_string(), String::new(), vec![])?;
+        let out = std::env::temp_dir().join(format!("chonk-hook-order-{}", std::process::id()));
+        let hooks = Hooks {
+            // The first event's hook is slow; the second's must still wait for it
+            on_create: vec![format!("sleep 0.2; echo \"$CHONK_EVENT\" >> '{}'", out.display())],
+            on_save: vec![format!("echo \"$CHONK_EVENT\" >> '{}'", out.display())],
+            ..Hooks::default()
+        };
+        hooks.fire(Event::Create, &note);
+        hooks.fire(Event::Save, &note);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        let mut written = String::new();
+        while written.lines().count() < 2 && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            written = std::fs::read_to_string(&out).unwrap_or_default();
+        }
+        std::fs::remove_file(&out)?;
+        assert_eq!(written, "on_create\non_save\n");
+        Ok(())
+    }
+}
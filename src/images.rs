@@ -0,0 +1,161 @@
+// Images pasted into notes
+// Ctrl+V with an image on the clipboard stores it as a PNG attachment of the note and writes
+// a token "[image:<filename>]" at the cursor, with empty rows opened below it. Terminals with
+// the Kitty graphics protocol show a thumbnail in those rows; elsewhere the token is the
+// placeholder. Each image is sent to the terminal once and placed again on every frame
+// (frames clear the screen, which removes placements but not the image data).
+use crate::config::images::{THUMBNAIL_MAX_COLS, THUMBNAIL_ROWS};
+use anyhow::Result;
+use std::collections::HashMap;
+
+const TOKEN_PREFIX: &str = "[image:";
+
+/// The text written into the note for an attached image
+pub fn token(filename: &str) -> String {
+    format!("{}{}]", TOKEN_PREFIX, filename)
+}
+
+/// (column, filename) of each image token in `line`
+pub fn tokens_in_line(line: &[char]) -> Vec<(usize, String)> {
+    let prefix: Vec<char> = TOKEN_PREFIX.chars().collect();
+    let mut found = Vec::new();
+    let mut col = 0;
+    while col + prefix.len() < line.len() {
+        if line[col..].starts_with(&prefix) {
+            let name_start = col + prefix.len();
+            if let Some(len) = line[name_start..].iter().position(|&c| c == ']').filter(|&len| len > 0) {
+                found.push((col, line[name_start..name_start + len].iter().collect()));
+                col = name_start + len;
+                continue;
+            }
+        }
+        col += 1;
+    }
+    found
+}
+
+/// Encode 8-bit RGBA pixels (as the clipboard hands them over) as a PNG
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(rgba)?;
+    Ok(png)
+}
+
+/// Width and height from a PNG's header
+pub fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    let word = |at: usize| png.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    (png.get(12..16) == Some(b"IHDR")).then(|| word(16).zip(word(20))).flatten()
+}
+
+/// Columns a thumbnail THUMBNAIL_ROWS tall needs to keep the image's shape (cells are
+/// about twice as tall as they are wide)
+pub fn thumbnail_cols(png: &[u8]) -> usize {
+    match png_size(png) {
+        Some((width, height)) if height > 0 => {
+            let cols = (THUMBNAIL_ROWS as u64 * 2 * width as u64).div_ceil(height as u64);
+            (cols as usize).clamp(1, THUMBNAIL_MAX_COLS)
+        }
+        _ => THUMBNAIL_MAX_COLS,
+    }
+}
+
+/// Images sent to the terminal for the open note
+pub struct Thumbnails {
+    /// filename -> (image id, columns), or None if the attachment could not be loaded
+    sent: HashMap<String, Option<(u32, usize)>>,
+    /// Images of a previous note, to free in the terminal on the next frame
+    stale: Vec<u32>,
+    next_id: u32,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        // High ids so they never collide with images other code sends without one
+        Self { sent: HashMap::new(), stale: Vec::new(), next_id: 1000 }
+    }
+
+    /// Forget the images of the previous note (they are freed in the terminal on the next frame)
+    pub fn clear(&mut self) {
+        self.stale.extend(self.sent.drain().filter_map(|(_, sent)| sent).map(|(id, _)| id));
+    }
+
+    /// Escape sequences freeing the images forgotten by `clear`
+    pub fn take_freed(&mut self) -> String {
+        self.stale.drain(..).map(|id| format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)).collect()
+    }
+
+    /// Escape sequences placing a thumbnail of `filename` with its top-left corner at screen
+    /// cell (x, y), 0-based; the image is loaded with `load` and sent the first time.
+    /// `max_cols` limits the width to what is left of the pane.
+    pub fn place(&mut self, filename: &str, x: u16, y: u16, max_cols: usize, load: impl FnOnce() -> Option<Vec<u8>>) -> String {
+        let mut output = String::new();
+        let sent = match self.sent.get(filename) {
+            Some(sent) => *sent,
+            None => {
+                let sent = load().map(|png| {
+                    let id = self.next_id;
+                    self.next_id += 1;
+                    output.push_str(&transmit(id, &png));
+                    (id, thumbnail_cols(&png))
+                });
+                self.sent.insert(filename.to_string(), sent);
+                sent
+            }
+        };
+        if let Some((id, cols)) = sent.filter(|_| max_cols > 0) {
+            output.push_str(&format!(
+                "\x1b[{};{}H\x1b_Ga=p,i={},c={},r={},C=1,q=2\x1b\\",
+                y + 1, x + 1, id, cols.min(max_cols), THUMBNAIL_ROWS
+            ));
+        }
+        output
+    }
+}
+
+/// Send a PNG to the terminal without showing it, in the chunks the protocol requires
+fn transmit(id: u32, png: &[u8]) -> String {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    const CHUNK: usize = 4096;
+
+    let encoded = STANDARD.encode(png);
+    let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(CHUNK).collect();
+    let mut output = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            output.push_str(&format!("\x1b_Ga=t,f=100,i={},q=2,m={};{}\x1b\\", id, more, chunk));
+        } else {
+            output.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_and_tokens() -> Result<()> {
+        // 4x2 red pixels
+        let png = encode_png(4, 2, &[255, 0, 0, 255].repeat(8))?;
+        assert_eq!(png_size(&png), Some((4, 2)));
+        assert_eq!(thumbnail_cols(&png), THUMBNAIL_MAX_COLS.min(THUMBNAIL_ROWS * 4));
+
+        let line: Vec<char> = format!("see {} and [image:] {}", token("pasted-1.png"), token("b.png")).chars().collect();
+        assert_eq!(tokens_in_line(&line), [(4, "pasted-1.png".to_string()), (38, "b.png".to_string())]);
+
+        let mut thumbnails = Thumbnails::new();
+        let first = thumbnails.place("pasted-1.png", 0, 1, 80, || Some(png.clone()));
+        assert!(first.starts_with("\x1b_Ga=t") && first.ends_with(&format!("\x1b[2;1H\x1b_Ga=p,i=1000,c={},r={},C=1,q=2\x1b\\", thumbnail_cols(&png), THUMBNAIL_ROWS)));
+        // Sent once, placed every time
+        assert!(thumbnails.place("pasted-1.png", 0, 1, 80, || unreachable!()).starts_with("\x1b[2;1H"));
+        thumbnails.clear();
+        assert_eq!(thumbnails.take_freed(), "\x1b_Ga=d,d=I,i=1000,q=2\x1b\\");
+        Ok(())
+    }
+}
@@ -0,0 +1,475 @@
+// Importers for notes from other apps
+// Evernote exports (.enex XML) and Apple Notes exports (HTML files, one per note, as written
+// by the common exporter apps and scripts) are turned into plain grid text: block elements
+// become line breaks, list items "- " and checkboxes "[ ]"/"[x]". Embedded and referenced
+// files are kept in the attachments table. Anything else is imported as a plain text note
+// (saved terminal output keeping its colors, see ansi.rs).
+use crate::notes_database::{Attachment, Note, NotesDatabase};
+use crate::{ansi, note_format};
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+pub struct ImportedNote {
+    pub title: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl ImportedNote {
+    fn plain(title: String, content: String) -> Self {
+        Self { title, content, tags: vec![], created_at: None, updated_at: None, attachments: vec![] }
+    }
+}
+
+/// Read notes from an .enex file, an Apple Notes export (an .html file or a folder of
+/// them), or a plain text file, and store them. Returns the new notes in file order.
+pub fn import_path(db: &NotesDatabase, path: &Path) -> Result<Vec<Note>> {
+    let imported = read_path(path)?;
+    if imported.is_empty() {
+        anyhow::bail!("No notes found in {}", path.display());
+    }
+
+    let mut notes = Vec::with_capacity(imported.len());
+    for note in imported {
+        let stored = db.create_note_at(note.title, note.content, note.tags, note.created_at, note.updated_at)?;
+        for attachment in note.attachments {
+            db.add_attachment(&stored.id, &attachment)?;
+        }
+        notes.push(stored);
+    }
+    Ok(notes)
+}
+
+fn read_path(path: &Path) -> Result<Vec<ImportedNote>> {
+    let stem = || path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "Imported".to_string());
+    if path.is_dir() {
+        return read_apple_notes_dir(path);
+    }
+    let text = std::fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    Ok(match extension.as_str() {
+        "enex" => parse_enex(&text)?,
+        "html" | "htm" => vec![parse_apple_note(&text, path.parent().unwrap_or(Path::new(".")), stem())],
+        // Saved terminal output: its colors become cell styles
+        _ if ansi::has_escapes(&text) => vec![ImportedNote::plain(stem(), note_format::encode(&ansi::to_grid(&text)))],
+        _ => vec![ImportedNote::plain(stem(), text.trim_end_matches('\n').to_string())],
+    })
+}
+
+fn read_apple_notes_dir(dir: &Path) -> Result<Vec<ImportedNote>> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("html") || e.eq_ignore_ascii_case("htm")))
+        .collect();
+    files.sort();
+
+    let mut notes = Vec::new();
+    for file in files {
+        let html = std::fs::read_to_string(&file).with_context(|| format!("Reading {}", file.display()))?;
+        let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let mut note = parse_apple_note(&html, dir, stem);
+        note.updated_at = std::fs::metadata(&file).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from);
+        notes.push(note);
+    }
+    Ok(notes)
+}
+
+/// One Apple Notes HTML export. Images are stored from data: URLs or from files next to it.
+fn parse_apple_note(html: &str, dir: &Path, fallback_title: String) -> ImportedNote {
+    let title = elements(html, "title")
+        .first()
+        .map(|e| decode_entities(e.inner).trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or(fallback_title);
+
+    let mut attachments = Vec::new();
+    for src in tag_attrs(html, "img").iter().filter_map(|attrs| attr(attrs, "src")) {
+        if let Some(data_url) = src.strip_prefix("data:") {
+            // data:image/png;base64,....
+            if let Some((mime, data)) = data_url.split_once(";base64,") {
+                if let Ok(data) = decode_base64(data) {
+                    let extension = mime.rsplit('/').next().unwrap_or("bin");
+                    attachments.push(Attachment { filename: format!("image-{}.{}", attachments.len() + 1, extension), mime: mime.to_string(), data });
+                }
+            }
+        } else if let Some(Ok(data)) = exported_file(dir, &src).map(std::fs::read) {
+            let filename = percent_decode(src.rsplit('/').next().unwrap_or(&src));
+            attachments.push(Attachment { mime: guess_mime(&filename).to_string(), filename, data });
+        }
+    }
+
+    let mut note = ImportedNote::plain(title, html_to_text(html));
+    note.attachments = attachments;
+    note
+}
+
+/// The file an <img src> of an export in `dir` refers to, if it is inside `dir`. Absolute
+/// paths, `..` and links leading out of the folder are refused, so an export can't pull in
+/// other files on the machine.
+fn exported_file(dir: &Path, src: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(percent_decode(src));
+    if !relative.components().all(|part| matches!(part, Component::Normal(_) | Component::CurDir)) {
+        log::warn!("Skipping image outside the export folder: {}", src);
+        return None;
+    }
+    let path = dir.join(relative).canonicalize().ok()?;
+    path.starts_with(dir.canonicalize().ok()?).then_some(path)
+}
+
+/// Every <note> in an Evernote export
+pub fn parse_enex(xml: &str) -> Result<Vec<ImportedNote>> {
+    if !xml.contains("<en-export") {
+        anyhow::bail!("Not an Evernote export (no <en-export> element)");
+    }
+    let mut notes = Vec::new();
+    for note in elements(xml, "note") {
+        // Look for the other fields outside the content, whose HTML could contain anything
+        let content = elements(note.inner, "content").into_iter().next();
+        let fields = match &content {
+            Some(content) => format!("{}{}", &note.inner[..content.span.start], &note.inner[content.span.end..]),
+            None => note.inner.to_string(),
+        };
+        let text = |tag: &str| elements(&fields, tag).first().map(|e| unwrap_cdata(e.inner).trim().to_string());
+
+        let mut attachments = Vec::new();
+        for resource in elements(&fields, "resource") {
+            let Some(data) = elements(resource.inner, "data").first().and_then(|e| decode_base64(e.inner).ok()) else {
+                continue;
+            };
+            let first = |tag: &str| elements(resource.inner, tag).first().map(|e| decode_entities(e.inner).trim().to_string());
+            let mime = first("mime").unwrap_or_else(|| "application/octet-stream".to_string());
+            let filename = first("file-name").unwrap_or_else(|| format!("attachment-{}", attachments.len() + 1));
+            attachments.push(Attachment { filename, mime, data });
+        }
+
+        notes.push(ImportedNote {
+            title: text("title").filter(|t| !t.is_empty()).unwrap_or_else(|| "Untitled".to_string()),
+            content: content.map(|c| html_to_text(&unwrap_cdata(c.inner))).unwrap_or_default(),
+            tags: elements(&fields, "tag").iter().map(|e| decode_entities(e.inner).trim().to_string()).collect(),
+            created_at: text("created").and_then(|t| parse_enex_time(&t)),
+            updated_at: text("updated").and_then(|t| parse_enex_time(&t)),
+            attachments,
+        });
+    }
+    Ok(notes)
+}
+
+/// ENEX timestamps look like 20231231T235959Z
+fn parse_enex_time(text: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ").ok().map(|t| t.and_utc())
+}
+
+/// Plain text for HTML (and Evernote's ENML): one line per block, list markers for items
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    // Text inside these is never shown
+    let mut hidden: Option<String> = None;
+
+    while let Some(open) = rest.find('<') {
+        if hidden.is_none() {
+            push_text(&mut out, &rest[..open]);
+        }
+        let after = &rest[open..];
+        // Comments, CDATA markers and declarations carry no text
+        let end = if after.starts_with("<!--") {
+            after.find("-->").map(|i| i + 3)
+        } else {
+            after.find('>').map(|i| i + 1)
+        };
+        let Some(end) = end else {
+            rest = "";
+            break;
+        };
+        let tag = &after[..end];
+        rest = &after[end..];
+
+        let closing = tag.starts_with("</");
+        let name: String = tag
+            .trim_start_matches(['<', '/'])
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '-')
+            .collect::<String>()
+            .to_lowercase();
+
+        if let Some(hidden_name) = &hidden {
+            if closing && name == *hidden_name {
+                hidden = None;
+            }
+            continue;
+        }
+        match name.as_str() {
+            "head" | "style" | "script" | "title" if !closing => hidden = Some(name),
+            "br" => out.push('\n'),
+            "li" if !closing => {
+                new_line(&mut out);
+                out.push_str("- ");
+            }
+            "en-todo" => {
+                let checked = attr(tag, "checked").is_some_and(|v| v == "true");
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            "en-media" | "img" => out.push_str("[attachment]"),
+            "td" | "th" if closing => out.push_str("  "),
+            "div" | "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "ul" | "ol" | "li" | "tr" | "table"
+            | "blockquote" | "pre" | "hr" | "en-note" | "body" => new_line(&mut out),
+            _ => {}
+        }
+    }
+    if hidden.is_none() {
+        push_text(&mut out, rest);
+    }
+
+    // Trailing spaces off every line, and no more than one blank line in a row
+    let mut text = String::new();
+    let mut blank_lines = 0;
+    for line in out.lines().map(str::trim_end) {
+        blank_lines = if line.is_empty() { blank_lines + 1 } else { 0 };
+        if blank_lines < 2 {
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    text.trim_matches('\n').to_string()
+}
+
+/// Append markup-free text, collapsing whitespace the way a browser would
+fn push_text(out: &mut String, raw: &str) {
+    for (i, word) in raw.split_ascii_whitespace().enumerate() {
+        let at_line_start = out.is_empty() || out.ends_with('\n');
+        if (i > 0 || raw.starts_with(|c: char| c.is_ascii_whitespace())) && !at_line_start && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        out.push_str(&decode_entities(word));
+    }
+    if raw.ends_with(|c: char| c.is_ascii_whitespace()) && !raw.trim().is_empty() {
+        out.push(' ');
+    }
+}
+
+/// Start a new line unless already at the start of one
+fn new_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+struct Element<'a> {
+    inner: &'a str,
+    /// Whole element, from the start tag to the end of the end tag
+    span: Range<usize>,
+}
+
+/// Non-nested elements named `tag`, in document order
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<Element<'a>> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = xml[from..].find(&open) {
+        let start = from + offset;
+        let after_name = &xml[start + open.len()..];
+        // <tag> or <tag attr=...>, but not <tag-other>
+        if !after_name.starts_with(|c: char| c == '>' || c.is_whitespace()) {
+            from = start + open.len();
+            continue;
+        }
+        let Some(open_end) = after_name.find('>').map(|i| start + open.len() + i + 1) else {
+            break;
+        };
+        let Some(close_start) = xml[open_end..].find(&close).map(|i| open_end + i) else {
+            break;
+        };
+        found.push(Element { inner: &xml[open_end..close_start], span: start..close_start + close.len() });
+        from = close_start + close.len();
+    }
+    found
+}
+
+/// Attribute text of every start tag named `tag`
+fn tag_attrs<'a>(html: &'a str, tag: &str) -> Vec<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", tag);
+    lower
+        .match_indices(&open)
+        .filter(|(i, _)| html[i + open.len()..].starts_with(char::is_whitespace))
+        .filter_map(|(i, _)| html[i..].find('>').map(|end| &html[i + open.len()..i + end]))
+        .collect()
+}
+
+/// Value of `name="..."` (or single-quoted) in a tag's attribute text
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(offset) = lower[from..].find(name) {
+        let at = from + offset;
+        from = at + name.len();
+        // Whole attribute names only (so "src" doesn't match "data-src")
+        if at > 0 && !lower[..at].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = attrs[at + name.len()..].trim_start().strip_prefix('=')?.trim_start();
+        let quote = value.chars().next()?;
+        if quote == '"' || quote == '\'' {
+            let value = &value[1..];
+            return value.find(quote).map(|end| decode_entities(&value[..end]));
+        }
+        let end = value.find(|c: char| c.is_whitespace() || c == '>' || c == '/').unwrap_or(value.len());
+        return Some(decode_entities(&value[..end]));
+    }
+    None
+}
+
+fn unwrap_cdata(text: &str) -> String {
+    let text = text.trim();
+    match text.strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")) {
+        Some(inner) => inner.to_string(),
+        None => decode_entities(text),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        let after = &rest[amp + 1..];
+        let decoded = after.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &after[..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((ch, end))
+        });
+        match decoded {
+            Some((ch, end)) => {
+                out.push(ch);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>> {
+    let cleaned: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    Ok(base64::engine::general_purpose::STANDARD.decode(cleaned)?)
+}
+
+/// %20 and friends in relative file links
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%').then(|| text.get(i + 1..i + 3)).flatten().and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn guess_mime(filename: &str) -> &'static str {
+    let extension = filename.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "heic" => "image/heic",
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_enex() -> Result<()> {
+        let enex = r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export>
+  <note>
+    <title>Groceries &amp; more</title>
+    <content><![CDATA[<?xml version="1.0"?><!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><div>Buy:</div><ul><li><en-todo checked="true"/>milk</li><li><en-todo/>eggs&nbsp;(12)</li></ul><div><br/></div><div>See <b>photo</b></div><en-media hash="abc" type="image/png"/></en-note>]]></content>
+    <created>20231231T235959Z</created>
+    <tag>home</tag><tag>shopping</tag>
+    <resource><data encoding="base64">aGVs
+bG8=</data><mime>image/png</mime><resource-attributes><file-name>list.png</file-name></resource-attributes></resource>
+  </note>
+</en-export>"#;
+        let notes = parse_enex(enex)?;
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.title, "Groceries & more");
+        assert_eq!(note.content, "Buy:\n- [x] milk\n- [ ] eggs (12)\n\nSee photo\n[attachment]");
+        assert_eq!(note.tags, ["home", "shopping"]);
+        assert_eq!(note.created_at.map(|t| t.to_rfc3339()).as_deref(), Some("2023-12-31T23:59:59+00:00"));
+        assert_eq!((note.attachments[0].filename.as_str(), note.attachments[0].data.as_slice()), ("list.png", &b"hello"[..]));
+
+        // Apple Notes HTML: title element, embedded image
+        let html = r#"<html><head><title>Trip</title><style>p { color: red }</style></head>
+            <body><h1>Trip</h1><p>Pack   the<br>tent</p><img src="data:image/png;base64,aGk="></body></html>"#;
+        let note = parse_apple_note(html, Path::new("."), "fallback".to_string());
+        assert_eq!(note.title, "Trip");
+        assert_eq!(note.content, "Trip\nPack the\ntent\n[attachment]");
+        assert_eq!(note.attachments[0].data, b"hi");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apple_images_stay_in_export_folder() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("chonk-apple-export-{}", std::process::id()));
+        let export = root.join("export");
+        std::fs::create_dir_all(export.join("Media"))?;
+        std::fs::write(export.join("Media").join("photo one.png"), "inside")?;
+        std::fs::write(root.join("secret"), "outside")?;
+        let outside = root.join("secret").display().to_string();
+        std::os::unix::fs::symlink(root.join("secret"), export.join("link.png"))?;
+
+        let html = format!(
+            r#"<img src="Media/photo%20one.png"><img src="../secret"><img src="Media/../../secret"><img src="{}"><img src="file://{}"><img src="link.png">"#,
+            outside, outside
+        );
+        let note = parse_apple_note(&html, &export, "Photos".to_string());
+        std::fs::remove_dir_all(&root)?;
+        assert_eq!(note.attachments.len(), 1);
+        assert_eq!((note.attachments[0].filename.as_str(), note.attachments[0].data.as_slice()), ("photo one.png", &b"inside"[..]));
+        Ok(())
+    }
+}
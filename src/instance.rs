@@ -0,0 +1,150 @@
+// Single instance
+// The first editor to start takes an exclusive lock on <data dir>/chonk-note/chonk-note.lock
+// and listens on instance.sock next to it (owner-only). Starting `chonk-note` again, or
+// `chonk-note open <title>`, finds the lock taken and hands the request to the running editor
+// over the socket instead of opening the database a second time. The running editor opens the
+// note, says so in its status bar and rings the terminal bell, since a terminal program can't
+// raise its own window. The lock is released by the OS when the process ends, so a crash
+// never leaves it behind.
+use crate::config::instance::{LOCK_NAME, SOCKET_NAME};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use tokio::sync::{mpsc, oneshot};
+
+/// The lock and socket of the running editor; the socket file is removed when dropped
+pub struct Instance {
+    _lock: File,
+    listener: Option<UnixListener>,
+    socket: PathBuf,
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
+
+/// A request from another invocation: open the note with this title (None: just come forward)
+pub struct Request {
+    pub title: Option<String>,
+    pub reply: oneshot::Sender<String>,
+}
+
+/// Take the instance lock in `dir`; None when another editor holds it
+pub fn acquire(dir: &Path) -> Result<Option<Instance>> {
+    std::fs::create_dir_all(dir)?;
+    let lock = File::options().create(true).truncate(false).write(true).open(dir.join(LOCK_NAME))?;
+    match lock.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => return Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => return Err(e).context("Locking the notes database"),
+    }
+    // A socket file left by an editor that didn't exit cleanly; the lock says nobody serves it
+    let socket = dir.join(SOCKET_NAME);
+    let _ = std::fs::remove_file(&socket);
+    let listener = bind_owner_only(&socket)?;
+    Ok(Some(Instance { _lock: lock, listener: Some(listener), socket }))
+}
+
+/// Listen on a Unix socket at `path` that only this user can connect to. The socket is bound
+/// in a fresh owner-only directory and made 0600 before it is moved into place, so it is never
+/// reachable with the umask's permissions.
+pub fn bind_owner_only(path: &Path) -> Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let dir = path.parent().context("Socket path has no directory")?;
+    let private = dir.join(format!(".sock-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&private);
+    std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+    let staged = private.join("s");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&private);
+    bound.with_context(|| format!("Listening on {}", path.display()))
+}
+
+impl Instance {
+    /// Start answering other invocations; their requests arrive on the returned channel.
+    /// Needs a Tokio runtime; only the first call starts the listener.
+    pub fn serve(&mut self) -> Result<mpsc::UnboundedReceiver<Request>> {
+        let listener = self.listener.take().context("Already serving")?;
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::UnixListener::from_std(listener)?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    continue;
+                };
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = answer(stream, &tx).await {
+                        log::warn!("Instance request failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(rx)
+    }
+}
+
+/// One request per connection: a JSON line {"title": ...}, answered with one line of text
+async fn answer(stream: tokio::net::UnixStream, requests: &mpsc::UnboundedSender<Request>) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    let (reader, mut writer) = stream.into_split();
+    let mut line = String::new();
+    tokio::io::BufReader::new(reader.take(64 * 1024)).read_line(&mut line).await?;
+    let message: serde_json::Value = serde_json::from_str(&line)?;
+    let title = message.get("title").and_then(|title| title.as_str()).map(str::to_string);
+    let (reply, response) = oneshot::channel();
+    requests.send(Request { title, reply }).map_err(|_| anyhow::anyhow!("editor has stopped"))?;
+    let response = response.await.unwrap_or_else(|_| "The editor has stopped".to_string());
+    writer.write_all(format!("{}\n", response).as_bytes()).await?;
+    Ok(())
+}
+
+/// Hand `title` (or nothing) to the editor running on `dir`; its answer
+pub fn send(dir: &Path, title: Option<&str>) -> Result<String> {
+    let socket = dir.join(SOCKET_NAME);
+    let mut stream = UnixStream::connect(&socket)
+        .with_context(|| format!("chonk-note is already running but doesn't answer on {}", socket.display()))?;
+    stream.write_all(format!("{}\n", serde_json::json!({ "title": title })).as_bytes())?;
+    let mut answer = String::new();
+    BufReader::new(stream).read_line(&mut answer)?;
+    Ok(answer.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_instance() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("chonk-note-instance-{}", std::process::id()));
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mut first = acquire(&dir)?.context("first instance")?;
+        let mode = std::os::unix::fs::PermissionsExt::mode(&std::fs::metadata(dir.join(SOCKET_NAME))?.permissions());
+        assert_eq!(mode & 0o777, 0o600);
+        let mut requests = runtime.block_on(async { first.serve() })?;
+        assert!(acquire(&dir)?.is_none());
+
+        let editor = runtime.spawn(async move {
+            let request = requests.recv().await.expect("request");
+            let _ = request.reply.send(format!("Opened {}", request.title.unwrap_or_default()));
+        });
+        assert_eq!(send(&dir, Some("Groceries"))?, "Opened Groceries");
+        runtime.block_on(editor)?;
+
+        drop(first);
+        assert!(acquire(&dir)?.is_some());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
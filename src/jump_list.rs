@@ -0,0 +1,84 @@
+// Jump list: where the cursor was before each big move (search hits, go-to-line, outline
+// headings, note switches), for Alt+Left / Alt+Right back and forward like a browser.
+// Positions are logical (relative to the canvas origin) so they survive the canvas growing.
+use crate::config::navigation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub note_id: String,
+    pub line: i64,
+    pub col: i64,
+}
+
+#[derive(Default)]
+pub struct JumpList {
+    entries: Vec<Position>,
+    /// Entry we're at while moving back and forth; entries.len() when not navigating
+    index: usize,
+}
+
+impl JumpList {
+    /// Remember `from` before a jump; positions ahead of the current one are dropped
+    pub fn record(&mut self, from: Position) {
+        self.entries.truncate(self.index);
+        if self.entries.last() != Some(&from) {
+            self.entries.push(from);
+        }
+        if self.entries.len() > navigation::JUMP_LIST_LEN {
+            self.entries.remove(0);
+        }
+        self.index = self.entries.len();
+    }
+
+    /// Position before `current`; the first step back also remembers `current` for forward
+    pub fn back(&mut self, current: Position) -> Option<Position> {
+        if self.index == self.entries.len() {
+            if self.entries.last() != Some(&current) {
+                self.entries.push(current);
+            }
+            self.index = self.entries.len() - 1;
+        }
+        self.index = self.index.checked_sub(1)?;
+        Some(self.entries[self.index].clone())
+    }
+
+    pub fn forward(&mut self) -> Option<Position> {
+        if self.index + 1 >= self.entries.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.entries[self.index].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(note: &str, line: i64) -> Position {
+        Position { note_id: note.to_string(), line, col: 0 }
+    }
+
+    #[test]
+    fn test_back_and_forward() {
+        let mut jumps = JumpList::default();
+        jumps.record(at("a", 1));
+        jumps.record(at("a", 40));
+        jumps.record(at("b", 0));
+
+        assert_eq!(jumps.back(at("b", 7)), Some(at("b", 0)));
+        assert_eq!(jumps.back(at("b", 0)), Some(at("a", 40)));
+        assert_eq!(jumps.forward(), Some(at("b", 0)));
+        assert_eq!(jumps.forward(), Some(at("b", 7)));
+        assert_eq!(jumps.forward(), None);
+
+        // A new jump from the middle of the list drops what was ahead
+        jumps.back(at("b", 7));
+        jumps.back(at("b", 0));
+        jumps.record(at("a", 41));
+        assert_eq!(jumps.forward(), None);
+        assert_eq!(jumps.back(at("c", 3)), Some(at("a", 41)));
+        assert_eq!(jumps.back(at("a", 41)), Some(at("a", 1)));
+        assert_eq!(jumps.back(at("a", 1)), None);
+    }
+}
@@ -1,10 +1,154 @@
 // Keyboard handling for chonk-note
 use crate::App;
+use crate::commands::{self, Command};
 use crate::kitty_native::{KeyCode, KeyEvent, KeyModifiers};
-use crate::config::{layout, navigation};
+use crate::overlay::Routed;
+use crate::config::navigation;
+use crate::unicode_input::{self, UnicodeInput};
 use anyhow::Result;
 
 pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
+    // F12 - Toggle performance HUD (works in every mode, even with a modal open)
+    if commands::bound_to(&key) == Some(Command::PerformanceHud) {
+        app.run_command(Command::PerformanceHud)?;
+        return Ok(true);
+    }
+
+    // A pending save conflict must be resolved before anything else
+    if app.save_conflict.is_some() {
+        match key.code {
+            KeyCode::Char('o') | KeyCode::Char('O') => app.resolve_conflict_keep_mine()?,
+            KeyCode::Char('r') | KeyCode::Char('R') => app.resolve_conflict_take_theirs(),
+            KeyCode::Char('m') | KeyCode::Char('M') => app.resolve_conflict_merge(),
+            KeyCode::Char('v') | KeyCode::Char('V') => {
+                app.conflict_diff_visible = !app.conflict_diff_visible;
+                app.conflict_diff_scroll = 0;
+            }
+            KeyCode::Up if app.conflict_diff_visible => {
+                app.conflict_diff_scroll = app.conflict_diff_scroll.saturating_sub(1);
+            }
+            KeyCode::Down if app.conflict_diff_visible => {
+                app.conflict_diff_scroll += 1;
+            }
+            KeyCode::Esc => app.conflict_diff_visible = false,
+            _ => {}
+        }
+        app.needs_redraw = true;
+        return Ok(true);
+    }
+
+    // Quit prompt after a failed save - the buffer must not be lost silently
+    if app.quit_save_error.is_some() {
+        match key.code {
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                app.quit_save_error = None;
+                app.request_quit();
+            }
+            KeyCode::Char('e') | KeyCode::Char('E') => app.quit_with_rescue_file(),
+            KeyCode::Char('q') | KeyCode::Char('Q') => app.quit_discarding_changes(),
+            KeyCode::Esc => app.quit_save_error = None,
+            _ => {}
+        }
+        app.needs_redraw = true;
+        return Ok(!app.exit_requested);
+    }
+
+    // Overlays (menus, pickers, prompts) get keys first
+    match app.overlays.handle_key(&key) {
+        Routed::Consumed => {
+            app.needs_redraw = true;
+            return Ok(true);
+        }
+        Routed::Action(action) => {
+            app.run_overlay_action(action)?;
+            return Ok(true);
+        }
+        Routed::NotHandled => {}
+    }
+
+    // Note info popup - any key closes it
+    if app.note_info_open {
+        app.note_info_open = false;
+        app.needs_redraw = true;
+        return Ok(true);
+    }
+
+    // F9 - Scratchpad (or back to the note it was opened from); F1 / Ctrl+Shift+P - command palette
+    if let Some(command @ (Command::Scratchpad | Command::Palette)) = commands::bound_to(&key) {
+        app.run_command(command)?;
+        return Ok(true);
+    }
+
+    // Choice after Ctrl+T: insert one of the stats below the selection, any other key dismisses
+    if let Some((stats, row, col)) = app.pending_column_stats.take() {
+        let value = match key.code {
+            KeyCode::Char('s') | KeyCode::Char('S') => Some(stats.sum),
+            KeyCode::Char('a') | KeyCode::Char('A') => Some(stats.average),
+            KeyCode::Char('n') | KeyCode::Char('N') => Some(stats.min),
+            KeyCode::Char('x') | KeyCode::Char('X') => Some(stats.max),
+            _ => None,
+        };
+        if let Some(value) = value {
+            let text = crate::column_stats::format_number(value);
+            app.status_message = format!("Inserted {} at {}:{}", text, row + 1, col + 1);
+            app.write_block(row, col, vec![text]);
+            app.grid.clear_selection();
+            return Ok(true);
+        }
+        app.needs_redraw = true;
+        // Fall through so the key still does its normal job
+    }
+
+    // Choice after Ctrl+E: F saves the CSV as a file, any other key dismisses
+    if let Some(csv) = app.pending_csv.take() {
+        if matches!(key.code, KeyCode::Char('f') | KeyCode::Char('F')) {
+            app.status_message = match app.export_csv_file(&csv) {
+                Ok(path) => format!("Saved CSV to {}", path.display()),
+                Err(e) => format!("CSV export failed: {}", e),
+            };
+            app.needs_redraw = true;
+            return Ok(true);
+        }
+    }
+
+    // Unicode input prompt (started with Ctrl+U or Ctrl+K)
+    if let Some(input) = app.unicode_input.take() {
+        handle_unicode_input(app, input, &key);
+        app.needs_redraw = true;
+        return Ok(true);
+    }
+
+    // If in go-to-line mode, handle prompt input
+    if app.goto_line_mode {
+        match key.code {
+            KeyCode::Enter => {
+                let query = std::mem::take(&mut app.goto_line_query);
+                app.status_message = if app.goto_line(&query) {
+                    format!("Line {}", app.cursor_row + 1)
+                } else {
+                    format!("Invalid line: {}", query)
+                };
+                app.goto_line_mode = false;
+            }
+            KeyCode::Esc => {
+                app.goto_line_mode = false;
+                app.goto_line_query.clear();
+                app.status_message = "Go to line cancelled".to_string();
+            }
+            KeyCode::Backspace => {
+                app.goto_line_query.pop();
+                app.status_message = format!("Go to line: {}", app.goto_line_query);
+            }
+            KeyCode::Char(c) if c.is_ascii_digit() || c == ':' => {
+                app.goto_line_query.push(c);
+                app.status_message = format!("Go to line: {}", app.goto_line_query);
+            }
+            _ => {}
+        }
+        app.needs_redraw = true;
+        return Ok(true);
+    }
+
     // If in search mode, handle search input
     if app.search_mode {
         match key.code {
@@ -13,8 +157,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
                 app.perform_search();
                 if !app.search_results.is_empty() {
                     let (row, col) = app.search_results[0];
-                    app.cursor_row = row;
-                    app.cursor_col = col;
+                    app.jump_to(row, col);
                     app.status_message = format!("Found {} matches", app.search_results.len());
                 } else {
                     app.status_message = "No matches found".to_string();
@@ -48,18 +191,45 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
         }
     }
 
+    // If the undo history browser is open, it captures navigation keys
+    if app.history_browser_open {
+        let entry_count = app.undo_stack.history().len();
+        match key.code {
+            KeyCode::Up => {
+                app.history_selected = app.history_selected.saturating_sub(1);
+            }
+            KeyCode::Down if app.history_selected + 1 < entry_count => {
+                app.history_selected += 1;
+            }
+            KeyCode::Enter => {
+                app.jump_to_history_entry(app.history_selected);
+                app.history_browser_open = false;
+            }
+            KeyCode::Esc => {
+                app.history_browser_open = false;
+            }
+            _ if commands::bound_to(&key) == Some(Command::UndoHistory) => {
+                app.history_browser_open = false;
+            }
+            _ => {}
+        }
+        app.needs_redraw = true;
+        return Ok(true);
+    }
+
     // If editing title, handle title editing keys
     if app.editing_title {
         match key.code {
             KeyCode::Enter => {
                 // Save title and exit editing mode
+                let title = app.title_edit.text().to_string();
                 if let Some(ref mut current_note) = app.notes_mode.current_note {
-                    current_note.title = app.title_buffer.clone();
+                    current_note.title = title.clone();
                     app.save_current_note()?;
 
                     // Update the note in the list
                     if app.selected_note_index < app.notes_list.len() {
-                        app.notes_list[app.selected_note_index].title = app.title_buffer.clone();
+                        app.notes_list[app.selected_note_index].title = title;
                     }
                 }
                 app.editing_title = false;
@@ -74,375 +244,93 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
                 app.needs_redraw = true;
                 return Ok(true);
             }
-            KeyCode::Backspace => {
-                app.title_buffer.pop();
-                app.needs_redraw = true;
-                return Ok(true);
+            KeyCode::Char(c) if c != 'a' && (key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SUPER)) => {
+                // Let Ctrl/Cmd shortcuts (other than select-all) fall through to be handled below
             }
-            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.contains(KeyModifiers::SUPER) => {
-                app.title_buffer.push(c);
+            _ => {
+                // Cursor movement, selection and typing
+                app.title_edit.handle_key(&key);
                 app.needs_redraw = true;
                 return Ok(true);
             }
-            KeyCode::Char(_) if key.modifiers.contains(KeyModifiers::CONTROL) || key.modifiers.contains(KeyModifiers::SUPER) => {
-                // Let Ctrl/Cmd shortcuts fall through to be handled below
-            }
-            _ => return Ok(true),
-        }
-    }
-
-    // Ctrl+Q - Quit
-    if key.code == KeyCode::Char('q') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.exit_requested = true;
-        return Ok(false);
-    }
-
-    // Ctrl+S - Manual save
-    if key.code == KeyCode::Char('s') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.save_current_note()?;
-        app.status_message = "Note saved".to_string();
-        app.needs_redraw = true;
-        return Ok(true);
-    }
-
-    // Ctrl+F - Search
-    if key.code == KeyCode::Char('f') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.search_mode = true;
-        app.search_query.clear();
-        app.search_results.clear();
-        app.status_message = "Search: ".to_string();
-        app.needs_redraw = true;
-        return Ok(true);
-    }
-
-    // Ctrl+G - Toggle grid lines
-    if key.code == KeyCode::Char('g') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        app.show_grid_lines = !app.show_grid_lines;
-        app.status_message = if app.show_grid_lines {
-            "Grid lines ON".to_string()
-        } else {
-            "Grid lines OFF".to_string()
-        };
-        app.needs_redraw = true;
-        return Ok(true);
-    }
-
-    // Ctrl+Z - Undo
-    if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.shift {
-        if app.undo_stack.undo(&mut app.grid) {
-            app.status_message = "Undo".to_string();
-            app.mark_dirty();
-            app.needs_redraw = true;
-        } else {
-            app.status_message = "Nothing to undo".to_string();
-            app.needs_redraw = true;
-        }
-        return Ok(true);
-    }
-
-    // Ctrl+Y or Ctrl+Shift+Z - Redo
-    if (key.code == KeyCode::Char('y') && key.modifiers.contains(KeyModifiers::CONTROL)) ||
-       (key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) && key.modifiers.shift) {
-        if app.undo_stack.redo(&mut app.grid) {
-            app.status_message = "Redo".to_string();
-            app.mark_dirty();
-            app.needs_redraw = true;
-        } else {
-            app.status_message = "Nothing to redo".to_string();
-            app.needs_redraw = true;
         }
-        return Ok(true);
     }
 
-    // Ctrl+A - Select all
-    if key.code == KeyCode::Char('a') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        // Find the bounds of all content
-        let lines = app.grid.to_lines();
-        if !lines.is_empty() {
-            let max_row = lines.len() - 1;
-            let max_col = lines.iter()
-                .map(|line| line.trim_end().len())
-                .max()
-                .unwrap_or(0)
-                .saturating_sub(1);
-
-            // Select from (0,0) to (max_row, max_col)
-            app.grid.start_selection(0, 0);
-            app.grid.update_selection(max_row, max_col);
-            app.status_message = format!("Selected all ({} rows)", lines.len());
-            app.needs_redraw = true;
-        }
-        return Ok(true);
-    }
-
-    // Ctrl+C - Copy block to system clipboard
-    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if let Some(copied) = app.grid.copy_block() {
-            app.block_clipboard = Some(copied.clone());
-
-            // Also copy to system clipboard
-            if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                let text = copied.join("\n");
-                if let Err(e) = clipboard.set_text(text) {
-                    app.status_message = format!("Copied {} rows (clipboard error: {})", copied.len(), e);
-                } else {
-                    app.status_message = format!("Copied {} rows to system clipboard", copied.len());
-                }
-            } else {
-                app.status_message = format!("Copied {} rows (clipboard unavailable)", copied.len());
+    // Several selections (Alt+S, Ctrl+Click, Ctrl+D): typing and deleting edit all of them, Left/Right/Home/End move them all
+    if app.selections.is_some() {
+        let plain = !key.modifiers.ctrl && !key.modifiers.alt && !key.modifiers.cmd;
+        let handled = plain && match key.code {
+            KeyCode::Char(c) => {
+                app.edit_selections(|selections, grid| selections.insert(grid, &c.to_string()));
+                true
             }
-
-            app.needs_redraw = true;
-        }
-        return Ok(true);
-    }
-
-    // Ctrl+X - Cut block to system clipboard
-    if key.code == KeyCode::Char('x') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if let Some(cut) = app.grid.copy_block() {
-            // Get selection bounds for undo
-            if let Some(ref sel) = app.grid.selection {
-                let (min_row, min_col, max_row, max_col) = sel.bounds();
-
-                // Collect the content that will be replaced (spaces)
-                let mut replaced_content = Vec::new();
-                for row in min_row..=max_row {
-                    let mut row_content = String::new();
-                    for col in min_col..=max_col {
-                        row_content.push(app.grid.get(row, col));
-                    }
-                    replaced_content.push(row_content);
-                }
-
-                // Create undo command for the cut operation (which replaces with spaces)
-                let cmd = crate::undo::Command::PasteBlock {
-                    row: min_row,
-                    col: min_col,
-                    lines: vec![" ".repeat(max_col - min_col + 1); max_row - min_row + 1],
-                    replaced_content,
-                };
-                app.undo_stack.push(cmd);
-
-                // Now actually cut the block
-                app.grid.cut_block();
-                app.block_clipboard = Some(cut.clone());
-
-                // Also copy to system clipboard
-                if let Ok(mut clipboard) = arboard::Clipboard::new() {
-                    let text = cut.join("\n");
-                    if let Err(e) = clipboard.set_text(text) {
-                        app.status_message = format!("Cut {} rows (clipboard error: {})", cut.len(), e);
-                    } else {
-                        app.status_message = format!("Cut {} rows to system clipboard", cut.len());
-                    }
-                } else {
-                    app.status_message = format!("Cut {} rows (clipboard unavailable)", cut.len());
-                }
-
-                app.mark_dirty();
-                app.needs_redraw = true;
+            KeyCode::Backspace => {
+                app.edit_selections(|selections, grid| selections.delete_backward(grid));
+                true
             }
-        }
-        return Ok(true);
-    }
-
-    // Ctrl+V - Paste from system clipboard
-    if key.code == KeyCode::Char('v') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        // Try system clipboard first
-        if let Ok(mut clipboard) = arboard::Clipboard::new() {
-            if let Ok(text) = clipboard.get_text() {
-                let raw_lines: Vec<String> = text.lines().map(|s| s.to_string()).collect();
-
-                // Apply word wrapping if soft_wrap_paste is enabled
-                let final_lines = if app.soft_wrap_paste {
-                    // Word wrap long lines at 150 characters
-                    const MAX_LINE_WIDTH: usize = 150;
-                    let mut wrapped_lines = Vec::new();
-
-                    for line in raw_lines {
-                        if line.chars().count() <= MAX_LINE_WIDTH {
-                            wrapped_lines.push(line);
-                        } else {
-                            // Word wrap this line
-                            let words: Vec<&str> = line.split_whitespace().collect();
-                            let mut current_line = String::new();
-
-                            for word in words {
-                                // If adding this word would exceed the limit, start a new line
-                                let test_line = if current_line.is_empty() {
-                                    word.to_string()
-                                } else {
-                                    format!("{} {}", current_line, word)
-                                };
-
-                                if test_line.chars().count() > MAX_LINE_WIDTH && !current_line.is_empty() {
-                                    // Push current line and start a new one
-                                    wrapped_lines.push(current_line.clone());
-                                    current_line = word.to_string();
-                                } else {
-                                    current_line = test_line;
-                                }
-                            }
-
-                            // Push the last line if not empty
-                            if !current_line.is_empty() {
-                                wrapped_lines.push(current_line);
-                            }
-                        }
-                    }
-                    wrapped_lines
-                } else {
-                    // No wrapping - use raw lines as-is
-                    raw_lines
-                };
-
-                // Collect replaced content for undo
-                if !final_lines.is_empty() {
-                    let mut replaced_content = Vec::new();
-                    for (line_idx, line) in final_lines.iter().enumerate() {
-                        let target_row = app.cursor_row + line_idx;
-                        let start_col = if line_idx == 0 { app.cursor_col } else { 0 };
-                        let mut row_content = String::new();
-
-                        for (col_offset, _) in line.chars().enumerate() {
-                            row_content.push(app.grid.get(target_row, start_col + col_offset));
-                        }
-                        replaced_content.push(row_content);
-                    }
-
-                    // Create undo command for paste
-                    let cmd = crate::undo::Command::PasteBlock {
-                        row: app.cursor_row,
-                        col: app.cursor_col,
-                        lines: final_lines.clone(),
-                        replaced_content,
-                    };
-                    cmd.execute(&mut app.grid);
-                    app.undo_stack.push(cmd);
-
-                    let status_suffix = if app.soft_wrap_paste { " (wrapped)" } else { "" };
-                    app.status_message = format!("Pasted {} rows{}", final_lines.len(), status_suffix);
-                    app.mark_dirty();
-                    app.needs_redraw = true;
-                    return Ok(true);
-                }
+            KeyCode::Delete => {
+                app.edit_selections(|selections, grid| selections.delete_forward(grid));
+                true
             }
-        }
-
-        // Fall back to internal block clipboard
-        if let Some(ref clipboard) = app.block_clipboard {
-            // Collect replaced content for undo
-            let mut replaced_content = Vec::new();
-            for (line_idx, line) in clipboard.iter().enumerate() {
-                let target_row = app.cursor_row + line_idx;
-                let mut row_content = String::new();
-
-                for (col_offset, _) in line.chars().enumerate() {
-                    row_content.push(app.grid.get(target_row, app.cursor_col + col_offset));
-                }
-                replaced_content.push(row_content);
+            KeyCode::Left | KeyCode::Right => {
+                let delta = if key.code == KeyCode::Left { -1 } else { 1 };
+                app.edit_selections(|selections, _| {
+                    selections.move_by(delta);
+                    Vec::new()
+                });
+                true
             }
-
-            // Create undo command for paste
-            let cmd = crate::undo::Command::PasteBlock {
-                row: app.cursor_row,
-                col: app.cursor_col,
-                lines: clipboard.clone(),
-                replaced_content,
-            };
-            cmd.execute(&mut app.grid);
-            app.undo_stack.push(cmd);
-
-            app.status_message = format!("Pasted {} rows", clipboard.len());
-            app.mark_dirty();
-            app.needs_redraw = true;
+            KeyCode::Home | KeyCode::End => {
+                app.edit_selections(|selections, grid| {
+                    selections.move_to_line_edge(grid, key.code == KeyCode::End);
+                    Vec::new()
+                });
+                true
+            }
+            _ => false,
+        };
+        if handled {
+            return Ok(true);
         }
-        return Ok(true);
-    }
-
-    // Escape - Clear selection
-    if key.code == KeyCode::Esc {
-        app.grid.clear_selection();
-        app.needs_redraw = true;
-        return Ok(true);
-    }
-
-    // Ctrl+N - Next search result (when search results exist) OR New note (when no search results)
-    if key.code == KeyCode::Char('n') && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if !app.search_results.is_empty() {
-            // Navigate to next search result
-            app.next_search_result();
-            app.status_message = format!("Match {}/{}", app.current_search_index + 1, app.search_results.len());
-            app.needs_redraw = true;
-        } else {
-            // Create new note
-            // Save current note
-            app.save_current_note()?;
-
-            // Create new note
-            let new_note = app.notes_mode.db.create_note("Untitled".to_string(), String::new(), vec![])?;
-            app.notes_mode.current_note = Some(new_note);
-
-            // Clear grid
-            app.grid.clear();
-            app.cursor_row = 0;
-            app.cursor_col = 0;
-            app.viewport_row = 0;
-            app.viewport_col = 0;
-
-            // Refresh notes list
-            if let Ok(notes) = app.notes_mode.db.list_notes(100) {
-                app.notes_list = notes;
+        // Any other key but Ctrl+D / Ctrl+Shift+L continues from the primary selection alone; Esc just does that
+        let adds_selections = match commands::bound_to(&key) {
+            // Ctrl+Shift+D (delete note) falls back to Ctrl+D's binding
+            Some(Command::SelectNextOccurrence) => !key.modifiers.shift,
+            Some(Command::SelectAllOccurrences) => true,
+            _ => false,
+        };
+        if !adds_selections {
+            app.collapse_selections();
+            if key.code == KeyCode::Esc {
+                return Ok(true);
             }
-
-            app.needs_redraw = true;
         }
-        return Ok(true);
     }
 
-    // Ctrl+P - Previous search result (only when search results exist)
-    if key.code == KeyCode::Char('p') && key.modifiers.contains(KeyModifiers::CONTROL) && !app.search_results.is_empty() {
-        app.prev_search_result();
-        app.status_message = format!("Match {}/{}", app.current_search_index + 1, app.search_results.len());
-        app.needs_redraw = true;
-        return Ok(true);
+    // Ctrl+N / Ctrl+P - Next/previous search result while there are results (Ctrl+N is New note otherwise)
+    if key.modifiers.contains(KeyModifiers::CONTROL) && !app.search_results.is_empty() {
+        let step = match key.code {
+            KeyCode::Char('n') => Some(Command::NextMatch),
+            KeyCode::Char('p') => Some(Command::PreviousMatch),
+            _ => None,
+        };
+        if let Some(command) = step {
+            app.run_command(command)?;
+            return Ok(true);
+        }
     }
 
-    // Ctrl+D - Delete current note (with confirmation)
-    if key.code == KeyCode::Char('d') && key.modifiers.contains(KeyModifiers::CONTROL) {
+    // Ctrl+Shift+D - Delete current note (with confirmation)
+    if matches!(key.code, KeyCode::Char('d') | KeyCode::Char('D')) && key.modifiers.contains(KeyModifiers::CONTROL) && key.modifiers.shift {
         if let Some(confirm_note) = app.delete_confirmation_note {
             // Second press - actually delete
             if confirm_note == app.selected_note_index && !app.notes_list.is_empty() {
-                let note_id = app.notes_list[app.selected_note_index].id.clone();
-                app.notes_mode.db.delete_note(&note_id)?;
-
-                // Refresh notes list
-                if let Ok(notes) = app.notes_mode.db.list_notes(100) {
-                    app.notes_list = notes;
-                }
-
-                // Load first note if any remain
-                if !app.notes_list.is_empty() {
-                    app.selected_note_index = 0;
-                    let first_note = &app.notes_list[0];
-                    let lines: Vec<String> = first_note.content.lines().map(|s| s.to_string()).collect();
-                    app.grid = crate::chunked_grid::ChunkedGrid::from_lines(&lines);
-                    app.notes_mode.current_note = Some(first_note.clone());
-                } else {
-                    app.grid.clear();
-                    app.notes_mode.current_note = None;
-                }
-
-                app.cursor_row = 0;
-                app.cursor_col = 0;
-                app.delete_confirmation_note = None;
-                app.status_message = "Note deleted".to_string();
-                app.needs_redraw = true;
+                app.delete_selected_note()?;
             }
         } else {
             // First press - ask for confirmation
             app.delete_confirmation_note = Some(app.selected_note_index);
-            app.status_message = "Press Ctrl+D again to delete this note".to_string();
+            app.status_message = "Press Ctrl+Shift+D again to delete this note".to_string();
             app.needs_redraw = true;
         }
 
@@ -456,72 +344,32 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
         app.needs_redraw = true;
     }
 
-    // Ctrl+Up/Down - Navigate notes
-    if key.code == KeyCode::Up && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if app.selected_note_index > 0 {
-            // Save current note
-            app.save_current_note()?;
-
-            app.selected_note_index -= 1;
-            if app.selected_note_index < app.notes_list_scroll {
-                app.notes_list_scroll = app.selected_note_index;
-            }
-
-            // Reload notes list to get fresh data
-            if let Ok(notes) = app.notes_mode.db.list_notes(100) {
-                app.notes_list = notes;
-            }
-
-            // Load selected note
-            if !app.notes_list.is_empty() {
-                let note = &app.notes_list[app.selected_note_index];
-                let lines: Vec<String> = note.content.lines().map(|s| s.to_string()).collect();
-                app.grid = crate::chunked_grid::ChunkedGrid::from_lines(&lines);
-                app.cursor_row = 0;
-                app.cursor_col = 0;
-                app.viewport_row = 0;
-                app.viewport_col = 0;
-                app.notes_mode.current_note = Some(note.clone());
-            }
+    // Every other shortcut goes through the command registry (Ctrl+Q can end the loop)
+    if let Some(command) = commands::bound_to(&key) {
+        app.run_command(command)?;
+        return Ok(!app.exit_requested);
+    }
 
-            app.needs_redraw = true;
+    // Alt+1..9 - Switch to a favorite note
+    if let KeyCode::Char(digit @ '1'..='9') = key.code {
+        if key.modifiers.alt && !key.modifiers.ctrl {
+            app.open_favorite(digit as u8 - b'0')?;
+            return Ok(true);
         }
-        return Ok(true);
     }
 
-    if key.code == KeyCode::Down && key.modifiers.contains(KeyModifiers::CONTROL) {
-        if app.selected_note_index < app.notes_list.len().saturating_sub(1) {
-            // Save current note
-            app.save_current_note()?;
-
-            app.selected_note_index += 1;
-            if app.selected_note_index >= app.notes_list_scroll + layout::VISIBLE_NOTE_COUNT_APPROX {
-                app.notes_list_scroll = app.selected_note_index - layout::VISIBLE_NOTE_COUNT_APPROX + 1;
-            }
-
-            // Reload notes list to get fresh data
-            if let Ok(notes) = app.notes_mode.db.list_notes(100) {
-                app.notes_list = notes;
-            }
-
-            // Load selected note
-            if !app.notes_list.is_empty() {
-                let note = &app.notes_list[app.selected_note_index];
-                let lines: Vec<String> = note.content.lines().map(|s| s.to_string()).collect();
-                app.grid = crate::chunked_grid::ChunkedGrid::from_lines(&lines);
-                app.cursor_row = 0;
-                app.cursor_col = 0;
-                app.viewport_row = 0;
-                app.viewport_col = 0;
-                app.notes_mode.current_note = Some(note.clone());
-            }
-
-            app.needs_redraw = true;
-        }
+    // Other Alt combinations do nothing rather than typing a character
+    if key.modifiers.contains(KeyModifiers::ALT) {
         return Ok(true);
     }
 
-
+    // Escape - Clear selection (and hide the settings problems banner)
+    if key.code == KeyCode::Esc {
+        app.grid.clear_selection();
+        app.config_problems.clear();
+        app.needs_redraw = true;
+        return Ok(true);
+    }
 
     // Arrow keys and navigation - Move cursor
     match key.code {
@@ -530,13 +378,13 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
             app.cursor_col = 0;
             app.needs_redraw = true;
         }
-        KeyCode::End => {
-            app.cursor_col = app.grid.get_line_length(app.cursor_row);
-            app.needs_redraw = true;
-        }
+        KeyCode::End => app.end_key()?,
         // Mac: Cmd+Left = Scroll viewport left
         KeyCode::Left if key.modifiers.cmd => {
-            app.viewport_col = app.viewport_col.saturating_sub(navigation::PAGE_JUMP_COLS);
+            if app.viewport_col < navigation::PAGE_JUMP_COLS {
+                app.extend_canvas(false, true);
+            }
+            app.viewport_col -= navigation::PAGE_JUMP_COLS;
             app.needs_redraw = true;
         }
         // Mac: Cmd+Right = Scroll viewport right
@@ -545,33 +393,48 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
             app.needs_redraw = true;
         }
         // PageUp/PageDown keys (also Cmd+Up/Down on Mac)
+        // Vertical moves keep heading for the column they started from (see move_to_goal_col)
         KeyCode::PageUp => {
-            app.cursor_row = app.cursor_row.saturating_sub(navigation::PAGE_JUMP_ROWS);
-            app.needs_redraw = true;
+            let goal = app.goal_col();
+            if app.cursor_row < navigation::PAGE_JUMP_ROWS {
+                app.extend_canvas(true, false);
+            }
+            app.cursor_row -= navigation::PAGE_JUMP_ROWS;
+            app.move_to_goal_col(goal);
         }
         KeyCode::PageDown => {
+            let goal = app.goal_col();
             app.cursor_row += navigation::PAGE_JUMP_ROWS;
-            app.needs_redraw = true;
+            app.move_to_goal_col(goal);
         }
         // Mac: Cmd+Up = PageUp
         KeyCode::Up if key.modifiers.contains(KeyModifiers::SUPER) => {
-            app.cursor_row = app.cursor_row.saturating_sub(navigation::PAGE_JUMP_ROWS);
-            app.needs_redraw = true;
+            let goal = app.goal_col();
+            if app.cursor_row < navigation::PAGE_JUMP_ROWS {
+                app.extend_canvas(true, false);
+            }
+            app.cursor_row -= navigation::PAGE_JUMP_ROWS;
+            app.move_to_goal_col(goal);
         }
         // Mac: Cmd+Down = PageDown
         KeyCode::Down if key.modifiers.contains(KeyModifiers::SUPER) => {
+            let goal = app.goal_col();
             app.cursor_row += navigation::PAGE_JUMP_ROWS;
-            app.needs_redraw = true;
+            app.move_to_goal_col(goal);
         }
         KeyCode::Up if !key.modifiers.contains(KeyModifiers::CONTROL) => {
-            if app.cursor_row > 0 {
-                app.cursor_row -= 1;
+            let goal = app.goal_col();
+            // Moving above the top of the canvas grows it upward
+            if app.cursor_row == 0 {
+                app.extend_canvas(true, false);
             }
-            app.needs_redraw = true;
+            app.cursor_row -= 1;
+            app.move_to_goal_col(goal);
         }
         KeyCode::Down if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+            let goal = app.goal_col();
             app.cursor_row += 1;
-            app.needs_redraw = true;
+            app.move_to_goal_col(goal);
         }
         KeyCode::Left => {
             if app.cursor_col > 0 {
@@ -580,6 +443,10 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
                 // Move to end of previous line
                 app.cursor_row -= 1;
                 app.cursor_col = app.grid.get_line_length(app.cursor_row);
+            } else {
+                // Top-left corner: grow the canvas to the left
+                app.extend_canvas(false, true);
+                app.cursor_col -= 1;
             }
             app.needs_redraw = true;
         }
@@ -597,9 +464,7 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
         KeyCode::Backspace => {
             // If there's a selection, delete it
             if app.grid.selection.is_some() {
-                app.grid.cut_block(); // Just clears the block, no copying
-                app.mark_dirty();
-                app.needs_redraw = true;
+                app.delete_selection();
                 return Ok(true);
             }
 
@@ -619,22 +484,13 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
                 app.mark_dirty();
             } else if app.cursor_row > 0 {
                 // Join with previous line (like Word)
-                let lines = app.grid.to_lines();
-
-                // Bounds check: make sure previous line exists
-                if app.cursor_row - 1 >= lines.len() {
-                    // Previous line doesn't exist, can't join
-                    app.needs_redraw = true;
-                    return Ok(true);
-                }
-
-                let current_line = if app.cursor_row < lines.len() {
-                    lines[app.cursor_row].clone()
+                let line_len = app.grid.get_line_length(app.cursor_row);
+                let current_line = if line_len > 0 {
+                    app.grid.get_line(app.cursor_row, 0, line_len - 1)
                 } else {
                     String::new()
                 };
-                let prev_line = &lines[app.cursor_row - 1];
-                let prev_line_len = prev_line.chars().count();
+                let prev_line_len = app.grid.get_line_length(app.cursor_row - 1);
 
                 // Create undo command for line deletion
                 let cmd = crate::undo::Command::DeleteLine {
@@ -653,6 +509,11 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
             app.needs_redraw = true;
         }
         KeyCode::Delete => {
+            if app.grid.selection.is_some() {
+                app.delete_selection();
+                return Ok(true);
+            }
+
             // Microsoft Word style delete - delete character at cursor
             let line_len = app.grid.get_line_length(app.cursor_row);
 
@@ -673,50 +534,73 @@ pub async fn handle_input(app: &mut App, key: KeyEvent) -> Result<bool> {
             // This is less common than character deletion and can stay as-is for now
             app.needs_redraw = true;
         }
+        KeyCode::Tab => {
+            app.tab(key.modifiers.shift);
+        }
         KeyCode::Enter => {
             // Microsoft Word style enter - insert new line and move content after cursor down
-            let lines = app.grid.to_lines();
-            let current_line = if app.cursor_row < lines.len() {
-                lines[app.cursor_row].clone()
-            } else {
-                String::new()
-            };
-
-            let chars: Vec<char> = current_line.chars().collect();
-            let text_after_cursor: String = chars.iter().skip(app.cursor_col).collect();
-
-            // Create undo command for newline insertion
-            let cmd = crate::undo::Command::InsertNewLine {
-                row: app.cursor_row,
-                col: app.cursor_col,
-                text_after_cursor: text_after_cursor.clone(),
-            };
-            cmd.execute(&mut app.grid);
-            app.undo_stack.push(cmd);
-
-            // Move cursor to beginning of new line
-            app.cursor_row += 1;
-            app.cursor_col = 0;
-
-            app.mark_dirty();
-            app.needs_redraw = true;
+            app.insert_newline();
         }
         KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) && !key.modifiers.contains(KeyModifiers::SUPER) => {
-            // Create undo command for character insertion
-            let cmd = crate::undo::Command::InsertChar {
-                row: app.cursor_row,
-                col: app.cursor_col,
-                ch: c,
-            };
-            cmd.execute(&mut app.grid);
-            app.undo_stack.push(cmd);
-
-            app.cursor_col += 1;
-            app.mark_dirty();
-            app.needs_redraw = true;
+            app.insert_char(c);
         }
         _ => {}
     }
 
     Ok(true)
 }
+
+/// One key of the Unicode input prompt; puts the prompt back in `app` unless it finished
+fn handle_unicode_input(app: &mut App, input: UnicodeInput, key: &KeyEvent) {
+    if key.code == KeyCode::Esc {
+        app.status_message = "Unicode input cancelled".to_string();
+        return;
+    }
+
+    match input {
+        UnicodeInput::CodePoint(mut hex) => {
+            match key.code {
+                KeyCode::Enter | KeyCode::Char(' ') => {
+                    match unicode_input::parse_code_point(&hex) {
+                        Some(ch) => {
+                            app.insert_char(ch);
+                            app.status_message = format!("Inserted U+{:04X} {}", ch as u32, ch);
+                        }
+                        None => app.status_message = format!("Invalid code point U+{}", hex),
+                    }
+                    return;
+                }
+                KeyCode::Backspace => {
+                    hex.pop();
+                }
+                KeyCode::Char(c) if c.is_ascii_hexdigit() && hex.len() < unicode_input::MAX_HEX_DIGITS => {
+                    hex.push(c.to_ascii_uppercase());
+                }
+                _ => {}
+            }
+            // Preview the character while typing
+            let preview = unicode_input::parse_code_point(&hex).map(|c| format!("  {}", c)).unwrap_or_default();
+            app.status_message = format!("Unicode U+{}{}", hex, preview);
+            app.unicode_input = Some(UnicodeInput::CodePoint(hex));
+        }
+        UnicodeInput::Digraph(first) => {
+            let KeyCode::Char(c) = key.code else {
+                app.unicode_input = Some(UnicodeInput::Digraph(first));
+                return;
+            };
+            match first {
+                None => {
+                    app.status_message = format!("Digraph: {}", c);
+                    app.unicode_input = Some(UnicodeInput::Digraph(Some(c)));
+                }
+                Some(first) => match unicode_input::digraph(first, c) {
+                    Some(ch) => {
+                        app.insert_char(ch);
+                        app.status_message = format!("Inserted {} ({}{})", ch, first, c);
+                    }
+                    None => app.status_message = format!("Unknown digraph {}{}", first, c),
+                },
+            }
+        }
+    }
+}
@@ -19,6 +19,7 @@ pub enum KeyCode {
     End,
     PageUp,
     PageDown,
+    F(u8),
 }
 
 // Mouse events support
@@ -38,7 +39,6 @@ pub struct MouseEvent {
     pub button: Option<MouseButton>,
     pub x: u16,
     pub y: u16,
-    #[allow(dead_code)]
     pub modifiers: KeyModifiers,
     pub is_press: bool,  // true = press, false = release
     pub is_drag: bool,
@@ -61,6 +61,7 @@ pub struct KeyModifiers {
 
 impl KeyModifiers {
     pub const CONTROL: Self = KeyModifiers { ctrl: true, alt: false, shift: false, cmd: false };
+    pub const ALT: Self = KeyModifiers { ctrl: false, alt: true, shift: false, cmd: false };
     pub const SUPER: Self = KeyModifiers { ctrl: false, alt: false, shift: false, cmd: true };
 
     pub fn contains(&self, other: KeyModifiers) -> bool {
@@ -79,6 +80,15 @@ pub struct KeyEvent {
 
 pub struct KittyTerminal;
 
+/// Stdin as a raw fd, so the event loop can wait for it to become readable
+pub struct StdinFd;
+
+impl std::os::fd::AsRawFd for StdinFd {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        libc::STDIN_FILENO
+    }
+}
+
 // Static buffer for incomplete escape sequences
 use std::sync::Mutex;
 static INPUT_BUFFER: Mutex<Vec<u8>> = Mutex::new(Vec::new());
@@ -94,7 +104,15 @@ impl KittyTerminal {
         // Enable mouse tracking
         print!("\x1b[?1000h");  // Enable mouse tracking (this should grab the mouse)
         print!("\x1b[?1002h");  // Enable mouse drag tracking
-        print!("\x1b[?1006h");  // Enable SGR mouse mode (extended coordinates)
+        if crate::term_caps::current().sgr_mouse {
+            print!("\x1b[?1003h");  // Enable any-motion tracking (menu hover)
+            print!("\x1b[?1006h");  // Enable SGR mouse mode (extended coordinates)
+        }
+
+        // Report keys the legacy bytes can't tell apart (Ctrl+Shift+letter, Ctrl+Enter);
+        // terminals that support neither ignore these
+        print!("\x1b[>1u");     // Push kitty keyboard protocol: disambiguate escape codes
+        print!("\x1b[>4;2m");   // Enable xterm modifyOtherKeys
 
         io::stdout().flush()?;
 
@@ -103,7 +121,10 @@ impl KittyTerminal {
     }
 
     pub fn exit_fullscreen() -> Result<(), io::Error> {
+        print!("\x1b[>4m");     // Reset xterm modifyOtherKeys
+        print!("\x1b[<u");      // Pop kitty keyboard protocol
         print!("\x1b[?1006l");  // Disable SGR mouse mode
+        print!("\x1b[?1003l");  // Disable any-motion tracking
         print!("\x1b[?1002l");  // Disable mouse drag tracking
         print!("\x1b[?1000l");  // Disable mouse tracking
         print!("\x1b[?25h");    // Show cursor
@@ -285,25 +306,22 @@ impl KittyTerminal {
         Self::parse_input_with_remainder(&bytes, &mut buffer_guard)
     }
 
-    // Parse input with remainder handling for multiple events
-    fn parse_input_with_remainder(bytes: &[u8], buffer: &mut Vec<u8>) -> Result<Option<InputEvent>, io::Error> {
-        if bytes.is_empty() {
-            return Ok(None);
-        }
-
-        // Try to parse an event and calculate how many bytes it consumed
-        let (event, consumed) = Self::parse_single_event(bytes)?;
-
-        if consumed > 0 {
-            // Remove consumed bytes from buffer
-            if consumed < buffer.len() {
-                *buffer = buffer[consumed..].to_vec();
-            } else {
-                buffer.clear();
+    // Parse input with remainder handling for multiple events. Sequences that parse to no
+    // event (unrecognized keys) are dropped and parsing goes on, so None means the buffer is
+    // empty or holds only the start of a sequence whose rest hasn't arrived.
+    pub(crate) fn parse_input_with_remainder(bytes: &[u8], buffer: &mut Vec<u8>) -> Result<Option<InputEvent>, io::Error> {
+        let mut start = 0;
+        while start < bytes.len() {
+            // Try to parse an event and calculate how many bytes it consumed
+            let (event, consumed) = Self::parse_single_event(&bytes[start..])?;
+            start += consumed;
+            if event.is_some() || consumed == 0 {
+                *buffer = bytes[start..].to_vec();
+                return Ok(event);
             }
         }
-
-        Ok(event)
+        buffer.clear();
+        Ok(None)
     }
 
     fn parse_single_event(bytes: &[u8]) -> Result<(Option<InputEvent>, usize), io::Error> {
@@ -318,9 +336,7 @@ impl KittyTerminal {
                 let sequence_end = 3 + end_pos + 1;  // +3 for ESC[<, +1 to include M/m
                 let sequence = &bytes[..sequence_end];
 
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[PARSE_SINGLE] SGR sequence found, length={}", sequence_end).ok();
-                }
+                log::trace!("[PARSE_SINGLE] SGR sequence found, length={}", sequence_end);
 
                 // Parse just this one sequence
                 if let Some(event) = Self::parse_sgr_mouse_single(sequence)? {
@@ -331,6 +347,19 @@ impl KittyTerminal {
             return Ok((None, 0));
         }
 
+        // Legacy X10 mouse report (terminals without SGR mode): CSI M Cb Cx Cy, each offset by 32
+        if bytes.len() >= 3 && bytes[0] == 27 && bytes[1] == b'[' && bytes[2] == b'M' {
+            if bytes.len() < 6 {
+                return Ok((None, 0));
+            }
+            let button_code = bytes[3].saturating_sub(32) as u32;
+            let x = bytes[4].saturating_sub(33) as u16;
+            let y = bytes[5].saturating_sub(33) as u16;
+            // Releases don't say which button was let go
+            let is_press = button_code & 3 != 3 || button_code & (32 | 64) != 0;
+            return Ok((Some(Self::mouse_event(button_code, x, y, is_press)), 6));
+        }
+
         // Fall back to old parse_input logic for non-mouse events
         Self::parse_keyboard_input(bytes)
     }
@@ -351,7 +380,8 @@ impl KittyTerminal {
         match bytes {
             // Special keys FIRST (before control character parsing)
             [13, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Enter, modifiers })), 1)),
-            [127, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Backspace, modifiers })), 1)),
+            // Backspace is DEL, or ^H in terminals set up that way (and Ctrl+Backspace in some)
+            [127 | 8, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Backspace, modifiers })), 1)),
             [9, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Tab, modifiers })), 1)),
             [27] if bytes.len() == 1 => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Esc, modifiers })), 1)),
 
@@ -363,7 +393,7 @@ impl KittyTerminal {
                 })), 1))
             }
 
-            // Control characters (excluding Enter=13, Tab=9 which are handled above)
+            // Control characters (excluding Enter=13, Tab=9 and Backspace=8 which are handled above)
             [b, ..] if *b >= 1 && *b <= 26 && *b != 13 && *b != 9 => {
                 modifiers.ctrl = true;
                 let ch = (*b - 1 + b'a') as char;
@@ -384,101 +414,19 @@ impl KittyTerminal {
                 })), 4))
             }
 
-            // Arrow keys
-            [27, 91, 65, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Up, modifiers })), 3)),
-            [27, 91, 66, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Down, modifiers })), 3)),
-            [27, 91, 68, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Left, modifiers })), 3)),
-            [27, 91, 67, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Right, modifiers })), 3)),
-
-            // Shift+Arrow keys (for selection)
-            [27, 91, 49, 59, 50, 65, ..] => {
-                modifiers.shift = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Up, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 50, 66, ..] => {
-                modifiers.shift = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Down, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 50, 68, ..] => {
-                modifiers.shift = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Left, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 50, 67, ..] => {
-                modifiers.shift = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Right, modifiers })), 6))
-            }
-
-            // Home/End
-            [27, 91, 72, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Home, modifiers })), 3)),
-            [27, 91, 70, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::End, modifiers })), 3)),
+            // F1-F4 (SS3 form)
+            [27, 79, b @ 80..=83, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::F(b - 79), modifiers })), 3)),
 
-            // Page Up/Down
-            [27, 91, 53, 126, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::PageUp, modifiers })), 4)),
-            [27, 91, 54, 126, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::PageDown, modifiers })), 4)),
+            // CSI sequences: arrows, Home/End, Page Up/Down, Delete, F-keys and Shift+Tab, with
+            // their modifiers; ones we don't use are consumed without an event
+            [27, 91, ..] => Ok(Self::parse_csi(bytes)),
 
-            // Delete key
-            [27, 91, 51, 126, ..] => Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Delete, modifiers })), 4)),
-
-            // Ctrl+Arrow keys (used for note navigation, but might also be trackpad gestures)
-            [27, 91, 49, 59, 53, 65, ..] => {
-                modifiers.ctrl = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Up, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 53, 66, ..] => {
-                modifiers.ctrl = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Down, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 53, 68, ..] => {
-                modifiers.ctrl = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Left, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 53, 67, ..] => {
-                modifiers.ctrl = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Right, modifiers })), 6))
-            }
-
-            // Cmd+Arrow keys (macOS, often used for home/end, page up/down)
-            [27, 91, 49, 59, 57, 65, ..] => {
-                modifiers.cmd = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Up, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 57, 66, ..] => {
-                modifiers.cmd = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Down, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 57, 68, ..] => {
-                modifiers.cmd = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Left, modifiers })), 6))
-            }
-            [27, 91, 49, 59, 57, 67, ..] => {
-                modifiers.cmd = true;
-                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Right, modifiers })), 6))
-            }
-
-            // IMPORTANT: Consume CSI sequences that we don't recognize
-            // CSI sequences: ESC [ ... (letter or ~)
-            bytes if bytes.len() >= 2 && bytes[0] == 27 && bytes[1] == b'[' => {
-                // Find the end of the CSI sequence
-                let mut consumed = 2;
-                for i in 2..bytes.len() {
-                    let b = bytes[i];
-                    // CSI sequences end with a letter (A-Z, a-z) or ~
-                    if (b >= b'A' && b <= b'Z') || (b >= b'a' && b <= b'z') || b == b'~' {
-                        consumed = i + 1;
-                        break;
-                    }
-                    // Also check for semicolons and digits which are parameter bytes
-                    if !(b == b';' || (b >= b'0' && b <= b'9')) {
-                        // Unexpected character, consume up to here
-                        consumed = i + 1;
-                        break;
-                    }
-                }
-
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log") {
-                    writeln!(file, "[PARSE_KEYBOARD] Consumed CSI sequence ({} bytes): {:?}", consumed, &bytes[..consumed]).ok();
-                }
-                Ok((None, consumed))
+            // Alt+key arrives as ESC followed by the key ('[' and 'O' start CSI/SS3 sequences);
+            // an uppercase letter means Shift was held too
+            [27, b, ..] if (33..=126).contains(b) && *b != b'[' && *b != b'O' => {
+                modifiers.alt = true;
+                modifiers.shift = b.is_ascii_uppercase();
+                Ok((Some(InputEvent::Key(KeyEvent { code: KeyCode::Char(*b as char), modifiers })), 2))
             }
 
             // Other escape sequences (ESC + something else)
@@ -486,9 +434,7 @@ impl KittyTerminal {
                 // Consume just the escape sequence, not the whole buffer
                 // For now, consume ESC + next byte
                 let consumed = if bytes.len() > 1 { 2 } else { 1 };
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log") {
-                    writeln!(file, "[PARSE_KEYBOARD] Consumed escape sequence ({} bytes): {:?}", consumed, &bytes[..consumed]).ok();
-                }
+                log::trace!("[PARSE_KEYBOARD] Consumed escape sequence ({} bytes): {:?}", consumed, &bytes[..consumed]);
                 Ok((None, consumed))
             }
 
@@ -496,6 +442,88 @@ impl KittyTerminal {
         }
     }
 
+    /// A CSI sequence (ESC [ parameters final-byte) and the bytes it takes. Modified keys
+    /// carry the modifiers as a second parameter, e.g. ESC [ 1 ; 3 A for Alt+Up. Keys the legacy
+    /// bytes can't tell apart (Ctrl+Shift+letter, Ctrl+Enter) come as ESC [ code ; modifiers u
+    /// (kitty keyboard protocol) or ESC [ 27 ; modifiers ; code ~ (xterm modifyOtherKeys).
+    fn parse_csi(bytes: &[u8]) -> (Option<InputEvent>, usize) {
+        // Parameter bytes (digits ; : < = > ?) and intermediate bytes run up to the final byte
+        let Some(end) = bytes[2..].iter().position(|&b| !(0x20..=0x3f).contains(&b)).map(|i| i + 2) else {
+            // The rest hasn't arrived yet (unless this is garbage that never ends)
+            return (None, if bytes.len() < 32 { 0 } else { bytes.len() });
+        };
+        let consumed = end + 1;
+        if !(0x40..=0x7e).contains(&bytes[end]) {
+            log::trace!("[PARSE_KEYBOARD] Malformed CSI sequence: {:?}", &bytes[..consumed]);
+            return (None, consumed);
+        }
+
+        // Numeric parameters; sub-parameters after ':' are ignored and empty ones default to 1
+        let params: Option<Vec<u32>> = std::str::from_utf8(&bytes[2..end])
+            .unwrap_or("?")
+            .split(';')
+            .map(|param| match param.split(':').next().unwrap_or("") {
+                "" => Some(1),
+                number => number.parse().ok(),
+            })
+            .collect();
+        let Some(params) = params else {
+            log::trace!("[PARSE_KEYBOARD] Consumed CSI sequence ({} bytes): {:?}", consumed, &bytes[..consumed]);
+            return (None, consumed);
+        };
+        let mut modifiers = Self::csi_modifiers(params.get(1).copied().unwrap_or(1));
+
+        let code = match (bytes[end], params[0]) {
+            (b'A', _) => Some(KeyCode::Up),
+            (b'B', _) => Some(KeyCode::Down),
+            (b'C', _) => Some(KeyCode::Right),
+            (b'D', _) => Some(KeyCode::Left),
+            (b'H', _) | (b'~', 1 | 7) => Some(KeyCode::Home),
+            (b'F', _) | (b'~', 4 | 8) => Some(KeyCode::End),
+            (b'P'..=b'S', _) => Some(KeyCode::F(bytes[end] - b'O')),
+            (b'Z', _) => {
+                modifiers.shift = true;
+                Some(KeyCode::Tab)
+            }
+            (b'u', code) => Self::csi_key_code(code, &modifiers),
+            (b'~', 27) => params.get(2).and_then(|&code| Self::csi_key_code(code, &modifiers)),
+            (b'~', 3) => Some(KeyCode::Delete),
+            (b'~', 5) => Some(KeyCode::PageUp),
+            (b'~', 6) => Some(KeyCode::PageDown),
+            (b'~', 15) => Some(KeyCode::F(5)),
+            (b'~', n @ 17..=21) => Some(KeyCode::F(n as u8 - 11)),
+            (b'~', n @ 23..=24) => Some(KeyCode::F(n as u8 - 12)),
+            _ => None,
+        };
+        if code.is_none() {
+            log::trace!("[PARSE_KEYBOARD] Consumed CSI sequence ({} bytes): {:?}", consumed, &bytes[..consumed]);
+        }
+        (code.map(|code| InputEvent::Key(KeyEvent { code, modifiers })), consumed)
+    }
+
+    /// Key of a Unicode code point in a CSI u / modifyOtherKeys sequence. Letters are reported
+    /// lowercase, so Shift+letter is made uppercase like a typed one; codes in the private use
+    /// area (keypad, media and lone modifier keys) have no key here.
+    fn csi_key_code(code: u32, modifiers: &KeyModifiers) -> Option<KeyCode> {
+        match code {
+            13 => Some(KeyCode::Enter),
+            9 => Some(KeyCode::Tab),
+            8 | 127 => Some(KeyCode::Backspace),
+            27 => Some(KeyCode::Esc),
+            0xE000..=0xF8FF => None,
+            _ => char::from_u32(code)
+                .filter(|ch| !ch.is_control())
+                .map(|ch| KeyCode::Char(if modifiers.shift { ch.to_ascii_uppercase() } else { ch })),
+        }
+    }
+
+    /// Modifiers of a CSI key: the parameter is 1 + a bit set of Shift 1, Alt 2, Ctrl 4 and
+    /// Super/Cmd 8 (higher bits, such as Caps Lock, are ignored)
+    fn csi_modifiers(param: u32) -> KeyModifiers {
+        let bits = param.saturating_sub(1);
+        KeyModifiers { shift: bits & 1 != 0, alt: bits & 2 != 0, ctrl: bits & 4 != 0, cmd: bits & 8 != 0 }
+    }
+
     // Parse SGR mouse events: CSI < button ; x ; y M/m - single event only
     fn parse_sgr_mouse_single(bytes: &[u8]) -> Result<Option<InputEvent>, io::Error> {
 
@@ -507,9 +535,7 @@ impl KittyTerminal {
             Some(idx) => idx,
             None => {
                 // Log failed parse but still consume the sequence
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[SGR_PARSE] No M/m terminator found, consuming sequence").ok();
-                }
+                log::warn!("[SGR_PARSE] No M/m terminator found, consuming sequence");
                 return Ok(None); // Consume but don't process
             }
         };
@@ -520,9 +546,7 @@ impl KittyTerminal {
             Ok(s) => s,
             Err(_) => {
                 // Log failed parse but still consume the sequence
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[SGR_PARSE] UTF8 parse failed, consuming sequence").ok();
-                }
+                log::warn!("[SGR_PARSE] UTF8 parse failed, consuming sequence");
                 return Ok(None); // Consume but don't process
             }
         };
@@ -530,9 +554,7 @@ impl KittyTerminal {
 
         if parts.len() != 3 {
             // Log failed parse but still consume the sequence
-            if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                writeln!(file, "[SGR_PARSE] Wrong number of parts: {}, consuming sequence", parts.len()).ok();
-            }
+            log::warn!("[SGR_PARSE] Wrong number of parts: {}, consuming sequence", parts.len());
             return Ok(None); // Consume but don't process
         }
 
@@ -540,9 +562,7 @@ impl KittyTerminal {
             Ok(n) => n,
             Err(_) => {
                 // Log failed parse but still consume the sequence
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[SGR_PARSE] Button code parse failed, consuming sequence").ok();
-                }
+                log::warn!("[SGR_PARSE] Button code parse failed, consuming sequence");
                 return Ok(None); // Consume but don't process
             }
         };
@@ -550,9 +570,7 @@ impl KittyTerminal {
             Ok(n) => n.saturating_sub(1), // Convert to 0-based
             Err(_) => {
                 // Log failed parse but still consume the sequence
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[SGR_PARSE] X coordinate parse failed, consuming sequence").ok();
-                }
+                log::warn!("[SGR_PARSE] X coordinate parse failed, consuming sequence");
                 return Ok(None); // Consume but don't process
             }
         };
@@ -560,13 +578,16 @@ impl KittyTerminal {
             Ok(n) => n.saturating_sub(1), // Convert to 0-based
             Err(_) => {
                 // Log failed parse but still consume the sequence
-                if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open("/Users/jack/chonker7_debug.log") {
-                    writeln!(file, "[SGR_PARSE] Y coordinate parse failed, consuming sequence").ok();
-                }
+                log::warn!("[SGR_PARSE] Y coordinate parse failed, consuming sequence");
                 return Ok(None); // Consume but don't process
             }
         };
 
+        Ok(Some(Self::mouse_event(button_code, x, y, is_press)))
+    }
+
+    /// Decode an xterm mouse button code (shared by SGR and X10 reports)
+    fn mouse_event(button_code: u32, x: u16, y: u16, is_press: bool) -> InputEvent {
         // Decode button and modifiers from button_code
         let mut modifiers = KeyModifiers {
             ctrl: false,
@@ -590,13 +611,8 @@ impl KittyTerminal {
         };
 
         // Debug logging
-        let _ = (|| -> std::io::Result<()> {
-            use std::io::Write;
-            let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-            writeln!(f, "MOUSE PARSE: button_code={} is_drag={} button_num={} is_press={} x={} y={}",
-                     button_code, is_drag, button_num, is_press, x, y)?;
-            f.flush()
-        })();
+        log::trace!("MOUSE PARSE: button_code={} is_drag={} button_num={} is_press={} x={} y={}",
+                     button_code, is_drag, button_num, is_press, x, y);
 
 
         let button = if is_drag {
@@ -617,31 +633,39 @@ impl KittyTerminal {
             }
         };
 
-        // Handle scroll separately (these have different codes)
-
-        let button = if !is_drag && button_code == 64 {
+        // Handle scroll separately (these have different codes); modifier bits are
+        // already extracted above, so Shift+wheel still reads as a wheel event
+        let wheel_code = button_code & !(4 | 8 | 16);
+        let button = if !is_drag && wheel_code == 64 {
             Some(MouseButton::ScrollUp)
-        } else if !is_drag && button_code == 65 {
+        } else if !is_drag && wheel_code == 65 {
             Some(MouseButton::ScrollDown)
-        } else if !is_drag && button_code == 66 {
+        } else if !is_drag && wheel_code == 66 {
             Some(MouseButton::ScrollLeft)
-        } else if !is_drag && button_code == 67 {
+        } else if !is_drag && wheel_code == 67 {
             Some(MouseButton::ScrollRight)
         } else {
             button
         };
 
-        let event = MouseEvent {
+        InputEvent::Mouse(MouseEvent {
             button,
             x,
             y,
             modifiers,
             is_press,
             is_drag,
-        };
+        })
+    }
 
+    /// Queue bytes read elsewhere (e.g. during capability detection) for the input parser
+    pub fn unread(bytes: &[u8]) {
+        INPUT_BUFFER.lock().unwrap().extend_from_slice(bytes);
+    }
 
-        Ok(Some(InputEvent::Mouse(event)))
+    /// True if bytes from an earlier read are still waiting to be parsed
+    pub fn has_buffered_input() -> bool {
+        !INPUT_BUFFER.lock().unwrap().is_empty()
     }
 
     // Non-blocking input check
@@ -668,6 +692,14 @@ impl KittyTerminal {
         }
     }
 
+    /// Put text on the clipboard of the terminal we're displayed in (OSC 52). Works over SSH
+    /// because the terminal, not this process, owns the clipboard; terminals that don't
+    /// support it silently ignore the sequence.
+    pub fn set_clipboard_osc52(text: &str) -> Result<(), io::Error> {
+        print!("{}", osc52_sequence(text, std::env::var_os("TMUX").is_some()));
+        io::stdout().flush()
+    }
+
     /// Display inline PNG image using Kitty graphics protocol
     /// Returns the number of terminal columns the image occupies
     pub fn display_inline_png(png_data: &[u8], cols: u16, rows: u16) -> Result<u16, io::Error> {
@@ -685,4 +717,143 @@ impl KittyTerminal {
         io::stdout().flush()?;
         Ok(cols)
     }
-}
\ No newline at end of file
+}
+
+/// OSC 52 "set clipboard" sequence; inside tmux it is wrapped in a passthrough (DCS) so it
+/// reaches the outer terminal
+fn osc52_sequence(text: &str, in_tmux: bool) -> String {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if in_tmux {
+        format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+    } else {
+        sequence
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Command;
+
+    /// The first event in `bytes` and what is left in the buffer after it
+    fn parse(bytes: &[u8]) -> (Option<InputEvent>, Vec<u8>) {
+        let mut buffer = bytes.to_vec();
+        let event = KittyTerminal::parse_input_with_remainder(bytes, &mut buffer).unwrap();
+        (event, buffer)
+    }
+
+    fn key_code(event: Option<InputEvent>) -> Option<KeyCode> {
+        match event {
+            Some(InputEvent::Key(key)) => Some(key.code),
+            _ => None,
+        }
+    }
+
+    /// Key and (ctrl, alt, shift) of the first event in `bytes`
+    fn key(bytes: &[u8]) -> Option<(KeyCode, bool, bool, bool)> {
+        match parse(bytes).0 {
+            Some(InputEvent::Key(KeyEvent { code, modifiers: m })) => Some((code, m.ctrl, m.alt, m.shift)),
+            _ => None,
+        }
+    }
+
+    /// Command a terminal's bytes for a key run (as keyboard.rs looks them up)
+    fn command(bytes: &[u8]) -> Option<Command> {
+        match parse(bytes).0 {
+            Some(InputEvent::Key(key)) => crate::commands::bound_to(&key),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_modified_csi_keys() {
+        assert_eq!(key(b"\x1b[A"), Some((KeyCode::Up, false, false, false)));
+        assert_eq!(key(b"\x1b[1;2B"), Some((KeyCode::Down, false, false, true)));
+        assert_eq!(key(b"\x1b[1;5C"), Some((KeyCode::Right, true, false, false)));
+        // Alt+Left/Right (jump list)
+        assert_eq!(key(b"\x1b[1;3D"), Some((KeyCode::Left, false, true, false)));
+        assert_eq!(key(b"\x1b[1;3C"), Some((KeyCode::Right, false, true, false)));
+        assert_eq!(key(b"\x1b[1;6H"), Some((KeyCode::Home, true, false, true)));
+        assert_eq!(key(b"\x1b[3;5~"), Some((KeyCode::Delete, true, false, false)));
+        assert_eq!(key(b"\x1b[24~"), Some((KeyCode::F(12), false, false, false)));
+        assert_eq!(key(b"\x1b[Z"), Some((KeyCode::Tab, false, false, true)));
+        let Some(InputEvent::Key(cmd_up)) = parse(b"\x1b[1;9A").0 else { panic!("no key") };
+        assert!(cmd_up.modifiers.cmd);
+
+        assert_eq!(command(b"\x1b[1;3D"), Some(Command::JumpBack));
+        assert_eq!(command(b"\x1b[1;3C"), Some(Command::JumpForward));
+        // Alt+Up/Down (move rows)
+        assert_eq!(command(b"\x1b[1;3A"), Some(Command::MoveRowsUp));
+        assert_eq!(command(b"\x1b[1;3B"), Some(Command::MoveRowsDown));
+        // Alt+Shift+Up/Down (duplicate rows)
+        assert_eq!(key(b"\x1b[1;4B"), Some((KeyCode::Down, false, true, true)));
+        assert_eq!(command(b"\x1b[1;4B"), Some(Command::DuplicateDown));
+        assert_eq!(command(b"\x1b[1;4A"), Some(Command::DuplicateUp));
+
+        // Incomplete sequences wait for the rest
+        assert!(matches!(parse(b"\x1b[1;3"), (None, rest) if rest == b"\x1b[1;3"));
+    }
+
+    #[test]
+    fn test_disambiguated_keys() {
+        // Kitty keyboard protocol (CSI u)
+        assert_eq!(key(b"\x1b[100;6u"), Some((KeyCode::Char('D'), true, false, true)));
+        assert_eq!(key(b"\x1b[97;5u"), Some((KeyCode::Char('a'), true, false, false)));
+        assert_eq!(key(b"\x1b[27u"), Some((KeyCode::Esc, false, false, false)));
+        assert_eq!(key(b"\x1b[13;5u"), Some((KeyCode::Enter, true, false, false)));
+        assert_eq!(key(b"\x1b[57399u"), None);
+        // xterm modifyOtherKeys
+        assert_eq!(key(b"\x1b[27;6;100~"), Some((KeyCode::Char('D'), true, false, true)));
+
+        // Ctrl+D selects the next occurrence; Ctrl+Shift+D (delete note) needs the protocol
+        assert_eq!(command(&[4]), Some(Command::SelectNextOccurrence));
+        assert_eq!(command(b"\x1b[100;5u"), Some(Command::SelectNextOccurrence));
+        // Ctrl+Shift+P opens the palette rather than running Ctrl+P
+        assert_eq!(command(&[16]), None);
+        assert_eq!(command(b"\x1b[112;6u"), Some(Command::Palette));
+        assert_eq!(command(b"\x1bOP"), Some(Command::Palette));
+        // ^H is Backspace, not a shortcut; the undo history is on Alt+H
+        assert_eq!(key(&[8]), Some((KeyCode::Backspace, false, false, false)));
+        assert_eq!(command(&[8]), None);
+        assert_eq!(command(b"\x1bh"), Some(Command::UndoHistory));
+        // Ctrl+Shift+L selects every occurrence rather than going to a line
+        assert_eq!(command(&[12]), Some(Command::GoToLine));
+        assert_eq!(command(b"\x1b[108;6u"), Some(Command::SelectAllOccurrences));
+        // Ctrl+Enter / Ctrl+Shift+Enter open a line instead of splitting one
+        assert_eq!(key(b"\r"), Some((KeyCode::Enter, false, false, false)));
+        assert_eq!(command(b"\x1b[13;5u"), Some(Command::OpenLineBelow));
+        assert_eq!(command(b"\x1b[13;6u"), Some(Command::OpenLineAbove));
+        assert_eq!(command(b"\x1b[27;5;13~"), Some(Command::OpenLineBelow));
+        // Alt+Shift+letter text objects, apart from the Alt+letter toggles
+        assert_eq!(key(b"\x1bW"), Some((KeyCode::Char('W'), false, true, true)));
+        assert_eq!(command(b"\x1bW"), Some(Command::SelectWord));
+        assert_eq!(command(b"\x1bL"), Some(Command::SelectLine));
+        assert_eq!(command(b"\x1bP"), Some(Command::SelectParagraph));
+        assert_eq!(command(b"\x1bw"), Some(Command::ToggleWhitespace));
+        assert_eq!(command(b"\x1bl"), Some(Command::ToggleLogNote));
+        // Alt+R reveals a path (Alt+Shift+O's ESC O starts an SS3 key instead)
+        assert_eq!(command(b"\x1br"), Some(Command::RevealPath));
+        assert_eq!(command(b"\x1bo"), Some(Command::OpenLink));
+        // Ctrl+Shift+A selects the block under the cursor, Ctrl+A everything
+        assert_eq!(command(&[1]), Some(Command::SelectAll));
+        assert_eq!(command(b"\x1b[97;6u"), Some(Command::SelectIsland));
+        assert_eq!(command(b"\x1b[27;6;97~"), Some(Command::SelectIsland));
+    }
+
+    #[test]
+    fn test_unrecognized_sequences_are_skipped() {
+        // An unknown sequence doesn't hold back the keys after it
+        let (event, rest) = parse(b"\x1b[99xab");
+        assert_eq!((key_code(event), rest), (Some(KeyCode::Char('a')), b"b".to_vec()));
+        let (event, rest) = parse(b"\x1b[99x");
+        assert!(event.is_none() && rest.is_empty());
+    }
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52_sequence("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
@@ -0,0 +1,169 @@
+// Single-line text editor shared by note title editing and overlay prompts
+// Cursor and selection anchor are character indices. Left/Right and Home/End move the
+// cursor (Shift extends a selection), Ctrl+A selects everything, and typing, Backspace or
+// Delete replace the selection.
+use crate::kitty_native::{KeyCode, KeyEvent};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LineEdit {
+    text: String,
+    /// Character index of the caret (0..=len)
+    cursor: usize,
+    /// Other end of the selection, if any
+    anchor: Option<usize>,
+}
+
+impl LineEdit {
+    /// Start editing `text` with the caret at the end
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), cursor: text.chars().count(), anchor: None }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Take the text out, leaving the editor empty
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        self.anchor = None;
+        std::mem::take(&mut self.text)
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// Selected character range, if it isn't empty
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        (anchor != self.cursor).then(|| anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.len();
+    }
+
+    /// Move the caret (e.g. to a clicked column), optionally extending the selection
+    pub fn set_cursor(&mut self, pos: usize, extend: bool) {
+        if extend {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = pos.min(self.len());
+    }
+
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text.char_indices().nth(char_index).map(|(i, _)| i).unwrap_or(self.text.len())
+    }
+
+    /// Remove the selected text; false if nothing was selected
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        let bytes = self.byte_index(range.start)..self.byte_index(range.end);
+        self.text.replace_range(bytes, "");
+        self.cursor = range.start;
+        self.anchor = None;
+        true
+    }
+
+    pub fn insert(&mut self, ch: char) {
+        self.delete_selection();
+        let at = self.byte_index(self.cursor);
+        self.text.insert(at, ch);
+        self.cursor += 1;
+    }
+
+    /// Apply an editing key; false if the key isn't one the editor uses
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        let shift = key.modifiers.shift;
+        match key.code {
+            KeyCode::Left => match self.selection() {
+                Some(range) if !shift => self.set_cursor(range.start, false),
+                _ => self.set_cursor(self.cursor.saturating_sub(1), shift),
+            },
+            KeyCode::Right => match self.selection() {
+                Some(range) if !shift => self.set_cursor(range.end, false),
+                _ => self.set_cursor(self.cursor + 1, shift),
+            },
+            KeyCode::Home => self.set_cursor(0, shift),
+            KeyCode::End => self.set_cursor(self.len(), shift),
+            KeyCode::Backspace => {
+                if !self.delete_selection() && self.cursor > 0 {
+                    self.anchor = Some(self.cursor - 1);
+                    self.delete_selection();
+                }
+            }
+            KeyCode::Delete => {
+                if !self.delete_selection() && self.cursor < self.len() {
+                    self.anchor = Some(self.cursor + 1);
+                    self.delete_selection();
+                }
+            }
+            KeyCode::Char('a') if key.modifiers.ctrl || key.modifiers.cmd => self.select_all(),
+            KeyCode::Char(ch) if !key.modifiers.ctrl && !key.modifiers.cmd => self.insert(ch),
+            _ => return false,
+        }
+        true
+    }
+
+    /// First visible character when `width` columns are available, keeping the caret in view
+    pub fn scroll(&self, width: usize) -> usize {
+        (self.cursor + 1).saturating_sub(width.max(1))
+    }
+
+    /// The visible part of the text for a `width`-column field: selection in reverse video
+    /// and the caret as an inverted cell. `style` restores the field colors after each highlight.
+    pub fn render(&self, width: usize, style: &str) -> String {
+        let start = self.scroll(width);
+        let selection = self.selection().unwrap_or(0..0);
+        let mut out = String::from(style);
+        for (i, ch) in self.text.chars().chain(std::iter::once(' ')).enumerate().skip(start).take(width) {
+            if i == self.cursor || selection.contains(&i) {
+                out.push_str("\x1b[7m");
+                out.push(ch);
+                out.push_str("\x1b[0m");
+                out.push_str(style);
+            } else {
+                out.push(ch);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitty_native::KeyModifiers;
+
+    fn press(edit: &mut LineEdit, code: KeyCode, shift: bool) {
+        edit.handle_key(&KeyEvent { code, modifiers: KeyModifiers { ctrl: false, alt: false, shift, cmd: false } });
+    }
+
+    #[test]
+    fn test_cursor_movement_and_selection() {
+        let mut edit = LineEdit::new("hëllo");
+        press(&mut edit, KeyCode::Home, false);
+        press(&mut edit, KeyCode::Right, false);
+        press(&mut edit, KeyCode::Char('X'), false);
+        assert_eq!(edit.text(), "hXëllo");
+
+        // Shift+End selects the rest; typing replaces it
+        press(&mut edit, KeyCode::End, true);
+        assert_eq!(edit.selection(), Some(2..6));
+        press(&mut edit, KeyCode::Char('!'), false);
+        assert_eq!(edit.text(), "hX!");
+
+        press(&mut edit, KeyCode::Left, false);
+        press(&mut edit, KeyCode::Backspace, false);
+        press(&mut edit, KeyCode::Delete, false);
+        assert_eq!(edit.text(), "h");
+        assert_eq!(edit.render(4, ""), "h\x1b[7m \x1b[0m");
+    }
+}
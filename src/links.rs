@@ -0,0 +1,217 @@
+// Links in note text
+// URLs are found by scanning a row for a known scheme and taking everything up to the next
+// space, minus trailing punctuation that usually belongs to the sentence. File paths are
+// words starting with `/` or `~/` (so they can't contain spaces). The editor draws both
+// underlined; Ctrl+Click or Alt+O opens one with the system's default handler, Alt+R
+// shows a file in the file manager.
+use crate::chunked_grid::ChunkedGrid;
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+const SCHEMES: &[&str] = &["https://", "http://", "file://", "mailto:"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkKind {
+    Url,
+    /// Absolute or `~/` path
+    Path,
+}
+
+/// A link found on one row: cells start..end
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+    pub kind: LinkKind,
+    pub target: String,
+}
+
+impl Link {
+    /// What to hand to the opener: the URL, or the path with `~` expanded. Fails for a
+    /// path that doesn't exist, which the opener would not report.
+    pub fn location(&self) -> Result<String> {
+        if self.kind == LinkKind::Url {
+            return Ok(self.target.clone());
+        }
+        let path = match self.target.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().context("No home directory")?.join(rest),
+            None => std::path::PathBuf::from(&self.target),
+        };
+        anyhow::ensure!(path.exists(), "no such file");
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+/// Column ranges of the URLs in `line`, left to right
+pub fn find_urls(line: &[char]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut col = 0;
+    while col < line.len() {
+        // A scheme only counts at the start of a word, so "xhttp://" is not a link
+        let at_word_start = col == 0 || !line[col - 1].is_alphanumeric();
+        let scheme = SCHEMES.iter().find(|scheme| starts_with(&line[col..], scheme));
+        let Some(scheme) = scheme.filter(|_| at_word_start) else {
+            col += 1;
+            continue;
+        };
+        let mut end = col + line[col..].iter().take_while(|c| !c.is_whitespace() && !"<>\"`".contains(**c)).count();
+        while end > col && is_trailing_punctuation(&line[col..end]) {
+            end -= 1;
+        }
+        if end > col + scheme.chars().count() {
+            found.push((col, end));
+        }
+        col = end.max(col + 1);
+    }
+    found
+}
+
+/// Column ranges of the file paths in `line`, left to right
+pub fn find_paths(line: &[char]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut col = 0;
+    while col < line.len() {
+        // At the start of a word, so "and/or" and "1/2" are not paths; "//" is a comment, not a path
+        let at_word_start = col == 0 || line[col - 1].is_whitespace() || "([{<\"'`".contains(line[col - 1]);
+        let root = match &line[col..] {
+            ['/', next, ..] if *next != '/' && !next.is_whitespace() => 1,
+            ['~', '/', next, ..] if !next.is_whitespace() => 2,
+            _ => 0,
+        };
+        if root == 0 || !at_word_start {
+            col += 1;
+            continue;
+        }
+        let mut end = col + line[col..].iter().take_while(|c| !c.is_whitespace() && !"<>\"`".contains(**c)).count();
+        while end > col + root && is_trailing_punctuation(&line[col..end]) {
+            end -= 1;
+        }
+        if end > col + root {
+            found.push((col, end));
+        }
+        col = end.max(col + 1);
+    }
+    found
+}
+
+/// Links on `row` of the grid, left to right
+pub fn links_in_row(grid: &ChunkedGrid, row: usize) -> Vec<Link> {
+    let line: Vec<char> = (0..grid.get_line_length(row)).map(|col| grid.get(row, col)).collect();
+    let link = |(start, end), kind| Link { row, start, end, kind, target: line[start..end].iter().collect() };
+    let mut links: Vec<Link> = find_urls(&line).into_iter().map(|range| link(range, LinkKind::Url)).collect();
+    // The path part of a URL is not a path of its own
+    let paths: Vec<Link> = find_paths(&line)
+        .into_iter()
+        .filter(|&(start, end)| !links.iter().any(|url| start < url.end && url.start < end))
+        .map(|range| link(range, LinkKind::Path))
+        .collect();
+    links.extend(paths);
+    links.sort_by_key(|link| link.start);
+    links
+}
+
+/// The link covering (row, col), if any
+pub fn link_at(grid: &ChunkedGrid, row: usize, col: usize) -> Option<Link> {
+    links_in_row(grid, row).into_iter().find(|link| (link.start..link.end).contains(&col))
+}
+
+/// Hand `target` to the system's opener (open on macOS, start on Windows, xdg-open elsewhere)
+pub fn open(target: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = Command::new("xdg-open");
+
+    spawn(command.arg(target), target)
+}
+
+/// Show `path` selected in the file manager (Finder, Explorer); elsewhere open its folder
+pub fn reveal(path: &str) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = Command::new("open");
+        command.args(["-R", path]);
+        command
+    };
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("explorer");
+        command.arg(format!("/select,{}", path));
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = {
+        let mut command = Command::new("xdg-open");
+        command.arg(std::path::Path::new(path).parent().unwrap_or(std::path::Path::new("/")));
+        command
+    };
+
+    spawn(&mut command, path)
+}
+
+/// Start an opener detached from the terminal
+fn spawn(command: &mut Command, target: &str) -> Result<()> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Opening {}", target))?;
+    // Reap the opener when it exits so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn starts_with(line: &[char], prefix: &str) -> bool {
+    let prefix: Vec<char> = prefix.chars().collect();
+    line.len() >= prefix.len() && line[..prefix.len()].iter().zip(&prefix).all(|(a, b)| a.eq_ignore_ascii_case(b))
+}
+
+/// Whether the last character of `url` is sentence punctuation rather than part of the URL;
+/// a closing bracket stays when the URL opened one
+fn is_trailing_punctuation(url: &[char]) -> bool {
+    let count = |ch: char| url.iter().filter(|c| **c == ch).count();
+    match url.last() {
+        Some('.' | ',' | ';' | ':' | '!' | '?' | '\'') => true,
+        Some(')') => count(')') > count('('),
+        Some(']') => count(']') > count('['),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(line: &str) -> Vec<String> {
+        let chars: Vec<char> = line.chars().collect();
+        find_urls(&chars).into_iter().map(|(start, end)| chars[start..end].iter().collect()).collect()
+    }
+
+    #[test]
+    fn test_find_urls() {
+        assert_eq!(urls("see https://example.com/a?b=1, then"), ["https://example.com/a?b=1"]);
+        assert_eq!(urls("(http://x.org/wiki/Foo_(bar)) and <mailto:me@x.org>."), ["http://x.org/wiki/Foo_(bar)", "mailto:me@x.org"]);
+        assert_eq!(urls("xhttp://no https:// nope"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_find_paths() {
+        let chars: Vec<char> = "see ~/Documents/a.pdf, (/tmp/x) and/or 1/2 // / ~/".chars().collect();
+        let paths: Vec<String> = find_paths(&chars).into_iter().map(|(start, end)| chars[start..end].iter().collect()).collect();
+        assert_eq!(paths, ["~/Documents/a.pdf", "/tmp/x"]);
+
+        let mut grid = ChunkedGrid::from_string("https://x.org/a /etc/hosts");
+        let kinds: Vec<LinkKind> = links_in_row(&grid, 0).iter().map(|link| link.kind).collect();
+        assert_eq!(kinds, [LinkKind::Url, LinkKind::Path]);
+        grid.clear();
+        assert_eq!(link_at(&grid, 0, 0), None);
+    }
+}
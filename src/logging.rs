@@ -0,0 +1,155 @@
+// Logging subsystem: a `log` backend that writes to chonk-note.log in the
+// data directory, rotating the file once it grows past a size limit.
+use crate::config::logging::{BACKUP_COUNT, MAX_FILE_BYTES};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Environment variable that overrides the configured level (e.g. CHONK_NOTE_LOG=debug)
+pub const LEVEL_ENV_VAR: &str = "CHONK_NOTE_LOG";
+
+/// Levels offered in the Settings panel, in cycling order
+pub const LEVEL_PRESETS: &[LevelFilter] = &[
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+struct LogFile {
+    file: File,
+    path: PathBuf,
+    size: u64,
+}
+
+struct FileLogger {
+    output: Mutex<Option<LogFile>>,
+}
+
+static LOGGER: FileLogger = FileLogger { output: Mutex::new(None) };
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut guard = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(ref mut out) = *guard else {
+            return;
+        };
+
+        let line = format!(
+            "{} {:<5} [{}] {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if out.size + line.len() as u64 > MAX_FILE_BYTES {
+            if let Ok(file) = rotate(&out.path) {
+                out.file = file;
+                out.size = 0;
+            }
+        }
+        if out.file.write_all(line.as_bytes()).is_ok() {
+            out.size += line.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(ref mut out) = *self.output.lock().unwrap_or_else(|e| e.into_inner()) {
+            let _ = out.file.flush();
+        }
+    }
+}
+
+/// Default log file location: <data dir>/chonk-note/chonk-note.log
+pub fn default_path() -> Option<PathBuf> {
+    crate::data_dir::file("chonk-note.log")
+}
+
+/// Install the logger. `level` comes from settings unless CHONK_NOTE_LOG overrides it.
+pub fn init(level: LevelFilter) {
+    let level = std::env::var(LEVEL_ENV_VAR)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(level);
+
+    if let Some(path) = default_path() {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Ok(file) = OpenOptions::new().create(true).append(true).open(&path) {
+            let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+            *LOGGER.output.lock().unwrap_or_else(|e| e.into_inner()) = Some(LogFile { file, path, size });
+        }
+    }
+
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Change the level at runtime (LevelFilter::Off disables logging)
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+pub fn level() -> LevelFilter {
+    log::max_level()
+}
+
+/// Path of the active log file, if one could be opened
+pub fn log_path() -> Option<PathBuf> {
+    LOGGER
+        .output
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .map(|out| out.path.clone())
+}
+
+/// Shift path -> path.1 -> path.2 ..., dropping the oldest backup, and reopen path empty
+fn rotate(path: &Path) -> std::io::Result<File> {
+    let backup = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    let _ = std::fs::remove_file(backup(BACKUP_COUNT));
+    for n in (1..BACKUP_COUNT).rev() {
+        let _ = std::fs::rename(backup(n), backup(n + 1));
+    }
+    std::fs::rename(path, backup(1))?;
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotate_shifts_backups() {
+        let dir = std::env::temp_dir().join(format!("chonk-note-log-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.log");
+
+        for generation in 0..=BACKUP_COUNT + 1 {
+            std::fs::write(&path, generation.to_string()).unwrap();
+            rotate(&path).unwrap();
+        }
+
+        // The newest backup is .1 and only BACKUP_COUNT backups are kept
+        let newest = std::fs::read_to_string(dir.join("test.log.1")).unwrap();
+        assert_eq!(newest, (BACKUP_COUNT + 1).to_string());
+        assert!(dir.join(format!("test.log.{}", BACKUP_COUNT)).exists());
+        assert!(!dir.join(format!("test.log.{}", BACKUP_COUNT + 1)).exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
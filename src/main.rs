@@ -5,18 +5,71 @@ use std::io::{self, Write};
 // Embed hamster emoji PNG at compile time
 const HAMSTER_PNG: &[u8] = include_bytes!("../assets/hamster.png");
 
+mod alignment;
+mod ansi;
+mod archive;
+mod auto_layout;
+mod automation;
+mod backlinks;
+mod broadcast;
+mod capture_server;
+mod cli;
 mod config;
+mod csv_export;
+mod data_dir;
+mod diagnostics;
+mod diff;
+mod focus_timer;
+mod frame;
+#[cfg(test)]
+mod headless;
+mod highlighter;
+mod hooks;
+mod images;
+mod importers;
+mod instance;
+mod jump_list;
 mod keyboard;
 mod kitty_native;
+mod line_edit;
+mod links;
+mod logging;
 mod mouse;
+mod note_format;
+mod note_info;
 mod notes_database;
 mod notes_mode;
 mod chunked_grid;
+mod auto_list;
+mod column_stats;
+mod commands;
+mod outline;
+mod plugins;
+mod overlay;
+mod popup_menu;
+mod preview;
+mod primary_selection;
+mod saved_searches;
+mod selections;
+mod settings;
+mod shell;
+mod smart_views;
+mod sync;
+mod tasks;
+mod term_caps;
+mod text_objects;
+mod timestamp;
+mod transclusion;
 mod undo;
+mod unicode_input;
+mod whitespace;
+mod word_goal;
 
 use kitty_native::KittyTerminal;
 use mouse::MouseState;
-use chunked_grid::ChunkedGrid;
+use chunked_grid::{ChunkedGrid, Style};
+use commands::Command;
+use notes_database::worker::{Request, Response};
 use config::{layout, timing, colors, rgb_bg, rgb_fg};
 
 pub struct App {
@@ -27,25 +80,58 @@ pub struct App {
     pub grid: ChunkedGrid,
     pub cursor_row: usize,
     pub cursor_col: usize,
+    /// (row, col) a vertical move left the cursor at, and the column it was aiming for
+    pub goal_column: Option<(usize, usize, usize)>,
+    /// Positions before search hits, go-to-line, heading jumps and note switches (Alt+Left/Right)
+    pub jump_list: jump_list::JumpList,
+    /// Several selections edited together (Alt+S, Ctrl+Click, Ctrl+D); the cursor is at the primary one
+    pub selections: Option<selections::Selections>,
 
     // Viewport scrolling
     pub viewport_row: usize,
     pub viewport_col: usize,
+    /// Cursor position the viewport last followed; wheel scrolling only sticks until the cursor moves
+    pub viewport_anchor: (usize, usize),
+    /// Row a smooth scroll is heading to (stepped once per frame)
+    pub scroll_target_row: Option<usize>,
+    /// Until when Find cursor's crosshair shows
+    pub cursor_flash_until: Option<std::time::Instant>,
+    /// Alignment guides after a snapped click or paste, and until when they show
+    pub guides: Vec<alignment::Guide>,
+    pub guides_until: Option<std::time::Instant>,
 
     // Notes list sidebar
     pub notes_list: Vec<notes_database::Note>,
+    /// Number of notes in the database (notes_list holds the pages loaded so far)
+    pub notes_total: usize,
+    /// notes_list must be re-read (notes were created or deleted); saves update it in place
+    pub notes_list_stale: bool,
     pub selected_note_index: usize,
     pub notes_list_scroll: usize,
+    /// (slot, note id) of the favorite notes, opened with Alt+1..9
+    pub favorites: Vec<(u8, String)>,
+    /// "Edited today", "Last 7 days" and "Recently deleted" above the list when it is expanded
+    pub smart_views: smart_views::SmartViews,
     pub sidebar_expanded: bool,
     pub editing_title: bool,
-    pub title_buffer: String,
+    pub title_edit: line_edit::LineEdit,
 
     // App state
     pub status_message: String,
     pub exit_requested: bool,
+    /// Printed after the terminal is restored (e.g. where a rescue file was written)
+    pub exit_message: Option<String>,
+    /// Note text written to stdout (through $PAGER) after the terminal is restored
+    pub exit_output: Option<String>,
+    /// Save error that blocked quitting; shows the quit prompt while set
+    pub quit_save_error: Option<String>,
     pub needs_redraw: bool,
     pub show_grid_lines: bool,
+    /// Mark blanks, tabs and hidden characters in the editor (Alt+W)
+    pub show_whitespace: bool,
     pub block_clipboard: Option<Vec<String>>,
+    /// Styles of the cells in block_clipboard (empty when they are all plain)
+    pub block_clipboard_styles: Vec<Vec<Style>>,
 
     // Delete confirmation
     pub delete_confirmation_note: Option<usize>,
@@ -54,8 +140,39 @@ pub struct App {
     pub dirty: bool,
     pub last_save_time: std::time::Instant,
 
+    // Save conflict with another writer (stored version of the note)
+    pub save_conflict: Option<notes_database::Note>,
+    pub conflict_diff_visible: bool,
+    pub conflict_diff_scroll: usize,
+
     // Undo/Redo system
     pub undo_stack: undo::UndoStack,
+    pub history_browser_open: bool,
+    pub history_selected: usize,
+
+    // Persistent user settings
+    pub settings: settings::Settings,
+    /// Problems found reading settings.json and the plugins, shown in a banner until Esc
+    pub config_problems: Vec<String>,
+
+    // Go-to-line prompt
+    pub goto_line_mode: bool,
+    pub goto_line_query: String,
+
+    // Note info (statistics) popup
+    pub note_info_open: bool,
+
+    // Stats of the last Ctrl+T on a selection, waiting for an insert choice
+    pub pending_column_stats: Option<(column_stats::ColumnStats, usize, usize)>, // (stats, row, col) to insert at
+
+    // CSV from the last Ctrl+E, waiting in case F asks to save it as a file
+    pub pending_csv: Option<String>,
+
+    // Popup menus, pickers, prompts and confirmations drawn over the editor (topmost gets input)
+    pub overlays: overlay::OverlayStack<OverlayAction>,
+
+    // Unicode input (Ctrl+U code point / Ctrl+K digraph)
+    pub unicode_input: Option<unicode_input::UnicodeInput>,
 
     // Search functionality
     pub search_mode: bool,
@@ -66,60 +183,236 @@ pub struct App {
     // Menu bar and settings
     pub soft_wrap_paste: bool,
     pub notes_menu_expanded: bool,
+    /// Menu item, note or toggle under the mouse pointer
+    pub hover: Option<mouse::Hover>,
+    /// Word count of the note last shown in a sidebar tooltip: (id, updated_at, words)
+    pub tooltip_words: Option<(String, chrono::DateTime<chrono::Utc>, usize)>,
     pub settings_menu_expanded: bool,
     pub settings_panel_expanded: bool,
+
+    // Note to return to when leaving the scratchpad (F9)
+    pub scratchpad_return: Option<String>,
+
+    // Focus session counting down in the status line
+    pub focus_timer: Option<focus_timer::FocusTimer>,
+
+    // Outline (table of contents) panel
+    pub outline: outline::Outline,
+    pub outline_panel_expanded: bool,
+    /// Other notes linking to or mentioning the open note (found on the database thread)
+    pub backlinks: backlinks::Backlinks,
+    pub backlinks_panel_expanded: bool,
+    /// To-do items gathered from every note
+    pub tasks: tasks::Tasks,
+    pub tasks_panel_expanded: bool,
+    /// The open note's word count, for its word goal
+    pub word_count: word_goal::WordCount,
+
+    // Performance HUD measurements
+    pub diagnostics: diagnostics::Diagnostics,
+
+    // Screen size used instead of asking the terminal (headless runs)
+    pub fixed_term_size: Option<(u16, u16)>,
+
+    // Editor rows last sent to the terminal (low-bandwidth diff updates)
+    pub editor_rows: frame::RowCache,
+
+    // Pasted images sent to the terminal for the open note
+    pub thumbnails: images::Thumbnails,
+    /// Notes embedded with ![[Title]], read when first drawn
+    pub embeds: transclusion::Sources,
+    /// PRAGMA data_version at the last live reload check (None until the first)
+    pub db_version: Option<i64>,
+    /// Another note pinned read-only on the right
+    pub preview: Option<preview::Preview>,
+    /// Script plugins: their commands are in the command palette
+    pub plugins: Vec<plugins::Plugin>,
+    /// Event hooks from settings.json and the plugins
+    pub hooks: hooks::Hooks,
+    /// Encrypted sync with the server in settings.json
+    pub sync: sync::SyncStatus,
+    /// Follow mode: the editor's view served to viewers on the local network
+    pub broadcast: Option<broadcast::Broadcast>,
 }
 
 impl App {
     pub fn new() -> Result<Self> {
-        let mut notes_mode = notes_mode::NotesMode::new()?;
+        let (settings, problems) = settings::Settings::load_checked();
+        let mut app = Self::with_parts(notes_mode::NotesMode::new()?, settings);
+        app.config_problems = problems;
+        if let Some(dir) = plugins::dir() {
+            app.load_plugins(&dir);
+        }
+        Ok(app)
+    }
+
+    /// Add the plugins installed in `dir`: their commands and hooks
+    pub fn load_plugins(&mut self, dir: &std::path::Path) {
+        let (plugins, problems) = plugins::load_all(dir);
+        for plugin in plugins {
+            self.hooks.extend(&plugin.hooks);
+            self.plugins.push(plugin);
+        }
+        self.config_problems.extend(problems);
+    }
+
+    /// Read settings.json and the plugins again, after fixing what the banner listed. Servers
+    /// and sync keep their ports and address until the next start.
+    pub fn reload_config(&mut self) {
+        let (settings, problems) = settings::Settings::load_checked();
+        logging::set_level(settings.log_level);
+        self.undo_stack.set_max_size(settings.undo_depth);
+        self.hooks = settings.hooks.clone();
+        self.settings = settings;
+        self.config_problems = problems;
+        self.plugins.clear();
+        if let Some(dir) = plugins::dir() {
+            self.load_plugins(&dir);
+        }
+        self.status_message = match self.config_problems.len() {
+            0 => "Settings and plugins reloaded".to_string(),
+            n => format!("Settings and plugins reloaded with {} problem{}", n, if n == 1 { "" } else { "s" }),
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Build the app around an opened database and settings, opening the most recent note
+    pub fn with_parts(mut notes_mode: notes_mode::NotesMode, settings: settings::Settings) -> Self {
         let mut notes_list = Vec::new();
         let mut grid = ChunkedGrid::new();
 
+        // Notes deleted more than TRASH_DAYS ago go for good
+        let trash_cutoff = chrono::Utc::now() - chrono::Duration::days(config::notes_list::TRASH_DAYS);
+        if let Err(e) = notes_mode.db.purge_trash(trash_cutoff) {
+            log::error!("Emptying Recently deleted failed: {}", e);
+        }
+
         // Load existing notes
-        if let Ok(notes) = notes_mode.db.list_notes(100) {
+        if let Ok(notes) = notes_mode.db.list_notes(config::notes_list::PAGE_SIZE) {
             notes_list = notes;
         }
+        let notes_total = notes_mode.db.count_notes().unwrap_or(notes_list.len());
+        let favorites = notes_mode.db.favorites().unwrap_or_default();
 
-        // Load the first note if available
-        if !notes_list.is_empty() {
+        // Load the first note if available (the start screen offers a choice instead)
+        if !notes_list.is_empty() && !settings.start_screen {
             let first_note = &notes_list[0];
-            let lines: Vec<String> = first_note.content.lines().map(|s| s.to_string()).collect();
-            grid = ChunkedGrid::from_lines(&lines);
+            grid = note_format::decode(&first_note.content);
             notes_mode.current_note = Some(first_note.clone());
         }
 
-        Ok(Self {
+        let hooks = settings.hooks.clone();
+        Self {
             notes_mode,
             grid,
             cursor_row: 0,
             cursor_col: 0,
+            goal_column: None,
+            jump_list: jump_list::JumpList::default(),
+            selections: None,
             viewport_row: 0,
             viewport_col: 0,
+            viewport_anchor: (0, 0),
+            scroll_target_row: None,
+            cursor_flash_until: None,
+            guides: Vec::new(),
+            guides_until: None,
             notes_list,
+            notes_total,
+            notes_list_stale: false,
             selected_note_index: 0,
             notes_list_scroll: 0,
+            favorites,
+            smart_views: smart_views::SmartViews::new(),
             sidebar_expanded: false,
             editing_title: false,
-            title_buffer: String::new(),
+            title_edit: line_edit::LineEdit::default(),
             status_message: "Ready".to_string(),
             exit_requested: false,
+            exit_message: None,
+            exit_output: None,
+            quit_save_error: None,
+            config_problems: Vec::new(),
             needs_redraw: true,
             show_grid_lines: false,
+            show_whitespace: false,
             block_clipboard: None,
+            block_clipboard_styles: Vec::new(),
             delete_confirmation_note: None,
             dirty: false,
             last_save_time: std::time::Instant::now(),
-            undo_stack: undo::UndoStack::new(100), // Max 100 undo levels
+            save_conflict: None,
+            conflict_diff_visible: false,
+            conflict_diff_scroll: 0,
+            undo_stack: undo::UndoStack::new(settings.undo_depth),
+            history_browser_open: false,
+            history_selected: 0,
+            settings,
+            goto_line_mode: false,
+            goto_line_query: String::new(),
+            note_info_open: false,
+            pending_column_stats: None,
+            pending_csv: None,
+            overlays: overlay::OverlayStack::default(),
+            unicode_input: None,
             search_mode: false,
             search_query: String::new(),
             search_results: Vec::new(),
             current_search_index: 0,
             soft_wrap_paste: true, // Default to ON
             notes_menu_expanded: false,
+            hover: None,
+            tooltip_words: None,
             settings_menu_expanded: false,
             settings_panel_expanded: false,
-        })
+            scratchpad_return: None,
+            focus_timer: None,
+            outline: outline::Outline::new(),
+            outline_panel_expanded: false,
+            backlinks: backlinks::Backlinks::new(),
+            backlinks_panel_expanded: false,
+            tasks: tasks::Tasks::new(),
+            tasks_panel_expanded: false,
+            word_count: word_goal::WordCount::new(),
+            diagnostics: diagnostics::Diagnostics::new(),
+            fixed_term_size: None,
+            editor_rows: frame::RowCache::default(),
+            thumbnails: images::Thumbnails::new(),
+            embeds: transclusion::Sources::default(),
+            db_version: None,
+            preview: None,
+            plugins: Vec::new(),
+            hooks,
+            sync: sync::SyncStatus::new(),
+            broadcast: None,
+        }
+    }
+
+    /// Terminal size in cells (columns, rows)
+    pub fn terminal_size(&self) -> Result<(u16, u16)> {
+        match self.fixed_term_size {
+            Some(size) => Ok(size),
+            None => Ok(KittyTerminal::size()?),
+        }
+    }
+
+    /// Column Up/Down aim for: where the current run of vertical moves started
+    pub fn goal_col(&self) -> usize {
+        match self.goal_column {
+            Some((row, col, goal)) if (row, col) == (self.cursor_row, self.cursor_col) => goal,
+            _ => self.cursor_col,
+        }
+    }
+
+    /// After a vertical move: go to `goal`, or to the end of a shorter line with snap_to_line_end
+    pub fn move_to_goal_col(&mut self, goal: usize) {
+        self.cursor_col = if self.settings.snap_to_line_end {
+            goal.min(self.grid.get_line_length(self.cursor_row))
+        } else {
+            goal
+        };
+        self.goal_column = Some((self.cursor_row, self.cursor_col, goal));
+        self.needs_redraw = true;
     }
 
     /// Update viewport to keep cursor visible
@@ -131,6 +424,139 @@ impl App {
         }
     }
 
+    /// Mouse wheel: scroll the editor by `notches` wheel steps (negative = up)
+    pub fn scroll_editor(&mut self, notches: isize) {
+        let delta = notches * self.settings.scroll_step as isize;
+        // Scrolling past the top grows the canvas upward instead of stopping
+        while self.scroll_target_row.unwrap_or(self.viewport_row).checked_add_signed(delta).is_none() {
+            self.extend_canvas(true, false);
+        }
+        let from = self.scroll_target_row.unwrap_or(self.viewport_row);
+        let target = from.saturating_add_signed(delta);
+        if self.settings.smooth_scroll {
+            self.scroll_target_row = Some(target);
+        } else {
+            self.viewport_row = target;
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Advance a smooth scroll by one frame; returns true while still moving
+    pub fn step_smooth_scroll(&mut self) -> bool {
+        let Some(target) = self.scroll_target_row else {
+            return false;
+        };
+        let distance = target.abs_diff(self.viewport_row);
+        let step = (distance / config::navigation::SMOOTH_SCROLL_DIVISOR).max(1).min(distance);
+        if target > self.viewport_row {
+            self.viewport_row += step;
+        } else {
+            self.viewport_row -= step;
+        }
+        if self.viewport_row == target {
+            self.scroll_target_row = None;
+        }
+        true
+    }
+
+    /// Grow the canvas by one chunk above and/or left of the current top-left corner.
+    /// Storage coordinates stay usize, so everything (content, origin, cursor, viewport,
+    /// undo history) moves down/right by a chunk and the view appears unchanged.
+    pub fn extend_canvas(&mut self, up: bool, left: bool) {
+        if !up && !left {
+            return;
+        }
+        let before = self.grid.origin;
+        self.grid.shift_chunks(up as i32, left as i32);
+        let chunk = chunked_grid::CHUNK_SIZE;
+        self.undo_stack.offset(up as usize * chunk, left as usize * chunk);
+        self.follow_origin(before);
+    }
+
+    /// Keep the cursor and view on the same content after the grid origin moved
+    pub fn follow_origin(&mut self, before: (usize, usize)) {
+        let (after_row, after_col) = self.grid.origin;
+        if (after_row, after_col) == before {
+            return;
+        }
+        let rows = after_row as isize - before.0 as isize;
+        let cols = after_col as isize - before.1 as isize;
+        self.cursor_row = self.cursor_row.saturating_add_signed(rows);
+        self.cursor_col = self.cursor_col.saturating_add_signed(cols);
+        self.viewport_row = self.viewport_row.saturating_add_signed(rows);
+        self.viewport_col = self.viewport_col.saturating_add_signed(cols);
+        self.viewport_anchor = (self.cursor_row, self.cursor_col);
+        self.scroll_target_row = self.scroll_target_row.map(|r| r.saturating_add_signed(rows));
+        // Stored positions no longer point at the same text
+        self.search_results.clear();
+        self.needs_redraw = true;
+    }
+
+    /// Pack far-away chunks while the grid is over the memory cap from Settings
+    pub fn enforce_memory_cap(&mut self, viewport_width: u16, viewport_height: u16) {
+        if self.settings.memory_cap_mb == 0 {
+            return;
+        }
+        let keep = chunked_grid::Viewport {
+            start_row: self.viewport_row,
+            start_col: self.viewport_col,
+            end_row: self.viewport_row + viewport_height as usize,
+            end_col: self.viewport_col + viewport_width as usize,
+        };
+        let packed = self.grid.enforce_memory_cap(self.settings.memory_cap_mb * 1024 * 1024, keep);
+        if packed > 0 {
+            log::debug!("Packed {} chunks to stay under the {} MB memory cap", packed, self.settings.memory_cap_mb);
+        }
+    }
+
+    /// Alt+C: scroll back to the cursor and flash a crosshair on its row and column
+    pub fn find_cursor(&mut self) {
+        // A viewport scrolled away by the wheel follows the cursor again
+        self.scroll_target_row = None;
+        self.viewport_anchor = (usize::MAX, usize::MAX);
+        self.cursor_flash_until = Some(std::time::Instant::now() + std::time::Duration::from_millis(timing::CURSOR_FLASH_MS));
+        self.needs_redraw = true;
+    }
+
+    /// When the next temporary highlight (cursor flash, alignment guides) is due to go
+    pub fn highlight_expiry(&self) -> Option<std::time::Instant> {
+        self.cursor_flash_until.into_iter().chain(self.guides_until).min()
+    }
+
+    /// Take down the temporary highlights whose time is up
+    pub fn expire_highlights(&mut self) {
+        let now = std::time::Instant::now();
+        if self.cursor_flash_until.take_if(|until| *until <= now).is_some() {
+            self.needs_redraw = true;
+        }
+        if self.guides_until.take_if(|until| *until <= now).is_some() {
+            self.guides.clear();
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Snap to Grid: the column a click or paste at (row, col) goes to, showing alignment
+    /// guides for it (just `col` with the setting off)
+    pub fn snap_column(&mut self, row: usize, col: usize) -> usize {
+        if !self.settings.snap_to_grid {
+            return col;
+        }
+        let col = alignment::snap(&self.grid, row, col, self.settings.grid_col_spacing);
+        self.guides = alignment::guides(&self.grid, row, col);
+        self.guides_until = (!self.guides.is_empty())
+            .then(|| std::time::Instant::now() + std::time::Duration::from_millis(config::alignment::GUIDE_MS));
+        col
+    }
+
+    /// What to tint around the cursor this frame: a crosshair while Find cursor flashes, then
+    /// whatever the Cursor Highlight setting asks for; the bool says whether it is the flash
+    pub fn cursor_highlight(&self) -> (settings::CursorHighlight, bool) {
+        match self.cursor_flash_until {
+            Some(until) if std::time::Instant::now() < until => (settings::CursorHighlight::Crosshair, true),
+            _ => (self.settings.cursor_highlight, false),
+        }
+    }
+
     pub fn update_viewport(&mut self, viewport_width: u16, viewport_height: u16) {
         // Normal mode - logical lines
         let margin_rows = (viewport_height / 3) as usize;
@@ -157,9 +583,14 @@ impl App {
         }
     }
 
-    /// Save current note if dirty and enough time has passed
+    /// Save current note if dirty and enough time has passed. The write happens on the
+    /// database thread; its result is applied by poll_db_responses.
     pub fn auto_save(&mut self) -> Result<()> {
-        if !self.dirty {
+        if !self.dirty || self.save_conflict.is_some() {
+            return Ok(());
+        }
+        // One background save at a time; edits made meanwhile go into the next one
+        if self.notes_mode.worker.as_ref().is_some_and(|worker| worker.is_busy()) {
             return Ok(());
         }
 
@@ -168,166 +599,3663 @@ impl App {
             return Ok(());
         }
 
-        self.save_current_note()?;
+        let Some(request) = self.save_request() else {
+            return Ok(());
+        };
+        // Edits from here on dirty the note again
+        self.dirty = false;
+        if let Err(e) = self.send_db_request(request) {
+            self.dirty = true;
+            return Err(e);
+        }
         Ok(())
     }
 
     /// Force save current note immediately
     pub fn save_current_note(&mut self) -> Result<()> {
-        if let Some(ref current_note) = self.notes_mode.current_note {
-            let lines = self.grid.to_lines();
-            let content = lines.join("\n");
-            self.notes_mode.db.update_note(&current_note.id, current_note.title.clone(), content, current_note.tags.clone())?;
+        // Background work lands first, so this save is checked against its timestamp
+        self.finish_db_requests()?;
+
+        // Don't write anything while an external change is waiting for a decision
+        if self.save_conflict.is_some() {
+            return Ok(());
+        }
+
+        let Some(Request::Save { note_id, expected_updated_at, title, content, tags }) = self.save_request() else {
+            return Ok(());
+        };
+        let had_changes = self.dirty;
+        let outcome = self.notes_mode.db.update_note_checked(&note_id, &expected_updated_at, title, content.clone(), tags)?;
+        if matches!(outcome, notes_database::SaveOutcome::Saved(_)) {
             self.dirty = false;
-            self.last_save_time = std::time::Instant::now();
         }
+        self.apply_save_outcome(content, outcome, had_changes);
         Ok(())
     }
 
-    /// Mark note as dirty (needs saving)
-    pub fn mark_dirty(&mut self) {
-        self.dirty = true;
+    /// A save of the open note with the editor contents, after updating its automatic title
+    fn save_request(&mut self) -> Option<Request> {
+        let content = self.stored_content();
+        let current_note = self.notes_mode.current_note.as_mut()?;
+
+        // Untitled notes, and ones still titled after their first line, follow the first line
+        if self.settings.auto_title {
+            if let Some(title) = note_format::first_line_title(&self.grid).filter(|t| *t != current_note.title) {
+                let untitled = current_note.title.is_empty() || current_note.title == "Untitled";
+                if untitled || note_format::first_line_title_of(&current_note.content).as_deref() == Some(current_note.title.as_str()) {
+                    if let Some(entry) = self.notes_list.iter_mut().find(|n| n.id == current_note.id) {
+                        entry.title = title.clone();
+                    }
+                    current_note.title = title;
+                }
+            }
+        }
+
+        Some(Request::Save {
+            note_id: current_note.id.clone(),
+            expected_updated_at: current_note.updated_at,
+            title: current_note.title.clone(),
+            content,
+            tags: current_note.tags.clone(),
+        })
     }
 
-    /// Perform search and populate results
-    pub fn perform_search(&mut self) {
-        self.search_results.clear();
+    /// The editor contents as saved, without trailing whitespace if strip_trailing_whitespace is set
+    fn stored_content(&self) -> String {
+        let trailing = self.grid.trailing_whitespace();
+        if !self.settings.strip_trailing_whitespace || trailing.is_empty() {
+            return note_format::encode(&self.grid);
+        }
+        let mut grid = self.grid.clone();
+        for (row, col) in trailing {
+            grid.delete_at(row, col);
+        }
+        note_format::encode(&grid)
+    }
 
-        if self.search_query.is_empty() {
-            return;
+    /// Remove whitespace at the ends of lines as one undoable edit
+    pub fn strip_trailing_whitespace(&mut self) {
+        let commands: Vec<undo::Command> = self.grid.trailing_whitespace()
+            .into_iter()
+            .map(|(row, col)| undo::Command::DeleteChar { row, col, deleted_char: self.grid.get(row, col) })
+            .collect();
+        self.status_message = match commands.len() {
+            0 => "No trailing whitespace".to_string(),
+            n => format!("Removed {} trailing whitespace character{}", n, if n == 1 { "" } else { "s" }),
+        };
+        if !commands.is_empty() {
+            let command = undo::Command::Group { commands };
+            command.execute(&mut self.grid);
+            self.undo_stack.push(command);
+            self.mark_dirty();
         }
+        self.needs_redraw = true;
+    }
 
-        let lines = self.grid.to_lines();
-        let query_lower = self.search_query.to_lowercase();
+    /// Command palette → Tidy layout: stack the note's scattered blocks against its left edge
+    /// as one undo step (see auto_layout.rs)
+    pub fn tidy_layout(&mut self) {
+        let Some((command, moved)) = auto_layout::tidy(&mut self.grid) else {
+            self.status_message = "Layout is already tidy".to_string();
+            return;
+        };
+        self.undo_stack.push(command);
+        self.grid.clear_selection();
+        if let Some((top, left, _, _)) = self.grid.bounds() {
+            self.cursor_row = top;
+            self.cursor_col = left;
+        }
+        self.status_message = format!("Moved {} block{}", moved, if moved == 1 { "" } else { "s" });
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
 
-        for (row, line) in lines.iter().enumerate() {
-            let line_lower = line.to_lowercase();
-            let mut start = 0;
+    /// End: to the end of the text on the line, then (pressed again) to the right edge of the view
+    pub fn end_key(&mut self) -> Result<()> {
+        let text_end = self.grid.get_text_length(self.cursor_row);
+        if self.cursor_col != text_end {
+            self.cursor_col = text_end;
+        } else {
+            let (term_width, _) = self.terminal_size()?;
+            let panels = if self.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 }
+                + if self.outline_panel_expanded { layout::OUTLINE_PANEL_WIDTH } else { 0 }
+                + self.backlinks_panel(term_width).map_or(0, |(_, width)| width)
+                + self.tasks_panel(term_width).map_or(0, |(_, width)| width)
+                + self.preview_panel(term_width).map_or(0, |(_, width)| width);
+            self.cursor_col = self.viewport_col + term_width.saturating_sub(panels).max(1) as usize - 1;
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
 
-            while let Some(pos) = line_lower[start..].find(&query_lower) {
-                let col = start + pos;
-                self.search_results.push((row, col));
-                start = col + 1;
+    /// Record a finished save of `content`; `had_changes` says whether the editor held
+    /// unsaved edits, which decides how a conflict is handled
+    fn apply_save_outcome(&mut self, content: String, outcome: notes_database::SaveOutcome, had_changes: bool) {
+        match outcome {
+            notes_database::SaveOutcome::Saved(updated_at) => {
+                if let Some(ref mut current_note) = self.notes_mode.current_note {
+                    current_note.updated_at = updated_at;
+                    current_note.content = content; // Base for merging a later conflict
+                    // Keep the cached list in step, so switching back needs no query
+                    if let Some(entry) = self.notes_list.iter_mut().find(|n| n.id == current_note.id) {
+                        *entry = current_note.clone();
+                    }
+                    self.hooks.fire(hooks::Event::Save, current_note);
+                }
+                self.smart_views.invalidate();
+                self.embeds.invalidate();
+                self.invalidate_preview();
+                self.last_save_time = std::time::Instant::now();
             }
+            notes_database::SaveOutcome::Conflict(theirs) => self.note_changed_elsewhere(theirs, had_changes),
         }
-
-        self.current_search_index = 0;
     }
 
-    /// Jump to next search result
-    pub fn next_search_result(&mut self) {
-        if !self.search_results.is_empty() {
-            self.current_search_index = (self.current_search_index + 1) % self.search_results.len();
-            let (row, col) = self.search_results[self.current_search_index];
-            self.cursor_row = row;
-            self.cursor_col = col;
+    /// The open note was changed by something else: ask what to do with unsaved edits, or
+    /// just load the other version when there are none
+    fn note_changed_elsewhere(&mut self, theirs: notes_database::Note, had_changes: bool) {
+        self.save_conflict = Some(theirs);
+        if had_changes {
+            self.dirty = true;
+            self.status_message = "Note changed elsewhere: [O]verwrite / [R]eload / [M]erge / [V]iew diff".to_string();
+        } else {
+            // Nothing local to lose - just pick up the other version
+            self.resolve_conflict_take_theirs();
+            self.status_message = "Reloaded note changed elsewhere".to_string();
         }
+        self.needs_redraw = true;
     }
 
-    /// Jump to previous search result
-    pub fn prev_search_result(&mut self) {
-        if !self.search_results.is_empty() {
-            self.current_search_index = if self.current_search_index == 0 {
-                self.search_results.len() - 1
-            } else {
-                self.current_search_index - 1
-            };
-            let (row, col) = self.search_results[self.current_search_index];
-            self.cursor_row = row;
-            self.cursor_col = col;
+    /// Start a sync with the server in settings.json, unless one is running. It uses a
+    /// connection of its own; notes it pulls show up through live reload.
+    pub fn start_sync(&mut self) -> Result<()> {
+        if self.settings.sync_url.trim().is_empty() {
+            self.status_message = "Set sync_url in settings.json to sync notes".to_string();
+            return Ok(());
         }
+        if self.sync.is_running() {
+            return Ok(());
+        }
+        self.save_current_note()?;
+        let db = self.notes_mode.db.reopen()?;
+        self.sync.start(db, self.settings.sync_url.clone())?;
+        self.needs_redraw = true;
+        Ok(())
     }
-}
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    let _ = std::fs::write("/tmp/chonk-debug.log", "=== CHONK-NOTE STARTED ===\n");
-
-    let mut app = App::new()?;
 
-    setup_terminal()?;
-    let result = run_app(&mut app).await;
-    restore_terminal()?;
+    /// Notes menu: start serving the editor's view to viewers on the local network (the
+    /// address goes on the clipboard), or stop
+    pub fn toggle_broadcast(&mut self) {
+        self.status_message = match self.broadcast.take() {
+            Some(_) => "Broadcast stopped".to_string(),
+            None => match broadcast::Broadcast::start(self.settings.broadcast_port) {
+                Ok(broadcast) => {
+                    let copied = self.set_clipboard(&broadcast.url).map(|place| format!(", copied to the {}", place)).unwrap_or_default();
+                    let message = format!("Broadcasting at {}{}", broadcast.url, copied);
+                    self.broadcast = Some(broadcast);
+                    message
+                }
+                Err(e) => format!("Broadcast not started: {:#}", e),
+            },
+        };
+        self.needs_redraw = true;
+    }
 
-    result
-}
+    /// What broadcast viewers see of an editor `width` x `height` cells
+    pub fn broadcast_view(&self, width: usize, height: usize) -> broadcast::View {
+        let title = self.notes_mode.current_note.as_ref().map(|note| note.title.as_str()).filter(|title| !title.is_empty());
+        let last_col = self.viewport_col + width.saturating_sub(1);
+        let rows = (self.viewport_row..self.viewport_row + height)
+            .map(|row| self.grid.get_line(row, self.viewport_col, last_col).trim_end().to_string())
+            .collect();
+        let on_screen = (self.viewport_row..self.viewport_row + height).contains(&self.cursor_row)
+            && (self.viewport_col..self.viewport_col + width).contains(&self.cursor_col);
+        broadcast::View {
+            title: title.unwrap_or("Untitled").to_string(),
+            rows,
+            cursor: on_screen.then(|| (self.cursor_row - self.viewport_row, self.cursor_col - self.viewport_col)),
+        }
+    }
 
-fn setup_terminal() -> Result<()> {
-    KittyTerminal::enable_raw_mode().map_err(|e| anyhow::anyhow!("Terminal setup failed: {}", e))?;
-    KittyTerminal::enter_fullscreen().map_err(|e| anyhow::anyhow!("Fullscreen failed: {}", e))?;
+    /// Pick up the outcome of a finished sync
+    pub fn poll_sync(&mut self) {
+        match self.sync.poll() {
+            Some(Ok(summary)) if summary.pushed + summary.pulled > 0 => {
+                self.status_message = format!("Synced: {} sent, {} received", summary.pushed, summary.pulled);
+            }
+            Some(Err(e)) => {
+                log::warn!("Sync failed: {}", e);
+                self.status_message = format!("Sync failed: {}", e);
+            }
+            Some(Ok(_)) | None => return,
+        }
+        self.needs_redraw = true;
+    }
 
-    // Show and configure cursor
-    print!("\x1b[?25h");  // Show cursor
-    print!("\x1b[1 q");   // Blinking block
-    print!("\x1b[?12h");  // Enable blinking
-    std::io::Write::flush(&mut std::io::stdout())?;
+    /// Live reload: when another process (a sync tool, `chonk-note import`) has written to the
+    /// database, re-read the sidebar if its notes changed and pick up a new version of the
+    /// open note. Autosaves commit on the database thread, which looks the same from here;
+    /// they are told apart by the timestamps the editor already has.
+    pub fn check_external_changes(&mut self) -> Result<()> {
+        // A save in flight would look like someone else's change until its reply lands
+        if self.notes_mode.worker.as_ref().is_some_and(|worker| worker.is_busy()) || self.save_conflict.is_some() {
+            return Ok(());
+        }
+        let version = self.notes_mode.db.data_version()?;
+        if self.db_version.replace(version).is_none_or(|seen| seen == version) {
+            return Ok(());
+        }
+        self.finish_db_requests()?;
+        self.smart_views.invalidate();
+        self.embeds.invalidate();
+        self.invalidate_preview();
+        self.backlinks.invalidate();
+        self.tasks.invalidate();
+
+        let loaded = self.notes_list.len().max(config::notes_list::PAGE_SIZE);
+        let versions = self.notes_mode.db.list_versions(loaded)?;
+        let known = self.notes_list.iter().map(|note| (&note.id, &note.title, &note.updated_at));
+        if !versions.iter().map(|(id, title, updated_at)| (id, title, updated_at)).take(self.notes_list.len()).eq(known)
+            || self.notes_mode.db.count_notes()? != self.notes_total
+        {
+            self.invalidate_notes_list();
+            self.refresh_notes_list();
+            self.needs_redraw = true;
+        }
 
-    Ok(())
-}
+        let Some(current) = self.notes_mode.current_note.as_ref() else {
+            return Ok(());
+        };
+        match self.notes_mode.db.get_note(&current.id)? {
+            Some(theirs) if theirs.updated_at != current.updated_at => {
+                let had_changes = self.dirty;
+                self.note_changed_elsewhere(theirs, had_changes);
+            }
+            Some(_) => {}
+            None => {
+                self.status_message = "The open note was deleted elsewhere (saving it adds it back)".to_string();
+                self.needs_redraw = true;
+            }
+        }
+        Ok(())
+    }
 
-fn restore_terminal() -> Result<()> {
-    // Reset cursor to default
-    print!("\x1b[0 q");  // Default cursor
-    print!("\x1b[?12l"); // Disable blinking
-    std::io::Write::flush(&mut std::io::stdout())?;
+    /// Hand a request to the database thread, or run it right away without one
+    fn send_db_request(&mut self, request: Request) -> Result<()> {
+        match self.notes_mode.worker.as_mut() {
+            Some(worker) => worker.send(request),
+            None => {
+                let response = notes_database::worker::execute(&self.notes_mode.db, request);
+                self.handle_db_response(response)
+            }
+        }
+    }
 
-    KittyTerminal::exit_fullscreen().map_err(|e| anyhow::anyhow!("Exit fullscreen failed: {}", e))?;
-    KittyTerminal::disable_raw_mode().map_err(|e| anyhow::anyhow!("Disable raw mode failed: {}", e))?;
-    Ok(())
-}
+    /// Apply the database thread's finished requests without waiting
+    pub fn poll_db_responses(&mut self) -> Result<()> {
+        while let Some(response) = self.notes_mode.worker.as_mut().and_then(|worker| worker.try_recv()) {
+            self.handle_db_response(response)?;
+        }
+        Ok(())
+    }
 
-async fn run_app(app: &mut App) -> Result<()> {
-    let mut stdout = io::stdout();
-    let mut last_term_size = (0, 0);
-    let mut last_render_time = std::time::Instant::now();
-    let mut mouse_state = MouseState::default();
+    /// Wait for everything sent to the database thread and apply the results
+    pub fn finish_db_requests(&mut self) -> Result<()> {
+        while let Some(response) = self.notes_mode.worker.as_mut().and_then(|worker| worker.recv()) {
+            self.handle_db_response(response)?;
+        }
+        Ok(())
+    }
 
-    loop {
-        let (term_width, term_height) = KittyTerminal::size()?;
+    fn handle_db_response(&mut self, response: Response) -> Result<()> {
+        match response {
+            Response::Saved { note_id, content, outcome, elapsed } => {
+                // Switching notes waits for saves, so this is still the open note
+                if self.notes_mode.current_note.as_ref().map(|n| n.id.as_str()) != Some(note_id.as_str()) {
+                    return Ok(());
+                }
+                match outcome {
+                    Ok(outcome) => {
+                        self.diagnostics.autosave_finished(elapsed);
+                        self.apply_save_outcome(content, outcome, true);
+                    }
+                    Err(e) => {
+                        log::error!("Auto-save failed: {}", e);
+                        self.dirty = true;
+                        self.status_message = format!("Auto-save failed: {}", e);
+                        self.needs_redraw = true;
+                    }
+                }
+            }
+            Response::Mentions { note_id, mentions } => {
+                let mentions = mentions.unwrap_or_else(|e| {
+                    log::error!("Finding backlinks failed: {}", e);
+                    Vec::new()
+                });
+                let current = self.notes_mode.current_note.as_ref().map(|note| note.id.as_str());
+                self.backlinks.finish_search(note_id, mentions, current);
+                self.needs_redraw = true;
+            }
+            Response::Tasks(tasks) => {
+                let tasks = tasks.unwrap_or_else(|e| {
+                    log::error!("Gathering tasks failed: {}", e);
+                    Vec::new()
+                });
+                self.tasks.finish_scan(tasks);
+                self.needs_redraw = true;
+            }
+            Response::Imported(Err(e)) => {
+                self.status_message = format!("Import failed: {}", e);
+                self.needs_redraw = true;
+            }
+            Response::Imported(Ok(notes)) => {
+                self.invalidate_notes_list();
+                // Opens the first imported note (unless a save conflict is in the way)
+                if let Some(first) = notes.first() {
+                    self.open_note_by_id(&first.id)?;
+                }
+                self.refresh_notes_list();
+                self.status_message = match notes.as_slice() {
+                    [note] => format!("Imported \"{}\"", note.title),
+                    notes => format!("Imported {} notes", notes.len()),
+                };
+                self.needs_redraw = true;
+            }
+        }
+        Ok(())
+    }
 
-        // Auto-save debounced
-        if let Err(e) = app.auto_save() {
-            let _ = std::fs::write("/tmp/chonk-debug.log", format!("Auto-save error: {}\n", e));
+    /// Ctrl+Q: save and exit, or open the quit prompt if the save failed
+    pub fn request_quit(&mut self) {
+        match self.save_current_note() {
+            // The conflict prompt is up; quitting waits until it is resolved
+            Ok(()) if self.save_conflict.is_some() => {}
+            Ok(()) => self.exit_requested = true,
+            Err(e) => {
+                log::error!("Save before quit failed: {}", e);
+                self.quit_save_error = Some(e.to_string());
+            }
         }
+        self.needs_redraw = true;
+    }
 
-        // Check if terminal was resized
-        if (term_width, term_height) != last_term_size {
-            app.needs_redraw = true;
-            last_term_size = (term_width, term_height);
+    /// Quit prompt: write the editor contents to a rescue file, then exit
+    pub fn quit_with_rescue_file(&mut self) {
+        match self.write_rescue_file() {
+            Ok(path) => {
+                self.exit_message = Some(format!("Unsaved changes were written to {}", path.display()));
+                self.dirty = false;
+                self.quit_save_error = None;
+                self.exit_requested = true;
+            }
+            Err(e) => {
+                log::error!("Writing rescue file failed: {}", e);
+                self.status_message = format!("Rescue file failed: {}", e);
+            }
         }
+        self.needs_redraw = true;
+    }
 
-        // Redraw when necessary (max 60 FPS)
-        let now = std::time::Instant::now();
-        let frame_time = now.duration_since(last_render_time);
+    /// Quit prompt: exit and drop the unsaved changes
+    pub fn quit_discarding_changes(&mut self) {
+        self.dirty = false;
+        self.quit_save_error = None;
+        self.exit_requested = true;
+    }
 
-        if app.needs_redraw && frame_time.as_millis() >= timing::FRAME_TIME_MS {
-            KittyTerminal::move_to(0, 0)?;
-            last_render_time = now;
+    /// Write the editor contents to <data dir>/chonk-note/rescue/<title>-<timestamp>.txt
+    pub fn write_rescue_file(&self) -> Result<std::path::PathBuf> {
+        let dir = data_dir::file("rescue")
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        std::fs::create_dir_all(&dir)?;
+
+        let title = self.notes_mode.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("Untitled");
+        let stem: String = title
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("{}-{}.txt", stem, chrono::Local::now().format("%Y%m%d-%H%M%S")));
+
+        let mut content = self.grid.to_lines().join("\n");
+        content.push('\n');
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
 
-            // BEGIN SYNCHRONIZED UPDATE - prevents flicker
-            print!("\x1b[?2026h");
-            print!("\x1b[s");
+    /// Save before leaving the current note; false means a conflict needs resolving first
+    pub fn save_before_switch(&mut self) -> Result<bool> {
+        self.save_current_note()?;
+        Ok(self.save_conflict.is_none())
+    }
 
-            // Clear entire screen first to prevent artifacts
-            print!("\x1b[2J");
+    /// Resolve a save conflict by overwriting the stored note with the editor contents
+    pub fn resolve_conflict_keep_mine(&mut self) -> Result<()> {
+        if self.save_conflict.take().is_none() {
+            return Ok(());
+        }
+        let content = self.stored_content();
+        if let Some(ref mut current_note) = self.notes_mode.current_note {
+            current_note.updated_at = self.notes_mode.db.update_note(
+                &current_note.id, current_note.title.clone(), content.clone(), current_note.tags.clone())?;
+            current_note.content = content;
+            if let Some(entry) = self.notes_list.iter_mut().find(|n| n.id == current_note.id) {
+                *entry = current_note.clone();
+            }
+            self.dirty = false;
+            self.last_save_time = std::time::Instant::now();
+        }
+        self.conflict_diff_visible = false;
+        self.status_message = "Kept your version".to_string();
+        self.needs_redraw = true;
+        Ok(())
+    }
 
-            // Render title bar
-            let total_width = term_width as usize;
-            let title_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
-            let title_fg = rgb_fg(colors::TITLE_BAR_FG.0, colors::TITLE_BAR_FG.1, colors::TITLE_BAR_FG.2);
+    /// Resolve a save conflict by replacing the editor contents with the stored note
+    pub fn resolve_conflict_take_theirs(&mut self) {
+        let Some(theirs) = self.save_conflict.take() else {
+            return;
+        };
+        let (cursor_row, cursor_col) = (self.cursor_row, self.cursor_col);
+        let (viewport_row, viewport_col) = (self.viewport_row, self.viewport_col);
 
-            // Draw full teal bar first (always full width)
-            print!("\x1b[1;1H{}{}\x1b[0m", title_bg, " ".repeat(total_width));
+        if let Some(entry) = self.notes_list.iter_mut().find(|n| n.id == theirs.id) {
+            *entry = theirs.clone();
+        }
+        self.load_note(theirs);
+
+        self.cursor_row = cursor_row;
+        self.cursor_col = cursor_col;
+        self.viewport_row = viewport_row;
+        self.viewport_col = viewport_col;
+        self.dirty = false;
+        self.conflict_diff_visible = false;
+        self.status_message = "Loaded the other version".to_string();
+        self.needs_redraw = true;
+    }
 
-            // Left side: "Notes ▾" and "Settings ▾" menu buttons
-            let notes_text = if app.notes_menu_expanded { "Notes ▴" } else { "Notes ▾" };
-            let settings_text = if app.settings_menu_expanded { "Settings ▴" } else { "Settings ▾" };
+    /// Resolve a save conflict with a three-way merge of the last saved version, the
+    /// editor contents and the stored note; clashing edits are left in conflict markers
+    pub fn resolve_conflict_merge(&mut self) {
+        let Some(theirs) = self.save_conflict.take() else {
+            return;
+        };
+        let Some(ref mut current_note) = self.notes_mode.current_note else {
+            return;
+        };
+
+        // Normalize all three versions the same way the grid does
+        let as_grid_lines = |content: &str| note_format::decode(content).to_lines();
+        let merge = diff::merge3(
+            &as_grid_lines(&current_note.content),
+            &self.grid.to_lines(),
+            &as_grid_lines(&theirs.content),
+        );
 
-            let notes_start_col = 0;
-            let settings_start_col = 10; // After "Notes ▾ "
+        // Their version becomes the base, so saving the merge doesn't conflict again
+        current_note.updated_at = theirs.updated_at;
+        current_note.content = theirs.content.clone();
+        if let Some(entry) = self.notes_list.iter_mut().find(|n| n.id == theirs.id) {
+            *entry = theirs;
+        }
 
-            print!("\x1b[1;{}H{}{}\x1b[1m{}\x1b[0m", notes_start_col + 1, title_bg, title_fg, notes_text);
-            print!("\x1b[1;{}H{}{}\x1b[1m{}\x1b[0m", settings_start_col + 1, title_bg, title_fg, settings_text);
+        self.grid = ChunkedGrid::from_lines(&merge.lines);
+        self.undo_stack.clear();
+        self.conflict_diff_visible = false;
+        self.mark_dirty();
+        self.status_message = if merge.conflicts == 0 {
+            "Merged both versions".to_string()
+        } else {
+            format!("Merged with {} conflict(s) marked {}", merge.conflicts, diff::CONFLICT_START)
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Type a character at the cursor (undoable) and advance the cursor
+    pub fn insert_char(&mut self, ch: char) {
+        let cmd = undo::Command::InsertChar {
+            row: self.cursor_row,
+            col: self.cursor_col,
+            ch,
+        };
+        cmd.execute(&mut self.grid);
+        self.undo_stack.push(cmd);
+
+        self.cursor_col += 1;
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Typing commands for `text` at (row, col), each shifting the rest of the line right
+    fn insert_text_commands(row: usize, col: usize, text: &str) -> Vec<undo::Command> {
+        text.chars()
+            .enumerate()
+            .map(|(i, ch)| undo::Command::InsertChar { row, col: col + i, ch })
+            .collect()
+    }
+
+    /// Enter: split the line at the cursor (Word-style), continuing list markers
+    /// and timestamping log note entries as one undoable step
+    pub fn insert_newline(&mut self) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        // Keep spaces up to the cursor so an empty item like "- " still parses
+        let line_len = self.grid.get_line_length(row).max(col);
+        let chars: Vec<char> = (0..line_len).map(|c| self.grid.get(row, c)).collect();
+        let current_line: String = chars.iter().collect();
+        let text_after_cursor: String = chars.iter().skip(col).collect();
+        let list_item = auto_list::ListItem::parse(&current_line).filter(|item| col >= item.content_col);
+
+        // Enter on an empty list item ends the list: drop the marker, keep the indent
+        if let Some(item) = list_item.as_ref().filter(|item| chars[item.content_col.min(chars.len())..].iter().all(|c| *c == ' ')) {
+            let commands: Vec<undo::Command> = chars[item.indent..]
+                .iter()
+                .map(|&deleted_char| undo::Command::DeleteChar { row, col: item.indent, deleted_char })
+                .collect();
+            for command in &commands {
+                command.execute(&mut self.grid);
+            }
+            self.undo_stack.push(undo::Command::Group { commands });
+            self.cursor_col = item.indent;
+            self.mark_dirty();
+            self.needs_redraw = true;
+            return;
+        }
+
+        let newline = undo::Command::InsertNewLine { row, col, text_after_cursor: text_after_cursor.clone() };
+        newline.execute(&mut self.grid);
+        self.cursor_row += 1;
+        self.cursor_col = 0;
+
+        // Appending at the end of the log note starts the entry with a timestamp
+        let mut prefix = String::new();
+        let is_last_line = self.grid.bounds().is_none_or(|(_, _, max_row, _)| self.cursor_row > max_row);
+        if is_last_line && text_after_cursor.trim().is_empty() {
+            prefix.extend(self.log_entry_prefix());
+        }
+        match list_item {
+            Some(item) => prefix.push_str(&item.next_marker()),
+            None => prefix.push_str(&" ".repeat(auto_list::new_line_indent(&chars, col, self.settings.enter_indent))),
+        }
+
+        if prefix.is_empty() {
+            self.undo_stack.push(newline);
+        } else {
+            let mut commands = vec![newline];
+            commands.extend(Self::insert_text_commands(self.cursor_row, 0, &prefix));
+            for command in &commands[1..] {
+                command.execute(&mut self.grid);
+            }
+            self.undo_stack.push(undo::Command::Group { commands });
+            self.cursor_col = prefix.chars().count();
+        }
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Tab: insert spaces or jump to the next grid column; Shift+Tab jumps back a stop
+    pub fn tab(&mut self, back: bool) {
+        let stop = match self.settings.tab_mode {
+            settings::TabMode::Spaces => self.settings.tab_width,
+            settings::TabMode::GridStop => self.settings.grid_col_spacing,
+        }
+        .max(1);
+
+        if self.grid.selection.is_some() {
+            self.indent_selection(stop, back);
+        } else if back {
+            self.cursor_col = (self.cursor_col.saturating_sub(1) / stop) * stop;
+        } else if self.settings.tab_mode == settings::TabMode::Spaces {
+            self.insert_text(&" ".repeat(stop));
+        } else {
+            self.cursor_col = (self.cursor_col / stop + 1) * stop;
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Tab / Shift+Tab with a block selection: move the text of its rows, from the block's left
+    /// edge, right by `width` columns or left over up to `width` blank columns
+    fn indent_selection(&mut self, width: usize, back: bool) {
+        let Some((min_row, min_col, max_row, _)) = self.grid.selection.as_ref().map(|block| block.bounds()) else {
+            return;
+        };
+        let amounts: Vec<isize> = (min_row..=max_row)
+            .map(|row| if back { -(self.grid.blank_run(row, min_col, width) as isize) } else { width as isize })
+            .collect();
+        let rows = amounts.len();
+        if amounts.iter().all(|a| *a == 0) {
+            self.status_message = "Nothing to outdent".to_string();
+            self.needs_redraw = true;
+            return;
+        }
+
+        // The selection stays put, so its left edge is where the next Tab or Shift+Tab works
+        let command = undo::Command::ShiftRows { first_row: min_row, col: min_col, amounts };
+        command.execute(&mut self.grid);
+        self.undo_stack.push(command);
+        self.status_message = format!("{} {} rows", if back { "Outdented" } else { "Indented" }, rows);
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Alt+Up / Alt+Down: move the cursor's row, or the rows of the block selection, past the
+    /// row above or below; the cursor and selection go with them
+    pub fn move_rows(&mut self, up: bool) {
+        let (first_row, last_row) = match self.grid.selection.as_ref().map(|block| block.bounds()) {
+            Some((min_row, _, max_row, _)) => (min_row, max_row),
+            None => (self.cursor_row, self.cursor_row),
+        };
+        if up && first_row == 0 {
+            self.status_message = "Already at the top of the canvas".to_string();
+            self.needs_redraw = true;
+            return;
+        }
+
+        let command = undo::Command::MoveRows { first_row, last_row, up };
+        command.execute(&mut self.grid);
+        self.undo_stack.push(command);
+        let step = |row: usize| if up { row - 1 } else { row + 1 };
+        self.cursor_row = step(self.cursor_row);
+        if let Some(block) = self.grid.selection.as_mut() {
+            block.start_row = step(block.start_row);
+            block.end_row = step(block.end_row);
+        }
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+Enter / Ctrl+Shift+Enter: open an empty row below/above the cursor's line without
+    /// splitting it; the cursor goes to the new row at the line's indent
+    pub fn open_line(&mut self, above: bool) {
+        let indent = self.grid.get_line_bounds(self.cursor_row).map_or(0, |(start, _)| start);
+        let row = if above { self.cursor_row } else { self.cursor_row + 1 };
+        let command = undo::Command::InsertRow { row };
+        command.execute(&mut self.grid);
+        self.undo_stack.push(command);
+
+        self.grid.clear_selection();
+        self.cursor_row = row;
+        self.cursor_col = indent;
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Alt+J: join the cursor's line with the next, or all rows of the block selection into its
+    /// first row, leaving one space at each seam. One undo step.
+    pub fn join_lines(&mut self) {
+        let (first_row, last_row) = match self.grid.selection.as_ref().map(|block| block.bounds()) {
+            Some((min_row, _, max_row, _)) if max_row > min_row => (min_row, max_row),
+            _ => (self.cursor_row, self.cursor_row + 1),
+        };
+
+        let mut commands = Vec::new();
+        let mut seam = self.grid.get_text_length(first_row);
+        for _ in first_row..last_row {
+            seam = join_next_line(&mut self.grid, first_row, &mut commands);
+        }
+        self.undo_stack.push(undo::Command::Group { commands });
+
+        self.grid.clear_selection();
+        self.cursor_row = first_row;
+        self.cursor_col = seam;
+        self.status_message = format!("Joined {} lines", last_row - first_row + 1);
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Alt+Shift+Down / Alt+Shift+Up: copy the cursor's line, or the block selection, into new
+    /// rows right below it as one undo step. Down moves the cursor (and selection) to the copy,
+    /// Up leaves them on the original, which is now the upper of the two.
+    pub fn duplicate(&mut self, down: bool) {
+        let block = self.grid.selection.as_ref().map(|block| block.bounds());
+        let (first_row, col, last_row, lines) = match block {
+            Some((min_row, min_col, max_row, max_col)) => {
+                let lines = (min_row..=max_row).map(|row| self.grid.get_line(row, min_col, max_col)).collect::<Vec<_>>();
+                (min_row, min_col, max_row, lines)
+            }
+            None => {
+                let len = self.grid.get_line_length(self.cursor_row);
+                let line = if len > 0 { self.grid.get_line(self.cursor_row, 0, len - 1) } else { String::new() };
+                (self.cursor_row, 0, self.cursor_row, vec![line])
+            }
+        };
+
+        let rows = last_row - first_row + 1;
+        let end_col = self.grid.get_line_length(last_row);
+        let mut commands: Vec<undo::Command> = (0..rows)
+            .map(|_| undo::Command::InsertNewLine { row: last_row, col: end_col, text_after_cursor: String::new() })
+            .collect();
+        let replaced_content = lines.iter().map(|line| " ".repeat(line.chars().count())).collect();
+        commands.push(undo::Command::PasteBlock { row: last_row + 1, col, lines, replaced_content });
+        let command = undo::Command::Group { commands };
+        command.execute(&mut self.grid);
+        self.undo_stack.push(command);
+
+        if down {
+            self.cursor_row += rows;
+            if let Some(block) = self.grid.selection.as_mut() {
+                block.start_row += rows;
+                block.end_row += rows;
+            }
+        }
+        self.status_message = match block {
+            Some(_) => format!("Duplicated {} rows", rows),
+            None => "Duplicated line".to_string(),
+        };
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Insert text at the cursor as one undoable step and move the cursor past it
+    pub fn insert_text(&mut self, text: &str) {
+        let commands = Self::insert_text_commands(self.cursor_row, self.cursor_col, text);
+        if commands.is_empty() {
+            return;
+        }
+        for command in &commands {
+            command.execute(&mut self.grid);
+        }
+        self.undo_stack.push(undo::Command::Group { commands });
+        self.cursor_col += text.chars().count();
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Alt+D / Alt+T / Alt+I: insert the current date, time or ISO datetime
+    pub fn insert_timestamp(&mut self, stamp: timestamp::Stamp) {
+        let now = chrono::Local::now();
+        let text = match stamp {
+            timestamp::Stamp::Date => timestamp::format_time(&now, &self.settings.date_format, config::timestamps::DATE_FORMAT),
+            timestamp::Stamp::Time => timestamp::format_time(&now, &self.settings.time_format, config::timestamps::TIME_FORMAT),
+            timestamp::Stamp::IsoDateTime => now.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        };
+        self.insert_text(&text);
+    }
+
+    /// Alt+L: make the current note the log note (or stop using it as one)
+    pub fn toggle_log_note(&mut self) {
+        let Some(ref note) = self.notes_mode.current_note else {
+            return;
+        };
+        if self.settings.log_note_id.as_deref() == Some(note.id.as_str()) {
+            self.settings.log_note_id = None;
+            self.status_message = format!("\"{}\" is no longer the log note", note.title);
+        } else {
+            self.settings.log_note_id = Some(note.id.clone());
+            self.status_message = format!("\"{}\" is now the log note: new lines at the end get a timestamp", note.title);
+        }
+        if let Err(e) = self.settings.save() {
+            self.status_message = format!("Could not save settings: {}", e);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Timestamp prefix for a line appended to the log note, if this is the log note
+    pub fn log_entry_prefix(&self) -> Option<String> {
+        let note = self.notes_mode.current_note.as_ref()?;
+        if self.settings.log_note_id.as_deref() != Some(note.id.as_str()) {
+            return None;
+        }
+        Some(timestamp::format_time(&chrono::Local::now(), &self.settings.log_entry_format, config::timestamps::LOG_ENTRY_FORMAT))
+    }
+
+    /// Append a timestamped line below the log note's content, whether or not it is open.
+    /// Returns false when there is no log note.
+    pub fn append_to_log_note(&mut self, text: &str) -> Result<bool> {
+        let Some(id) = self.settings.log_note_id.clone() else {
+            return Ok(false);
+        };
+        let prefix = timestamp::format_time(&chrono::Local::now(), &self.settings.log_entry_format, config::timestamps::LOG_ENTRY_FORMAT);
+        let entry = format!("{}{}", prefix, text);
+        let below = |grid: &ChunkedGrid| grid.bounds().map_or((0, 0), |(_, min_col, max_row, _)| (max_row + 1, min_col));
+
+        if self.notes_mode.current_note.as_ref().is_some_and(|n| n.id == id) {
+            let (row, col) = below(&self.grid);
+            self.write_block(row, col, vec![entry]);
+            return Ok(true);
+        }
+        let Some(mut note) = self.notes_mode.db.get_note(&id)? else {
+            return Ok(false);
+        };
+        let mut grid = note_format::decode(&note.content);
+        let (row, col) = below(&grid);
+        grid.insert_at(row, col, &entry);
+        note.content = note_format::encode(&grid);
+        note.updated_at = self.notes_mode.db.update_note(&note.id, note.title.clone(), note.content.clone(), note.tags.clone())?;
+        if let Some(listed) = self.notes_list.iter_mut().find(|n| n.id == note.id) {
+            *listed = note;
+        }
+        Ok(true)
+    }
+
+    /// Notes menu: start a focus session of `focus_minutes`, or stop the running one early
+    pub fn toggle_focus_timer(&mut self) {
+        let now = std::time::Instant::now();
+        self.status_message = match self.focus_timer.take() {
+            Some(timer) => format!("Focus timer stopped after {} min", timer.elapsed_minutes(now)),
+            None => {
+                let title = self.notes_mode.current_note.as_ref().map(|n| n.title.clone());
+                let timer = focus_timer::FocusTimer::start(self.settings.focus_minutes, title, now);
+                let message = format!("Focus timer started ({} min)", timer.minutes());
+                self.focus_timer = Some(timer);
+                message
+            }
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Called when the countdown changes: redraw it, and wrap up the session once it runs out
+    pub fn update_focus_timer(&mut self) {
+        let now = std::time::Instant::now();
+        let Some(timer) = self.focus_timer.take_if(|timer| timer.is_finished(now)) else {
+            self.needs_redraw |= self.focus_timer.is_some();
+            return;
+        };
+        if self.settings.focus_bell {
+            print!("\x07");
+        }
+        let text = match timer.note_title {
+            Some(ref title) => format!("Focus session: {} min on \"{}\"", timer.minutes(), title),
+            None => format!("Focus session: {} min", timer.minutes()),
+        };
+        self.status_message = match self.append_to_log_note(&text) {
+            Ok(true) => format!("Focus session done ({} min), logged", timer.minutes()),
+            Ok(false) => format!("Focus session done ({} min)", timer.minutes()),
+            Err(e) => format!("Focus session done, but logging it failed: {}", e),
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Overwrite a block of cells starting at (row, col) as one undoable step
+    pub fn write_block(&mut self, row: usize, col: usize, lines: Vec<String>) {
+        self.write_styled_block(row, col, lines, &[]);
+    }
+
+    /// Like write_block, drawing the new cells in `styles` (row by row like `lines`; missing
+    /// ones are plain). Undoing brings back the styles of the cells written over.
+    pub fn write_styled_block(&mut self, row: usize, col: usize, lines: Vec<String>, styles: &[Vec<Style>]) {
+        let replaced_content = lines
+            .iter()
+            .enumerate()
+            .map(|(offset, line)| (0..line.chars().count()).map(|c| self.grid.get(row + offset, col + c)).collect())
+            .collect();
+        let mut replaced_styles = Vec::new();
+        let mut new_styles = Vec::new();
+        for (offset, line) in lines.iter().enumerate() {
+            for (offset_col, ch) in line.chars().enumerate() {
+                let (r, c) = (row + offset, col + offset_col);
+                let before = self.grid.style(r, c);
+                let after = styles.get(offset).and_then(|line| line.get(offset_col)).copied().unwrap_or_default();
+                if !before.is_plain() {
+                    replaced_styles.push((r, c, before, Style::default()));
+                }
+                if !after.is_plain() && ch != ' ' {
+                    new_styles.push((r, c, Style::default(), after));
+                }
+            }
+        }
+        let paste = undo::Command::PasteBlock { row, col, lines, replaced_content };
+        let cmd = if replaced_styles.is_empty() && new_styles.is_empty() {
+            paste
+        } else {
+            // Old styles are cleared before the paste so that undoing restores them after their characters
+            let commands = [
+                (!replaced_styles.is_empty()).then_some(undo::Command::Restyle { cells: replaced_styles }),
+                Some(paste),
+                (!new_styles.is_empty()).then_some(undo::Command::Restyle { cells: new_styles }),
+            ];
+            undo::Command::Group { commands: commands.into_iter().flatten().collect() }
+        };
+        cmd.execute(&mut self.grid);
+        self.undo_stack.push(cmd);
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+A: select everything from the origin to the end of the content
+    pub fn select_all(&mut self) {
+        // Find the bounds of all content
+        let lines = self.grid.to_lines();
+        if !lines.is_empty() {
+            let max_row = lines.len() - 1;
+            let max_col = lines.iter()
+                .map(|line| line.trim_end().len())
+                .max()
+                .unwrap_or(0)
+                .saturating_sub(1);
+
+            // Select from (0,0) to (max_row, max_col)
+            self.grid.start_selection(0, 0);
+            self.grid.update_selection(max_row, max_col);
+            self.status_message = format!("Selected all ({} rows)", lines.len());
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Ctrl+Shift+A: select the connected block of text around the cursor
+    pub fn select_island(&mut self) {
+        let Some((top, left, bottom, right)) = auto_layout::island_at(&self.grid, self.cursor_row, self.cursor_col) else {
+            self.status_message = "No text under the cursor".to_string();
+            self.needs_redraw = true;
+            return;
+        };
+        self.grid.start_selection(top, left);
+        self.grid.update_selection(bottom, right);
+        self.status_message = format!("Selected block ({} rows)", bottom - top + 1);
+        self.needs_redraw = true;
+    }
+
+    /// Alt+Shift+W / L / P: select the word, line or paragraph at the cursor
+    pub fn select_text_object(&mut self, object: text_objects::TextObject) {
+        self.collapse_selections();
+        self.needs_redraw = true;
+        let Some((top, left, bottom, right)) = text_objects::bounds(&self.grid, object, self.cursor_row, self.cursor_col) else {
+            self.status_message = format!("No {} at the cursor", object.name());
+            return;
+        };
+        self.grid.start_selection(top, left);
+        self.grid.update_selection(bottom, right);
+        self.status_message = match bottom - top + 1 {
+            1 => format!("Selected {}", object.name()),
+            rows => format!("Selected {} ({} rows)", object.name(), rows),
+        };
+    }
+
+    /// Backspace / Delete with a block selection: blank it as one undo step
+    pub fn delete_selection(&mut self) {
+        let Some((min_row, min_col, max_row, max_col)) = self.grid.selection.as_ref().map(|sel| sel.bounds()) else {
+            return;
+        };
+        self.write_block(min_row, min_col, vec![" ".repeat(max_col - min_col + 1); max_row - min_row + 1]);
+        self.grid.clear_selection();
+        self.cursor_row = min_row;
+        self.cursor_col = min_col;
+    }
+
+    /// Alt+S: turn the block selection into one selection per row, edited together
+    pub fn split_selection_into_lines(&mut self) {
+        let Some(block) = self.grid.selection.take() else {
+            self.status_message = "Select a block to split into lines first".to_string();
+            self.needs_redraw = true;
+            return;
+        };
+        let (min_row, min_col, max_row, max_col) = block.bounds();
+        let ranges: Vec<selections::Range> = (min_row..=max_row)
+            .map(|row| selections::Range { row, anchor: min_col, head: max_col + 1 })
+            .collect();
+        // The row the cursor is on stays primary
+        let primary = self.cursor_row.clamp(min_row, max_row) - min_row;
+        let selections = selections::Selections::new(ranges, primary);
+        self.status_message = format!("{} selections (Esc keeps one)", selections.len());
+        self.selections = Some(selections);
+        self.sync_cursor_to_selections();
+    }
+
+    /// Alt+O: open the link under the cursor
+    pub fn open_link_at_cursor(&mut self) {
+        if !self.open_link(self.cursor_row, self.cursor_col) {
+            self.status_message = "No link under the cursor".to_string();
+        }
+    }
+
+    /// Open the link covering (row, col) with the system's default handler, or the note a
+    /// [[link]] or embed token there names; false if there is none
+    pub fn open_link(&mut self, row: usize, col: usize) -> bool {
+        let line: Vec<char> = (0..self.grid.get_line_length(row)).map(|c| self.grid.get(row, c)).collect();
+        let mut note_tokens = transclusion::embeds_in_line(&line).into_iter().chain(transclusion::links_in_line(&line));
+        if let Some((_, _, title)) = note_tokens.find(|&(start, end, _)| (start..end).contains(&col)) {
+            if let Err(e) = self.open_note_titled(&title) {
+                self.status_message = format!("Could not open {}: {}", title, e);
+            }
+            return true;
+        }
+        let Some(link) = links::link_at(&self.grid, row, col) else {
+            return false;
+        };
+        self.status_message = match link.location().and_then(|location| links::open(&location)) {
+            Ok(()) => format!("Opened {}", link.target),
+            Err(e) => format!("Could not open {}: {}", link.target, e),
+        };
+        self.needs_redraw = true;
+        true
+    }
+
+    /// The block selection, or the cursor's line as a block; None on an empty line
+    fn selection_or_line(&self) -> Option<(usize, usize, usize, usize)> {
+        match self.grid.selection.as_ref() {
+            Some(selection) => Some(selection.bounds()),
+            None => {
+                let row = self.cursor_row;
+                self.grid.get_line_bounds(row).map(|(_, end)| (row, 0, row, end))
+            }
+        }
+    }
+
+    /// The block's text, one line per row with trailing spaces dropped
+    fn block_text(&self, (first_row, col, last_row, last_col): (usize, usize, usize, usize)) -> String {
+        (first_row..=last_row).map(|row| format!("{}\n", self.grid.get_line(row, col, last_col).trim_end())).collect()
+    }
+
+    /// Command palette → Filter selection: ask for the command to run the block selection
+    /// (or the cursor's line) through
+    pub fn open_filter_prompt(&mut self) {
+        let Some(block) = self.selection_or_line() else {
+            self.status_message = "Select a block or move to a line to filter".to_string();
+            return;
+        };
+        let rows = block.2 - block.0 + 1;
+        let title = format!("Filter {} through command (e.g. sort, jq ., fmt)", if rows == 1 { "1 line".to_string() } else { format!("{} lines", rows) });
+        self.overlays.push(overlay::Prompt::new(&title, move |command| OverlayAction::FilterBlock(block, command)));
+        self.needs_redraw = true;
+    }
+
+    /// Command palette → Color selection: pick a text color, highlight or style for the block
+    /// selection (or the cursor's line)
+    pub fn open_color_picker(&mut self) {
+        use highlighter::Paint;
+        use overlay::Swatch;
+        let Some(block) = self.selection_or_line() else {
+            self.status_message = "Select a block or move to a line to color".to_string();
+            return;
+        };
+        let swatches = |presets: &[(&str, (u8, u8, u8))], paint: fn((u8, u8, u8)) -> Paint| -> Vec<(Swatch, OverlayAction)> {
+            presets.iter().map(|&(_, color)| (Swatch::Color(color), OverlayAction::PaintBlock(block, paint(color)))).collect()
+        };
+        let styles = [(Swatch::Word("Bold"), Paint::Bold), (Swatch::Word("Underline"), Paint::Underline), (Swatch::Word("Clear"), Paint::Clear)];
+        let rows = vec![
+            ("Text".to_string(), swatches(config::highlighter::TEXT_COLORS, Paint::Text)),
+            ("Highlight".to_string(), swatches(config::highlighter::HIGHLIGHT_COLORS, Paint::Highlight)),
+            ("Style".to_string(), styles.into_iter().map(|(swatch, paint)| (swatch, OverlayAction::PaintBlock(block, paint))).collect()),
+        ];
+        self.overlays.push(overlay::SwatchPicker::new("Color selection", rows));
+        self.needs_redraw = true;
+    }
+
+    /// Put `paint` on the block's characters, or take it off when they all have it (one undo
+    /// step). The selection goes so the result shows.
+    pub fn paint_block(&mut self, block: (usize, usize, usize, usize), paint: highlighter::Paint) {
+        match highlighter::paint(&self.grid, block, paint) {
+            Some((command, on)) => {
+                command.execute(&mut self.grid);
+                self.undo_stack.push(command);
+                self.grid.clear_selection();
+                self.mark_dirty();
+                self.status_message = if on { paint.describe() } else { format!("{} removed", paint.describe()) };
+            }
+            None => self.status_message = "Nothing to color there".to_string(),
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Send the block's text to `command` and put the output in its place (one undo step)
+    pub fn filter_block(&mut self, block: (usize, usize, usize, usize), command: &str) {
+        if command.trim().is_empty() {
+            return;
+        }
+        let output = match shell::run(command, &self.block_text(block)) {
+            Ok(output) => shell::output_lines(&output),
+            Err(e) => {
+                self.status_message = format!("{} failed: {}", command, e);
+                self.needs_redraw = true;
+                return;
+            }
+        };
+        let rows = block.2 - block.0 + 1;
+        self.status_message = format!("{}: {} lines in, {} out", command, rows, output.len());
+        self.replace_block_with_output(block, output);
+    }
+
+    /// Put command output in place of the block (one undo step). Output with more lines than
+    /// the block opens rows below it; with fewer, the rest of the block is left blank.
+    fn replace_block_with_output(&mut self, (first_row, col, last_row, last_col): (usize, usize, usize, usize), output: Vec<String>) {
+        let rows = last_row - first_row + 1;
+        let width = last_col - col + 1;
+        let cells = |grid: &ChunkedGrid, row: usize, len: usize| -> String { (0..len).map(|i| grid.get(row, col + i)).collect() };
+        let mut commands = vec![undo::Command::PasteBlock {
+            row: first_row,
+            col,
+            lines: vec![" ".repeat(width); rows],
+            replaced_content: (first_row..=last_row).map(|row| cells(&self.grid, row, width)).collect(),
+        }];
+        commands.extend((rows..output.len()).map(|_| undo::Command::InsertRow { row: last_row + 1 }));
+        for command in &commands {
+            command.execute(&mut self.grid);
+        }
+        let paste = undo::Command::PasteBlock {
+            row: first_row,
+            col,
+            replaced_content: output.iter().enumerate().map(|(i, line)| cells(&self.grid, first_row + i, line.chars().count())).collect(),
+            lines: output,
+        };
+        paste.execute(&mut self.grid);
+        commands.push(paste);
+        self.undo_stack.push(undo::Command::Group { commands });
+
+        self.grid.clear_selection();
+        self.cursor_row = first_row;
+        self.cursor_col = col;
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Command palette → Move selection to new note: ask for the new note's title
+    pub fn open_extract_prompt(&mut self) {
+        let Some(block) = self.grid.selection.as_ref().map(|selection| selection.bounds()) else {
+            self.status_message = "Select the text to move to a new note first".to_string();
+            return;
+        };
+        let title = "Title for the new note (empty: its first line)";
+        self.overlays.push(overlay::Prompt::new(title, move |title| OverlayAction::ExtractToNote(block, title)));
+        self.needs_redraw = true;
+    }
+
+    /// Create a note holding the block's text, then offer to replace the block with a
+    /// [[link]] to it
+    pub fn extract_to_note(&mut self, (first_row, col, last_row, last_col): (usize, usize, usize, usize), title: &str) -> Result<()> {
+        let content = (first_row..=last_row)
+            .map(|row| self.grid.get_line(row, col, last_col).trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let title = match title.trim() {
+            "" => note_format::first_line_title_of(&content).unwrap_or_else(|| "Untitled".to_string()),
+            title => title.to_string(),
+        };
+        if !transclusion::linkable(&title) {
+            self.status_message = "A linked note's title can't contain [ or ]".to_string();
+            self.needs_redraw = true;
+            return Ok(());
+        }
+
+        let note = self.notes_mode.db.create_note(title, content, vec![])?;
+        self.hooks.fire(hooks::Event::Create, &note);
+        self.invalidate_notes_list();
+        self.refresh_notes_list();
+        self.status_message = format!("Created \"{}\"", note.title);
+        let message = format!("Replace the selection with {}?", transclusion::link_token(&note.title));
+        let action = OverlayAction::LinkExtracted((first_row, col, last_row, last_col), note.title);
+        self.overlays.push(overlay::Confirm::new(&message, action));
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Blank the block and write a [[link]] to `title` at its top left (one undo step)
+    pub fn replace_block_with_link(&mut self, (first_row, col, last_row, last_col): (usize, usize, usize, usize), title: &str) {
+        let link = transclusion::link_token(title);
+        let cells = |grid: &ChunkedGrid, row: usize, len: usize| -> String { (0..len).map(|i| grid.get(row, col + i)).collect() };
+        let rows = last_row - first_row + 1;
+        let width = last_col - col + 1;
+        let blank = undo::Command::PasteBlock {
+            row: first_row,
+            col,
+            lines: vec![" ".repeat(width); rows],
+            replaced_content: (first_row..=last_row).map(|row| cells(&self.grid, row, width)).collect(),
+        };
+        blank.execute(&mut self.grid);
+        let paste = undo::Command::PasteBlock {
+            row: first_row,
+            col,
+            replaced_content: vec![cells(&self.grid, first_row, link.chars().count())],
+            lines: vec![link.clone()],
+        };
+        paste.execute(&mut self.grid);
+        self.undo_stack.push(undo::Command::Group { commands: vec![blank, paste] });
+
+        self.grid.clear_selection();
+        self.cursor_row = first_row;
+        self.cursor_col = col + link.chars().count();
+        self.status_message = format!("Moved the selection to \"{}\"", title);
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Run `command` and insert its output at the cursor, in rows opened below it; long
+    /// output is cut to MAX_INSERT_LINES and asks for confirmation first
+    pub fn insert_command_output(&mut self, command: &str) {
+        use config::shell::{INSERT_CONFIRM_LINES, MAX_INSERT_LINES};
+        if command.trim().is_empty() {
+            return;
+        }
+        let mut lines = match shell::run(command, "") {
+            Ok(output) => shell::output_lines(&output),
+            Err(e) => {
+                self.status_message = format!("{} failed: {}", command, e);
+                return;
+            }
+        };
+        if lines.is_empty() {
+            self.status_message = format!("{} printed nothing", command);
+            return;
+        }
+        let total = lines.len();
+        lines.truncate(MAX_INSERT_LINES);
+        if total > INSERT_CONFIRM_LINES {
+            let cut = if total > MAX_INSERT_LINES { format!(" (the first {} of {})", MAX_INSERT_LINES, total) } else { String::new() };
+            let message = format!("Insert {} lines{} from {}?", lines.len(), cut, command);
+            self.overlays.push(overlay::Confirm::new(&message, OverlayAction::InsertLines(lines)));
+        } else {
+            self.insert_lines(lines);
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Insert text lines at the cursor, opening rows below it for all but the first
+    fn insert_lines(&mut self, lines: Vec<String>) {
+        let count = lines.len();
+        self.insert_block_at_cursor(lines, count.saturating_sub(1));
+        self.status_message = format!("Inserted {} lines", count);
+    }
+
+    /// Run command `index` of plugin `plugin` on the selection, the cursor's line or the note,
+    /// and use its output as the plugin asks
+    pub fn run_plugin_command(&mut self, plugin: usize, index: usize) -> Result<()> {
+        use plugins::{Input, Output};
+        let Some(command) = self.plugins.get(plugin).and_then(|p| p.commands.get(index)).cloned() else {
+            return Ok(());
+        };
+        let block = match command.input {
+            Input::None => None,
+            Input::Selection => self.selection_or_line(),
+            Input::Note => self.grid.bounds(),
+        };
+        if command.output == Output::Replace && block.is_none() {
+            self.status_message = format!("{}: nothing to work on", command.label);
+            return Ok(());
+        }
+        let input = block.map(|block| self.block_text(block)).unwrap_or_default();
+        let output = match self.plugins[plugin].run(index, &input, self.notes_mode.current_note.as_ref()) {
+            Ok(output) => output,
+            Err(e) => {
+                self.status_message = format!("{} failed: {}", command.label, e);
+                return Ok(());
+            }
+        };
+        let mut lines = shell::output_lines(&output);
+        match command.output {
+            Output::Replace => {
+                if let Some(block) = block {
+                    self.replace_block_with_output(block, lines);
+                    self.status_message = format!("{} done", command.label);
+                }
+            }
+            Output::Insert if lines.is_empty() => self.status_message = format!("{} printed nothing", command.label),
+            Output::Insert => {
+                lines.truncate(config::shell::MAX_INSERT_LINES);
+                self.insert_lines(lines);
+            }
+            Output::NewNote => {
+                let content = lines.join("\n");
+                let title = note_format::first_line_title_of(&content).unwrap_or_else(|| command.label.clone());
+                let note = self.notes_mode.db.create_note(title, content, vec![])?;
+                self.hooks.fire(hooks::Event::Create, &note);
+                self.invalidate_notes_list();
+                self.refresh_notes_list();
+                self.status_message = format!("{} created \"{}\"", command.label, note.title);
+            }
+            Output::Status => {
+                let first = lines.iter().map(|line| line.trim()).find(|line| !line.is_empty()).unwrap_or("done");
+                self.status_message = format!("{}: {}", command.label, first);
+            }
+            Output::None => self.status_message = format!("{} done", command.label),
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Open the note with this title (the most recently edited one, ignoring case); false if
+    /// there is none
+    pub fn open_note_titled(&mut self, title: &str) -> Result<bool> {
+        let Some(note) = self.notes_mode.db.find_note_by_title(title)? else {
+            self.status_message = format!("No note named \"{}\"", title);
+            return Ok(false);
+        };
+        self.open_note_by_id(&note.id)?;
+        Ok(true)
+    }
+
+    /// Another `chonk-note` invocation found this editor running: open its note if it named
+    /// one, draw attention with the bell, and tell it what happened
+    pub fn answer_other_instance(&mut self, title: Option<String>) -> String {
+        let answer = match title {
+            Some(title) => match self.open_note_titled(&title) {
+                Ok(true) => {
+                    self.status_message = format!("Opened \"{}\" from another terminal", title);
+                    format!("Opened \"{}\" in the running chonk-note", title)
+                }
+                Ok(false) => format!("chonk-note is already running, but has no note named \"{}\"", title),
+                Err(e) => format!("chonk-note is already running; opening \"{}\" failed: {}", title, e),
+            },
+            None => {
+                self.status_message = "chonk-note was started again in another terminal".to_string();
+                "chonk-note is already running".to_string()
+            }
+        };
+        print!("\x07");
+        let _ = io::stdout().flush();
+        self.needs_redraw = true;
+        answer
+    }
+
+    /// Command palette → Embed note: pick the note to show at the cursor
+    pub fn open_embed_picker(&mut self) {
+        let titles = match self.notes_mode.db.list_titles() {
+            Ok(titles) => titles,
+            Err(e) => {
+                self.status_message = format!("Could not list notes: {}", e);
+                return;
+            }
+        };
+        let items = titles.into_iter().map(|(_, title)| (title.clone(), OverlayAction::EmbedNote(title))).collect();
+        self.overlays.push(overlay::ListPicker::new("Embed note", items));
+        self.needs_redraw = true;
+    }
+
+    /// Start finding the open note's backlinks on the database thread, when the panel shows
+    /// them and they may be out of date
+    pub fn refresh_backlinks(&mut self) {
+        let Some(note) = self.notes_mode.current_note.as_ref() else {
+            return;
+        };
+        if !self.backlinks_panel_expanded || !self.backlinks.needs_search(&note.id) {
+            return;
+        }
+        let request = Request::FindMentions { note_id: note.id.clone(), title: note.title.clone() };
+        self.backlinks.start_search();
+        if let Err(e) = self.send_db_request(request) {
+            log::error!("Finding backlinks failed: {}", e);
+        }
+    }
+
+    /// Open the note of backlinks entry `index` at the mention
+    pub fn open_mention(&mut self, index: usize) -> Result<()> {
+        let Some(mention) = self.backlinks.mentions.get(index).cloned() else {
+            return Ok(());
+        };
+        self.open_note_by_id(&mention.note_id)?;
+        if self.notes_mode.current_note.as_ref().is_some_and(|note| note.id == mention.note_id) {
+            // Mentions count lines from the content's top left, like the stored text
+            let (top, left) = self.grid.bounds().map_or((0, 0), |(row, col, _, _)| (row, col));
+            self.jump_to(top + mention.line, left + mention.col);
+        }
+        Ok(())
+    }
+
+    /// Turn unlinked mention `index` into a [[link]] to the open note
+    pub fn link_mention(&mut self, index: usize) {
+        let (Some(mention), Some(note)) = (self.backlinks.mentions.get(index), self.notes_mode.current_note.as_ref()) else {
+            return;
+        };
+        self.status_message = match backlinks::link(&self.notes_mode.db, mention, &note.title) {
+            Ok(true) => format!("Linked the mention in \"{}\"", mention.note_title),
+            Ok(false) => format!("\"{}\" has changed since; looking again", mention.note_title),
+            Err(e) => format!("Could not link: {}", e),
+        };
+        self.invalidate_notes_list();
+        self.refresh_notes_list();
+        self.needs_redraw = true;
+    }
+
+    /// Bring the tasks panel up to date: the open note from the editor, and a scan of the
+    /// others on the database thread when notes have changed
+    pub fn refresh_tasks(&mut self) {
+        if !self.tasks_panel_expanded {
+            return;
+        }
+        if self.tasks.start_scan() {
+            let except_id = self.notes_mode.current_note.as_ref().map(|note| note.id.clone());
+            if let Err(e) = self.send_db_request(Request::FindTasks { except_id }) {
+                log::error!("Gathering tasks failed: {}", e);
+            }
+        }
+        let note = self.notes_mode.current_note.as_ref().map(|note| (note.id.as_str(), note.title.as_str()));
+        self.tasks.refresh_current(note, &self.grid);
+    }
+
+    /// Open the note of task `index` at its marker
+    pub fn open_task(&mut self, index: usize) -> Result<()> {
+        let Some(task) = self.tasks.items.get(index).cloned() else {
+            return Ok(());
+        };
+        if self.notes_mode.current_note.as_ref().is_none_or(|note| note.id != task.note_id) {
+            self.open_note_by_id(&task.note_id)?;
+        }
+        if self.notes_mode.current_note.as_ref().is_some_and(|note| note.id == task.note_id) {
+            let (top, left) = self.grid.bounds().map_or((0, 0), |(row, col, _, _)| (row, col));
+            self.jump_to(top + task.line, left + task.col);
+            self.grid.clear_selection();
+        }
+        Ok(())
+    }
+
+    /// Mark task `index` done, or open again: in the editor (one undo step) for the open
+    /// note, in the database for the others
+    pub fn toggle_task(&mut self, index: usize) {
+        let Some(task) = self.tasks.items.get(index).cloned() else {
+            return;
+        };
+        let replacement = task.toggled();
+        let state = if task.done { "open again" } else { "done" };
+        if self.notes_mode.current_note.as_ref().is_some_and(|note| note.id == task.note_id) {
+            let (top, left) = self.grid.bounds().map_or((0, 0), |(row, col, _, _)| (row, col));
+            let (row, col) = (top + task.line, left + task.col);
+            let current: String = (0..task.marker.chars().count()).map(|i| self.grid.get(row, col + i)).collect();
+            if current != task.marker {
+                self.tasks.invalidate_current();
+                return;
+            }
+            let command = undo::Command::PasteBlock { row, col, lines: vec![replacement.to_string()], replaced_content: vec![current] };
+            command.execute(&mut self.grid);
+            self.undo_stack.push(command);
+            self.mark_dirty();
+            self.status_message = format!("Marked \"{}\" {}", task.text, state);
+        } else {
+            self.status_message = match tasks::toggle(&self.notes_mode.db, &task) {
+                Ok(true) => format!("Marked \"{}\" {} in \"{}\"", task.text, state, task.note_title),
+                Ok(false) => format!("\"{}\" has changed since; looking again", task.note_title),
+                Err(e) => format!("Could not change the task: {}", e),
+            };
+            self.invalidate_notes_list();
+            self.refresh_notes_list();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Pick a note to pin read-only on the right
+    pub fn open_preview_picker(&mut self) {
+        let titles = match self.notes_mode.db.list_titles() {
+            Ok(titles) => titles,
+            Err(e) => {
+                self.status_message = format!("Could not list notes: {}", e);
+                return;
+            }
+        };
+        let items = titles.into_iter().map(|(id, title)| (title, OverlayAction::PinPreview(id))).collect();
+        self.overlays.push(overlay::ListPicker::new("Pin note preview", items));
+        self.needs_redraw = true;
+    }
+
+    pub fn pin_preview(&mut self, note_id: String) {
+        let mut preview = preview::Preview::new(note_id);
+        match preview.refresh(&self.notes_mode.db) {
+            Ok(()) => {
+                self.status_message = format!("Pinned \"{}\" (read-only)", preview.title);
+                self.preview = Some(preview);
+            }
+            Err(e) => self.status_message = format!("Could not read note: {}", e),
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn close_preview(&mut self) {
+        if self.preview.take().is_some() {
+            self.status_message = "Preview closed".to_string();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Write an embed token for `title` at the cursor, with blank rows opened below it for
+    /// the embedded note's lines
+    pub fn embed_note(&mut self, title: &str) {
+        let room = match self.embeds.get(&self.notes_mode.db, title) {
+            Ok(transclusion::Source::Note { lines, .. }) => lines.len().clamp(1, config::transclusion::MAX_ROWS),
+            _ => 1,
+        };
+        self.insert_block_at_cursor(vec![transclusion::token(title)], room);
+        self.status_message = format!("Embedded \"{}\"", title);
+    }
+
+    /// Write `lines` as a block at the cursor after opening `open_rows` empty rows below the
+    /// cursor's row, as one undo step; the cursor ends after the last line
+    fn insert_block_at_cursor(&mut self, lines: Vec<String>, open_rows: usize) {
+        let (row, col) = (self.cursor_row, self.cursor_col);
+        let mut commands: Vec<undo::Command> = (0..open_rows).map(|_| undo::Command::InsertRow { row: row + 1 }).collect();
+        for command in &commands {
+            command.execute(&mut self.grid);
+        }
+        let replaced_content = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| (0..line.chars().count()).map(|c| self.grid.get(row + i, col + c)).collect())
+            .collect();
+        let paste = undo::Command::PasteBlock { row, col, lines: lines.clone(), replaced_content };
+        paste.execute(&mut self.grid);
+        commands.push(paste);
+        self.undo_stack.push(undo::Command::Group { commands });
+
+        self.grid.clear_selection();
+        self.cursor_row = row + lines.len().saturating_sub(1);
+        self.cursor_col = col + lines.last().map_or(0, |line| line.chars().count());
+        self.mark_dirty();
+        self.needs_redraw = true;
+    }
+
+    /// Alt+R: show the file path under the cursor in the file manager
+    pub fn reveal_path_at_cursor(&mut self) {
+        let link = links::link_at(&self.grid, self.cursor_row, self.cursor_col).filter(|link| link.kind == links::LinkKind::Path);
+        self.status_message = match link {
+            Some(link) => match link.location().and_then(|location| links::reveal(&location)) {
+                Ok(()) => format!("Revealed {}", link.target),
+                Err(e) => format!("Could not reveal {}: {}", link.target, e),
+            },
+            None => "No file path under the cursor".to_string(),
+        };
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+Click: add a caret there (or remove one already there), keeping the cursor as another
+    pub fn add_caret(&mut self, row: usize, col: usize) {
+        self.grid.clear_selection();
+        let cursor = selections::Range::caret(self.cursor_row, self.cursor_col);
+        let selections = self.selections.get_or_insert_with(|| selections::Selections::new(vec![cursor], 0));
+        selections.toggle_caret(row, col);
+        self.status_message = format!("{} cursors (Esc keeps one)", selections.len());
+        self.sync_cursor_to_selections();
+    }
+
+    /// Text Ctrl+D and Ctrl+Shift+L look for: the primary selection, a one-row block selection,
+    /// or the word under the cursor
+    fn occurrence_target(&self) -> Option<selections::Range> {
+        if let Some(selections) = self.selections.as_ref() {
+            return Some(selections.primary()).filter(|range| !range.is_empty());
+        }
+        match self.grid.selection.as_ref().map(|block| block.bounds()) {
+            Some((row, min_col, max_row, max_col)) if row == max_row => {
+                Some(selections::Range { row, anchor: min_col, head: max_col + 1 })
+            }
+            _ => selections::word_at(&self.grid, self.cursor_row, self.cursor_col),
+        }
+    }
+
+    /// Ctrl+D: select the word under the cursor, then each next occurrence of it in turn
+    pub fn select_next_occurrence(&mut self) {
+        let Some(target) = self.occurrence_target() else {
+            self.status_message = "No word under the cursor".to_string();
+            self.needs_redraw = true;
+            return;
+        };
+        let text = selections::text_of(&self.grid, target);
+        let Some(selections) = self.selections.as_mut() else {
+            self.grid.clear_selection();
+            self.status_message = format!("Selected \"{}\" (Ctrl+D again for the next one)", text);
+            self.selections = Some(selections::Selections::new(vec![target], 0));
+            self.sync_cursor_to_selections();
+            return;
+        };
+
+        if let Some(next) = selections::next_occurrence(&self.grid, &text, target, selections.ranges()) {
+            selections.add(next);
+            self.status_message = format!("{} occurrences of \"{}\" selected", selections.len(), text);
+        } else {
+            self.status_message = format!("No more occurrences of \"{}\"", text);
+        }
+        self.sync_cursor_to_selections();
+    }
+
+    /// Ctrl+Shift+L: select every occurrence of the word under the cursor (or the selection)
+    pub fn select_all_occurrences(&mut self) {
+        let Some(target) = self.occurrence_target() else {
+            self.status_message = "No word under the cursor".to_string();
+            self.needs_redraw = true;
+            return;
+        };
+        let text = selections::text_of(&self.grid, target);
+        let ranges = selections::occurrences(&self.grid, &text);
+        // The occurrence the cursor was on stays primary
+        let primary = ranges.iter().position(|r| r.row == target.row && r.start() == target.start()).unwrap_or(0);
+        self.grid.clear_selection();
+        self.status_message = format!("{} occurrences of \"{}\" selected", ranges.len(), text);
+        self.selections = Some(selections::Selections::new(ranges, primary));
+        self.sync_cursor_to_selections();
+    }
+
+    /// Apply an edit to every selection as one undo step (a typing burst at the same carets is one step)
+    pub fn edit_selections(&mut self, edit: impl FnOnce(&mut selections::Selections, &mut ChunkedGrid) -> Vec<undo::Command>) {
+        let Some(selections) = self.selections.as_mut() else {
+            return;
+        };
+        let was_typing = selections.is_typing();
+        let commands = edit(selections, &mut self.grid);
+        let continues = was_typing && selections.is_typing();
+        if !commands.is_empty() {
+            self.undo_stack.push_continued(commands, continues);
+            self.mark_dirty();
+        }
+        self.sync_cursor_to_selections();
+    }
+
+    /// Put the cursor at the head of the primary selection
+    pub fn sync_cursor_to_selections(&mut self) {
+        if let Some(primary) = self.selections.as_ref().map(|s| s.primary()) {
+            self.cursor_row = primary.row;
+            self.cursor_col = primary.head;
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Back to a single cursor (at the primary selection)
+    pub fn collapse_selections(&mut self) {
+        if self.selections.take().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Ctrl+C: copy the block selection to the internal and system clipboards
+    pub fn copy_selection(&mut self) {
+        if let Some(copied) = self.grid.copy_block() {
+            self.keep_block_clipboard(copied.clone());
+            primary_selection::set(&copied.join("\n"));
+
+            // Also copy to system clipboard
+            self.status_message = match self.set_clipboard(&copied.join("\n")) {
+                Ok(destination) => format!("Copied {} rows to {}", copied.len(), destination),
+                Err(e) => format!("Copied {} rows (clipboard error: {})", copied.len(), e),
+            };
+
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Put text on the system clipboard, or send it through the terminal (OSC 52) when
+    /// Settings prefers that or the system clipboard is unreachable (SSH, no display).
+    /// Returns where the text went, for the status line.
+    fn set_clipboard(&self, text: &str) -> Result<&'static str> {
+        if !self.settings.osc52_clipboard {
+            match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.to_string())) {
+                Ok(()) => return Ok("system clipboard"),
+                Err(e) => log::debug!("System clipboard unavailable, falling back to OSC 52: {}", e),
+            }
+        }
+        KittyTerminal::set_clipboard_osc52(text)?;
+        Ok("terminal clipboard")
+    }
+
+    /// Remember a copied block, and the styles of the selected cells it came from, for pasting
+    fn keep_block_clipboard(&mut self, lines: Vec<String>) {
+        let styles = self.grid.copy_block_styles().filter(|styles| styles.iter().flatten().any(|style| !style.is_plain()));
+        self.block_clipboard = Some(lines);
+        self.block_clipboard_styles = styles.unwrap_or_default();
+    }
+
+    /// Ctrl+X: copy the block selection and blank it (undoable)
+    pub fn cut_selection(&mut self) {
+        let (Some(cut), Some((min_row, min_col, max_row, max_col))) = (self.grid.copy_block(), self.grid.selection.as_ref().map(|sel| sel.bounds())) else {
+            return;
+        };
+        self.keep_block_clipboard(cut.clone());
+
+        // Blank the block as one undo step (its styles come back on undo too)
+        self.write_block(min_row, min_col, vec![" ".repeat(max_col - min_col + 1); max_row - min_row + 1]);
+        self.grid.clear_selection();
+
+        // Also copy to system clipboard
+        self.status_message = match self.set_clipboard(&cut.join("\n")) {
+            Ok(destination) => format!("Cut {} rows to {}", cut.len(), destination),
+            Err(e) => format!("Cut {} rows (clipboard error: {})", cut.len(), e),
+        };
+    }
+
+    /// Paste the internal block clipboard at (row, col) with its styles; false if it is empty
+    fn paste_block_clipboard(&mut self, row: usize, col: usize) -> bool {
+        let Some(lines) = self.block_clipboard.clone().filter(|lines| !lines.is_empty()) else {
+            return false;
+        };
+        self.status_message = format!("Pasted {} rows", lines.len());
+        let styles = self.block_clipboard_styles.clone();
+        self.write_styled_block(row, col, lines, &styles);
+        true
+    }
+
+    /// Ctrl+V: paste the system clipboard at the cursor, falling back to the internal block clipboard
+    pub fn paste_clipboard(&mut self) {
+        self.cursor_col = self.snap_column(self.cursor_row, self.cursor_col);
+        // Try system clipboard first
+        if let Ok(mut clipboard) = arboard::Clipboard::new() {
+            if let Ok(text) = clipboard.get_text() {
+                // Styled cells copied here: paste them back with their styles
+                let own_copy = self.block_clipboard.as_ref().is_some_and(|lines| text.lines().eq(lines.iter().map(String::as_str)));
+                if own_copy && !self.block_clipboard_styles.is_empty() {
+                    self.paste_block_clipboard(self.cursor_row, self.cursor_col);
+                    return;
+                }
+                // Terminal output with color codes keeps its colors (and its layout: no wrapping)
+                if ansi::has_escapes(&text) {
+                    self.paste_ansi(self.cursor_row, self.cursor_col, &text);
+                    return;
+                }
+                let raw_lines: Vec<String> = text.lines().map(|s| s.to_string()).collect();
+
+                // Apply word wrapping if soft_wrap_paste is enabled
+                let final_lines = if self.soft_wrap_paste {
+                    // Word wrap long lines at 150 characters
+                    const MAX_LINE_WIDTH: usize = 150;
+                    let mut wrapped_lines = Vec::new();
+
+                    for line in raw_lines {
+                        if line.chars().count() <= MAX_LINE_WIDTH {
+                            wrapped_lines.push(line);
+                        } else {
+                            // Word wrap this line
+                            let words: Vec<&str> = line.split_whitespace().collect();
+                            let mut current_line = String::new();
+
+                            for word in words {
+                                // If adding this word would exceed the limit, start a new line
+                                let test_line = if current_line.is_empty() {
+                                    word.to_string()
+                                } else {
+                                    format!("{} {}", current_line, word)
+                                };
+
+                                if test_line.chars().count() > MAX_LINE_WIDTH && !current_line.is_empty() {
+                                    // Push current line and start a new one
+                                    wrapped_lines.push(current_line.clone());
+                                    current_line = word.to_string();
+                                } else {
+                                    current_line = test_line;
+                                }
+                            }
+
+                            // Push the last line if not empty
+                            if !current_line.is_empty() {
+                                wrapped_lines.push(current_line);
+                            }
+                        }
+                    }
+                    wrapped_lines
+                } else {
+                    // No wrapping - use raw lines as-is
+                    raw_lines
+                };
+
+                // Collect replaced content for undo
+                if !final_lines.is_empty() {
+                    let mut replaced_content = Vec::new();
+                    for (line_idx, line) in final_lines.iter().enumerate() {
+                        let target_row = self.cursor_row + line_idx;
+                        let start_col = if line_idx == 0 { self.cursor_col } else { 0 };
+                        let mut row_content = String::new();
+
+                        for (col_offset, _) in line.chars().enumerate() {
+                            row_content.push(self.grid.get(target_row, start_col + col_offset));
+                        }
+                        replaced_content.push(row_content);
+                    }
+
+                    // Create undo command for paste
+                    let cmd = undo::Command::PasteBlock {
+                        row: self.cursor_row,
+                        col: self.cursor_col,
+                        lines: final_lines.clone(),
+                        replaced_content,
+                    };
+                    cmd.execute(&mut self.grid);
+                    self.undo_stack.push(cmd);
+
+                    let status_suffix = if self.soft_wrap_paste { " (wrapped)" } else { "" };
+                    self.status_message = format!("Pasted {} rows{}", final_lines.len(), status_suffix);
+                    self.mark_dirty();
+                    self.needs_redraw = true;
+                    return;
+                }
+            }
+
+            // No text: an image (a screenshot, say) becomes an attachment of the note
+            if let Ok(image) = clipboard.get_image() {
+                self.paste_image(image.width, image.height, &image.bytes);
+                return;
+            }
+        }
+
+        // Fall back to internal block clipboard
+        self.paste_block_clipboard(self.cursor_row, self.cursor_col);
+    }
+
+    /// Right-click: open the context menu at a 0-based screen position
+    pub fn open_context_menu(&mut self, x: u16, y: u16, term_width: u16, term_height: u16) {
+        use popup_menu::MenuItem;
+        let has_selection = self.grid.selection.is_some();
+        let items = vec![
+            MenuItem { action: OverlayAction::Context(ContextAction::Cut), label: "Cut", hint: "Ctrl+X", enabled: has_selection },
+            MenuItem { action: OverlayAction::Context(ContextAction::Copy), label: "Copy", hint: "Ctrl+C", enabled: has_selection },
+            MenuItem { action: OverlayAction::Context(ContextAction::Paste), label: "Paste", hint: "Ctrl+V", enabled: true },
+            MenuItem { action: OverlayAction::Context(ContextAction::SelectAll), label: "Select All", hint: "Ctrl+A", enabled: true },
+            MenuItem { action: OverlayAction::Context(ContextAction::SearchSelection), label: "Search for selection", hint: "", enabled: has_selection },
+            MenuItem { action: OverlayAction::Context(ContextAction::Color), label: "Color...", hint: "", enabled: has_selection },
+        ];
+        // Keep the status line visible below the menu
+        self.overlays.push(popup_menu::PopupMenu::new(x, y, items, term_width, term_height.saturating_sub(1)));
+        self.needs_redraw = true;
+    }
+
+    pub fn run_context_action(&mut self, action: ContextAction) {
+        match action {
+            ContextAction::Cut => self.cut_selection(),
+            ContextAction::Copy => self.copy_selection(),
+            ContextAction::Paste => self.paste_clipboard(),
+            ContextAction::SelectAll => self.select_all(),
+            ContextAction::SearchSelection => self.search_for_selection(),
+            ContextAction::Color => self.open_color_picker(),
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Search the note for the first line of the block selection
+    pub fn search_for_selection(&mut self) {
+        let Some(query) = self.grid.copy_block().and_then(|lines| lines.into_iter().map(|l| l.trim().to_string()).find(|l| !l.is_empty())) else {
+            self.status_message = "Nothing selected to search for".to_string();
+            return;
+        };
+        self.search_query = query;
+        self.perform_search();
+        self.grid.clear_selection();
+        if self.search_results.is_empty() {
+            self.status_message = format!("No matches for \"{}\"", self.search_query);
+        } else {
+            let (row, col) = self.search_results[0];
+            self.jump_to(row, col);
+            self.status_message = format!("Found {} matches for \"{}\" (Ctrl+N/Ctrl+P)", self.search_results.len(), self.search_query);
+        }
+    }
+
+    /// Store a clipboard image (8-bit RGBA) as a PNG attachment of the open note and write its
+    /// token at the cursor, with rows opened below for the thumbnail. One undo step; undoing
+    /// leaves the attachment stored.
+    fn paste_image(&mut self, width: usize, height: usize, rgba: &[u8]) {
+        let Some(note_id) = self.notes_mode.current_note.as_ref().map(|note| note.id.clone()) else {
+            self.status_message = "Open a note before pasting an image".to_string();
+            return;
+        };
+        let db = &self.notes_mode.db;
+        let stored = images::encode_png(width as u32, height as u32, rgba).and_then(|png| {
+            let filename = format!("pasted-{}.png", db.count_attachments(&note_id)? + 1);
+            let attachment = notes_database::Attachment { filename: filename.clone(), mime: "image/png".to_string(), data: png };
+            db.add_attachment(&note_id, &attachment)?;
+            Ok(filename)
+        });
+        let filename = match stored {
+            Ok(filename) => filename,
+            Err(e) => {
+                log::error!("Storing pasted image failed: {}", e);
+                self.status_message = format!("Could not store the pasted image: {}", e);
+                return;
+            }
+        };
+
+        self.insert_block_at_cursor(vec![images::token(&filename)], config::images::THUMBNAIL_ROWS);
+        self.status_message = format!("Pasted {}x{} image as {}", width, height, filename);
+    }
+
+    /// Paste text carrying ANSI color codes as a block at (row, col), its colors becoming cell
+    /// styles (one undo step)
+    fn paste_ansi(&mut self, row: usize, col: usize, text: &str) {
+        let lines = ansi::parse(text);
+        let styles: Vec<Vec<Style>> = lines.iter().map(|line| line.iter().map(|&(_, style)| style).collect()).collect();
+        self.write_styled_block(row, col, ansi::plain_lines(&lines), &styles);
+        self.status_message = format!("Pasted {} rows with their colors", lines.len());
+    }
+
+    /// Middle-click: paste the primary selection (or the last internal copy) as a block at (row, col)
+    pub fn paste_primary_at(&mut self, row: usize, col: usize) {
+        let col = self.snap_column(row, col);
+        let selection = primary_selection::get();
+        self.cursor_row = row;
+        self.cursor_col = col;
+        self.grid.clear_selection();
+        if let Some(text) = selection.as_deref().filter(|text| ansi::has_escapes(text)) {
+            self.paste_ansi(row, col, text);
+            self.needs_redraw = true;
+            return;
+        }
+        let lines: Vec<String> = selection.map(|text| text.lines().map(|s| s.to_string()).collect()).unwrap_or_default();
+        if !lines.is_empty() {
+            self.status_message = format!("Pasted {} rows", lines.len());
+            self.write_block(row, col, lines);
+        } else if !self.paste_block_clipboard(row, col) {
+            self.status_message = "Nothing to paste".to_string();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+T: show sum/average/min/max of the numbers in the block selection
+    pub fn show_selection_stats(&mut self) {
+        let Some((min_row, min_col, max_row, _)) = self.grid.selection.as_ref().map(|s| s.bounds()) else {
+            self.status_message = "Select a block of numbers first".to_string();
+            return;
+        };
+        let lines = self.grid.copy_block().unwrap_or_default();
+        match column_stats::ColumnStats::from_lines(&lines) {
+            Some(stats) => {
+                let f = column_stats::format_number;
+                self.status_message = format!(
+                    "Sum {}  Avg {}  Min {}  Max {}  ({} numbers)  Insert below: [S]um [A]vg [N] min [X] max",
+                    f(stats.sum), f(stats.average), f(stats.min), f(stats.max), stats.count);
+                self.pending_column_stats = Some((stats, max_row + 1, min_col));
+            }
+            None => self.status_message = format!("No numbers in rows {}-{}", min_row + 1, max_row + 1),
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Mark note as dirty (needs saving)
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+        self.outline.invalidate();
+        self.tasks.invalidate_current();
+        self.word_count.invalidate();
+    }
+
+    /// Replace the editor contents with a note and reset cursor/viewport
+    pub fn load_note(&mut self, note: notes_database::Note) {
+        if self.notes_mode.current_note.as_ref().is_some_and(|current| current.id != note.id) {
+            self.record_jump();
+        }
+        if self.notes_mode.current_note.as_ref().is_none_or(|current| current.id != note.id) {
+            self.hooks.fire(hooks::Event::NoteOpen, &note);
+        }
+        self.grid = note_format::decode(&note.content);
+        self.selections = None;
+        self.cursor_row = 0;
+        self.cursor_col = 0;
+        self.viewport_row = 0;
+        self.viewport_col = 0;
+        self.notes_mode.current_note = Some(note);
+        self.thumbnails.clear();
+        self.embeds.invalidate();
+        self.outline.invalidate();
+        // The note left behind is scanned with the others from now on
+        self.tasks.invalidate();
+        self.word_count.invalidate();
+        self.undo_stack.clear();
+    }
+
+    /// Restore the grid to the state selected in the history browser
+    pub fn jump_to_history_entry(&mut self, index: usize) {
+        let entries = self.undo_stack.history();
+        if let Some(entry) = entries.get(index) {
+            if self.undo_stack.jump_to(entry.id, &mut self.grid) {
+                self.status_message = format!("Restored: {}", entry.label);
+                self.mark_dirty();
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Move the cursor to an outline heading
+    pub fn jump_to_heading(&mut self, index: usize) {
+        if let Some(heading) = self.outline.headings.get(index).cloned() {
+            self.jump_to(heading.row, heading.col);
+            self.grid.clear_selection();
+            self.status_message = format!("Jumped to \"{}\"", heading.text);
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Start the go-to-line prompt in the status line
+    pub fn open_goto_line(&mut self) {
+        self.goto_line_mode = true;
+        self.goto_line_query.clear();
+        self.status_message = "Go to line: ".to_string();
+        self.needs_redraw = true;
+    }
+
+    /// Jump to "line" or "line:col" (1-based, relative to the origin; zero or negative
+    /// values are above/left of it) from the go-to-line prompt
+    pub fn goto_line(&mut self, query: &str) -> bool {
+        let mut parts = query.trim().splitn(2, ':');
+        let Some(Ok(line)) = parts.next().map(|p| p.trim().parse::<i64>()) else {
+            return false;
+        };
+        let col = match parts.next() {
+            Some(p) => match p.trim().parse::<i64>() {
+                Ok(c) => c,
+                Err(_) => return false,
+            },
+            None => 1,
+        };
+
+        self.record_jump();
+        self.move_to_logical(line - 1, col - 1);
+        self.grid.clear_selection();
+        true
+    }
+
+    /// Put the cursor at a 0-based logical position, growing the canvas if it is above/left of it
+    pub fn move_to_logical(&mut self, line: i64, col: i64) {
+        let mut row = self.grid.origin.0 as i64 + line;
+        let mut col = self.grid.origin.1 as i64 + col;
+        let chunk = chunked_grid::CHUNK_SIZE as i64;
+        while row < 0 || col < 0 {
+            self.extend_canvas(row < 0, col < 0);
+            if row < 0 {
+                row += chunk;
+            }
+            if col < 0 {
+                col += chunk;
+            }
+        }
+        self.cursor_row = row as usize;
+        self.cursor_col = col as usize;
+        self.needs_redraw = true;
+    }
+
+    /// Where the cursor is, for the jump list
+    fn jump_position(&self) -> Option<jump_list::Position> {
+        let note = self.notes_mode.current_note.as_ref()?;
+        let (line, col) = self.grid.logical_pos(self.cursor_row, self.cursor_col);
+        Some(jump_list::Position { note_id: note.id.clone(), line, col })
+    }
+
+    /// Remember the cursor position before a big move
+    pub fn record_jump(&mut self) {
+        if let Some(position) = self.jump_position() {
+            self.jump_list.record(position);
+        }
+    }
+
+    /// Move the cursor to (row, col) as a jump that Alt+Left can come back from
+    pub fn jump_to(&mut self, row: usize, col: usize) {
+        if (row, col) != (self.cursor_row, self.cursor_col) {
+            self.record_jump();
+        }
+        self.cursor_row = row;
+        self.cursor_col = col;
+        self.needs_redraw = true;
+    }
+
+    /// Alt+Left / Alt+Right: go back or forward through the jump list, across notes
+    pub fn jump_back(&mut self, forward: bool) -> Result<()> {
+        let target = if forward {
+            self.jump_list.forward()
+        } else {
+            match self.jump_position() {
+                Some(current) => self.jump_list.back(current),
+                None => None,
+            }
+        };
+        let Some(target) = target else {
+            self.status_message = if forward { "No later position" } else { "No earlier position" }.to_string();
+            self.needs_redraw = true;
+            return Ok(());
+        };
+
+        if self.notes_mode.current_note.as_ref().is_some_and(|note| note.id != target.note_id) {
+            // Switching notes here must not record a new jump
+            let jumps = std::mem::take(&mut self.jump_list);
+            let opened = self.open_note_by_id(&target.note_id);
+            self.jump_list = jumps;
+            opened?;
+            if self.notes_mode.current_note.as_ref().is_none_or(|note| note.id != target.note_id) {
+                return Ok(());
+            }
+        }
+        self.move_to_logical(target.line, target.col);
+        self.grid.clear_selection();
+        Ok(())
+    }
+
+
+    /// Perform search and populate results
+    pub fn perform_search(&mut self) {
+        self.search_results.clear();
+
+        if self.search_query.is_empty() {
+            return;
+        }
+
+        let lines = self.grid.to_lines();
+        let query_lower = self.search_query.to_lowercase();
+
+        for (row, line) in lines.iter().enumerate() {
+            let line_lower = line.to_lowercase();
+            let mut start = 0;
+
+            while let Some(pos) = line_lower[start..].find(&query_lower) {
+                let col = start + pos;
+                self.search_results.push((row, col));
+                start = col + 1;
+            }
+        }
+
+        self.current_search_index = 0;
+    }
+
+    /// Jump to next search result
+    pub fn next_search_result(&mut self) {
+        if !self.search_results.is_empty() {
+            self.current_search_index = (self.current_search_index + 1) % self.search_results.len();
+            let (row, col) = self.search_results[self.current_search_index];
+            self.jump_to(row, col);
+        }
+    }
+
+    /// Jump to previous search result
+    pub fn prev_search_result(&mut self) {
+        if !self.search_results.is_empty() {
+            self.current_search_index = if self.current_search_index == 0 {
+                self.search_results.len() - 1
+            } else {
+                self.current_search_index - 1
+            };
+            let (row, col) = self.search_results[self.current_search_index];
+            self.jump_to(row, col);
+        }
+    }
+
+    /// Re-read the notes list on the next reload (after creating or deleting notes)
+    pub fn invalidate_notes_list(&mut self) {
+        self.notes_list_stale = true;
+        self.smart_views.invalidate();
+        self.embeds.invalidate();
+        self.invalidate_preview();
+        self.backlinks.invalidate();
+        self.tasks.invalidate();
+    }
+
+    fn invalidate_preview(&mut self) {
+        if let Some(preview) = self.preview.as_mut() {
+            preview.invalidate();
+        }
+    }
+
+    /// Screen column and width of the backlinks panel, left of the outline and settings panels
+    pub fn backlinks_panel(&self, term_width: u16) -> Option<(u16, u16)> {
+        if !self.backlinks_panel_expanded {
+            return None;
+        }
+        let right = term_width
+            .saturating_sub(if self.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 })
+            .saturating_sub(if self.outline_panel_expanded { layout::OUTLINE_PANEL_WIDTH } else { 0 });
+        let width = layout::BACKLINKS_PANEL_WIDTH.min(right);
+        (width >= 8).then_some((right - width, width))
+    }
+
+    /// Screen column and width of the tasks panel, left of the backlinks panel
+    pub fn tasks_panel(&self, term_width: u16) -> Option<(u16, u16)> {
+        if !self.tasks_panel_expanded {
+            return None;
+        }
+        let right = match self.backlinks_panel(term_width) {
+            Some((x, _)) => x,
+            None => term_width
+                .saturating_sub(if self.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 })
+                .saturating_sub(if self.outline_panel_expanded { layout::OUTLINE_PANEL_WIDTH } else { 0 }),
+        };
+        let width = layout::TASKS_PANEL_WIDTH.min(right);
+        (width >= 8).then_some((right - width, width))
+    }
+
+    /// Screen column and width of the pinned preview, left of the other right-hand panels
+    pub fn preview_panel(&self, term_width: u16) -> Option<(u16, u16)> {
+        self.preview.as_ref()?;
+        let right = match self.tasks_panel(term_width).or(self.backlinks_panel(term_width)) {
+            Some((x, _)) => x,
+            None => term_width
+                .saturating_sub(if self.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 })
+                .saturating_sub(if self.outline_panel_expanded { layout::OUTLINE_PANEL_WIDTH } else { 0 }),
+        };
+        let width = layout::PREVIEW_PANEL_WIDTH.min(term_width / 2).min(right);
+        (width >= 4).then_some((right - width, width))
+    }
+
+    /// Re-read the pages of the notes list loaded so far (at least one), if the cached list
+    /// was invalidated
+    pub fn reload_notes_list(&mut self) {
+        if !self.notes_list_stale {
+            return;
+        }
+        self.notes_list_stale = false;
+        let limit = self.notes_list.len().max(config::notes_list::PAGE_SIZE);
+        if let Ok(notes) = self.notes_mode.db.list_notes(limit) {
+            self.notes_list = notes;
+        }
+        self.notes_total = self.notes_mode.db.count_notes().unwrap_or(self.notes_list.len());
+        self.favorites = self.notes_mode.db.favorites().unwrap_or_default();
+    }
+
+    /// Fetch further pages until `index` (plus a margin) is loaded or every note is
+    pub fn ensure_notes_loaded(&mut self, index: usize) {
+        while self.notes_list.len() < self.notes_total
+            && index + config::notes_list::PREFETCH_MARGIN >= self.notes_list.len()
+        {
+            match self.notes_mode.db.list_notes_page(config::notes_list::PAGE_SIZE, self.notes_list.len()) {
+                Ok(page) if !page.is_empty() => self.notes_list.extend(page),
+                Ok(_) => break,
+                Err(e) => {
+                    log::error!("Loading more notes failed: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Reload the notes list and keep the current note selected
+    pub fn refresh_notes_list(&mut self) {
+        self.reload_notes_list();
+        if let Some(ref current_note) = self.notes_mode.current_note {
+            if let Some(index) = self.notes_list.iter().position(|n| n.id == current_note.id) {
+                self.selected_note_index = index;
+            }
+        }
+    }
+
+    /// Save the current note, then create and open a new empty note
+    pub fn create_note(&mut self) -> Result<()> {
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+        let new_note = self.notes_mode.db.create_note("Untitled".to_string(), String::new(), vec![])?;
+        self.hooks.fire(hooks::Event::Create, &new_note);
+        self.invalidate_notes_list();
+        self.load_note(new_note);
+        self.refresh_notes_list();
+        self.status_message = "New note created".to_string();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Open the note titled with today's date (in the Insert date format), creating it the
+    /// first time
+    pub fn open_todays_note(&mut self) -> Result<()> {
+        let title = timestamp::format_time(&chrono::Local::now(), &self.settings.date_format, config::timestamps::DATE_FORMAT);
+        if self.notes_mode.db.find_note_by_title(&title)?.is_some() {
+            self.open_note_titled(&title)?;
+            return Ok(());
+        }
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+        let note = self.notes_mode.db.create_note(title, String::new(), vec![])?;
+        self.hooks.fire(hooks::Event::Create, &note);
+        self.invalidate_notes_list();
+        self.status_message = format!("Started \"{}\"", note.title);
+        self.load_note(note);
+        self.refresh_notes_list();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// The start screen: recently edited notes, favorites, and things to start with
+    pub fn open_start_screen(&mut self) {
+        let recent = self
+            .notes_mode
+            .db
+            .list_edited_between(chrono::DateTime::UNIX_EPOCH, chrono::Utc::now() + chrono::Duration::minutes(1), config::dashboard::RECENT_NOTES)
+            .map(|(notes, _)| notes)
+            .unwrap_or_default();
+        let title = |note: &notes_database::Note| {
+            let title = if note.title.is_empty() { "Untitled" } else { &note.title };
+            match &note.icon {
+                Some(icon) => format!("{} {}", icon, title),
+                None => title.to_string(),
+            }
+        };
+        let recent = recent
+            .iter()
+            .map(|note| {
+                let edited = note.updated_at.with_timezone(&chrono::Local).format("%b %-d").to_string();
+                (title(note), edited, OverlayAction::OpenNote(note.id.clone()))
+            })
+            .collect();
+        let favorites = self
+            .favorites
+            .iter()
+            .filter_map(|(slot, id)| {
+                let note = self.notes_mode.db.get_note(id).ok().flatten()?;
+                Some((title(&note), format!("Alt+{}", slot), OverlayAction::OpenNote(note.id)))
+            })
+            .collect();
+        let actions = [Command::TodaysNote, Command::NewNote, Command::OpenNote, Command::Search, Command::Scratchpad, Command::Palette]
+            .into_iter()
+            .map(|command| {
+                let (label, hint) = commands::describe(command);
+                (label.to_string(), hint.to_string(), OverlayAction::Command(command))
+            })
+            .collect();
+        let columns = vec![
+            ("Recent".to_string(), recent),
+            ("Favorites".to_string(), favorites),
+            ("Start".to_string(), actions),
+        ];
+        let today = chrono::Local::now().format("%A, %B %-d").to_string();
+        self.overlays.push(overlay::Dashboard::new("chonk-note", &today, columns, Some(OverlayAction::LeaveStartScreen)));
+        self.needs_redraw = true;
+    }
+
+    /// Esc on the start screen opens the newest note if none is open yet
+    fn leave_start_screen(&mut self) {
+        if self.notes_mode.current_note.is_none() {
+            if let Some(note) = self.notes_list.first().cloned() {
+                self.selected_note_index = 0;
+                self.load_note(note);
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Move the selected note to Recently deleted and open the first remaining one
+    pub fn delete_selected_note(&mut self) -> Result<()> {
+        if self.selected_note_index >= self.notes_list.len() {
+            return Ok(());
+        }
+        let note_id = self.notes_list[self.selected_note_index].id.clone();
+        self.notes_mode.db.trash_note(&note_id)?;
+        self.invalidate_notes_list();
+
+        // Refresh notes list
+        self.reload_notes_list();
+
+        // Load first note if any remain
+        if !self.notes_list.is_empty() {
+            self.selected_note_index = 0;
+            let first_note = self.notes_list[0].clone();
+            self.load_note(first_note);
+        } else {
+            self.grid.clear();
+            self.notes_mode.current_note = None;
+            self.outline.invalidate();
+            self.cursor_row = 0;
+            self.cursor_col = 0;
+        }
+
+        self.delete_confirmation_note = None;
+        self.status_message = "Note moved to Recently deleted".to_string();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Sidebar click on a smart view line: fold a header, or open the note (restoring it
+    /// first if it was deleted)
+    pub fn open_smart_view_row(&mut self, row: smart_views::Row) -> Result<()> {
+        use smart_views::{Row, View};
+        let (section, index) = match row {
+            Row::Header(header) => {
+                self.smart_views.toggle(header);
+                self.needs_redraw = true;
+                return Ok(());
+            }
+            Row::More(section) => {
+                let section = &self.smart_views.sections[section];
+                self.status_message = format!("{} holds {} notes; the newest {} are shown",
+                    section.label, section.total, section.notes.len());
+                return Ok(());
+            }
+            Row::Note(section, index) => (section, index),
+        };
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+        let section = &self.smart_views.sections[section];
+        let mut note = section.notes[index].clone();
+        if section.view == View::RecentlyDeleted {
+            self.notes_mode.db.restore_note(&note.id)?;
+            self.status_message = format!("Restored \"{}\"", note.title);
+        }
+        // The copy in the section may predate the latest save
+        if let Some(latest) = self.notes_mode.db.get_note(&note.id)? {
+            note = latest;
+        }
+        self.invalidate_notes_list();
+        self.load_note(note);
+        self.refresh_notes_list();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Command palette → Save search: ask for the query, then for a name
+    pub fn open_save_search_prompt(&mut self) {
+        let title = "Search to keep in the sidebar (words, \"phrases\", tag:work, after:2025-01-31, before:...)";
+        self.overlays.push(overlay::Prompt::new(title, OverlayAction::NameSearch));
+        self.needs_redraw = true;
+    }
+
+    /// Check the query, then ask what to call it
+    pub fn name_search(&mut self, query: String) {
+        if query.trim().is_empty() {
+            return;
+        }
+        if let Err(e) = saved_searches::Query::parse(&query) {
+            self.status_message = format!("Can't save that search: {}", e);
+            self.needs_redraw = true;
+            return;
+        }
+        let title = format!("Name for \"{}\" (empty: the search itself)", query.trim());
+        self.overlays.push(overlay::Prompt::new(&title, move |name| OverlayAction::SaveSearch(query.clone(), name)));
+        self.needs_redraw = true;
+    }
+
+    pub fn save_search(&mut self, query: &str, name: &str) -> Result<()> {
+        let name = match name.trim() {
+            "" => query.trim(),
+            name => name,
+        };
+        self.notes_mode.db.save_search(name, query.trim())?;
+        self.smart_views.invalidate();
+        self.sidebar_expanded = true;
+        self.status_message = format!("Saved search \"{}\" is in the sidebar", name);
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Pick a saved search to take out of the sidebar
+    pub fn open_delete_search_picker(&mut self) {
+        let items: Vec<(String, OverlayAction)> = match self.notes_mode.db.list_saved_searches() {
+            Ok(searches) => searches
+                .into_iter()
+                .map(|(id, name, query)| (format!("{} ({})", name, query), OverlayAction::DeleteSavedSearch(id)))
+                .collect(),
+            Err(e) => {
+                self.status_message = format!("Could not list saved searches: {}", e);
+                return;
+            }
+        };
+        if items.is_empty() {
+            self.status_message = "There are no saved searches".to_string();
+        } else {
+            self.overlays.push(overlay::ListPicker::new("Remove saved search", items));
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Rows of the sidebar taken by the smart views before the list of all notes
+    pub fn smart_view_rows(&self) -> usize {
+        if self.sidebar_expanded { self.smart_views.rows().len() } else { 0 }
+    }
+
+    /// Tags, word count and last edit of `note`, for its sidebar tooltip. The word count is
+    /// kept until the note changes, as the tooltip is rebuilt on every pointer move.
+    pub fn note_tooltip_lines(&mut self, note: &notes_database::Note) -> Vec<String> {
+        let words = match &self.tooltip_words {
+            Some((id, updated_at, words)) if *id == note.id && *updated_at == note.updated_at => *words,
+            _ => {
+                let words = word_goal::count(&note_format::decode(&note.content));
+                self.tooltip_words = Some((note.id.clone(), note.updated_at, words));
+                words
+            }
+        };
+        let tags = if note.tags.is_empty() { "No tags".to_string() } else { format!("Tags: {}", note.tags.join(", ")) };
+        let edited = note.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+        vec![tags, format!("{} word{}", words, if words == 1 { "" } else { "s" }), format!("Edited {}", edited)]
+    }
+
+    /// Show the tooltip of the sidebar note under the pointer (screen row `y`), unless
+    /// something else is open over the editor
+    pub fn show_sidebar_tooltip(&mut self, y: u16) {
+        if !self.overlays.is_empty() || self.notes_menu_expanded || self.settings_menu_expanded {
+            return;
+        }
+        let note = match self.hover {
+            Some(mouse::Hover::SidebarNote(i)) => self.notes_list.get(i),
+            Some(mouse::Hover::SidebarSmartRow(i)) => match self.smart_views.rows().get(i) {
+                Some(&smart_views::Row::Note(section, n)) => self.smart_views.sections.get(section).and_then(|s| s.notes.get(n)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(note) = note.cloned() else { return };
+        let lines = self.note_tooltip_lines(&note);
+        self.overlays.push(overlay::Tooltip::new(&note.title, lines, layout::SIDEBAR_WIDTH_EXPANDED, y));
+    }
+
+    /// Save the current note, then open a copy of it
+    pub fn duplicate_current_note(&mut self) -> Result<()> {
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+        let Some(ref current_note) = self.notes_mode.current_note else {
+            self.status_message = "No note to duplicate".to_string();
+            return Ok(());
+        };
+        let title = format!("{} (copy)", current_note.title);
+        let tags = current_note.tags.clone();
+        let content = note_format::encode(&self.grid);
+
+        let copy = self.notes_mode.db.create_note(title, content, tags)?;
+        self.hooks.fire(hooks::Event::Create, &copy);
+        self.invalidate_notes_list();
+        self.status_message = format!("Duplicated as \"{}\"", copy.title);
+        self.load_note(copy);
+        self.refresh_notes_list();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Notes menu → Note color: the open note's accent goes through the presets, then back to none
+    pub fn cycle_note_accent(&mut self) -> Result<()> {
+        let Some(note) = self.notes_mode.current_note.as_ref() else {
+            self.status_message = "No note open".to_string();
+            return Ok(());
+        };
+        let presets = config::notes_list::ACCENT_PRESETS;
+        let accent = match (note.accent, note.accent.and_then(|accent| presets.iter().position(|&p| p == accent))) {
+            (None, _) => Some(presets[0]),
+            (Some(_), Some(i)) if i + 1 < presets.len() => Some(presets[i + 1]),
+            _ => None,
+        };
+        let icon = note.icon.clone();
+        self.set_note_style(accent, icon)
+    }
+
+    /// Store the open note's accent color and icon, updating its sidebar entry too
+    fn set_note_style(&mut self, accent: Option<(u8, u8, u8)>, icon: Option<String>) -> Result<()> {
+        let Some(note) = self.notes_mode.current_note.as_mut() else {
+            return Ok(());
+        };
+        self.notes_mode.db.set_note_style(&note.id, accent, icon.as_deref())?;
+        note.accent = accent;
+        note.icon = icon.clone();
+        let id = note.id.clone();
+        for listed in self.notes_list.iter_mut().filter(|listed| listed.id == id) {
+            listed.accent = accent;
+            listed.icon = icon.clone();
+        }
+        self.status_message = "Note style updated".to_string();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Store the open note's word goal (None removes it)
+    fn set_word_goal(&mut self, goal: Option<u32>) -> Result<()> {
+        let Some(note) = self.notes_mode.current_note.as_mut() else {
+            self.status_message = "No note open".to_string();
+            return Ok(());
+        };
+        self.notes_mode.db.set_word_goal(&note.id, goal)?;
+        note.word_goal = goal;
+        self.status_message = match goal {
+            Some(goal) => format!("Word goal set to {}", goal),
+            None => "Word goal removed".to_string(),
+        };
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Favorite slot (1-9) of a note
+    pub fn favorite_slot(&self, id: &str) -> Option<u8> {
+        self.favorites.iter().find(|(_, favorite)| favorite == id).map(|&(slot, _)| slot)
+    }
+
+    /// Notes menu → Add to favorites: give the open note the first free slot, or take it out
+    fn toggle_favorite(&mut self) -> Result<()> {
+        let Some(note) = self.notes_mode.current_note.as_ref() else {
+            self.status_message = "No note open".to_string();
+            return Ok(());
+        };
+        let (id, title) = (note.id.clone(), note.title.clone());
+        let slot = match self.favorite_slot(&id) {
+            Some(_) => None,
+            None => {
+                let free = (1..=config::notes_list::FAVORITES).find(|slot| self.favorites.iter().all(|(taken, _)| taken != slot));
+                let Some(free) = free else {
+                    self.status_message = format!("All {} favorites are taken; remove one first", config::notes_list::FAVORITES);
+                    return Ok(());
+                };
+                Some(free)
+            }
+        };
+        self.notes_mode.db.set_favorite(&id, slot)?;
+        self.favorites = self.notes_mode.db.favorites()?;
+        self.status_message = match slot {
+            Some(slot) => format!("Alt+{} opens \"{}\"", slot, title),
+            None => format!("\"{}\" removed from favorites", title),
+        };
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Alt+1..9: switch to a favorite note
+    pub fn open_favorite(&mut self, slot: u8) -> Result<()> {
+        let Some((_, id)) = self.favorites.iter().find(|(favorite, _)| *favorite == slot).cloned() else {
+            self.status_message = format!("No favorite {} (Notes menu → Add to favorites)", slot);
+            self.needs_redraw = true;
+            return Ok(());
+        };
+        if self.notes_mode.current_note.as_ref().is_some_and(|note| note.id == id) {
+            return Ok(());
+        }
+        self.open_note_by_id(&id)
+    }
+
+    /// Start renaming the selected note in the sidebar
+    pub fn start_rename(&mut self) {
+        if let Some(note) = self.notes_list.get(self.selected_note_index) {
+            self.title_edit = line_edit::LineEdit::new(&note.title);
+            self.editing_title = true;
+            self.sidebar_expanded = true;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// Start renaming the open note in place in the title bar
+    pub fn start_title_bar_edit(&mut self) {
+        let Some(note) = self.notes_mode.current_note.as_ref() else {
+            return;
+        };
+        if let Some(index) = self.notes_list.iter().position(|n| n.id == note.id) {
+            self.selected_note_index = index;
+        }
+        self.title_edit = line_edit::LineEdit::new(&note.title);
+        self.editing_title = true;
+        self.needs_redraw = true;
+    }
+
+    /// Write the current note to a plain text file in the user's documents directory
+    pub fn export_current_note(&mut self) -> Result<std::path::PathBuf> {
+        let Some(ref current_note) = self.notes_mode.current_note else {
+            anyhow::bail!("No note to export");
+        };
+        let dir = export_dir()?;
+
+        let path = unique_export_path(&dir, &current_note.title, "txt");
+        let mut content = self.grid.to_lines().join("\n");
+        content.push('\n');
+        std::fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// Ctrl+E: copy the block selection to the clipboard as CSV (F then saves it as a file)
+    pub fn copy_selection_as_csv(&mut self) {
+        let Some(lines) = self.grid.copy_block() else {
+            self.status_message = "Select a table block first".to_string();
+            return;
+        };
+        let rows = csv_export::split_columns(&lines);
+        let columns = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let csv = csv_export::to_csv(&rows);
+
+        self.status_message = match self.set_clipboard(&csv) {
+            Ok(_) => format!("Copied {} rows x {} columns as CSV  [F] Save as .csv file", rows.len(), columns),
+            Err(e) => format!("CSV ready (clipboard error: {})  [F] Save as .csv file", e),
+        };
+        self.pending_csv = Some(csv);
+        self.needs_redraw = true;
+    }
+
+    /// Save CSV text as <note title>.csv in the documents directory
+    pub fn export_csv_file(&mut self, csv: &str) -> Result<std::path::PathBuf> {
+        let title = self.notes_mode.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("Table");
+        let path = unique_export_path(&export_dir()?, title, "csv");
+        std::fs::write(&path, format!("{}\n", csv))?;
+        Ok(path)
+    }
+
+    /// Start the import prompt in the status line
+    pub fn open_import_prompt(&mut self) {
+        self.overlays.push(overlay::Prompt::new("Import file (text, .enex, Apple Notes .html or folder)", OverlayAction::Import));
+        self.needs_redraw = true;
+    }
+
+    /// Ctrl+O: pick a note by title (from all notes, not just the loaded pages)
+    pub fn open_note_picker(&mut self) {
+        let titles = match self.notes_mode.db.list_titles() {
+            Ok(titles) => titles,
+            Err(e) => {
+                self.status_message = format!("Could not list notes: {}", e);
+                return;
+            }
+        };
+        let items = titles.into_iter().map(|(id, title)| (title, OverlayAction::OpenNote(id))).collect();
+        self.overlays.push(overlay::ListPicker::new("Open note", items));
+        self.needs_redraw = true;
+    }
+
+    /// Switch to the note with this ID (saving the current one first)
+    pub fn open_note_by_id(&mut self, id: &str) -> Result<()> {
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+        self.refresh_notes_list();
+        // Notes beyond the loaded pages are loaded up to this one
+        while !self.notes_list.iter().any(|note| note.id == id) && self.notes_list.len() < self.notes_total {
+            let loaded = self.notes_list.len();
+            self.ensure_notes_loaded(loaded);
+            if self.notes_list.len() == loaded {
+                break;
+            }
+        }
+        let Some(index) = self.notes_list.iter().position(|note| note.id == id) else {
+            self.status_message = "Note no longer exists".to_string();
+            return Ok(());
+        };
+        self.selected_note_index = index;
+        if index < self.notes_list_scroll {
+            self.notes_list_scroll = index;
+        }
+        let note = self.notes_list[index].clone();
+        self.status_message = format!("Opened \"{}\"", note.title);
+        self.load_note(note);
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Run what a finished overlay asked for
+    pub fn run_overlay_action(&mut self, action: OverlayAction) -> Result<()> {
+        match action {
+            OverlayAction::Context(action) => self.run_context_action(action),
+            OverlayAction::Import(path) => {
+                if let Err(e) = self.import_note(&path) {
+                    self.status_message = format!("Import failed: {}", e);
+                }
+            }
+            OverlayAction::OpenNote(id) => self.open_note_by_id(&id)?,
+            OverlayAction::LeaveStartScreen => self.leave_start_screen(),
+            OverlayAction::DeleteNote(id) => {
+                if let Some(index) = self.notes_list.iter().position(|note| note.id == id) {
+                    self.selected_note_index = index;
+                    self.delete_selected_note()?;
+                }
+            }
+            OverlayAction::SetNoteIcon(text) => {
+                let icon = first_glyph(text.trim());
+                let accent = self.notes_mode.current_note.as_ref().and_then(|note| note.accent);
+                self.set_note_style(accent, (!icon.is_empty()).then_some(icon))?;
+            }
+            OverlayAction::SetWordGoal(text) => match word_goal::parse(&text) {
+                Ok(goal) => self.set_word_goal(goal)?,
+                Err(e) => self.status_message = e.to_string(),
+            },
+            OverlayAction::EmbedNote(title) => self.embed_note(&title),
+            OverlayAction::PinPreview(id) => self.pin_preview(id),
+            OverlayAction::NameSearch(query) => self.name_search(query),
+            OverlayAction::SaveSearch(query, name) => self.save_search(&query, &name)?,
+            OverlayAction::DeleteSavedSearch(id) => {
+                self.notes_mode.db.delete_saved_search(id)?;
+                self.smart_views.invalidate();
+                self.status_message = "Saved search removed".to_string();
+            }
+            OverlayAction::ExtractToNote(block, title) => self.extract_to_note(block, &title)?,
+            OverlayAction::LinkExtracted(block, title) => self.replace_block_with_link(block, &title),
+            OverlayAction::FilterBlock(block, command) => self.filter_block(block, &command),
+            OverlayAction::RunCommand(command) => self.insert_command_output(&command),
+            OverlayAction::InsertLines(lines) => self.insert_lines(lines),
+            OverlayAction::ClearScratchpad => self.clear_scratchpad()?,
+            OverlayAction::Command(command) => self.run_command(command)?,
+            OverlayAction::RunPlugin(plugin, index) => self.run_plugin_command(plugin, index)?,
+            OverlayAction::PaintBlock(block, paint) => self.paint_block(block, paint),
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub fn scratchpad_is_open(&self) -> bool {
+        let scratchpad = self.settings.scratchpad_note_id.as_deref();
+        scratchpad.is_some() && self.notes_mode.current_note.as_ref().map(|n| n.id.as_str()) == scratchpad
+    }
+
+    /// F9: open the scratchpad note (created on first use), or go back to the note it was opened from
+    pub fn toggle_scratchpad(&mut self) -> Result<()> {
+        self.editing_title = false;
+        self.search_mode = false;
+        self.goto_line_mode = false;
+        if self.scratchpad_is_open() {
+            match self.scratchpad_return.take() {
+                Some(previous) => self.open_note_by_id(&previous)?,
+                None => self.status_message = "No note to go back to".to_string(),
+            }
+            self.needs_redraw = true;
+            return Ok(());
+        }
+
+        let existing = match self.settings.scratchpad_note_id.clone() {
+            Some(id) => self.notes_mode.db.get_note(&id)?.map(|note| note.id),
+            None => None,
+        };
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let note = self.notes_mode.db.create_note("Scratchpad".to_string(), String::new(), vec![])?;
+                self.hooks.fire(hooks::Event::Create, &note);
+                self.invalidate_notes_list();
+                self.settings.scratchpad_note_id = Some(note.id.clone());
+                if let Err(e) = self.settings.save() {
+                    log::warn!("Could not save settings: {}", e);
+                }
+                note.id
+            }
+        };
+
+        let previous = self.notes_mode.current_note.as_ref().map(|n| n.id.clone());
+        self.open_note_by_id(&id)?;
+        if self.scratchpad_is_open() {
+            self.scratchpad_return = previous;
+            self.status_message = "Scratchpad (F9 goes back)".to_string();
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Empty the scratchpad: an undoable edit when it is open, otherwise straight in the database
+    pub fn clear_scratchpad(&mut self) -> Result<()> {
+        let Some(id) = self.settings.scratchpad_note_id.clone() else {
+            self.status_message = "No scratchpad yet (F9 creates it)".to_string();
+            return Ok(());
+        };
+        if self.scratchpad_is_open() {
+            if let Some((min_row, min_col, max_row, max_col)) = self.grid.bounds() {
+                self.grid.clear_selection();
+                self.write_block(min_row, min_col, vec![" ".repeat(max_col - min_col + 1); max_row - min_row + 1]);
+                (self.cursor_row, self.cursor_col) = (min_row, min_col);
+            }
+        } else if let Some(mut note) = self.notes_mode.db.get_note(&id)? {
+            note.content.clear();
+            note.updated_at = self.notes_mode.db.update_note(&note.id, note.title.clone(), String::new(), note.tags.clone())?;
+            if let Some(listed) = self.notes_list.iter_mut().find(|n| n.id == note.id) {
+                *listed = note;
+            }
+        }
+        self.status_message = "Scratchpad cleared".to_string();
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Store a note sent to the capture endpoint, staying in the open note
+    pub fn add_captured_note(&mut self, request: capture_server::CaptureRequest) -> Result<notes_database::Note> {
+        let mut content = request.content.replace("\r\n", "\n").trim_end().to_string();
+        if let Some(url) = request.url.filter(|url| !url.trim().is_empty()) {
+            if !content.is_empty() {
+                content.push_str("\n\n");
+            }
+            content.push_str(&format!("Source: {}", url.trim()));
+        }
+        let title = match request.title.trim() {
+            "" => note_format::first_line_title_of(&content).unwrap_or_else(|| "Captured".to_string()),
+            title => title.to_string(),
+        };
+
+        let note = self.notes_mode.db.create_note(title, content, request.tags)?;
+        self.hooks.fire(hooks::Event::Create, &note);
+        self.invalidate_notes_list();
+        self.refresh_notes_list();
+        self.status_message = format!("Captured \"{}\"", note.title);
+        self.needs_redraw = true;
+        Ok(note)
+    }
+
+    /// Create notes from a file and open the first: a text file (titled after the file name),
+    /// an Evernote .enex export, or Apple Notes HTML (a file or a folder). The file is read
+    /// and stored on the database thread; the note opens when that finishes.
+    pub fn import_note(&mut self, path: &str) -> Result<()> {
+        let path = match path.trim().strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => std::path::PathBuf::from(path.trim()),
+        };
+        self.status_message = format!("Importing {}…", path.display());
+        self.needs_redraw = true;
+        self.send_db_request(Request::Import { path })
+    }
+
+    /// Run an item picked from the Notes menu
+    pub fn run_notes_menu_action(&mut self, command: Command) -> Result<()> {
+        self.delete_confirmation_note = None;
+        self.notes_menu_expanded = false;
+        self.hover = None;
+        self.run_command(command)
+    }
+
+    /// Label for a command in menus and the palette (the focus timer item says what it will do)
+    pub fn command_label(&self, command: Command) -> &'static str {
+        match command {
+            Command::FocusTimer if self.focus_timer.is_some() => "Stop focus timer",
+            Command::Broadcast if self.broadcast.is_some() => "Stop broadcast",
+            Command::Favorite if self.notes_mode.current_note.as_ref().is_some_and(|note| self.favorite_slot(&note.id).is_some()) => {
+                "Remove from favorites"
+            }
+            command => commands::describe(command).0,
+        }
+    }
+
+    /// F1 / Ctrl+Shift+P: pick any command by name
+    pub fn open_command_palette(&mut self) {
+        let items = commands::COMMANDS
+            .iter()
+            .map(|&(command, _, hint)| (self.command_label(command).to_string(), hint.to_string(), OverlayAction::Command(command)))
+            .chain(self.plugins.iter().enumerate().flat_map(|(p, plugin)| {
+                plugin.commands.iter().enumerate().map(move |(c, command)| {
+                    (format!("{}: {}", plugin.name, command.label), "plugin".to_string(), OverlayAction::RunPlugin(p, c))
+                })
+            }))
+            .collect();
+        self.overlays.push(overlay::ListPicker::with_hints("Commands", items));
+        self.needs_redraw = true;
+    }
+
+    /// Status message for a flipped setting, saving settings.json
+    fn setting_changed(&mut self, what: &str, state: &str) {
+        self.status_message = match self.settings.save() {
+            Ok(()) => format!("{}: {}", what, state),
+            Err(e) => format!("{}: {} (not saved: {})", what, state, e),
+        };
+    }
+
+    /// Carry out a command from the keyboard, a menu or the command palette
+    pub fn run_command(&mut self, command: Command) -> Result<()> {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        // Commands work on a single cursor; the palette and HUD leave the selections alone
+        if !matches!(command, Command::Palette | Command::PerformanceHud | Command::SelectNextOccurrence | Command::SelectAllOccurrences) {
+            self.collapse_selections();
+        }
+        match command {
+            Command::Save => {
+                let previous_save = self.last_save_time;
+                self.save_current_note()?;
+                if self.last_save_time != previous_save {
+                    self.status_message = "Note saved".to_string();
+                }
+            }
+            // Stays open on a save conflict or failure (the matching prompt is shown instead)
+            Command::Quit => self.request_quit(),
+            Command::PrintNote => {
+                // Printed by main once the terminal is restored; nothing if the quit is held up
+                self.request_quit();
+                if self.exit_requested {
+                    self.exit_output = Some(cli::note_text(&self.grid));
+                }
+            }
+            Command::NewNote => self.create_note()?,
+            Command::TodaysNote => self.open_todays_note()?,
+            Command::StartScreen => self.open_start_screen(),
+            Command::SyncNow => self.start_sync()?,
+            Command::Broadcast => self.toggle_broadcast(),
+            Command::OpenNote => self.open_note_picker(),
+            Command::PreviousNote => self.switch_to_adjacent_note(false)?,
+            Command::NextNote => self.switch_to_adjacent_note(true)?,
+            Command::DuplicateNote => self.duplicate_current_note()?,
+            Command::RenameNote => self.start_rename(),
+            Command::NoteColor => self.cycle_note_accent()?,
+            Command::Favorite => self.toggle_favorite()?,
+            Command::NoteIcon => {
+                self.overlays.push(overlay::Prompt::new("Note icon (an emoji; leave empty to remove it)", OverlayAction::SetNoteIcon));
+            }
+            Command::WordGoal => {
+                let title = match self.notes_mode.current_note.as_ref().and_then(|note| note.word_goal) {
+                    Some(goal) => format!("Word goal (now {}; leave empty to remove it)", goal),
+                    None => "Word goal (number of words; leave empty to remove it)".to_string(),
+                };
+                self.overlays.push(overlay::Prompt::new(&title, OverlayAction::SetWordGoal));
+            }
+            Command::ExportNote => {
+                self.save_current_note()?;
+                self.status_message = match self.export_current_note() {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+            }
+            Command::ImportNote => self.open_import_prompt(),
+            Command::NoteInfo => self.note_info_open = true,
+            Command::DeleteNote => {
+                if let Some(note) = self.notes_list.get(self.selected_note_index) {
+                    let message = format!("Delete \"{}\"?", note.title);
+                    self.overlays.push(overlay::Confirm::new(&message, OverlayAction::DeleteNote(note.id.clone())));
+                }
+            }
+            Command::Scratchpad => self.toggle_scratchpad()?,
+            // Undoable when the scratchpad is open; otherwise ask first
+            Command::ClearScratchpad if self.scratchpad_is_open() => self.clear_scratchpad()?,
+            Command::ClearScratchpad => {
+                self.overlays.push(overlay::Confirm::new("Clear the scratchpad?", OverlayAction::ClearScratchpad));
+            }
+            Command::FocusTimer => self.toggle_focus_timer(),
+            Command::Search => {
+                self.search_mode = true;
+                self.search_query.clear();
+                self.search_results.clear();
+                self.status_message = "Search: ".to_string();
+            }
+            Command::NextMatch | Command::PreviousMatch if self.search_results.is_empty() => {
+                self.status_message = "No search results (Ctrl+F to search)".to_string();
+            }
+            Command::NextMatch | Command::PreviousMatch => {
+                if command == Command::NextMatch {
+                    self.next_search_result();
+                } else {
+                    self.prev_search_result();
+                }
+                self.status_message = format!("Match {}/{}", self.current_search_index + 1, self.search_results.len());
+            }
+            Command::GoToLine => self.open_goto_line(),
+            Command::FindCursor => self.find_cursor(),
+            Command::JumpBack => self.jump_back(false)?,
+            Command::JumpForward => self.jump_back(true)?,
+            Command::Undo | Command::Redo => {
+                let (done, label) = if command == Command::Undo {
+                    (self.undo_stack.undo(&mut self.grid), "undo")
+                } else {
+                    (self.undo_stack.redo(&mut self.grid), "redo")
+                };
+                if done {
+                    self.status_message = if command == Command::Undo { "Undo" } else { "Redo" }.to_string();
+                    self.mark_dirty();
+                } else {
+                    self.status_message = format!("Nothing to {}", label);
+                }
+            }
+            Command::UndoHistory => {
+                let entries = self.undo_stack.history();
+                self.history_selected = entries.iter().position(|e| e.is_current).unwrap_or(0);
+                self.history_browser_open = true;
+                self.status_message = format!("Undo history ({} states)", entries.len());
+            }
+            Command::SelectAll => self.select_all(),
+            Command::SelectIsland => self.select_island(),
+            Command::SelectWord => self.select_text_object(text_objects::TextObject::Word),
+            Command::SelectLine => self.select_text_object(text_objects::TextObject::Line),
+            Command::SelectParagraph => self.select_text_object(text_objects::TextObject::Paragraph),
+            Command::StripTrailingWhitespace => self.strip_trailing_whitespace(),
+            Command::TidyLayout => self.tidy_layout(),
+            Command::SplitSelection => self.split_selection_into_lines(),
+            Command::SelectNextOccurrence => self.select_next_occurrence(),
+            Command::SelectAllOccurrences => self.select_all_occurrences(),
+            Command::Copy => self.copy_selection(),
+            Command::Cut => self.cut_selection(),
+            Command::Paste => self.paste_clipboard(),
+            Command::SelectionTotals => self.show_selection_stats(),
+            Command::CopyAsCsv => self.copy_selection_as_csv(),
+            Command::InsertDate => self.insert_timestamp(timestamp::Stamp::Date),
+            Command::InsertTime => self.insert_timestamp(timestamp::Stamp::Time),
+            Command::InsertDateTime => self.insert_timestamp(timestamp::Stamp::IsoDateTime),
+            Command::UnicodeInput => {
+                self.unicode_input = Some(unicode_input::UnicodeInput::CodePoint(String::new()));
+                self.status_message = "Unicode U+".to_string();
+            }
+            Command::Digraph => {
+                self.unicode_input = Some(unicode_input::UnicodeInput::Digraph(None));
+                self.status_message = "Digraph: ".to_string();
+            }
+            Command::OpenLineBelow => self.open_line(false),
+            Command::OpenLineAbove => self.open_line(true),
+            Command::JoinLines => self.join_lines(),
+            Command::OpenLink => self.open_link_at_cursor(),
+            Command::FilterSelection => self.open_filter_prompt(),
+            Command::ColorSelection => self.open_color_picker(),
+            Command::BoldSelection => match self.selection_or_line() {
+                Some(block) => self.paint_block(block, highlighter::Paint::Bold),
+                None => self.status_message = "Select a block or move to a line to make bold".to_string(),
+            },
+            Command::InsertCommandOutput => {
+                self.overlays.push(overlay::Prompt::new("Insert output of command (e.g. date, ls, git log -5)", OverlayAction::RunCommand));
+            }
+            Command::EmbedNote => self.open_embed_picker(),
+            Command::PinPreview => self.open_preview_picker(),
+            Command::ClosePreview => self.close_preview(),
+            Command::ExtractToNote => self.open_extract_prompt(),
+            Command::SaveSearch => self.open_save_search_prompt(),
+            Command::DeleteSavedSearch => self.open_delete_search_picker(),
+            Command::RevealPath => self.reveal_path_at_cursor(),
+            Command::DuplicateDown => self.duplicate(true),
+            Command::DuplicateUp => self.duplicate(false),
+            Command::MoveRowsUp => self.move_rows(true),
+            Command::MoveRowsDown => self.move_rows(false),
+            Command::ToggleLogNote => self.toggle_log_note(),
+            Command::ToggleGridLines => {
+                self.show_grid_lines = !self.show_grid_lines;
+                self.status_message = format!("Grid lines: {}", on_off(self.show_grid_lines));
+            }
+            Command::ToggleWhitespace => {
+                self.show_whitespace = !self.show_whitespace;
+                self.status_message = format!("Whitespace: {}", on_off(self.show_whitespace));
+            }
+            Command::ToggleWrapPaste => {
+                self.soft_wrap_paste = !self.soft_wrap_paste;
+                self.status_message = format!("Soft-wrapped paste: {}", on_off(self.soft_wrap_paste));
+            }
+            Command::ToggleSmoothScroll => {
+                self.settings.smooth_scroll = !self.settings.smooth_scroll;
+                self.setting_changed("Smooth scroll", on_off(self.settings.smooth_scroll));
+            }
+            Command::ToggleOsc52 => {
+                self.settings.osc52_clipboard = !self.settings.osc52_clipboard;
+                let state = if self.settings.osc52_clipboard { "terminal (OSC 52) clipboard" } else { "system clipboard" };
+                self.setting_changed("Copy to", state);
+            }
+            Command::ToggleAutoTitle => {
+                self.settings.auto_title = !self.settings.auto_title;
+                self.setting_changed("Titles from first line", on_off(self.settings.auto_title));
+            }
+            Command::ToggleStartScreen => {
+                self.settings.start_screen = !self.settings.start_screen;
+                self.setting_changed("Start screen at launch", on_off(self.settings.start_screen));
+            }
+            Command::ReloadConfig => self.reload_config(),
+            Command::ToggleSnapToGrid => {
+                self.settings.snap_to_grid = !self.settings.snap_to_grid;
+                self.setting_changed("Snap to grid", on_off(self.settings.snap_to_grid));
+            }
+            Command::ToggleSidebar => self.sidebar_expanded = !self.sidebar_expanded,
+            Command::ToggleOutline => self.outline_panel_expanded = !self.outline_panel_expanded,
+            Command::ToggleBacklinks => self.backlinks_panel_expanded = !self.backlinks_panel_expanded,
+            Command::ToggleTasks => self.tasks_panel_expanded = !self.tasks_panel_expanded,
+            Command::ToggleSettings => self.settings_panel_expanded = !self.settings_panel_expanded,
+            Command::PerformanceHud => self.diagnostics.hud_visible = !self.diagnostics.hud_visible,
+            Command::Palette => self.open_command_palette(),
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    /// Ctrl+Up/Down: save and open the note above or below in the sidebar
+    pub fn switch_to_adjacent_note(&mut self, down: bool) -> Result<()> {
+        if down {
+            self.ensure_notes_loaded(self.selected_note_index + 1);
+            if self.selected_note_index + 1 >= self.notes_list.len() {
+                return Ok(());
+            }
+        } else if self.selected_note_index == 0 {
+            return Ok(());
+        }
+        // Save current note (stay put if it conflicts with an external change)
+        if !self.save_before_switch()? {
+            return Ok(());
+        }
+
+        if down {
+            self.selected_note_index += 1;
+            if self.selected_note_index >= self.notes_list_scroll + layout::VISIBLE_NOTE_COUNT_APPROX {
+                self.notes_list_scroll = self.selected_note_index - layout::VISIBLE_NOTE_COUNT_APPROX + 1;
+            }
+        } else {
+            self.selected_note_index -= 1;
+            if self.selected_note_index < self.notes_list_scroll {
+                self.notes_list_scroll = self.selected_note_index;
+            }
+        }
+
+        // Reload notes list to get fresh data, then load the selected note
+        self.reload_notes_list();
+        if let Some(note) = self.notes_list.get(self.selected_note_index).cloned() {
+            self.load_note(note);
+        }
+        self.needs_redraw = true;
+        Ok(())
+    }
+}
+
+/// Where exported files go: the user's documents directory, else home
+fn export_dir() -> Result<std::path::PathBuf> {
+    dirs::document_dir()
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| anyhow::anyhow!("Could not find documents directory"))
+}
+
+/// `dir/<title>.<ext>` with the title made filename-safe, numbered so nothing is overwritten
+fn unique_export_path(dir: &std::path::Path, title: &str, ext: &str) -> std::path::PathBuf {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let stem = if stem.trim().is_empty() { "Untitled".to_string() } else { stem.trim().to_string() };
+
+    let mut path = dir.join(format!("{}.{}", stem, ext));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{} ({}).{}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+/// Join `row + 1` onto `row`: drop the whitespace on either side of the seam, leave one space
+/// between the two texts, and return the column where the joined text starts
+fn join_next_line(grid: &mut ChunkedGrid, row: usize, commands: &mut Vec<undo::Command>) -> usize {
+    let mut run = |grid: &mut ChunkedGrid, command: undo::Command| {
+        command.execute(grid);
+        commands.push(command);
+    };
+
+    let text_len = grid.get_text_length(row);
+    for _ in text_len..grid.get_line_length(row) {
+        run(grid, undo::Command::DeleteChar { row, col: text_len, deleted_char: grid.get(row, text_len) });
+    }
+    let next = row + 1;
+    let indent = (0..grid.get_line_length(next)).take_while(|&col| grid.get(next, col).is_whitespace()).count();
+    for _ in 0..indent {
+        run(grid, undo::Command::DeleteChar { row: next, col: 0, deleted_char: grid.get(next, 0) });
+    }
+
+    let next_len = grid.get_line_length(next);
+    let deleted_line = if next_len > 0 { grid.get_line(next, 0, next_len - 1) } else { String::new() };
+    let seam = if text_len > 0 && next_len > 0 { text_len + 1 } else { text_len };
+    run(grid, undo::Command::DeleteLine { row: next, deleted_line, prev_line_length: seam });
+    seam
+}
+
+/// The first character of `text` with the modifiers that belong to it (variation selectors,
+/// skin tones, zero-width-joined parts), so a note icon is one emoji
+fn first_glyph(text: &str) -> String {
+    let mut chars = text.chars().peekable();
+    let mut glyph: String = chars.next().into_iter().collect();
+    while let Some(&ch) = chars.peek() {
+        let joined = glyph.ends_with('\u{200D}');
+        if !(joined || matches!(ch, '\u{FE0E}' | '\u{FE0F}' | '\u{200D}' | '\u{1F3FB}'..='\u{1F3FF}')) {
+            break;
+        }
+        glyph.push(ch);
+        chars.next();
+    }
+    glyph
+}
+
+/// Notes menu items in display order (labels and hints come from the command registry)
+pub const NOTES_MENU_ITEMS: &[Command] = &[
+    Command::NewNote,
+    Command::DuplicateNote,
+    Command::RenameNote,
+    Command::NoteColor,
+    Command::NoteIcon,
+    Command::WordGoal,
+    Command::Favorite,
+    Command::ExportNote,
+    Command::ImportNote,
+    Command::NoteInfo,
+    Command::Scratchpad,
+    Command::ClearScratchpad,
+    Command::FocusTimer,
+    Command::SyncNow,
+    Command::Broadcast,
+    Command::DeleteNote,
+];
+
+/// Screen row (0-based) of the first Notes menu item; the menu's top border sits just above it
+pub const NOTES_MENU_FIRST_ROW: u16 = 2;
+
+/// What a finished overlay asks the app to do
+#[derive(Debug, Clone, PartialEq)]
+pub enum OverlayAction {
+    Context(ContextAction),
+    /// Import the file at this path
+    Import(String),
+    /// Switch to the note with this ID
+    OpenNote(String),
+    /// Esc on the start screen: back to the open note, or the newest one
+    LeaveStartScreen,
+    DeleteNote(String),
+    /// Give the open note this icon (empty removes it)
+    SetNoteIcon(String),
+    /// Give the open note this word goal (as typed; empty removes it)
+    SetWordGoal(String),
+    /// Embed the note with this title at the cursor
+    EmbedNote(String),
+    /// Ask for a name for this search query
+    NameSearch(String),
+    /// Keep this search query in the sidebar under this name
+    SaveSearch(String, String),
+    DeleteSavedSearch(i64),
+    /// Show the note with this ID read-only beside the editor
+    PinPreview(String),
+    /// Replace the block (row, col, last row, last col) with a shell command's output for it
+    FilterBlock((usize, usize, usize, usize), String),
+    /// Create a note titled this from the block's text
+    ExtractToNote((usize, usize, usize, usize), String),
+    /// Replace the block with a [[link]] to the note titled this
+    LinkExtracted((usize, usize, usize, usize), String),
+    /// Run a shell command and insert its output at the cursor
+    RunCommand(String),
+    /// Insert these lines at the cursor (command output, once confirmed)
+    InsertLines(Vec<String>),
+    ClearScratchpad,
+    /// Run a command picked in the command palette
+    Command(Command),
+    /// Run command (second) of plugin (first)
+    RunPlugin(usize, usize),
+    /// Color, highlight or embolden the characters of the block
+    PaintBlock((usize, usize, usize, usize), highlighter::Paint),
+}
+
+/// Actions offered by the editor's right-click menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContextAction {
+    Cut,
+    Copy,
+    Paste,
+    SelectAll,
+    SearchSelection,
+    Color,
+}
+
+fn main() -> Result<()> {
+    let settings = settings::Settings::load();
+    logging::init(settings.log_level);
+    log::info!("chonk-note {} started", env!("CARGO_PKG_VERSION"));
+
+    // Subcommands (e.g. `chonk-note import notes.enex`) run without the editor
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        return result;
+    }
+    let open_title = cli::open_title(&args);
+
+    // One editor per database: with one running already, hand the request to it
+    let data_dir = data_dir::dir()
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+    let Some(mut instance) = instance::acquire(&data_dir)? else {
+        println!("{}", instance::send(&data_dir, open_title.as_deref())?);
+        return Ok(());
+    };
+
+    let mut app = App::new()?;
+    if let Some(title) = open_title {
+        app.open_note_titled(&title)?;
+    }
+    if app.notes_mode.current_note.is_none() && app.settings.start_screen {
+        app.open_start_screen();
+    }
+    let runtime = tokio::runtime::Runtime::new()?;
+    install_panic_hook();
+
+    let result = {
+        let _terminal = TerminalGuard::new()?;
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| runtime.block_on(run_app(&mut app, &mut instance))))
+    };
+
+    // The terminal is restored by now, so messages below are readable
+    match result {
+        Ok(Ok(())) => {
+            if let Some(message) = app.exit_message.take() {
+                eprintln!("chonk-note: {}", message);
+            }
+            match app.exit_output.take() {
+                Some(text) => cli::page(&text),
+                None => Ok(()),
+            }
+        }
+        Ok(Err(e)) => {
+            save_after_crash(&mut app);
+            Err(e)
+        }
+        Err(panic) => {
+            save_after_crash(&mut app);
+            std::panic::resume_unwind(panic)
+        }
+    }
+}
+
+/// Best-effort save of the open note after run_app failed or panicked
+fn save_after_crash(app: &mut App) {
+    // Let a background save in progress land first
+    if let Err(e) = app.finish_db_requests() {
+        eprintln!("chonk-note: a background save failed: {}", e);
+    }
+    if !app.dirty {
+        return;
+    }
+    match app.save_current_note() {
+        Ok(()) if app.save_conflict.is_none() => eprintln!("chonk-note: unsaved changes were saved before exiting."),
+        Ok(()) => eprintln!("chonk-note: not saved, the note was changed by another program meanwhile."),
+        Err(e) => eprintln!("chonk-note: could not save unsaved changes: {}", e),
+    }
+}
+
+/// Whether the terminal is currently in raw/alternate-screen mode
+static TERMINAL_ACTIVE: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Puts the terminal into editor mode and restores it when dropped, including during a panic unwind
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new() -> Result<Self> {
+        setup_terminal()?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = restore_terminal();
+    }
+}
+
+/// Restore the terminal before the default hook prints the panic, so the message is not
+/// lost in the alternate screen or mangled by raw mode
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        log::error!("panic: {}", info);
+        eprintln!("\nchonk-note crashed. Please report this along with the message below.\n");
+        default_hook(info);
+    }));
+}
+
+fn setup_terminal() -> Result<()> {
+    TERMINAL_ACTIVE.store(true, std::sync::atomic::Ordering::SeqCst);
+    KittyTerminal::enable_raw_mode().map_err(|e| anyhow::anyhow!("Terminal setup failed: {}", e))?;
+    // Probe what the terminal supports before choosing mouse modes; keep any early keystrokes
+    KittyTerminal::unread(&term_caps::detect());
+    KittyTerminal::enter_fullscreen().map_err(|e| anyhow::anyhow!("Fullscreen failed: {}", e))?;
+
+    // Show and configure cursor
+    print!("\x1b[?25h");  // Show cursor
+    print!("\x1b[1 q");   // Blinking block
+    print!("\x1b[?12h");  // Enable blinking
+    std::io::Write::flush(&mut std::io::stdout())?;
+
+    Ok(())
+}
+
+/// Leave editor mode; safe to call more than once (later calls do nothing)
+fn restore_terminal() -> Result<()> {
+    if !TERMINAL_ACTIVE.swap(false, std::sync::atomic::Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // Reset cursor to default
+    print!("\x1b[0 q");  // Default cursor
+    print!("\x1b[?12l"); // Disable blinking
+    std::io::Write::flush(&mut std::io::stdout())?;
+
+    KittyTerminal::exit_fullscreen().map_err(|e| anyhow::anyhow!("Exit fullscreen failed: {}", e))?;
+    KittyTerminal::disable_raw_mode().map_err(|e| anyhow::anyhow!("Disable raw mode failed: {}", e))?;
+    Ok(())
+}
+
+/// Wait until stdin has bytes; without a watchable stdin (e.g. not a tty) fall back to a short sleep
+async fn stdin_readable(stdin: &Option<tokio::io::unix::AsyncFd<kitty_native::StdinFd>>) {
+    match stdin {
+        Some(fd) => {
+            if let Ok(mut guard) = fd.readable().await {
+                // Cleared before the next drain, so bytes arriving after it wake us again
+                guard.clear_ready();
+            }
+        }
+        None => tokio::time::sleep(std::time::Duration::from_millis(timing::FRAME_TIME_MS as u64)).await,
+    }
+}
+
+/// Next call from an automation client (never, when the server isn't running)
+async fn next_automation_call(calls: &mut Option<tokio::sync::mpsc::UnboundedReceiver<automation::Call>>) -> Option<automation::Call> {
+    match calls {
+        Some(calls) => calls.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Next note sent to the capture endpoint (never, when it isn't running)
+async fn next_capture(captures: &mut Option<tokio::sync::mpsc::UnboundedReceiver<capture_server::Capture>>) -> Option<capture_server::Capture> {
+    match captures {
+        Some(captures) => captures.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Resolves when the database thread has a finished request (never, without one)
+async fn db_response_ready(worker: &Option<notes_database::worker::DbWorker>) {
+    match worker {
+        Some(worker) => worker.ready().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Next request from another `chonk-note` invocation (never, if listening failed)
+async fn next_instance_request(requests: &mut Option<tokio::sync::mpsc::UnboundedReceiver<instance::Request>>) -> Option<instance::Request> {
+    match requests {
+        Some(requests) => requests.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn run_app(app: &mut App, instance: &mut instance::Instance) -> Result<()> {
+    let mut stdout = io::stdout();
+    let mut last_term_size = (0, 0);
+    let mut last_render_time = std::time::Instant::now();
+    let mut mouse_state = MouseState::default();
+
+    // Things that wake the loop: input, terminal resize, the autosave timer while there are unsaved changes
+    // and the live reload check
+    let stdin = tokio::io::unix::AsyncFd::new(kitty_native::StdinFd).ok();
+    let mut resized = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())?;
+    let mut autosave_timer = tokio::time::interval(std::time::Duration::from_millis(timing::AUTOSAVE_CHECK_MS));
+    autosave_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut autoscroll_timer = tokio::time::interval(std::time::Duration::from_millis(config::autoscroll::INTERVAL_MS));
+    let mut live_reload_timer = tokio::time::interval(std::time::Duration::from_millis(timing::LIVE_RELOAD_MS));
+    live_reload_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut sync_timer = tokio::time::interval(std::time::Duration::from_secs(app.settings.sync_minutes.max(1) * 60));
+    sync_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    // Optional local capture endpoint for browser extensions and scripts
+    let mut captures = if app.settings.capture_server {
+        match capture_server::start(app.settings.capture_port).await {
+            Ok(captures) => Some(captures),
+            Err(e) => {
+                log::error!("Capture endpoint not started: {}", e);
+                app.status_message = format!("Capture endpoint not started: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Optional automation socket; the socket file is removed when `_automation` drops
+    let (_automation, mut automation_calls) = if app.settings.automation_server {
+        match automation::start() {
+            Ok((server, calls)) => (Some(server), Some(calls)),
+            Err(e) => {
+                log::error!("Automation server not started: {}", e);
+                app.status_message = format!("Automation server not started: {}", e);
+                (None, None)
+            }
+        }
+    } else {
+        (None, None)
+    };
+
+    // Later invocations of chonk-note open their note here
+    let mut instance_requests = match instance.serve() {
+        Ok(requests) => Some(requests),
+        Err(e) => {
+            log::error!("Not answering other invocations: {}", e);
+            None
+        }
+    };
+
+    // Newest drag/hover position, handled once per frame so fast drags don't redraw per event
+    let mut pending_motion: Option<kitty_native::MouseEvent> = None;
+
+    'event_loop: loop {
+        let (term_width, term_height) = KittyTerminal::size()?;
+
+        // Pick up finished background saves and imports
+        if let Err(e) = app.poll_db_responses() {
+            log::error!("Applying a database result failed: {}", e);
+        }
+
+        // Auto-save debounced
+        if let Err(e) = app.auto_save() {
+            log::error!("Auto-save failed: {}", e);
+        }
+
+        // Check if terminal was resized
+        if (term_width, term_height) != last_term_size {
+            app.needs_redraw = true;
+            last_term_size = (term_width, term_height);
+        }
+
+        // Redraw when necessary (max 60 FPS)
+        let now = std::time::Instant::now();
+        let frame_time = now.duration_since(last_render_time);
+
+        // Smooth scrolling moves the viewport one step per frame
+        if frame_time.as_millis() >= timing::FRAME_TIME_MS && app.step_smooth_scroll() {
+            app.needs_redraw = true;
+        }
+
+        // Apply the latest drag position right before the frame that shows it
+        if frame_time.as_millis() >= timing::FRAME_TIME_MS {
+            if let Some(motion) = pending_motion.take() {
+                mouse::handle_mouse(app, motion, &mut mouse_state).await?;
+            }
+        }
+
+        if app.needs_redraw && frame_time.as_millis() >= timing::FRAME_TIME_MS {
+            KittyTerminal::move_to(0, 0)?;
+            last_render_time = now;
+
+            // Reduced output over slow links: palette colors, no images, only changed editor rows
+            let low_bandwidth = app.settings.low_bandwidth_active();
+            term_caps::set_low_bandwidth(low_bandwidth);
+            term_caps::set_ascii_borders(app.settings.ascii_borders);
+
+            // BEGIN SYNCHRONIZED UPDATE - prevents flicker
+            let synchronized = term_caps::current().synchronized_output;
+            if synchronized {
+                print!("\x1b[?2026h");
+            }
+            print!("\x1b[s");
+
+            // Clear entire screen first to prevent artifacts (low-bandwidth frames rewrite
+            // every line they change instead)
+            if !low_bandwidth {
+                print!("\x1b[2J");
+            }
+
+            // Render title bar
+            let total_width = term_width as usize;
+            let title_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+            let title_fg = rgb_fg(colors::TITLE_BAR_FG.0, colors::TITLE_BAR_FG.1, colors::TITLE_BAR_FG.2);
+
+            // Draw full teal bar first (always full width)
+            print!("\x1b[1;1H{}{}\x1b[0m", title_bg, " ".repeat(total_width));
+
+            // Left side: "Notes ▾" and "Settings ▾" menu buttons
+            let arrow = |open: bool| term_caps::glyph(if open { '▴' } else { '▾' });
+            let notes_text = format!("Notes {}", arrow(app.notes_menu_expanded));
+            let settings_text = format!("Settings {}", arrow(app.settings_menu_expanded));
+            let outline_text = format!("Outline {}", arrow(app.outline_panel_expanded));
+
+            let [notes_start_col, settings_start_col, outline_start_col] = mouse::TITLE_BUTTONS.map(|(start, _)| start);
+
+            // The button under the pointer is shown in reverse video
+            for (i, (col, text)) in [(notes_start_col, notes_text), (settings_start_col, settings_text), (outline_start_col, outline_text)].into_iter().enumerate() {
+                let hovered = if app.hover == Some(mouse::Hover::TitleButton(i)) { "\x1b[7m" } else { "" };
+                print!("\x1b[1;{}H{}{}\x1b[1m{}{}\x1b[0m", col + 1, title_bg, title_fg, hovered, text);
+            }
+
+            // Middle: title of the open note (click to rename it here)
+            let field_width = mouse::title_field_width(term_width);
+            if field_width > 0 {
+                let style = format!("{}{}", title_bg, title_fg);
+                let field = if app.editing_title {
+                    app.title_edit.render(field_width, &style)
+                } else {
+                    let title = app.notes_mode.current_note.as_ref().map(|n| n.title.as_str()).unwrap_or("");
+                    let title = if title.is_empty() { "Untitled" } else { title };
+                    let mut text: String = title.chars().take(field_width).collect();
+                    if title.chars().count() > field_width {
+                        text.pop();
+                        text.push('…');
+                    }
+                    text
+                };
+                print!("\x1b[1;{}H{}{}\x1b[0m", mouse::TITLE_FIELD_START + 1, style, field);
+
+                // The note's icon (on its accent color) or an accent dot, just left of the title
+                if let Some(note) = app.notes_mode.current_note.as_ref() {
+                    let accent_bg = note.accent.map(|(r, g, b)| rgb_bg(r, g, b)).unwrap_or_else(|| title_bg.clone());
+                    let marker = match (&note.icon, note.accent) {
+                        (Some(icon), _) => Some(format!("{}{}", accent_bg, icon)),
+                        (None, Some((r, g, b))) => Some(format!("{}{}● ", title_bg, rgb_fg(r, g, b))),
+                        (None, None) => None,
+                    };
+                    if let Some(marker) = marker {
+                        print!("\x1b[1;{}H{}\x1b[0m", mouse::TITLE_FIELD_START - 1, marker);
+                    }
+                }
+            }
 
             // Right side: Hamster + "Chonk-Note"
             let branding_text = "  Chonk-Note "; // Extra space at start to move text right
@@ -337,15 +4265,19 @@ async fn run_app(app: &mut App) -> Result<()> {
             let right_col = total_width.saturating_sub(branding_len + hamster_cols + 1); // Move left by 1
 
             print!("\x1b[1;{}H", right_col + 1); // Position for hamster
-            let _ = KittyTerminal::display_inline_png(HAMSTER_PNG, hamster_cols as u16, hamster_rows as u16);
+            if term_caps::current().kitty_graphics && !low_bandwidth {
+                let _ = KittyTerminal::display_inline_png(HAMSTER_PNG, hamster_cols as u16, hamster_rows as u16);
+            } else {
+                print!("{}{}🐹", title_bg, title_fg);
+            }
             print!("{}{}\x1b[1m{}\x1b[0m", title_bg, title_fg, branding_text);
 
             // Render dropdown menus if expanded
             if app.notes_menu_expanded {
-                render_notes_menu(app, notes_start_col as u16, 2)?;
+                render_notes_menu(app, notes_start_col, 2)?;
             }
             if app.settings_menu_expanded {
-                render_settings_menu(app, settings_start_col as u16, 2)?;
+                render_settings_menu(app, settings_start_col, 2)?;
             }
 
             // Sidebar widths
@@ -355,14 +4287,39 @@ async fn run_app(app: &mut App) -> Result<()> {
             // Ensure cursor is not under the sidebar
             app.clamp_cursor_to_visible_area(notes_list_width);
 
-            // Update viewport to keep cursor visible (subtract 2 rows: 1 for title bar, 1 for status line)
+            // Update viewport to follow the cursor when it moved (subtract 2 rows: 1 for title bar, 1 for status line)
             let editor_height = term_height.saturating_sub(2);
-            app.update_viewport(term_width, editor_height);
+            if app.viewport_anchor != (app.cursor_row, app.cursor_col) {
+                app.update_viewport(term_width, editor_height);
+                app.viewport_anchor = (app.cursor_row, app.cursor_col);
+            }
+
+            app.enforce_memory_cap(term_width, editor_height);
+
+            // Unchanged editor rows can be skipped while the panels around them stay put and
+            // nothing is drawn over the editor (menus, popups, prompts)
+            let covers_editor = app.notes_menu_expanded || app.settings_menu_expanded || !app.overlays.is_empty()
+                || !app.config_problems.is_empty() || app.note_info_open || app.diagnostics.hud_visible
+                || app.quit_save_error.is_some() || app.save_conflict.is_some();
+            if low_bandwidth && !covers_editor {
+                use std::hash::{Hash, Hasher};
+                let mut layout = std::hash::DefaultHasher::new();
+                (term_width, term_height, notes_list_width, settings_panel_width, app.outline_panel_expanded, app.history_browser_open, app.backlinks_panel_expanded, app.tasks_panel_expanded, app.preview.is_some())
+                    .hash(&mut layout);
+                app.editor_rows.start_frame(layout.finish());
+            } else {
+                app.editor_rows.invalidate();
+            }
 
             // Render notes editor at full width starting at row 2 (after 1-row title bar)
             let cursor_screen_pos = render_notes_pane(&mut *app, 0, 1, term_width, editor_height)?;
 
             // Render notes list sidebar on top of editor (overlay, also starting at row 2)
+            if app.sidebar_expanded {
+                if let Err(e) = app.smart_views.refresh(&app.notes_mode.db) {
+                    log::error!("Reading smart views failed: {}", e);
+                }
+            }
             render_notes_list(&app, 0, 1, notes_list_width, editor_height)?;
 
             // Render settings panel on right side (overlay)
@@ -371,9 +4328,75 @@ async fn run_app(app: &mut App) -> Result<()> {
                 render_settings_panel(&app, panel_x, 1, settings_panel_width, editor_height)?;
             }
 
+            // Render outline panel to the left of the settings panel (overlay)
+            if app.outline_panel_expanded {
+                app.outline.refresh(&app.grid);
+                let panel_x = term_width.saturating_sub(settings_panel_width + layout::OUTLINE_PANEL_WIDTH);
+                render_outline_panel(app, panel_x, 1, layout::OUTLINE_PANEL_WIDTH, editor_height)?;
+            }
+
+            // Render the backlinks panel to the left of the outline panel (overlay)
+            if let Some((panel_x, width)) = app.backlinks_panel(term_width) {
+                app.refresh_backlinks();
+                render_backlinks_panel(app, panel_x, 1, width, editor_height)?;
+            }
+
+            // Render the tasks panel to the left of the backlinks panel (overlay)
+            if let Some((panel_x, width)) = app.tasks_panel(term_width) {
+                app.refresh_tasks();
+                render_tasks_panel(app, panel_x, 1, width, editor_height)?;
+            }
+
+            // Render the pinned preview to the left of the other right-hand panels (overlay)
+            if let Some((panel_x, width)) = app.preview_panel(term_width) {
+                render_preview_panel(app, panel_x, 1, width, editor_height)?;
+            }
+
+            // Render undo history browser (overlay on the right edge)
+            if app.history_browser_open {
+                let panel_x = term_width.saturating_sub(layout::HISTORY_PANEL_WIDTH);
+                render_history_panel(app, panel_x, 1, layout::HISTORY_PANEL_WIDTH, editor_height)?;
+            }
+
+            // Render the problems found in settings.json and the plugins
+            if !app.config_problems.is_empty() {
+                render_config_banner(app, term_width);
+            }
+
+            // Render overlays (menus, pickers, prompts)
+            app.overlays.render(term_width, term_height);
+
+            // Render note info popup
+            if app.note_info_open {
+                render_note_info(app, term_width)?;
+            }
+
+            // Render performance HUD
+            if app.diagnostics.hud_visible {
+                render_performance_hud(app, term_width)?;
+            }
+
+            // Render quit prompt after a failed save
+            if app.quit_save_error.is_some() {
+                render_quit_prompt(app, term_width)?;
+            }
+
+            // Render save conflict prompt (modal, on top of everything)
+            if app.save_conflict.is_some() {
+                render_conflict_prompt(app, term_width, editor_height)?;
+            }
+
             // Render status line at bottom
+            if app.notes_mode.current_note.as_ref().is_some_and(|note| note.word_goal.is_some()) {
+                app.word_count.refresh(&app.grid);
+            }
             render_status_line(&app, term_width, term_height)?;
 
+            // Follow mode: viewers get what the editor now shows
+            if let Some(broadcast) = &app.broadcast {
+                broadcast.publish(app.broadcast_view(term_width as usize, editor_height as usize));
+            }
+
             // Position terminal cursor at the actual cursor location
             if let Some((screen_x, screen_y)) = cursor_screen_pos {
                 print!("\x1b[{};{}H", screen_y + 1, screen_x + 1); // Move to cursor position (1-based)
@@ -381,34 +4404,99 @@ async fn run_app(app: &mut App) -> Result<()> {
 
             // Make sure cursor is visible
             print!("\x1b[?25h");  // Show cursor
-            print!("\x1b[?2026l");
+            if synchronized {
+                print!("\x1b[?2026l");
+            }
             stdout.flush()?;
 
+            app.diagnostics.frame_rendered(now);
             app.needs_redraw = false;
         }
 
-        // Handle input
-        if KittyTerminal::poll_input()? {
-            if let Some(input) = KittyTerminal::read_input()? {
-                match input {
-                    kitty_native::InputEvent::Key(key) => {
-                        if !keyboard::handle_input(app, key).await? {
-                            break;
-                        }
-                        if app.exit_requested {
-                            break;
-                        }
+        // Handle every input event that is already available
+        while KittyTerminal::poll_input()? || KittyTerminal::has_buffered_input() {
+            let Some(input) = KittyTerminal::read_input()? else {
+                break; // Nothing left but the start of an escape sequence, wait for the rest
+            };
+            app.diagnostics.input_received(std::time::Instant::now());
+
+            // Anything other than motion applies after the drag that preceded it
+            let is_motion = matches!(input, kitty_native::InputEvent::Mouse(ref event) if event.is_drag);
+            if !is_motion {
+                if let Some(motion) = pending_motion.take() {
+                    mouse::handle_mouse(app, motion, &mut mouse_state).await?;
+                }
+            }
+
+            match input {
+                kitty_native::InputEvent::Key(key) => {
+                    if !keyboard::handle_input(app, key).await? || app.exit_requested {
+                        break 'event_loop;
                     }
-                    kitty_native::InputEvent::Mouse(mouse_event) => {
-                        mouse::handle_mouse(app, mouse_event, &mut mouse_state).await?;
+                }
+                kitty_native::InputEvent::Mouse(mouse_event) if is_motion => {
+                    let replacing = pending_motion.is_some();
+                    match mouse::coalesce_motion(&mut pending_motion, mouse_event) {
+                        Some(previous) => mouse::handle_mouse(app, previous, &mut mouse_state).await?,
+                        None if replacing => app.diagnostics.motion_events_coalesced += 1,
+                        None => {}
                     }
                 }
+                kitty_native::InputEvent::Mouse(mouse_event) => {
+                    mouse::handle_mouse(app, mouse_event, &mut mouse_state).await?;
+                }
+            }
+        }
+
+        // Sleep until something happens; a pending redraw or smooth scroll wakes at the next frame
+        let frame_pending = app.needs_redraw || app.scroll_target_row.is_some() || pending_motion.is_some();
+        let next_frame = last_render_time + std::time::Duration::from_millis(timing::FRAME_TIME_MS as u64);
+        let focus_tick = app.focus_timer.as_ref().map(|timer| timer.next_change(std::time::Instant::now()));
+        let highlight_expiry = app.highlight_expiry();
+        tokio::select! {
+            _ = stdin_readable(&stdin) => {}
+            _ = resized.recv() => app.needs_redraw = true,
+            _ = autosave_timer.tick(), if app.dirty => {}
+            _ = db_response_ready(&app.notes_mode.worker) => {}
+            Some(call) = next_automation_call(&mut automation_calls) => {
+                let _ = call.reply.send(automation::execute(app, &call.method, &call.params));
+            }
+            Some(request) = next_instance_request(&mut instance_requests) => {
+                let _ = request.reply.send(app.answer_other_instance(request.title));
+            }
+            Some(capture) = next_capture(&mut captures) => {
+                let reply = app.add_captured_note(capture.request).map(|note| note.id).map_err(|e| e.to_string());
+                let _ = capture.reply.send(reply);
+            }
+            _ = sync_timer.tick(), if !app.settings.sync_url.trim().is_empty() => {
+                if let Err(e) = app.start_sync() {
+                    log::warn!("Sync failed to start: {}", e);
+                }
+            }
+            _ = live_reload_timer.tick() => {
+                app.poll_sync();
+                if let Err(e) = app.check_external_changes() {
+                    log::warn!("Live reload failed: {}", e);
+                }
+            }
+            _ = autoscroll_timer.tick(), if mouse_state.is_dragging && mouse_state.autoscroll != (0, 0) => {
+                mouse::autoscroll_step(app, &mut mouse_state)?;
             }
+            _ = tokio::time::sleep_until(focus_tick.unwrap_or(next_frame).into()), if focus_tick.is_some() => {
+                app.update_focus_timer();
+            }
+            _ = tokio::time::sleep_until(highlight_expiry.unwrap_or(next_frame).into()), if highlight_expiry.is_some() => {
+                app.expire_highlights();
+            }
+            _ = tokio::time::sleep_until(next_frame.into()), if frame_pending => {}
         }
     }
 
-    // Final save on exit
-    app.save_current_note()?;
+    // Final save on exit (Ctrl+Q has already saved or explicitly discarded changes)
+    app.finish_db_requests()?;
+    if app.dirty {
+        app.save_current_note()?;
+    }
     Ok(())
 }
 
@@ -419,72 +4507,326 @@ fn render_notes_pane(app: &mut App, x: u16, y: u16, width: u16, height: u16) ->
 fn render_notes_pane_normal(app: &mut App, x: u16, y: u16, width: u16, height: u16) -> Result<Option<(u16, u16)>> {
     let viewport_start_row = app.viewport_row;
     let viewport_start_col = app.viewport_col;
+    // Lay out only the occupied chunks that intersect the viewport, drawn as text runs
+    let frame::EditorFrame { mut screen, mut rows, cells_drawn } =
+        frame::build(&app.grid, viewport_start_row, viewport_start_col, x, y, width, height);
+    let mut cells_redrawn = cells_drawn;
 
-    // Render visible lines with selection highlighting
-    for screen_row in 0..height {
-        let grid_row = viewport_start_row + screen_row as usize;
+    // Render grid lines if enabled (anchored to the canvas origin so logical row/col 0 are the axes)
+    if app.show_grid_lines {
+        let (r, g, b) = app.settings.grid_color;
+        let grid_fg = rgb_fg(r, g, b);
+        let axis_fg = rgb_fg(colors::ORIGIN_AXIS_FG.0, colors::ORIGIN_AXIS_FG.1, colors::ORIGIN_AXIS_FG.2);
+        let col_spacing = app.settings.grid_col_spacing.max(1);
+        let row_spacing = app.settings.grid_row_spacing.max(1);
+        use std::fmt::Write as _;
+        let (vertical_ch, horizontal_ch) = match app.settings.grid_style {
+            settings::GridStyle::Solid => ('│', '─'),
+            settings::GridStyle::Dotted => ('┆', '┄'),
+        };
+
+        for row in 0..height {
+            let grid_row = viewport_start_row + row as usize;
+            let (logical_row, _) = app.grid.logical_pos(grid_row, 0);
+            let on_horizontal = logical_row.rem_euclid(row_spacing as i64) == 0;
+
+            // Runs of adjacent grid cells in the same color: (start column, length, axis, text)
+            let mut runs: Vec<(usize, usize, bool, String)> = Vec::new();
+            for (col, &cell) in screen[row as usize].iter().enumerate() {
+                let grid_col = viewport_start_col + col;
+                let (_, logical_col) = app.grid.logical_pos(0, grid_col);
+                let on_vertical = logical_col.rem_euclid(col_spacing as i64) == 0;
+                if !on_vertical && !on_horizontal {
+                    continue;
+                }
 
-        // Clear line
-        print!("\x1b[{};{}H\x1b[K", y + screen_row + 1, x + 1);
+                // Only draw grid line if cell is empty
+                if cell != ' ' {
+                    continue;
+                }
 
-        // Render each character with selection highlighting
-        for screen_col in 0..width as usize {
-            let grid_col = viewport_start_col + screen_col;
-            let ch = app.grid.get(grid_row, grid_col);
+                let row_axis = on_horizontal && logical_row == 0;
+                let col_axis = on_vertical && logical_col == 0;
+                let ch = match (on_vertical, on_horizontal) {
+                    (true, true) if row_axis || col_axis => '╋',
+                    (true, true) => '┼',
+                    (true, false) if col_axis => '┃',
+                    (true, false) => vertical_ch,
+                    _ if row_axis => '━',
+                    _ => horizontal_ch,
+                };
+                let ch = term_caps::glyph(ch);
+                let axis = row_axis || col_axis;
+                match runs.last_mut() {
+                    Some((start, len, run_axis, text)) if *run_axis == axis && *start + *len == col => {
+                        text.push(ch);
+                        *len += 1;
+                    }
+                    _ => runs.push((col, 1, axis, ch.to_string())),
+                }
+                cells_redrawn += 1;
+            }
 
-            // Check if this position is in the selection
-            let in_selection = if let Some(ref sel) = app.grid.selection {
-                sel.contains(grid_row, grid_col)
-            } else {
-                false
+            let line = &mut rows[row as usize];
+            for (col, _, axis, text) in runs {
+                let fg = if axis { &axis_fg } else { &grid_fg };
+                let _ = write!(line, "\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + col as u16 + 1, fg, text);
+            }
+        }
+    }
+    // Whitespace mode: blanks inside each line as faint dots, tabs as arrows, and characters
+    // that are otherwise invisible in a warning color (later layers draw the marks too)
+    if app.show_whitespace {
+        use std::fmt::Write as _;
+        let blank_fg = rgb_fg(colors::WHITESPACE_FG.0, colors::WHITESPACE_FG.1, colors::WHITESPACE_FG.2);
+        let hidden_colors = format!("{}{}",
+            rgb_bg(colors::HIDDEN_CHAR_BG.0, colors::HIDDEN_CHAR_BG.1, colors::HIDDEN_CHAR_BG.2),
+            rgb_fg(colors::HIDDEN_CHAR_FG.0, colors::HIDDEN_CHAR_FG.1, colors::HIDDEN_CHAR_FG.2));
+        for screen_row in 0..height as usize {
+            let row = viewport_start_row + screen_row;
+            let end = app.grid.get_line_length(row).saturating_sub(viewport_start_col).min(width as usize);
+            // Runs of adjacent marks of one kind: (start column, hidden, text)
+            let mut runs: Vec<(usize, bool, String)> = Vec::new();
+            for (screen_col, cell) in screen[screen_row].iter_mut().enumerate().take(end) {
+                let col = viewport_start_col + screen_col;
+                if *cell != ' ' || app.grid.selection.as_ref().is_some_and(|s| s.contains(row, col)) {
+                    continue;
+                }
+                let (ch, hidden) = match whitespace::mark(app.grid.get(row, col)) {
+                    Some(whitespace::Mark::Blank(ch)) => (term_caps::glyph(ch), false),
+                    Some(whitespace::Mark::Hidden(ch)) => (term_caps::glyph(ch), true),
+                    None => continue,
+                };
+                *cell = ch;
+                match runs.last_mut() {
+                    Some((start, run_hidden, text)) if *run_hidden == hidden && *start + text.chars().count() == screen_col => text.push(ch),
+                    _ => runs.push((screen_col, hidden, ch.to_string())),
+                }
+                cells_redrawn += 1;
+            }
+            for (start, hidden, text) in runs {
+                let colors = if hidden { &hidden_colors } else { &blank_fg };
+                let _ = write!(rows[screen_row], "\x1b[{};{}H{}{}\x1b[0m", y + screen_row as u16 + 1, x + start as u16 + 1, colors, text);
+            }
+        }
+    }
+
+    // Alignment guides after a snapped click or paste, dotted through the blank cells between
+    // the cursor and the blocks it lines up with
+    if !app.guides.is_empty() {
+        use std::fmt::Write as _;
+        let guide_fg = rgb_fg(colors::GUIDE_FG.0, colors::GUIDE_FG.1, colors::GUIDE_FG.2);
+        let guide = term_caps::glyph('┆');
+        for found in &app.guides {
+            let Some(screen_col) = found.col.checked_sub(viewport_start_col).filter(|&col| col < width as usize) else {
+                continue;
             };
+            for row in found.top.max(viewport_start_row)..=found.bottom.min(viewport_start_row + height as usize - 1) {
+                let screen_row = row - viewport_start_row;
+                if screen[screen_row][screen_col] == ' ' {
+                    let _ = write!(rows[screen_row], "\x1b[{};{}H{}{}\x1b[0m", y + screen_row as u16 + 1, x + screen_col as u16 + 1, guide_fg, guide);
+                    cells_redrawn += 1;
+                }
+            }
+        }
+    }
 
-            // Render with appropriate color
-            if in_selection {
-                // For selected cells, always show background even for spaces
-                let display_ch = if ch == ' ' { ' ' } else { ch };
-                let sel_bg = rgb_bg(colors::SELECTION_BG.0, colors::SELECTION_BG.1, colors::SELECTION_BG.2);
-                let sel_fg = rgb_fg(colors::SELECTION_FG.0, colors::SELECTION_FG.1, colors::SELECTION_FG.2);
-                print!("{}{}{}\x1b[0m", sel_bg, sel_fg, display_ch);
-            } else {
-                print!("{}", ch);
+    // Links (URLs and file paths), underlined in the link color
+    {
+        use std::fmt::Write as _;
+        let link_fg = rgb_fg(colors::LINK_FG.0, colors::LINK_FG.1, colors::LINK_FG.2);
+        for screen_row in 0..height as usize {
+            for link in links::links_in_row(&app.grid, viewport_start_row + screen_row) {
+                let start = link.start.max(viewport_start_col);
+                let end = link.end.min(viewport_start_col + width as usize);
+                if start >= end {
+                    continue;
+                }
+                let text: String = screen[screen_row][start - viewport_start_col..end - viewport_start_col].iter().collect();
+                let _ = write!(rows[screen_row], "\x1b[{};{}H{}\x1b[4m{}\x1b[0m",
+                    y + screen_row as u16 + 1, x + (start - viewport_start_col) as u16 + 1, link_fg, text);
+                cells_redrawn += end - start;
             }
         }
     }
 
-    // Render grid lines if enabled
-    if app.show_grid_lines {
-        let grid_fg = rgb_fg(colors::GRID_LINE_FG.0, colors::GRID_LINE_FG.1, colors::GRID_LINE_FG.2);
+    // Embedded notes, in the empty rows under each ![[Title]] token (tokens above the view
+    // can reach into it)
+    {
+        use std::fmt::Write as _;
+        let embed_bg = rgb_bg(colors::EMBED_BG.0, colors::EMBED_BG.1, colors::EMBED_BG.2);
+        let embed_fg = rgb_fg(colors::EMBED_FG.0, colors::EMBED_FG.1, colors::EMBED_FG.2);
+        let bar_fg = rgb_fg(colors::LINK_FG.0, colors::LINK_FG.1, colors::LINK_FG.2);
+        let max_rows = config::transclusion::MAX_ROWS;
+        let end_row = viewport_start_row + height as usize;
+        let end_col = viewport_start_col + width as usize;
+        for row in viewport_start_row.saturating_sub(max_rows)..end_row {
+            let line: Vec<char> = (0..app.grid.get_line_length(row)).map(|col| app.grid.get(row, col)).collect();
+            for (start, _, title) in transclusion::embeds_in_line(&line) {
+                let room = (row + 1..row + 1 + max_rows).take_while(|&r| app.grid.get_line_length(r) == 0).count();
+                let lines = match app.embeds.get(&app.notes_mode.db, &title) {
+                    Ok(transclusion::Source::Note { lines, .. }) if lines.len() > room => {
+                        let mut shown = lines[..room.saturating_sub(1)].to_vec();
+                        shown.push(format!("… {} more lines", lines.len() + 1 - room));
+                        shown
+                    }
+                    Ok(transclusion::Source::Note { lines, .. }) => lines.clone(),
+                    Ok(transclusion::Source::Missing) => vec![format!("No note named \"{}\"", title)],
+                    Err(e) => {
+                        log::error!("Reading embedded note {} failed: {}", title, e);
+                        continue;
+                    }
+                };
+                for (i, text) in lines.iter().take(room).enumerate() {
+                    let Some(screen_row) = (row + 1 + i).checked_sub(viewport_start_row).filter(|&r| r < height as usize) else {
+                        continue;
+                    };
+                    // A bar in the first column, then the text, cut to the visible columns
+                    let cells: Vec<char> = std::iter::once('│').chain(std::iter::once(' ')).chain(text.chars()).collect();
+                    let first = start.max(viewport_start_col);
+                    let last = (start + cells.len()).min(end_col);
+                    if first >= last {
+                        continue;
+                    }
+                    let visible: String = cells[first - start..last - start].iter().collect();
+                    let (bar, rest) = if first == start { visible.split_at('│'.len_utf8()) } else { ("", visible.as_str()) };
+                    let _ = write!(rows[screen_row], "\x1b[{};{}H{}{}{}{}{}\x1b[0m",
+                        y + screen_row as u16 + 1, x + (first - viewport_start_col) as u16 + 1,
+                        embed_bg, bar_fg, bar, embed_fg, rest);
+                    cells_redrawn += last - first;
+                }
+            }
+        }
+    }
 
-        // Vertical lines every 8 characters
-        for col in (layout::GRID_VERTICAL_SPACING..width as usize).step_by(layout::GRID_VERTICAL_SPACING) {
-            for row in 0..height {
-                let grid_row = viewport_start_row + row as usize;
-                let grid_col = viewport_start_col + col;
-                let ch = app.grid.get(grid_row, grid_col);
+    // The cursor's row (and column, for a crosshair) tinted so the cursor is easy to spot;
+    // selected cells keep the selection colors
+    let (cursor_highlight, flashing) = app.cursor_highlight();
+    if cursor_highlight != settings::CursorHighlight::Off {
+        use std::fmt::Write as _;
+        let (bg, text_fg) = if flashing {
+            (colors::CURSOR_FLASH_BG, Some(colors::CURSOR_FLASH_FG))
+        } else {
+            (colors::CURSOR_LINE_BG, None)
+        };
+        let bg = rgb_bg(bg.0, bg.1, bg.2);
+        let styled = app.grid.has_styles();
+        let cell = |screen_row: usize, screen_col: usize| -> Option<String> {
+            let (row, col) = (viewport_start_row + screen_row, viewport_start_col + screen_col);
+            if app.grid.selection.as_ref().is_some_and(|selection| selection.contains(row, col)) {
+                return None;
+            }
+            let style = if styled { app.grid.style(row, col) } else { Style::default() };
+            let fg = style.fg.or(text_fg).map(|(r, g, b)| rgb_fg(r, g, b)).unwrap_or_default();
+            let bold = if style.bold { "\x1b[1m" } else { "" };
+            let underline = if style.underline { "\x1b[4m" } else { "" };
+            Some(format!("\x1b[{};{}H{}{}{}{}{}\x1b[0m",
+                y + screen_row as u16 + 1, x + screen_col as u16 + 1, bg, fg, bold, underline, screen[screen_row][screen_col]))
+        };
+        let mut cells = Vec::new();
+        let cursor_row = app.cursor_row.checked_sub(viewport_start_row).filter(|&row| row < height as usize);
+        let cursor_col = app.cursor_col.checked_sub(viewport_start_col).filter(|&col| col < width as usize);
+        if let Some(screen_row) = cursor_row {
+            cells.extend((0..width as usize).map(|screen_col| (screen_row, screen_col)));
+        }
+        if let (Some(screen_col), settings::CursorHighlight::Crosshair) = (cursor_col, cursor_highlight) {
+            cells.extend((0..height as usize).filter(|&row| Some(row) != cursor_row).map(|screen_row| (screen_row, screen_col)));
+        }
+        for (screen_row, screen_col) in cells {
+            if let Some(text) = cell(screen_row, screen_col) {
+                let _ = write!(rows[screen_row], "{}", text);
+                cells_redrawn += 1;
+            }
+        }
+    }
 
-                // Only draw grid line if cell is empty
-                if ch == ' ' {
-                    print!("\x1b[{};{}H{}│\x1b[0m",
-                           y + row as u16 + 1, x + col as u16 + 1, grid_fg);
+    // Other occurrences of the selected text, to show what Ctrl+D / Ctrl+Shift+L would select
+    let target = match (app.selections.as_ref(), app.grid.selection.as_ref().map(|block| block.bounds())) {
+        (Some(selections), _) => Some(selections.primary()).filter(|range| !range.is_empty()),
+        (None, Some((row, min_col, max_row, max_col))) if row == max_row => {
+            Some(selections::Range { row, anchor: min_col, head: max_col + 1 })
+        }
+        _ => None,
+    };
+    if let Some(target) = target {
+        use std::fmt::Write as _;
+        let match_bg = rgb_bg(colors::MATCH_HIGHLIGHT_BG.0, colors::MATCH_HIGHLIGHT_BG.1, colors::MATCH_HIGHLIGHT_BG.2);
+        let needle: Vec<char> = selections::text_of(&app.grid, target).chars().collect();
+        let needle = if needle.iter().all(|c| c.is_whitespace()) { Vec::new() } else { needle };
+        let selected = |found: &selections::Range| {
+            app.selections.as_ref().is_some_and(|s| s.ranges().contains(found))
+                || app.grid.selection.as_ref().is_some_and(|block| block.contains(found.row, found.start()))
+        };
+        for screen_row in 0..height as usize {
+            let row = viewport_start_row + screen_row;
+            for found in selections::occurrences_in_row(&app.grid, row, &needle) {
+                let start = found.start().max(viewport_start_col);
+                let end = found.end().min(viewport_start_col + width as usize);
+                if start >= end || selected(&found) {
+                    continue;
                 }
+                let text: String = screen[screen_row][start - viewport_start_col..end - viewport_start_col].iter().collect();
+                let _ = write!(rows[screen_row], "\x1b[{};{}H{}{}\x1b[0m",
+                    y + screen_row as u16 + 1, x + (start - viewport_start_col) as u16 + 1, match_bg, text);
+                cells_redrawn += end - start;
             }
         }
+    }
 
-        // Horizontal lines every 4 rows
-        for row in (layout::GRID_HORIZONTAL_SPACING..height as usize).step_by(layout::GRID_HORIZONTAL_SPACING) {
-            for col in 0..width as usize {
-                let grid_row = viewport_start_row + row;
-                let grid_col = viewport_start_col + col;
-                let ch = app.grid.get(grid_row, grid_col);
-
-                // Only draw grid line if cell is empty
-                if ch == ' ' {
-                    print!("\x1b[{};{}H{}─\x1b[0m",
-                           y + row as u16 + 1, x + col as u16 + 1, grid_fg);
+    // Multiple selections: ranges in the selection colors, carets other than the cursor as teal blocks
+    if let Some(selections) = app.selections.as_ref() {
+        use std::fmt::Write as _;
+        let sel_bg = rgb_bg(colors::SELECTION_BG.0, colors::SELECTION_BG.1, colors::SELECTION_BG.2);
+        let sel_fg = rgb_fg(colors::SELECTION_FG.0, colors::SELECTION_FG.1, colors::SELECTION_FG.2);
+        let caret_bg = rgb_bg(colors::SECONDARY_CURSOR_BG.0, colors::SECONDARY_CURSOR_BG.1, colors::SECONDARY_CURSOR_BG.2);
+        let caret_fg = rgb_fg(colors::SECONDARY_CURSOR_FG.0, colors::SECONDARY_CURSOR_FG.1, colors::SECONDARY_CURSOR_FG.2);
+        let primary = selections.primary();
+        for range in selections.ranges() {
+            let Some(screen_row) = range.row.checked_sub(viewport_start_row).filter(|&r| r < height as usize) else {
+                continue;
+            };
+            let (start, end) = if range.is_empty() { (range.head, range.head + 1) } else { (range.start(), range.end()) };
+            let start = start.max(viewport_start_col);
+            let end = end.min(viewport_start_col + width as usize);
+            if start >= end || (range.is_empty() && *range == primary) {
+                continue;
+            }
+            let text: String = screen[screen_row][start - viewport_start_col..end - viewport_start_col].iter().collect();
+            let (bg, fg) = if range.is_empty() { (&caret_bg, &caret_fg) } else { (&sel_bg, &sel_fg) };
+            let _ = write!(rows[screen_row], "\x1b[{};{}H{}{}{}\x1b[0m",
+                y + screen_row as u16 + 1, x + (start - viewport_start_col) as u16 + 1, bg, fg, text);
+            cells_redrawn += end - start;
+        }
+    }
+    app.diagnostics.cells_redrawn = cells_redrawn;
+    print!("{}", app.editor_rows.update(rows));
+
+    // Thumbnails of pasted images in the rows below their tokens. Only whole ones: part of a
+    // thumbnail can't be cut off, and one reaching past the bottom would scroll the terminal.
+    if term_caps::current().kitty_graphics && !app.settings.low_bandwidth_active() {
+        let mut output = app.thumbnails.take_freed();
+        let note_id = app.notes_mode.current_note.as_ref().map(|note| note.id.clone()).unwrap_or_default();
+        for row in viewport_start_row.saturating_sub(1)..viewport_start_row + height as usize {
+            let line: Vec<char> = (0..app.grid.get_line_length(row)).map(|col| app.grid.get(row, col)).collect();
+            for (col, filename) in images::tokens_in_line(&line) {
+                let Some(screen_row) = (row + 1).checked_sub(viewport_start_row) else {
+                    continue;
+                };
+                let visible_cols = (viewport_start_col + width as usize).saturating_sub(col);
+                if screen_row + config::images::THUMBNAIL_ROWS > height as usize || col < viewport_start_col || visible_cols == 0 {
+                    continue;
                 }
+                let db = &app.notes_mode.db;
+                output.push_str(&app.thumbnails.place(
+                    &filename,
+                    x + (col - viewport_start_col) as u16,
+                    y + screen_row as u16,
+                    visible_cols,
+                    || db.attachment(&note_id, &filename).ok().flatten().map(|attachment| attachment.data),
+                ));
             }
         }
+        print!("{}", output);
     }
 
     // Calculate cursor screen position
@@ -516,10 +4858,54 @@ fn render_notes_list(app: &App, x: u16, y: u16, width: u16, height: u16) -> Resu
         print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, sidebar_bg, " ".repeat(width as usize));
     }
 
+    let hover_bg = rgb_bg(colors::SIDEBAR_HOVER_BG.0, colors::SIDEBAR_HOVER_BG.1, colors::SIDEBAR_HOVER_BG.2);
+    let scroll_fg = rgb_fg(colors::SIDEBAR_SCROLL_FG.0, colors::SIDEBAR_SCROLL_FG.1, colors::SIDEBAR_SCROLL_FG.2);
+
+    // Smart views first, then the list of all notes below them
+    let smart_rows = if app.sidebar_expanded { app.smart_views.rows() } else { Vec::new() };
+    let list_y = y + smart_rows.len() as u16;
+    for (i, row) in smart_rows.iter().enumerate().take(height.saturating_sub(1) as usize) {
+        let bg = if app.hover == Some(mouse::Hover::SidebarSmartRow(i)) { &hover_bg } else { &sidebar_bg };
+        let fold = |collapsed: bool| if collapsed { "▸" } else { "▾" };
+        let line = match *row {
+            smart_views::Row::Header(Some(section)) => {
+                let section = &app.smart_views.sections[section];
+                let label: String = section.label.chars().take((width as usize).saturating_sub(10)).collect();
+                format!("\x1b[1m{}{} {} ({})", scroll_fg, fold(section.collapsed), label, section.total)
+            }
+            smart_views::Row::Header(None) => {
+                format!("\x1b[1m{}{} All notes ({})", scroll_fg, fold(app.smart_views.all_collapsed), app.notes_total)
+            }
+            smart_views::Row::Note(section, index) => {
+                let note = &app.smart_views.sections[section].notes[index];
+                let (r, g, b) = note.accent.unwrap_or(colors::SIDEBAR_FG);
+                let title = if note.title.is_empty() { "Untitled" } else { &note.title };
+                let title = match &note.icon {
+                    Some(icon) => format!("{} {}", icon, title),
+                    None => title.to_string(),
+                };
+                let max_title_len = (width as usize).saturating_sub(4);
+                let title: String = if title.chars().count() > max_title_len {
+                    title.chars().take(max_title_len.saturating_sub(1)).chain(['…']).collect()
+                } else {
+                    title
+                };
+                // Favorites show their Alt+number in place of the bullet
+                let bullet = app.favorite_slot(&note.id).map_or('•', |slot| char::from(b'0' + slot));
+                format!("  {}{}{} {}", rgb_fg(r, g, b), bullet, sidebar_fg, title)
+            }
+            smart_views::Row::More(section) => {
+                let section = &app.smart_views.sections[section];
+                format!("{}    … {} more", scroll_fg, section.total - section.notes.len())
+            }
+        };
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + i as u16 + 1, x + 1, bg, line);
+    }
+
     if app.notes_list.is_empty() {
-        print!("\x1b[{};{}H{}{} +\x1b[0m", y + 2, x + 1, sidebar_bg, sidebar_icon_fg);
-    } else {
-        let visible_count = (height - 2) as usize;
+        print!("\x1b[{};{}H{}{} +\x1b[0m", list_y + 2, x + 1, sidebar_bg, sidebar_icon_fg);
+    } else if !(app.sidebar_expanded && app.smart_views.all_collapsed) {
+        let visible_count = height.saturating_sub(2 + smart_rows.len() as u16) as usize;
         let start_index = app.notes_list_scroll;
         let end_index = (start_index + visible_count).min(app.notes_list.len());
 
@@ -532,50 +4918,80 @@ fn render_notes_list(app: &App, x: u16, y: u16, width: u16, height: u16) -> Resu
 
             let (bg_color, text_color) = if is_selected {
                 (&selected_bg, &selected_fg)
+            } else if app.hover == Some(mouse::Hover::SidebarNote(note_idx)) {
+                (&hover_bg, &sidebar_fg)
             } else {
                 (&sidebar_bg, &sidebar_fg)
             };
 
             if app.sidebar_expanded {
                 // If this is the selected note and we're editing the title, show the buffer with cursor
-                let display_title = if is_selected && app.editing_title {
-                    format!("{}_", &app.title_buffer) // Show cursor with underscore
+                let prefix = if is_selected { "▸ " } else { "  " };
+                let favorite = app.favorite_slot(&note.id).map(|slot| format!("{}{} ", scroll_fg, slot)).unwrap_or_default();
+                let favorite_width = if favorite.is_empty() { 0 } else { 2 };
+                let max_title_len = (width as usize).saturating_sub(prefix.chars().count() + favorite_width);
+                // The marker takes the note's accent color; an accent on an unselected note shows as a bar
+                let (prefix, marker_fg) = match note.accent {
+                    Some((r, g, b)) => (if is_selected { "▸ " } else { "▌ " }, rgb_fg(r, g, b)),
+                    None => (prefix, text_color.clone()),
+                };
+
+                // The title being renamed is edited in place
+                if is_selected && app.editing_title {
+                    let style = format!("{}\x1b[1m{}", bg_color, text_color);
+                    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+                        list_y + display_pos as u16 + 1, x + 1,
+                        style, prefix, app.title_edit.render(max_title_len, &style));
+                    continue;
+                }
+
+                let display_title = if note.title.is_empty() {
+                    "Untitled".to_string()
                 } else {
-                    let title = if note.title.is_empty() {
-                        "Untitled".to_string()
-                    } else {
-                        note.title.clone()
-                    };
-                    title
+                    note.title.clone()
+                };
+                let display_title = match &note.icon {
+                    Some(icon) => format!("{} {}", icon, display_title),
+                    None => display_title,
                 };
 
-                let prefix = if is_selected { "▸ " } else { "  " };
-                let max_title_len = (width as usize).saturating_sub(prefix.len());
                 let truncated_title: String = if display_title.len() > max_title_len {
                     format!("{}…", &display_title[..max_title_len.saturating_sub(1)])
                 } else {
                     display_title
                 };
 
-                print!("\x1b[{};{}H{}\x1b[1m{}{}{}\x1b[0m",
-                    y + display_pos as u16 + 1, x + 1,
-                    bg_color, text_color, prefix, truncated_title);
+                print!("\x1b[{};{}H{}\x1b[1m{}{}{}{}{}\x1b[0m",
+                    list_y + display_pos as u16 + 1, x + 1,
+                    bg_color, marker_fg, prefix, favorite, text_color, truncated_title);
             } else {
-                let indicator = if is_selected { "▸" } else { " " };
+                let (indicator, indicator_fg) = match note.accent {
+                    Some((r, g, b)) => (if is_selected { "▸" } else { "▌" }, rgb_fg(r, g, b)),
+                    None => (if is_selected { "▸" } else { " " }, text_color.clone()),
+                };
+                // Unselected favorites show their Alt+number
+                let slot = app.favorite_slot(&note.id).filter(|_| !is_selected).map(|slot| slot.to_string());
+                let indicator = slot.as_deref().unwrap_or(indicator);
                 print!("\x1b[{};{}H{}{}{}\x1b[0m",
-                    y + display_pos as u16 + 1, x + 1,
-                    bg_color, text_color, indicator);
+                    list_y + display_pos as u16 + 1, x + 1,
+                    bg_color, indicator_fg, indicator);
             }
         }
 
-        // Scroll indicators
-        let scroll_fg = rgb_fg(colors::SIDEBAR_SCROLL_FG.0, colors::SIDEBAR_SCROLL_FG.1, colors::SIDEBAR_SCROLL_FG.2);
-        if start_index > 0 {
+        // Scroll indicators (the "All notes" header takes the place of the up arrow)
+        if start_index > 0 && smart_rows.is_empty() {
             print!("\x1b[{};{}H{}{}↑\x1b[0m", y, x + 2, sidebar_bg, scroll_fg);
         }
-        if end_index < app.notes_list.len() {
+        if end_index < app.notes_total {
             print!("\x1b[{};{}H{}{}↓\x1b[0m", y + height - 1, x + 2, sidebar_bg, scroll_fg);
         }
+
+        // Count indicator, e.g. "31-58 of 3402"
+        if app.sidebar_expanded {
+            let count = format!("{}-{} of {} ", start_index + 1, end_index, app.notes_total);
+            let col = x + width.saturating_sub(count.len() as u16);
+            print!("\x1b[{};{}H{}{}{}\x1b[0m", y + height - 1, col + 1, sidebar_bg, scroll_fg, count);
+        }
     }
 
     Ok(())
@@ -584,24 +5000,34 @@ fn render_notes_list(app: &App, x: u16, y: u16, width: u16, height: u16) -> Resu
 fn render_notes_menu(app: &App, x: u16, y: u16) -> Result<()> {
     let menu_bg = rgb_bg(250, 250, 250); // Light gray
     let menu_fg = rgb_fg(0, 0, 0); // Black text
+    let hover_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+    let hover_fg = rgb_fg(255, 255, 255);
+    let hint_fg = rgb_fg(120, 120, 120);
+
+    let menu_width = 45;
+    let separator = term_caps::rule(menu_width);
+
+    // y is the 1-based terminal row of the top separator
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y, x + 1, menu_bg, menu_fg, separator);
+
+    for (i, &command) in NOTES_MENU_ITEMS.iter().enumerate() {
+        let hovered = app.hover == Some(mouse::Hover::NotesMenuItem(i));
+        let (label, hint) = (app.command_label(command), commands::describe(command).1);
+        let (bg, fg, hint_fg) = if hovered {
+            (&hover_bg, &hover_fg, &hover_fg)
+        } else {
+            (&menu_bg, &menu_fg, &hint_fg)
+        };
 
-    let menu_width = 45;
-    let menu_items = vec![
-        "─────────────────────────────────────────────".to_string(),
-        "Manage Notes:".to_string(),
-        "  Ctrl+N  - New note".to_string(),
-        "  Ctrl+D  - Delete note (press twice)".to_string(),
-        "  Ctrl+S  - Save note".to_string(),
-        "  Ctrl+↑/↓ - Navigate notes".to_string(),
-        "  Double-click - Rename note".to_string(),
-        "─────────────────────────────────────────────".to_string(),
-    ];
+        let label_width = menu_width.saturating_sub(hint.chars().count() + 4);
+        let label_text = format!("  {:<width$}", label, width = label_width);
 
-    for (i, item) in menu_items.iter().enumerate() {
-        let display_text = format!("{:<width$}", item, width = menu_width);
-        print!("\x1b[{};{}H{}{}{}\x1b[0m", y + i as u16, x + 1, menu_bg, menu_fg, display_text);
+        print!("\x1b[{};{}H{}{}{}{}{}  \x1b[0m", y + 1 + i as u16, x + 1, bg, fg, label_text, hint_fg, hint);
     }
 
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + 1 + NOTES_MENU_ITEMS.len() as u16, x + 1, menu_bg, menu_fg, separator);
+
     Ok(())
 }
 
@@ -620,9 +5046,9 @@ fn render_settings_menu(app: &App, x: u16, y: u16) -> Result<()> {
     let button_text = if app.soft_wrap_paste { " ON " } else { " OFF " };
     let soft_wrap_line = format!("Soft-Wrapped Paste: {}", button_text);
     let menu_items = vec![
-        "─────────────────────────────────────────────".to_string(),
+        term_caps::rule(45),
         soft_wrap_line,
-        "─────────────────────────────────────────────".to_string(),
+        term_caps::rule(45),
     ];
 
     for (i, item) in menu_items.iter().enumerate() {
@@ -665,18 +5091,25 @@ fn render_settings_panel(app: &App, x: u16, y: u16, width: u16, height: u16) ->
     print!("\x1b[{};{}H{}\x1b[1m{}Settings\x1b[0m", y + 1, x + 2, panel_bg, panel_fg);
 
     // Separator
-    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, "─".repeat(width as usize));
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
 
     // Toggle switches
     let toggle_row_start = 3;
 
+    // The label of the toggle under the pointer is highlighted
+    let hover_fg = format!("{}\x1b[4m", rgb_fg(colors::SELECTED_ITEM_BG.0, colors::SELECTED_ITEM_BG.1, colors::SELECTED_ITEM_BG.2));
+    let label_fg = |row: u16| match app.hover {
+        Some(mouse::Hover::SettingsToggle(index)) if index == (row / 3) as usize => &hover_fg,
+        _ => &panel_fg,
+    };
+
     // 1. Soft-Wrapped Paste
     let soft_wrap_label = "Soft-Wrapped Paste";
     let soft_wrap_state = if app.soft_wrap_paste { " ON " } else { " OFF" };
     let soft_wrap_bg = if app.soft_wrap_paste { &on_bg } else { &off_bg };
 
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
-        y + toggle_row_start, x + 2, panel_bg, panel_fg, soft_wrap_label);
+        y + toggle_row_start, x + 2, panel_bg, label_fg(0), soft_wrap_label);
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
         y + toggle_row_start + 1, x + 2, soft_wrap_bg, &toggle_fg, soft_wrap_state);
 
@@ -686,7 +5119,7 @@ fn render_settings_panel(app: &App, x: u16, y: u16, width: u16, height: u16) ->
     let grid_lines_bg = if app.show_grid_lines { &on_bg } else { &off_bg };
 
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
-        y + toggle_row_start + 3, x + 2, panel_bg, panel_fg, grid_lines_label);
+        y + toggle_row_start + 3, x + 2, panel_bg, label_fg(3), grid_lines_label);
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
         y + toggle_row_start + 4, x + 2, grid_lines_bg, &toggle_fg, grid_lines_state);
 
@@ -695,64 +5128,812 @@ fn render_settings_panel(app: &App, x: u16, y: u16, width: u16, height: u16) ->
     let autosave_state = " ON ";
 
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
-        y + toggle_row_start + 6, x + 2, panel_bg, panel_fg, autosave_label);
+        y + toggle_row_start + 6, x + 2, panel_bg, label_fg(6), autosave_label);
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
         y + toggle_row_start + 7, x + 2, &on_bg, &toggle_fg, autosave_state);
 
+    // 4. Undo history depth (click to cycle presets)
+    let undo_depth_label = "Undo History Depth";
+    let undo_depth_state = format!(" {} ", app.settings.undo_depth);
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 9, x + 2, panel_bg, label_fg(9), undo_depth_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 10, x + 2, &on_bg, &toggle_fg, undo_depth_state);
+
+    // 5. Grid spacing (click to cycle presets)
+    let grid_spacing_label = "Grid Spacing (cols x rows)";
+    let grid_spacing_state = format!(" {} x {} ", app.settings.grid_col_spacing, app.settings.grid_row_spacing);
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 12, x + 2, panel_bg, label_fg(12), grid_spacing_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 13, x + 2, &on_bg, &toggle_fg, grid_spacing_state);
+
+    // 6. Grid style
+    let grid_style_label = "Grid Style";
+    let grid_style_state = match app.settings.grid_style {
+        settings::GridStyle::Solid => " Solid ",
+        settings::GridStyle::Dotted => " Dotted ",
+    };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 15, x + 2, panel_bg, label_fg(15), grid_style_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 16, x + 2, &on_bg, &toggle_fg, grid_style_state);
+
+    // 7. Grid color (swatch shows the current color)
+    let grid_color_label = "Grid Color";
+    let (r, g, b) = app.settings.grid_color;
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 18, x + 2, panel_bg, label_fg(18), grid_color_label);
+    print!("\x1b[{};{}H{}{}      \x1b[0m",
+        y + toggle_row_start + 19, x + 2, rgb_bg(r, g, b), &toggle_fg);
+
+    // 8. Log level (Off disables the log file)
+    let log_level_label = "Log Level";
+    let log_level_state = format!(" {} ", logging::level());
+    let log_level_bg = if logging::level() == log::LevelFilter::Off { &off_bg } else { &on_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 21, x + 2, panel_bg, label_fg(21), log_level_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 22, x + 2, log_level_bg, &toggle_fg, log_level_state);
+
+    // 9. Where Enter puts the cursor on the new line
+    let enter_indent_label = "Enter Indent";
+    let enter_indent_state = match app.settings.enter_indent {
+        settings::EnterIndent::Off => " OFF",
+        settings::EnterIndent::Indent => " Keep indent ",
+        settings::EnterIndent::Column => " Keep column ",
+    };
+    let enter_indent_bg = if app.settings.enter_indent == settings::EnterIndent::Off { &off_bg } else { &on_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 24, x + 2, panel_bg, label_fg(24), enter_indent_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 25, x + 2, enter_indent_bg, &toggle_fg, enter_indent_state);
+
+    // 10. Tab key behavior
+    let tab_label = "Tab Key";
+    let tab_state = match app.settings.tab_mode {
+        settings::TabMode::Spaces => format!(" {} spaces ", app.settings.tab_width),
+        settings::TabMode::GridStop => " Grid stops ".to_string(),
+    };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 27, x + 2, panel_bg, label_fg(27), tab_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 28, x + 2, &on_bg, &toggle_fg, tab_state);
+
+    // 11. Mouse wheel step and smooth scrolling
+    let scroll_step_label = "Wheel Scroll Step";
+    let scroll_step_state = format!(" {} rows ", app.settings.scroll_step);
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 30, x + 2, panel_bg, label_fg(30), scroll_step_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 31, x + 2, &on_bg, &toggle_fg, scroll_step_state);
+
+    let smooth_scroll_label = "Smooth Scroll";
+    let smooth_scroll_state = if app.settings.smooth_scroll { " ON " } else { " OFF" };
+    let smooth_scroll_bg = if app.settings.smooth_scroll { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 33, x + 2, panel_bg, label_fg(33), smooth_scroll_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 34, x + 2, smooth_scroll_bg, &toggle_fg, smooth_scroll_state);
+
+    // 12. Grid memory cap
+    let memory_cap_label = "Memory Cap";
+    let memory_cap_state = match app.settings.memory_cap_mb {
+        0 => " OFF".to_string(),
+        mb => format!(" {} MB ", mb),
+    };
+    let memory_cap_bg = if app.settings.memory_cap_mb == 0 { &off_bg } else { &on_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 36, x + 2, panel_bg, label_fg(36), memory_cap_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 37, x + 2, memory_cap_bg, &toggle_fg, memory_cap_state);
+
+    // 13. Clipboard backend
+    let osc52_label = "OSC 52 Clipboard";
+    let osc52_state = if app.settings.osc52_clipboard { " ON " } else { " OFF" };
+    let osc52_bg = if app.settings.osc52_clipboard { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 39, x + 2, panel_bg, label_fg(39), osc52_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 40, x + 2, osc52_bg, &toggle_fg, osc52_state);
+
+    // 14. Reduced output for slow connections
+    let low_bandwidth_label = "Low Bandwidth";
+    let active = app.settings.low_bandwidth_active();
+    let low_bandwidth_state = match app.settings.low_bandwidth {
+        settings::LowBandwidth::Auto if active => " AUTO (on) ",
+        settings::LowBandwidth::Auto => " AUTO (off) ",
+        settings::LowBandwidth::On => " ON ",
+        settings::LowBandwidth::Off => " OFF",
+    };
+    let low_bandwidth_bg = if active { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 42, x + 2, panel_bg, label_fg(42), low_bandwidth_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 43, x + 2, low_bandwidth_bg, &toggle_fg, low_bandwidth_state);
+
+    // 15. Titles from the first line
+    let auto_title_label = "Auto Title";
+    let auto_title_state = if app.settings.auto_title { " ON " } else { " OFF" };
+    let auto_title_bg = if app.settings.auto_title { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 45, x + 2, panel_bg, label_fg(45), auto_title_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 46, x + 2, auto_title_bg, &toggle_fg, auto_title_state);
+
+    // 16. Tint around the cursor
+    let cursor_highlight_label = "Cursor Highlight";
+    let (cursor_highlight_state, cursor_highlight_bg) = match app.settings.cursor_highlight {
+        settings::CursorHighlight::Off => (" OFF", &off_bg),
+        settings::CursorHighlight::Line => (" LINE ", &on_bg),
+        settings::CursorHighlight::Crosshair => (" CROSSHAIR ", &on_bg),
+    };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 48, x + 2, panel_bg, label_fg(48), cursor_highlight_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 49, x + 2, cursor_highlight_bg, &toggle_fg, cursor_highlight_state);
+
+    // 17. Clicks and pastes on grid lines
+    let snap_label = "Snap to Grid";
+    let snap_state = if app.settings.snap_to_grid { " ON " } else { " OFF" };
+    let snap_bg = if app.settings.snap_to_grid { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 51, x + 2, panel_bg, label_fg(51), snap_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 52, x + 2, snap_bg, &toggle_fg, snap_state);
+
+    // 18. Start screen instead of the newest note at launch
+    let start_screen_label = "Start Screen";
+    let start_screen_state = if app.settings.start_screen { " ON " } else { " OFF" };
+    let start_screen_bg = if app.settings.start_screen { &on_bg } else { &off_bg };
+
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 54, x + 2, panel_bg, label_fg(54), start_screen_label);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m",
+        y + toggle_row_start + 55, x + 2, start_screen_bg, &toggle_fg, start_screen_state);
+
+    Ok(())
+}
+
+fn render_history_panel(app: &App, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    let panel_bg = rgb_bg(colors::SIDEBAR_BG.0, colors::SIDEBAR_BG.1, colors::SIDEBAR_BG.2);
+    let panel_fg = rgb_fg(colors::SIDEBAR_FG.0, colors::SIDEBAR_FG.1, colors::SIDEBAR_FG.2);
+    let selected_bg = rgb_bg(colors::SELECTED_ITEM_BG.0, colors::SELECTED_ITEM_BG.1, colors::SELECTED_ITEM_BG.2);
+    let selected_fg = rgb_fg(colors::SELECTED_ITEM_FG.0, colors::SELECTED_ITEM_FG.1, colors::SELECTED_ITEM_FG.2);
+
+    // Clear panel background
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, panel_bg, " ".repeat(width as usize));
+    }
+
+    // Title and key hints
+    print!("\x1b[{};{}H{}\x1b[1m{}Undo History\x1b[0m", y + 1, x + 2, panel_bg, panel_fg);
+    print!("\x1b[{};{}H{}{}↑/↓ select  Enter restore  Esc close\x1b[0m", y + 2, x + 2, panel_bg, panel_fg);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 3, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
+
+    // Entries, scrolled so the selection stays visible
+    let entries = app.undo_stack.history();
+    let list_row_start = 4;
+    let visible_count = height.saturating_sub(list_row_start) as usize;
+    let scroll = (app.history_selected + 1).saturating_sub(visible_count);
+    let max_len = (width as usize).saturating_sub(2);
+
+    for (display_pos, (index, entry)) in entries.iter().enumerate().skip(scroll).take(visible_count).enumerate() {
+        let marker = if entry.is_current { "●" } else { "○" };
+        let age = if entry.age_secs < 60 {
+            format!("{}s", entry.age_secs)
+        } else {
+            format!("{}m", entry.age_secs / 60)
+        };
+        let text = format!("{}{} {} ({})", format!("{} ", term_caps::glyph('│')).repeat(entry.depth.min(6)), marker, entry.label, age);
+        let truncated: String = text.chars().take(max_len).collect();
+
+        let (bg_color, text_color) = if index == app.history_selected {
+            (&selected_bg, &selected_fg)
+        } else {
+            (&panel_bg, &panel_fg)
+        };
+        print!("\x1b[{};{}H{}{}{:<width$}\x1b[0m",
+            y + list_row_start + display_pos as u16, x + 2,
+            bg_color, text_color, truncated, width = max_len);
+    }
+
+    Ok(())
+}
+
+fn render_outline_panel(app: &App, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    let panel_bg = rgb_bg(colors::SIDEBAR_BG.0, colors::SIDEBAR_BG.1, colors::SIDEBAR_BG.2);
+    let panel_fg = rgb_fg(colors::SIDEBAR_FG.0, colors::SIDEBAR_FG.1, colors::SIDEBAR_FG.2);
+    let selected_bg = rgb_bg(colors::SELECTED_ITEM_BG.0, colors::SELECTED_ITEM_BG.1, colors::SELECTED_ITEM_BG.2);
+    let selected_fg = rgb_fg(colors::SELECTED_ITEM_FG.0, colors::SELECTED_ITEM_FG.1, colors::SELECTED_ITEM_FG.2);
+
+    // Clear panel background
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, panel_bg, " ".repeat(width as usize));
+    }
+
+    // Title
+    print!("\x1b[{};{}H{}\x1b[1m{}Outline\x1b[0m", y + 1, x + 2, panel_bg, panel_fg);
+
+    // Separator
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
+
+    if app.outline.headings.is_empty() {
+        print!("\x1b[{};{}H{}{}No headings (# Title)\x1b[0m", y + 3, x + 2, panel_bg, panel_fg);
+        return Ok(());
+    }
+
+    // Heading entries, indented by level
+    let list_row_start = 3;
+    let visible_count = height.saturating_sub(list_row_start) as usize;
+    let current = app.outline.current_index(app.cursor_row);
+
+    for (display_pos, index) in (app.outline.scroll..app.outline.headings.len()).take(visible_count).enumerate() {
+        let heading = &app.outline.headings[index];
+        let indent = "  ".repeat(heading.level.saturating_sub(1).min(4));
+        let entry = format!("{}{}", indent, heading.text);
+        let max_len = (width as usize).saturating_sub(2);
+        let truncated: String = if entry.chars().count() > max_len {
+            let mut t: String = entry.chars().take(max_len.saturating_sub(1)).collect();
+            t.push('…');
+            t
+        } else {
+            entry
+        };
+
+        let (bg_color, text_color) = if current == Some(index) {
+            (&selected_bg, &selected_fg)
+        } else {
+            (&panel_bg, &panel_fg)
+        };
+        print!("\x1b[{};{}H{}{}{:<width$}\x1b[0m",
+            y + list_row_start + display_pos as u16, x + 2,
+            bg_color, text_color, truncated, width = max_len);
+    }
+
+    Ok(())
+}
+
+fn render_backlinks_panel(app: &App, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    let panel_bg = rgb_bg(colors::SIDEBAR_BG.0, colors::SIDEBAR_BG.1, colors::SIDEBAR_BG.2);
+    let panel_fg = rgb_fg(colors::SIDEBAR_FG.0, colors::SIDEBAR_FG.1, colors::SIDEBAR_FG.2);
+    let button_bg = rgb_bg(colors::SELECTED_ITEM_BG.0, colors::SELECTED_ITEM_BG.1, colors::SELECTED_ITEM_BG.2);
+    let button_fg = rgb_fg(colors::SELECTED_ITEM_FG.0, colors::SELECTED_ITEM_FG.1, colors::SELECTED_ITEM_FG.2);
+
+    // Clear panel background
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, panel_bg, " ".repeat(width as usize));
+    }
+
+    // Title and separator
+    let searching = if app.backlinks.pending { " (searching…)" } else { "" };
+    print!("\x1b[{};{}H{}\x1b[1m{}Backlinks\x1b[22m{}\x1b[0m", y + 1, x + 2, panel_bg, panel_fg, searching);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
+
+    // Entries: section headers, then "Note: context"; unlinked ones end in a link button
+    let list_row_start = 3;
+    let visible_count = height.saturating_sub(list_row_start) as usize;
+    let max_len = (width as usize).saturating_sub(2);
+    for (display_pos, row) in app.backlinks.rows().into_iter().skip(app.backlinks.scroll).take(visible_count).enumerate() {
+        let screen_row = y + list_row_start + display_pos as u16;
+        match row {
+            backlinks::Row::Header(label, count) => {
+                print!("\x1b[{};{}H{}{}\x1b[1m{} ({})\x1b[0m", screen_row, x + 2, panel_bg, panel_fg, label, count);
+            }
+            backlinks::Row::Empty => print!("\x1b[{};{}H{}{}  None\x1b[0m", screen_row, x + 2, panel_bg, panel_fg),
+            backlinks::Row::Mention(index) => {
+                let mention = &app.backlinks.mentions[index];
+                let button = if mention.linked { "" } else { BACKLINK_BUTTON };
+                let room = max_len.saturating_sub(button.chars().count());
+                let entry: String = format!("  {}: {}", mention.note_title, mention.context).chars().take(room).collect();
+                print!("\x1b[{};{}H{}{}{:<room$}{}{}{}\x1b[0m", screen_row, x + 2, panel_bg, panel_fg, entry, button_bg, button_fg, button, room = room);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn render_tasks_panel(app: &App, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    let panel_bg = rgb_bg(colors::SIDEBAR_BG.0, colors::SIDEBAR_BG.1, colors::SIDEBAR_BG.2);
+    let panel_fg = rgb_fg(colors::SIDEBAR_FG.0, colors::SIDEBAR_FG.1, colors::SIDEBAR_FG.2);
+
+    // Clear panel background
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, panel_bg, " ".repeat(width as usize));
+    }
+
+    // Title and separator
+    let open = app.tasks.items.iter().filter(|task| !task.done).count();
+    let searching = if app.tasks.pending { " (searching…)" } else { "" };
+    print!("\x1b[{};{}H{}\x1b[1m{}Tasks ({} open)\x1b[22m{}\x1b[0m", y + 1, x + 2, panel_bg, panel_fg, open, searching);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
+
+    if app.tasks.items.is_empty() && !app.tasks.pending {
+        print!("\x1b[{};{}H{}{}No TODO, FIXME or [ ] items\x1b[0m", y + 3, x + 2, panel_bg, panel_fg);
+        return Ok(());
+    }
+
+    // Note titles, each followed by its items with a box to click
+    let list_row_start = 3;
+    let visible_count = height.saturating_sub(list_row_start) as usize;
+    let max_len = (width as usize).saturating_sub(2);
+    for (display_pos, row) in app.tasks.rows().into_iter().skip(app.tasks.scroll).take(visible_count).enumerate() {
+        let screen_row = y + list_row_start + display_pos as u16;
+        let (style, text) = match row {
+            tasks::Row::Note(first, open) => {
+                let title = &app.tasks.items[first].note_title;
+                ("\x1b[1m", format!("{} ({})", if title.is_empty() { "Untitled" } else { title }, open))
+            }
+            tasks::Row::Task(index) => {
+                let task = &app.tasks.items[index];
+                let text = if task.text.is_empty() { task.marker } else { &task.text };
+                (if task.done { "\x1b[2m" } else { "" }, format!("  {} {}", if task.done { "☑" } else { "☐" }, text))
+            }
+        };
+        let text: String = text.chars().take(max_len).collect();
+        print!("\x1b[{};{}H{}{}{}{}\x1b[0m", screen_row, x + 2, panel_bg, panel_fg, style, text);
+    }
+
+    Ok(())
+}
+
+/// Drawn at the end of unlinked mentions in the backlinks panel; clicking it links the mention
+pub const BACKLINK_BUTTON: &str = " link ";
+
+fn render_preview_panel(app: &mut App, x: u16, y: u16, width: u16, height: u16) -> Result<()> {
+    let Some(preview) = app.preview.as_mut() else {
+        return Ok(());
+    };
+    if let Err(e) = preview.refresh(&app.notes_mode.db) {
+        log::error!("Reading the pinned note failed: {}", e);
+    }
+    let panel_bg = rgb_bg(colors::SIDEBAR_BG.0, colors::SIDEBAR_BG.1, colors::SIDEBAR_BG.2);
+    let panel_fg = rgb_fg(colors::SIDEBAR_FG.0, colors::SIDEBAR_FG.1, colors::SIDEBAR_FG.2);
+    let text_bg = rgb_bg(colors::EMBED_BG.0, colors::EMBED_BG.1, colors::EMBED_BG.2);
+    let text_fg = rgb_fg(colors::EMBED_FG.0, colors::EMBED_FG.1, colors::EMBED_FG.2);
+    let inner = (width as usize).saturating_sub(2);
+
+    // Title with the close button on the right, then a separator
+    let title = if preview.missing { format!("{} (deleted)", preview.title) } else { preview.title.clone() };
+    let title: String = title.chars().take(inner.saturating_sub(2)).collect();
+    print!("\x1b[{};{}H{}{}\x1b[1m {:<rest$}\x1b[0m", y + 1, x + 1, panel_bg, panel_fg, title, rest = width as usize - 1);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 1, x + width - 1, panel_bg, panel_fg, term_caps::glyph('×'));
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 1, panel_bg, panel_fg, term_caps::rule(width as usize));
+
+    // The note's text from the preview's own scroll position, with a margin column each side
+    for row in 2..height {
+        let line = preview.lines.get(preview.scroll_row + (row - 2) as usize).map_or(String::new(), |line| preview.visible(line, inner));
+        print!("\x1b[{};{}H{}{} {:<inner$} \x1b[0m", y + row + 1, x + 1, text_bg, text_fg, line, inner = inner);
+    }
+
+    Ok(())
+}
+
+fn render_conflict_prompt(app: &App, term_width: u16, editor_height: u16) -> Result<()> {
+    let Some(ref theirs) = app.save_conflict else {
+        return Ok(());
+    };
+
+    let box_bg = rgb_bg(250, 250, 250); // Light gray, like the dropdown menus
+    let box_fg = rgb_fg(0, 0, 0);
+
+    let width = if app.conflict_diff_visible { term_width.saturating_sub(4) } else { term_width.saturating_sub(4).min(80) };
+    let height = if app.conflict_diff_visible { editor_height.saturating_sub(2) } else { 7 };
+    let x = (term_width.saturating_sub(width)) / 2;
+    let y = 2;
+    let inner = (width as usize).saturating_sub(2);
+
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, box_bg, " ".repeat(width as usize));
+    }
+
+    let lines = [
+        "This note was changed by another program or instance.".to_string(),
+        format!("Their version was saved at {}", theirs.updated_at.with_timezone(&chrono::Local).format("%H:%M:%S")),
+        String::new(),
+        "[O] Overwrite with mine  [R] Reload theirs  [M] Merge  [V] View diff".to_string(),
+    ];
+    print!("\x1b[{};{}H{}{}\x1b[1m Save conflict\x1b[0m", y + 1, x + 2, box_bg, box_fg);
+    for (i, line) in lines.iter().enumerate() {
+        let text: String = line.chars().take(inner).collect();
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 3 + i as u16, x + 2, box_bg, box_fg, text);
+    }
+
+    if app.conflict_diff_visible {
+        // Side by side: their stored version on the left, the editor on the right
+        let mine = app.grid.to_lines();
+        let theirs_lines = note_format::decode(&theirs.content).to_lines();
+        let rows = diff::side_by_side(&diff::diff_lines(&theirs_lines, &mine));
+
+        let column_width = inner.saturating_sub(3) / 2;
+        let right_x = x + 2 + column_width as u16 + 3;
+        let diff_row_start = 8;
+        print!("\x1b[{};{}H{}{}\x1b[1mTheirs (stored)\x1b[0m", y + diff_row_start, x + 2, box_bg, box_fg);
+        print!("\x1b[{};{}H{}{}\x1b[1mMine (editor)\x1b[0m", y + diff_row_start, right_x, box_bg, box_fg);
+
+        let visible_count = height.saturating_sub(diff_row_start + 2) as usize;
+        for (display_pos, row) in rows.iter().skip(app.conflict_diff_scroll).take(visible_count).enumerate() {
+            let screen_row = y + diff_row_start + 1 + display_pos as u16;
+            print!("\x1b[{};{}H{}{}{}\x1b[0m", screen_row, right_x - 2, box_bg, box_fg, term_caps::glyph('│'));
+            render_diff_cell(row.left.as_deref(), row.right.as_deref(), row.changed, true, x + 2, screen_row, column_width);
+            render_diff_cell(row.right.as_deref(), row.left.as_deref(), row.changed, false, right_x, screen_row, column_width);
+        }
+    }
+
+    Ok(())
+}
+
+/// One half of a side-by-side diff row. Changed lines highlight the words that differ
+/// from the other side (removed words on the left, added words on the right).
+fn render_diff_cell(text: Option<&str>, other: Option<&str>, changed: bool, is_left: bool, x: u16, y: u16, width: usize) {
+    let box_bg = rgb_bg(250, 250, 250);
+    let box_fg = rgb_fg(0, 0, 0);
+    let (line_bg, word_bg) = if is_left {
+        (rgb_bg(255, 228, 228), rgb_bg(240, 160, 160)) // Light red / red
+    } else {
+        (rgb_bg(228, 255, 228), rgb_bg(150, 220, 150)) // Light green / green
+    };
+
+    let Some(text) = text else {
+        return; // Filler row on this side
+    };
+    if !changed {
+        let shown: String = text.chars().take(width).collect();
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", y, x, box_bg, box_fg, shown);
+        return;
+    }
+
+    print!("\x1b[{};{}H{}{}", y, x, line_bg, box_fg);
+    let mut remaining = width;
+    let words = match (is_left, other) {
+        (true, Some(other)) => diff::diff_words(text, other),
+        (false, Some(other)) => diff::diff_words(other, text),
+        _ => vec![if is_left { diff::DiffOp::Removed(text.to_string()) } else { diff::DiffOp::Added(text.to_string()) }],
+    };
+    for op in words {
+        let (word, highlighted) = match op {
+            diff::DiffOp::Same(w) => (w, false),
+            diff::DiffOp::Removed(w) if is_left => (w, true),
+            diff::DiffOp::Added(w) if !is_left => (w, true),
+            _ => continue, // The other side's words
+        };
+        let shown: String = word.chars().take(remaining).collect();
+        remaining -= shown.chars().count();
+        if highlighted {
+            print!("{}{}{}", word_bg, shown, line_bg);
+        } else {
+            print!("{}", shown);
+        }
+        if remaining == 0 {
+            break;
+        }
+    }
+    print!("{}\x1b[0m", " ".repeat(remaining));
+}
+
+/// Banner under the title bar listing what was wrong in settings.json and the plugins
+fn render_config_banner(app: &App, term_width: u16) {
+    let bg = rgb_bg(255, 228, 228); // Light red
+    let fg = rgb_fg(0, 0, 0);
+    let error_fg = rgb_fg(200, 40, 40);
+    let problems = &app.config_problems;
+    let shown = problems.len().min(config::config_banner::MAX_PROBLEMS);
+
+    let mut lines = vec![(&fg, " Some settings could not be used, so their defaults apply. Fix them, then run \"Reload settings and plugins\" (Esc hides this)".to_string())];
+    lines.extend(problems[..shown].iter().map(|problem| (&error_fg, format!("   {}", problem))));
+    if problems.len() > shown {
+        lines.push((&error_fg, format!("   ...and {} more", problems.len() - shown)));
+    }
+    for (i, (color, line)) in lines.iter().enumerate() {
+        let text: String = line.chars().take(term_width as usize).collect();
+        let pad = (term_width as usize).saturating_sub(text.chars().count());
+        print!("\x1b[{};1H{}{}{}{}\x1b[0m", i + 2, bg, color, text, " ".repeat(pad));
+    }
+}
+
+fn render_quit_prompt(app: &App, term_width: u16) -> Result<()> {
+    let Some(ref error) = app.quit_save_error else {
+        return Ok(());
+    };
+
+    let box_bg = rgb_bg(250, 250, 250); // Light gray, like the dropdown menus
+    let box_fg = rgb_fg(0, 0, 0);
+    let error_fg = rgb_fg(200, 40, 40);
+
+    let width = term_width.saturating_sub(4).min(80);
+    let height = 8;
+    let x = (term_width.saturating_sub(width)) / 2;
+    let y = 2;
+    let inner = (width as usize).saturating_sub(2);
+
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, box_bg, " ".repeat(width as usize));
+    }
+
+    let lines = [
+        (&box_fg, "Your changes could not be saved:".to_string()),
+        (&error_fg, error.clone()),
+        (&box_fg, String::new()),
+        (&box_fg, "[R] Retry    [E] Export to rescue file and quit".to_string()),
+        (&box_fg, "[Q] Quit without saving    [Esc] Keep editing".to_string()),
+    ];
+    print!("\x1b[{};{}H{}{}\x1b[1m Quit\x1b[0m", y + 1, x + 2, box_bg, box_fg);
+    for (i, (color, line)) in lines.iter().enumerate() {
+        let text: String = line.chars().take(inner).collect();
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 3 + i as u16, x + 2, box_bg, color, text);
+    }
+
+    Ok(())
+}
+
+fn render_note_info(app: &App, term_width: u16) -> Result<()> {
+    let box_bg = rgb_bg(250, 250, 250); // Light gray, like the dropdown menus
+    let box_fg = rgb_fg(0, 0, 0);
+    let label_fg = rgb_fg(100, 100, 100);
+
+    let stats = note_info::NoteStats::compute(&app.grid, app.notes_mode.current_note.as_ref());
+    let format_time = |t: Option<chrono::DateTime<chrono::Utc>>| match t {
+        Some(t) => t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => "-".to_string(),
+    };
+    let bounds = match stats.bounds {
+        Some((min_row, min_col, max_row, max_col)) => format!(
+            "rows {}-{}, cols {}-{} ({} x {})",
+            min_row + 1, max_row + 1, min_col + 1, max_col + 1,
+            max_col - min_col + 1, max_row - min_row + 1),
+        None => "empty".to_string(),
+    };
+    let memory = match (stats.memory.packed_chunks, app.settings.memory_cap_mb) {
+        (0, 0) => format!("~{}", note_info::format_bytes(stats.memory.bytes as u64)),
+        (packed, 0) => format!("~{} ({} chunks packed)", note_info::format_bytes(stats.memory.bytes as u64), packed),
+        (packed, cap) => format!("~{} of {} MB ({} packed)", note_info::format_bytes(stats.memory.bytes as u64), cap, packed),
+    };
+    let attachments = app.notes_mode.current_note.as_ref()
+        .and_then(|note| app.notes_mode.db.count_attachments(&note.id).ok())
+        .map_or_else(|| "-".to_string(), |count| count.to_string());
+    let db_size = app.notes_mode.db.file_size()
+        .map(note_info::format_bytes)
+        .unwrap_or_else(|| "-".to_string());
+
+    let rows = [
+        ("Words", stats.words.to_string()),
+        ("Characters", stats.chars.to_string()),
+        ("Lines", stats.lines.to_string()),
+        ("Content bounds", bounds),
+        ("Chunks", stats.chunks.to_string()),
+        ("Cells", stats.cells.to_string()),
+        ("Memory", memory),
+        ("Created", format_time(stats.created_at)),
+        ("Modified", format_time(stats.updated_at)),
+        ("Size", note_info::format_bytes(stats.content_bytes as u64)),
+        ("Attachments", attachments),
+        ("Database file", db_size),
+    ];
+
+    let width = term_width.saturating_sub(4).min(56);
+    let height = rows.len() as u16 + 5;
+    let x = (term_width.saturating_sub(width)) / 2;
+    let y = 2;
+    let inner = (width as usize).saturating_sub(2);
+
+    for row in 0..height {
+        print!("\x1b[{};{}H{}{}\x1b[0m", y + row + 1, x + 1, box_bg, " ".repeat(width as usize));
+    }
+
+    print!("\x1b[{};{}H{}{}\x1b[1m Note info\x1b[0m", y + 1, x + 2, box_bg, box_fg);
+    for (i, (label, value)) in rows.iter().enumerate() {
+        let line: String = format!("{:<16}{}", label, value).chars().take(inner).collect();
+        let (label_part, value_part) = line.split_at(line.len().min(16));
+        print!("\x1b[{};{}H{}{}{}{}{}\x1b[0m",
+            y + 3 + i as u16, x + 2, box_bg, label_fg, label_part, box_fg, value_part);
+    }
+    print!("\x1b[{};{}H{}{}Press any key to close\x1b[0m", y + height, x + 2, box_bg, label_fg);
+
+    Ok(())
+}
+
+fn render_performance_hud(app: &App, term_width: u16) -> Result<()> {
+    let hud_bg = rgb_bg(20, 20, 20);
+    let hud_fg = rgb_fg(0, 255, 128); // Terminal green
+    let width = 36usize;
+    let x = term_width.saturating_sub(width as u16 + 1);
+    let diag = &app.diagnostics;
+
+    let autosave_age = match diag.last_autosave {
+        Some(at) => format!("{}s ago", at.elapsed().as_secs()),
+        None => "never".to_string(),
+    };
+    let log_state = match logging::log_path() {
+        Some(_) if logging::level() == log::LevelFilter::Off => "off".to_string(),
+        Some(path) => format!("{} > {}", logging::level(), path.display()),
+        None => "unavailable".to_string(),
+    };
+
+    let caps = term_caps::current();
+    let mut terminal = match caps.color {
+        term_caps::ColorDepth::TrueColor => "truecolor",
+        term_caps::ColorDepth::Palette256 => "256 colors",
+        term_caps::ColorDepth::Basic16 => "16 colors",
+    }.to_string();
+    let low_bandwidth = app.settings.low_bandwidth_active();
+    for (on, name) in [(caps.synchronized_output, "sync"), (caps.kitty_graphics, "images"), (caps.sgr_mouse, "sgr mouse"), (low_bandwidth, "low bandwidth")] {
+        if on {
+            terminal.push_str(", ");
+            terminal.push_str(name);
+        }
+    }
+
+    let lines = [
+        " Performance (F12 to hide)".to_string(),
+        format!(" Frame     {}", diagnostics::format_ms(diag.frame_times.last())),
+        format!("   avg/max {} / {}", diagnostics::format_ms(diag.frame_times.average()), diagnostics::format_ms(diag.frame_times.max())),
+        format!(" Frames    {}", diag.frames_rendered),
+        format!(" Cells     {}", diag.cells_redrawn),
+        format!(" Coalesced {} drag events", diag.motion_events_coalesced),
+        format!(" Input lat {}", diagnostics::format_ms(diag.input_latency.last())),
+        format!("   avg/max {} / {}", diagnostics::format_ms(diag.input_latency.average()), diagnostics::format_ms(diag.input_latency.max())),
+        format!(" Autosave  {} ({})", diagnostics::format_ms(diag.autosave_times.last()), autosave_age),
+        format!(" Log       {}", log_state),
+        format!(" Terminal  {}", terminal),
+    ];
+
+    for (i, line) in lines.iter().enumerate() {
+        let text: String = line.chars().take(width).collect();
+        print!("\x1b[{};{}H{}{}{:<width$}\x1b[0m", 3 + i, x + 1, hud_bg, hud_fg, text, width = width);
+    }
+
     Ok(())
 }
 
+/// Clickable regions of the status line as (start, end) screen columns, end exclusive.
+/// Computed the same way for rendering and for mouse hit-testing.
+pub struct StatusLineLayout {
+    pub dirty: (u16, u16),
+    /// Focus timer countdown, while one is running
+    pub focus: Option<(u16, u16)>,
+    pub focus_text: String,
+    /// Progress towards the open note's word goal, while it has one
+    pub goal: Option<(u16, u16)>,
+    pub goal_text: String,
+    pub goal_reached: bool,
+    /// Sync indicator, while sync is set up
+    pub sync: Option<(u16, u16)>,
+    pub sync_text: String,
+    pub sync_failed: bool,
+    /// Follow mode indicator, while broadcasting
+    pub live: Option<(u16, u16)>,
+    pub wrap: (u16, u16),
+    pub grid: (u16, u16),
+    pub position: (u16, u16),
+    pub position_text: String,
+}
+
+impl StatusLineLayout {
+    pub const WRAP_TEXT: &'static str = " Wrap ";
+    pub const GRID_TEXT: &'static str = " Grid ";
+    pub const LIVE_TEXT: &'static str = " LIVE ";
+
+    pub fn new(app: &App, term_width: u16) -> Self {
+        let (line, column) = app.grid.logical_pos(app.cursor_row, app.cursor_col);
+        let position_text = format!("Ln {}, Col {} ", line + 1, column + 1);
+        let position_start = term_width.saturating_sub(position_text.len() as u16);
+        let grid_start = position_start.saturating_sub(Self::GRID_TEXT.len() as u16 + 1);
+        let wrap_start = grid_start.saturating_sub(Self::WRAP_TEXT.len() as u16 + 1);
+        let focus_text = app.focus_timer.as_ref().map(|t| t.label(std::time::Instant::now())).unwrap_or_default();
+        let focus_start = wrap_start.saturating_sub(focus_text.len() as u16 + 1);
+        let focus = app.focus_timer.is_some().then_some((focus_start, focus_start + focus_text.len() as u16));
+        let goal = app.notes_mode.current_note.as_ref().and_then(|note| note.word_goal).zip(app.word_count.words());
+        let goal_text = goal.map(|(goal, words)| format!(" {} ", word_goal::progress(words, goal))).unwrap_or_default();
+        let goal_width = goal_text.chars().count() as u16;
+        let goal_start = focus.unwrap_or((wrap_start, 0)).0.saturating_sub(goal_width + 1);
+        let goal_region = goal.is_some().then_some((goal_start, goal_start + goal_width));
+        let (sync_text, sync_failed) = app.sync.label();
+        let sync_text = format!(" {} ", sync_text);
+        let sync_width = sync_text.chars().count() as u16;
+        let sync_start = goal_region.or(focus).unwrap_or((wrap_start, 0)).0.saturating_sub(sync_width + 1);
+        let sync = (!app.settings.sync_url.trim().is_empty()).then_some((sync_start, sync_start + sync_width));
+        let live_start = sync.or(goal_region).or(focus).unwrap_or((wrap_start, 0)).0.saturating_sub(Self::LIVE_TEXT.len() as u16 + 1);
+
+        Self {
+            dirty: (0, 1),
+            focus,
+            focus_text,
+            goal: goal_region,
+            goal_reached: goal.is_some_and(|(goal, words)| words >= goal as usize),
+            goal_text,
+            sync,
+            sync_text,
+            sync_failed,
+            live: app.broadcast.is_some().then_some((live_start, live_start + Self::LIVE_TEXT.len() as u16)),
+            wrap: (wrap_start, wrap_start + Self::WRAP_TEXT.len() as u16),
+            grid: (grid_start, grid_start + Self::GRID_TEXT.len() as u16),
+            position: (position_start, term_width),
+            position_text,
+        }
+    }
+
+    /// Width available for the status message (everything left of the indicators)
+    pub fn message_width(&self) -> usize {
+        self.live.or(self.sync).or(self.goal).or(self.focus).unwrap_or(self.wrap).0.saturating_sub(1) as usize
+    }
+}
+
 fn render_status_line(app: &App, term_width: u16, term_height: u16) -> Result<()> {
     // Status line at bottom row
     let status_row = term_height;
+    let layout = StatusLineLayout::new(app, term_width);
 
     // Status line colors (dark gray background)
     let status_bg = rgb_bg(40, 40, 40);
     let status_fg = rgb_fg(200, 200, 200);
     let dirty_fg = rgb_fg(255, 193, 7); // Amber for dirty indicator
+    let on_bg = rgb_bg(76, 175, 80); // Green, like the settings toggles
+    let off_bg = rgb_bg(70, 70, 70);
 
     // Build status line content
     let dirty_indicator = if app.dirty { "*" } else { " " };
-    let position_info = format!("Ln {}, Col {} ", app.cursor_row + 1, app.cursor_col + 1);
-
-    // Left side: status message with dirty indicator
-    let left_text = format!("{}{}", dirty_indicator, app.status_message);
-
-    // Calculate how much space we have
-    let total_width = term_width as usize;
-    let position_len = position_info.len();
-    let max_message_len = total_width.saturating_sub(position_len).saturating_sub(1); // -1 for spacing
 
     // Truncate message if needed
-    let truncated_left = if left_text.len() > max_message_len {
-        format!("{}…", &left_text[..max_message_len.saturating_sub(1)])
+    let max_message_len = layout.message_width().saturating_sub(1);
+    let truncated_message: String = if app.status_message.chars().count() > max_message_len {
+        let mut t: String = app.status_message.chars().take(max_message_len.saturating_sub(1)).collect();
+        t.push('…');
+        t
     } else {
-        left_text
+        app.status_message.clone()
     };
 
     // Clear status line with background color
-    print!("\x1b[{};1H{}{}\x1b[0m", status_row, status_bg, " ".repeat(total_width));
+    print!("\x1b[{};1H{}{}\x1b[0m", status_row, status_bg, " ".repeat(term_width as usize));
 
-    // Draw left side (message + dirty indicator)
-    if app.dirty {
-        // Highlight dirty indicator in amber
-        print!("\x1b[{};1H{}{}{}\x1b[0m{}{}{}\x1b[0m",
-            status_row,
-            status_bg, dirty_fg, dirty_indicator,
-            status_bg, status_fg, &truncated_left[1..] // Skip first char (dirty indicator)
-        );
-    } else {
-        print!("\x1b[{};1H{}{}{}\x1b[0m",
-            status_row,
-            status_bg, status_fg, truncated_left
-        );
+    // Draw left side (dirty indicator in amber + message)
+    print!("\x1b[{};1H{}{}{}\x1b[0m{}{}{}\x1b[0m",
+        status_row,
+        status_bg, dirty_fg, dirty_indicator,
+        status_bg, status_fg, truncated_message
+    );
+
+    // Draw setting indicators (click to toggle)
+    let wrap_bg = if app.soft_wrap_paste { &on_bg } else { &off_bg };
+    let grid_bg = if app.show_grid_lines { &on_bg } else { &off_bg };
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", status_row, layout.wrap.0 + 1, wrap_bg, status_fg, StatusLineLayout::WRAP_TEXT);
+    print!("\x1b[{};{}H{}{}{}\x1b[0m", status_row, layout.grid.0 + 1, grid_bg, status_fg, StatusLineLayout::GRID_TEXT);
+    if let Some((focus_start, _)) = layout.focus {
+        let focus_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", status_row, focus_start + 1, focus_bg, rgb_fg(255, 255, 255), layout.focus_text);
+    }
+    if let Some((goal_start, _)) = layout.goal {
+        let goal_fg = if layout.goal_reached { rgb_fg(76, 175, 80) } else { rgb_fg(140, 140, 140) };
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", status_row, goal_start + 1, status_bg, goal_fg, layout.goal_text);
+    }
+    if let Some((sync_start, _)) = layout.sync {
+        let sync_fg = if layout.sync_failed { rgb_fg(244, 67, 54) } else { rgb_fg(140, 140, 140) };
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", status_row, sync_start + 1, status_bg, sync_fg, layout.sync_text);
+    }
+    if let Some((live_start, _)) = layout.live {
+        print!("\x1b[{};{}H{}\x1b[1m{}{}\x1b[0m", status_row, live_start + 1, rgb_bg(244, 67, 54), rgb_fg(255, 255, 255), StatusLineLayout::LIVE_TEXT);
     }
 
     // Draw right side (position info) - right-aligned
-    let position_col = total_width.saturating_sub(position_len) + 1;
     print!("\x1b[{};{}H{}{}{}\x1b[0m",
-        status_row, position_col,
-        status_bg, status_fg, position_info
+        status_row, layout.position.0 + 1,
+        status_bg, status_fg, layout.position_text
     );
 
     Ok(())
@@ -1,7 +1,10 @@
 // Mouse handling for chonk-note
 use crate::App;
-use crate::kitty_native::MouseEvent;
-use crate::config::layout;
+use crate::commands::Command;
+use crate::kitty_native::{MouseButton, MouseEvent};
+use crate::overlay::Routed;
+use crate::config::{autoscroll, layout, navigation};
+use crate::settings::{LowBandwidth, Settings};
 use anyhow::Result;
 
 pub struct MouseState {
@@ -9,6 +12,10 @@ pub struct MouseState {
     pub is_dragging: bool,
     pub last_click_time: std::time::Instant,
     pub last_clicked_note: Option<usize>,
+    /// Rows/cols per autoscroll step while a drag is held near an edge (0 = not scrolling)
+    pub autoscroll: (isize, isize),
+    /// Screen position of the latest drag event, reused by autoscroll steps
+    pub last_drag_pos: Option<(u16, u16)>,
 }
 
 impl Default for MouseState {
@@ -18,27 +25,53 @@ impl Default for MouseState {
             is_dragging: false,
             last_click_time: std::time::Instant::now(),
             last_clicked_note: None,
+            autoscroll: (0, 0),
+            last_drag_pos: None,
         }
     }
 }
 
 pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut MouseState) -> Result<()> {
-    let (term_width, term_height) = crate::kitty_native::KittyTerminal::size()?;
+    let (term_width, term_height) = app.terminal_size()?;
     let notes_list_width = if app.sidebar_expanded { layout::SIDEBAR_WIDTH_EXPANDED } else { layout::SIDEBAR_WIDTH_COLLAPSED };
 
+    // Overlays (menus, pickers, prompts) get mouse events first
+    match app.overlays.handle_mouse(&event, term_width, term_height) {
+        Routed::Consumed => {
+            app.needs_redraw = true;
+            return Ok(());
+        }
+        Routed::Action(action) => return app.run_overlay_action(action),
+        Routed::NotHandled => {}
+    }
+
     match event {
+        // Right click in the editor - context menu at the pointer
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::Right), is_press: true, is_drag: false, x, y, .. }
+            if x >= notes_list_width && y >= 1 && y < term_height.saturating_sub(1) => {
+            app.open_context_menu(x, y, term_width, term_height);
+        }
+
         // Left click - position cursor or select note
-        MouseEvent { button: Some(crate::kitty_native::MouseButton::Left), is_press: true, is_drag: false, x, y, .. } => {
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::Left), is_press: true, is_drag: false, x, y, modifiers } => {
+            // Any click closes the note info popup
+            if app.note_info_open {
+                app.note_info_open = false;
+                app.needs_redraw = true;
+                return Ok(());
+            }
+
+            // Click on status line (last row) - indicators are clickable
+            if y == term_height.saturating_sub(1) {
+                return handle_status_line_click(app, x, term_width);
+            }
+
             // Click on title bar (row 0) - handle menu buttons
             if y == 0 {
-                // Menu button positions (left side of title bar)
-                let notes_start_col = 0;
-                let notes_end_col = 8; // "Notes ▾" is 8 chars
-                let settings_start_col = 10;
-                let settings_end_col = 21; // "Settings ▾" is 11 chars
+                let button = title_button_at(x);
 
                 // Click on Notes menu button
-                if x >= notes_start_col as u16 && x < notes_end_col as u16 {
+                if button == Some(TITLE_NOTES) {
                     app.notes_menu_expanded = !app.notes_menu_expanded;
                     app.sidebar_expanded = !app.sidebar_expanded; // ALSO toggle sidebar
                     app.settings_menu_expanded = false; // Close other menu
@@ -47,13 +80,36 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                 }
 
                 // Click on Settings menu button
-                if x >= settings_start_col as u16 && x < settings_end_col as u16 {
+                if button == Some(TITLE_SETTINGS) {
                     app.settings_menu_expanded = !app.settings_menu_expanded;
                     app.settings_panel_expanded = !app.settings_panel_expanded; // ALSO toggle panel
                     app.notes_menu_expanded = false; // Close other menu
                     app.needs_redraw = true;
                     return Ok(());
                 }
+
+                // Click on Outline button - toggles the outline panel
+                if button == Some(TITLE_OUTLINE) {
+                    app.outline_panel_expanded = !app.outline_panel_expanded;
+                    app.notes_menu_expanded = false;
+                    app.settings_menu_expanded = false;
+                    app.needs_redraw = true;
+                    return Ok(());
+                }
+
+                // Click on the note title - rename it in place, or move the caret while renaming
+                let field_width = title_field_width(term_width);
+                if x >= TITLE_FIELD_START && ((x - TITLE_FIELD_START) as usize) < field_width {
+                    if app.editing_title {
+                        let column = (x - TITLE_FIELD_START) as usize;
+                        let pos = app.title_edit.scroll(field_width) + column;
+                        app.title_edit.set_cursor(pos, false);
+                    } else {
+                        app.start_title_bar_edit();
+                    }
+                    app.needs_redraw = true;
+                    return Ok(());
+                }
             }
 
             // Click in Settings menu dropdown (when expanded) - handle menu item clicks
@@ -89,7 +145,9 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                 let notes_end_col = notes_start_col + 45; // Menu width
 
                 if x >= notes_start_col as u16 && x < notes_end_col as u16 {
-                    // Just keep menu open for now - no actions yet
+                    if let Some(index) = notes_menu_item_at(y) {
+                        app.run_notes_menu_action(crate::NOTES_MENU_ITEMS[index])?;
+                    }
                     return Ok(());
                 } else {
                     // Clicked outside menu while it was open - close it
@@ -110,40 +168,230 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
 
                     // Soft-Wrapped Paste toggle (rows 3-4)
                     if y >= toggle_row_start as u16 && y <= (toggle_row_start + 1) as u16 {
-                        app.soft_wrap_paste = !app.soft_wrap_paste;
-                        app.status_message = if app.soft_wrap_paste {
-                            "Soft-wrapped paste: ON".to_string()
-                        } else {
-                            "Soft-wrapped paste: OFF".to_string()
+                        return app.run_command(Command::ToggleWrapPaste);
+                    }
+
+                    // Show Grid Lines toggle (rows 6-7)
+                    if y >= (toggle_row_start + 3) as u16 && y <= (toggle_row_start + 4) as u16 {
+                        return app.run_command(Command::ToggleGridLines);
+                    }
+
+                    // Auto-Save toggle (rows 9-10) - placeholder, always on
+                    if y >= (toggle_row_start + 6) as u16 && y <= (toggle_row_start + 7) as u16 {
+                        app.status_message = "Auto-save is always enabled".to_string();
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+
+                    // Undo History Depth (rows 12-13) - cycle through presets
+                    if y >= (toggle_row_start + 9) as u16 && y <= (toggle_row_start + 10) as u16 {
+                        app.settings.cycle_undo_depth();
+                        app.undo_stack.set_max_size(app.settings.undo_depth);
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Undo history depth: {}", app.settings.undo_depth),
+                            Err(e) => format!("Undo history depth: {} (not saved: {})", app.settings.undo_depth, e),
                         };
                         app.needs_redraw = true;
                         return Ok(());
                     }
 
-                    // Show Grid Lines toggle (rows 6-7)
-                    if y >= (toggle_row_start + 3) as u16 && y <= (toggle_row_start + 4) as u16 {
-                        app.show_grid_lines = !app.show_grid_lines;
-                        app.status_message = if app.show_grid_lines {
-                            "Grid lines: ON".to_string()
-                        } else {
-                            "Grid lines: OFF".to_string()
+                    // Grid Spacing, Grid Style and Grid Color rows: (row, name, what a click changes)
+                    let grid_setting_rows = [
+                        (toggle_row_start + 12, "spacing", Settings::cycle_grid_spacing as fn(&mut Settings)),
+                        (toggle_row_start + 15, "style", Settings::toggle_grid_style),
+                        (toggle_row_start + 18, "color", Settings::cycle_grid_color),
+                    ];
+                    for (row, setting, change) in grid_setting_rows {
+                        if y >= row as u16 && y <= (row + 1) as u16 {
+                            change(&mut app.settings);
+                            app.status_message = match app.settings.save() {
+                                Ok(()) => format!("Grid {} updated", setting),
+                                Err(e) => format!("Grid {} updated (not saved: {})", setting, e),
+                            };
+                            app.needs_redraw = true;
+                            return Ok(());
+                        }
+                    }
+
+                    // Log Level row cycles the level and applies it right away
+                    if y >= (toggle_row_start + 21) as u16 && y <= (toggle_row_start + 22) as u16 {
+                        app.settings.cycle_log_level();
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Log level: {}", app.settings.log_level),
+                            Err(e) => format!("Log level: {} (not saved: {})", app.settings.log_level, e),
                         };
                         app.needs_redraw = true;
                         return Ok(());
                     }
 
-                    // Auto-Save toggle (rows 9-10) - placeholder, always on
-                    if y >= (toggle_row_start + 6) as u16 && y <= (toggle_row_start + 7) as u16 {
-                        app.status_message = "Auto-save is always enabled".to_string();
+                    // Enter Indent row cycles Off / Keep indent / Keep column
+                    if y >= (toggle_row_start + 24) as u16 && y <= (toggle_row_start + 25) as u16 {
+                        app.settings.cycle_enter_indent();
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Enter indent: {:?}", app.settings.enter_indent),
+                            Err(e) => format!("Enter indent: {:?} (not saved: {})", app.settings.enter_indent, e),
+                        };
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+
+                    // Tab Key row cycles 2/4/8 spaces and grid stops
+                    if y >= (toggle_row_start + 27) as u16 && y <= (toggle_row_start + 28) as u16 {
+                        app.settings.cycle_tab_mode();
+                        let mode = match app.settings.tab_mode {
+                            crate::settings::TabMode::Spaces => format!("{} spaces", app.settings.tab_width),
+                            crate::settings::TabMode::GridStop => "grid stops".to_string(),
+                        };
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Tab key: {}", mode),
+                            Err(e) => format!("Tab key: {} (not saved: {})", mode, e),
+                        };
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+
+                    // Wheel Scroll Step and Smooth Scroll rows
+                    if y >= (toggle_row_start + 30) as u16 && y <= (toggle_row_start + 31) as u16 {
+                        app.settings.cycle_scroll_step();
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Wheel scrolls {} rows", app.settings.scroll_step),
+                            Err(e) => format!("Wheel scrolls {} rows (not saved: {})", app.settings.scroll_step, e),
+                        };
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+                    if y >= (toggle_row_start + 33) as u16 && y <= (toggle_row_start + 34) as u16 {
+                        return app.run_command(Command::ToggleSmoothScroll);
+                    }
+
+                    // Memory Cap rows
+                    if y >= (toggle_row_start + 36) as u16 && y <= (toggle_row_start + 37) as u16 {
+                        app.settings.cycle_memory_cap();
+                        let state = match app.settings.memory_cap_mb {
+                            0 => "OFF".to_string(),
+                            mb => format!("{} MB", mb),
+                        };
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Memory cap: {}", state),
+                            Err(e) => format!("Memory cap: {} (not saved: {})", state, e),
+                        };
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+
+                    // OSC 52 Clipboard rows
+                    if y >= (toggle_row_start + 39) as u16 && y <= (toggle_row_start + 40) as u16 {
+                        return app.run_command(Command::ToggleOsc52);
+                    }
+
+                    // Low Bandwidth rows
+                    if y >= (toggle_row_start + 42) as u16 && y <= (toggle_row_start + 43) as u16 {
+                        app.settings.cycle_low_bandwidth();
+                        let state = match app.settings.low_bandwidth {
+                            LowBandwidth::Auto => "AUTO (over SSH)",
+                            LowBandwidth::On => "ON",
+                            LowBandwidth::Off => "OFF",
+                        };
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Low bandwidth: {}", state),
+                            Err(e) => format!("Low bandwidth: {} (not saved: {})", state, e),
+                        };
                         app.needs_redraw = true;
                         return Ok(());
                     }
 
+                    // Auto Title rows
+                    if y >= (toggle_row_start + 45) as u16 && y <= (toggle_row_start + 46) as u16 {
+                        return app.run_command(Command::ToggleAutoTitle);
+                    }
+
+                    // Cursor Highlight rows cycle Off / Line / Crosshair
+                    if y >= (toggle_row_start + 48) as u16 && y <= (toggle_row_start + 49) as u16 {
+                        app.settings.cycle_cursor_highlight();
+                        app.status_message = match app.settings.save() {
+                            Ok(()) => format!("Cursor highlight: {:?}", app.settings.cursor_highlight),
+                            Err(e) => format!("Cursor highlight: {:?} (not saved: {})", app.settings.cursor_highlight, e),
+                        };
+                        app.needs_redraw = true;
+                        return Ok(());
+                    }
+
+                    // Snap to Grid rows
+                    if y >= (toggle_row_start + 51) as u16 && y <= (toggle_row_start + 52) as u16 {
+                        return app.run_command(Command::ToggleSnapToGrid);
+                    }
+
+                    // Start Screen rows
+                    if y >= (toggle_row_start + 54) as u16 && y <= (toggle_row_start + 55) as u16 {
+                        return app.run_command(Command::ToggleStartScreen);
+                    }
+
                     // Clicked elsewhere in panel - just keep it open
                     return Ok(());
                 }
             }
 
+            // Click in outline panel (when expanded) - jump to heading
+            if app.outline_panel_expanded {
+                let settings_panel_width = if app.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 };
+                let panel_end = term_width.saturating_sub(settings_panel_width);
+                let panel_x = panel_end.saturating_sub(layout::OUTLINE_PANEL_WIDTH);
+
+                if x >= panel_x && x < panel_end {
+                    // Heading entries start at row 3 (matching render_outline_panel)
+                    let list_row_start = 3;
+                    if y >= list_row_start {
+                        let index = app.outline.scroll + (y - list_row_start) as usize;
+                        app.jump_to_heading(index);
+                    }
+                    return Ok(());
+                }
+            }
+
+            // Click in the backlinks panel - open the mention, or link it with its button
+            if let Some((panel_x, width)) = app.backlinks_panel(term_width) {
+                if x >= panel_x && x < panel_x + width {
+                    // Entries start at row 3 (matching render_backlinks_panel)
+                    let list_row_start = 3;
+                    let row = y.checked_sub(list_row_start).and_then(|row| app.backlinks.rows().get(app.backlinks.scroll + row as usize).copied());
+                    if let Some(crate::backlinks::Row::Mention(index)) = row {
+                        let on_button = x + 1 + crate::BACKLINK_BUTTON.len() as u16 >= panel_x + width;
+                        if on_button && !app.backlinks.mentions[index].linked {
+                            app.link_mention(index);
+                        } else {
+                            app.open_mention(index)?;
+                        }
+                    }
+                    return Ok(());
+                }
+            }
+
+            // Click in the tasks panel - the box toggles an item, the rest opens it
+            if let Some((panel_x, width)) = app.tasks_panel(term_width) {
+                if x >= panel_x && x < panel_x + width {
+                    // Entries start at row 3 (matching render_tasks_panel)
+                    let list_row_start = 3;
+                    let row = y.checked_sub(list_row_start).and_then(|row| app.tasks.rows().get(app.tasks.scroll + row as usize).copied());
+                    match row {
+                        // The box is the third column of an item (or the one after it)
+                        Some(crate::tasks::Row::Task(index)) if x <= panel_x + 4 => app.toggle_task(index),
+                        Some(crate::tasks::Row::Task(index) | crate::tasks::Row::Note(index, _)) => app.open_task(index)?,
+                        None => {}
+                    }
+                    return Ok(());
+                }
+            }
+
+            // Click in the pinned preview - the close button on its title row; the text is read-only
+            if let Some((panel_x, width)) = app.preview_panel(term_width) {
+                if x >= panel_x && x < panel_x + width {
+                    if y == 1 && x >= panel_x + width - 3 {
+                        app.close_preview();
+                    }
+                    return Ok(());
+                }
+            }
+
             // Click in notes list sidebar
             if x < notes_list_width {
                 if !app.notes_list.is_empty() {
@@ -154,10 +402,19 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                         return Ok(());
                     }
 
-                    // Check for double-click
+                    // Smart views sit above the list
                     let clicked_row = y.saturating_sub(1) as usize;
+                    let smart_rows = app.smart_views.rows();
+                    if let Some(&row) = smart_rows.get(clicked_row) {
+                        return app.open_smart_view_row(row);
+                    }
+                    if app.smart_views.all_collapsed {
+                        return Ok(());
+                    }
+
+                    // Check for double-click
                     let visible_start = app.notes_list_scroll;
-                    let note_index = visible_start + clicked_row;
+                    let note_index = visible_start + clicked_row - smart_rows.len();
 
                     if note_index < app.notes_list.len() {
                         let now = std::time::Instant::now();
@@ -172,31 +429,22 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                             app.selected_note_index = note_index;
                             let note = &app.notes_list[note_index];
                             app.editing_title = true;
-                            app.title_buffer = note.title.clone();
+                            app.title_edit = crate::line_edit::LineEdit::new(&note.title);
                             app.needs_redraw = true;
                         } else {
                             // Single click: switch to the note
-                            // Save current note
-                            app.save_current_note()?;
+                            // Save current note (stay put if it conflicts with an external change)
+                            if !app.save_before_switch()? {
+                                return Ok(());
+                            }
 
                             // Reload notes list to get fresh data
-                            if let Ok(notes) = app.notes_mode.db.list_notes(100) {
-                                app.notes_list = notes;
-                            }
+                            app.reload_notes_list();
 
                             // Load selected note
                             app.selected_note_index = note_index;
-                            let note = &app.notes_list[note_index];
-
-                            // Load note content into grid
-                            let lines: Vec<String> = note.content.lines().map(|s| s.to_string()).collect();
-                            app.grid = crate::chunked_grid::ChunkedGrid::from_lines(&lines);
-                            app.cursor_row = 0;
-                            app.cursor_col = 0;
-                            app.viewport_row = 0;
-                            app.viewport_col = 0;
-
-                            app.notes_mode.current_note = Some(note.clone());
+                            let note = app.notes_list[note_index].clone();
+                            app.load_note(note);
 
                             app.needs_redraw = true;
                         }
@@ -214,12 +462,23 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
             let screen_x = x as usize;
             let screen_y = y.saturating_sub(1) as usize;
 
-            // Set cursor position
+            // Ctrl+Click opens a link, anywhere else it adds a caret (or removes one) and the
+            // cursor stays a caret too
+            if modifiers.ctrl {
+                let (row, col) = (app.viewport_row + screen_y, app.viewport_col + screen_x);
+                if !app.open_link(row, col) {
+                    app.add_caret(row, col);
+                }
+                return Ok(());
+            }
+
+            // Set cursor position (on the nearest grid line, with Snap to Grid)
             app.cursor_row = app.viewport_row + screen_y;
-            app.cursor_col = app.viewport_col + screen_x;
+            app.cursor_col = app.snap_column(app.cursor_row, app.viewport_col + screen_x);
 
             // Clear any existing selection on new click
             app.grid.clear_selection();
+            app.collapse_selections();
 
             // Mark position for potential drag
             mouse_state.last_click_pos = Some((x, y));
@@ -228,14 +487,19 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
             app.needs_redraw = true;
         }
 
+        // Mouse motion with no button held - highlight what's under the pointer
+        MouseEvent { button: None, is_drag: true, x, y, .. } => {
+            let hover = hover_at(app, x, y, term_width, term_height);
+            if hover != app.hover {
+                app.hover = hover;
+                app.needs_redraw = true;
+            }
+            app.show_sidebar_tooltip(y);
+        }
+
         // Mouse drag - update selection
         MouseEvent { is_drag: true, x, y, .. } => {
-            let _ = (|| -> std::io::Result<()> {
-                use std::io::Write;
-                let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-                writeln!(f, "DRAG EVENT: x={} y={} is_dragging={}", x, y, mouse_state.is_dragging)?;
-                f.flush()
-            })();
+            log::trace!("DRAG EVENT: x={} y={} is_dragging={}", x, y, mouse_state.is_dragging);
 
             // Editor now spans full width, so use x directly
             let screen_x = x as usize;
@@ -256,66 +520,113 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                         app.grid.start_selection(start_grid_row, start_grid_col);
                         mouse_state.is_dragging = true;
 
-                        let _ = (|| -> std::io::Result<()> {
-                            use std::io::Write;
-                            let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-                            writeln!(f, "  -> Started selection at ({}, {})", start_screen_y, start_screen_x)?;
-                            f.flush()
-                        })();
+                        log::trace!("  -> Started selection at ({}, {})", start_screen_y, start_screen_x);
                     }
                 }
 
-                // Auto-scroll viewport when dragging near edges
-                let scroll_margin = 2; // cells from edge to trigger scrolling
+                // Near an edge the autoscroll timer scrolls, faster the deeper into the margin
+                // (rows count from the title bar so dragging onto it or the status line scrolls fastest)
+                let editor_width = term_width.saturating_sub(notes_list_width) as usize;
+                mouse_state.autoscroll = (
+                    edge_speed(y as usize, term_height as usize),
+                    edge_speed(x.saturating_sub(notes_list_width) as usize, editor_width),
+                );
+                mouse_state.last_drag_pos = Some((x, y));
 
-                // Scroll vertically - allow cursor to keep moving at edges
-                if screen_y < scroll_margin {
-                    if app.viewport_row > 0 {
-                        app.viewport_row = app.viewport_row.saturating_sub(1);
-                    }
-                    // Even if we can't scroll, cursor can still move within visible area
-                } else if screen_y >= (term_height.saturating_sub(2) as usize).saturating_sub(scroll_margin) {
-                    app.viewport_row += 1;
-                }
+                drag_selection_to(app, x, y, term_height);
 
-                // Scroll horizontally
-                let editor_width = (term_width.saturating_sub(notes_list_width)) as usize;
-                if screen_x < scroll_margin {
-                    if app.viewport_col > 0 {
-                        app.viewport_col = app.viewport_col.saturating_sub(1);
-                    }
-                    // Even if we can't scroll, cursor can still move within visible area
-                } else if screen_x >= editor_width.saturating_sub(scroll_margin) {
-                    app.viewport_col += 1;
-                }
-
-                // Update cursor position
-                app.cursor_row = app.viewport_row + screen_y;
-                app.cursor_col = app.viewport_col + screen_x;
-
-                // Update block selection
-                app.grid.update_selection(app.cursor_row, app.cursor_col);
-
-                let _ = (|| -> std::io::Result<()> {
-                    use std::io::Write;
-                    let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-                    writeln!(f, "  -> Updated selection to ({}, {})", screen_y, screen_x)?;
-                    f.flush()
-                })();
-
-                app.needs_redraw = true;
+                log::trace!("  -> Updated selection to ({}, {})", screen_y, screen_x);
             }
         }
 
         // Mouse release - keep selection but stop dragging
         MouseEvent { button: Some(crate::kitty_native::MouseButton::Left), is_press: false, .. } => {
+            // A finished drag selection becomes the primary selection (Linux)
+            if mouse_state.is_dragging {
+                if let Some(selected) = app.grid.copy_block() {
+                    crate::primary_selection::set(&selected.join("\n"));
+                }
+            }
             mouse_state.is_dragging = false;
             mouse_state.last_click_pos = None;
+            mouse_state.autoscroll = (0, 0);
+            mouse_state.last_drag_pos = None;
+        }
+
+        // Middle click in the editor - paste the primary selection there
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::Middle), is_press: true, is_drag: false, x, y, .. }
+            if x >= notes_list_width && y >= 1 && y < term_height.saturating_sub(1) => {
+            let row = app.viewport_row + y.saturating_sub(1) as usize;
+            let col = app.viewport_col + x as usize;
+            app.paste_primary_at(row, col);
+        }
+
+        // Wheel over the pinned preview scrolls it (sideways with Shift or the horizontal wheel)
+        MouseEvent { button: Some(button @ (MouseButton::ScrollUp | MouseButton::ScrollDown | MouseButton::ScrollLeft | MouseButton::ScrollRight)), x, modifiers, .. }
+            if is_over_preview_panel(app, x, term_width) => {
+            let (rows, cols) = match (button, modifiers.shift) {
+                (MouseButton::ScrollUp, false) => (-1, 0),
+                (MouseButton::ScrollDown, false) => (1, 0),
+                (MouseButton::ScrollUp | MouseButton::ScrollLeft, _) => (0, -1),
+                _ => (0, 1),
+            };
+            if let Some(preview) = app.preview.as_mut() {
+                preview.scroll(rows * app.settings.scroll_step as isize, cols * navigation::WHEEL_SCROLL_COLS as isize);
+            }
+            app.needs_redraw = true;
+        }
+
+        // Horizontal wheel, or Shift+wheel over the editor - scroll the canvas sideways
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollLeft), x, .. } if x >= notes_list_width => {
+            scroll_editor_horizontally(app, -1);
+        }
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollRight), x, .. } if x >= notes_list_width => {
+            scroll_editor_horizontally(app, 1);
+        }
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollUp), x, modifiers, .. }
+            if modifiers.shift && x >= notes_list_width && !is_over_outline_panel(app, x, term_width) => {
+            scroll_editor_horizontally(app, -1);
+        }
+        MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollDown), x, modifiers, .. }
+            if modifiers.shift && x >= notes_list_width && !is_over_outline_panel(app, x, term_width) => {
+            scroll_editor_horizontally(app, 1);
+        }
+
+        // Wheel over the backlinks panel scrolls its entries
+        MouseEvent { button: Some(button @ (MouseButton::ScrollUp | MouseButton::ScrollDown)), x, .. }
+            if app.backlinks_panel(term_width).is_some_and(|(panel_x, width)| x >= panel_x && x < panel_x + width) => {
+            let rows = app.backlinks.rows().len();
+            let backlinks = &mut app.backlinks;
+            backlinks.scroll = if button == MouseButton::ScrollUp {
+                backlinks.scroll.saturating_sub(1)
+            } else {
+                (backlinks.scroll + 1).min(rows.saturating_sub(1))
+            };
+            app.needs_redraw = true;
+        }
+
+        // Wheel over the tasks panel scrolls its entries
+        MouseEvent { button: Some(button @ (MouseButton::ScrollUp | MouseButton::ScrollDown)), x, .. }
+            if app.tasks_panel(term_width).is_some_and(|(panel_x, width)| x >= panel_x && x < panel_x + width) => {
+            let rows = app.tasks.rows().len();
+            let tasks = &mut app.tasks;
+            tasks.scroll = if button == MouseButton::ScrollUp {
+                tasks.scroll.saturating_sub(1)
+            } else {
+                (tasks.scroll + 1).min(rows.saturating_sub(1))
+            };
+            app.needs_redraw = true;
         }
 
         // Scroll up
         MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollUp), x, .. } => {
-            if x < notes_list_width {
+            if is_over_outline_panel(app, x, term_width) {
+                // Scroll outline entries
+                if app.outline.scroll > 0 {
+                    app.outline.scroll -= 1;
+                    app.needs_redraw = true;
+                }
+            } else if x < notes_list_width {
                 // Scroll notes list
                 if app.notes_list_scroll > 0 {
                     app.notes_list_scroll -= 1;
@@ -323,18 +634,22 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                 }
             } else {
                 // Scroll editor viewport up
-                if app.viewport_row > 0 {
-                    app.viewport_row = app.viewport_row.saturating_sub(3); // Scroll by 3 rows
-                    app.needs_redraw = true;
-                }
+                app.scroll_editor(-1);
             }
         }
 
         // Scroll down
         MouseEvent { button: Some(crate::kitty_native::MouseButton::ScrollDown), x, .. } => {
-            if x < notes_list_width {
+            if is_over_outline_panel(app, x, term_width) {
+                // Scroll outline entries
+                if app.outline.scroll + 1 < app.outline.headings.len() {
+                    app.outline.scroll += 1;
+                    app.needs_redraw = true;
+                }
+            } else if x < notes_list_width {
                 // Scroll notes list
-                let visible_count = term_height.saturating_sub(2) as usize;
+                let visible_count = term_height.saturating_sub(2).saturating_sub(app.smart_view_rows() as u16) as usize;
+                app.ensure_notes_loaded(app.notes_list_scroll + visible_count);
                 let max_scroll = app.notes_list.len().saturating_sub(visible_count);
                 if app.notes_list_scroll < max_scroll {
                     app.notes_list_scroll += 1;
@@ -342,8 +657,7 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
                 }
             } else {
                 // Scroll editor viewport down
-                app.viewport_row += 3; // Scroll by 3 rows
-                app.needs_redraw = true;
+                app.scroll_editor(1);
             }
         }
 
@@ -352,3 +666,207 @@ pub async fn handle_mouse(app: &mut App, event: MouseEvent, mouse_state: &mut Mo
 
     Ok(())
 }
+
+/// Autoscroll speed for a drag at `pos` in a viewport of `len` cells: negative toward the
+/// start, positive toward the end, growing with the square of the depth into the edge margin
+pub fn edge_speed(pos: usize, len: usize) -> isize {
+    let margin = autoscroll::EDGE_MARGIN;
+    let depth = if pos < margin {
+        -((margin - pos) as isize)
+    } else if pos + margin >= len {
+        (pos + margin + 1 - len) as isize
+    } else {
+        0
+    };
+    (depth * depth.abs()).clamp(-(autoscroll::MAX_SPEED as isize), autoscroll::MAX_SPEED as isize)
+}
+
+/// Move the cursor and the selection end to the editor cell under screen position (x, y)
+fn drag_selection_to(app: &mut App, x: u16, y: u16, term_height: u16) {
+    let editor_height = term_height.saturating_sub(2) as usize;
+    let screen_y = (y.saturating_sub(1) as usize).min(editor_height.saturating_sub(1));
+    app.cursor_row = app.viewport_row + screen_y;
+    app.cursor_col = app.viewport_col + x as usize;
+    app.grid.update_selection(app.cursor_row, app.cursor_col);
+    // The viewport is driven by the drag, so it should not jump to follow the cursor
+    app.viewport_anchor = (app.cursor_row, app.cursor_col);
+    app.needs_redraw = true;
+}
+
+/// One autoscroll timer tick while a drag is held near the editor edge
+pub fn autoscroll_step(app: &mut App, mouse_state: &mut MouseState) -> Result<()> {
+    let Some((x, y)) = mouse_state.last_drag_pos else {
+        return Ok(());
+    };
+    let (_, term_height) = app.terminal_size()?;
+    let (row_speed, col_speed) = mouse_state.autoscroll;
+    app.viewport_row = app.viewport_row.saturating_add_signed(row_speed);
+    app.viewport_col = app.viewport_col.saturating_add_signed(col_speed);
+    drag_selection_to(app, x, y, term_height);
+    Ok(())
+}
+
+/// Queue a drag/hover motion event until the next frame, keeping only the newest position.
+/// Returns a queued event that must be handled now because this one cannot replace it.
+pub fn coalesce_motion(pending: &mut Option<MouseEvent>, event: MouseEvent) -> Option<MouseEvent> {
+    match pending.replace(event) {
+        Some(previous) if previous.button != event.button => Some(previous),
+        _ => None,
+    }
+}
+
+/// Move the editor viewport one wheel notch left (-1) or right (1); the cursor stays put
+fn scroll_editor_horizontally(app: &mut App, direction: i8) {
+    app.viewport_col = if direction < 0 {
+        if app.viewport_col < navigation::WHEEL_SCROLL_COLS {
+            app.extend_canvas(false, true);
+        }
+        app.viewport_col - navigation::WHEEL_SCROLL_COLS
+    } else {
+        app.viewport_col + navigation::WHEEL_SCROLL_COLS
+    };
+    app.needs_redraw = true;
+}
+
+/// Check whether a screen column falls inside the pinned preview
+fn is_over_preview_panel(app: &App, x: u16, term_width: u16) -> bool {
+    app.preview_panel(term_width).is_some_and(|(panel_x, width)| x >= panel_x && x < panel_x + width)
+}
+
+/// Check whether a screen column falls inside the outline panel
+fn is_over_outline_panel(app: &App, x: u16, term_width: u16) -> bool {
+    if !app.outline_panel_expanded {
+        return false;
+    }
+    let settings_panel_width = if app.settings_panel_expanded { layout::SETTINGS_PANEL_WIDTH } else { 0 };
+    let panel_end = term_width.saturating_sub(settings_panel_width);
+    x >= panel_end.saturating_sub(layout::OUTLINE_PANEL_WIDTH) && x < panel_end
+}
+
+/// Dispatch a click on the status line using the same layout the renderer used
+fn handle_status_line_click(app: &mut App, x: u16, term_width: u16) -> Result<()> {
+    let layout = crate::StatusLineLayout::new(app, term_width);
+    let hit = |(start, end): (u16, u16)| x >= start && x < end;
+
+    if hit(layout.dirty) {
+        // Force save
+        app.save_current_note()?;
+        if app.save_conflict.is_none() {
+            app.status_message = "Note saved".to_string();
+        }
+    } else if layout.focus.is_some_and(hit) {
+        app.toggle_focus_timer();
+    } else if hit(layout.wrap) {
+        app.run_command(Command::ToggleWrapPaste)?;
+    } else if hit(layout.grid) {
+        app.run_command(Command::ToggleGridLines)?;
+    } else if hit(layout.position) {
+        app.open_goto_line();
+    }
+
+    app.needs_redraw = true;
+    Ok(())
+}
+
+/// Notes menu item under screen row `y`, if any
+fn notes_menu_item_at(y: u16) -> Option<usize> {
+    let index = y.checked_sub(crate::NOTES_MENU_FIRST_ROW)? as usize;
+    (index < crate::NOTES_MENU_ITEMS.len()).then_some(index)
+}
+
+/// Title bar menu buttons, as (start, end) columns
+pub const TITLE_BUTTONS: [(u16, u16); 3] = [
+    (0, 8),   // "Notes ▾"
+    (10, 21), // "Settings ▾"
+    (22, 31), // "Outline ▾"
+];
+pub const TITLE_NOTES: usize = 0;
+pub const TITLE_SETTINGS: usize = 1;
+pub const TITLE_OUTLINE: usize = 2;
+
+/// Note title field in the title bar: starts after the menu buttons and ends before the
+/// hamster and "Chonk-Note" on the right
+pub const TITLE_FIELD_START: u16 = 33;
+const TITLE_BRANDING_WIDTH: u16 = 16;
+
+pub fn title_field_width(term_width: u16) -> usize {
+    term_width.saturating_sub(TITLE_FIELD_START + TITLE_BRANDING_WIDTH + 1) as usize
+}
+
+fn title_button_at(x: u16) -> Option<usize> {
+    TITLE_BUTTONS.iter().position(|&(start, end)| x >= start && x < end)
+}
+
+/// Rows of the settings panel toggles: each takes a label row and a state row, then a gap
+const SETTINGS_TOGGLE_FIRST_ROW: u16 = 3;
+const SETTINGS_TOGGLE_COUNT: usize = 19;
+
+/// Something the pointer is over, highlighted before it is clicked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hover {
+    /// Index into TITLE_BUTTONS
+    TitleButton(usize),
+    /// Index into NOTES_MENU_ITEMS
+    NotesMenuItem(usize),
+    /// Index into the notes list
+    SidebarNote(usize),
+    /// Index into the smart view rows above the notes list
+    SidebarSmartRow(usize),
+    /// Settings panel toggle, counted from the top
+    SettingsToggle(usize),
+}
+
+/// What a click at (x, y) would hit, using the same positions as the click handling above
+fn hover_at(app: &App, x: u16, y: u16, term_width: u16, term_height: u16) -> Option<Hover> {
+    if y >= term_height.saturating_sub(1) {
+        return None;
+    }
+    if y == 0 {
+        return title_button_at(x).map(Hover::TitleButton);
+    }
+    if app.notes_menu_expanded && x < 45 {
+        return notes_menu_item_at(y).map(Hover::NotesMenuItem);
+    }
+    if app.settings_panel_expanded && x >= term_width.saturating_sub(layout::SETTINGS_PANEL_WIDTH) {
+        let offset = y.checked_sub(SETTINGS_TOGGLE_FIRST_ROW)?;
+        let index = (offset / 3) as usize;
+        return (offset % 3 != 2 && index < SETTINGS_TOGGLE_COUNT).then_some(Hover::SettingsToggle(index));
+    }
+    if app.sidebar_expanded && x < layout::SIDEBAR_WIDTH_EXPANDED {
+        let row = y.checked_sub(1)? as usize;
+        let smart_rows = app.smart_view_rows();
+        if row < smart_rows {
+            return Some(Hover::SidebarSmartRow(row));
+        }
+        let index = app.notes_list_scroll + row - smart_rows;
+        return (!app.smart_views.all_collapsed && index < app.notes_list.len()).then_some(Hover::SidebarNote(index));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edge_speed() {
+        assert_eq!(edge_speed(10, 40), 0);
+        assert_eq!(edge_speed(2, 40), -1);
+        assert_eq!(edge_speed(0, 40), -9);
+        assert_eq!(edge_speed(39, 40), 9);
+        assert_eq!(edge_speed(37, 40), 1);
+    }
+
+    #[test]
+    fn test_hover_at() -> Result<()> {
+        let mut app = crate::headless::Driver::new()?.app;
+        assert_eq!(hover_at(&app, 12, 0, 80, 24), Some(Hover::TitleButton(TITLE_SETTINGS)));
+        assert_eq!(hover_at(&app, 9, 0, 80, 24), None);
+
+        app.settings_panel_expanded = true;
+        let x = 80 - layout::SETTINGS_PANEL_WIDTH;
+        assert_eq!(hover_at(&app, x, 7, 80, 24), Some(Hover::SettingsToggle(1)));
+        assert_eq!(hover_at(&app, x, 8, 80, 24), None); // Gap between toggles
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+// Stored form of a note's canvas
+// Compact notes are saved as plain text lines (what to_lines() produces). Notes whose
+// content is spread thinly over a large area are saved sparsely instead: a header line
+// followed by one JSON record [row, col, "text"] per run of characters, written straight
+// from the chunks so the empty interior of the bounding box is never materialized.
+// Either form can be followed by where the content sits relative to the canvas origin (Ln 1,
+// Col 1): a header line and a JSON record [row, col] for its top-left corner, left out when
+// that is the origin itself. Then come the cells' styles (colors from pasted terminal output):
+// a header line, then one JSON record [row, col, length, style] per run of equally styled cells.
+use crate::chunked_grid::{ChunkedGrid, Style, CHUNK_SIZE};
+use crate::config::storage;
+use std::collections::BTreeSet;
+
+/// First line of a sparse note (the control character keeps it from matching real text)
+pub const SPARSE_HEADER: &str = "\u{1}chonk-sparse 1";
+
+/// Line after the text of a note that doesn't start at the origin
+pub const ORIGIN_HEADER: &str = "\u{1}chonk-origin 1";
+
+/// Line after the text of a note with styled cells
+pub const STYLES_HEADER: &str = "\u{1}chonk-styles 1";
+
+/// True if the content was written in the sparse format
+pub fn is_sparse(content: &str) -> bool {
+    content.lines().next() == Some(SPARSE_HEADER)
+}
+
+/// Whether the grid would be much larger as plain text than as runs
+fn prefers_sparse(grid: &ChunkedGrid) -> bool {
+    let Some((min_row, min_col, max_row, max_col)) = grid.bounds() else {
+        return false;
+    };
+    let area = (max_row - min_row + 1).saturating_mul(max_col - min_col + 1);
+    area >= storage::SPARSE_MIN_AREA && area / grid.cell_count().max(1) >= storage::SPARSE_AREA_PER_CELL
+}
+
+/// Serialize the grid for the database, choosing plain text or the sparse format
+pub fn encode(grid: &ChunkedGrid) -> String {
+    let mut out = if prefers_sparse(grid) {
+        encode_sparse(grid)
+    } else {
+        grid.to_lines().join("\n")
+    };
+    if let Some((min_row, min_col, _, _)) = grid.bounds() {
+        let (row, col) = grid.logical_pos(min_row, min_col);
+        if (row, col) != (0, 0) {
+            out.push_str(&format!("\n{}\n{}", ORIGIN_HEADER, serde_json::json!([row, col])));
+        }
+    }
+    if grid.has_styles() {
+        out.push_str(&encode_styles(grid));
+    }
+    out
+}
+
+/// The styles section: runs of cells in one style, relative to the content bounds
+fn encode_styles(grid: &ChunkedGrid) -> String {
+    let mut out = format!("\n{}", STYLES_HEADER);
+    let Some((min_row, min_col, _, _)) = grid.bounds() else {
+        return out;
+    };
+    let mut runs: Vec<(usize, usize, usize, Style)> = Vec::new();
+    let mut cells: Vec<((usize, usize), Style)> = grid.styled_cells().collect();
+    cells.sort_by_key(|&(pos, _)| pos);
+    for ((row, col), style) in cells {
+        match runs.last_mut() {
+            Some((run_row, run_col, len, run_style)) if *run_row == row && *run_col + *len == col && *run_style == style => *len += 1,
+            _ => runs.push((row, col, 1, style)),
+        }
+    }
+    for (row, col, len, style) in runs {
+        out.push('\n');
+        out.push_str(&serde_json::json!([row - min_row, col - min_col, len, style]).to_string());
+    }
+    out
+}
+
+/// Sparse format: positions are relative to the content bounds, like to_lines()
+pub fn encode_sparse(grid: &ChunkedGrid) -> String {
+    let mut out = String::from(SPARSE_HEADER);
+    let Some((min_row, min_col, _, max_col)) = grid.bounds() else {
+        return out;
+    };
+
+    let rows: BTreeSet<usize> = grid.cell_positions().map(|(row, _)| row).collect();
+    for row in rows {
+        for (col, text) in grid.line_runs(row, min_col..max_col + 1) {
+            let record = serde_json::json!([row - min_row, col - min_col, text]);
+            out.push('\n');
+            out.push_str(&record.to_string());
+        }
+    }
+    out
+}
+
+/// Load stored content in either format. Malformed sparse and style records are skipped.
+pub fn decode(content: &str) -> ChunkedGrid {
+    let (text, styles) = match content.split_once(&format!("\n{}", STYLES_HEADER)) {
+        Some((text, styles)) => (text, Some(styles)),
+        None => (content, None),
+    };
+    let (text, origin) = match text.split_once(&format!("\n{}", ORIGIN_HEADER)) {
+        Some((text, origin)) => (text, Some(origin)),
+        None => (text, None),
+    };
+    let mut grid = if is_sparse(text) {
+        let mut grid = ChunkedGrid::new();
+        for line in text.lines().skip(1) {
+            match serde_json::from_str::<(usize, usize, String)>(line) {
+                Ok((row, col, text)) => grid.insert_at(row, col, &text),
+                Err(e) => log::warn!("Skipping malformed sparse note record: {}", e),
+            }
+        }
+        grid
+    } else {
+        let lines: Vec<String> = text.lines().map(|s| s.to_string()).collect();
+        ChunkedGrid::from_lines(&lines)
+    };
+
+    // Text is loaded at the top left, so the records' positions are already where it is
+    for line in styles.unwrap_or_default().lines().filter(|line| !line.is_empty()) {
+        match serde_json::from_str::<(usize, usize, usize, Style)>(line) {
+            Ok((row, col, len, style)) => (col..col + len).for_each(|col| grid.set_style(row, col, style)),
+            Err(e) => log::warn!("Skipping malformed style record: {}", e),
+        }
+    }
+    match origin.map(|record| serde_json::from_str::<(i64, i64)>(record.trim())) {
+        Some(Ok((row, col))) => place(&grid, row, col),
+        Some(Err(e)) => {
+            log::warn!("Skipping malformed origin record: {}", e);
+            grid
+        }
+        None => grid,
+    }
+}
+
+/// `grid` (loaded at the top left) moved so that its top-left corner is at the logical
+/// position (row, col). The origin stays on a chunk boundary, as ChunkedGrid::shift_chunks
+/// keeps it.
+fn place(grid: &ChunkedGrid, row: i64, col: i64) -> ChunkedGrid {
+    let origin = |pos: i64| (-pos).max(0).unsigned_abs().div_ceil(CHUNK_SIZE as u64) as usize * CHUNK_SIZE;
+    let origin = (origin(row), origin(col));
+    let (top, left) = ((origin.0 as i64 + row) as usize, (origin.1 as i64 + col) as usize);
+    let mut placed = ChunkedGrid::new();
+    for (r, c) in grid.cell_positions() {
+        placed.set_styled(top + r, left + c, grid.get(r, c), grid.style(r, c));
+    }
+    placed.origin = origin;
+    placed
+}
+
+/// Title suggested by the content: its first non-empty line, trimmed and shortened
+pub fn first_line_title(grid: &ChunkedGrid) -> Option<String> {
+    let (_, min_col, _, max_col) = grid.bounds()?;
+    let rows: BTreeSet<usize> = grid.cell_positions().map(|(row, _)| row).collect();
+    rows.into_iter().find_map(|row| {
+        let mut line = String::new();
+        for (col, text) in grid.line_runs(row, min_col..max_col + 1) {
+            let width = line.chars().count();
+            line.extend(std::iter::repeat_n(' ', (col - min_col).saturating_sub(width)));
+            line.push_str(&text);
+        }
+        shorten_title(&line)
+    })
+}
+
+/// Same as first_line_title, for stored content
+pub fn first_line_title_of(content: &str) -> Option<String> {
+    if is_sparse(content) {
+        first_line_title(&decode(content))
+    } else {
+        content.lines().take_while(|line| !line.starts_with('\u{1}')).find_map(shorten_title)
+    }
+}
+
+fn shorten_title(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let mut title: String = line.chars().take(storage::AUTO_TITLE_MAX_CHARS).collect();
+    if line.chars().count() > storage::AUTO_TITLE_MAX_CHARS {
+        title.pop();
+        title.push('…');
+    }
+    Some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(3, 7, "top \"left\"");
+        grid.insert_at(50_000, 90_000, "far away");
+
+        let encoded = encode(&grid);
+        assert!(is_sparse(&encoded));
+        assert_eq!(encoded.lines().count(), 7); // Header, one record per run and the origin
+        let decoded = decode(&encoded);
+        assert_eq!(decoded.get(3, 7), 't');
+        assert_eq!(decoded.get_line(50_000, 90_000, 90_007), "far away");
+
+        // Compact notes stay plain text, and plain text still loads
+        let mut small = ChunkedGrid::new();
+        small.insert_at(0, 0, "hello");
+        assert_eq!(encode(&small), "hello");
+        assert_eq!(decode("hello\n  world").get(1, 2), 'w');
+
+        // Styles follow the text, in either format
+        let red = Style { fg: Some((255, 0, 0)), ..Style::default() };
+        small.insert_at(1, 2, "world");
+        (1..4).for_each(|col| small.set_style(0, col, red));
+        small.set_style(1, 2, Style { underline: true, ..red });
+        let encoded = encode(&small);
+        assert_eq!(encoded, "hello\n  world\n\u{1}chonk-styles 1\n[0,1,3,{\"fg\":[255,0,0]}]\n[1,2,1,{\"fg\":[255,0,0],\"underline\":true}]");
+        let decoded = decode(&encoded);
+        assert_eq!(decoded.to_lines(), ["hello", "  world"]);
+        assert_eq!((decoded.style(0, 0), decoded.style(0, 3), decoded.style(1, 2)), (Style::default(), red, Style { underline: true, ..red }));
+        grid.set_style(50_000, 90_000, red);
+        assert_eq!(decode(&encode(&grid)).style(50_000, 90_000), red);
+    }
+
+    #[test]
+    fn test_origin_round_trip() {
+        // Content above and left of the origin keeps its logical position
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 0, "start");
+        grid.shift_chunks(1, 2);
+        grid.insert_at(20, 50, "above");
+        grid.set_style(20, 50, Style { bold: true, ..Style::default() });
+        assert_eq!(grid.logical_pos(20, 50), (-12, -14));
+
+        let encoded = encode(&grid);
+        assert!(encoded.contains(&format!("{}\n[-12,-14]", ORIGIN_HEADER)));
+        let decoded = decode(&encoded);
+        let (min_row, min_col, _, _) = decoded.bounds().unwrap();
+        assert_eq!(decoded.logical_pos(min_row, min_col), (-12, -14));
+        assert_eq!(decoded.origin, (32, 32));
+        assert_eq!(decoded.get_line(20, 18, 22), "above");
+        assert!(decoded.style(20, 18).bold);
+        assert_eq!(decoded.get_line(32, 32, 36), "start");
+        assert_eq!(decode(&encode(&decoded)).to_lines(), decoded.to_lines());
+        assert_eq!(first_line_title_of("\n\u{1}chonk-origin 1\n[2,0]"), None);
+    }
+
+    #[test]
+    fn test_first_line_title() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(2, 4, "  Shopping  list ");
+        grid.insert_at(3, 0, "eggs");
+        assert_eq!(first_line_title(&grid).as_deref(), Some("Shopping  list"));
+        assert_eq!(first_line_title_of(&encode(&grid)).as_deref(), Some("Shopping  list"));
+        assert_eq!(first_line_title_of("\n   \n"), None);
+        assert_eq!(first_line_title_of(&"x".repeat(100)).map(|t| t.chars().count()), Some(storage::AUTO_TITLE_MAX_CHARS));
+    }
+}
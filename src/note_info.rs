@@ -0,0 +1,78 @@
+// Statistics shown in the "Note info" popup
+use crate::chunked_grid::{ChunkedGrid, MemoryUsage};
+use crate::notes_database::Note;
+use chrono::{DateTime, Utc};
+
+pub struct NoteStats {
+    pub words: usize,
+    pub chars: usize,
+    pub lines: usize,
+    /// (min_row, min_col, max_row, max_col) of the content on the canvas
+    pub bounds: Option<(usize, usize, usize, usize)>,
+    pub chunks: usize,
+    pub cells: usize,
+    /// Estimated memory held by the grid, including packed (far-away) chunks
+    pub memory: MemoryUsage,
+    /// Size of the content as it is saved (UTF-8 bytes)
+    pub content_bytes: usize,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl NoteStats {
+    /// Gather statistics from the editor grid (which may hold unsaved edits)
+    pub fn compute(grid: &ChunkedGrid, note: Option<&Note>) -> Self {
+        let lines = grid.to_lines();
+        let content = lines.join("\n");
+
+        Self {
+            words: content.split_whitespace().count(),
+            chars: lines.iter().map(|l| l.chars().count()).sum(),
+            lines: lines.len(),
+            bounds: grid.bounds(),
+            chunks: grid.chunk_count(),
+            cells: grid.cell_count(),
+            memory: grid.memory_usage(),
+            content_bytes: content.len(),
+            created_at: note.map(|n| n.created_at),
+            updated_at: note.map(|n| n.updated_at),
+        }
+    }
+}
+
+/// Human-readable byte size (B, KB, MB, GB)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_stats() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 0, "hello world");
+        grid.insert_at(2, 40, "spatial note");
+
+        let stats = NoteStats::compute(&grid, None);
+        assert_eq!(stats.words, 4);
+        assert_eq!(stats.lines, 3);
+        assert_eq!(stats.cells, 21);
+        assert_eq!(stats.chunks, 2);
+        assert_eq!(stats.bounds, Some((0, 0, 2, 51)));
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+    }
+}
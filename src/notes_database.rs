@@ -5,6 +5,9 @@ use rusqlite::{Connection, params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 
+mod migrations;
+pub mod worker;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Note {
     pub id: String,
@@ -13,53 +16,122 @@ pub struct Note {
     pub tags: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Color of the note's marker in the sidebar and title bar, if one was picked
+    #[serde(default)]
+    pub accent: Option<(u8, u8, u8)>,
+    /// Emoji (or other short text) shown before the title
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// Word count the note is being written towards, shown as progress in the status line
+    #[serde(default)]
+    pub word_goal: Option<u32>,
+}
+
+/// A file stored with a note
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub filename: String,
+    pub mime: String,
+    pub data: Vec<u8>,
 }
 
 pub struct NotesDatabase {
     conn: Connection,
+    path: std::path::PathBuf,
+}
+
+/// Result of a save that checks for changes made by another writer
+pub enum SaveOutcome {
+    /// Saved; carries the new updated_at timestamp
+    Saved(DateTime<Utc>),
+    /// The stored note changed since we loaded it; carries the stored version
+    Conflict(Note),
 }
 
 impl NotesDatabase {
     pub fn new() -> Result<Self> {
-        let mut path = dirs::data_local_dir()
+        let mut path = crate::data_dir::dir()
             .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
-        path.push("chonk-note");
         std::fs::create_dir_all(&path)?;
         path.push("notes.db");
 
-        let conn = Connection::open(path)?;
-
-        // Create tables
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS notes (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                content TEXT NOT NULL,
-                tags TEXT NOT NULL,
-                created_at DATETIME NOT NULL,
-                updated_at DATETIME NOT NULL
-            )",
-            [],
-        )?;
+        let conn = Connection::open(&path)?;
+        Self::init(conn, path)
+    }
 
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_notes_updated
-             ON notes(updated_at DESC)",
-            [],
-        )?;
+    /// A database that lives only in memory (for headless tests)
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?, std::path::PathBuf::new())
+    }
 
-        Ok(Self { conn })
+    /// A database in a file of the caller's choosing (for tests that need a second connection)
+    #[cfg(test)]
+    pub fn open_at(path: std::path::PathBuf) -> Result<Self> {
+        Self::init(Connection::open(&path)?, path)
+    }
+
+    fn init(conn: Connection, path: std::path::PathBuf) -> Result<Self> {
+        // Wait for other instances holding the write lock instead of failing immediately
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+
+        // WAL lets readers (other instances, CLI subcommands) run while a save is writing,
+        // and NORMAL sync only fsyncs at checkpoints, which is still safe with WAL.
+        // (In-memory databases keep their own journal mode.)
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        // Room for every statement below, so each is prepared once per connection
+        conn.set_prepared_statement_cache_capacity(40);
+
+        // Create or upgrade the schema
+        migrations::migrate(&conn, &path)?;
+
+        Ok(Self { conn, path })
+    }
+
+    /// Another connection to the same database file (for the background thread)
+    pub fn reopen(&self) -> Result<Self> {
+        if self.path.as_os_str().is_empty() {
+            anyhow::bail!("An in-memory database can't be reopened");
+        }
+        Self::init(Connection::open(&self.path)?, self.path.clone())
+    }
+
+    /// Size of the database on disk, in bytes (including the write-ahead log)
+    pub fn file_size(&self) -> Option<u64> {
+        let wal = std::fs::metadata(format!("{}-wal", self.path.display())).map(|m| m.len()).unwrap_or(0);
+        std::fs::metadata(&self.path).ok().map(|m| m.len() + wal)
     }
 
     pub fn create_note(&self, title: String, content: String, tags: Vec<String>) -> Result<Note> {
+        self.create_note_at(title, content, tags, None, None)
+    }
+
+    /// Store a note exactly as given (id and timestamps included), replacing any note with its id
+    pub fn put_note(&self, note: &Note) -> Result<()> {
+        self.conn.prepare_cached(
+            "INSERT OR REPLACE INTO notes (id, title, content, tags, created_at, updated_at, accent, icon, word_goal)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        )?.execute(params![note.id, note.title, note.content, serde_json::to_string(&note.tags)?,
+                           note.created_at.to_rfc3339(), note.updated_at.to_rfc3339(),
+                           note.accent.map(accent_to_hex), note.icon, note.word_goal])?;
+        Ok(())
+    }
+
+    /// Create a note with the given timestamps (imports keep the original dates); missing ones are now
+    pub fn create_note_at(&self, title: String, content: String, tags: Vec<String>,
+                          created_at: Option<DateTime<Utc>>, updated_at: Option<DateTime<Utc>>) -> Result<Note> {
         let now = Utc::now();
+        let created_at = created_at.unwrap_or(now);
+        let updated_at = updated_at.unwrap_or(created_at);
+        // Seeded with the current time, so notes imported with equal titles and dates still differ
         let id = self.generate_id(&title, &now);
         let tags_json = serde_json::to_string(&tags)?;
 
-        self.conn.execute(
+        self.conn.prepare_cached(
             "INSERT INTO notes (id, title, content, tags, created_at, updated_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![id, title, content, tags_json, now.to_rfc3339(), now.to_rfc3339()],
+        )?.execute(params![id, title, content, tags_json, created_at.to_rfc3339(), updated_at.to_rfc3339()],
         )?;
 
         Ok(Note {
@@ -67,106 +139,376 @@ impl NotesDatabase {
             title,
             content,
             tags,
-            created_at: now,
-            updated_at: now,
+            created_at,
+            updated_at,
+            accent: None,
+            icon: None,
+            word_goal: None,
         })
     }
 
-    pub fn update_note(&self, id: &str, title: String, content: String, tags: Vec<String>) -> Result<()> {
+    /// Set a note's accent color and icon (None clears them). Leaves updated_at alone: this
+    /// is not an edit of the note, so it neither reorders it nor looks like a conflict.
+    pub fn set_note_style(&self, id: &str, accent: Option<(u8, u8, u8)>, icon: Option<&str>) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE notes SET accent = ?1, icon = ?2 WHERE id = ?3")?
+            .execute(params![accent.map(accent_to_hex), icon, id])?;
+        Ok(())
+    }
+
+    /// Set a note's word count goal (None clears it); like the style, not an edit of the note
+    pub fn set_word_goal(&self, id: &str, goal: Option<u32>) -> Result<()> {
+        self.conn.prepare_cached("UPDATE notes SET word_goal = ?1 WHERE id = ?2")?.execute(params![goal, id])?;
+        Ok(())
+    }
+
+    pub fn update_note(&self, id: &str, title: String, content: String, tags: Vec<String>) -> Result<DateTime<Utc>> {
         let now = Utc::now();
         let tags_json = serde_json::to_string(&tags)?;
 
-        self.conn.execute(
+        self.conn.prepare_cached(
             "UPDATE notes SET title = ?1, content = ?2, tags = ?3, updated_at = ?4
              WHERE id = ?5",
-            params![title, content, tags_json, now.to_rfc3339(), id],
+        )?.execute(params![title, content, tags_json, now.to_rfc3339(), id],
         )?;
 
-        Ok(())
+        Ok(now)
+    }
+
+    /// Update a note only if nobody else modified it since `expected_updated_at`
+    pub fn update_note_checked(&self, id: &str, expected_updated_at: &DateTime<Utc>, title: String, content: String, tags: Vec<String>) -> Result<SaveOutcome> {
+        // IMMEDIATE takes the write lock up front so the check and the write are atomic
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+
+        let result = (|| -> Result<SaveOutcome> {
+            if let Some(stored) = self.get_note(id)? {
+                if stored.updated_at != *expected_updated_at {
+                    return Ok(SaveOutcome::Conflict(stored));
+                }
+            }
+            Ok(SaveOutcome::Saved(self.update_note(id, title, content, tags)?))
+        })();
+
+        match result {
+            Ok(outcome) => {
+                self.conn.execute_batch("COMMIT")?;
+                Ok(outcome)
+            }
+            Err(e) => {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
     }
 
     pub fn get_note(&self, id: &str) -> Result<Option<Note>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, tags, created_at, updated_at
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
              FROM notes WHERE id = ?1"
         )?;
 
-        let note = stmt.query_row([id], |row| {
-            let tags_json: String = row.get(3)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                tags,
-                created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
-                updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
-            })
-        }).optional()?;
+        let note = stmt.query_row([id], note_from_row).optional()?;
 
         Ok(note)
     }
 
     pub fn list_notes(&self, limit: usize) -> Result<Vec<Note>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, tags, created_at, updated_at
+        self.list_notes_page(limit, 0)
+    }
+
+    /// Notes `offset..offset + limit`, newest first (the sidebar loads these page by page)
+    pub fn list_notes_page(&self, limit: usize, offset: usize) -> Result<Vec<Note>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
              FROM notes
+             WHERE deleted_at IS NULL
              ORDER BY created_at DESC
-             LIMIT ?1"
+             LIMIT ?1 OFFSET ?2"
         )?;
 
-        let notes = stmt.query_map([limit], |row| {
-            let tags_json: String = row.get(3)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                tags,
-                created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
-                updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
-            })
-        })?
+        let notes = stmt.query_map([limit, offset], note_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(notes)
     }
 
+    pub fn count_notes(&self) -> Result<usize> {
+        let count: i64 = self.conn
+            .prepare_cached("SELECT COUNT(*) FROM notes WHERE deleted_at IS NULL")?
+            .query_row([], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// (id, title) of every note, newest first, without loading content
+    pub fn list_titles(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, title FROM notes WHERE deleted_at IS NULL ORDER BY created_at DESC")?;
+        let titles = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(titles)
+    }
+
+    /// Changes whenever another connection (another process, or the database thread)
+    /// commits; the same value means nothing changed since the last look
+    pub fn data_version(&self) -> Result<i64> {
+        Ok(self.conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+    }
+
+    /// (id, title, updated_at) of the newest `limit` notes, in sidebar order, without content
+    pub fn list_versions(&self, limit: usize) -> Result<Vec<(String, String, DateTime<Utc>)>> {
+        let versions = self.conn.prepare_cached(
+            "SELECT id, title, updated_at FROM notes WHERE deleted_at IS NULL ORDER BY created_at DESC LIMIT ?1"
+        )?
+        .query_map([limit], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?.parse().unwrap_or_else(|_| Utc::now())))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(versions)
+    }
+
+    /// The note titled `title` (ignoring case), the most recently edited one if several are
+    pub fn find_note_by_title(&self, title: &str) -> Result<Option<Note>> {
+        let note = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
+             FROM notes
+             WHERE title = ?1 COLLATE NOCASE AND deleted_at IS NULL
+             ORDER BY updated_at DESC
+             LIMIT 1"
+        )?
+        .query_row([title], note_from_row)
+        .optional()?;
+        Ok(note)
+    }
+
+    /// Notes edited in `from..until`, most recent first, at most `limit` of them, with the
+    /// number there are in all
+    pub fn list_edited_between(&self, from: DateTime<Utc>, until: DateTime<Utc>, limit: usize) -> Result<(Vec<Note>, usize)> {
+        let (from, until) = (from.to_rfc3339(), until.to_rfc3339());
+        let notes = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
+             FROM notes
+             WHERE updated_at >= ?1 AND updated_at < ?2 AND deleted_at IS NULL
+             ORDER BY updated_at DESC
+             LIMIT ?3"
+        )?
+        .query_map(params![from, until, limit], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+        let count: i64 = self.conn
+            .prepare_cached("SELECT COUNT(*) FROM notes WHERE updated_at >= ?1 AND updated_at < ?2 AND deleted_at IS NULL")?
+            .query_row([from, until], |row| row.get(0))?;
+        Ok((notes, count as usize))
+    }
+
+    /// Delete a note and its attachments for good
     pub fn delete_note(&self, id: &str) -> Result<()> {
-        self.conn.execute("DELETE FROM notes WHERE id = ?1", [id])?;
+        self.delete_attachments(id)?;
+        self.conn.prepare_cached("DELETE FROM favorites WHERE note_id = ?1")?.execute([id])?;
+        self.conn.prepare_cached("DELETE FROM notes WHERE id = ?1")?.execute([id])?;
+        Ok(())
+    }
+
+    /// Move a note to Recently deleted: it leaves every list but can be restored
+    pub fn trash_note(&self, id: &str) -> Result<()> {
+        self.trash_note_at(id, Utc::now())
+    }
+
+    /// `trash_note`, recording when it was deleted (sync applies deletions made elsewhere)
+    pub fn trash_note_at(&self, id: &str, deleted_at: DateTime<Utc>) -> Result<()> {
+        self.conn
+            .prepare_cached("UPDATE notes SET deleted_at = ?1 WHERE id = ?2")?
+            .execute(params![deleted_at.to_rfc3339(), id])?;
+        Ok(())
+    }
+
+    pub fn restore_note(&self, id: &str) -> Result<()> {
+        self.conn.prepare_cached("UPDATE notes SET deleted_at = NULL WHERE id = ?1")?.execute([id])?;
         Ok(())
     }
 
+    /// Recently deleted notes, last deleted first, at most `limit` of them, with the number
+    /// there are in all
+    pub fn list_trashed(&self, limit: usize) -> Result<(Vec<Note>, usize)> {
+        let notes = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
+             FROM notes
+             WHERE deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC
+             LIMIT ?1"
+        )?
+        .query_map([limit], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+        let count: i64 = self.conn
+            .prepare_cached("SELECT COUNT(*) FROM notes WHERE deleted_at IS NOT NULL")?
+            .query_row([], |row| row.get(0))?;
+        Ok((notes, count as usize))
+    }
+
+    /// Delete for good the notes that were moved to Recently deleted before `before`;
+    /// returns how many
+    pub fn purge_trash(&self, before: DateTime<Utc>) -> Result<usize> {
+        let ids: Vec<String> = self.conn
+            .prepare_cached("SELECT id FROM notes WHERE deleted_at IS NOT NULL AND deleted_at < ?1")?
+            .query_map([before.to_rfc3339()], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for id in &ids {
+            self.delete_note(id)?;
+        }
+        Ok(ids.len())
+    }
+
+    /// Store a file belonging to a note (e.g. an image from an imported note)
+    pub fn add_attachment(&self, note_id: &str, attachment: &Attachment) -> Result<()> {
+        self.conn.prepare_cached(
+            "INSERT INTO attachments (note_id, filename, mime, data, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?.execute(params![note_id, attachment.filename, attachment.mime, attachment.data, Utc::now().to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// A note's attachments, in the order they were added
+    pub fn attachments(&self, note_id: &str) -> Result<Vec<Attachment>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT filename, mime, data FROM attachments WHERE note_id = ?1 ORDER BY id",
+        )?;
+        let attachments = stmt
+            .query_map([note_id], |row| Ok(Attachment { filename: row.get(0)?, mime: row.get(1)?, data: row.get(2)? }))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(attachments)
+    }
+
+    /// A note's attachment by file name (the first one, if several share it)
+    pub fn attachment(&self, note_id: &str, filename: &str) -> Result<Option<Attachment>> {
+        let attachment = self.conn.prepare_cached(
+            "SELECT filename, mime, data FROM attachments WHERE note_id = ?1 AND filename = ?2 ORDER BY id LIMIT 1",
+        )?
+        .query_row([note_id, filename], |row| Ok(Attachment { filename: row.get(0)?, mime: row.get(1)?, data: row.get(2)? }))
+        .optional()?;
+        Ok(attachment)
+    }
+
+    pub fn delete_attachments(&self, note_id: &str) -> Result<()> {
+        self.conn.prepare_cached("DELETE FROM attachments WHERE note_id = ?1")?.execute([note_id])?;
+        Ok(())
+    }
+
+    pub fn count_attachments(&self, note_id: &str) -> Result<usize> {
+        let count: i64 = self.conn
+            .prepare_cached("SELECT COUNT(*) FROM attachments WHERE note_id = ?1")?
+            .query_row([note_id], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
     pub fn search_notes(&self, query: &str) -> Result<Vec<Note>> {
         let pattern = format!("%{}%", query);
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, tags, created_at, updated_at
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
              FROM notes
-             WHERE title LIKE ?1 OR content LIKE ?1 OR tags LIKE ?1
+             WHERE (title LIKE ?1 OR content LIKE ?1 OR tags LIKE ?1) AND deleted_at IS NULL
              ORDER BY updated_at DESC
              LIMIT 50"
         )?;
 
-        let notes = stmt.query_map([pattern], |row| {
-            let tags_json: String = row.get(3)?;
-            let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
-
-            Ok(Note {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                content: row.get(2)?,
-                tags,
-                created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
-                updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
-            })
-        })?
+        let notes = stmt.query_map([pattern], note_from_row)?
         .collect::<Result<Vec<_>, _>>()?;
 
         Ok(notes)
     }
 
+    /// Notes matching a saved search, most recently edited first: the first `limit` of them
+    /// and how many there are
+    pub fn find_matching(&self, query: &crate::saved_searches::Query, limit: usize) -> Result<(Vec<Note>, usize)> {
+        // Built per query, so these statements aren't cached
+        let like = |text: &str| format!("%{}%", text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+        let mut conditions = vec!["deleted_at IS NULL".to_string()];
+        let mut values: Vec<String> = Vec::new();
+        for word in &query.words {
+            values.push(like(word));
+            conditions.push(format!("(title LIKE ?{0} ESCAPE '\\' OR content LIKE ?{0} ESCAPE '\\')", values.len()));
+        }
+        for tag in &query.tags {
+            values.push(like(&serde_json::to_string(tag)?));
+            conditions.push(format!("tags LIKE ?{} ESCAPE '\\'", values.len()));
+        }
+        let (after, before) = query.edited_range();
+        if let Some(after) = after {
+            values.push(after.to_rfc3339());
+            conditions.push(format!("updated_at >= ?{}", values.len()));
+        }
+        if let Some(before) = before {
+            values.push(before.to_rfc3339());
+            conditions.push(format!("updated_at < ?{}", values.len()));
+        }
+        let filter = conditions.join(" AND ");
+
+        let notes = self.conn.prepare(&format!(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
+             FROM notes WHERE {} ORDER BY updated_at DESC LIMIT {}", filter, limit
+        ))?
+        .query_map(rusqlite::params_from_iter(&values), note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+        let count: i64 = self.conn
+            .prepare(&format!("SELECT COUNT(*) FROM notes WHERE {}", filter))?
+            .query_row(rusqlite::params_from_iter(&values), |row| row.get(0))?;
+        Ok((notes, count as usize))
+    }
+
+    /// Keep a search for the sidebar; its id
+    pub fn save_search(&self, name: &str, query: &str) -> Result<i64> {
+        self.conn
+            .prepare_cached("INSERT INTO saved_searches (name, query, created_at) VALUES (?1, ?2, ?3)")?
+            .execute(params![name, query, Utc::now().to_rfc3339()])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// (id, name, query) of every saved search, oldest first
+    pub fn list_saved_searches(&self) -> Result<Vec<(i64, String, String)>> {
+        let searches = self.conn
+            .prepare_cached("SELECT id, name, query FROM saved_searches ORDER BY id")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(searches)
+    }
+
+    pub fn delete_saved_search(&self, id: i64) -> Result<()> {
+        self.conn.prepare_cached("DELETE FROM saved_searches WHERE id = ?1")?.execute([id])?;
+        Ok(())
+    }
+
+    /// (slot, note id) of the favorite notes, by slot; notes in Recently deleted are left out
+    pub fn favorites(&self) -> Result<Vec<(u8, String)>> {
+        let favorites = self.conn
+            .prepare_cached(
+                "SELECT slot, note_id FROM favorites JOIN notes ON notes.id = favorites.note_id
+                 WHERE notes.deleted_at IS NULL ORDER BY slot",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(favorites)
+    }
+
+    /// Make a note the favorite in `slot` (taking the slot from any other note), or with None
+    /// no favorite at all
+    pub fn set_favorite(&self, id: &str, slot: Option<u8>) -> Result<()> {
+        self.conn.prepare_cached("DELETE FROM favorites WHERE note_id = ?1")?.execute([id])?;
+        if let Some(slot) = slot {
+            self.conn
+                .prepare_cached("INSERT OR REPLACE INTO favorites (slot, note_id) VALUES (?1, ?2)")?
+                .execute(params![slot, id])?;
+        }
+        Ok(())
+    }
+
+    /// Notes whose content contains `text` (ASCII letters in any case), most recently edited first
+    pub fn notes_containing(&self, text: &str) -> Result<Vec<Note>> {
+        let notes = self.conn.prepare_cached(
+            "SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal
+             FROM notes
+             WHERE instr(lower(content), lower(?1)) > 0 AND deleted_at IS NULL
+             ORDER BY updated_at DESC"
+        )?
+        .query_map([text], note_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+        Ok(notes)
+    }
+
     fn generate_id(&self, title: &str, timestamp: &DateTime<Utc>) -> String {
         let mut hasher = Sha256::new();
         hasher.update(title.as_bytes());
@@ -174,4 +516,148 @@ impl NotesDatabase {
         let result = hasher.finalize();
         format!("{:x}", result)[..8].to_string()
     }
-}
\ No newline at end of file
+
+    /// Sync bookkeeping: (id, changed_at, deleted) of every note as it is now. A note's
+    /// changed_at is when it was deleted if it is in Recently deleted, otherwise its updated_at.
+    pub fn sync_entries(&self) -> Result<Vec<(String, DateTime<Utc>, bool)>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, COALESCE(deleted_at, updated_at), deleted_at IS NOT NULL FROM notes")?;
+        let rows = stmt.query_map([], sync_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The same for every note as it was when last synced
+    pub fn sync_base(&self) -> Result<Vec<(String, DateTime<Utc>, bool)>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id, changed_at, deleted FROM sync_state")?;
+        let rows = stmt.query_map([], sync_row)?.collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    pub fn set_sync_base(&self, id: &str, changed_at: DateTime<Utc>, deleted: bool) -> Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR REPLACE INTO sync_state (id, changed_at, deleted) VALUES (?1, ?2, ?3)")?
+            .execute(params![id, changed_at.to_rfc3339(), deleted])?;
+        Ok(())
+    }
+}
+
+fn sync_row(row: &rusqlite::Row) -> rusqlite::Result<(String, DateTime<Utc>, bool)> {
+    Ok((row.get(0)?, row.get::<_, String>(1)?.parse().unwrap_or_else(|_| Utc::now()), row.get(2)?))
+}
+
+/// Build a Note from `SELECT id, title, content, tags, created_at, updated_at, accent, icon, word_goal`
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    let tags_json: String = row.get(3)?;
+    let tags: Vec<String> = serde_json::from_str(&tags_json).unwrap_or_default();
+
+    Ok(Note {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get(2)?,
+        tags,
+        created_at: row.get::<_, String>(4)?.parse().unwrap_or_else(|_| Utc::now()),
+        updated_at: row.get::<_, String>(5)?.parse().unwrap_or_else(|_| Utc::now()),
+        accent: row.get::<_, Option<String>>(6)?.as_deref().and_then(accent_from_hex),
+        icon: row.get(7)?,
+        word_goal: row.get(8)?,
+    })
+}
+
+/// (r, g, b) -> "#rrggbb", as accents are stored
+fn accent_to_hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn accent_from_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wal_readers_during_write() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("chonk-note-wal-test-{}.db", std::process::id()));
+        let db = NotesDatabase::init(Connection::open(&path)?, path.clone())?;
+        let mode: String = db.conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        assert_eq!(mode, "wal");
+        db.create_note("First".to_string(), String::new(), vec![])?;
+
+        // Another connection can still read while this one holds the write lock
+        db.conn.execute_batch("BEGIN IMMEDIATE")?;
+        let reader = Connection::open(&path)?;
+        let count: i64 = reader.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+        db.conn.execute_batch("COMMIT")?;
+
+        drop((reader, db));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_trash() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let kept = db.create_note("Kept".to_string(), String::new(), vec![])?;
+        let note = db.create_note("Binned".to_string(), String::new(), vec![])?;
+        let hour = chrono::Duration::hours(1);
+        assert_eq!(db.list_edited_between(Utc::now() - hour, Utc::now() + hour, 1)?.1, 2);
+
+        db.trash_note(&note.id)?;
+        assert_eq!(db.count_notes()?, 1);
+        assert_eq!(db.list_edited_between(Utc::now() - hour, Utc::now() + hour, 10)?.0[0].id, kept.id);
+        let (trashed, count) = db.list_trashed(10)?;
+        assert_eq!((trashed[0].id.as_str(), count), (note.id.as_str(), 1));
+
+        db.restore_note(&note.id)?;
+        assert_eq!(db.count_notes()?, 2);
+        db.trash_note(&note.id)?;
+        assert_eq!(db.purge_trash(Utc::now() - hour)?, 0);
+        assert_eq!(db.purge_trash(Utc::now() + hour)?, 1);
+        assert!(db.get_note(&note.id)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_note_style() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let note = db.create_note("Styled".to_string(), String::new(), vec![])?;
+        db.set_note_style(&note.id, Some((255, 99, 71)), Some("🐹"))?;
+        let stored = db.get_note(&note.id)?.unwrap();
+        assert_eq!((stored.accent, stored.icon.as_deref(), stored.updated_at), (Some((255, 99, 71)), Some("🐹"), note.updated_at));
+
+        db.set_note_style(&note.id, None, None)?;
+        let stored = db.get_note(&note.id)?.unwrap();
+        assert_eq!((stored.accent, stored.icon), (None, None));
+
+        db.set_word_goal(&note.id, Some(750))?;
+        assert_eq!(db.get_note(&note.id)?.and_then(|stored| stored.word_goal), Some(750));
+        Ok(())
+    }
+
+    #[test]
+    fn test_favorites() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let first = db.create_note("First".to_string(), String::new(), vec![])?;
+        let second = db.create_note("Second".to_string(), String::new(), vec![])?;
+        db.set_favorite(&first.id, Some(2))?;
+        db.set_favorite(&second.id, Some(1))?;
+        assert_eq!(db.favorites()?, [(1, second.id.clone()), (2, first.id.clone())]);
+
+        // A note has one slot, a slot one note; deleted notes drop out
+        db.set_favorite(&first.id, Some(1))?;
+        assert_eq!(db.favorites()?, [(1, first.id.clone())]);
+        db.set_favorite(&second.id, Some(3))?;
+        db.trash_note(&second.id)?;
+        assert_eq!(db.favorites()?.len(), 1);
+        db.delete_note(&first.id)?;
+        db.set_favorite(&second.id, None)?;
+        db.restore_note(&second.id)?;
+        assert!(db.favorites()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+// Schema versioning for notes.db
+// The schema version lives in SQLite's user_version pragma. On open, every migration newer
+// than it runs in order, each in its own transaction together with the version bump.
+// Before touching a database that already holds notes, a copy is written next to it
+// (notes.db.bak-v<old version>) so a failed or unwanted upgrade can be rolled back by hand.
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Ordered by version; append new migrations at the end and never edit shipped ones
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "notes table and recency index",
+        // IF NOT EXISTS: databases from before versioning already have these
+        sql: "CREATE TABLE IF NOT EXISTS notes (
+                  id TEXT PRIMARY KEY,
+                  title TEXT NOT NULL,
+                  content TEXT NOT NULL,
+                  tags TEXT NOT NULL,
+                  created_at DATETIME NOT NULL,
+                  updated_at DATETIME NOT NULL
+              );
+              CREATE INDEX IF NOT EXISTS idx_notes_updated ON notes(updated_at DESC);",
+    },
+    Migration {
+        version: 2,
+        description: "attachments table",
+        sql: "CREATE TABLE attachments (
+                  id INTEGER PRIMARY KEY,
+                  note_id TEXT NOT NULL,
+                  filename TEXT NOT NULL,
+                  mime TEXT NOT NULL,
+                  data BLOB NOT NULL,
+                  created_at DATETIME NOT NULL
+              );
+              CREATE INDEX idx_attachments_note ON attachments(note_id);",
+    },
+    Migration {
+        version: 3,
+        description: "note accent color and icon",
+        sql: "ALTER TABLE notes ADD COLUMN accent TEXT;
+              ALTER TABLE notes ADD COLUMN icon TEXT;",
+    },
+    Migration {
+        version: 4,
+        description: "recently deleted notes",
+        sql: "ALTER TABLE notes ADD COLUMN deleted_at DATETIME;",
+    },
+    Migration {
+        version: 5,
+        description: "saved searches",
+        sql: "CREATE TABLE saved_searches (
+                  id INTEGER PRIMARY KEY,
+                  name TEXT NOT NULL,
+                  query TEXT NOT NULL,
+                  created_at DATETIME NOT NULL
+              );",
+    },
+    Migration {
+        version: 6,
+        description: "note word count goal",
+        sql: "ALTER TABLE notes ADD COLUMN word_goal INTEGER;",
+    },
+    Migration {
+        version: 7,
+        description: "state of each note at the last sync",
+        sql: "CREATE TABLE sync_state (
+                  id TEXT PRIMARY KEY,
+                  changed_at DATETIME NOT NULL,
+                  deleted INTEGER NOT NULL
+              );",
+    },
+    Migration {
+        version: 8,
+        description: "favorite notes (Alt+1..9)",
+        sql: "CREATE TABLE favorites (
+                  slot INTEGER PRIMARY KEY,
+                  note_id TEXT NOT NULL UNIQUE
+              );",
+    },
+];
+
+/// Newest schema this build knows
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+}
+
+/// Bring the database up to the latest schema. `path` is the database file (empty for
+/// in-memory databases, which are never backed up).
+pub fn migrate(conn: &Connection, path: &Path) -> Result<()> {
+    let current = schema_version(conn)?;
+    if current > latest_version() {
+        anyhow::bail!(
+            "notes.db has schema version {}, but this chonk-note only knows up to {}; please update chonk-note",
+            current, latest_version()
+        );
+    }
+
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > current).collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    if has_notes_table(conn)? && !path.as_os_str().is_empty() {
+        let backup = backup_path(path, current);
+        // VACUUM INTO writes a consistent copy even while other connections are open
+        conn.execute("VACUUM INTO ?1", [backup.to_string_lossy()])
+            .with_context(|| format!("Backing up the database to {} before migrating", backup.display()))?;
+        log::info!("Backed up notes database to {}", backup.display());
+    }
+
+    for migration in pending {
+        log::info!("Migrating notes database to version {}: {}", migration.version, migration.description);
+        conn.execute_batch("BEGIN IMMEDIATE")?;
+        let applied = conn
+            .execute_batch(migration.sql)
+            .and_then(|_| conn.execute_batch(&format!("PRAGMA user_version = {}", migration.version)));
+        match applied {
+            Ok(()) => conn.execute_batch("COMMIT")?,
+            Err(e) => {
+                let _ = conn.execute_batch("ROLLBACK");
+                return Err(e).with_context(|| format!("Migration to schema version {} failed", migration.version));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_notes_table(conn: &Connection) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'notes'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// notes.db -> notes.db.bak-v3 (a numbered suffix is added if that exists already)
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let base = format!("{}.bak-v{}", path.display(), version);
+    let mut candidate = PathBuf::from(&base);
+    let mut n = 2;
+    while candidate.exists() {
+        candidate = PathBuf::from(format!("{}-{}", base, n));
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_unversioned_database() -> Result<()> {
+        // A database created before versioning: notes table, user_version 0
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE notes (id TEXT PRIMARY KEY, title TEXT NOT NULL, content TEXT NOT NULL,
+                                 tags TEXT NOT NULL, created_at DATETIME NOT NULL, updated_at DATETIME NOT NULL);
+             INSERT INTO notes VALUES ('a', 't', 'c', '[]', '2024-01-01T00:00:00Z', '2024-01-01T00:00:00Z');",
+        )?;
+
+        migrate(&conn, Path::new(""))?;
+        assert_eq!(schema_version(&conn)?, latest_version());
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))?;
+        assert_eq!(count, 1);
+
+        // Running again is a no-op; a newer schema is refused
+        migrate(&conn, Path::new(""))?;
+        conn.execute_batch(&format!("PRAGMA user_version = {}", latest_version() + 1))?;
+        assert!(migrate(&conn, Path::new("")).is_err());
+        Ok(())
+    }
+}
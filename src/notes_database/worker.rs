@@ -0,0 +1,147 @@
+// Background database thread
+// Autosaves, imports, backlink searches and task scans run on a thread with its own connection to notes.db, so a slow
+// write or a big import never holds up input or rendering. Requests go in over one channel
+// and responses come back over another, in order; the event loop waits on `ready()` and
+// applies them with `try_recv()`. Anything that must finish first (switching notes,
+// quitting) drains the outstanding responses with `recv()`.
+use super::{Note, NotesDatabase, SaveOutcome};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+pub enum Request {
+    /// update_note_checked for the open note
+    Save { note_id: String, expected_updated_at: DateTime<Utc>, title: String, content: String, tags: Vec<String> },
+    /// Read a text file, .enex or Apple Notes export and store it as new notes
+    Import { path: PathBuf },
+    /// Find the other notes mentioning the note titled `title`
+    FindMentions { note_id: String, title: String },
+    /// Gather the to-do items of every note but the open one
+    FindTasks { except_id: Option<String> },
+}
+
+pub enum Response {
+    /// `content` is what was written; `elapsed` is the time spent in the database
+    Saved { note_id: String, content: String, outcome: Result<SaveOutcome>, elapsed: Duration },
+    Imported(Result<Vec<Note>>),
+    Mentions { note_id: String, mentions: Result<Vec<crate::backlinks::Mention>> },
+    Tasks(Result<Vec<crate::tasks::Task>>),
+}
+
+/// Carry out a request against a connection (on the worker thread, or inline without one)
+pub fn execute(db: &NotesDatabase, request: Request) -> Response {
+    match request {
+        Request::Save { note_id, expected_updated_at, title, content, tags } => {
+            let started = Instant::now();
+            let outcome = db.update_note_checked(&note_id, &expected_updated_at, title, content.clone(), tags);
+            Response::Saved { note_id, content, outcome, elapsed: started.elapsed() }
+        }
+        Request::Import { path } => Response::Imported(crate::importers::import_path(db, &path)),
+        Request::FindMentions { note_id, title } => {
+            let mentions = crate::backlinks::find(db, &note_id, &title);
+            Response::Mentions { note_id, mentions }
+        }
+        Request::FindTasks { except_id } => Response::Tasks(crate::tasks::find(db, except_id.as_deref())),
+    }
+}
+
+pub struct DbWorker {
+    requests: mpsc::Sender<Request>,
+    responses: mpsc::Receiver<Response>,
+    wake: Arc<Notify>,
+    /// Requests sent whose responses haven't been received yet
+    in_flight: usize,
+}
+
+impl DbWorker {
+    /// Start the thread; it stops once the worker is dropped and the queue is empty
+    pub fn spawn(db: NotesDatabase) -> Result<Self> {
+        let (requests, request_rx) = mpsc::channel::<Request>();
+        let (response_tx, responses) = mpsc::channel();
+        let wake = Arc::new(Notify::new());
+        let notify = wake.clone();
+        std::thread::Builder::new().name("notes-db".to_string()).spawn(move || {
+            for request in request_rx {
+                if response_tx.send(execute(&db, request)).is_err() {
+                    break;
+                }
+                notify.notify_one();
+            }
+        })?;
+        Ok(Self { requests, responses, wake, in_flight: 0 })
+    }
+
+    pub fn send(&mut self, request: Request) -> Result<()> {
+        self.requests.send(request).map_err(|_| anyhow::anyhow!("Database thread has stopped"))?;
+        self.in_flight += 1;
+        Ok(())
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.in_flight > 0
+    }
+
+    /// Next finished response, if any
+    pub fn try_recv(&mut self) -> Option<Response> {
+        let response = self.responses.try_recv().ok()?;
+        self.in_flight -= 1;
+        Some(response)
+    }
+
+    /// Wait for the next response; None if nothing is outstanding
+    pub fn recv(&mut self) -> Option<Response> {
+        if self.in_flight == 0 {
+            return None;
+        }
+        let response = self.responses.recv().ok();
+        self.in_flight = if response.is_some() { self.in_flight - 1 } else { 0 };
+        response
+    }
+
+    /// Resolves when a response has arrived since the last wake-up
+    pub async fn ready(&self) {
+        self.wake.notified().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_run_in_order() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("chonk-note-worker-test-{}.db", std::process::id()));
+        let db = NotesDatabase::init(rusqlite::Connection::open(&path)?, path.clone())?;
+        let note = db.create_note("Note".to_string(), String::new(), vec![])?;
+        let mut worker = DbWorker::spawn(db.reopen()?)?;
+
+        let import = path.with_extension("txt");
+        std::fs::write(&import, "imported\n")?;
+        worker.send(Request::Save {
+            note_id: note.id.clone(), expected_updated_at: note.updated_at,
+            title: note.title.clone(), content: "saved".to_string(), tags: vec![],
+        })?;
+        worker.send(Request::Import { path: import.clone() })?;
+        assert!(worker.is_busy());
+
+        assert!(matches!(worker.recv(), Some(Response::Saved { outcome: Ok(SaveOutcome::Saved(_)), .. })));
+        match worker.recv() {
+            Some(Response::Imported(Ok(imported))) => assert_eq!(imported[0].content, "imported"),
+            _ => panic!("expected an import"),
+        }
+        assert!(worker.recv().is_none());
+        // The other connection sees the background writes
+        assert_eq!(db.get_note(&note.id)?.map(|n| n.content).as_deref(), Some("saved"));
+
+        drop((worker, db));
+        let _ = std::fs::remove_file(&import);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+        Ok(())
+    }
+}
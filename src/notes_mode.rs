@@ -1,17 +1,30 @@
 // Notes mode for Chonker7
 use anyhow::Result;
+use crate::notes_database::worker::DbWorker;
 use crate::notes_database::{NotesDatabase, Note};
 
 pub struct NotesMode {
     pub db: NotesDatabase,
+    /// Background connection for autosaves and imports (without one they run inline)
+    pub worker: Option<DbWorker>,
     pub current_note: Option<Note>,
 }
 
 impl NotesMode {
     pub fn new() -> Result<Self> {
-        Ok(Self {
-            db: NotesDatabase::new()?,
-            current_note: None,
-        })
+        let db = NotesDatabase::new()?;
+        let worker = match db.reopen().and_then(DbWorker::spawn) {
+            Ok(worker) => Some(worker),
+            Err(e) => {
+                log::warn!("Database thread unavailable, saving on the main thread: {}", e);
+                None
+            }
+        };
+        Ok(Self { db, worker, current_note: None })
+    }
+
+    #[cfg(test)]
+    pub fn with_db(db: NotesDatabase) -> Self {
+        Self { db, worker: None, current_note: None }
     }
 }
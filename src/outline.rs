@@ -0,0 +1,105 @@
+// Outline (table of contents) for the current note
+// Headings are Markdown-style lines: the first non-space character on the
+// row is '#', and the number of leading '#' gives the level.
+use crate::chunked_grid::ChunkedGrid;
+
+/// A detected heading on the canvas
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heading {
+    pub row: usize,
+    pub col: usize,
+    pub level: usize,
+    pub text: String,
+}
+
+/// Cached list of headings, rebuilt lazily after the grid changes
+pub struct Outline {
+    pub headings: Vec<Heading>,
+    pub scroll: usize,
+    stale: bool,
+}
+
+impl Outline {
+    pub fn new() -> Self {
+        Self {
+            headings: Vec::new(),
+            scroll: 0,
+            stale: true,
+        }
+    }
+
+    /// Mark the outline as out of date (called whenever the note is edited)
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Rebuild headings if the grid changed since the last refresh
+    pub fn refresh(&mut self, grid: &ChunkedGrid) {
+        if !self.stale {
+            return;
+        }
+        self.headings = detect_headings(grid);
+        self.scroll = self.scroll.min(self.headings.len().saturating_sub(1));
+        self.stale = false;
+    }
+
+    /// Index of the heading the cursor is currently under (last heading at or above the row)
+    pub fn current_index(&self, cursor_row: usize) -> Option<usize> {
+        self.headings.iter().rposition(|h| h.row <= cursor_row)
+    }
+}
+
+/// Parse a single row into a heading, if it is one
+pub fn parse_heading(grid: &ChunkedGrid, row: usize) -> Option<Heading> {
+    let (min_col, max_col) = grid.get_line_bounds(row)?;
+    if grid.get(row, min_col) != '#' {
+        return None;
+    }
+
+    let line = grid.get_line(row, min_col, max_col);
+    let level = line.chars().take_while(|&c| c == '#').count();
+    let text = line[level..].trim().to_string();
+
+    // "#" alone or "#tag" are not headings
+    if text.is_empty() || !line[level..].starts_with(' ') {
+        return None;
+    }
+
+    Some(Heading { row, col: min_col, level, text })
+}
+
+/// Scan every row of the grid for headings, in document order
+pub fn detect_headings(grid: &ChunkedGrid) -> Vec<Heading> {
+    let Some((min_row, _, max_row, _)) = grid.bounds() else {
+        return Vec::new();
+    };
+
+    (min_row..=max_row)
+        .filter_map(|row| parse_heading(grid, row))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_headings() {
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(0, 0, "# Title");
+        grid.insert_at(2, 4, "## Indented section");
+        grid.insert_at(3, 0, "#hashtag is not a heading");
+        grid.insert_at(5, 0, "plain text");
+
+        let headings = detect_headings(&grid);
+        assert_eq!(headings.len(), 2);
+        assert_eq!(headings[0].text, "Title");
+        assert_eq!(headings[0].level, 1);
+        assert_eq!((headings[1].row, headings[1].col, headings[1].level), (2, 4, 2));
+
+        let mut outline = Outline::new();
+        outline.refresh(&grid);
+        assert_eq!(outline.current_index(0), Some(0));
+        assert_eq!(outline.current_index(4), Some(1));
+    }
+}
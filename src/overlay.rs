@@ -0,0 +1,775 @@
+// Overlay widgets drawn over the editor: popup menus, list pickers, prompts, confirmations and
+// tooltips
+// The OverlayStack sends keys and mouse events to the topmost widget; a widget that finishes
+// hands back an action for the app to run, so callers never deal with drawing or hit testing.
+use crate::config::{colors, rgb_bg, rgb_fg};
+use crate::kitty_native::{KeyCode, KeyEvent, MouseButton, MouseEvent};
+use crate::line_edit::LineEdit;
+use crate::term_caps::{glyph, rule};
+
+/// What a widget did with an input event
+pub enum Outcome<A> {
+    /// Handled; the widget stays open
+    Pending,
+    /// Dismissed without an action
+    Close,
+    /// Dismissed, and the event should still reach the app (e.g. right-click elsewhere)
+    Pass,
+    /// Finished with an action for the app
+    Done(A),
+}
+
+pub trait Widget<A> {
+    fn render(&self, term_width: u16, term_height: u16);
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A>;
+    /// Mouse coordinates are 0-based screen cells
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A>;
+}
+
+/// Result of routing an event through the stack
+pub enum Routed<A> {
+    /// An overlay used the event
+    Consumed,
+    /// An overlay finished; run this action
+    Action(A),
+    /// No overlay wanted the event; handle it normally
+    NotHandled,
+}
+
+/// Open overlays, topmost last; only the topmost receives input
+pub struct OverlayStack<A> {
+    layers: Vec<Box<dyn Widget<A>>>,
+}
+
+impl<A> Default for OverlayStack<A> {
+    fn default() -> Self {
+        Self { layers: Vec::new() }
+    }
+}
+
+impl<A> OverlayStack<A> {
+    pub fn push(&mut self, widget: impl Widget<A> + 'static) {
+        self.layers.push(Box::new(widget));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Draw every layer, bottom to top
+    pub fn render(&self, term_width: u16, term_height: u16) {
+        for layer in &self.layers {
+            layer.render(term_width, term_height);
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Routed<A> {
+        let Some(top) = self.layers.last_mut() else {
+            return Routed::NotHandled;
+        };
+        let outcome = top.handle_key(key);
+        self.finish(outcome)
+    }
+
+    pub fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Routed<A> {
+        let Some(top) = self.layers.last_mut() else {
+            return Routed::NotHandled;
+        };
+        let outcome = top.handle_mouse(event, term_width, term_height);
+        self.finish(outcome)
+    }
+
+    fn finish(&mut self, outcome: Outcome<A>) -> Routed<A> {
+        match outcome {
+            Outcome::Pending => Routed::Consumed,
+            Outcome::Close => {
+                self.layers.pop();
+                Routed::Consumed
+            }
+            Outcome::Pass => {
+                self.layers.pop();
+                Routed::NotHandled
+            }
+            Outcome::Done(action) => {
+                self.layers.pop();
+                Routed::Action(action)
+            }
+        }
+    }
+}
+
+/// A box centered on the screen (0-based top-left corner)
+pub fn centered(width: u16, height: u16, term_width: u16, term_height: u16) -> (u16, u16) {
+    (term_width.saturating_sub(width) / 2, term_height.saturating_sub(height) / 2)
+}
+
+/// Draw a bordered box with a title in the top border
+pub fn draw_box(x: u16, y: u16, width: u16, height: u16, title: &str) {
+    let bg = rgb_bg(250, 250, 250);
+    let fg = rgb_fg(0, 0, 0);
+    let inner = width.saturating_sub(2) as usize;
+    let title: String = title.chars().take(inner.saturating_sub(2)).collect();
+    let side = glyph('│');
+    let top = format!("{} {} {}{}", glyph('┌'), title, rule(inner.saturating_sub(title.chars().count() + 2)), glyph('┐'));
+    print!("\x1b[{};{}H{}{}\x1b[1m{}\x1b[0m", y + 1, x + 1, bg, fg, top);
+    for row in 1..height.saturating_sub(1) {
+        print!("\x1b[{};{}H{}{}{}{}{}\x1b[0m", y + row + 1, x + 1, bg, fg, side, " ".repeat(inner), side);
+    }
+    print!("\x1b[{};{}H{}{}{}{}{}\x1b[0m", y + height, x + 1, bg, fg, glyph('└'), rule(inner), glyph('┘'));
+}
+
+fn is_click(event: &MouseEvent) -> bool {
+    matches!(event, MouseEvent { button: Some(MouseButton::Left), is_press: true, is_drag: false, .. })
+}
+
+fn inside(event: &MouseEvent, x: u16, y: u16, width: u16, height: u16) -> bool {
+    event.x >= x && event.x < x + width && event.y >= y && event.y < y + height
+}
+
+/// Single-line text input; Enter submits, Esc cancels
+pub struct Prompt<A> {
+    pub title: String,
+    pub input: LineEdit,
+    submit: Box<dyn Fn(String) -> A>,
+}
+
+impl<A> Prompt<A> {
+    pub fn new(title: &str, submit: impl Fn(String) -> A + 'static) -> Self {
+        Self { title: title.to_string(), input: LineEdit::default(), submit: Box::new(submit) }
+    }
+
+    const WIDTH: u16 = 60;
+    const HEIGHT: u16 = 4;
+}
+
+impl<A> Widget<A> for Prompt<A> {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let (x, y) = centered(Self::WIDTH, Self::HEIGHT, term_width, term_height);
+        draw_box(x, y, Self::WIDTH, Self::HEIGHT, &self.title);
+        let inner = Self::WIDTH as usize - 4;
+        let bg = rgb_bg(250, 250, 250);
+        let style = format!("{}{}", bg, rgb_fg(0, 0, 0));
+        print!("\x1b[{};{}H{}\x1b[0m", y + 2, x + 3, self.input.render(inner, &style));
+        let hint_fg = rgb_fg(120, 120, 120);
+        print!("\x1b[{};{}H{}{}Enter to confirm, Esc to cancel\x1b[0m", y + 3, x + 3, bg, hint_fg);
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        match key.code {
+            KeyCode::Enter => Outcome::Done((self.submit)(self.input.take())),
+            KeyCode::Esc => Outcome::Close,
+            _ => {
+                self.input.handle_key(key);
+                Outcome::Pending
+            }
+        }
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A> {
+        let (x, y) = centered(Self::WIDTH, Self::HEIGHT, term_width, term_height);
+        if is_click(event) && !inside(event, x, y, Self::WIDTH, Self::HEIGHT) {
+            return Outcome::Close;
+        }
+        Outcome::Pending
+    }
+}
+
+/// Yes/No question; Y or Enter confirms, N or Esc cancels
+pub struct Confirm<A> {
+    pub message: String,
+    on_yes: Option<A>,
+}
+
+impl<A> Confirm<A> {
+    pub fn new(message: &str, on_yes: A) -> Self {
+        Self { message: message.to_string(), on_yes: Some(on_yes) }
+    }
+
+    fn size(&self) -> (u16, u16) {
+        ((self.message.chars().count() as u16 + 6).max(30), 5)
+    }
+
+    fn yes(&mut self) -> Outcome<A> {
+        self.on_yes.take().map(Outcome::Done).unwrap_or(Outcome::Close)
+    }
+}
+
+impl<A> Widget<A> for Confirm<A> {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let (width, height) = self.size();
+        let (x, y) = centered(width, height, term_width, term_height);
+        draw_box(x, y, width, height, "Confirm");
+        let bg = rgb_bg(250, 250, 250);
+        let fg = rgb_fg(0, 0, 0);
+        let button_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+        let button_fg = rgb_fg(255, 255, 255);
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 3, bg, fg, self.message);
+        print!("\x1b[{};{}H{}{} [Y]es \x1b[0m{}  {}{} [N]o \x1b[0m",
+            y + 4, x + 3, button_bg, button_fg, bg, button_bg, button_fg);
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        match key.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => self.yes(),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => Outcome::Close,
+            _ => Outcome::Pending,
+        }
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A> {
+        if !is_click(event) {
+            return Outcome::Pending;
+        }
+        let (width, height) = self.size();
+        let (x, y) = centered(width, height, term_width, term_height);
+        if !inside(event, x, y, width, height) {
+            return Outcome::Close;
+        }
+        // Buttons: " [Y]es " at x+2..x+9, " [N]o " at x+11..x+17 on the button row
+        match (event.y == y + 3, event.x.saturating_sub(x)) {
+            (true, 2..=8) => self.yes(),
+            (true, 11..=16) => Outcome::Close,
+            _ => Outcome::Pending,
+        }
+    }
+}
+
+/// Filterable list; typing narrows the items (fuzzily, best matches first), Enter or a click picks one
+pub struct ListPicker<A> {
+    pub title: String,
+    /// (label, hint shown right-aligned, action)
+    items: Vec<(String, String, A)>,
+    pub query: String,
+    /// Indices into `items` that match the query
+    matches: Vec<usize>,
+    pub selected: usize,
+    scroll: usize,
+}
+
+impl<A> ListPicker<A> {
+    const WIDTH: u16 = 60;
+    const VISIBLE_ROWS: usize = 12;
+
+    pub fn new(title: &str, items: Vec<(String, A)>) -> Self {
+        Self::with_hints(title, items.into_iter().map(|(label, action)| (label, String::new(), action)).collect())
+    }
+
+    /// Items with a hint (e.g. a key binding) shown after the label
+    pub fn with_hints(title: &str, items: Vec<(String, String, A)>) -> Self {
+        let matches = (0..items.len()).collect();
+        Self { title: title.to_string(), items, query: String::new(), matches, selected: 0, scroll: 0 }
+    }
+
+    /// Items whose label fuzzily matches the query, best first (ties keep their order)
+    fn refilter(&mut self) {
+        let mut scored: Vec<(i32, usize)> = (0..self.items.len())
+            .filter_map(|i| fuzzy_score(&self.query, &self.items[i].0).map(|score| (score, i)))
+            .collect();
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+        self.scroll = 0;
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.matches.len().saturating_sub(1));
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + Self::VISIBLE_ROWS {
+            self.scroll = self.selected + 1 - Self::VISIBLE_ROWS;
+        }
+    }
+
+    fn pick(&mut self, index: usize) -> Outcome<A> {
+        match self.matches.get(index) {
+            Some(&item) => Outcome::Done(self.items.swap_remove(item).2),
+            None => Outcome::Pending,
+        }
+    }
+
+    /// Title row, query row, list rows and the bottom border
+    fn height(&self) -> u16 {
+        Self::VISIBLE_ROWS as u16 + 4
+    }
+}
+
+impl<A> Widget<A> for ListPicker<A> {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let height = self.height();
+        let (x, y) = centered(Self::WIDTH, height, term_width, term_height);
+        draw_box(x, y, Self::WIDTH, height, &self.title);
+
+        let bg = rgb_bg(250, 250, 250);
+        let fg = rgb_fg(0, 0, 0);
+        let hint_fg = rgb_fg(120, 120, 120);
+        let selected_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+        let selected_fg = rgb_fg(255, 255, 255);
+        let inner = Self::WIDTH as usize - 4;
+
+        print!("\x1b[{};{}H{}{}> {}\x1b[7m \x1b[0m{}{} {}/{}\x1b[0m",
+            y + 2, x + 3, bg, fg, self.query, bg, hint_fg, self.matches.len(), self.items.len());
+
+        for (row, &item) in self.matches.iter().skip(self.scroll).take(Self::VISIBLE_ROWS).enumerate() {
+            let (label, hint, _) = &self.items[item];
+            let hint_width = hint.chars().count();
+            let label_width = inner.saturating_sub(if hint_width > 0 { hint_width + 1 } else { 0 });
+            let label: String = label.chars().take(label_width).collect();
+            let selected = self.scroll + row == self.selected;
+            let (row_bg, row_fg) = if selected { (&selected_bg, &selected_fg) } else { (&bg, &fg) };
+            let row_hint_fg = if selected { &selected_fg } else { &hint_fg };
+            print!("\x1b[{};{}H{}{}{:<width$}{}{}\x1b[0m",
+                y + 3 + row as u16, x + 3, row_bg, row_fg, label, row_hint_fg, hint, width = inner - hint_width.min(inner));
+        }
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        match key.code {
+            KeyCode::Enter => self.pick(self.selected),
+            KeyCode::Esc => Outcome::Close,
+            KeyCode::Up => {
+                self.select(self.selected.saturating_sub(1));
+                Outcome::Pending
+            }
+            KeyCode::Down => {
+                self.select(self.selected + 1);
+                Outcome::Pending
+            }
+            KeyCode::PageUp => {
+                self.select(self.selected.saturating_sub(Self::VISIBLE_ROWS));
+                Outcome::Pending
+            }
+            KeyCode::PageDown => {
+                self.select(self.selected + Self::VISIBLE_ROWS);
+                Outcome::Pending
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.refilter();
+                Outcome::Pending
+            }
+            KeyCode::Char(c) if !key.modifiers.ctrl && !key.modifiers.cmd => {
+                self.query.push(c);
+                self.refilter();
+                Outcome::Pending
+            }
+            _ => Outcome::Pending,
+        }
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A> {
+        let height = self.height();
+        let (x, y) = centered(Self::WIDTH, height, term_width, term_height);
+        match event.button {
+            Some(MouseButton::ScrollUp) => self.select(self.selected.saturating_sub(1)),
+            Some(MouseButton::ScrollDown) => self.select(self.selected + 1),
+            _ if is_click(event) => {
+                if !inside(event, x, y, Self::WIDTH, height) {
+                    return Outcome::Close;
+                }
+                let first_row = y + 2;
+                if event.y >= first_row && event.y < first_row + Self::VISIBLE_ROWS as u16 {
+                    return self.pick(self.scroll + (event.y - first_row) as usize);
+                }
+            }
+            _ => {}
+        }
+        Outcome::Pending
+    }
+}
+
+/// One choice in a SwatchPicker
+pub enum Swatch {
+    /// A patch of this color
+    Color((u8, u8, u8)),
+    /// A short word (e.g. "Bold")
+    Word(&'static str),
+}
+
+impl Swatch {
+    fn width(&self) -> u16 {
+        match self {
+            Swatch::Color(_) => 2,
+            Swatch::Word(word) => word.chars().count() as u16,
+        }
+    }
+}
+
+/// Small palette: labelled rows of swatches, chosen with the arrow keys and Enter or a click
+pub struct SwatchPicker<A> {
+    pub title: String,
+    rows: Vec<(String, Vec<(Swatch, A)>)>,
+    /// (row, swatch)
+    pub selected: (usize, usize),
+}
+
+impl<A> SwatchPicker<A> {
+    const LABEL_WIDTH: u16 = 11;
+
+    pub fn new(title: &str, rows: Vec<(String, Vec<(Swatch, A)>)>) -> Self {
+        Self { title: title.to_string(), rows, selected: (0, 0) }
+    }
+
+    /// Left edges of a row's swatches, relative to the first; each is drawn between brackets
+    fn offsets(&self, row: usize) -> Vec<u16> {
+        let mut x = 0;
+        self.rows[row].1.iter().map(|(swatch, _)| {
+            let left = x;
+            x += swatch.width() + 3;
+            left
+        }).collect()
+    }
+
+    fn width(&self) -> u16 {
+        let widest = (0..self.rows.len())
+            .map(|row| self.rows[row].1.iter().map(|(swatch, _)| swatch.width() + 3).sum::<u16>())
+            .max()
+            .unwrap_or(0);
+        (Self::LABEL_WIDTH + widest + 4).max(self.title.chars().count() as u16 + 6)
+    }
+
+    /// Swatch rows, the key hint and the borders
+    fn height(&self) -> u16 {
+        self.rows.len() as u16 + 3
+    }
+
+    fn select(&mut self, row: usize, col: usize) {
+        let row = row.min(self.rows.len().saturating_sub(1));
+        let len = self.rows.get(row).map_or(0, |(_, swatches)| swatches.len());
+        self.selected = (row, col.min(len.saturating_sub(1)));
+    }
+
+    fn pick(&mut self, (row, col): (usize, usize)) -> Outcome<A> {
+        if self.rows.get(row).is_none_or(|(_, swatches)| col >= swatches.len()) {
+            return Outcome::Pending;
+        }
+        Outcome::Done(self.rows.swap_remove(row).1.swap_remove(col).1)
+    }
+}
+
+impl<A> Widget<A> for SwatchPicker<A> {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let (width, height) = (self.width(), self.height());
+        let (x, y) = centered(width, height, term_width, term_height);
+        draw_box(x, y, width, height, &self.title);
+
+        let bg = rgb_bg(250, 250, 250);
+        let fg = rgb_fg(0, 0, 0);
+        let hint_fg = rgb_fg(120, 120, 120);
+        let left = x + 2 + Self::LABEL_WIDTH;
+        for (row, (label, swatches)) in self.rows.iter().enumerate() {
+            let term_row = y + 2 + row as u16;
+            let label: String = label.chars().take(Self::LABEL_WIDTH as usize - 1).collect();
+            print!("\x1b[{};{}H{}{}{}", term_row, x + 3, bg, fg, label);
+            for (col, ((swatch, _), offset)) in swatches.iter().zip(self.offsets(row)).enumerate() {
+                let (open, close) = if self.selected == (row, col) { ('[', ']') } else { (' ', ' ') };
+                let inside = match swatch {
+                    Swatch::Color((r, g, b)) => format!("{}  {}", rgb_bg(*r, *g, *b), bg),
+                    Swatch::Word(word) => word.to_string(),
+                };
+                print!("\x1b[{};{}H{}{}{}{}{}", term_row, left + offset + 1, bg, fg, open, inside, close);
+            }
+        }
+        print!("\x1b[{};{}H{}{}←→↑↓ choose, Enter apply, Esc cancel\x1b[0m", y + height - 1, x + 3, bg, hint_fg);
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        let (row, col) = self.selected;
+        match key.code {
+            KeyCode::Enter => return self.pick(self.selected),
+            KeyCode::Esc => return Outcome::Close,
+            KeyCode::Left => self.select(row, col.saturating_sub(1)),
+            KeyCode::Right => self.select(row, col + 1),
+            KeyCode::Up => self.select(row.saturating_sub(1), col),
+            KeyCode::Down => self.select(row + 1, col),
+            _ => {}
+        }
+        Outcome::Pending
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A> {
+        let (width, height) = (self.width(), self.height());
+        let (x, y) = centered(width, height, term_width, term_height);
+        if !is_click(event) {
+            return Outcome::Pending;
+        }
+        if !inside(event, x, y, width, height) {
+            return Outcome::Close;
+        }
+        let row = event.y.saturating_sub(y + 1) as usize;
+        let left = x + 2 + Self::LABEL_WIDTH;
+        if event.y <= y || row >= self.rows.len() || event.x < left {
+            return Outcome::Pending;
+        }
+        let clicked = self.offsets(row).iter().zip(&self.rows[row].1).position(|(&offset, (swatch, _))| {
+            event.x >= left + offset && event.x < left + offset + swatch.width() + 2
+        });
+        match clicked {
+            Some(col) => self.pick((row, col)),
+            None => Outcome::Pending,
+        }
+    }
+}
+
+/// Small box of facts beside what the pointer is over. It takes no input: any key or mouse
+/// event closes it and still reaches the app, which puts it back while the pointer stays.
+pub struct Tooltip {
+    pub title: String,
+    pub lines: Vec<String>,
+    /// Top-left corner (0-based), moved in to fit on the screen
+    x: u16,
+    y: u16,
+}
+
+impl Tooltip {
+    pub fn new(title: &str, lines: Vec<String>, x: u16, y: u16) -> Self {
+        Self { title: title.to_string(), lines, x, y }
+    }
+
+    fn size(&self) -> (u16, u16) {
+        let widest = self.lines.iter().map(|line| line.chars().count() + 4).max().unwrap_or(0);
+        (widest.max(self.title.chars().count() + 6) as u16, self.lines.len() as u16 + 2)
+    }
+}
+
+impl<A> Widget<A> for Tooltip {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let (width, height) = self.size();
+        let width = width.min(term_width);
+        let x = self.x.min(term_width.saturating_sub(width));
+        let y = self.y.min(term_height.saturating_sub(height + 1));
+        draw_box(x, y, width, height, &self.title);
+        let bg = rgb_bg(250, 250, 250);
+        let fg = rgb_fg(0, 0, 0);
+        for (row, line) in self.lines.iter().enumerate() {
+            let line: String = line.chars().take(width.saturating_sub(4) as usize).collect();
+            print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2 + row as u16, x + 3, bg, fg, line);
+        }
+    }
+
+    fn handle_key(&mut self, _key: &KeyEvent) -> Outcome<A> {
+        Outcome::Pass
+    }
+
+    fn handle_mouse(&mut self, _event: &MouseEvent, _term_width: u16, _term_height: u16) -> Outcome<A> {
+        Outcome::Pass
+    }
+}
+
+/// A Dashboard column: heading, then entries as (label, hint, action)
+pub type Column<A> = (String, Vec<(String, String, A)>);
+
+/// Start screen: columns of entries under a heading each (recent notes, favorites, actions),
+/// chosen with the arrow keys and Enter or a click. Fills most of the screen.
+pub struct Dashboard<A> {
+    pub title: String,
+    /// Line under the title (e.g. the date)
+    pub subtitle: String,
+    columns: Vec<Column<A>>,
+    /// (column, entry)
+    pub selected: (usize, usize),
+    /// What Esc does, if anything beyond closing
+    dismiss: Option<A>,
+}
+
+impl<A> Dashboard<A> {
+    const COLUMN_WIDTH: u16 = 34;
+    /// Rows above the first entry: border, subtitle, gap, heading, rule
+    const HEADER_ROWS: u16 = 5;
+
+    pub fn new(title: &str, subtitle: &str, columns: Vec<Column<A>>, dismiss: Option<A>) -> Self {
+        let first = columns.iter().position(|(_, entries)| !entries.is_empty()).unwrap_or(0);
+        Self { title: title.to_string(), subtitle: subtitle.to_string(), columns, selected: (first, 0), dismiss }
+    }
+
+    /// (x, y, width, height, column width) on this screen
+    fn frame(&self, term_width: u16, term_height: u16) -> (u16, u16, u16, u16, u16) {
+        let count = self.columns.len().max(1) as u16;
+        let width = (count * Self::COLUMN_WIDTH + 4).min(term_width.saturating_sub(2));
+        let longest = self.columns.iter().map(|(_, entries)| entries.len().max(1)).max().unwrap_or(1) as u16;
+        let height = (longest + Self::HEADER_ROWS + 3).min(term_height.saturating_sub(2));
+        let (x, y) = centered(width, height, term_width, term_height);
+        (x, y, width, height, width.saturating_sub(4) / count)
+    }
+
+    /// Rows of entries that fit
+    fn visible_rows(height: u16) -> usize {
+        height.saturating_sub(Self::HEADER_ROWS + 3) as usize
+    }
+
+    fn select(&mut self, column: usize, entry: usize) {
+        let column = column.min(self.columns.len().saturating_sub(1));
+        let len = self.columns.get(column).map_or(0, |(_, entries)| entries.len());
+        if len > 0 {
+            self.selected = (column, entry.min(len - 1));
+        }
+    }
+
+    /// The next column with entries to the left or right
+    fn step_column(&mut self, right: bool) {
+        let (column, entry) = self.selected;
+        let mut candidates: Box<dyn Iterator<Item = usize>> =
+            if right { Box::new(column + 1..self.columns.len()) } else { Box::new((0..column).rev()) };
+        if let Some(next) = candidates.find(|&c| !self.columns[c].1.is_empty()) {
+            self.select(next, entry);
+        }
+    }
+
+    fn pick(&mut self, (column, entry): (usize, usize)) -> Outcome<A> {
+        if self.columns.get(column).is_none_or(|(_, entries)| entry >= entries.len()) {
+            return Outcome::Pending;
+        }
+        Outcome::Done(self.columns.swap_remove(column).1.swap_remove(entry).2)
+    }
+}
+
+impl<A> Widget<A> for Dashboard<A> {
+    fn render(&self, term_width: u16, term_height: u16) {
+        let (x, y, width, height, column_width) = self.frame(term_width, term_height);
+        draw_box(x, y, width, height, &self.title);
+
+        let bg = rgb_bg(250, 250, 250);
+        let fg = rgb_fg(0, 0, 0);
+        let hint_fg = rgb_fg(120, 120, 120);
+        let selected_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+        let selected_fg = rgb_fg(255, 255, 255);
+        let inner = column_width.saturating_sub(2) as usize;
+
+        let subtitle: String = self.subtitle.chars().take(width.saturating_sub(4) as usize).collect();
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 2, x + 3, bg, hint_fg, subtitle);
+        for (column, (heading, entries)) in self.columns.iter().enumerate() {
+            let left = x + 3 + column as u16 * column_width;
+            let heading: String = heading.chars().take(inner).collect();
+            print!("\x1b[{};{}H{}{}\x1b[1m{}\x1b[0m", y + 4, left, bg, fg, heading);
+            print!("\x1b[{};{}H{}{}{}\x1b[0m", y + 5, left, bg, hint_fg, rule(inner));
+            if entries.is_empty() {
+                print!("\x1b[{};{}H{}{}(none)\x1b[0m", y + 6, left, bg, hint_fg);
+            }
+            for (row, (label, hint, _)) in entries.iter().take(Self::visible_rows(height)).enumerate() {
+                let hint_width = hint.chars().count().min(inner);
+                let label_width = inner.saturating_sub(if hint_width > 0 { hint_width + 1 } else { 0 });
+                let label: String = label.chars().take(label_width).collect();
+                let selected = self.selected == (column, row);
+                let (row_bg, row_fg, row_hint_fg) = if selected { (&selected_bg, &selected_fg, &selected_fg) } else { (&bg, &fg, &hint_fg) };
+                print!("\x1b[{};{}H{}{}{:<width$}{}{}\x1b[0m",
+                    y + 6 + row as u16, left, row_bg, row_fg, label, row_hint_fg, hint, width = inner - hint_width);
+            }
+        }
+        print!("\x1b[{};{}H{}{}←→↑↓ choose, Enter open, Esc close\x1b[0m", y + height - 1, x + 3, bg, hint_fg);
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        let (column, entry) = self.selected;
+        match key.code {
+            KeyCode::Enter => return self.pick(self.selected),
+            KeyCode::Esc => return self.dismiss.take().map_or(Outcome::Close, Outcome::Done),
+            KeyCode::Up => self.select(column, entry.saturating_sub(1)),
+            KeyCode::Down => self.select(column, entry + 1),
+            KeyCode::Left => self.step_column(false),
+            KeyCode::Right | KeyCode::Tab => self.step_column(true),
+            _ => {}
+        }
+        Outcome::Pending
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, term_width: u16, term_height: u16) -> Outcome<A> {
+        let (x, y, _, height, column_width) = self.frame(term_width, term_height);
+        let first_row = y + Self::HEADER_ROWS;
+        if !is_click(event) || event.y < first_row || event.x < x + 2 || column_width == 0 {
+            return Outcome::Pending;
+        }
+        let (column, entry) = (((event.x - x - 2) / column_width) as usize, (event.y - first_row) as usize);
+        if entry >= Self::visible_rows(height) {
+            return Outcome::Pending;
+        }
+        self.pick((column, entry))
+    }
+}
+
+/// Score for `text` containing the query's characters in order (case-insensitive), or None.
+/// The best alignment wins: matches at word starts and runs of adjacent characters score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    let Some((&first, rest)) = query.split_first() else {
+        return Some(0);
+    };
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let bonus = |i: usize| if i == 0 || !text[i - 1].is_alphanumeric() { 7 } else { 1 };
+
+    // best[i]: best score with the latest query character matched at text position i
+    let mut best: Vec<Option<i32>> = (0..text.len()).map(|i| (lower[i] == first).then(|| bonus(i))).collect();
+    for &q in rest {
+        let mut next = vec![None; text.len()];
+        let mut earlier: Option<i32> = None; // best over positions before i - 1
+        for i in 0..text.len() {
+            if i >= 2 {
+                earlier = earlier.max(best[i - 2]);
+            }
+            if lower[i] == q {
+                let adjacent = i.checked_sub(1).and_then(|p| best[p]).map(|score| score + 4);
+                next[i] = earlier.max(adjacent).map(|score| score + bonus(i));
+            }
+        }
+        best = next;
+    }
+    best.into_iter().max().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kitty_native::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent { code, modifiers: KeyModifiers { ctrl: false, alt: false, shift: false, cmd: false } }
+    }
+
+    #[test]
+    fn test_stack_routes_to_top_widget() {
+        let mut stack: OverlayStack<String> = OverlayStack::default();
+        stack.push(ListPicker::new("Pick", vec![("apple".to_string(), "a".to_string()), ("banana".to_string(), "b".to_string())]));
+        stack.push(Prompt::new("Name", |text| format!("name:{}", text)));
+
+        assert!(matches!(stack.handle_key(&key(KeyCode::Char('x'))), Routed::Consumed));
+        assert!(matches!(stack.handle_key(&key(KeyCode::Enter)), Routed::Action(ref a) if a == "name:x"));
+
+        // The picker is on top now; filtering leaves only "banana"
+        stack.handle_key(&key(KeyCode::Char('n')));
+        assert!(matches!(stack.handle_key(&key(KeyCode::Enter)), Routed::Action(ref a) if a == "b"));
+        assert!(stack.is_empty());
+        assert!(matches!(stack.handle_key(&key(KeyCode::Esc)), Routed::NotHandled));
+    }
+
+    #[test]
+    fn test_dashboard() {
+        let entry = |label: &str| (label.to_string(), String::new(), label.to_string());
+        let columns = vec![
+            ("Recent".to_string(), vec![entry("a"), entry("b")]),
+            ("Favorites".to_string(), vec![]),
+            ("Start".to_string(), vec![entry("new")]),
+        ];
+        let mut stack: OverlayStack<String> = OverlayStack::default();
+        stack.push(Dashboard::new("Start", "today", columns, Some("leave".to_string())));
+
+        // Right skips the empty column and keeps the row in range
+        stack.handle_key(&key(KeyCode::Down));
+        stack.handle_key(&key(KeyCode::Right));
+        assert!(matches!(stack.handle_key(&key(KeyCode::Enter)), Routed::Action(ref a) if a == "new"));
+
+        let mut dashboard = Dashboard::new("Start", "", vec![("Recent".to_string(), vec![entry("a")])], Some("leave".to_string()));
+        assert!(matches!(dashboard.handle_key(&key(KeyCode::Esc)), Outcome::Done(ref a) if a == "leave"));
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("tgl", "Toggle grid lines").is_some());
+        assert!(fuzzy_score("lgt", "Toggle grid lines").is_none());
+        // Word starts beat letters in the middle of words
+        assert!(fuzzy_score("gl", "Toggle grid lines") > fuzzy_score("gl", "Ugly"));
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+}
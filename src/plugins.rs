@@ -0,0 +1,146 @@
+// Script plugins
+// Each plugin.json in plugins/ adds palette commands that pipe text through a script, and
+// hooks for note events (see hooks.rs).
+use crate::hooks::Hooks;
+use crate::notes_database::Note;
+use crate::shell;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Input {
+    None,
+    #[default]
+    Selection,
+    Note,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Output {
+    Replace,
+    #[default]
+    Insert,
+    NewNote,
+    Status,
+    None,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginCommand {
+    pub label: String,
+    /// Shell command, run in the plugin's directory
+    pub run: String,
+    #[serde(default)]
+    pub input: Input,
+    #[serde(default)]
+    pub output: Output,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Plugin {
+    pub name: String,
+    #[serde(default)]
+    pub commands: Vec<PluginCommand>,
+    /// Event hooks, already set to run in the plugin's directory
+    #[serde(default)]
+    pub hooks: Hooks,
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+impl Plugin {
+    /// Read `dir`/plugin.json
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join("plugin.json");
+        let text = std::fs::read_to_string(&path).with_context(|| format!("Reading {}", path.display()))?;
+        let mut plugin: Plugin = serde_json::from_str(&text).with_context(|| format!("Parsing {}", path.display()))?;
+        plugin.dir = dir.to_path_buf();
+        for commands in [&mut plugin.hooks.on_save, &mut plugin.hooks.on_note_open, &mut plugin.hooks.on_create] {
+            for command in commands.iter_mut() {
+                *command = in_dir(dir, command);
+            }
+        }
+        Ok(plugin)
+    }
+
+    /// Run command `index` with `input` on stdin, for the open note; its stdout
+    pub fn run(&self, index: usize, input: &str, note: Option<&Note>) -> Result<String> {
+        let command = self.commands.get(index).context("No such plugin command")?;
+        let dir = self.dir.to_string_lossy();
+        let env = [
+            ("CHONK_NOTE_ID", note.map_or("", |note| note.id.as_str())),
+            ("CHONK_NOTE_TITLE", note.map_or("", |note| note.title.as_str())),
+            ("CHONK_PLUGIN_DIR", dir.as_ref()),
+        ];
+        shell::run_with_env(&in_dir(&self.dir, &command.run), input, &env)
+    }
+}
+
+/// Where plugins are installed
+pub fn dir() -> Option<PathBuf> {
+    crate::data_dir::file("plugins")
+}
+
+/// Every plugin in `dir`, in name order (none if it doesn't exist), and a line for each
+/// plugin that was skipped
+pub fn load_all(dir: &Path) -> (Vec<Plugin>, Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut dirs: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).filter(|path| path.is_dir()).collect();
+    dirs.sort();
+    let mut plugins = Vec::new();
+    let mut problems = Vec::new();
+    for dir in &dirs {
+        match Plugin::load(dir) {
+            Ok(plugin) => {
+                log::info!("Loaded plugin {} ({} commands)", plugin.name, plugin.commands.len());
+                plugins.push(plugin);
+            }
+            Err(e) => {
+                log::warn!("Skipping plugin in {}: {:#}", dir.display(), e);
+                problems.push(format!("{:#}", e));
+            }
+        }
+    }
+    (plugins, problems)
+}
+
+/// `command` to run from inside `dir`
+fn in_dir(dir: &Path, command: &str) -> String {
+    format!("cd {} && {}", shell::quote(&dir.to_string_lossy()), command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plugins() -> Result<()> {
+        let root = std::env::temp_dir().join(format!("chonk-plugins-{}", std::process::id()));
+        let upper = root.join("upper case");
+        std::fs::create_dir_all(&upper)?;
+        std::fs::write(upper.join("upper.sh"), "tr a-z A-Z")?;
+        std::fs::write(
+            upper.join("plugin.json"),
+            r#"{"name": "Upper", "commands": [{"label": "Shout", "run": "sh upper.sh", "output": "replace"}],
+                "hooks": {"on_save": ["true"]}}"#,
+        )?;
+        std::fs::create_dir_all(root.join("broken"))?;
+        std::fs::write(root.join("broken").join("plugin.json"), "{")?;
+
+        let (plugins, problems) = load_all(&root);
+        assert_eq!(plugins.len(), 1);
+        assert!(problems.len() == 1 && problems[0].starts_with("Parsing ") && problems[0].contains("broken"));
+        let plugin = &plugins[0];
+        assert_eq!(plugin.run(0, "hello", None)?, "HELLO");
+        std::fs::remove_dir_all(&root)?;
+        assert_eq!((plugin.commands[0].input, plugin.commands[0].output), (Input::Selection, Output::Replace));
+        assert!(plugin.hooks.on_save[0].starts_with("cd '"));
+        assert!(load_all(&root).0.is_empty());
+        Ok(())
+    }
+}
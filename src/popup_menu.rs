@@ -0,0 +1,163 @@
+// Popup menu overlay: a list of actions drawn at a screen position (e.g. the right-click menu)
+use crate::config::{colors, rgb_bg, rgb_fg};
+use crate::kitty_native::{KeyCode, KeyEvent, MouseButton, MouseEvent};
+use crate::overlay::{Outcome, Widget};
+
+pub struct MenuItem<A> {
+    pub action: A,
+    pub label: &'static str,
+    /// Shortcut shown right-aligned (may be empty)
+    pub hint: &'static str,
+    /// Disabled items are drawn dimmed and ignore clicks
+    pub enabled: bool,
+}
+
+pub struct PopupMenu<A> {
+    /// 0-based screen position of the top border
+    pub x: u16,
+    pub y: u16,
+    pub items: Vec<MenuItem<A>>,
+    pub hover: Option<usize>,
+}
+
+impl<A: Clone> PopupMenu<A> {
+    /// Menu with its top-left corner at (x, y), moved left/up as needed to fit the screen
+    pub fn new(x: u16, y: u16, items: Vec<MenuItem<A>>, term_width: u16, term_height: u16) -> Self {
+        let mut menu = Self { x, y, items, hover: None };
+        menu.x = x.min(term_width.saturating_sub(menu.width()));
+        menu.y = y.min(term_height.saturating_sub(menu.height()));
+        menu
+    }
+
+    pub fn width(&self) -> u16 {
+        let widest = self.items.iter()
+            .map(|item| item.label.chars().count() + item.hint.chars().count())
+            .max()
+            .unwrap_or(0);
+        (widest + 6) as u16 // 2 left padding, 2 between label and hint, 2 right padding
+    }
+
+    /// Items plus the top and bottom borders
+    pub fn height(&self) -> u16 {
+        self.items.len() as u16 + 2
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x && x < self.x + self.width() && y >= self.y && y < self.y + self.height()
+    }
+
+    /// Index of the item at a 0-based screen position
+    pub fn item_at(&self, x: u16, y: u16) -> Option<usize> {
+        if !self.contains(x, y) || y == self.y {
+            return None;
+        }
+        let index = (y - self.y - 1) as usize;
+        (index < self.items.len()).then_some(index)
+    }
+
+    /// Action of an enabled item
+    pub fn action(&self, index: usize) -> Option<A> {
+        self.items.get(index).filter(|item| item.enabled).map(|item| item.action.clone())
+    }
+
+    /// Move the keyboard highlight up (-1) or down (1), skipping disabled items
+    pub fn move_hover(&mut self, direction: isize) {
+        let count = self.items.len() as isize;
+        let mut index = self.hover.map(|i| i as isize).unwrap_or(if direction > 0 { -1 } else { count });
+        for _ in 0..count {
+            index = (index + direction).rem_euclid(count);
+            if self.items[index as usize].enabled {
+                self.hover = Some(index as usize);
+                return;
+            }
+        }
+    }
+
+    fn draw(&self) {
+        let menu_bg = rgb_bg(250, 250, 250); // Light gray, like the title bar dropdowns
+        let menu_fg = rgb_fg(0, 0, 0);
+        let disabled_fg = rgb_fg(170, 170, 170);
+        let hover_bg = rgb_bg(colors::TITLE_BAR_BG.0, colors::TITLE_BAR_BG.1, colors::TITLE_BAR_BG.2);
+        let hover_fg = rgb_fg(255, 255, 255);
+        let hint_fg = rgb_fg(120, 120, 120);
+
+        let width = self.width() as usize;
+        let separator = crate::term_caps::rule(width);
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", self.y + 1, self.x + 1, menu_bg, menu_fg, separator);
+
+        for (i, item) in self.items.iter().enumerate() {
+            let hovered = self.hover == Some(i) && item.enabled;
+            let (bg, fg, hint_fg) = match (hovered, item.enabled) {
+                (true, _) => (&hover_bg, &hover_fg, &hover_fg),
+                (false, true) => (&menu_bg, &menu_fg, &hint_fg),
+                (false, false) => (&menu_bg, &disabled_fg, &disabled_fg),
+            };
+            let label_width = width.saturating_sub(item.hint.chars().count() + 4);
+            let label_text = format!("  {:<width$}", item.label, width = label_width);
+            print!("\x1b[{};{}H{}{}{}{}{}  \x1b[0m", self.y + 2 + i as u16, self.x + 1, bg, fg, label_text, hint_fg, item.hint);
+        }
+
+        print!("\x1b[{};{}H{}{}{}\x1b[0m", self.y + self.height(), self.x + 1, menu_bg, menu_fg, separator);
+    }
+}
+
+impl<A: Clone> Widget<A> for PopupMenu<A> {
+    fn render(&self, _term_width: u16, _term_height: u16) {
+        self.draw();
+    }
+
+    /// Arrows move, Enter runs, Esc (or any other key) closes
+    fn handle_key(&mut self, key: &KeyEvent) -> Outcome<A> {
+        match key.code {
+            KeyCode::Up => self.move_hover(-1),
+            KeyCode::Down => self.move_hover(1),
+            KeyCode::Enter => {
+                return match self.hover.and_then(|i| self.action(i)) {
+                    Some(action) => Outcome::Done(action),
+                    None => Outcome::Pending,
+                }
+            }
+            _ => return Outcome::Close,
+        }
+        Outcome::Pending
+    }
+
+    fn handle_mouse(&mut self, event: &MouseEvent, _term_width: u16, _term_height: u16) -> Outcome<A> {
+        match *event {
+            MouseEvent { button: Some(MouseButton::Left), is_press: true, is_drag: false, x, y, .. } => {
+                match self.item_at(x, y) {
+                    Some(index) => self.action(index).map(Outcome::Done).unwrap_or(Outcome::Pending),
+                    None if self.contains(x, y) => Outcome::Pending,
+                    None => Outcome::Close,
+                }
+            }
+            MouseEvent { button: None, is_drag: true, x, y, .. } => {
+                self.hover = self.item_at(x, y);
+                Outcome::Pending
+            }
+            // Right-clicking again reopens the menu at the new position
+            MouseEvent { button: Some(MouseButton::Right), is_press: true, .. } => Outcome::Pass,
+            MouseEvent { button: Some(MouseButton::Right), is_press: false, .. } => Outcome::Pending,
+            _ => Outcome::Close,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu_geometry() {
+        let item = |action, enabled| MenuItem { action, label: "Copy", hint: "Ctrl+C", enabled };
+        let mut menu = PopupMenu::new(78, 5, vec![item(1, true), item(2, false), item(3, true)], 80, 24);
+        assert_eq!(menu.width(), 16);
+        assert_eq!(menu.x, 64); // moved left to fit
+        assert_eq!(menu.item_at(70, 5), None); // top border
+        assert_eq!(menu.item_at(70, 7), Some(1));
+        assert_eq!(menu.action(1), None); // disabled
+        menu.move_hover(1);
+        menu.move_hover(1);
+        assert_eq!(menu.hover, Some(2)); // skipped the disabled item
+    }
+}
@@ -0,0 +1,98 @@
+// Pinned note preview
+// A second note shown read-only in a panel on the right while another is edited, for copying
+// from or referring to. It scrolls on its own (mouse wheel over the panel, Shift+wheel or the
+// horizontal wheel sideways) and is read again whenever notes change, so it follows edits made
+// to it from the sidebar, automation or other processes.
+use crate::note_format;
+use crate::notes_database::NotesDatabase;
+use anyhow::Result;
+
+pub struct Preview {
+    pub note_id: String,
+    pub title: String,
+    pub lines: Vec<String>,
+    /// First line and column shown
+    pub scroll_row: usize,
+    pub scroll_col: usize,
+    /// The pinned note was deleted; the last lines read stay on screen
+    pub missing: bool,
+    stale: bool,
+}
+
+impl Preview {
+    pub fn new(note_id: String) -> Self {
+        Self {
+            note_id,
+            title: String::new(),
+            lines: Vec::new(),
+            scroll_row: 0,
+            scroll_col: 0,
+            missing: false,
+            stale: true,
+        }
+    }
+
+    /// Read the note again when next drawn (called whenever notes change)
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Re-read the pinned note if it may have changed
+    pub fn refresh(&mut self, db: &NotesDatabase) -> Result<()> {
+        if !self.stale {
+            return Ok(());
+        }
+        self.stale = false;
+        match db.get_note(&self.note_id)? {
+            Some(note) => {
+                self.title = note.title;
+                self.lines = note_format::decode(&note.content).to_lines();
+                self.missing = false;
+            }
+            None => self.missing = true,
+        }
+        self.scroll_row = self.scroll_row.min(self.lines.len().saturating_sub(1));
+        Ok(())
+    }
+
+    /// Move the view by `rows` and `cols` (negative: up/left), staying within the text
+    pub fn scroll(&mut self, rows: isize, cols: isize) {
+        self.scroll_row = self.scroll_row.saturating_add_signed(rows).min(self.lines.len().saturating_sub(1));
+        let widest = self.lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        self.scroll_col = self.scroll_col.saturating_add_signed(cols).min(widest.saturating_sub(1));
+    }
+
+    /// The visible part of `line`, `width` columns wide
+    pub fn visible(&self, line: &str, width: usize) -> String {
+        line.chars().skip(self.scroll_col).take(width).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let note = db.create_note("Reference".to_string(), "one\ntwo\nthree".to_string(), vec![])?;
+        let mut preview = Preview::new(note.id.clone());
+        preview.refresh(&db)?;
+        assert_eq!((preview.title.as_str(), preview.lines.len()), ("Reference", 3));
+
+        preview.scroll(10, 2);
+        assert_eq!((preview.scroll_row, preview.scroll_col), (2, 2));
+        assert_eq!(preview.visible(&preview.lines[preview.scroll_row], 10), "ree");
+        preview.scroll(-1, -5);
+        assert_eq!((preview.scroll_row, preview.scroll_col), (1, 0));
+
+        // Edits elsewhere show up once notes change
+        db.update_note(&note.id, "Reference".to_string(), "new".to_string(), vec![])?;
+        preview.refresh(&db)?;
+        assert_eq!(preview.lines[0], "one");
+        preview.invalidate();
+        preview.refresh(&db)?;
+        assert_eq!((preview.lines.len(), preview.scroll_row), (1, 0));
+        Ok(())
+    }
+}
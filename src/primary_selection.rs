@@ -0,0 +1,30 @@
+// X11/Wayland primary selection (select to copy, middle-click to paste)
+// Only Linux has a primary selection; elsewhere these are no-ops.
+
+/// Offer `text` as the primary selection
+#[cfg(target_os = "linux")]
+pub fn set(text: &str) {
+    use arboard::{LinuxClipboardKind, SetExtLinux};
+    let result = arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set().clipboard(LinuxClipboardKind::Primary).text(text.to_string()));
+    if let Err(e) = result {
+        log::debug!("Could not set primary selection: {}", e);
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set(_text: &str) {}
+
+/// Current primary selection text, if any
+#[cfg(target_os = "linux")]
+pub fn get() -> Option<String> {
+    use arboard::{GetExtLinux, LinuxClipboardKind};
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get().clipboard(LinuxClipboardKind::Primary).text())
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get() -> Option<String> {
+    None
+}
@@ -0,0 +1,94 @@
+// Saved searches
+// A search saved under a name becomes a sidebar section next to the smart views, listing the
+// notes it matches; it runs again whenever notes change and when its section is unfolded.
+// A query is words and quoted phrases that must all appear in a note's title or content
+// (ignoring case), plus filters: `tag:work` (the note has that tag), `after:2025-01-31` and
+// `before:2025-03-01` (last edited on/after, or before, that local day). For example
+// `tag:work TODO` or `"release notes" after:2025-01-01`.
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// Words and phrases that must all appear
+    pub words: Vec<String>,
+    pub tags: Vec<String>,
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut query = Query::default();
+        for term in terms(text) {
+            let date = |value: &str| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| anyhow::anyhow!("\"{}\" isn't a date like 2025-01-31", value))
+            };
+            match term.split_once(':') {
+                Some(("tag", tag)) if !tag.is_empty() => query.tags.push(tag.to_string()),
+                Some(("after", day)) => query.after = Some(date(day)?),
+                Some(("before", day)) => query.before = Some(date(day)?),
+                _ => query.words.push(term),
+            }
+        }
+        anyhow::ensure!(query != Query::default(), "The search is empty");
+        Ok(query)
+    }
+
+    /// Start of the `after` day and of the `before` day, as stored timestamps compare
+    pub fn edited_range(&self) -> (Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+        let midnight = |day: NaiveDate| {
+            day.and_hms_opt(0, 0, 0)
+                .and_then(|start| start.and_local_timezone(Local).earliest())
+                .map(|start| start.with_timezone(&Utc))
+        };
+        (self.after.and_then(midnight), self.before.and_then(midnight))
+    }
+}
+
+/// Whitespace-separated terms; "double quotes" keep a phrase together
+fn terms(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for (i, part) in text.split('"').enumerate() {
+        if i % 2 == 1 {
+            terms.push(part.to_string());
+        } else {
+            terms.extend(part.split_whitespace().map(str::to_string));
+        }
+    }
+    terms.retain(|term| !term.trim().is_empty());
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes_database::NotesDatabase;
+
+    #[test]
+    fn test_queries() -> Result<()> {
+        let query = Query::parse("tag:work TODO \"next week\" after:2025-01-31")?;
+        assert_eq!((query.words.as_slice(), query.tags.as_slice()), (&["TODO".to_string(), "next week".to_string()][..], &["work".to_string()][..]));
+        assert_eq!(query.after, NaiveDate::from_ymd_opt(2025, 1, 31));
+        assert!(Query::parse("before:soon").is_err());
+        assert!(Query::parse("  ").is_err());
+
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Standup".to_string(), "todo: 100% done".to_string(), vec!["work".to_string()])?;
+        db.create_note("Chores".to_string(), "TODO dishes".to_string(), vec!["home".to_string()])?;
+        let titles = |query: &str| -> Result<Vec<String>> {
+            Ok(db.find_matching(&Query::parse(query)?, 10)?.0.into_iter().map(|note| note.title).collect())
+        };
+        assert_eq!(titles("tag:work TODO")?, ["Standup"]);
+        assert_eq!(titles("todo")?, ["Chores", "Standup"]);
+        assert_eq!(titles("100%")?, ["Standup"]);
+        assert_eq!(titles("\"0% d\"")?, ["Standup"]);
+        assert!(titles("before:2000-01-01")?.is_empty());
+
+        let id = db.save_search("Work todos", "tag:work TODO")?;
+        assert_eq!(db.list_saved_searches()?, [(id, "Work todos".to_string(), "tag:work TODO".to_string())]);
+        db.delete_saved_search(id)?;
+        assert!(db.list_saved_searches()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+// Multiple selections, edited all at once (selection-first, like Helix and Kakoune)
+// Each selection is a range of cells on one row; an empty range is a caret. Typing replaces
+// every range with the text, Backspace/Delete remove them (or one character beside each
+// caret), and the whole edit is a single undo step. One range is primary: the cursor sits at
+// its head. Line joins and splits need a single caret, so those keys collapse the set first.
+use crate::chunked_grid::ChunkedGrid;
+use crate::undo::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub row: usize,
+    /// Where the range started; the head moves
+    pub anchor: usize,
+    pub head: usize,
+}
+
+impl Range {
+    pub fn caret(row: usize, col: usize) -> Self {
+        Self { row, anchor: col, head: col }
+    }
+
+    pub fn start(&self) -> usize {
+        self.anchor.min(self.head)
+    }
+
+    /// One past the last selected column
+    pub fn end(&self) -> usize {
+        self.anchor.max(self.head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.head
+    }
+
+    pub fn contains(&self, row: usize, col: usize) -> bool {
+        row == self.row && col >= self.start() && col < self.end()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Selections {
+    /// Sorted by position, never overlapping
+    ranges: Vec<Range>,
+    primary: usize,
+    typing: bool,
+}
+
+impl Selections {
+    /// Selections from `ranges` (in any order); `primary` indexes `ranges`
+    pub fn new(ranges: Vec<Range>, primary: usize) -> Self {
+        let primary_range = ranges.get(primary).copied();
+        let mut selections = Self { ranges, primary: 0, typing: false };
+        selections.normalize(primary_range);
+        selections
+    }
+
+    pub fn ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+
+    pub fn primary(&self) -> Range {
+        self.ranges[self.primary]
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Sort, then merge ranges that overlap or carets on the same cell, keeping track of the primary
+    fn normalize(&mut self, primary: Option<Range>) {
+        self.ranges.sort_by_key(|r| (r.row, r.start(), r.end()));
+        let mut merged: Vec<Range> = Vec::with_capacity(self.ranges.len());
+        let mut primary_index = 0;
+        for range in &self.ranges {
+            match merged.last_mut() {
+                Some(last) if last.row == range.row && (range.start() < last.end() || range.start() == last.start()) => {
+                    let (start, end) = (last.start(), last.end().max(range.end()));
+                    *last = Range { row: range.row, anchor: start, head: end };
+                }
+                _ => merged.push(*range),
+            }
+            if Some(*range) == primary {
+                primary_index = merged.len() - 1;
+            }
+        }
+        self.ranges = merged;
+        self.primary = primary_index;
+    }
+
+    /// Replace every range with `text` (typing); returns the executed commands
+    pub fn insert(&mut self, grid: &mut ChunkedGrid, text: &str) -> Vec<Command> {
+        let len = text.chars().count();
+        let commands = self.edit(grid, |grid, range, commands| {
+            delete_cells(grid, range.row, range.start(), range.end(), commands);
+            for (i, ch) in text.chars().enumerate() {
+                let command = Command::InsertChar { row: range.row, col: range.start() + i, ch };
+                command.execute(grid);
+                commands.push(command);
+            }
+            range.start() + len
+        });
+        self.typing = true;
+        commands
+    }
+
+    /// Backspace: delete each range, or the character before each caret
+    pub fn delete_backward(&mut self, grid: &mut ChunkedGrid) -> Vec<Command> {
+        self.edit(grid, |grid, range, commands| {
+            let start = if range.is_empty() { range.start().saturating_sub(1) } else { range.start() };
+            delete_cells(grid, range.row, start, range.end(), commands);
+            start
+        })
+    }
+
+    /// Delete: delete each range, or the character under each caret (never joining lines)
+    pub fn delete_forward(&mut self, grid: &mut ChunkedGrid) -> Vec<Command> {
+        self.edit(grid, |grid, range, commands| {
+            let end = if range.is_empty() {
+                (range.end() + 1).min(grid.get_line_length(range.row).max(range.end()))
+            } else {
+                range.end()
+            };
+            delete_cells(grid, range.row, range.start(), end, commands);
+            range.start()
+        })
+    }
+
+    /// Run `edit` on each range from first to last. It gets the range moved by earlier edits
+    /// on its row and returns the column for the caret left behind.
+    fn edit(&mut self, grid: &mut ChunkedGrid, mut edit: impl FnMut(&mut ChunkedGrid, Range, &mut Vec<Command>) -> usize) -> Vec<Command> {
+        self.typing = false;
+        let mut commands = Vec::new();
+        let mut shift: (usize, isize) = (usize::MAX, 0);
+        for range in self.ranges.iter_mut() {
+            if shift.0 != range.row {
+                shift = (range.row, 0);
+            }
+            let moved = |col: usize| col.saturating_add_signed(shift.1);
+            let current = Range { row: range.row, anchor: moved(range.anchor), head: moved(range.head) };
+            let first = commands.len();
+            let caret = edit(grid, current, &mut commands);
+            // Each insert pushed the rest of the row right by one, each delete pulled it left
+            shift.1 += commands[first..]
+                .iter()
+                .map(|command| if matches!(command, Command::InsertChar { .. }) { 1 } else { -1 })
+                .sum::<isize>();
+            *range = Range::caret(range.row, caret);
+        }
+        let primary = self.primary();
+        self.normalize(Some(primary));
+        commands
+    }
+
+    /// Move every caret by `delta` columns, collapsing ranges
+    pub fn move_by(&mut self, delta: isize) {
+        self.typing = false;
+        for range in self.ranges.iter_mut() {
+            *range = Range::caret(range.row, range.head.saturating_add_signed(delta));
+        }
+        let primary = self.primary();
+        self.normalize(Some(primary));
+    }
+
+    /// Home/End: carets to the start or end of their lines
+    pub fn move_to_line_edge(&mut self, grid: &ChunkedGrid, end: bool) {
+        self.typing = false;
+        for range in self.ranges.iter_mut() {
+            let col = if end { grid.get_line_length(range.row) } else { 0 };
+            *range = Range::caret(range.row, col);
+        }
+        let primary = self.primary();
+        self.normalize(Some(primary));
+    }
+
+    /// Add `range` and make it the primary selection
+    pub fn add(&mut self, range: Range) {
+        self.typing = false;
+        self.ranges.push(range);
+        self.normalize(Some(range));
+    }
+
+    /// Ctrl+Click: add a caret, or remove the caret already there (unless it is the last one)
+    pub fn toggle_caret(&mut self, row: usize, col: usize) {
+        let caret = Range::caret(row, col);
+        match self.ranges.iter().position(|r| *r == caret) {
+            Some(index) if self.ranges.len() > 1 => {
+                self.typing = false;
+                let primary = self.primary();
+                self.ranges.remove(index);
+                // Removing the primary caret hands that role to its neighbour
+                let primary = if primary == caret { self.ranges[index.min(self.ranges.len() - 1)] } else { primary };
+                self.normalize(Some(primary));
+            }
+            Some(_) => {}
+            None => self.add(caret),
+        }
+    }
+
+    /// Whether the last edit was typing with nothing done since, so the next keystroke can
+    /// join its undo step
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+}
+
+/// The word (letters, digits, `_`) under or just before (row, col)
+pub fn word_at(grid: &ChunkedGrid, row: usize, col: usize) -> Option<Range> {
+    let is_word = |c: usize| {
+        let ch = grid.get(row, c);
+        ch.is_alphanumeric() || ch == '_'
+    };
+    let col = if is_word(col) { col } else { col.checked_sub(1).filter(|&c| is_word(c))? };
+    let start = (0..col).rev().take_while(|&c| is_word(c)).last().unwrap_or(col);
+    let end = (col..grid.get_line_length(row)).take_while(|&c| is_word(c)).last().unwrap_or(col) + 1;
+    Some(Range { row, anchor: start, head: end })
+}
+
+/// Text of a one-row range
+pub fn text_of(grid: &ChunkedGrid, range: Range) -> String {
+    (range.start()..range.end()).map(|col| grid.get(range.row, col)).collect()
+}
+
+/// Every occurrence of `text` in the note, first to last, not overlapping each other
+pub fn occurrences(grid: &ChunkedGrid, text: &str) -> Vec<Range> {
+    let needle: Vec<char> = text.chars().collect();
+    let Some((min_row, _, max_row, _)) = grid.bounds().filter(|_| !needle.is_empty()) else {
+        return Vec::new();
+    };
+    (min_row..=max_row).flat_map(|row| occurrences_in_row(grid, row, &needle)).collect()
+}
+
+/// Occurrences of `needle` on one row, left to right
+pub fn occurrences_in_row(grid: &ChunkedGrid, row: usize, needle: &[char]) -> Vec<Range> {
+    let line: Vec<char> = (0..grid.get_line_length(row)).map(|col| grid.get(row, col)).collect();
+    let mut found = Vec::new();
+    let mut col = 0;
+    while !needle.is_empty() && col + needle.len() <= line.len() {
+        if line[col..col + needle.len()] == needle[..] {
+            found.push(Range { row, anchor: col, head: col + needle.len() });
+            col += needle.len();
+        } else {
+            col += 1;
+        }
+    }
+    found
+}
+
+/// Ctrl+D: the first occurrence of `text` after `after` (wrapping around) that no existing
+/// selection overlaps
+pub fn next_occurrence(grid: &ChunkedGrid, text: &str, after: Range, existing: &[Range]) -> Option<Range> {
+    let free = |r: &Range| !existing.iter().any(|e| e.row == r.row && r.start() < e.end().max(e.start() + 1) && e.start() < r.end());
+    let all = occurrences(grid, text);
+    let later = all.iter().position(|r| (r.row, r.start()) >= (after.row, after.end())).unwrap_or(all.len());
+    all[later..].iter().chain(&all[..later]).find(|r| free(r)).copied()
+}
+
+/// Delete cells start..end of `row`, shifting the rest of the line left
+fn delete_cells(grid: &mut ChunkedGrid, row: usize, start: usize, end: usize, commands: &mut Vec<Command>) {
+    for _ in start..end {
+        let command = Command::DeleteChar { row, col: start, deleted_char: grid.get(row, start) };
+        command.execute(grid);
+        commands.push(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(grid: &ChunkedGrid) -> Vec<String> {
+        (0..3).map(|row| grid.get_line(row, 0, grid.get_line_length(row).saturating_sub(1)).trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn test_edit_all_selections() {
+        let mut grid = ChunkedGrid::from_string("let a = 1;\nlet b = 2;\nlet a = a;");
+        // "a" twice on the last row, "1" on the first, and a caret after "b"
+        let mut selections = Selections::new(
+            vec![Range { row: 2, anchor: 4, head: 5 }, Range { row: 0, anchor: 8, head: 9 }, Range::caret(1, 5), Range { row: 2, anchor: 9, head: 8 }],
+            0,
+        );
+        assert_eq!(selections.primary(), Range { row: 2, anchor: 4, head: 5 });
+
+        let typed = Command::Group { commands: selections.insert(&mut grid, "xy") };
+        assert_eq!(lines(&grid), ["let a = xy;", "let bxy = 2;", "let xy = xy;"]);
+        assert_eq!(selections.ranges(), [Range::caret(0, 10), Range::caret(1, 7), Range::caret(2, 6), Range::caret(2, 11)]);
+        assert_eq!(selections.primary(), Range::caret(2, 6));
+
+        let deleted = Command::Group { commands: selections.delete_backward(&mut grid) };
+        assert_eq!(lines(&grid), ["let a = x;", "let bx = 2;", "let x = x;"]);
+        assert_eq!(selections.ranges()[3], Range::caret(2, 9));
+
+        // One undo step per edit, whatever the number of selections
+        deleted.undo(&mut grid);
+        typed.undo(&mut grid);
+        assert_eq!(lines(&grid), ["let a = 1;", "let b = 2;", "let a = a;"]);
+
+        // Carets that meet merge
+        let mut carets = Selections::new(vec![Range::caret(0, 1), Range::caret(0, 2)], 1);
+        carets.move_by(-1);
+        carets.move_by(-1);
+        assert_eq!(carets.ranges(), [Range::caret(0, 0)]);
+    }
+}
@@ -0,0 +1,353 @@
+// Persistent user settings for chonk-note
+// Stored as JSON next to the notes database; missing fields fall back to defaults. The file is
+// checked setting by setting when read: one that is unknown, of the wrong type or out of range
+// is left at its default and reported (in a banner in the editor), while the others apply.
+use crate::config::{broadcast, capture, colors, focus, grid, layout, memory, navigation, sync, tab, timestamps, undo};
+use crate::hooks::Hooks;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+
+/// How grid lines are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridStyle {
+    Solid,
+    Dotted,
+}
+
+/// Where Enter puts the cursor on the new line
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnterIndent {
+    /// Column 0
+    Off,
+    /// Same leading indentation as the current line
+    Indent,
+    /// Column where the text block under the cursor starts (for side-by-side text on the canvas)
+    Column,
+}
+
+/// What Tab does in the editor
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TabMode {
+    /// Insert `tab_width` spaces
+    Spaces,
+    /// Move the cursor to the next vertical grid line (grid_col_spacing)
+    GridStop,
+}
+
+/// When to use reduced terminal output (palette colors, only changed editor rows)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LowBandwidth {
+    /// On when running over SSH
+    Auto,
+    On,
+    Off,
+}
+
+/// Which of the cursor's row and column the editor tints
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorHighlight {
+    Off,
+    /// The cursor's row
+    Line,
+    /// The cursor's row and column
+    Crosshair,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Maximum number of edits remembered in the undo tree
+    pub undo_depth: usize,
+    /// Columns between vertical grid lines
+    pub grid_col_spacing: usize,
+    /// Rows between horizontal grid lines
+    pub grid_row_spacing: usize,
+    pub grid_style: GridStyle,
+    pub grid_color: (u8, u8, u8),
+    /// Log file verbosity (Off disables logging)
+    pub log_level: log::LevelFilter,
+    /// strftime formats for Alt+D / Alt+T
+    pub date_format: String,
+    pub time_format: String,
+    /// strftime prefix added to each line appended to the log note
+    pub log_entry_format: String,
+    /// ID of the note that gets timestamped entries (toggled with Alt+L)
+    pub log_note_id: Option<String>,
+    /// ID of the scratchpad note opened with F9 (created on first use)
+    pub scratchpad_note_id: Option<String>,
+    /// Length of a focus timer session
+    pub focus_minutes: u64,
+    /// Ring the terminal bell when a focus session ends
+    pub focus_bell: bool,
+    pub enter_indent: EnterIndent,
+    pub tab_mode: TabMode,
+    pub tab_width: usize,
+    /// Rows scrolled per mouse wheel notch
+    pub scroll_step: usize,
+    /// Animate wheel scrolling over a few frames instead of jumping
+    pub smooth_scroll: bool,
+    /// Estimated grid memory (MB) above which far-away chunks are packed; 0 = no cap
+    pub memory_cap_mb: usize,
+    /// Copy through the terminal (OSC 52) instead of the system clipboard, e.g. over SSH
+    pub osc52_clipboard: bool,
+    pub low_bandwidth: LowBandwidth,
+    /// ASCII instead of box-drawing characters for borders and grid lines
+    pub ascii_borders: bool,
+    /// Up/Down stop at the end of shorter lines (returning to the column on longer ones)
+    /// instead of moving freely over the canvas
+    pub snap_to_line_end: bool,
+    /// Leave whitespace at the ends of lines (tabs, no-break spaces) out of saved notes
+    pub strip_trailing_whitespace: bool,
+    /// Untitled notes take their title from the first non-empty line when saved
+    pub auto_title: bool,
+    /// Accept notes from browser extensions and scripts on 127.0.0.1 (POST /capture)
+    pub capture_server: bool,
+    pub capture_port: u16,
+    /// Serve JSON-RPC (and MCP) on a Unix socket next to the database for tools and assistants
+    pub automation_server: bool,
+    /// Shell commands run on note events (on_save, on_note_open, on_create)
+    pub hooks: Hooks,
+    /// WebDAV folder notes are synced with, encrypted; empty turns sync off
+    pub sync_url: String,
+    pub sync_minutes: u64,
+    /// Port follow mode broadcasts on
+    pub broadcast_port: u16,
+    pub cursor_highlight: CursorHighlight,
+    /// Clicks and pastes on empty canvas land on the nearest vertical grid line
+    pub snap_to_grid: bool,
+    /// Open on the start screen (recent notes, favorites, actions) rather than the newest note
+    pub start_screen: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            undo_depth: undo::DEFAULT_DEPTH,
+            grid_col_spacing: layout::GRID_VERTICAL_SPACING,
+            grid_row_spacing: layout::GRID_HORIZONTAL_SPACING,
+            grid_style: GridStyle::Solid,
+            grid_color: colors::GRID_LINE_FG,
+            log_level: log::LevelFilter::Warn,
+            date_format: timestamps::DATE_FORMAT.to_string(),
+            time_format: timestamps::TIME_FORMAT.to_string(),
+            log_entry_format: timestamps::LOG_ENTRY_FORMAT.to_string(),
+            log_note_id: None,
+            scratchpad_note_id: None,
+            focus_minutes: focus::DEFAULT_MINUTES,
+            focus_bell: true,
+            enter_indent: EnterIndent::Off,
+            tab_mode: TabMode::Spaces,
+            tab_width: tab::DEFAULT_WIDTH,
+            scroll_step: navigation::WHEEL_SCROLL_ROWS,
+            smooth_scroll: false,
+            memory_cap_mb: 0,
+            osc52_clipboard: false,
+            low_bandwidth: LowBandwidth::Auto,
+            ascii_borders: false,
+            snap_to_line_end: false,
+            strip_trailing_whitespace: false,
+            auto_title: false,
+            capture_server: false,
+            capture_port: capture::DEFAULT_PORT,
+            automation_server: false,
+            hooks: Hooks::default(),
+            sync_url: String::new(),
+            sync_minutes: sync::DEFAULT_MINUTES,
+            broadcast_port: broadcast::DEFAULT_PORT,
+            cursor_highlight: CursorHighlight::Off,
+            snap_to_grid: false,
+            start_screen: false,
+        }
+    }
+}
+
+/// Next value after `current` in `presets`, wrapping around (first preset if not found)
+fn next_preset<T: PartialEq + Copy>(presets: &[T], current: T) -> T {
+    presets
+        .iter()
+        .position(|&p| p == current)
+        .map(|i| presets[(i + 1) % presets.len()])
+        .unwrap_or(presets[0])
+}
+
+impl Settings {
+    /// Location of the settings file (alongside notes.db)
+    pub fn path() -> Option<PathBuf> {
+        crate::data_dir::file("settings.json")
+    }
+
+    /// Load settings, falling back to defaults if the file is missing or unreadable
+    pub fn load() -> Self {
+        Self::load_checked().0
+    }
+
+    /// Load settings along with the problems found in the file (none if it is missing)
+    pub fn load_checked() -> (Self, Vec<String>) {
+        let Some(path) = Self::path() else {
+            return (Self::default(), Vec::new());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(e) => (Self::default(), vec![format!("settings.json: {}", e)]),
+        }
+    }
+
+    /// Settings from the JSON `text`, and a line for each setting that was left at its default
+    pub fn parse(text: &str) -> (Self, Vec<String>) {
+        let fields = match serde_json::from_str::<Value>(text) {
+            Ok(Value::Object(fields)) => fields,
+            Ok(_) => return (Self::default(), vec!["settings.json: expected an object of settings".to_string()]),
+            Err(e) => return (Self::default(), vec![format!("settings.json: {}", e)]),
+        };
+        let known = serde_json::to_value(Self::default()).unwrap_or(Value::Null);
+        let mut problems = Vec::new();
+        let mut valid = Map::new();
+        for (key, value) in fields {
+            if known.get(&key).is_none() {
+                problems.push(format!("settings.json: unknown setting \"{}\"", key));
+                continue;
+            }
+            // Each setting on its own, so that one bad value doesn't take the others with it
+            let alone = Map::from_iter([(key.clone(), value.clone())]);
+            match serde_json::from_value::<Self>(Value::Object(alone)) {
+                Ok(_) => {
+                    valid.insert(key, value);
+                }
+                Err(e) => problems.push(format!("settings.json: \"{}\": {}", key, e)),
+            }
+        }
+        let mut settings: Self = serde_json::from_value(Value::Object(valid)).unwrap_or_default();
+
+        // Zero here would divide by zero or spin
+        let defaults = Self::default();
+        macro_rules! at_least_one {
+            ($($field:ident),*) => {$(
+                if settings.$field == 0 {
+                    problems.push(format!("settings.json: \"{}\" must be at least 1", stringify!($field)));
+                    settings.$field = defaults.$field;
+                }
+            )*};
+        }
+        at_least_one!(undo_depth, grid_col_spacing, grid_row_spacing, tab_width, scroll_step, focus_minutes, sync_minutes);
+        (settings, problems)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Advance undo depth to the next preset (wrapping around)
+    pub fn cycle_undo_depth(&mut self) {
+        self.undo_depth = next_preset(undo::DEPTH_PRESETS, self.undo_depth);
+    }
+
+    /// Advance grid spacing to the next (columns, rows) preset
+    pub fn cycle_grid_spacing(&mut self) {
+        let next = next_preset(grid::SPACING_PRESETS, (self.grid_col_spacing, self.grid_row_spacing));
+        self.grid_col_spacing = next.0;
+        self.grid_row_spacing = next.1;
+    }
+
+    pub fn toggle_grid_style(&mut self) {
+        self.grid_style = match self.grid_style {
+            GridStyle::Solid => GridStyle::Dotted,
+            GridStyle::Dotted => GridStyle::Solid,
+        };
+    }
+
+    pub fn cycle_grid_color(&mut self) {
+        self.grid_color = next_preset(grid::COLOR_PRESETS, self.grid_color);
+    }
+
+    /// Advance log level (Off, Error, ... Trace) and apply it immediately
+    pub fn cycle_log_level(&mut self) {
+        self.log_level = next_preset(crate::logging::LEVEL_PRESETS, self.log_level);
+        crate::logging::set_level(self.log_level);
+    }
+
+    pub fn cycle_enter_indent(&mut self) {
+        self.enter_indent = match self.enter_indent {
+            EnterIndent::Off => EnterIndent::Indent,
+            EnterIndent::Indent => EnterIndent::Column,
+            EnterIndent::Column => EnterIndent::Off,
+        };
+    }
+
+    /// Cycle Tab through each spaces width preset, then grid stops
+    pub fn cycle_tab_mode(&mut self) {
+        let last_width = tab::WIDTH_PRESETS[tab::WIDTH_PRESETS.len() - 1];
+        match self.tab_mode {
+            TabMode::Spaces if self.tab_width == last_width => self.tab_mode = TabMode::GridStop,
+            TabMode::Spaces => self.tab_width = next_preset(tab::WIDTH_PRESETS, self.tab_width),
+            TabMode::GridStop => {
+                self.tab_mode = TabMode::Spaces;
+                self.tab_width = tab::WIDTH_PRESETS[0];
+            }
+        }
+    }
+
+    pub fn cycle_scroll_step(&mut self) {
+        self.scroll_step = next_preset(navigation::WHEEL_SCROLL_PRESETS, self.scroll_step);
+    }
+
+    pub fn cycle_memory_cap(&mut self) {
+        self.memory_cap_mb = next_preset(memory::CAP_PRESETS_MB, self.memory_cap_mb);
+    }
+
+    pub fn cycle_low_bandwidth(&mut self) {
+        self.low_bandwidth = match self.low_bandwidth {
+            LowBandwidth::Auto => LowBandwidth::On,
+            LowBandwidth::On => LowBandwidth::Off,
+            LowBandwidth::Off => LowBandwidth::Auto,
+        };
+    }
+
+    pub fn cycle_cursor_highlight(&mut self) {
+        self.cursor_highlight = match self.cursor_highlight {
+            CursorHighlight::Off => CursorHighlight::Line,
+            CursorHighlight::Line => CursorHighlight::Crosshair,
+            CursorHighlight::Crosshair => CursorHighlight::Off,
+        };
+    }
+
+    /// Whether low-bandwidth output is in effect (Auto follows SSH detection)
+    pub fn low_bandwidth_active(&self) -> bool {
+        match self.low_bandwidth {
+            LowBandwidth::Auto => crate::term_caps::current().remote,
+            LowBandwidth::On => true,
+            LowBandwidth::Off => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let (settings, problems) = Settings::parse(r#"{"tab_width": 2, "grid_style": "wavy", "colour": 1, "scroll_step": 0}"#);
+        assert_eq!((settings.tab_width, settings.grid_style, settings.scroll_step), (2, GridStyle::Solid, navigation::WHEEL_SCROLL_ROWS));
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("\"colour\""));
+        assert!(problems[1].contains("\"grid_style\"") && problems[1].contains("wavy"));
+        assert!(problems[2].contains("\"scroll_step\" must be at least 1"));
+
+        let (settings, problems) = Settings::parse("{\"tab_width\": 2,");
+        assert_eq!((settings.tab_width, problems.len()), (tab::DEFAULT_WIDTH, 1));
+        assert!(Settings::parse("{}").1.is_empty());
+    }
+}
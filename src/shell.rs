@@ -0,0 +1,95 @@
+// Shell commands run from the editor
+// "Filter selection through command" sends the selected block (or the cursor's line) to a
+// command's stdin and replaces it with the command's stdout, e.g. `sort`, `jq .` or `fmt`.
+// "Insert command output" writes a command's stdout at the cursor. Commands run through
+// `sh -c` in the editor's working directory and are killed after TIMEOUT_MS so a command
+// waiting for input can't hang the editor; output past MAX_OUTPUT_BYTES is refused.
+use crate::config::shell::{MAX_OUTPUT_BYTES, TIMEOUT_MS};
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Run `command` with `input` on stdin; its stdout, or an error carrying the first line of
+/// stderr when it fails
+pub fn run(command: &str, input: &str) -> Result<String> {
+    run_with_env(command, input, &[])
+}
+
+/// `run` with extra environment variables set for the command
+pub fn run_with_env(command: &str, input: &str, env: &[(&str, &str)]) -> Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Starting {}", command))?;
+
+    // Feed and drain the pipes on threads, so a command that writes before it has read all of
+    // its input doesn't deadlock
+    let mut stdin = child.stdin.take().context("No stdin")?;
+    let input = input.to_string();
+    std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    // At most MAX_OUTPUT_BYTES are kept; the rest is drained so the command can finish
+    let read = |mut pipe: Box<dyn Read + Send>| {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            let _ = (&mut pipe).take(MAX_OUTPUT_BYTES).read_to_end(&mut bytes);
+            let overflow = std::io::copy(&mut pipe, &mut std::io::sink()).unwrap_or(0) > 0;
+            (String::from_utf8_lossy(&bytes).into_owned(), overflow)
+        })
+    };
+    let stdout = read(Box::new(child.stdout.take().context("No stdout")?));
+    let stderr = read(Box::new(child.stderr.take().context("No stderr")?));
+
+    let deadline = Instant::now() + Duration::from_millis(TIMEOUT_MS);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("timed out after {} s", TIMEOUT_MS / 1000);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let (stdout, overflow) = stdout.join().unwrap_or_default();
+    let (stderr, _) = stderr.join().unwrap_or_default();
+    if !status.success() {
+        let reason = stderr.lines().find(|line| !line.trim().is_empty()).map(str::trim).unwrap_or("no error output");
+        anyhow::bail!("{} ({})", reason, status);
+    }
+    anyhow::ensure!(!overflow, "output is over {} KB", MAX_OUTPUT_BYTES / 1024);
+    Ok(stdout)
+}
+
+/// `text` quoted for `sh`, as one word
+pub fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// Output as canvas lines: tabs expanded to 4 columns, the final newline dropped
+pub fn output_lines(output: &str) -> Vec<String> {
+    output.lines().map(|line| line.replace('\t', "    ")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() -> Result<()> {
+        assert_eq!(output_lines(&run("sort", "pear\napple\n")?), ["apple", "pear"]);
+        assert_eq!(output_lines("a\tb\n"), ["a    b"]);
+        assert_eq!(run(&format!("printf %s {}", quote("it's $HOME")), "")?, "it's $HOME");
+        let error = run("echo oops >&2; exit 3", "")
+            .err()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+        assert!(error.starts_with("oops ("), "{}", error);
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+// Smart views: sidebar sections computed from timestamps
+// "Edited today" and "Last 7 days" (the six days before today) list notes by when they were
+// last saved; "Recently deleted" lists notes moved there by Delete, which are purged for good
+// after TRASH_DAYS. Saved searches follow as sections of their own. The sections sit above
+// the list of all notes and fold independently of it and of each other. They are re-read
+// from the database when the sidebar is drawn after notes were saved, created or deleted (or
+// the day changed), and a saved search also when its section is unfolded.
+use crate::config::notes_list::SMART_VIEW_MAX_NOTES;
+use crate::notes_database::{Note, NotesDatabase};
+use crate::saved_searches::Query;
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    EditedToday,
+    LastWeek,
+    RecentlyDeleted,
+    /// The saved search with this id
+    Saved(i64),
+}
+
+pub struct Section {
+    pub view: View,
+    /// The view's name, or the saved search's
+    pub label: String,
+    /// The saved search's query text
+    pub query: Option<String>,
+    /// The first SMART_VIEW_MAX_NOTES notes of the section
+    pub notes: Vec<Note>,
+    pub total: usize,
+    pub collapsed: bool,
+}
+
+/// One line of the smart view area, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row {
+    /// Header of a section (an index into `sections`), or None for the list of all notes
+    Header(Option<usize>),
+    /// (section, index into its notes)
+    Note(usize, usize),
+    /// "… n more" under a section that has more notes than it shows
+    More(usize),
+}
+
+pub struct SmartViews {
+    pub sections: Vec<Section>,
+    /// The list of all notes is folded away
+    pub all_collapsed: bool,
+    /// Day the sections were read on; None when they must be read again
+    read_on: Option<NaiveDate>,
+}
+
+impl SmartViews {
+    pub fn new() -> Self {
+        let section = |view, label: &str, collapsed| Section { view, label: label.to_string(), query: None, notes: Vec::new(), total: 0, collapsed };
+        Self {
+            sections: vec![
+                section(View::EditedToday, "Edited today", false),
+                section(View::LastWeek, "Last 7 days", true),
+                section(View::RecentlyDeleted, "Recently deleted", true),
+            ],
+            all_collapsed: false,
+            read_on: None,
+        }
+    }
+
+    /// Read the sections again before they are next drawn
+    pub fn invalidate(&mut self) {
+        self.read_on = None;
+    }
+
+    /// Re-read the sections if they were invalidated or were read on another day
+    pub fn refresh(&mut self, db: &NotesDatabase) -> Result<()> {
+        let now = Local::now();
+        if self.read_on == Some(now.date_naive()) {
+            return Ok(());
+        }
+        self.read_saved_searches(db)?;
+        let (today, week) = day_starts(now);
+        for section in &mut self.sections {
+            (section.notes, section.total) = match (section.view, &section.query) {
+                (View::EditedToday, _) => db.list_edited_between(today, Utc::now() + chrono::Duration::days(1), SMART_VIEW_MAX_NOTES)?,
+                (View::LastWeek, _) => db.list_edited_between(week, today, SMART_VIEW_MAX_NOTES)?,
+                (View::RecentlyDeleted, _) => db.list_trashed(SMART_VIEW_MAX_NOTES)?,
+                (View::Saved(_), Some(query)) => match Query::parse(query) {
+                    Ok(query) => db.find_matching(&query, SMART_VIEW_MAX_NOTES)?,
+                    Err(_) => (Vec::new(), 0),
+                },
+                (View::Saved(_), None) => (Vec::new(), 0),
+            };
+        }
+        self.read_on = Some(now.date_naive());
+        Ok(())
+    }
+
+    /// Replace the saved search sections with those in the database, keeping their folding
+    fn read_saved_searches(&mut self, db: &NotesDatabase) -> Result<()> {
+        let mut saved = Vec::new();
+        for (id, name, query) in db.list_saved_searches()? {
+            let view = View::Saved(id);
+            let collapsed = self.sections.iter().find(|section| section.view == view).is_some_and(|section| section.collapsed);
+            saved.push(Section { view, label: name, query: Some(query), notes: Vec::new(), total: 0, collapsed });
+        }
+        self.sections.retain(|section| !matches!(section.view, View::Saved(_)));
+        self.sections.extend(saved);
+        Ok(())
+    }
+
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (i, section) in self.sections.iter().enumerate() {
+            rows.push(Row::Header(Some(i)));
+            if !section.collapsed {
+                rows.extend((0..section.notes.len()).map(|n| Row::Note(i, n)));
+                if section.total > section.notes.len() {
+                    rows.push(Row::More(i));
+                }
+            }
+        }
+        rows.push(Row::Header(None));
+        rows
+    }
+
+    /// Fold or unfold a section (None: the list of all notes)
+    pub fn toggle(&mut self, header: Option<usize>) {
+        match header.and_then(|i| self.sections.get_mut(i)) {
+            Some(section) => {
+                section.collapsed = !section.collapsed;
+                // Unfolding a saved search runs it again
+                if matches!(section.view, View::Saved(_)) && !section.collapsed {
+                    self.read_on = None;
+                }
+            }
+            None => self.all_collapsed = !self.all_collapsed,
+        }
+    }
+}
+
+/// Start of the local day of `now`, and of the day six days before that
+fn day_starts(now: DateTime<Local>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)
+            .and_then(|start| start.and_local_timezone(Local).earliest())
+            .map(|start| start.with_timezone(&Utc))
+            .unwrap_or_else(|| now.with_timezone(&Utc))
+    };
+    let today = now.date_naive();
+    (midnight(today), midnight(today - chrono::Days::new(6)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections() -> Result<()> {
+        let db = NotesDatabase::open_in_memory()?;
+        let today = db.create_note("Today".to_string(), String::new(), vec![])?;
+        let old = db.create_note_at("Old".to_string(), String::new(), vec![], None, Some(Utc::now() - chrono::Duration::days(3)))?;
+        let binned = db.create_note("Binned".to_string(), String::new(), vec![])?;
+        db.trash_note(&binned.id)?;
+
+        let mut views = SmartViews::new();
+        views.refresh(&db)?;
+        let ids = |section: usize| views.sections[section].notes.iter().map(|n| n.id.clone()).collect::<Vec<_>>();
+        assert_eq!((ids(0), ids(1), ids(2)), (vec![today.id], vec![old.id.clone()], vec![binned.id]));
+
+        // Only "Edited today" starts unfolded
+        assert_eq!(views.rows(), [Row::Header(Some(0)), Row::Note(0, 0), Row::Header(Some(1)), Row::Header(Some(2)), Row::Header(None)]);
+        views.toggle(Some(0));
+        views.toggle(Some(2));
+        assert_eq!(views.rows(), [Row::Header(Some(0)), Row::Header(Some(1)), Row::Header(Some(2)), Row::Note(2, 0), Row::Header(None)]);
+
+        // Saved searches come after the built-in views
+        db.save_search("Old ones", "Old")?;
+        views.invalidate();
+        views.refresh(&db)?;
+        assert_eq!((views.sections[3].label.as_str(), views.sections[3].notes[0].id.as_str()), ("Old ones", old.id.as_str()));
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+// Encrypted sync with a WebDAV server
+// One AES-GCM file per note plus an encrypted index; conflicts go to the last writer.
+use crate::config::sync::KEY_FILE;
+use crate::notes_database::{Note, NotesDatabase};
+use crate::shell;
+use crate::term_caps::glyph;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::mpsc;
+
+const INDEX: &str = "index";
+const NONCE_BYTES: usize = 12;
+
+/// Where encrypted files are kept
+pub trait Remote {
+    /// Contents of the file `name`; None if there is no such file
+    fn get(&self, name: &str) -> Result<Option<String>>;
+    fn put(&self, name: &str, contents: &str) -> Result<()>;
+}
+
+/// A folder on a WebDAV (or plain HTTP PUT/GET) server, reached with curl
+pub struct WebDav {
+    url: String,
+}
+
+impl WebDav {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.trim_end_matches('/').to_string() }
+    }
+
+    fn curl(&self, name: &str, args: &str, input: &str) -> Result<String> {
+        let url = format!("{}/{}", self.url, name);
+        shell::run_with_env(&format!("curl -sS --netrc-optional {} \"$CHONK_SYNC_URL\"", args), input, &[("CHONK_SYNC_URL", &url)])
+    }
+}
+
+impl Remote for WebDav {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        let output = self.curl(name, "-w '\\n%{http_code}'", "")?;
+        let (body, status) = output.rsplit_once('\n').unwrap_or(("", output.as_str()));
+        match status.trim() {
+            "200" => Ok(Some(body.trim().to_string())),
+            "404" => Ok(None),
+            status => anyhow::bail!("{} answered HTTP {} for {}", self.url, status, name),
+        }
+    }
+
+    fn put(&self, name: &str, contents: &str) -> Result<()> {
+        self.curl(name, "--fail -T -", contents)?;
+        Ok(())
+    }
+}
+
+/// A note's state: when it last changed (was edited, or deleted) and whether it is deleted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub changed_at: DateTime<Utc>,
+    pub deleted: bool,
+}
+
+/// The result of one sync
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Summary {
+    pub pushed: usize,
+    pub pulled: usize,
+}
+
+/// Encrypts and decrypts files; each is bound to its name so the server can't swap them
+pub struct Cipher(Aes256Gcm);
+
+impl Cipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self(Aes256Gcm::new(key.into()))
+    }
+
+    /// base64 of nonce + ciphertext
+    pub fn seal(&self, name: &str, plain: &[u8]) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let sealed = self.0.encrypt(&nonce, Payload { msg: plain, aad: name.as_bytes() }).map_err(|_| anyhow::anyhow!("Encrypting {} failed", name))?;
+        Ok(STANDARD.encode([nonce.as_slice(), &sealed].concat()))
+    }
+
+    pub fn open(&self, name: &str, text: &str) -> Result<Vec<u8>> {
+        let bytes = STANDARD.decode(text.trim()).with_context(|| format!("{} on the server is damaged", name))?;
+        anyhow::ensure!(bytes.len() > NONCE_BYTES, "{} on the server is damaged", name);
+        let (nonce, sealed) = bytes.split_at(NONCE_BYTES);
+        self.0
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad: name.as_bytes() })
+            .map_err(|_| anyhow::anyhow!("Can't decrypt {}: is sync-key the same on every machine?", name))
+    }
+}
+
+/// Push local changes and pull remote ones, then remember where both sides stand
+pub fn sync(db: &NotesDatabase, remote: &dyn Remote, cipher: &Cipher) -> Result<Summary> {
+    let mut index: HashMap<String, Entry> = match remote.get(INDEX)? {
+        Some(text) => serde_json::from_slice(&cipher.open(INDEX, &text)?)?,
+        None => HashMap::new(),
+    };
+    let entry = |(id, changed_at, deleted)| (id, Entry { changed_at, deleted });
+    let local: HashMap<String, Entry> = db.sync_entries()?.into_iter().map(entry).collect();
+    let base: HashMap<String, Entry> = db.sync_base()?.into_iter().map(entry).collect();
+
+    let mut summary = Summary::default();
+    let ids: BTreeSet<String> = local.keys().chain(index.keys()).cloned().collect();
+    let mut index_changed = false;
+    // Written once the index is on the server, so a failed upload is retried next time
+    let mut settled_states = Vec::new();
+    for id in ids {
+        let (mine, theirs, last) = (local.get(&id), index.get(&id).copied(), base.get(&id));
+        let settled = match (mine, theirs) {
+            (Some(&mine), None) => Some((mine, true)),
+            // Gone for good here (emptied from Recently deleted) or never seen
+            (None, Some(theirs)) => (!theirs.deleted && Some(&theirs) != last).then_some((theirs, false)),
+            (Some(&mine), Some(theirs)) if mine == theirs => Some((mine, false)),
+            (Some(&mine), Some(theirs)) => {
+                let mine_changed = Some(&mine) != last;
+                let theirs_changed = Some(&theirs) != last;
+                let push = mine_changed && (!theirs_changed || mine.changed_at >= theirs.changed_at);
+                Some(if push { (mine, true) } else { (theirs, false) })
+            }
+            (None, None) => None,
+        };
+        let Some((state, push)) = settled else {
+            continue;
+        };
+        if push {
+            if !state.deleted {
+                let note = db.get_note(&id)?.context("Note vanished during sync")?;
+                let name = format!("note-{}", id);
+                remote.put(&name, &cipher.seal(&name, &serde_json::to_vec(&note)?)?)?;
+            }
+            index.insert(id.clone(), state);
+            index_changed = true;
+            summary.pushed += 1;
+        } else if mine != Some(&state) {
+            pull(db, remote, cipher, &id, state)?;
+            summary.pulled += 1;
+        }
+        settled_states.push((id, state));
+    }
+    if index_changed {
+        remote.put(INDEX, &cipher.seal(INDEX, &serde_json::to_vec(&index)?)?)?;
+    }
+    for (id, state) in settled_states {
+        db.set_sync_base(&id, state.changed_at, state.deleted)?;
+    }
+    Ok(summary)
+}
+
+/// Make the local note match the server's `state`
+fn pull(db: &NotesDatabase, remote: &dyn Remote, cipher: &Cipher, id: &str, state: Entry) -> Result<()> {
+    if state.deleted {
+        return db.trash_note_at(id, state.changed_at);
+    }
+    let name = format!("note-{}", id);
+    let text = remote.get(&name)?.with_context(|| format!("{} is in the index but not on the server", name))?;
+    let note: Note = serde_json::from_slice(&cipher.open(&name, &text)?)?;
+    anyhow::ensure!(note.id == id, "{} on the server holds another note", name);
+    // Replacing the row also brings it back from Recently deleted
+    db.put_note(&note)
+}
+
+/// Syncs running in the background, and how the last one went (for the status line)
+pub struct SyncStatus {
+    running: Option<mpsc::Receiver<Result<Summary, String>>>,
+    /// When the last sync finished, and its outcome
+    pub last: Option<(DateTime<Local>, Result<Summary, String>)>,
+}
+
+impl SyncStatus {
+    pub fn new() -> Self {
+        Self { running: None, last: None }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    /// Sync `db` (a connection of its own) with the folder at `url` on another thread
+    pub fn start(&mut self, db: NotesDatabase, url: String) -> Result<()> {
+        let (done, running) = mpsc::channel();
+        std::thread::Builder::new().name("sync".to_string()).spawn(move || {
+            let result = load_or_create_key().and_then(|key| sync(&db, &WebDav::new(&url), &Cipher::new(&key)));
+            let _ = done.send(result.map_err(|e| format!("{:#}", e)));
+        })?;
+        self.running = Some(running);
+        Ok(())
+    }
+
+    /// The outcome of the running sync, once it has finished
+    pub fn poll(&mut self) -> Option<Result<Summary, String>> {
+        let result = match self.running.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err("Sync stopped unexpectedly".to_string()),
+        };
+        self.running = None;
+        self.last = Some((Local::now(), result.clone()));
+        Some(result)
+    }
+
+    /// Status line indicator: its text, and whether the last sync failed
+    pub fn label(&self) -> (String, bool) {
+        match &self.last {
+            _ if self.running.is_some() => (format!("{} syncing", glyph('⟳')), false),
+            None => (format!("{} not synced", glyph('⟳')), false),
+            Some((at, Ok(_))) => (format!("{} synced {}", glyph('⟳'), at.format("%H:%M")), false),
+            Some((_, Err(_))) => (format!("{} sync failed", glyph('⟳')), true),
+        }
+    }
+}
+
+/// The sync key, generated the first time
+pub fn load_or_create_key() -> Result<[u8; 32]> {
+    let text = crate::data_dir::load_or_create_secret(KEY_FILE, 32)?;
+    let bytes: Option<Vec<u8>> = (0..text.len()).step_by(2).map(|i| text.get(i..i + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok())).collect();
+    bytes.and_then(|bytes| bytes.try_into().ok()).with_context(|| format!("{} should hold 64 hex digits", KEY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Folder(RefCell<HashMap<String, String>>);
+
+    impl Remote for Folder {
+        fn get(&self, name: &str) -> Result<Option<String>> {
+            Ok(self.0.borrow().get(name).cloned())
+        }
+
+        fn put(&self, name: &str, contents: &str) -> Result<()> {
+            self.0.borrow_mut().insert(name.to_string(), contents.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sync() -> Result<()> {
+        let server = Folder::default();
+        let cipher = Cipher::new(&[7; 32]);
+        let (laptop, desktop) = (NotesDatabase::open_in_memory()?, NotesDatabase::open_in_memory()?);
+        let note = laptop.create_note("Plans".to_string(), "v1".to_string(), vec![])?;
+        let gone = laptop.create_note("Old".to_string(), String::new(), vec![])?;
+
+        assert_eq!(sync(&laptop, &server, &cipher)?, Summary { pushed: 2, pulled: 0 });
+        assert!(server.0.borrow().values().all(|text| !text.contains("Plans")));
+        assert_eq!(sync(&desktop, &server, &cipher)?, Summary { pushed: 0, pulled: 2 });
+        assert_eq!(desktop.get_note(&note.id)?.map(|n| n.content).as_deref(), Some("v1"));
+        assert_eq!(sync(&desktop, &server, &cipher)?, Summary::default());
+
+        // An edit on one side and a deletion on the other both come across
+        desktop.update_note(&note.id, "Plans".to_string(), "v2".to_string(), vec![])?;
+        laptop.trash_note(&gone.id)?;
+        assert_eq!(sync(&desktop, &server, &cipher)?, Summary { pushed: 1, pulled: 0 });
+        assert_eq!(sync(&laptop, &server, &cipher)?, Summary { pushed: 1, pulled: 1 });
+        assert_eq!(laptop.get_note(&note.id)?.map(|n| n.content).as_deref(), Some("v2"));
+        assert_eq!(sync(&desktop, &server, &cipher)?, Summary { pushed: 0, pulled: 1 });
+        assert_eq!(desktop.list_trashed(10)?.1, 1);
+
+        // Both changed: the later edit wins
+        laptop.update_note(&note.id, "Plans".to_string(), "laptop".to_string(), vec![])?;
+        desktop.update_note(&note.id, "Plans".to_string(), "desktop".to_string(), vec![])?;
+        sync(&laptop, &server, &cipher)?;
+        assert_eq!(sync(&desktop, &server, &cipher)?, Summary { pushed: 1, pulled: 0 });
+        sync(&laptop, &server, &cipher)?;
+        assert_eq!(laptop.get_note(&note.id)?.map(|n| n.content).as_deref(), Some("desktop"));
+
+        assert!(sync(&laptop, &server, &Cipher::new(&[8; 32])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+// Tasks gathered from every note
+// The tasks panel lists the to-do items written anywhere in the notes, grouped by note with
+// the open note first: checkboxes "[ ]" / "[x]" and the words TODO, FIXME, DONE and FIXED.
+// Clicking an item opens its note there; clicking its box marks it done or open again by
+// rewriting the marker in place ("[ ]" <-> "[x]", TODO <-> DONE, FIXME <-> FIXED), in the
+// editor for the open note (one undo step) and in the database for the others. The other
+// notes are scanned on the database thread; the open note is read from the editor, so the
+// panel follows unsaved edits.
+use crate::chunked_grid::ChunkedGrid;
+use crate::config::tasks::MAX_TASKS;
+use crate::note_format;
+use crate::notes_database::NotesDatabase;
+use anyhow::Result;
+use std::collections::HashSet;
+
+/// (open marker, done marker); each pair has equal widths so toggling moves nothing
+const MARKERS: &[(&str, &str)] = &[("[ ]", "[x]"), ("TODO", "DONE"), ("FIXME", "FIXED")];
+
+/// One to-do item; positions are in its note's lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub note_id: String,
+    pub note_title: String,
+    pub line: usize,
+    /// Column of the marker
+    pub col: usize,
+    pub marker: &'static str,
+    pub done: bool,
+    /// The rest of the line after the marker
+    pub text: String,
+}
+
+impl Task {
+    /// The marker that replaces this one when toggled
+    pub fn toggled(&self) -> &'static str {
+        MARKERS
+            .iter()
+            .find_map(|&(open, done)| if self.marker == open { Some(done) } else if self.marker == done { Some(open) } else { None })
+            .unwrap_or(self.marker)
+    }
+}
+
+/// The items in one note's lines
+pub fn in_lines(note_id: &str, note_title: &str, lines: &[String]) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    for (line_index, line) in lines.iter().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut col = 0;
+        while col < chars.len() {
+            let found = MARKERS.iter().flat_map(|&(open, done)| [(open, false), (done, true)]).find(|&(marker, _)| {
+                let marker: Vec<char> = marker.chars().collect();
+                let word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric());
+                chars[col..].starts_with(&marker)
+                    && (marker[0] == '[' || (!word(col.checked_sub(1).and_then(|before| chars.get(before))) && !word(chars.get(col + marker.len()))))
+            });
+            match found {
+                Some((marker, done)) => {
+                    let rest: String = chars[col + marker.chars().count()..].iter().collect();
+                    tasks.push(Task {
+                        note_id: note_id.to_string(),
+                        note_title: note_title.to_string(),
+                        line: line_index,
+                        col,
+                        marker,
+                        done,
+                        text: rest.trim_start_matches([':', ' ']).trim_end().to_string(),
+                    });
+                    // One item per line: the first marker on it
+                    break;
+                }
+                None => col += 1,
+            }
+        }
+    }
+    tasks
+}
+
+/// The items in every note except `except_id` (the open note), at most MAX_TASKS
+pub fn find(db: &NotesDatabase, except_id: Option<&str>) -> Result<Vec<Task>> {
+    let mut tasks = Vec::new();
+    let mut seen: HashSet<String> = except_id.map(str::to_string).into_iter().collect();
+    for marker in MARKERS.iter().flat_map(|&(open, done)| [open, done]) {
+        for note in db.notes_containing(marker)? {
+            if !seen.insert(note.id.clone()) {
+                continue;
+            }
+            tasks.extend(in_lines(&note.id, &note.title, &note_format::decode(&note.content).to_lines()));
+            if tasks.len() >= MAX_TASKS {
+                tasks.truncate(MAX_TASKS);
+                return Ok(tasks);
+            }
+        }
+    }
+    Ok(tasks)
+}
+
+/// Toggle an item in a note that isn't open; false if the note has changed so the marker
+/// is no longer there
+pub fn toggle(db: &NotesDatabase, task: &Task) -> Result<bool> {
+    let Some(note) = db.get_note(&task.note_id)? else {
+        return Ok(false);
+    };
+    // Flip the marker in the decoded grid, so the rest of the note (its origin, its layout,
+    // its colors) is written back as it was
+    let mut grid = note_format::decode(&note.content);
+    let (top, left) = grid.bounds().map_or((0, 0), |(row, col, _, _)| (row, col));
+    let (row, col) = (top + task.line, left + task.col);
+    if !(0..task.marker.chars().count()).map(|i| grid.get(row, col + i)).eq(task.marker.chars()) {
+        return Ok(false);
+    }
+    for (i, ch) in task.toggled().chars().enumerate() {
+        grid.set_styled(row, col + i, ch, grid.style(row, col + i));
+    }
+    db.update_note(&note.id, note.title, note_format::encode(&grid), note.tags)?;
+    Ok(true)
+}
+
+/// A row of the tasks panel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Row {
+    /// A note's title above its items: index of its first item, and how many are open
+    Note(usize, usize),
+    Task(usize),
+}
+
+/// What the panel shows
+pub struct Tasks {
+    /// The open note's items, then the other notes'
+    pub items: Vec<Task>,
+    /// Items of the other notes, from the last scan
+    others: Vec<Task>,
+    pub scroll: usize,
+    /// A scan is running on the database thread
+    pub pending: bool,
+    /// The other notes must be scanned again
+    stale: bool,
+    /// The open note must be read again
+    current_stale: bool,
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        Self { items: Vec::new(), others: Vec::new(), scroll: 0, pending: false, stale: true, current_stale: true }
+    }
+
+    /// Scan every note again (called whenever notes change)
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+        self.current_stale = true;
+    }
+
+    /// Read the open note again (called whenever it is edited)
+    pub fn invalidate_current(&mut self) {
+        self.current_stale = true;
+    }
+
+    /// Whether the other notes should be scanned; marks the scan as started
+    pub fn start_scan(&mut self) -> bool {
+        let start = self.stale && !self.pending;
+        if start {
+            self.pending = true;
+            self.stale = false;
+        }
+        start
+    }
+
+    pub fn finish_scan(&mut self, tasks: Vec<Task>) {
+        self.pending = false;
+        self.others = tasks;
+        self.current_stale = true;
+    }
+
+    /// Re-read the open note's items from the editor if it changed
+    pub fn refresh_current(&mut self, note: Option<(&str, &str)>, grid: &ChunkedGrid) {
+        if !self.current_stale {
+            return;
+        }
+        self.current_stale = false;
+        self.items = match note {
+            Some((id, title)) => in_lines(id, title, &grid.to_lines()),
+            None => Vec::new(),
+        };
+        let current = note.map(|(id, _)| id);
+        self.items.extend(self.others.iter().filter(|task| Some(task.note_id.as_str()) != current).cloned());
+        self.scroll = self.scroll.min(self.rows().len().saturating_sub(1));
+    }
+
+    /// A title row for each note, followed by its items
+    pub fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for (i, task) in self.items.iter().enumerate() {
+            if i == 0 || self.items[i - 1].note_id != task.note_id {
+                let open = self.items[i..].iter().take_while(|other| other.note_id == task.note_id).filter(|other| !other.done).count();
+                rows.push(Row::Note(i, open));
+            }
+            rows.push(Row::Task(i));
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked_grid::Style;
+
+    #[test]
+    fn test_tasks() -> Result<()> {
+        let lines = ["- [ ] milk", "TODO: call Sam", "  [x] done already", "TODOS and mastodon", "FIXME later"].map(String::from);
+        let found: Vec<_> = in_lines("n", "Note", &lines).into_iter().map(|t| (t.line, t.col, t.marker, t.done, t.text)).collect();
+        assert_eq!(found, [
+            (0, 2, "[ ]", false, "milk".to_string()),
+            (1, 0, "TODO", false, "call Sam".to_string()),
+            (2, 2, "[x]", true, "done already".to_string()),
+            (4, 0, "FIXME", false, "later".to_string()),
+        ]);
+
+        let db = NotesDatabase::open_in_memory()?;
+        let open = db.create_note("Open".to_string(), "TODO here".to_string(), vec![])?;
+        let other = db.create_note("Other".to_string(), "x\n[ ] buy bread".to_string(), vec![])?;
+        let tasks = find(&db, Some(&open.id))?;
+        assert_eq!(tasks.len(), 1);
+        assert!(toggle(&db, &tasks[0])?);
+        assert_eq!(db.get_note(&other.id)?.map(|n| n.content).as_deref(), Some("x\n[x] buy bread"));
+        assert!(!toggle(&db, &tasks[0])?);
+
+        // Text above the origin stays above it
+        let mut grid = ChunkedGrid::new();
+        grid.insert_at(40, 40, "above");
+        grid.insert_at(41, 42, "[ ] plant");
+        grid.origin = (41, 40);
+        let placed = db.create_note("Placed".to_string(), note_format::encode(&grid), vec![])?;
+        let task = find(&db, Some(&open.id))?.into_iter().find(|t| t.note_id == placed.id).unwrap();
+        assert!(toggle(&db, &task)?);
+        let toggled = note_format::decode(&db.get_note(&placed.id)?.unwrap().content);
+        let (top, left, _, _) = toggled.bounds().unwrap();
+        assert_eq!(toggled.logical_pos(top, left), (-1, 0));
+        assert_eq!(toggled.to_lines(), ["above", "  [x] plant"]);
+
+        // So do the note's colors, the marker's included
+        let red = Style { fg: Some((200, 0, 0)), ..Style::default() };
+        let mut grid = ChunkedGrid::from_lines(&["TODO: paint".to_string()]);
+        for col in 0..11 {
+            grid.set_style(0, col, red);
+        }
+        let colored = db.create_note("Colored".to_string(), note_format::encode(&grid), vec![])?;
+        let task = find(&db, Some(&open.id))?.into_iter().find(|t| t.note_id == colored.id).unwrap();
+        assert!(toggle(&db, &task)?);
+        let toggled = note_format::decode(&db.get_note(&colored.id)?.unwrap().content);
+        assert_eq!(toggled.to_lines(), ["DONE: paint"]);
+        assert!((0..11).filter(|&col| toggled.get(0, col) != ' ').all(|col| toggled.style(0, col) == red));
+
+        let mut panel = Tasks::new();
+        panel.finish_scan(tasks);
+        panel.refresh_current(Some((&open.id, "Open")), &ChunkedGrid::from_lines(&["TODO here".to_string()]));
+        assert_eq!(panel.rows(), [Row::Note(0, 1), Row::Task(0), Row::Note(1, 1), Row::Task(1)]);
+        Ok(())
+    }
+}
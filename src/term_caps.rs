@@ -0,0 +1,313 @@
+// Terminal capability detection
+// Starts from environment hints (TERM, COLORTERM, TMUX, STY, KITTY_WINDOW_ID, TERM_PROGRAM),
+// then asks the terminal itself: a Kitty graphics query, a DECRQM query for synchronized
+// output (mode 2026) and Primary Device Attributes, whose reply ends the probe. Terminals
+// that ignore a query simply don't get that feature, so tmux, screen and simpler terminals
+// fall back to palette colors, no inline images and basic mouse reporting.
+// Low-bandwidth mode (see Settings) further caps colors at the 256 palette, and ASCII
+// borders replace box-drawing characters.
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the terminal to answer the startup queries
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    Palette256,
+    Basic16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Caps {
+    pub color: ColorDepth,
+    /// Kitty graphics protocol (inline PNG in the title bar)
+    pub kitty_graphics: bool,
+    /// Synchronized output (DEC mode 2026) to avoid flicker
+    pub synchronized_output: bool,
+    /// SGR (1006) mouse encoding; otherwise legacy X10 reports without hover tracking
+    pub sgr_mouse: bool,
+    /// Running over SSH (low-bandwidth mode turns on automatically)
+    pub remote: bool,
+}
+
+impl Caps {
+    /// Everything on (used when detection never ran, e.g. in tests)
+    pub const FULL: Caps = Caps {
+        color: ColorDepth::TrueColor,
+        kitty_graphics: true,
+        synchronized_output: true,
+        sgr_mouse: true,
+        remote: false,
+    };
+
+    /// Best guess from environment variables; `var` looks one up
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+        let kitty = var("KITTY_WINDOW_ID").is_some() || term == "xterm-kitty";
+        let in_screen = var("STY").is_some() || (term.starts_with("screen") && var("TMUX").is_none());
+
+        let mut color = if colorterm == "truecolor" || colorterm == "24bit" || kitty
+            || matches!(program.as_str(), "iTerm.app" | "WezTerm" | "vscode" | "ghostty")
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") || term.starts_with("xterm") || term.starts_with("tmux") || term.starts_with("screen") {
+            ColorDepth::Palette256
+        } else {
+            ColorDepth::Basic16
+        };
+        // Explicit override, like CHONK_NOTE_LOG for logging
+        match var("CHONK_NOTE_COLORS").as_deref() {
+            Some("truecolor") => color = ColorDepth::TrueColor,
+            Some("256") => color = ColorDepth::Palette256,
+            Some("16") => color = ColorDepth::Basic16,
+            _ => {}
+        }
+
+        Self {
+            color,
+            // Confirmed by the probe
+            kitty_graphics: false,
+            synchronized_output: false,
+            sgr_mouse: !in_screen && term != "linux",
+            remote: ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"].iter().any(|name| var(name).is_some()),
+        }
+    }
+
+    /// Apply the terminal's answers to the probe queries
+    fn apply_replies(&mut self, replies: &str) {
+        if replies.contains("\x1b_Gi=31;OK") {
+            self.kitty_graphics = true;
+        }
+        if replies.contains("\x1b[?2026;1$y") || replies.contains("\x1b[?2026;2$y") {
+            self.synchronized_output = true;
+        }
+    }
+}
+
+static CAPS: OnceLock<Caps> = OnceLock::new();
+static LOW_BANDWIDTH: AtomicBool = AtomicBool::new(false);
+static ASCII_BORDERS: AtomicBool = AtomicBool::new(false);
+
+/// Capabilities detected at startup (everything on if detection never ran)
+pub fn current() -> Caps {
+    CAPS.get().copied().unwrap_or(Caps::FULL)
+}
+
+/// Cap colors at the 256-color palette (shorter escape sequences)
+pub fn set_low_bandwidth(on: bool) {
+    LOW_BANDWIDTH.store(on, Ordering::Relaxed);
+}
+
+/// Draw borders and grid lines with ASCII instead of box-drawing characters
+pub fn set_ascii_borders(on: bool) {
+    ASCII_BORDERS.store(on, Ordering::Relaxed);
+}
+
+/// Color depth to draw with: the detected one, capped in low-bandwidth mode
+fn color_depth() -> ColorDepth {
+    match current().color {
+        ColorDepth::TrueColor if LOW_BANDWIDTH.load(Ordering::Relaxed) => ColorDepth::Palette256,
+        color => color,
+    }
+}
+
+/// Box-drawing character, or its ASCII stand-in when ASCII borders are on
+pub fn glyph(ch: char) -> char {
+    if !ASCII_BORDERS.load(Ordering::Relaxed) {
+        return ch;
+    }
+    match ch {
+        '─' | '┄' => '-',
+        '━' => '=',
+        '│' | '┃' => '|',
+        '┆' => ':',
+        '┌' | '┐' | '└' | '┘' | '┼' | '╋' => '+',
+        '▾' => 'v',
+        '▴' => '^',
+        '×' => 'x',
+        '▰' => '#',
+        '▱' => '.',
+        '⟳' => '~',
+        '·' => '.',
+        '→' => '>',
+        '°' | '¤' | '\u{2400}'..='\u{2421}' => '?',
+        other => other,
+    }
+}
+
+/// Horizontal rule `width` cells long
+pub fn rule(width: usize) -> String {
+    glyph('─').to_string().repeat(width)
+}
+
+/// Detect capabilities and remember them for `current()`. The terminal must already be in
+/// raw mode. Returns any bytes read that were not replies (e.g. early keystrokes) so they
+/// can be handed back to the input parser.
+pub fn detect() -> Vec<u8> {
+    let mut caps = Caps::from_env(|name| std::env::var(name).ok());
+    let (replies, leftover) = probe().unwrap_or_default();
+    caps.apply_replies(&replies);
+    log::info!("Terminal capabilities: {:?}", caps);
+    let _ = CAPS.set(caps);
+    leftover
+}
+
+/// Send the queries and collect replies until the Device Attributes answer arrives
+fn probe() -> io::Result<(String, Vec<u8>)> {
+    let mut stdout = io::stdout();
+    stdout.write_all(b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[?2026$p\x1b[c")?;
+    stdout.flush()?;
+
+    let started = Instant::now();
+    let mut received = Vec::new();
+    let mut buffer = [0u8; 256];
+    while let Some(remaining) = PROBE_TIMEOUT.checked_sub(started.elapsed()) {
+        if !wait_readable(remaining) {
+            break;
+        }
+        let read = io::stdin().read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[..read]);
+        if device_attributes_end(&received).is_some() {
+            break;
+        }
+    }
+    Ok(split_replies(&received))
+}
+
+/// Wait up to `timeout` for stdin to have data
+fn wait_readable(timeout: Duration) -> bool {
+    let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    unsafe { libc::poll(&mut fd, 1, timeout.as_millis() as libc::c_int) > 0 }
+}
+
+/// End index of a Primary Device Attributes reply (ESC [ ? ... c)
+fn device_attributes_end(bytes: &[u8]) -> Option<usize> {
+    let start = bytes.windows(3).position(|w| w == b"\x1b[?")?;
+    let body = &bytes[start + 3..];
+    let end = body.iter().position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+    (body[end] == b'c').then_some(start + 3 + end + 1)
+}
+
+/// Separate the query replies from any other input that arrived meanwhile
+fn split_replies(bytes: &[u8]) -> (String, Vec<u8>) {
+    let mut replies = Vec::new();
+    let mut leftover = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let reply_len = if rest.starts_with(b"\x1b_G") {
+            // APC reply, ends with ST (ESC \)
+            rest.windows(2).position(|w| w == b"\x1b\\").map(|p| p + 2)
+        } else if rest.starts_with(b"\x1b[?") {
+            // DECRPM (... $y) or Device Attributes (... c)
+            rest.iter().position(|&b| b == b'y' || b == b'c').map(|p| p + 1)
+        } else {
+            None
+        };
+        match reply_len {
+            Some(len) => {
+                replies.extend_from_slice(&rest[..len]);
+                i += len;
+            }
+            None => {
+                leftover.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    (String::from_utf8_lossy(&replies).into_owned(), leftover)
+}
+
+/// Foreground color escape for the detected color depth
+pub fn fg(r: u8, g: u8, b: u8) -> String {
+    match color_depth() {
+        ColorDepth::TrueColor => format!("\x1b[38;2;{};{};{}m", r, g, b),
+        ColorDepth::Palette256 => format!("\x1b[38;5;{}m", to_palette256(r, g, b)),
+        ColorDepth::Basic16 => {
+            let (index, bright) = to_basic16(r, g, b);
+            format!("\x1b[{}m", if bright { 90 } else { 30 } + index)
+        }
+    }
+}
+
+/// Background color escape for the detected color depth
+pub fn bg(r: u8, g: u8, b: u8) -> String {
+    match color_depth() {
+        ColorDepth::TrueColor => format!("\x1b[48;2;{};{};{}m", r, g, b),
+        ColorDepth::Palette256 => format!("\x1b[48;5;{}m", to_palette256(r, g, b)),
+        ColorDepth::Basic16 => {
+            let (index, bright) = to_basic16(r, g, b);
+            format!("\x1b[{}m", if bright { 100 } else { 40 } + index)
+        }
+    }
+}
+
+/// Nearest xterm 256-color index (6x6x6 cube or the gray ramp)
+pub fn to_palette256(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    let cube = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40,
+    };
+    let (cr, cg, cb) = (cube(r), cube(g), cube(b));
+    let cube_rgb = (LEVELS[cr as usize], LEVELS[cg as usize], LEVELS[cb as usize]);
+
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let gray_index = if average > 238 { 23 } else { (average.saturating_sub(3) / 10) as u8 };
+    let gray = 8 + 10 * gray_index;
+
+    let distance = |(pr, pg, pb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(pr, r) + d(pg, g) + d(pb, b)
+    };
+    if distance((gray, gray, gray)) < distance(cube_rgb) {
+        232 + gray_index
+    } else {
+        16 + 36 * cr + 6 * cg + cb
+    }
+}
+
+/// Nearest of the 8 ANSI colors, and whether the bright variant fits better
+pub fn to_basic16(r: u8, g: u8, b: u8) -> (u8, bool) {
+    let index = (r >= 128) as u8 | ((g >= 128) as u8) << 1 | ((b >= 128) as u8) << 2;
+    let bright = r.max(g).max(b) >= 192 || (index == 0 && r.max(g).max(b) >= 64);
+    (index, bright)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_and_palettes() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        };
+        assert_eq!(Caps::from_env(env(&[("TERM", "xterm-kitty")])).color, ColorDepth::TrueColor);
+        let screen = Caps::from_env(env(&[("TERM", "screen"), ("STY", "1.pts")]));
+        assert_eq!((screen.color, screen.sgr_mouse, screen.remote), (ColorDepth::Palette256, false, false));
+        assert!(Caps::from_env(env(&[("TERM", "xterm"), ("SSH_TTY", "/dev/pts/1")])).remote);
+        assert_eq!(Caps::from_env(env(&[("TERM", "vt100")])).color, ColorDepth::Basic16);
+
+        let mut caps = Caps::from_env(env(&[("TERM", "tmux-256color"), ("TMUX", "1")]));
+        let (replies, leftover) = split_replies(b"\x1b[?2026;2$yq\x1b[?62;22c");
+        caps.apply_replies(&replies);
+        assert!(caps.synchronized_output && !caps.kitty_graphics);
+        assert_eq!(leftover, b"q");
+        assert_eq!(device_attributes_end(b"x\x1b[?62;22c"), Some(10));
+
+        assert_eq!(to_palette256(255, 0, 0), 196);
+        assert_eq!(to_palette256(128, 128, 128), 244);
+        assert_eq!(to_basic16(0, 128, 128), (6, false));
+    }
+}
@@ -0,0 +1,69 @@
+// Word, line and paragraph text objects
+// Select word (Alt+Shift+W), Select line (Alt+Shift+L) and Select paragraph (Alt+Shift+P) put
+// a block selection on the text around the cursor, so that Ctrl+C, Ctrl+X, Delete and the
+// selection commands act on it. A line is the text of the row without the blanks around it; a
+// paragraph is the run of rows with text above and below the cursor, up to the blank rows.
+use crate::chunked_grid::ChunkedGrid;
+use crate::selections;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextObject {
+    Word,
+    Line,
+    Paragraph,
+}
+
+impl TextObject {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextObject::Word => "word",
+            TextObject::Line => "line",
+            TextObject::Paragraph => "paragraph",
+        }
+    }
+}
+
+/// First and last column of the text on `row`, if it has any
+fn text_span(grid: &ChunkedGrid, row: usize) -> Option<(usize, usize)> {
+    let end = grid.get_text_length(row).checked_sub(1)?;
+    let (start, _) = grid.get_line_bounds(row)?;
+    let start = (start..=end).find(|&col| !grid.get(row, col).is_whitespace())?;
+    Some((start, end))
+}
+
+/// Bounds (first row, first col, last row, last col) of `object` at (row, col)
+pub fn bounds(grid: &ChunkedGrid, object: TextObject, row: usize, col: usize) -> Option<(usize, usize, usize, usize)> {
+    match object {
+        TextObject::Word => selections::word_at(grid, row, col).map(|word| (row, word.start(), row, word.end() - 1)),
+        TextObject::Line => text_span(grid, row).map(|(start, end)| (row, start, row, end)),
+        TextObject::Paragraph => {
+            text_span(grid, row)?;
+            let first = (0..row).rev().take_while(|&r| text_span(grid, r).is_some()).last().unwrap_or(row);
+            let last = (row + 1..).take_while(|&r| text_span(grid, r).is_some()).last().unwrap_or(row);
+            let spans: Vec<(usize, usize)> = (first..=last).filter_map(|r| text_span(grid, r)).collect();
+            let start = spans.iter().map(|&(start, _)| start).min()?;
+            let end = spans.iter().map(|&(_, end)| end).max()?;
+            Some((first, start, last, end))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_objects() {
+        let lines = ["title", "", "  first line,", "    second\t", "", "end"].map(String::from);
+        let grid = ChunkedGrid::from_lines(&lines);
+
+        assert_eq!(bounds(&grid, TextObject::Word, 2, 5), Some((2, 2, 2, 6)));
+        assert_eq!(bounds(&grid, TextObject::Word, 2, 7), Some((2, 2, 2, 6)));
+        assert_eq!(bounds(&grid, TextObject::Word, 2, 1), None);
+        assert_eq!(bounds(&grid, TextObject::Line, 3, 0), Some((3, 4, 3, 9)));
+        assert_eq!(bounds(&grid, TextObject::Line, 1, 0), None);
+        assert_eq!(bounds(&grid, TextObject::Paragraph, 3, 20), Some((2, 2, 3, 12)));
+        assert_eq!(bounds(&grid, TextObject::Paragraph, 5, 0), Some((5, 0, 5, 2)));
+        assert_eq!(bounds(&grid, TextObject::Paragraph, 4, 0), None);
+    }
+}
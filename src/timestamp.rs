@@ -0,0 +1,35 @@
+// Date/time insertion with user-configurable strftime formats
+use chrono::{DateTime, Local};
+use std::fmt::Write;
+
+/// What to insert at the cursor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stamp {
+    Date,
+    Time,
+    /// ISO 8601 / RFC 3339 with offset, always the same format
+    IsoDateTime,
+}
+
+/// Format `now` with a strftime pattern, falling back to `fallback` if the pattern is invalid
+pub fn format_time(now: &DateTime<Local>, pattern: &str, fallback: &str) -> String {
+    let mut text = String::new();
+    if write!(text, "{}", now.format(pattern)).is_ok() {
+        return text;
+    }
+    now.format(fallback).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_format_time() {
+        let now = Local.with_ymd_and_hms(2024, 3, 5, 9, 7, 0).unwrap();
+        assert_eq!(format_time(&now, "%d/%m/%Y", "%Y-%m-%d"), "05/03/2024");
+        // Invalid specifier falls back instead of panicking
+        assert_eq!(format_time(&now, "%Q", "%Y-%m-%d"), "2024-03-05");
+    }
+}
@@ -0,0 +1,123 @@
+// Note-to-note links and transclusion
+// "[[Other Note]]" links to the note with that title: Alt+O or Ctrl+Click opens it.
+// A token "![[Other Note]]" shows the saved content of the note with that title (ignoring
+// case) below it, read-only, in the empty rows that follow the token: leave blank lines under
+// it for as much of the other note as should show. The embedded text is drawn over the canvas
+// and is not part of the note. Sources are read once and kept until notes change (saves,
+// deletes, renames, automation edits), then read again, so the view follows its sources.
+// Embeds inside an embedded note are shown as text, which also keeps cycles finite.
+use crate::note_format;
+use crate::notes_database::NotesDatabase;
+use anyhow::Result;
+use std::collections::HashMap;
+
+const EMBED: &str = "!";
+const OPEN: &str = "[[";
+const CLOSE: &str = "]]";
+
+/// The text that embeds the note titled `title`
+pub fn token(title: &str) -> String {
+    format!("{}{}", EMBED, link_token(title))
+}
+
+/// The text that links to the note titled `title`
+pub fn link_token(title: &str) -> String {
+    format!("{}{}{}", OPEN, title, CLOSE)
+}
+
+/// Whether `title` can be written in a link or embed token
+pub fn linkable(title: &str) -> bool {
+    !title.trim().is_empty() && !title.contains(['[', ']'])
+}
+
+/// (start column, end column, title) of each embed token in `line`
+pub fn embeds_in_line(line: &[char]) -> Vec<(usize, usize, String)> {
+    tokens_in_line(line).into_iter().filter(|&(_, _, _, embed)| embed).map(|(start, end, title, _)| (start, end, title)).collect()
+}
+
+/// (start column, end column, title) of each link token in `line` (embeds not included)
+pub fn links_in_line(line: &[char]) -> Vec<(usize, usize, String)> {
+    tokens_in_line(line).into_iter().filter(|&(_, _, _, embed)| !embed).map(|(start, end, title, _)| (start, end, title)).collect()
+}
+
+/// (start column, end column, title, is an embed) of each [[...]] and ![[...]] token
+fn tokens_in_line(line: &[char]) -> Vec<(usize, usize, String, bool)> {
+    let open: Vec<char> = OPEN.chars().collect();
+    let mut found = Vec::new();
+    let mut col = 0;
+    while col + open.len() < line.len() {
+        if line[col..].starts_with(&open) {
+            let title_start = col + open.len();
+            let close = line[title_start..].windows(2).position(|pair| pair == [']', ']']).filter(|&len| len > 0);
+            if let Some(len) = close {
+                let title: String = line[title_start..title_start + len].iter().collect();
+                if linkable(&title) {
+                    let end = title_start + len + CLOSE.len();
+                    let embed = col > 0 && line[col - 1] == '!';
+                    found.push((if embed { col - 1 } else { col }, end, title.trim().to_string(), embed));
+                    col = end;
+                    continue;
+                }
+            }
+        }
+        col += 1;
+    }
+    found
+}
+
+/// What an embed shows
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    /// The lines of the embedded note, with its id
+    Note { id: String, lines: Vec<String> },
+    Missing,
+}
+
+/// Embedded notes read so far, by lower-cased title
+#[derive(Default)]
+pub struct Sources {
+    cache: HashMap<String, Source>,
+}
+
+impl Sources {
+    /// Forget every source, so embeds read their notes again when next drawn
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn get(&mut self, db: &NotesDatabase, title: &str) -> Result<&Source> {
+        let key = title.to_lowercase();
+        if !self.cache.contains_key(&key) {
+            let source = match db.find_note_by_title(title)? {
+                Some(note) => Source::Note {
+                    id: note.id,
+                    lines: note_format::decode(&note.content).to_lines(),
+                },
+                None => Source::Missing,
+            };
+            self.cache.insert(key.clone(), source);
+        }
+        Ok(&self.cache[&key])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeds() -> Result<()> {
+        let line: Vec<char> = format!("{} and {} ![[]] ![[a", token("Recipes"), token(" Pasta ")).chars().collect();
+        assert_eq!(embeds_in_line(&line), [(0, 12, "Recipes".to_string()), (17, 29, "Pasta".to_string())]);
+        let line: Vec<char> = format!("see {} or {}", link_token("Pasta"), token("Recipes")).chars().collect();
+        assert_eq!(links_in_line(&line), [(4, 13, "Pasta".to_string())]);
+        assert_eq!(embeds_in_line(&line).len(), 1);
+
+        let db = NotesDatabase::open_in_memory()?;
+        db.create_note("Pasta".to_string(), "boil\nsalt".to_string(), vec![])?;
+        let mut sources = Sources::default();
+        assert!(matches!(sources.get(&db, "pasta")?, Source::Note { lines, .. } if lines == &["boil", "salt"]));
+        assert_eq!(sources.get(&db, "Nothing")?, &Source::Missing);
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 /// Undo/Redo system using command pattern
-use crate::chunked_grid::ChunkedGrid;
+use crate::chunked_grid::{ChunkedGrid, Style};
+use crate::config::timing;
+use std::collections::BTreeMap;
 
 /// Unified command enum for all undo/redo operations
 #[derive(Clone)]
@@ -30,9 +32,105 @@ pub enum Command {
         lines: Vec<String>,
         replaced_content: Vec<String>,
     },
+    /// Indent/outdent a block: move each row's text from `col` by its amount (see ChunkedGrid::shift_rows)
+    ShiftRows {
+        first_row: usize,
+        col: usize,
+        amounts: Vec<isize>,
+    },
+    /// Open an empty row at `row`, moving it and everything below down one row
+    InsertRow {
+        row: usize,
+    },
+    /// Alt+Up/Down: move rows first_row..=last_row one row up or down past their neighbour
+    MoveRows {
+        first_row: usize,
+        last_row: usize,
+        up: bool,
+    },
+    /// Restyle cells, keeping their characters: (row, col, style before, style after)
+    Restyle {
+        cells: Vec<(usize, usize, Style, Style)>,
+    },
+    /// Several commands undone/redone as one unit (executed in order, undone in reverse)
+    Group {
+        commands: Vec<Command>,
+    },
 }
 
 impl Command {
+    /// Short human-readable label for the history browser
+    pub fn describe(&self) -> String {
+        match self {
+            Command::InsertChar { row, col, ch } => format!("Type '{}' at {}:{}", ch, row + 1, col + 1),
+            Command::DeleteChar { row, col, deleted_char } => format!("Delete '{}' at {}:{}", deleted_char, row + 1, col + 1),
+            Command::InsertNewLine { row, .. } => format!("Split line {}", row + 1),
+            Command::DeleteLine { row, .. } => format!("Join line {}", row + 1),
+            Command::PasteBlock { row, col, lines, .. } => format!("Paste {} rows at {}:{}", lines.len(), row + 1, col + 1),
+            Command::InsertRow { row } => format!("Open line {}", row + 1),
+            Command::Restyle { cells } => format!("Style {} cells", cells.len()),
+            Command::MoveRows { first_row, last_row, up } => {
+                format!("Move {} rows {}", last_row - first_row + 1, if *up { "up" } else { "down" })
+            }
+            Command::ShiftRows { amounts, .. } => {
+                let verb = if amounts.iter().any(|a| *a > 0) { "Indent" } else { "Outdent" };
+                format!("{} {} rows", verb, amounts.len())
+            }
+            Command::Group { commands } => {
+                let typed: Option<String> = commands.iter().map(|c| match c {
+                    Command::InsertChar { ch, .. } => Some(*ch),
+                    _ => None,
+                }).collect();
+                match (typed, commands.first()) {
+                    (Some(text), Some(Command::InsertChar { row, col, .. })) => format!("Type '{}' at {}:{}", text, row + 1, col + 1),
+                    _ => format!("{} edits", commands.len()),
+                }
+            }
+        }
+    }
+
+    /// Extend a typing burst: true if `next` continues this command on the same line
+    fn continues_typing(&self, next: &Command) -> bool {
+        let Command::InsertChar { row: next_row, col: next_col, .. } = next else {
+            return false;
+        };
+        let last = match self {
+            Command::Group { commands } => commands.last(),
+            other => Some(other),
+        };
+        matches!(last, Some(Command::InsertChar { row, col, .. }) if row == next_row && col + 1 == *next_col)
+    }
+
+    /// Move the command's positions down/right, after the content did (the canvas grew)
+    fn offset(&mut self, rows: usize, cols: usize) {
+        match self {
+            Command::InsertChar { row, col, .. }
+            | Command::DeleteChar { row, col, .. }
+            | Command::InsertNewLine { row, col, .. }
+            | Command::PasteBlock { row, col, .. }
+            | Command::ShiftRows { first_row: row, col, .. } => {
+                *row += rows;
+                *col += cols;
+            }
+            Command::DeleteLine { row, .. } | Command::InsertRow { row } => *row += rows,
+            Command::MoveRows { first_row, last_row, .. } => {
+                *first_row += rows;
+                *last_row += rows;
+            }
+            Command::Restyle { cells } => {
+                for (row, col, _, _) in cells {
+                    *row += rows;
+                    *col += cols;
+                }
+            }
+            Command::Group { commands } => {
+                for command in commands {
+                    command.offset(rows, cols);
+                }
+            }
+        }
+    }
+
     pub fn execute(&self, grid: &mut ChunkedGrid) {
         match self {
             Command::InsertChar { row, col, ch } => {
@@ -52,6 +150,25 @@ impl Command {
             Command::PasteBlock { row, col, lines, .. } => {
                 grid.paste_block(lines, *row, *col);
             }
+            Command::ShiftRows { first_row, col, amounts } => {
+                grid.shift_rows(*first_row, *col, amounts);
+            }
+            Command::InsertRow { row } => {
+                grid.insert_row(*row);
+            }
+            Command::MoveRows { first_row, last_row, up } => {
+                grid.move_row_range(*first_row, *last_row, *up);
+            }
+            Command::Restyle { cells } => {
+                for &(row, col, _, after) in cells {
+                    grid.set_style(row, col, after);
+                }
+            }
+            Command::Group { commands } => {
+                for command in commands {
+                    command.execute(grid);
+                }
+            }
         }
     }
 
@@ -73,217 +190,508 @@ impl Command {
             Command::PasteBlock { row, col, lines, replaced_content } => {
                 undo_paste_block(grid, *row, *col, lines, replaced_content);
             }
+            Command::ShiftRows { first_row, col, amounts } => {
+                let inverse: Vec<isize> = amounts.iter().map(|a| -a).collect();
+                grid.shift_rows(*first_row, *col, &inverse);
+            }
+            Command::InsertRow { row } => {
+                grid.remove_row(*row);
+            }
+            // The rows now sit one further along; move them back
+            Command::MoveRows { first_row, last_row, up: true } => {
+                grid.move_row_range(first_row - 1, last_row - 1, false);
+            }
+            Command::MoveRows { first_row, last_row, up: false } => {
+                grid.move_row_range(first_row + 1, last_row + 1, true);
+            }
+            Command::Restyle { cells } => {
+                for &(row, col, before, _) in cells {
+                    grid.set_style(row, col, before);
+                }
+            }
+            Command::Group { commands } => {
+                for command in commands.iter().rev() {
+                    command.undo(grid);
+                }
+            }
         }
     }
 }
 
 fn execute_insert_newline(grid: &mut ChunkedGrid, row: usize, col: usize, text_after_cursor: &str) {
-    let lines = grid.to_lines();
-
-    // Shift all lines below down by one (starting from bottom)
-    for r in (row + 1..=lines.len()).rev() {
-        let prev_line = if r > 0 && r - 1 < lines.len() {
-            lines[r - 1].clone()
-        } else {
-            String::new()
-        };
+    // Open a line below, then move the text after the cursor down to its start
+    grid.insert_row(row + 1);
+    grid.clear_line_from(row, col);
+    grid.insert_at(row + 1, 0, text_after_cursor);
+}
 
-        // Clear the row
-        for c in 0..1000 {
-            grid.set(r, c, ' ');
-        }
+fn undo_insert_newline(grid: &mut ChunkedGrid, row: usize, col: usize, text_after_cursor: &str) {
+    grid.remove_row(row + 1);
+    grid.insert_at(row, col, text_after_cursor);
+}
 
-        // Write previous line content (unless it's the new line being created)
-        if r != row + 1 {
-            for (c, ch) in prev_line.chars().enumerate() {
-                grid.set(r, c, ch);
-            }
+fn execute_delete_line(grid: &mut ChunkedGrid, row: usize, deleted_line: &str, prev_line_length: usize) {
+    // Append current line to previous line, then close the gap
+    grid.insert_at(row - 1, prev_line_length, deleted_line);
+    grid.remove_row(row);
+}
+
+fn undo_delete_line(grid: &mut ChunkedGrid, row: usize, deleted_line: &str, prev_line_length: usize) {
+    grid.insert_row(row);
+    grid.insert_at(row, 0, deleted_line);
+    grid.clear_line_from(row - 1, prev_line_length);
+}
+
+fn undo_paste_block(grid: &mut ChunkedGrid, row: usize, col: usize, lines: &[String], replaced_content: &[String]) {
+    // Clear the pasted content and restore original
+    for (i, line) in lines.iter().enumerate() {
+        let r = row + i;
+        for c_idx in 0..line.len() {
+            grid.set(r, col + c_idx, ' ');
         }
     }
 
-    // Clear text after cursor on current line
-    for c in col..1000 {
-        grid.set(row, c, ' ');
+    // Restore replaced content
+    for (i, line) in replaced_content.iter().enumerate() {
+        let r = row + i;
+        for (c_idx, ch) in line.chars().enumerate() {
+            grid.set(r, col + c_idx, ch);
+        }
     }
+}
 
-    // Write text after cursor to next line
-    for (c, ch) in text_after_cursor.chars().enumerate() {
-        grid.set(row + 1, c, ch);
-    }
+/// A single state in the undo tree
+struct UndoNode {
+    // Command that led from the parent state to this one (None for the root)
+    command: Option<Command>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    // Child to follow on redo (the most recently visited branch)
+    redo_child: Option<usize>,
+    created: std::time::Instant,
+    // Last time a command was coalesced into this node
+    updated: std::time::Instant,
 }
 
-fn undo_insert_newline(grid: &mut ChunkedGrid, row: usize, col: usize, text_after_cursor: &str) {
-    let lines = grid.to_lines();
+/// One row of the history browser
+pub struct HistoryEntry {
+    pub id: usize,
+    pub depth: usize,
+    pub label: String,
+    pub is_current: bool,
+    pub age_secs: u64,
+}
+
+/// Undo/Redo manager backed by a tree, so undoing and then editing keeps the old branch
+pub struct UndoStack {
+    nodes: BTreeMap<usize, UndoNode>,
+    root: usize,
+    current: usize,
+    next_id: usize,
+    max_size: usize,
+}
 
-    // Clear the next line
-    for c in 0..1000 {
-        grid.set(row + 1, c, ' ');
+impl UndoStack {
+    pub fn new(max_size: usize) -> Self {
+        let mut stack = Self {
+            nodes: BTreeMap::new(),
+            root: 0,
+            current: 0,
+            next_id: 1,
+            max_size,
+        };
+        stack.nodes.insert(0, UndoNode::root());
+        stack
     }
 
-    // Restore text after cursor to current line
-    for (i, ch) in text_after_cursor.chars().enumerate() {
-        grid.set(row, col + i, ch);
+    pub fn push(&mut self, command: Command) {
+        if self.try_coalesce(&command) {
+            return;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.nodes.insert(id, UndoNode {
+            command: Some(command),
+            parent: Some(self.current),
+            children: Vec::new(),
+            redo_child: None,
+            created: std::time::Instant::now(),
+            updated: std::time::Instant::now(),
+        });
+
+        let parent = self.nodes.get_mut(&self.current).expect("current undo node exists");
+        parent.children.push(id);
+        parent.redo_child = Some(id);
+        self.current = id;
+
+        self.prune();
     }
 
-    // Shift all lines below up by one
-    for r in (row + 1)..lines.len() {
-        let next_line = if r + 1 < lines.len() {
-            lines[r + 1].clone()
-        } else {
-            String::new()
+    /// Merge consecutive typing on one line into the current node, if within the grouping window
+    fn try_coalesce(&mut self, command: &Command) -> bool {
+        let node = self.nodes.get_mut(&self.current).expect("current undo node exists");
+        let Some(ref mut last) = node.command else {
+            return false;
         };
+        if !node.children.is_empty()
+            || node.updated.elapsed().as_millis() > timing::UNDO_GROUP_WINDOW_MS
+            || !last.continues_typing(command)
+        {
+            return false;
+        }
 
-        // Clear the row
-        for c in 0..1000 {
-            grid.set(r, c, ' ');
+        match last {
+            Command::Group { commands } => commands.push(command.clone()),
+            single => {
+                *single = Command::Group { commands: vec![single.clone(), command.clone()] };
+            }
         }
+        node.updated = std::time::Instant::now();
+        true
+    }
 
-        // Write next line content
-        for (c, ch) in next_line.chars().enumerate() {
-            grid.set(r, c, ch);
+    /// Add typing at several carets to the current step if it was typing there too (the caller
+    /// knows the carets haven't moved), or start a new step
+    pub fn push_continued(&mut self, commands: Vec<Command>, continues: bool) {
+        let node = self.nodes.get_mut(&self.current).expect("current undo node exists");
+        if continues && node.children.is_empty() && node.updated.elapsed().as_millis() <= timing::UNDO_GROUP_WINDOW_MS {
+            if let Some(Command::Group { commands: group }) = node.command.as_mut() {
+                group.extend(commands);
+                node.updated = std::time::Instant::now();
+                return;
+            }
         }
+        self.push(Command::Group { commands });
     }
-}
 
-fn execute_delete_line(grid: &mut ChunkedGrid, row: usize, deleted_line: &str, prev_line_length: usize) {
-    let lines = grid.to_lines();
+    pub fn undo(&mut self, grid: &mut ChunkedGrid) -> bool {
+        let node = &self.nodes[&self.current];
+        let (Some(command), Some(parent)) = (&node.command, node.parent) else {
+            return false;
+        };
 
-    // Append current line to previous line
-    for (i, ch) in deleted_line.chars().enumerate() {
-        grid.set(row - 1, prev_line_length + i, ch);
+        command.undo(grid);
+        let child = self.current;
+        self.nodes.get_mut(&parent).expect("parent undo node exists").redo_child = Some(child);
+        self.current = parent;
+        true
     }
 
-    // Shift all lines below up by one
-    for r in row..lines.len() {
-        let next_line = if r + 1 < lines.len() {
-            lines[r + 1].clone()
-        } else {
-            String::new()
+    pub fn redo(&mut self, grid: &mut ChunkedGrid) -> bool {
+        let Some(child) = self.nodes[&self.current].redo_child else {
+            return false;
         };
 
-        // Clear the row
-        for c in 0..1000 {
-            grid.set(r, c, ' ');
-        }
-
-        // Write next line content
-        for (c, ch) in next_line.chars().enumerate() {
-            grid.set(r, c, ch);
+        if let Some(ref command) = self.nodes[&child].command {
+            command.execute(grid);
         }
+        self.current = child;
+        true
     }
-}
 
-fn undo_delete_line(grid: &mut ChunkedGrid, row: usize, deleted_line: &str, prev_line_length: usize) {
-    let lines = grid.to_lines();
-
-    // Shift all lines below down by one
-    for r in (row..=lines.len()).rev() {
-        let prev_line = if r > 0 && r - 1 < lines.len() {
-            lines[r - 1].clone()
-        } else {
-            String::new()
-        };
+    /// Move the grid to any state in the tree, undoing up to the common ancestor and redoing down
+    pub fn jump_to(&mut self, target: usize, grid: &mut ChunkedGrid) -> bool {
+        if !self.nodes.contains_key(&target) {
+            return false;
+        }
 
-        // Clear the row
-        for c in 0..1000 {
-            grid.set(r, c, ' ');
+        let target_path = self.path_from_root(target);
+        while !target_path.contains(&self.current) {
+            self.undo(grid);
         }
 
-        // Write previous line content (unless it's the restored line)
-        if r != row {
-            for (c, ch) in prev_line.chars().enumerate() {
-                grid.set(r, c, ch);
+        let start = target_path.iter().position(|&id| id == self.current).unwrap_or(0);
+        for &id in &target_path[start + 1..] {
+            if let Some(ref command) = self.nodes[&id].command {
+                command.execute(grid);
             }
+            let parent = self.nodes[&id].parent.expect("non-root undo node has a parent");
+            self.nodes.get_mut(&parent).expect("parent undo node exists").redo_child = Some(id);
+            self.current = id;
         }
+        true
     }
 
-    // Restore the deleted line
-    for (c, ch) in deleted_line.chars().enumerate() {
-        grid.set(row, c, ch);
+    /// Keep the history pointing at the same text after the canvas grew and moved it all
+    /// `rows`/`cols` down/right; growing the canvas isn't an edit of its own
+    pub fn offset(&mut self, rows: usize, cols: usize) {
+        for command in self.nodes.values_mut().filter_map(|node| node.command.as_mut()) {
+            command.offset(rows, cols);
+        }
     }
 
-    // Remove the appended text from previous line
-    for c in prev_line_length..1000 {
-        grid.set(row - 1, c, ' ');
+    pub fn can_undo(&self) -> bool {
+        self.current != self.root
     }
-}
 
-fn undo_paste_block(grid: &mut ChunkedGrid, row: usize, col: usize, lines: &[String], replaced_content: &[String]) {
-    // Clear the pasted content and restore original
-    for (i, line) in lines.iter().enumerate() {
-        let r = row + i;
-        for c_idx in 0..line.len() {
-            grid.set(r, col + c_idx, ' ');
+    pub fn can_redo(&self) -> bool {
+        self.nodes[&self.current].redo_child.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.nodes.insert(0, UndoNode::root());
+        self.root = 0;
+        self.current = 0;
+        self.next_id = 1;
+    }
+
+    /// Change the maximum number of remembered edits, pruning the oldest if needed
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.prune();
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Flatten the tree for display: the oldest branch continues in place, newer branches indent
+    pub fn history(&self) -> Vec<HistoryEntry> {
+        let now = std::time::Instant::now();
+        let mut entries = Vec::new();
+        let mut stack = vec![(self.root, 0)];
+
+        while let Some((id, depth)) = stack.pop() {
+            let node = &self.nodes[&id];
+            let label = match node.command {
+                Some(ref command) => command.describe(),
+                None if id == 0 => "Original".to_string(),
+                None => "(older history pruned)".to_string(),
+            };
+            entries.push(HistoryEntry {
+                id,
+                depth,
+                label,
+                is_current: id == self.current,
+                age_secs: now.duration_since(node.created).as_secs(),
+            });
+
+            // Push newer branches first so the oldest child is visited next
+            for (i, &child) in node.children.iter().enumerate().rev() {
+                stack.push((child, if i == 0 { depth } else { depth + 1 }));
+            }
         }
+
+        entries
     }
 
-    // Restore replaced content
-    for (i, line) in replaced_content.iter().enumerate() {
-        let r = row + i;
-        for (c_idx, ch) in line.chars().enumerate() {
-            grid.set(r, col + c_idx, ch);
+    fn path_from_root(&self, mut id: usize) -> Vec<usize> {
+        let mut path = vec![id];
+        while let Some(parent) = self.nodes[&id].parent {
+            path.push(parent);
+            id = parent;
         }
+        path.reverse();
+        path
     }
-}
 
-/// Undo/Redo stack manager
-pub struct UndoStack {
-    undo_stack: Vec<Command>,
-    redo_stack: Vec<Command>,
-    max_size: usize,
+    /// Drop the oldest history until the tree fits within max_size edits
+    fn prune(&mut self) {
+        while self.nodes.len() - 1 > self.max_size {
+            let path = self.path_from_root(self.current);
+            let path_child = path.get(1).copied();
+            let Some(&oldest) = self.nodes[&self.root].children.iter().min() else {
+                break;
+            };
+
+            if Some(oldest) == path_child {
+                // The oldest edit is on the way to the current state: make it the new root
+                let old_root = self.root;
+                for sibling in self.nodes[&old_root].children.clone() {
+                    if sibling != oldest {
+                        self.remove_subtree(sibling);
+                    }
+                }
+                self.nodes.remove(&old_root);
+                let new_root = self.nodes.get_mut(&oldest).expect("path child exists");
+                new_root.command = None;
+                new_root.parent = None;
+                self.root = oldest;
+            } else {
+                // The oldest edit is on an abandoned branch: drop that branch
+                self.remove_subtree(oldest);
+                let root = self.nodes.get_mut(&self.root).expect("root exists");
+                root.children.retain(|&c| c != oldest);
+                if root.redo_child == Some(oldest) {
+                    root.redo_child = root.children.last().copied();
+                }
+            }
+        }
+    }
+
+    fn remove_subtree(&mut self, id: usize) {
+        if let Some(node) = self.nodes.remove(&id) {
+            for child in node.children {
+                self.remove_subtree(child);
+            }
+        }
+    }
 }
 
-impl UndoStack {
-    pub fn new(max_size: usize) -> Self {
+impl UndoNode {
+    fn root() -> Self {
         Self {
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
-            max_size,
+            command: None,
+            parent: None,
+            children: Vec::new(),
+            redo_child: None,
+            created: std::time::Instant::now(),
+            updated: std::time::Instant::now(),
         }
     }
+}
 
-    pub fn push(&mut self, command: Command) {
-        // Clear redo stack when new command is added
-        self.redo_stack.clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
 
-        self.undo_stack.push(command);
+    fn type_char(stack: &mut UndoStack, grid: &mut ChunkedGrid, col: usize, ch: char) {
+        let cmd = Command::InsertChar { row: 0, col, ch };
+        cmd.execute(grid);
+        stack.push(cmd);
+    }
 
-        // Limit stack size
-        if self.undo_stack.len() > self.max_size {
-            self.undo_stack.remove(0);
-        }
+    #[test]
+    fn test_undo_branch_is_preserved() {
+        let mut grid = ChunkedGrid::new();
+        let mut stack = UndoStack::new(100);
+
+        type_char(&mut stack, &mut grid, 0, 'a');
+        type_char(&mut stack, &mut grid, 2, 'b');
+        assert!(stack.undo(&mut grid));
+        type_char(&mut stack, &mut grid, 2, 'c');
+        assert_eq!(grid.get_line(0, 0, 2), "a c");
+
+        // The abandoned "a b" branch is still reachable
+        let ab = stack.history().iter().find(|e| e.label.starts_with("Type 'b'")).map(|e| e.id).unwrap();
+        assert!(stack.jump_to(ab, &mut grid));
+        assert_eq!(grid.get_line(0, 0, 2), "a b");
+
+        // Jumping back to the root restores the empty grid
+        assert!(stack.jump_to(0, &mut grid));
+        assert_eq!(grid.cell_count(), 0);
     }
 
-    pub fn undo(&mut self, grid: &mut ChunkedGrid) -> bool {
-        if let Some(command) = self.undo_stack.pop() {
-            command.undo(grid);
-            self.redo_stack.push(command);
-            true
-        } else {
-            false
+    #[test]
+    fn test_typing_burst_is_one_undo_step() {
+        let mut grid = ChunkedGrid::new();
+        let mut stack = UndoStack::new(100);
+
+        for (i, ch) in "hello".chars().enumerate() {
+            type_char(&mut stack, &mut grid, i, ch);
         }
+        assert!(stack.undo(&mut grid));
+        assert_eq!(grid.cell_count(), 0);
+        assert!(!stack.can_undo());
+
+        assert!(stack.redo(&mut grid));
+        assert_eq!(grid.get_line(0, 0, 4), "hello");
     }
 
-    pub fn redo(&mut self, grid: &mut ChunkedGrid) -> bool {
-        if let Some(command) = self.redo_stack.pop() {
-            command.execute(grid);
-            self.undo_stack.push(command);
-            true
-        } else {
-            false
+    #[test]
+    fn test_prune_respects_max_size() {
+        let mut grid = ChunkedGrid::new();
+        let mut stack = UndoStack::new(3);
+
+        // Type at non-adjacent columns so each edit is its own undo step
+        for (i, ch) in "hello".chars().enumerate() {
+            type_char(&mut stack, &mut grid, i * 2, ch);
         }
+
+        // Only the last 3 edits can be undone
+        let mut undone = 0;
+        while stack.undo(&mut grid) {
+            undone += 1;
+        }
+        assert_eq!(undone, 3);
+        assert_eq!(grid.get_line(0, 0, 8), "h e");
     }
 
-    pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+    #[test]
+    fn test_restyle() {
+        let bold = Style { bold: true, ..Style::default() };
+        let mut grid = ChunkedGrid::from_string("old");
+        grid.set_style(0, 0, bold);
+        // A paste over a styled cell, as App::write_styled_block builds it
+        let command = Command::Group { commands: vec![
+            Command::Restyle { cells: vec![(0, 0, bold, Style::default())] },
+            Command::PasteBlock { row: 0, col: 0, lines: vec!["new".to_string()], replaced_content: vec!["old".to_string()] },
+            Command::Restyle { cells: vec![(0, 2, Style::default(), bold)] },
+        ] };
+        command.execute(&mut grid);
+        assert_eq!((grid.to_lines(), grid.style(0, 0), grid.style(0, 2)), (vec!["new".to_string()], Style::default(), bold));
+        command.undo(&mut grid);
+        assert_eq!((grid.to_lines(), grid.style(0, 0), grid.style(0, 2)), (vec!["old".to_string()], bold, Style::default()));
     }
 
-    pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+    /// Every non-empty cell, in position order
+    fn snapshot(grid: &ChunkedGrid) -> Vec<((usize, usize), char)> {
+        let mut cells: Vec<_> = grid.cell_positions().map(|(r, c)| ((r, c), grid.get(r, c))).collect();
+        cells.sort_unstable();
+        cells
     }
 
-    pub fn clear(&mut self) {
-        self.undo_stack.clear();
-        self.redo_stack.clear();
+    fn grid_strategy() -> impl Strategy<Value = ChunkedGrid> {
+        prop::collection::vec((0..40usize, 0..80usize, proptest::char::range('!', '~')), 0..80).prop_map(|cells| {
+            let mut grid = ChunkedGrid::new();
+            for (row, col, ch) in cells {
+                grid.set(row, col, ch);
+            }
+            grid
+        })
+    }
+
+    /// A command valid for `grid`, built from its contents the way the editor builds them
+    fn command_for(grid: &ChunkedGrid, kind: u8, row: usize, col: usize, ch: char, block: &[String]) -> Command {
+        let line_len = grid.get_line_length(row);
+        match kind {
+            0 => Command::InsertChar { row, col, ch },
+            1 if col < line_len => Command::DeleteChar { row, col, deleted_char: grid.get(row, col) },
+            2 => Command::InsertNewLine {
+                row,
+                col,
+                text_after_cursor: if col < line_len { grid.get_line(row, col, line_len - 1) } else { String::new() },
+            },
+            3 if row > 0 => Command::DeleteLine {
+                row,
+                deleted_line: grid.get_line(row, 0, line_len.max(1) - 1),
+                prev_line_length: grid.get_line_length(row - 1),
+            },
+            4 => Command::InsertRow { row },
+            _ => Command::PasteBlock {
+                row,
+                col,
+                lines: block.to_vec(),
+                replaced_content: block
+                    .iter()
+                    .enumerate()
+                    .map(|(i, line)| (0..line.chars().count()).map(|c| grid.get(row + i, col + c)).collect())
+                    .collect(),
+            },
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn prop_execute_then_undo_restores_grid(
+            grid in grid_strategy(),
+            kind in 0u8..6,
+            row in 0..45usize,
+            col in 0..85usize,
+            ch in proptest::char::range('!', '~'),
+            block in prop::collection::vec("[a-z#]{0,6}", 1..4),
+        ) {
+            let mut grid = grid;
+            let before = snapshot(&grid);
+            let command = command_for(&grid, kind, row, col, ch, &block);
+
+            command.execute(&mut grid);
+            command.undo(&mut grid);
+            prop_assert_eq!(snapshot(&grid), before);
+        }
     }
 }
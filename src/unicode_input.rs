@@ -0,0 +1,85 @@
+// Unicode input: type a code point in hex (Ctrl+U) or a two-character
+// digraph (Ctrl+K), vim/RFC 1345 style.
+
+/// In-progress Unicode input in the status line
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnicodeInput {
+    /// Hex digits typed so far after Ctrl+U
+    CodePoint(String),
+    /// First character of a digraph after Ctrl+K (None until typed)
+    Digraph(Option<char>),
+}
+
+/// Longest code point is U+10FFFF
+pub const MAX_HEX_DIGITS: usize = 6;
+
+/// Digraph table: accents use ' (acute) ! (grave) > (circumflex) : (diaeresis) ? (tilde)
+const DIGRAPHS: &[(&str, char)] = &[
+    // Latin letters with accents
+    ("a'", 'á'), ("e'", 'é'), ("i'", 'í'), ("o'", 'ó'), ("u'", 'ú'),
+    ("A'", 'Á'), ("E'", 'É'), ("I'", 'Í'), ("O'", 'Ó'), ("U'", 'Ú'),
+    ("a!", 'à'), ("e!", 'è'), ("i!", 'ì'), ("o!", 'ò'), ("u!", 'ù'),
+    ("A!", 'À'), ("E!", 'È'), ("I!", 'Ì'), ("O!", 'Ò'), ("U!", 'Ù'),
+    ("a>", 'â'), ("e>", 'ê'), ("i>", 'î'), ("o>", 'ô'), ("u>", 'û'),
+    ("a:", 'ä'), ("e:", 'ë'), ("i:", 'ï'), ("o:", 'ö'), ("u:", 'ü'), ("y:", 'ÿ'),
+    ("A:", 'Ä'), ("O:", 'Ö'), ("U:", 'Ü'),
+    ("a?", 'ã'), ("o?", 'õ'), ("n?", 'ñ'), ("N?", 'Ñ'),
+    ("c,", 'ç'), ("C,", 'Ç'), ("ss", 'ß'), ("ae", 'æ'), ("AE", 'Æ'), ("o/", 'ø'), ("O/", 'Ø'),
+    ("aa", 'å'), ("AA", 'Å'),
+    // Greek
+    ("a*", 'α'), ("b*", 'β'), ("g*", 'γ'), ("d*", 'δ'), ("e*", 'ε'), ("l*", 'λ'),
+    ("m*", 'μ'), ("p*", 'π'), ("s*", 'σ'), ("t*", 'τ'), ("f*", 'φ'), ("w*", 'ω'),
+    ("D*", 'Δ'), ("S*", 'Σ'), ("W*", 'Ω'),
+    // Arrows
+    ("->", '→'), ("<-", '←'), ("-!", '↑'), ("-v", '↓'), ("<>", '↔'), ("=>", '⇒'),
+    // Math
+    ("+-", '±'), ("*X", '×'), ("-:", '÷'), ("!=", '≠'), ("=<", '≤'), (">=", '≥'),
+    ("?2", '≈'), ("00", '∞'), ("RT", '√'), ("DG", '°'), ("1S", '¹'), ("2S", '²'), ("3S", '³'),
+    ("12", '½'), ("14", '¼'), ("34", '¾'),
+    // Currency and symbols
+    ("Eu", '€'), ("Pd", '£'), ("Ye", '¥'), ("Ct", '¢'),
+    ("Co", '©'), ("Rg", '®'), ("TM", '™'), ("SE", '§'), ("PI", '¶'),
+    ("OK", '✓'), ("XX", '✗'), ("*1", '☆'), ("*2", '★'),
+    // Punctuation
+    ("-N", '–'), ("-M", '—'), (".3", '…'), ("'6", '‘'), ("'9", '’'), ("\"6", '“'), ("\"9", '”'),
+    ("<<", '«'), (">>", '»'), ("!I", '¡'), ("?I", '¿'), ("Sb", '•'), ("NS", '\u{a0}'),
+];
+
+/// Look up a digraph; like vim, the two characters may also be typed in reverse order
+pub fn digraph(first: char, second: char) -> Option<char> {
+    let find = |a: char, b: char| {
+        DIGRAPHS.iter().find_map(|&(keys, ch)| {
+            let mut k = keys.chars();
+            (k.next() == Some(a) && k.next() == Some(b)).then_some(ch)
+        })
+    };
+    find(first, second).or_else(|| find(second, first))
+}
+
+/// Parse hex digits (optionally prefixed with U+) into a character
+pub fn parse_code_point(hex: &str) -> Option<char> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix("U+").or_else(|| hex.strip_prefix("u+")).unwrap_or(hex);
+    if hex.is_empty() || hex.len() > MAX_HEX_DIGITS {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32).filter(|c| !c.is_control())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_points_and_digraphs() {
+        assert_eq!(parse_code_point("e9"), Some('é'));
+        assert_eq!(parse_code_point("U+1F439"), Some('🐹'));
+        assert_eq!(parse_code_point("D800"), None); // Surrogate
+        assert_eq!(parse_code_point("7"), None); // Control character
+        assert_eq!(parse_code_point("zz"), None);
+
+        assert_eq!(digraph('e', '\''), Some('é'));
+        assert_eq!(digraph('>', '-'), Some('→')); // Reversed
+        assert_eq!(digraph('q', 'q'), None);
+    }
+}
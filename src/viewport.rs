@@ -69,13 +69,8 @@ impl Viewport {
     pub fn draw_scrollbars(&self) -> io::Result<()> {
         let mut stdout = io::stdout();
 
-        let _ = (|| -> std::io::Result<()> {
-            use std::io::Write;
-            let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-            writeln!(f, "DRAW_SCROLLBARS called: needs_v={}, needs_h={}",
-                self.needs_vertical_scrollbar(), self.needs_horizontal_scrollbar())?;
-            f.flush()
-        })();
+        log::trace!("DRAW_SCROLLBARS called: needs_v={}, needs_h={}",
+                self.needs_vertical_scrollbar(), self.needs_horizontal_scrollbar());
 
         // Draw vertical scrollbar
         if self.needs_vertical_scrollbar() {
@@ -91,13 +86,8 @@ impl Viewport {
                 (thumb_h, thumb_pos)
             };
 
-            let _ = (|| -> std::io::Result<()> {
-                use std::io::Write;
-                let mut f = std::fs::OpenOptions::new().create(true).append(true).open("/tmp/chonk-debug.log")?;
-                writeln!(f, "V_SCROLLBAR: height={}, content_h={}, thumb_h={}, thumb_pos={}, scroll_y={}",
-                    scrollbar_height, self.content_height, thumb_height, thumb_position, self.scroll_y)?;
-                f.flush()
-            })();
+            log::trace!("V_SCROLLBAR: height={}, content_h={}, thumb_h={}, thumb_pos={}, scroll_y={}",
+                    scrollbar_height, self.content_height, thumb_height, thumb_position, self.scroll_y);
 
             for row in 0..scrollbar_height {
                 // Terminal coords are 1-based, mouse coords are 0-based
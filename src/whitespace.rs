@@ -0,0 +1,44 @@
+// Showing whitespace and hidden characters
+// Toggle whitespace (Alt+W) marks what the canvas otherwise draws blank, to find what is
+// throwing a line out of alignment: blanks inside a line become faint dots and tabs arrows,
+// while no-break spaces, control characters (shown as their Unicode control pictures, e.g.
+// ␍ for a stray carriage return) and zero-width characters stand out in a warning color.
+use crate::frame::is_invisible;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mark {
+    /// Ordinary spacing, drawn faintly
+    Blank(char),
+    /// A character that is easy to paste by accident and hard to see
+    Hidden(char),
+}
+
+/// How whitespace mode draws `ch`, or None for visible characters
+pub fn mark(ch: char) -> Option<Mark> {
+    match ch {
+        ' ' => Some(Mark::Blank('·')),
+        '\t' => Some(Mark::Blank('→')),
+        '\u{0}'..='\u{1f}' => Some(Mark::Hidden(char::from_u32(0x2400 + ch as u32).unwrap_or('¤'))),
+        '\u{7f}' => Some(Mark::Hidden('␡')),
+        // No-break and other fixed-width spaces look like spaces but don't act like them
+        c if c.is_whitespace() && !c.is_control() => Some(Mark::Hidden('°')),
+        c if is_invisible(c) => Some(Mark::Hidden('¤')),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        assert_eq!(mark(' '), Some(Mark::Blank('·')));
+        assert_eq!(mark('\t'), Some(Mark::Blank('→')));
+        assert_eq!(mark('\r'), Some(Mark::Hidden('␍')));
+        assert_eq!(mark('\u{a0}'), Some(Mark::Hidden('°')));
+        assert_eq!(mark('\u{200b}'), Some(Mark::Hidden('¤')));
+        assert_eq!(mark('\u{85}'), Some(Mark::Hidden('¤')));
+        assert_eq!(mark('x'), None);
+    }
+}
@@ -0,0 +1,79 @@
+// Word count goals
+// A note can have a goal of so many words (Notes menu → Word goal, kept with the note like its
+// accent and icon). While it has one, the status line shows how far along it is, e.g.
+// "320/500 words ▰▰▰▱▱▱▱▱", in green once the goal is reached. Words are counted the way Note
+// info counts them, from the editor so unsaved edits count; the count is kept until the next
+// edit rather than redone every frame.
+use crate::chunked_grid::ChunkedGrid;
+use crate::config::word_goal::BAR_CELLS;
+use crate::term_caps::glyph;
+
+/// The open note's word count, counted again only after it changes
+pub struct WordCount {
+    words: Option<usize>,
+}
+
+impl WordCount {
+    pub fn new() -> Self {
+        Self { words: None }
+    }
+
+    /// Count again when next asked (called whenever the open note is edited or replaced)
+    pub fn invalidate(&mut self) {
+        self.words = None;
+    }
+
+    pub fn refresh(&mut self, grid: &ChunkedGrid) {
+        if self.words.is_none() {
+            self.words = Some(count(grid));
+        }
+    }
+
+    /// The last count, if it is still current
+    pub fn words(&self) -> Option<usize> {
+        self.words
+    }
+}
+
+pub fn count(grid: &ChunkedGrid) -> usize {
+    grid.to_lines().iter().map(|line| line.split_whitespace().count()).sum()
+}
+
+/// "320/500 words ▰▰▰▱▱▱▱▱"
+pub fn progress(words: usize, goal: u32) -> String {
+    let filled = (words * BAR_CELLS / (goal as usize).max(1)).min(BAR_CELLS);
+    let bar: String = std::iter::repeat_n(glyph('▰'), filled).chain(std::iter::repeat_n(glyph('▱'), BAR_CELLS - filled)).collect();
+    format!("{}/{} words {}", words, goal, bar)
+}
+
+/// A goal typed into the prompt: a positive whole number, or nothing (or 0) to remove it
+pub fn parse(text: &str) -> anyhow::Result<Option<u32>> {
+    let text = text.trim().replace([',', '_'], "");
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let goal: u32 = text.parse().map_err(|_| anyhow::anyhow!("\"{}\" isn't a number of words", text))?;
+    Ok((goal > 0).then_some(goal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_goal() -> anyhow::Result<()> {
+        let grid = ChunkedGrid::from_lines(&["Dear diary,".to_string(), String::new(), "  today   I wrote".to_string()]);
+        let mut words = WordCount::new();
+        assert_eq!(words.words(), None);
+        words.refresh(&grid);
+        assert_eq!(words.words(), Some(5));
+
+        assert_eq!(progress(250, 1000), "250/1000 words ▰▰▱▱▱▱▱▱");
+        assert_eq!(progress(1200, 1000), "1200/1000 words ▰▰▰▰▰▰▰▰");
+        assert_eq!(parse(" 1,500 ")?, Some(1500));
+        assert_eq!(parse("")?, None);
+        assert_eq!(parse("0")?, None);
+        assert!(parse("lots").is_err());
+        Ok(())
+    }
+}